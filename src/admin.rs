@@ -0,0 +1,477 @@
+//! # Offline Admin
+//!
+//! Maintenance commands that work on the database file directly, without
+//! the HTTP server:
+//!
+//! ```bash
+//! vibedb admin vacuum --db data.db
+//! vibedb admin export --db data.db --collection users --format ndjson --out users.ndjson
+//! vibedb admin create-user --db data.db --email ops@example.com --password ... --role admin
+//! vibedb admin integrity-check --db data.db
+//! ```
+//!
+//! They go through the same code as the server: `VibeStore` snapshots for
+//! exports (the `/v1/export` line format) and `AuthService` for users.
+//!
+//! A server may have the file open. SQLite's locks keep both consistent;
+//! commands that write (`vacuum`, `create-user`) wait up to
+//! [`LOCK_TIMEOUT`] for the write lock and then fail with a `Conflict`
+//! naming the file instead of retrying.
+
+use crate::auth::{AuthService, User, ADMIN_ROLE, USER_ROLE};
+use crate::db::{BusyRetry, PragmaUpdate, SnapshotItem, SnapshotShape, VibeStore};
+use crate::error::{VibeError, VibeResult};
+use crate::guard::SchemaGuard;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long a writing command waits for another connection's lock
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Rows buffered between the snapshot reader and the export file
+const EXPORT_BUFFER_ROWS: usize = 256;
+
+/// Output format of `admin export`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// The line format of `GET /v1/export`: a `snapshot` line, a `row`
+    /// line per row and an `end` line
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "ndjson" | "jsonl" => Some(Self::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+/// A parsed `vibedb admin` command line
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdminCommand {
+    Vacuum {
+        db: PathBuf,
+    },
+    Export {
+        db: PathBuf,
+        collection: String,
+        format: ExportFormat,
+        out: PathBuf,
+    },
+    CreateUser {
+        db: PathBuf,
+        email: String,
+        password: String,
+        role: String,
+    },
+    IntegrityCheck {
+        db: PathBuf,
+    },
+}
+
+impl AdminCommand {
+    /// Parses the arguments after `admin`; `--db` defaults to `default_db`
+    ///
+    /// `create-user` takes the password from `VIBEDB_ADMIN_PASSWORD` when
+    /// `--password` is omitted.
+    pub fn parse(args: &[String], default_db: &str) -> Result<Self, String> {
+        let (command, rest) = args.split_first().ok_or_else(|| "missing admin command".to_string())?;
+
+        let mut options: HashMap<&str, String> = HashMap::new();
+        let mut rest = rest.iter();
+        while let Some(flag) = rest.next() {
+            let name = match flag.as_str() {
+                "--db" | "-d" => "db",
+                "--collection" | "-c" => "collection",
+                "--format" => "format",
+                "--out" | "-o" => "out",
+                "--email" => "email",
+                "--password" => "password",
+                "--role" => "role",
+                other => return Err(format!("unknown option '{}'", other)),
+            };
+            let value = rest.next().ok_or_else(|| format!("{} needs a value", flag))?;
+            options.insert(name, value.clone());
+        }
+        let db = PathBuf::from(options.remove("db").unwrap_or_else(|| default_db.to_string()));
+        let mut required = |name: &str| options.remove(name).ok_or_else(|| format!("{} needs --{}", command, name));
+
+        let parsed = match command.as_str() {
+            "vacuum" => Self::Vacuum { db },
+            "export" => {
+                let collection = required("collection")?;
+                let out = PathBuf::from(required("out")?);
+                let format = match options.remove("format") {
+                    Some(format) => ExportFormat::parse(&format)
+                        .ok_or_else(|| format!("unknown export format '{}' (expected ndjson)", format))?,
+                    None => ExportFormat::Ndjson,
+                };
+                Self::Export { db, collection, format, out }
+            }
+            "create-user" => {
+                let email = required("email")?;
+                let password = match required("password") {
+                    Ok(password) => password,
+                    Err(e) => std::env::var("VIBEDB_ADMIN_PASSWORD").map_err(|_| e)?,
+                };
+                let role = options.remove("role").unwrap_or_else(|| USER_ROLE.to_string());
+                Self::CreateUser { db, email, password, role }
+            }
+            "integrity-check" => Self::IntegrityCheck { db },
+            other => return Err(format!("unknown admin command '{}'", other)),
+        };
+        if let Some(name) = options.keys().next() {
+            return Err(format!("{} does not take --{}", command, name));
+        }
+        Ok(parsed)
+    }
+
+    /// Runs the command, returning a summary to print
+    pub async fn run(self) -> VibeResult<String> {
+        match self {
+            Self::Vacuum { db } => {
+                let report = vacuum(&db).await?;
+                Ok(format!(
+                    "Vacuumed {}: {} -> {} bytes",
+                    db.display(),
+                    report.bytes_before,
+                    report.bytes_after
+                ))
+            }
+            Self::Export { db, collection, format, out } => {
+                let file = std::fs::File::create(&out).map_err(|e| io_error(&out, e))?;
+                let mut writer = std::io::BufWriter::new(file);
+                let written = export(&db, &collection, format, &mut writer).await;
+                let rows = match written.and_then(|rows| writer.flush().map(|_| rows).map_err(|e| io_error(&out, e))) {
+                    Ok(rows) => rows,
+                    Err(e) => {
+                        drop(writer);
+                        let _ = std::fs::remove_file(&out);
+                        return Err(e);
+                    }
+                };
+                Ok(format!("Exported {} rows of {} to {}", rows, collection, out.display()))
+            }
+            Self::CreateUser { db, email, password, role } => {
+                let user = create_user(&db, &email, &password, &role).await?;
+                Ok(format!("Created {} user {} (id {})", user.role, user.email, user.id))
+            }
+            Self::IntegrityCheck { db } => {
+                let report = integrity_check(&db).await?;
+                if report.ok {
+                    Ok(format!("{}: ok", db.display()))
+                } else {
+                    Err(VibeError::Database(format!(
+                        "{} failed the integrity check:\n{}",
+                        db.display(),
+                        report.problems.join("\n")
+                    )))
+                }
+            }
+        }
+    }
+}
+
+/// File sizes around a vacuum, WAL included
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct VacuumReport {
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+/// Result of `PRAGMA integrity_check`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IntegrityReport {
+    pub ok: bool,
+    /// SQLite's messages when not `ok`
+    pub problems: Vec<String>,
+}
+
+/// Rebuilds the database file, then truncates the WAL so the space is
+/// returned to the filesystem
+pub async fn vacuum(db: &Path) -> VibeResult<VacuumReport> {
+    let store = open(db, false).await?;
+    let bytes_before = file_size(db);
+    store
+        .execute_batch("VACUUM; PRAGMA wal_checkpoint(TRUNCATE);".to_string())
+        .await
+        .map_err(|e| locked(db, e))?;
+    Ok(VacuumReport {
+        bytes_before,
+        bytes_after: file_size(db),
+    })
+}
+
+/// Writes every row of `collection` to `out`, returning how many
+///
+/// Rows come from one read transaction, so writes a running server makes
+/// meanwhile are not included.
+pub async fn export(db: &Path, collection: &str, format: ExportFormat, out: &mut impl Write) -> VibeResult<u64> {
+    let store = open(db, false).await?;
+    let guard = SchemaGuard::new(Arc::clone(&store));
+    let table = guard.resolve_table(collection, false).await?;
+    guard.require_columns(&table).await?;
+
+    match format {
+        ExportFormat::Ndjson => {}
+    }
+    let mut rx = store.read_snapshot(Some(vec![table]), SnapshotShape::default(), EXPORT_BUFFER_ROWS);
+    let mut rows = 0u64;
+    while let Some(item) = rx.recv().await {
+        let line = match item? {
+            SnapshotItem::Begin { schema_version, tables } => json!({
+                "type": "snapshot",
+                "schema_version": schema_version,
+                "tables": tables,
+                "exported_at": crate::audit::now_timestamp()
+            }),
+            SnapshotItem::Row { table, row } => {
+                rows += 1;
+                json!({"type": "row", "table": table, "row": Value::Object(row.into_iter().collect())})
+            }
+        };
+        writeln!(out, "{}", line).map_err(|e| VibeError::Internal(e.into()))?;
+    }
+    writeln!(out, "{}", json!({"type": "end", "rows": rows})).map_err(|e| VibeError::Internal(e.into()))?;
+    Ok(rows)
+}
+
+/// Creates a user with `role` (`user` or `admin`), creating the database
+/// when there is none yet
+pub async fn create_user(db: &Path, email: &str, password: &str, role: &str) -> VibeResult<User> {
+    if role != USER_ROLE && role != ADMIN_ROLE {
+        return Err(VibeError::InvalidPayload(format!(
+            "role must be '{}' or '{}'",
+            USER_ROLE, ADMIN_ROLE
+        )));
+    }
+    let store = open(db, true).await?;
+    // The secret only signs tokens, and this service issues none
+    let auth = AuthService::new(store, AuthService::generate_secret())
+        .await
+        .map_err(|e| locked(db, e))?;
+    auth.create_user(email, password, role, json!({}))
+        .await
+        .map_err(|e| locked(db, e))
+}
+
+/// Runs `PRAGMA integrity_check` over the whole file
+pub async fn integrity_check(db: &Path) -> VibeResult<IntegrityReport> {
+    let store = open(db, false).await?;
+    let rows = store.query_simple("PRAGMA integrity_check".to_string()).await?;
+    let messages: Vec<String> = rows
+        .into_iter()
+        .filter_map(|row| row.into_iter().next())
+        .map(|(_, value)| value.as_str().map_or_else(|| value.to_string(), str::to_string))
+        .collect();
+    let ok = messages.len() == 1 && messages[0] == "ok";
+    Ok(IntegrityReport {
+        ok,
+        problems: if ok { Vec::new() } else { messages },
+    })
+}
+
+/// Opens `db` without retrying busy writes, waiting at most `LOCK_TIMEOUT`
+/// for a lock
+async fn open(db: &Path, create: bool) -> VibeResult<Arc<VibeStore>> {
+    if !create && !db.is_file() {
+        return Err(VibeError::NotFound(format!("No database at {}", db.display())));
+    }
+    let store = VibeStore::new(db)
+        .await
+        .map_err(|e| locked(db, e))?
+        .with_busy_retry(BusyRetry {
+            max_attempts: 1,
+            ..BusyRetry::default()
+        });
+    let timeout = PragmaUpdate {
+        busy_timeout: Some(LOCK_TIMEOUT.as_millis() as u64),
+        ..PragmaUpdate::default()
+    };
+    store.set_pragmas(timeout).await?;
+    Ok(Arc::new(store))
+}
+
+/// Explains busy/locked errors: another connection, most likely a running
+/// server, held the lock for longer than `LOCK_TIMEOUT`
+fn locked(db: &Path, err: VibeError) -> VibeError {
+    match err {
+        VibeError::Sqlite {
+            code: rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked,
+            ..
+        } => VibeError::Conflict(format!(
+            "{} is locked by another connection (is the server running?); stop it or try again later",
+            db.display()
+        )),
+        err => err,
+    }
+}
+
+fn io_error(path: &Path, err: std::io::Error) -> VibeError {
+    VibeError::Internal(anyhow::anyhow!("{}: {}", path.display(), err))
+}
+
+/// Size of the database file and its WAL
+fn file_size(db: &Path) -> u64 {
+    let wal = PathBuf::from(format!("{}-wal", db.display()));
+    [db, wal.as_path()]
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SqlValue;
+    use crate::testing::TestServer;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    async fn seeded(dir: &Path) -> PathBuf {
+        let db = dir.join("admin.db");
+        let store = VibeStore::new(&db).await.unwrap();
+        store
+            .execute_batch(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, bio TEXT);
+                 INSERT INTO users (name) VALUES ('ada'), ('grace');"
+                    .to_string(),
+            )
+            .await
+            .unwrap();
+        db
+    }
+
+    #[test]
+    fn test_parse_admin_commands() {
+        assert_eq!(
+            AdminCommand::parse(&args("vacuum --db data.db"), "vibedb.db").unwrap(),
+            AdminCommand::Vacuum { db: "data.db".into() }
+        );
+        assert_eq!(
+            AdminCommand::parse(&args("export -c users --out users.ndjson"), "vibedb.db").unwrap(),
+            AdminCommand::Export {
+                db: "vibedb.db".into(),
+                collection: "users".to_string(),
+                format: ExportFormat::Ndjson,
+                out: "users.ndjson".into(),
+            }
+        );
+        for (line, error) in [
+            ("", "missing admin command"),
+            ("compact", "unknown admin command"),
+            ("export --collection users", "needs --out"),
+            ("export --collection users --out x --format csv", "unknown export format"),
+            ("vacuum --email a@b.c", "does not take --email"),
+            ("vacuum --db", "needs a value"),
+            ("integrity-check --force", "unknown option"),
+        ] {
+            let message = AdminCommand::parse(&args(line), "vibedb.db").unwrap_err();
+            assert!(message.contains(error), "{}: {}", line, message);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_vacuum_shrinks_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = seeded(dir.path()).await;
+        {
+            let store = VibeStore::new(&db).await.unwrap();
+            let bio = "x".repeat(4096);
+            for _ in 0..200 {
+                store
+                    .execute("INSERT INTO users (name, bio) VALUES ('filler', ?)".to_string(), vec![SqlValue::Text(bio.clone())])
+                    .await
+                    .unwrap();
+            }
+            store
+                .execute_batch("DELETE FROM users WHERE name = 'filler'; PRAGMA wal_checkpoint(TRUNCATE);".to_string())
+                .await
+                .unwrap();
+        }
+
+        let report = vacuum(&db).await.unwrap();
+        assert!(report.bytes_after < report.bytes_before / 4, "{:?}", report);
+        assert_eq!(integrity_check(&db).await.unwrap(), IntegrityReport { ok: true, problems: vec![] });
+
+        let missing = dir.path().join("missing.db");
+        assert_eq!(vacuum(&missing).await.unwrap_err().error_code(), "NOT_FOUND");
+        assert!(!missing.exists());
+    }
+
+    #[tokio::test]
+    async fn test_vacuum_fails_clearly_while_locked() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = seeded(dir.path()).await;
+        let server = rusqlite::Connection::open(&db).unwrap();
+        server.execute_batch("BEGIN IMMEDIATE; INSERT INTO users (name) VALUES ('pending');").unwrap();
+
+        let err = vacuum(&db).await.unwrap_err();
+        assert_eq!(err.error_code(), "CONFLICT");
+        assert!(err.to_string().contains("is the server running?"), "{}", err);
+        server.execute_batch("COMMIT").unwrap();
+        vacuum(&db).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_export_writes_a_collection() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = seeded(dir.path()).await;
+        let out = dir.path().join("users.ndjson");
+        let command = AdminCommand::parse(
+            &args(&format!("export --db {} --collection users --format ndjson --out {}", db.display(), out.display())),
+            "vibedb.db",
+        )
+        .unwrap();
+        assert_eq!(command.run().await.unwrap(), format!("Exported 2 rows of users to {}", out.display()));
+
+        let lines: Vec<Value> = std::fs::read_to_string(&out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["type"], "snapshot");
+        assert_eq!(lines[0]["tables"], json!(["users"]));
+        assert_eq!(lines[1]["row"], json!({"id": 1, "name": "ada", "bio": null}));
+        assert_eq!(lines[2]["row"]["name"], "grace");
+        assert_eq!(lines[3], json!({"type": "end", "rows": 2}));
+
+        let err = export(&db, "orders", ExportFormat::Ndjson, &mut Vec::new()).await.unwrap_err();
+        assert_eq!(err.error_code(), "TABLE_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_created_user_can_log_in_to_the_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("fresh.db");
+        let user = create_user(&db, "ops@example.com", "offline-secret-1", ADMIN_ROLE).await.unwrap();
+        assert_eq!(user.role, ADMIN_ROLE);
+        let err = create_user(&db, "ops@example.com", "offline-secret-1", ADMIN_ROLE).await.unwrap_err();
+        assert_eq!(err.error_code(), "CONFLICT");
+        let err = create_user(&db, "root@example.com", "offline-secret-1", "root").await.unwrap_err();
+        assert_eq!(err.error_code(), "INVALID_PAYLOAD");
+
+        let store = Arc::new(VibeStore::new(&db).await.unwrap());
+        let server = TestServer::builder().store(store).spawn().await;
+        let tokens = server
+            .post("/v1/auth/login")
+            .json(json!({"email": "ops@example.com", "password": "offline-secret-1"}))
+            .send()
+            .await
+            .assert_success();
+        let token = tokens["access_token"].as_str().unwrap();
+        server.get("/v1/admin/pragmas").bearer(token).send().await.assert_success();
+    }
+}
@@ -0,0 +1,366 @@
+//! # Aggregation
+//!
+//! Grouped metrics over a collection, served by
+//! `GET /v1/query/:collection/aggregate`:
+//!
+//! ```text
+//! ?metric=count&metric=avg:latency_ms&metric=p95:latency_ms&group_by=service
+//! ```
+//!
+//! `count`, `count:col`, `sum:col`, `avg:col`, `min:col` and `max:col` are
+//! the SQL aggregates. `pNN:col` (0-100) and `median:col` (`p50`) are
+//! nearest-rank percentiles: the smallest value with at least NN% of the
+//! column's non-NULL values at or below it, so p50 of 1..=100 is 50.
+//!
+//! Percentiles use window functions when the SQLite build has them (one
+//! ranked query per metric). Otherwise every group takes two passes: a COUNT
+//! to find the rank, then `ORDER BY ... LIMIT 1 OFFSET rank - 1`. Both give
+//! identical results.
+
+use crate::capabilities::{Capabilities, Capability};
+use crate::db::{json_to_sql_value, SqlValue, VibeStore};
+use crate::error::{VibeError, VibeResult};
+use crate::guard::SchemaGuard;
+use crate::inference::SqliteType;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// Most groups a single aggregation may return
+pub const MAX_GROUPS: usize = 1000;
+
+/// One requested metric
+#[derive(Debug, Clone, PartialEq)]
+pub enum Metric {
+    /// `COUNT(*)`, or `COUNT(column)` when a column is given
+    Count(Option<String>),
+    Sum(String),
+    Avg(String),
+    Min(String),
+    Max(String),
+    /// Nearest-rank percentile of the column's non-NULL values
+    Percentile { percent: u32, column: String },
+}
+
+impl Metric {
+    /// Parses `function` or `function:column`
+    pub fn parse(spec: &str) -> VibeResult<Self> {
+        let (function, column) = match spec.split_once(':') {
+            Some((function, column)) => {
+                SchemaGuard::validate_identifier(column)?;
+                (function.to_ascii_lowercase(), Some(column.to_string()))
+            }
+            None => (spec.to_ascii_lowercase(), None),
+        };
+        let column_for = |function: &str| {
+            column.clone().ok_or_else(|| {
+                VibeError::InvalidPayload(format!(
+                    "Metric {} needs a column, e.g. {}:latency_ms",
+                    function, function
+                ))
+            })
+        };
+
+        match function.as_str() {
+            "count" => Ok(Metric::Count(column.clone())),
+            "sum" => Ok(Metric::Sum(column_for("sum")?)),
+            "avg" => Ok(Metric::Avg(column_for("avg")?)),
+            "min" => Ok(Metric::Min(column_for("min")?)),
+            "max" => Ok(Metric::Max(column_for("max")?)),
+            "median" => Ok(Metric::Percentile {
+                percent: 50,
+                column: column_for("median")?,
+            }),
+            other => match other.strip_prefix('p').and_then(|p| p.parse::<u32>().ok()) {
+                Some(percent) if percent <= 100 => Ok(Metric::Percentile {
+                    percent,
+                    column: column_for(other)?,
+                }),
+                _ => Err(VibeError::InvalidPayload(format!(
+                    "Unknown metric {}; use count, sum, avg, min, max, median or p0-p100",
+                    spec
+                ))),
+            },
+        }
+    }
+
+    /// Column the metric reads, if any
+    pub fn column(&self) -> Option<&str> {
+        match self {
+            Metric::Count(column) => column.as_deref(),
+            Metric::Sum(column)
+            | Metric::Avg(column)
+            | Metric::Min(column)
+            | Metric::Max(column)
+            | Metric::Percentile { column, .. } => Some(column),
+        }
+    }
+
+    /// Reads the column through a cast hint, e.g. `CAST(latency_ms AS REAL)`
+    ///
+    /// Call after the column has been validated.
+    pub fn cast(&mut self, target: &SqliteType) {
+        let column = match self {
+            Metric::Count(None) => return,
+            Metric::Count(Some(column))
+            | Metric::Sum(column)
+            | Metric::Avg(column)
+            | Metric::Min(column)
+            | Metric::Max(column)
+            | Metric::Percentile { column, .. } => column,
+        };
+        *column = target.cast(column);
+    }
+
+    /// Aggregate expression; percentiles have none
+    fn sql(&self) -> Option<String> {
+        match self {
+            Metric::Count(None) => Some("COUNT(*)".to_string()),
+            Metric::Count(Some(column)) => Some(format!("COUNT({})", column)),
+            Metric::Sum(column) => Some(format!("SUM({})", column)),
+            Metric::Avg(column) => Some(format!("AVG({})", column)),
+            Metric::Min(column) => Some(format!("MIN({})", column)),
+            Metric::Max(column) => Some(format!("MAX({})", column)),
+            Metric::Percentile { .. } => None,
+        }
+    }
+}
+
+/// How percentiles were computed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PercentileMethod {
+    WindowFunctions,
+    TwoPass,
+}
+
+impl PercentileMethod {
+    /// Window functions when the build has them
+    pub fn for_capabilities(capabilities: &Capabilities) -> Self {
+        if capabilities.has(Capability::WindowFunctions) {
+            PercentileMethod::WindowFunctions
+        } else {
+            PercentileMethod::TwoPass
+        }
+    }
+}
+
+/// 1-based nearest rank of `percent` among `n` values
+fn nearest_rank(percent: u32, n: i64) -> i64 {
+    ((i64::from(percent) * n + 99) / 100).max(1)
+}
+
+/// A grouped aggregation over one collection
+#[derive(Debug, Clone)]
+pub struct Aggregation {
+    pub collection: String,
+    pub group_by: Vec<String>,
+    /// Metrics keyed by the name they are reported under
+    pub metrics: Vec<(String, Metric)>,
+    /// WHERE conditions, each with one `?` bound from `params` in order
+    pub conditions: Vec<String>,
+    pub params: Vec<SqlValue>,
+}
+
+impl Aggregation {
+    fn where_clause(&self, extra: &[String]) -> String {
+        let conditions: Vec<&String> = self.conditions.iter().chain(extra).collect();
+        if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(
+                " WHERE {}",
+                conditions.iter().map(|c| c.as_str()).collect::<Vec<_>>().join(" AND ")
+            )
+        }
+    }
+
+    fn group_clause(&self, keyword: &str) -> String {
+        if self.group_by.is_empty() {
+            String::new()
+        } else {
+            format!(" {} {}", keyword, self.group_by.join(", "))
+        }
+    }
+
+    /// One object per group, ordered by the group columns
+    pub async fn run(&self, store: &VibeStore, method: PercentileMethod) -> VibeResult<Vec<Value>> {
+        // Pass 1: the groups and every plain aggregate
+        let mut select = self.group_by.clone();
+        let aggregates: Vec<(&String, String)> = self
+            .metrics
+            .iter()
+            .filter_map(|(name, metric)| metric.sql().map(|sql| (name, sql)))
+            .collect();
+        select.extend(aggregates.iter().map(|(_, sql)| sql.clone()));
+        // Keeps the query aggregate when only percentiles are requested
+        select.push("COUNT(*)".to_string());
+
+        let sql = format!(
+            "SELECT {} FROM {}{}{}{} LIMIT {}",
+            select.join(", "),
+            self.collection,
+            self.where_clause(&[]),
+            self.group_clause("GROUP BY"),
+            self.group_clause("ORDER BY"),
+            MAX_GROUPS + 1
+        );
+        let rows = store.query(sql, self.params.clone()).await?;
+        if rows.len() > MAX_GROUPS {
+            return Err(VibeError::InvalidPayload(format!(
+                "Aggregation yields more than {} groups; narrow the filter or group_by",
+                MAX_GROUPS
+            )));
+        }
+
+        let mut keys = Vec::with_capacity(rows.len());
+        let mut groups = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut values = row.into_iter().map(|(_, value)| value);
+            let key: Vec<Value> = values.by_ref().take(self.group_by.len()).collect();
+            let mut group: Map<String, Value> =
+                self.group_by.iter().cloned().zip(key.iter().cloned()).collect();
+            for ((name, _), value) in aggregates.iter().zip(values) {
+                group.insert(name.to_string(), value);
+            }
+            keys.push(key);
+            groups.push(group);
+        }
+
+        // Pass 2: percentiles, one metric at a time
+        for (name, metric) in &self.metrics {
+            let Metric::Percentile { percent, column } = metric else {
+                continue;
+            };
+            let values = match method {
+                PercentileMethod::WindowFunctions => {
+                    let ranked = self.ranked_percentile(store, *percent, column).await?;
+                    keys.iter()
+                        .map(|key| ranked.get(&Value::Array(key.clone()).to_string()).cloned())
+                        .collect()
+                }
+                PercentileMethod::TwoPass => {
+                    let mut values = Vec::with_capacity(keys.len());
+                    for key in &keys {
+                        values.push(self.two_pass_percentile(store, *percent, column, key).await?);
+                    }
+                    values
+                }
+            };
+            for (group, value) in groups.iter_mut().zip(values) {
+                group.insert(name.clone(), value.unwrap_or(Value::Null));
+            }
+        }
+
+        Ok(groups.into_iter().map(Value::Object).collect())
+    }
+
+    /// Percentile of every group in one query, keyed by the JSON array of
+    /// the group values
+    async fn ranked_percentile(
+        &self,
+        store: &VibeStore,
+        percent: u32,
+        column: &str,
+    ) -> VibeResult<HashMap<String, Value>> {
+        let partition = self.group_clause("PARTITION BY");
+        let mut select = self.group_by.clone();
+        select.push(format!("{} AS vibe_value", column));
+        select.push(format!(
+            "ROW_NUMBER() OVER ({} ORDER BY {}) AS vibe_rank",
+            partition, column
+        ));
+        select.push(format!("COUNT(*) OVER ({}) AS vibe_count", partition));
+
+        let mut outer = self.group_by.clone();
+        outer.push("vibe_value".to_string());
+        let sql = format!(
+            "SELECT {} FROM (SELECT {} FROM {}{}) WHERE vibe_rank = MAX(1, (? * vibe_count + 99) / 100)",
+            outer.join(", "),
+            select.join(", "),
+            self.collection,
+            self.where_clause(&[format!("{} IS NOT NULL", column)]),
+        );
+        let mut params = self.params.clone();
+        params.push(SqlValue::Integer(i64::from(percent)));
+
+        let mut values = HashMap::new();
+        for row in store.query(sql, params).await? {
+            let mut row: Vec<Value> = row.into_iter().map(|(_, value)| value).collect();
+            let value = row.pop().unwrap_or(Value::Null);
+            values.insert(Value::Array(row).to_string(), value);
+        }
+        Ok(values)
+    }
+
+    /// Percentile of one group: count its values, then fetch the one at
+    /// the nearest rank
+    async fn two_pass_percentile(
+        &self,
+        store: &VibeStore,
+        percent: u32,
+        column: &str,
+        key: &[Value],
+    ) -> VibeResult<Option<Value>> {
+        let mut conditions: Vec<String> =
+            self.group_by.iter().map(|c| format!("{} IS ?", c)).collect();
+        conditions.push(format!("{} IS NOT NULL", column));
+        let mut params = self.params.clone();
+        params.extend(key.iter().map(json_to_sql_value));
+        let where_clause = self.where_clause(&conditions);
+
+        let count_sql = format!("SELECT COUNT(*) FROM {}{}", self.collection, where_clause);
+        let count = store.query(count_sql, params.clone()).await?;
+        let n = count
+            .first()
+            .and_then(|row| row.first())
+            .and_then(|(_, value)| value.as_i64())
+            .unwrap_or(0);
+        if n == 0 {
+            return Ok(None);
+        }
+
+        let sql = format!(
+            "SELECT {} FROM {}{} ORDER BY {} LIMIT 1 OFFSET {}",
+            column,
+            self.collection,
+            where_clause,
+            column,
+            nearest_rank(percent, n) - 1
+        );
+        let rows = store.query(sql, params).await?;
+        Ok(rows
+            .into_iter()
+            .next()
+            .and_then(|row| row.into_iter().next())
+            .map(|(_, value)| value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_metrics() {
+        assert_eq!(Metric::parse("count").unwrap(), Metric::Count(None));
+        assert_eq!(
+            Metric::parse("P95:latency_ms").unwrap(),
+            Metric::Percentile {
+                percent: 95,
+                column: "latency_ms".to_string()
+            }
+        );
+        assert_eq!(Metric::parse("median:x").unwrap(), Metric::parse("p50:x").unwrap());
+        for bad in ["p101:x", "p95", "stddev:x", "avg:x;--", "pfoo:x"] {
+            assert!(Metric::parse(bad).is_err(), "{}", bad);
+        }
+
+        assert_eq!(nearest_rank(50, 100), 50);
+        assert_eq!(nearest_rank(95, 100), 95);
+        assert_eq!(nearest_rank(50, 3), 2);
+        assert_eq!(nearest_rank(0, 10), 1);
+        assert_eq!(nearest_rank(100, 7), 7);
+    }
+}
@@ -7,32 +7,99 @@
 //!
 //! - `POST /v1/push/:collection` - Insert data with auto-schema evolution
 //! - `GET /v1/query/:collection` - Query data from a collection
+//! - `GET /v1/query/:collection/asof` - Query audited data as of a timestamp
+//! - `GET /v1/query/:collection/aggregate` - Grouped metrics and percentiles
+//! - `POST /v1/update/:collection/:id/increment` - Atomically add to a numeric field
+//! - `POST /v1/update/:collection/:id/array-append` - Atomically append to a JSON array field
+//! - `GET|POST|DELETE /v1/locks/:collection/:id` - Advisory row locks for editors
+//! - `POST /v1/delete/:collection` - Delete by filters, with a count-only precheck
 //! - `GET /v1/tables` - List all tables
 //! - `GET /v1/tables/:collection` - Get table stats
+//! - `GET /v1/tables/:collection/perf` - Per-collection write statistics
+//! - `GET|PUT /v1/tables/:collection/settings` - Per-collection settings
+//! - `PUT /v1/tables/:collection/descriptions` - Describe columns
+//! - `GET|POST /v1/sources` - Remote JSON endpoints polled into collections
+//! - `GET /v1/dead-letters` - Rejected pushes; `POST .../:id/retry` re-pushes one
+//! - `POST /v1/admin/erasure` - Erase a data subject's rows across collections
+//! - `GET|PUT /v1/admin/concurrency` - Requests in flight and their limits
+//! - `GET|POST /v1/admin/pragmas` - SQLite pragmas of the main connection
+//! - `POST /v1/admin/checkpoint` - Checkpoint the WAL on demand
+//! - `GET /v1/admin/index-suggestions` - Unindexed columns queries filter or order by
+//! - `POST /v1/render` - Render query results through an HTML template
+//! - `GET|PUT|DELETE /v1/templates/:name` - Named HTML templates
+//! - `POST /v1/snapshots` - Begin a snapshot for paging with `X-Vibe-Snapshot`
 //! - `GET /v1/stream/:collection` - SSE stream for real-time updates
+//! - `GET /v1/sync/:collection` - Current rows, then live updates, without gaps
+//! - `GET /metrics` - Write statistics and requests in flight (Prometheus)
 //! - `GET /explore` - Vibe-Explorer dashboard
 
-use crate::db::{json_to_sql_value, SqlValue, VibeStore};
+use crate::aggregate::{Aggregation, Metric, PercentileMethod};
+use crate::audit::{self, AuditOp, InsertAudit};
+use crate::auth::{AuthService, AuthUser};
+use crate::batching::WriteBatcher;
+use crate::capabilities::{Capabilities, Capability};
+use crate::clock::{SharedClock, SystemClock};
+use crate::config::{BasePathConfig, CorsConfig, VibeConfig};
+use crate::db::{json_to_sql_value, CheckpointMode, PragmaUpdate, ReadSession, SnapshotItem, SnapshotShape, SqlValue, VibeStore};
+use crate::dead_letters::{self, DeadLetterFilter, DeadLetterStore, Origin};
+use crate::descriptions::DescriptionStore;
+use crate::erasure::{self, ErasureRequest};
 use crate::error::VibeError;
-use crate::guard::SchemaGuard;
+use crate::explode;
+use crate::explorer::FORWARDED_PREFIX;
+use crate::filters::{Filter, Filters};
+use crate::guard::{self, KeyCollision, SchemaGuard, TypeHints, MAX_CACHE_CHECKS};
+use crate::limits::ConcurrencyLimiter;
+use crate::locks::{LockRegistry, RowLock, DEFAULT_LOCK_TTL, LOCK_SESSION_HEADER, MAX_LOCK_TTL};
+use crate::inference::{
+    infer_batch_schema_confident, InexactNumber, InferenceMode, InferenceReport, SqliteType, DEFAULT_CONFIDENCE,
+};
+use crate::inline_files::{self, StoredFiles};
+use crate::perf::{ColumnUse, PerfRegistry, QueryColumnUse};
+use crate::projection::Selection;
+use crate::proposals::{ProposalStore, STATUS_EXECUTED};
+use crate::row_cache::RowCache;
+use crate::row_counts::RowCounts;
+use crate::settings::{CollectionSettings, ColumnMask, SettingsStore};
+use crate::sources::{SourceDefinition, SourceStore};
+use crate::snapshots::{SnapshotRegistry, SNAPSHOT_HEADER};
+use crate::sql_policy::{SqlAccess, SqlPolicy};
+use crate::storage::{is_storage_stream, Compression, StorageEvent, StorageService};
+use crate::templates::{self, RenderLimits, Template, TemplateStore};
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::{sse::Event, IntoResponse, Sse},
-    routing::{get, post},
+    body::{Body, Bytes},
+    extract::{ConnectInfo, FromRef, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{sse::Event, IntoResponse, Response, Sse},
+    routing::{delete, get, post, put},
     Json, Router,
 };
 use futures::stream::Stream;
+use rusqlite::OptionalExtension;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::convert::Infallible;
+use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::broadcast;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowHeaders, AllowMethods, Any, CorsLayer};
 use tower_http::trace::TraceLayer;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
+
+/// Default window for "recent" in column usage reports
+const DEFAULT_USAGE_RECENT_DAYS: u32 = 90;
+
+/// How long the readiness probe waits for the database
+const READINESS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long the readiness check waits on the main connection before
+/// reporting it `busy`
+const MAIN_CONNECTION_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Content type for newline-delimited JSON responses
+const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
 
 /// Application state shared across all handlers
 #[derive(Clone)]
@@ -41,20 +108,155 @@ pub struct AppState {
     pub guard: Arc<SchemaGuard>,
     /// Broadcast channel for real-time updates per table
     pub broadcasters: Arc<dashmap::DashMap<String, broadcast::Sender<Value>>>,
+    /// Sequence number of the last event broadcast per collection
+    pub event_seqs: Arc<dashmap::DashMap<String, u64>>,
+    /// Ids of inserts waiting for their collection's `inserts` summary
+    pub pending_inserts: Arc<dashmap::DashMap<String, Vec<i64>>>,
+    /// Runtime configuration
+    pub config: Arc<VibeConfig>,
+    /// Statement screening for the raw SQL endpoints
+    pub sql_policy: Arc<SqlPolicy>,
+    /// Per-collection settings (column visibility, ...)
+    pub settings: Arc<SettingsStore>,
+    /// SQL statements awaiting admin approval
+    pub proposals: Arc<ProposalStore>,
+    /// Per-collection write statistics
+    pub perf: Arc<PerfRegistry>,
+    /// Remote JSON endpoints polled into collections
+    pub sources: Arc<SourceStore>,
+    /// Rejected pushes kept for retry
+    pub dead_letters: Arc<DeadLetterStore>,
+    /// Free-text descriptions of columns
+    pub descriptions: Arc<DescriptionStore>,
+    /// Coalesces pushes into shared transactions
+    pub batcher: Arc<WriteBatcher>,
+    /// Rows of recent lookups by id (off by default)
+    pub row_cache: Arc<RowCache>,
+    /// Row counts of table stats, moved by writes (off by default)
+    pub row_counts: Arc<RowCounts>,
+    /// Named HTML templates for `POST /v1/render`
+    pub templates: Arc<TemplateStore>,
+    /// Read transactions shared by several requests
+    pub snapshots: Arc<SnapshotRegistry>,
+    /// Requests in flight, overall and per class; the application is
+    /// wrapped in `limits::limit_concurrency` with it
+    pub limits: Arc<ConcurrencyLimiter>,
+    /// Advisory row locks of concurrent editors
+    pub locks: Arc<LockRegistry>,
+    /// Time source of TTLs, expiry checks and sweeps
+    pub clock: SharedClock,
+    /// Resolves callers from bearer tokens; everyone is anonymous without it
+    pub auth: Option<AuthService>,
+    /// File storage for inline files; collections with `inline_files`
+    /// refuse data URLs without it
+    pub storage: Option<StorageService>,
 }
 
 impl AppState {
     pub fn new(store: Arc<VibeStore>) -> Self {
-        let guard = Arc::new(SchemaGuard::new(Arc::clone(&store)));
+        Self::with_config(store, VibeConfig::default())
+    }
+
+    /// Creates the application state with an explicit configuration
+    pub fn with_config(store: Arc<VibeStore>, config: VibeConfig) -> Self {
+        Self::with_clock(store, config, SystemClock::shared())
+    }
+
+    /// Creates the application state reading time from `clock`
+    pub fn with_clock(store: Arc<VibeStore>, config: VibeConfig, clock: SharedClock) -> Self {
+        let perf = Arc::new(PerfRegistry::default());
+        let settings = Arc::new(SettingsStore::new(Arc::clone(&store)));
+        let guard = Arc::new(
+            SchemaGuard::new(Arc::clone(&store))
+                .with_perf(Arc::clone(&perf))
+                .with_settings(Arc::clone(&settings))
+                .with_identifiers(config.identifiers.clone())
+                .with_payload_limits(config.payload.clone()),
+        );
+        let sql_policy = Arc::new(
+            SqlPolicy::new(&config.sql.blocklist).with_pragma_allowlist(&config.sql.pragma_allowlist),
+        );
+        let proposals = Arc::new(ProposalStore::new(Arc::clone(&store)));
+        let sources = Arc::new(SourceStore::new(Arc::clone(&store)).with_clock(Arc::clone(&clock)));
+        let dead_letters = Arc::new(DeadLetterStore::new(Arc::clone(&store)).with_clock(Arc::clone(&clock)));
+        let descriptions = Arc::new(DescriptionStore::new(Arc::clone(&store)));
+        let batcher = Arc::new(WriteBatcher::new(Arc::clone(&store), config.batching.clone()));
+        let row_cache = Arc::new(RowCache::new(config.query.row_cache_size));
+        let row_counts = Arc::new(RowCounts::new(config.query.row_count_cache));
+        let templates = Arc::new(TemplateStore::new(Arc::clone(&store)));
+        let snapshots = Arc::new(
+            SnapshotRegistry::new(Arc::clone(&store), config.snapshots.clone()).with_clock(Arc::clone(&clock)),
+        );
+        let limits = Arc::new(ConcurrencyLimiter::new(&config.concurrency));
         Self {
             store,
             guard,
             broadcasters: Arc::new(dashmap::DashMap::new()),
+            event_seqs: Arc::new(dashmap::DashMap::new()),
+            pending_inserts: Arc::new(dashmap::DashMap::new()),
+            config: Arc::new(config),
+            sql_policy,
+            settings,
+            proposals,
+            perf,
+            sources,
+            dead_letters,
+            descriptions,
+            batcher,
+            row_cache,
+            row_counts,
+            templates,
+            snapshots,
+            limits,
+            locks: Arc::new(LockRegistry::new().with_clock(Arc::clone(&clock))),
+            clock,
+            auth: None,
+            storage: None,
+        }
+    }
+
+    /// Enables caller identification through the given auth service
+    pub fn with_auth(mut self, auth: AuthService) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Stores inline files of pushed documents in the given storage
+    pub fn with_storage(mut self, storage: StorageService) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Inserts a pushed row, batched with concurrent pushes when the
+    /// collection or the server has batching on; with `audit`, its audit
+    /// entry is written in the transaction committing it
+    async fn insert_row(
+        &self,
+        collection: &str,
+        label: &str,
+        settings: &CollectionSettings,
+        sql: String,
+        params: Vec<SqlValue>,
+        audit: Option<InsertAudit>,
+    ) -> Result<i64, VibeError> {
+        if settings.batch_writes || self.batcher.enabled_globally() {
+            self.batcher.insert(collection, label, sql, params, audit).await
+        } else {
+            audit::insert_row(&self.store, label, sql, params, audit).await
         }
     }
 
+    /// Column mask for a caller on a collection
+    async fn column_mask(
+        &self,
+        collection: &str,
+        caller: Option<&AuthUser>,
+    ) -> Result<ColumnMask, VibeError> {
+        Ok(self.settings.get(collection).await?.mask_for(caller))
+    }
+
     /// Get or create a broadcaster for a collection
-    fn get_broadcaster(&self, collection: &str) -> broadcast::Sender<Value> {
+    pub(crate) fn get_broadcaster(&self, collection: &str) -> broadcast::Sender<Value> {
         self.broadcasters
             .entry(collection.to_string())
             .or_insert_with(|| {
@@ -63,6 +265,89 @@ impl AppState {
             })
             .clone()
     }
+
+    /// Broadcasts a write event to the collection's subscribers
+    ///
+    /// Stamps the event with `collection`, a `seq` that increases by one
+    /// per event of the collection (from 1 at startup) and an RFC 3339
+    /// `ts`. The counter stays locked until the event is sent, so
+    /// subscribers see `seq` in order.
+    pub(crate) fn broadcast(&self, collection: &str, mut event: Value) {
+        let tx = self.get_broadcaster(collection);
+        let mut seq = self.event_seqs.entry(collection.to_string()).or_insert(0);
+        *seq += 1;
+        if let Value::Object(fields) = &mut event {
+            fields.insert("collection".to_string(), json!(collection));
+            fields.insert("seq".to_string(), json!(*seq));
+            fields.insert("ts".to_string(), json!(audit::now_timestamp()));
+        }
+        let _ = tx.send(event);
+    }
+
+    /// Broadcasts an insert event, or adds its id to the collection's
+    /// pending `inserts` summary when the collection coalesces inserts
+    ///
+    /// The first insert of a window schedules the summary; every insert
+    /// arriving before it is sent joins it.
+    fn broadcast_insert(&self, collection: &str, settings: &CollectionSettings, id: i64, event: Value) {
+        let Some(window) = settings.coalesce_inserts() else {
+            return self.broadcast(collection, event);
+        };
+        let mut pending = self.pending_inserts.entry(collection.to_string()).or_default();
+        pending.push(id);
+        if pending.len() > 1 {
+            return;
+        }
+        drop(pending);
+
+        let state = self.clone();
+        let collection = collection.to_string();
+        tokio::spawn(async move {
+            tokio::time::sleep(window).await;
+            let ids = state
+                .pending_inserts
+                .remove(&collection)
+                .map(|(_, ids)| ids)
+                .unwrap_or_default();
+            state.broadcast(&collection, json!({
+                "event": "inserts",
+                "count": ids.len(),
+                "ids": ids
+            }));
+        });
+    }
+
+    /// Broadcasts the release of a row lock; `reason` is `released`,
+    /// `expired` or `disconnected`
+    fn broadcast_unlock(&self, lock: &RowLock, reason: &str) {
+        self.broadcast(&lock.collection, json!({
+            "event": "unlock",
+            "id": lock.id,
+            "holder": lock.holder,
+            "reason": reason
+        }));
+    }
+
+    /// Releases a lock at its deadline, unless it was refreshed or
+    /// released by then
+    fn schedule_lock_expiry(&self, lock: &RowLock) {
+        let state = self.clone();
+        let (collection, id, token) = (lock.collection.clone(), lock.id, lock.token());
+        let deadline = self.clock.sleep_until(lock.deadline());
+        tokio::spawn(async move {
+            deadline.await;
+            if let Some(lock) = state.locks.expire(&collection, id, token) {
+                debug!("🔓 Lock on {} of {} expired", id, collection);
+                state.broadcast_unlock(&lock, "expired");
+            }
+        });
+    }
+}
+
+impl FromRef<AppState> for Option<AuthService> {
+    fn from_ref(state: &AppState) -> Self {
+        state.auth.clone()
+    }
 }
 
 /// Standard API response
@@ -92,19 +377,80 @@ impl<T: Serialize> ApiResponse<T> {
 }
 
 /// Push response data
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct PushResponse {
     pub id: i64,
     pub collection: String,
     pub columns_added: Vec<String>,
+    /// Ids of rows exploded into child collections
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub children: BTreeMap<String, Vec<i64>>,
+    /// Over-long keys stored under shortened names, original -> stored
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+    /// Keys naming system columns (`id`, `created_at`, `updated_at` in any
+    /// case) that were not stored
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_fields: Vec<String>,
+    /// Numbers that weren't stored exactly (see `number_warnings`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Where the push spent its time; only sent with `X-Vibe-Timings: 1`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<PushTimings>,
+}
+
+/// Request header asking for the `timings` of a push
+pub const TIMINGS_HEADER: &str = "x-vibe-timings";
+
+/// Time spent by a push before and in its insert
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PushTimings {
+    /// Resolving the collection and evolving its schema
+    pub guard_ms: f64,
+    /// Of which migrations
+    pub ddl_ms: f64,
+    pub ddl_statements: u64,
+    /// Inserting the row and its exploded children
+    pub insert_ms: f64,
+}
+
+/// Query parameters for pushes
+#[derive(Debug, Default, Deserialize)]
+pub struct PushParams {
+    /// Store `created_at`/`updated_at` from the payload (backfills)
+    #[serde(default)]
+    pub allow_timestamps: bool,
+    /// How batch pushes type columns whose values disagree
+    #[serde(default)]
+    pub inference: InferenceMode,
+    /// Share the dominant type needs with `inference=confident`
+    #[serde(default)]
+    pub confidence: Option<f64>,
+    /// Store keys that aren't valid identifiers under sanitized names,
+    /// suffixing (`suffix`) or refusing (`reject`) colliding ones
+    #[serde(default)]
+    pub sanitize_keys: Option<KeyCollision>,
 }
 
 /// Batch push response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct BatchPushResponse {
     pub inserted: u64,
     pub collection: String,
     pub columns_added: Vec<String>,
+    /// Over-long keys stored under shortened names, original -> stored
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+    /// Columns typed over outliers, with `inference=confident`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inference: Option<InferenceReport>,
+    /// System column keys of any item that were not stored
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_fields: Vec<String>,
+    /// Numbers that weren't stored exactly, prefixed with their item
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
 /// Query parameters for GET requests
@@ -118,16 +464,111 @@ pub struct QueryParams {
     pub order_by: Option<String>,
     #[serde(default)]
     pub order_dir: Option<String>,
+    /// Stream rows as NDJSON instead of a buffered JSON envelope
+    #[serde(default)]
+    pub stream: Option<bool>,
+    /// Comma-separated exploded array fields to nest under each row
+    #[serde(default)]
+    pub expand: Option<String>,
+    /// Return renamed keys as first pushed instead of as stored
+    #[serde(default)]
+    pub original_keys: Option<bool>,
+    /// Fields to return, renamed or computed (see `projection`)
+    #[serde(default)]
+    pub select: Option<String>,
+    /// Every other parameter; see `filter_condition`
+    #[serde(flatten)]
+    pub filters: Filters,
+}
+
+/// Query parameters for single-document reads
+#[derive(Debug, Deserialize)]
+pub struct ExpandParams {
+    /// Comma-separated exploded array fields to nest under the row
+    pub expand: Option<String>,
+    /// Return renamed keys as first pushed instead of as stored
+    #[serde(default)]
+    pub original_keys: bool,
+}
+
+/// Query parameters for column usage reports
+#[derive(Debug, Deserialize)]
+pub struct ColumnUsageParams {
+    /// Rows created within this many days count as recent
+    pub recent_days: Option<u32>,
+    /// Only scan the newest `sample` rows
+    pub sample: Option<u64>,
+}
+
+/// Query parameters for column previews
+#[derive(Debug, Deserialize)]
+pub struct ColumnPreviewParams {
+    /// Newest rows the examples are taken from
+    pub sample_size: Option<u64>,
+    /// Characters kept of each text example
+    pub max_length: Option<usize>,
+}
+
+/// Query parameters for time-travel reads
+#[derive(Debug, Deserialize)]
+pub struct AsOfParams {
+    /// RFC 3339 timestamp to read at
+    pub ts: String,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
     #[serde(flatten)]
     pub filters: HashMap<String, String>,
 }
 
+/// Body of `POST /v1/update/:collection/:id/increment`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IncrementRequest {
+    pub field: String,
+    /// Delta to add, 1 when omitted; any JSON number
+    pub by: Option<Value>,
+}
+
+/// Body of `POST /v1/update/:collection/:id/array-append`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArrayAppendRequest {
+    pub field: String,
+    /// Element to append; any JSON value
+    pub value: Value,
+}
+
+/// Query parameters of the row lock endpoints
+#[derive(Debug, Deserialize)]
+pub struct LockParams {
+    /// Seconds until the lock expires unless taken again (default 30)
+    pub ttl_secs: Option<u64>,
+    /// Session of an open stream: the lock is released when it closes,
+    /// and anonymous callers hold locks through it
+    pub session: Option<String>,
+}
+
+/// Query parameters of the table listing and stats
+#[derive(Debug, Default, Deserialize)]
+pub struct RowCountParams {
+    /// Count rows instead of using the row count cache
+    #[serde(default)]
+    pub exact: bool,
+}
+
 /// Table stats response
 #[derive(Debug, Serialize)]
 pub struct TableStatsResponse {
     pub name: String,
     pub column_count: usize,
     pub row_count: u64,
+    /// `row_count` is a maintained count rather than a fresh one (see
+    /// `row_counts`)
+    pub approximate: bool,
+    /// Read requests since startup (see `perf`)
+    pub reads: u64,
+    /// Write requests since startup
+    pub writes: u64,
     pub columns: Vec<ColumnResponse>,
 }
 
@@ -137,43 +578,200 @@ pub struct ColumnResponse {
     pub col_type: String,
     pub nullable: bool,
     pub primary_key: bool,
+    /// Generated column computed from other columns
+    #[serde(rename = "virtual")]
+    pub is_virtual: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// CORS for the whole application, auth and storage routes included
+///
+/// Expects a configuration that passed `CorsConfig::validate`. With
+/// credentials, the requested methods and headers are echoed back, since
+/// browsers ignore `*` for credentialed requests.
+pub fn cors_layer(config: &CorsConfig) -> CorsLayer {
+    let cors = if config.allows_any_origin() {
+        CorsLayer::new().allow_origin(Any)
+    } else {
+        let origins: Vec<HeaderValue> = config
+            .allowed_origins
+            .iter()
+            .filter_map(|origin| HeaderValue::from_str(origin).ok())
+            .collect();
+        CorsLayer::new().allow_origin(origins).vary([header::ORIGIN])
+    };
+    let cors = if config.allow_credentials {
+        cors.allow_credentials(true)
+            .allow_methods(AllowMethods::mirror_request())
+            .allow_headers(AllowHeaders::mirror_request())
+    } else {
+        cors.allow_methods(Any).allow_headers(Any)
+    };
+    match config.max_age {
+        Some(max_age) => cors.max_age(max_age),
+        None => cors,
+    }
 }
 
 /// Creates the Axum router with all endpoints
+///
+/// CORS is not included; wrap the assembled application in `cors_layer`.
 pub fn create_router(state: AppState) -> Router {
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
-
     Router::new()
         // Data endpoints
         .route("/v1/push/:collection", post(push_handler))
         .route("/v1/push/:collection/batch", post(batch_push_handler))
         .route("/v1/query/:collection", get(query_handler))
+        .route("/v1/query/:collection/asof", get(query_as_of_handler))
+        .route("/v1/query/:collection/aggregate", get(aggregate_handler))
         .route("/v1/query/:collection/:id", get(get_by_id_handler))
+        .route("/v1/query/:collection/:id/asof", get(get_as_of_handler))
         .route("/v1/update/:collection/:id", post(update_handler))
+        .route("/v1/update/:collection/:id/increment", post(increment_handler))
+        .route("/v1/update/:collection/:id/array-append", post(array_append_handler))
+        .route(
+            "/v1/locks/:collection/:id",
+            get(get_lock_handler).post(acquire_lock_handler).delete(release_lock_handler),
+        )
+        .route("/v1/delete/:collection", post(filtered_delete_handler))
         .route("/v1/delete/:collection/:id", post(delete_handler))
         // SQL Control endpoints
         .route("/v1/sql/query", post(sql_query_handler))
         .route("/v1/sql/execute", post(sql_execute_handler))
+        .route("/v1/sql/propose", post(propose_sql_handler))
+        .route("/v1/sql/proposals", get(list_proposals_handler))
+        .route("/v1/sql/proposals/:id", get(get_proposal_handler))
+        .route("/v1/sql/proposals/:id/approve", post(approve_proposal_handler))
+        .route("/v1/sql/proposals/:id/reject", post(reject_proposal_handler))
         // Meta endpoints
         .route("/v1/tables", get(list_tables_handler))
         .route("/v1/tables/:collection", get(table_stats_handler))
+        .route("/v1/tables/:collection/perf", get(table_perf_handler))
+        .route(
+            "/v1/tables/:collection/settings",
+            get(get_settings_handler).put(put_settings_handler),
+        )
+        .route(
+            "/v1/tables/:collection/descriptions",
+            put(put_descriptions_handler),
+        )
+        .route(
+            "/v1/tables/:collection/columns/:column/description",
+            put(put_description_handler),
+        )
+        .route(
+            "/v1/tables/:collection/computed",
+            post(add_computed_column_handler),
+        )
+        .route(
+            "/v1/tables/:collection/column-usage",
+            get(column_usage_handler),
+        )
+        .route("/v1/tables/:collection/preview", get(column_preview_handler))
+        .route("/v1/tables/:source/move-to/:target", post(move_rows_handler))
+        .route("/v1/schema/:collection/jsonschema", get(json_schema_handler))
+        // Admin
+        .route(
+            "/v1/admin/schema-cache",
+            get(inspect_schema_cache_handler).delete(clear_schema_cache_handler),
+        )
+        .route(
+            "/v1/admin/schema-cache/:collection",
+            delete(evict_schema_cache_handler),
+        )
+        .route("/v1/admin/erasure", post(erasure_handler))
+        .route(
+            "/v1/admin/concurrency",
+            get(get_concurrency_handler).put(put_concurrency_handler),
+        )
+        .route(
+            "/v1/admin/pragmas",
+            get(get_pragmas_handler).post(set_pragmas_handler),
+        )
+        .route("/v1/admin/checkpoint", post(checkpoint_handler))
+        .route("/v1/admin/index-suggestions", get(index_suggestions_handler))
+        // HTML rendering
+        .route("/v1/render", post(render_handler))
+        .route("/v1/templates", get(list_templates_handler))
+        .route(
+            "/v1/templates/:name",
+            get(get_template_handler)
+                .put(put_template_handler)
+                .delete(delete_template_handler),
+        )
+        // Pull sources
+        .route("/v1/sources", get(list_sources_handler).post(create_source_handler))
+        .route(
+            "/v1/sources/:id",
+            get(get_source_handler)
+                .put(update_source_handler)
+                .delete(delete_source_handler),
+        )
+        .route("/v1/sources/:id/run", post(run_source_handler))
+        .route("/v1/sources/:id/runs", get(source_runs_handler))
+        // Dead letters
+        .route("/v1/dead-letters", get(list_dead_letters_handler))
+        .route("/v1/dead-letters/:id", get(get_dead_letter_handler))
+        .route("/v1/dead-letters/:id/retry", post(retry_dead_letter_handler))
+        // Export
+        .route("/v1/export", get(export_handler))
+        .route("/v1/snapshots", post(create_snapshot_handler))
+        .route("/v1/snapshots/:id", delete(release_snapshot_handler))
         // Real-time streaming
         .route("/v1/stream/:collection", get(stream_handler))
+        .route("/v1/sync/:collection", get(sync_handler))
         // Health check
-        .route("/health", get(health_handler))
+        .route("/health", get(readiness_handler))
+        .route("/health/live", get(liveness_handler))
+        .route("/health/ready", get(readiness_handler))
+        .route("/v1/capabilities", get(capabilities_handler))
+        .route("/metrics", get(metrics_handler))
         .route("/", get(root_handler))
         // Middleware
-        .layer(cors)
+        .layer(axum::middleware::from_fn_with_state(state.clone(), log_slow_migrations))
         .layer(TraceLayer::new_for_http())
         .with_state(state)
 }
 
+/// Tallies the schema migrations of each request (see
+/// `guard::tally_migrations`), warning when they pass the thresholds of
+/// `MigrationConfig`
+async fn log_slow_migrations(
+    State(state): State<AppState>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> Response {
+    let route = format!("{} {}", request.method(), request.uri().path());
+    let (response, tally) = guard::tally_migrations(next.run(request)).await;
+    let limits = &state.config.migrations;
+    if tally.ddl_statements > limits.slow_ddl_statements || tally.ddl_time > limits.slow_after {
+        warn!(
+            "🐢 {} ran {} DDL statement(s) taking {}ms",
+            route,
+            tally.ddl_statements,
+            tally.ddl_time.as_millis()
+        );
+    }
+    response
+}
+
+/// Moves every route of an assembled application under the configured
+/// base path (see `BasePathConfig`); apply middleware layers afterwards
+pub fn nest_under_base_path(app: Router, base_path: &BasePathConfig) -> Router {
+    if base_path.prefix.is_empty() {
+        return app;
+    }
+    Router::new().nest(&base_path.prefix, app)
+}
+
 /// Root handler - API info
-async fn root_handler() -> impl IntoResponse {
-    Json(json!({
+///
+/// Endpoint paths carry the prefix the client reached the server under.
+async fn root_handler(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    let forwarded = headers.get(FORWARDED_PREFIX).and_then(|v| v.to_str().ok());
+    let prefix = state.config.base_path.public_prefix(forwarded);
+    let mut info = json!({
         "name": "VibeDB",
         "version": "1.0.0",
         "description": "🛸 Schema-Later Database with Automatic Evolution",
@@ -182,50 +780,391 @@ async fn root_handler() -> impl IntoResponse {
             "batch_push": "POST /v1/push/:collection/batch",
             "query": "GET /v1/query/:collection",
             "get_by_id": "GET /v1/query/:collection/:id",
+            "aggregate": "GET /v1/query/:collection/aggregate",
             "update": "POST /v1/update/:collection/:id",
+            "increment": "POST /v1/update/:collection/:id/increment",
+            "array_append": "POST /v1/update/:collection/:id/array-append",
+            "locks": "GET|POST|DELETE /v1/locks/:collection/:id",
             "delete": "POST /v1/delete/:collection/:id",
+            "filtered_delete": "POST /v1/delete/:collection?column=value[&count_only=true]",
             "tables": "GET /v1/tables",
             "table_stats": "GET /v1/tables/:collection",
+            "table_perf": "GET /v1/tables/:collection/perf",
+            "column_usage": "GET /v1/tables/:collection/column-usage",
+            "column_preview": "GET /v1/tables/:collection/preview?sample_size=100&max_length=80",
+            "column_descriptions": "PUT /v1/tables/:collection/descriptions",
+            "json_schema": "GET /v1/schema/:collection/jsonschema",
+            "schema_cache": "GET|DELETE /v1/admin/schema-cache",
+            "erasure": "POST /v1/admin/erasure",
+            "concurrency": "GET|PUT /v1/admin/concurrency",
+            "pragmas": "GET|POST /v1/admin/pragmas",
+            "checkpoint": "POST /v1/admin/checkpoint",
+            "index_suggestions": "GET /v1/admin/index-suggestions",
+            "render": "POST /v1/render",
+            "templates": "GET|PUT|DELETE /v1/templates/:name",
+            "sources": "GET|POST /v1/sources",
+            "source_runs": "GET /v1/sources/:id/runs",
+            "dead_letters": "GET /v1/dead-letters",
+            "retry_dead_letter": "POST /v1/dead-letters/:id/retry",
+            "snapshots": "POST /v1/snapshots, DELETE /v1/snapshots/:id",
             "stream": "GET /v1/stream/:collection",
+            "sync": "GET /v1/sync/:collection",
             "health": "GET /health",
+            "liveness": "GET /health/live",
+            "readiness": "GET /health/ready",
+            "capabilities": "GET /v1/capabilities",
+            "metrics": "GET /metrics",
             "explorer": "GET /explore"
         }
+    });
+    if let Some(endpoints) = info["endpoints"].as_object_mut() {
+        for endpoint in endpoints.values_mut() {
+            if let Some(routes) = endpoint.as_str() {
+                *endpoint = Value::from(routes.replace(" /", &format!(" {}/", prefix)));
+            }
+        }
+    }
+    info["base_path"] = Value::from(prefix);
+    Json(info)
+}
+
+/// GET /health/live - Liveness probe
+///
+/// Only says the process is serving requests; it never touches the database
+/// so a database hiccup does not get the process restarted.
+async fn liveness_handler() -> impl IntoResponse {
+    Json(json!({
+        "status": "alive"
     }))
 }
 
-/// Health check endpoint
-async fn health_handler(State(state): State<AppState>) -> impl IntoResponse {
-    match state.store.query_simple("SELECT 1".to_string()).await {
-        Ok(_) => Json(json!({
-            "status": "healthy",
-            "database": "connected"
-        })),
-        Err(e) => Json(json!({
+/// GET /v1/capabilities - Optional SQLite features of this build
+async fn capabilities_handler(State(state): State<AppState>) -> impl IntoResponse {
+    Json(ApiResponse::success(state.store.capabilities().clone()))
+}
+
+/// GET /metrics - Per-collection write statistics and requests in flight
+/// in Prometheus text format
+async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.perf.render_prometheus(&state.store) + &state.limits.render_prometheus(),
+    )
+}
+
+/// GET /health/ready (and /health) - Readiness probe
+///
+/// The database is probed on a connection of its own (see
+/// `VibeStore::probe`), so a long write can't hold the check up. Returns
+/// 503 when the probe fails or doesn't answer in time, or when the store's
+/// last attempt to reopen its connection failed. When only the main
+/// connection is slow to answer, as behind a long write, the status is
+/// `degraded` with 200, so orchestrators don't restart a busy server.
+async fn readiness_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let (probe, main) = tokio::join!(
+        tokio::time::timeout(READINESS_TIMEOUT, state.store.probe()),
+        tokio::time::timeout(
+            MAIN_CONNECTION_TIMEOUT,
+            state.store.query_simple("SELECT 1".to_string())
+        ),
+    );
+    let watchdog = state.store.health();
+
+    let error = match (probe, main) {
+        (Ok(Ok(())), Ok(Err(e))) => e.to_string(),
+        (Ok(Ok(())), main) if watchdog.healthy => {
+            let (status, database) = match main {
+                Ok(_) => ("healthy", "connected"),
+                Err(_) => ("degraded", "busy"),
+            };
+            return (
+                StatusCode::OK,
+                Json(json!({
+                    "status": status,
+                    "database": database,
+                    "watchdog": watchdog,
+                    "concurrency": state.limits.stats(),
+                    "capabilities": state.store.capabilities(),
+                    "explorer": state.config.explorer
+                })),
+            );
+        }
+        (Ok(Ok(())), _) => format!(
+            "Reopening the database failed after: {}",
+            watchdog.last_fault.as_deref().unwrap_or("an unrecoverable error")
+        ),
+        (Ok(Err(e)), _) => e.to_string(),
+        (Err(_), _) => format!(
+            "Database did not respond within {}ms",
+            READINESS_TIMEOUT.as_millis()
+        ),
+    };
+
+    warn!("Readiness check failed: {}", error);
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(json!({
             "status": "unhealthy",
             "database": "disconnected",
-            "error": e.to_string()
+            "watchdog": watchdog,
+            "concurrency": state.limits.stats(),
+            "error": error
         })),
-    }
+    )
 }
 
 /// POST /v1/push/:collection - Insert a single document
+///
+/// Rejected payloads are kept as dead letters when the collection has
+/// `dead_letter` enabled. Keys naming system columns (`id`, `created_at`,
+/// `updated_at`, in any case) are not stored and are listed in
+/// `skipped_fields`, unless `allow_timestamps` takes the timestamps. A
+/// `_types` object picks the types of columns the push creates (see
+/// `guard::take_type_hints`) and is not stored. With `X-Vibe-Timings: 1`
+/// the response has `timings`, showing whether a migration slowed the
+/// push down.
 async fn push_handler(
     State(state): State<AppState>,
     Path(collection): Path<String>,
+    caller: Option<AuthUser>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Query(params): Query<PushParams>,
     Json(payload): Json<Value>,
 ) -> Result<impl IntoResponse, VibeError> {
-    info!("📥 Pushing to collection: {}", collection);
+    info!("📥 Pushing to collection: {} (by {})", collection, AuthUser::label(caller.as_ref()));
+    require_timestamp_backfill(&state, caller.as_ref(), &params)?;
+    reject_batch_inference(&params)?;
+
+    let dead_letter = state
+        .settings
+        .get(&collection)
+        .await
+        .is_ok_and(|settings| settings.dead_letter);
+    let original = dead_letter.then(|| payload.clone());
+
+    let timed = headers.get(TIMINGS_HEADER).is_some_and(|v| v == "1");
+    match push_document(&state, &collection, caller.as_ref(), payload, &params).await {
+        Ok(mut pushed) => {
+            if !timed {
+                pushed.timings = None;
+            }
+            let response = ApiResponse::success_with_message(pushed, "Data pushed successfully");
+            Ok((StatusCode::CREATED, Json(response)))
+        }
+        Err(err) => {
+            if let Some(payload) = original.filter(|_| dead_letters::is_rejection(&err)) {
+                let trust_proxy = state.config.base_path.trust_forwarded;
+                let origin = Origin::new(client_ip(&headers, connect_info, trust_proxy), caller.as_ref());
+                state.dead_letters.capture(&collection, payload, &err, origin);
+            }
+            Err(err)
+        }
+    }
+}
+
+/// Rejects `?allow_timestamps=true` from non-admin callers when auth is
+/// enabled, since backdated rows would pass for history
+fn require_timestamp_backfill(
+    state: &AppState,
+    caller: Option<&AuthUser>,
+    params: &PushParams,
+) -> Result<(), VibeError> {
+    if params.allow_timestamps {
+        require_admin(state, caller, "store their own timestamps (allow_timestamps)")?;
+    }
+    Ok(())
+}
+
+/// Rejects `?inference=`/`?confidence=` on single-document pushes; one
+/// document has nothing for inference to weigh
+fn reject_batch_inference(params: &PushParams) -> Result<(), VibeError> {
+    if params.inference != InferenceMode::Strict || params.confidence.is_some() {
+        return Err(VibeError::InvalidPayload(
+            "inference and confidence only apply to batch pushes".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Stores the inline files of a pushed payload when the collection has
+/// `inline_files` settings (see `inline_files::extract`)
+async fn extract_inline_files(
+    state: &AppState,
+    collection: &str,
+    settings: &CollectionSettings,
+    payload: &mut Value,
+    caller: Option<&AuthUser>,
+) -> Result<StoredFiles, VibeError> {
+    let Some(config) = &settings.inline_files else {
+        return Ok(StoredFiles::none());
+    };
+    let storage = state.storage.as_ref().ok_or_else(|| {
+        VibeError::Storage("Inline files need file storage, which is not enabled".to_string())
+    })?;
+    inline_files::extract(storage, collection, config, payload, caller.map(|user| user.id)).await
+}
+
+/// Sanitizes the keys of an object payload with `?sanitize_keys=`, returning
+/// the renames made
+fn sanitize_payload_keys(
+    payload: &mut Value,
+    params: &PushParams,
+) -> Result<BTreeMap<String, String>, VibeError> {
+    match (params.sanitize_keys, payload.as_object()) {
+        (Some(on_collision), Some(object)) => {
+            let (sanitized, renames) = SchemaGuard::sanitize_keys(object, on_collision)?;
+            *payload = Value::Object(sanitized);
+            Ok(renames)
+        }
+        _ => Ok(BTreeMap::new()),
+    }
+}
+
+/// Takes the `_types` hints off a payload (see `guard::take_type_hints`),
+/// with aliased column names replaced by the stored ones
+async fn type_hints(state: &AppState, collection: &str, payload: &mut Value) -> Result<TypeHints, VibeError> {
+    let mut hints = TypeHints::new();
+    for (column, sql_type) in guard::take_type_hints(payload)? {
+        let column = state.guard.resolve_column(collection, &column).await?;
+        hints.insert(column.to_lowercase(), sql_type);
+    }
+    Ok(hints)
+}
+
+/// Client address, preferring the proxy headers over the socket peer
+///
+/// The proxy headers are client-controlled unless the server runs behind
+/// a trusted proxy (`--trust-proxy`), so only then are they believed.
+fn client_ip(
+    headers: &HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    trust_proxy: bool,
+) -> Option<String> {
+    let forwarded = || {
+        headers
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(',').next())
+            .or_else(|| headers.get("x-real-ip").and_then(|v| v.to_str().ok()))
+            .map(|ip| ip.trim().to_string())
+            .filter(|ip| !ip.is_empty())
+    };
+    trust_proxy
+        .then(forwarded)
+        .flatten()
+        .or_else(|| connect_info.map(|ConnectInfo(addr)| addr.ip().to_string()))
+}
+
+/// Inserts a single document through schema evolution, explosion and auditing
+///
+/// With `allow_timestamps`, `created_at`/`updated_at` in the payload are
+/// stored instead of the defaults (see `SchemaGuard::take_timestamps`).
+/// With `sanitize_keys`, keys are sanitized first (see
+/// `SchemaGuard::sanitize_keys`) and the renames reported as aliases.
+async fn push_document(
+    state: &AppState,
+    collection: &str,
+    caller: Option<&AuthUser>,
+    mut payload: Value,
+    params: &PushParams,
+) -> Result<PushResponse, VibeError> {
+    let started = Instant::now();
+    let collection = state.guard.resolve_table(collection, true).await?;
+    let hints = type_hints(state, &collection, &mut payload).await?;
+    let mut aliases = sanitize_payload_keys(&mut payload, params)?;
+    aliases.extend(state.guard.apply_column_aliases(&collection, &mut payload).await?);
+    let mask = state.column_mask(&collection, caller).await?;
+    mask.check(payload_keys(&payload))?;
 
     // Ensure table exists
     state.guard.ensure_table(&collection).await?;
 
+    let timestamps = if params.allow_timestamps {
+        state.guard.take_timestamps(&collection, &mut payload).await?
+    } else {
+        Vec::new()
+    };
+    let skipped_fields = guard::system_fields(&payload);
+
+    let settings = state.settings.get(&collection).await?;
+    let files = extract_inline_files(state, &collection, &settings, &mut payload, caller).await?;
+
+    // Configured arrays go to child collections, whose schemas evolve first
+    let mut children = explode::split(&collection, &settings, &mut payload);
+    for child in &mut children {
+        let probe = child.schema_probe();
+        state
+            .column_mask(&child.collection, caller)
+            .await?
+            .check(payload_keys(&probe))?;
+        state.guard.ensure_table(&child.collection).await?;
+        let columns = state.guard.ensure_columns(&child.collection, &probe).await?;
+        child.retain_columns(&columns);
+        if state.settings.get(&child.collection).await?.audit {
+            audit::ensure_table(&state.store).await?;
+            child.audit = Some(InsertAudit::new(&child.collection, caller));
+        }
+    }
+
     // Ensure columns exist and get insertable column names
-    let columns = state.guard.ensure_columns(&collection, &payload).await?;
+    let mut columns = state.guard.ensure_columns_hinted(&collection, &payload, &hints).await?;
+    let warnings = number_warnings(state, &collection, &payload).await?;
+    if let Some(obj) = payload.as_object_mut() {
+        for (column, value) in timestamps {
+            obj.insert(column.clone(), json!(value));
+            columns.push(column);
+        }
+    }
 
-    if columns.is_empty() {
+    let guard_time = started.elapsed();
+    let migrations = guard::current_migrations();
+
+    // Audit entries commit with the rows they describe
+    let audit = if settings.audit {
+        audit::ensure_table(&state.store).await?;
+        Some(InsertAudit::new(&collection, caller))
+    } else {
+        None
+    };
+
+    let label = state.perf.label(&collection);
+    let mut exploded = Vec::new();
+    let inserting = Instant::now();
+    let id = if !children.is_empty() {
+        let obj = payload.as_object().ok_or_else(|| {
+            VibeError::InvalidPayload("Payload must be a JSON object".to_string())
+        })?;
+        let sql = if columns.is_empty() {
+            format!("INSERT INTO {} DEFAULT VALUES", collection)
+        } else {
+            format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                collection,
+                columns.join(", "),
+                vec!["?"; columns.len()].join(", ")
+            )
+        };
+        let params = columns
+            .iter()
+            .map(|col| obj.get(col).map(json_to_sql_value).unwrap_or(SqlValue::Null))
+            .collect();
+
+        debug!("Executing: {} with {} exploded arrays", sql, children.len());
+        let rows = 1 + children.iter().map(|c| c.rows.len() as u64).sum::<u64>();
+        let started = Instant::now();
+        let result = explode::insert_with_children(&state.store, &label, sql, params, audit, &children).await;
+        state.perf.record_write(&collection, started.elapsed(), rows, result.is_ok());
+        let (id, inserted) = result?;
+        exploded = inserted;
+        id
+    } else if columns.is_empty() {
         // Insert with only default values
         let sql = format!("INSERT INTO {} DEFAULT VALUES", collection);
-        state.store.execute_simple(sql).await?;
+        let started = Instant::now();
+        let result = state.insert_row(&collection, &label, &settings, sql, Vec::new(), audit).await;
+        state.perf.record_write(&collection, started.elapsed(), 1, result.is_ok());
+        result?
     } else {
         // Build INSERT statement
         let placeholders: Vec<&str> = columns.iter().map(|_| "?").collect();
@@ -251,207 +1190,856 @@ async fn push_handler(
             .collect();
 
         debug!("Executing: {} with {} params", sql, params.len());
-        state.store.execute(sql, params).await?;
+        let started = Instant::now();
+        let result = state.insert_row(&collection, &label, &settings, sql, params, audit).await;
+        state.perf.record_write(&collection, started.elapsed(), 1, result.is_ok());
+        result?
+    };
+    let insert_time = inserting.elapsed();
+    files.keep();
+    state.row_counts.adjust(&collection, 1);
+    for (child_collection, ids) in &exploded {
+        state.row_counts.adjust(child_collection, ids.len() as i64);
     }
 
-    // Get the inserted ID
-    let id = state.store.last_insert_rowid().await?;
+    let stored = stored_row(state, &collection, id, &settings).await?;
 
     // Broadcast the new data
-    let tx = state.get_broadcaster(&collection);
-    let _ = tx.send(json!({
+    state.broadcast_insert(&collection, &settings, id, json!({
         "event": "insert",
         "id": id,
-        "data": payload
+        "data": event_data(&settings, stored, payload),
+        "actor": AuthUser::actor(caller)
     }));
 
-    let response = ApiResponse::success_with_message(
-        PushResponse {
-            id,
-            collection: collection.clone(),
-            columns_added: columns,
-        },
-        "Data pushed successfully",
-    );
+    for (child, (child_collection, ids)) in children.iter().zip(&exploded) {
+        let child_settings = state.settings.get(child_collection).await?;
+        for (row, child_id) in child.rows.iter().zip(ids) {
+            let stored = stored_row(state, child_collection, *child_id, &child_settings).await?;
+            let mut data = row.clone();
+            data.insert(child.foreign_key.clone(), json!(id));
+            state.broadcast_insert(child_collection, &child_settings, *child_id, json!({
+                "event": "insert",
+                "id": child_id,
+                "data": event_data(&child_settings, stored, Value::Object(data)),
+                "actor": AuthUser::actor(caller)
+            }));
+        }
+    }
 
-    Ok((StatusCode::CREATED, Json(response)))
+    Ok(PushResponse {
+        id,
+        collection,
+        columns_added: columns,
+        children: exploded.into_iter().fold(BTreeMap::new(), |mut acc, (child, ids)| {
+            acc.entry(child).or_insert_with(Vec::new).extend(ids);
+            acc
+        }),
+        aliases,
+        skipped_fields,
+        warnings,
+        timings: Some(PushTimings {
+            guard_ms: guard_time.as_secs_f64() * 1000.0,
+            ddl_ms: migrations.ddl_time.as_secs_f64() * 1000.0,
+            ddl_statements: migrations.ddl_statements,
+            insert_ms: insert_time.as_secs_f64() * 1000.0,
+        }),
+    })
+}
+
+/// The row as stored, when `broadcast_rows` needs it
+async fn stored_row(
+    state: &AppState,
+    collection: &str,
+    id: i64,
+    settings: &CollectionSettings,
+) -> Result<Option<Value>, VibeError> {
+    if settings.broadcast_rows {
+        audit::snapshot(&state.store, collection, id).await
+    } else {
+        Ok(None)
+    }
+}
+
+/// Warnings for the top-level numbers of a written document that weren't
+/// stored exactly (see `inference::InexactNumber`)
+///
+/// Integers beyond `i64` keep every digit in TEXT columns, which new
+/// columns for them are; INTEGER and REAL columns store the nearest REAL.
+/// Read once the document's columns exist; with `strict_numbers` such
+/// documents are rejected before that.
+async fn number_warnings(state: &AppState, collection: &str, payload: &Value) -> Result<Vec<String>, VibeError> {
+    let inexact: Vec<(&String, &serde_json::Number, InexactNumber)> = payload
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(key, value)| {
+            let n = value.as_number()?;
+            InexactNumber::classify(n).map(|kind| (key, n, kind))
+        })
+        .collect();
+    if inexact.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let types = state.guard.column_types(collection).await?;
+    Ok(inexact
+        .into_iter()
+        .filter_map(|(key, n, kind)| {
+            // Keys without a column (frozen schemas) weren't stored at all
+            let declared = types.get(&key.to_lowercase())?;
+            let approximate = declared == "INTEGER" || declared == "REAL";
+            Some(match kind {
+                InexactNumber::BigInteger if approximate => format!(
+                    "{}: {} exceeds the INTEGER range; the {} column stored the nearest REAL",
+                    key, n, declared
+                ),
+                InexactNumber::BigInteger => {
+                    format!("{}: {} exceeds the INTEGER range and was stored as TEXT", key, n)
+                }
+                InexactNumber::ImpreciseInteger => format!(
+                    "{}: {} arrived as a float and may have lost digits; send large integers as strings",
+                    key, n
+                ),
+            })
+        })
+        .collect())
+}
+
+/// `data` of a stream event: the stored row with `broadcast_rows`, the
+/// request payload otherwise
+fn event_data(settings: &CollectionSettings, stored: Option<Value>, payload: Value) -> Value {
+    match stored {
+        Some(row) if settings.broadcast_rows => row,
+        _ => payload,
+    }
 }
 
 /// POST /v1/push/:collection/batch - Insert multiple documents
+///
+/// Each item may carry `_types`; the hints apply to the whole batch, and
+/// two items hinting different types for a column are rejected. Inline
+/// files are stored per item, as for single pushes.
 async fn batch_push_handler(
     State(state): State<AppState>,
     Path(collection): Path<String>,
-    Json(payloads): Json<Vec<Value>>,
+    caller: Option<AuthUser>,
+    Query(params): Query<PushParams>,
+    Json(mut payloads): Json<Vec<Value>>,
 ) -> Result<impl IntoResponse, VibeError> {
     info!(
-        "📥 Batch pushing {} items to collection: {}",
+        "📥 Batch pushing {} items to collection: {} (by {})",
         payloads.len(),
-        collection
+        collection,
+        AuthUser::label(caller.as_ref())
     );
+    require_timestamp_backfill(&state, caller.as_ref(), &params)?;
 
     if payloads.is_empty() {
         return Err(VibeError::InvalidPayload("Empty batch".to_string()));
     }
 
+    let collection = state.guard.resolve_table(&collection, true).await?;
+    // One set of hints for the batch; items may not disagree
+    let mut hints = TypeHints::new();
+    for (index, payload) in payloads.iter_mut().enumerate() {
+        for (column, sql_type) in type_hints(&state, &collection, payload).await.map_err(|e| e.at_index(index))? {
+            match hints.get(&column) {
+                Some(hinted) if *hinted != sql_type => {
+                    return Err(VibeError::InvalidFields {
+                        message: format!(
+                            "{} hints {} as both {} and {}",
+                            guard::TYPE_HINTS_KEY,
+                            column,
+                            hinted.as_sql(),
+                            sql_type.as_sql()
+                        ),
+                        fields: vec![column],
+                    }
+                    .at_index(index))
+                }
+                _ => {
+                    hints.insert(column, sql_type);
+                }
+            }
+        }
+    }
+    let mut aliases = BTreeMap::new();
+    for (index, payload) in payloads.iter_mut().enumerate() {
+        aliases.extend(sanitize_payload_keys(payload, &params).map_err(|e| e.at_index(index))?);
+        let applied = state
+            .guard
+            .apply_column_aliases(&collection, payload)
+            .await
+            .map_err(|e| e.at_index(index))?;
+        aliases.extend(applied);
+    }
+
+    let mask = state.column_mask(&collection, caller.as_ref()).await?;
+    for (index, payload) in payloads.iter().enumerate() {
+        mask.check(payload_keys(payload)).map_err(|e| e.at_index(index))?;
+    }
+
     // Ensure table exists
     state.guard.ensure_table(&collection).await?;
 
+    // Inline files are stored per item and discarded unless its row is
+    let settings = state.settings.get(&collection).await?;
+    let mut files = Vec::with_capacity(payloads.len());
+    for (index, payload) in payloads.iter_mut().enumerate() {
+        files.push(
+            extract_inline_files(&state, &collection, &settings, payload, caller.as_ref())
+                .await
+                .map_err(|e| e.at_index(index))?,
+        );
+    }
+
+    // A dominant numeric type wins over a few stray values, which are
+    // stored as NULL; columns already declared TEXT keep them
+    let inference = match params.inference {
+        InferenceMode::Strict => None,
+        InferenceMode::Confident => {
+            let confidence = params.confidence.unwrap_or(DEFAULT_CONFIDENCE);
+            let (_, mut report) = infer_batch_schema_confident(&payloads, confidence)?;
+            let types = state.guard.column_types(&collection).await?;
+            report.columns.retain(|name, _| {
+                types
+                    .get(&name.to_lowercase())
+                    .is_none_or(|declared| declared == "INTEGER" || declared == "REAL")
+            });
+            report.null_outliers(&mut payloads);
+            state.guard.ensure_columns_hinted(&collection, &report.schema_probe(), &hints).await?;
+            Some(report)
+        }
+    };
+
     // Process all payloads to ensure all columns exist
     let mut all_columns: std::collections::HashSet<String> = std::collections::HashSet::new();
-    for payload in &payloads {
-        let columns = state.guard.ensure_columns(&collection, payload).await?;
+    let mut timestamp_columns = std::collections::HashSet::new();
+    let mut skipped_fields = BTreeSet::new();
+    for (index, payload) in payloads.iter_mut().enumerate() {
+        let timestamps = if params.allow_timestamps {
+            state
+                .guard
+                .take_timestamps(&collection, payload)
+                .await
+                .map_err(|e| e.at_index(index))?
+        } else {
+            Vec::new()
+        };
+        skipped_fields.extend(guard::system_fields(payload));
+        let columns = state
+            .guard
+            .ensure_columns_hinted(&collection, payload, &hints)
+            .await
+            .map_err(|e| e.at_index(index))?;
         all_columns.extend(columns);
+        if let Some(obj) = payload.as_object_mut() {
+            for (column, value) in timestamps {
+                obj.insert(column.clone(), json!(value));
+                timestamp_columns.insert(column);
+            }
+        }
+    }
+    // Items without an explicit timestamp still get the time of the push
+    if !timestamp_columns.is_empty() {
+        let now = json!(SchemaGuard::now_timestamp());
+        for payload in &mut payloads {
+            if let Some(obj) = payload.as_object_mut() {
+                for column in &timestamp_columns {
+                    obj.entry(column.clone()).or_insert_with(|| now.clone());
+                }
+            }
+        }
+        all_columns.extend(timestamp_columns);
+    }
+
+    let mut warnings = Vec::new();
+    for (index, payload) in payloads.iter().enumerate() {
+        for warning in number_warnings(&state, &collection, payload).await? {
+            warnings.push(format!("item {}: {}", index, warning));
+        }
     }
 
     let columns: Vec<String> = all_columns.into_iter().collect();
     let mut inserted = 0u64;
-
-    if columns.is_empty() {
-        // Insert with only default values
-        for _ in &payloads {
-            let sql = format!("INSERT INTO {} DEFAULT VALUES", collection);
-            state.store.execute_simple(sql).await?;
-            inserted += 1;
-        }
+    // Each row commits with its audit entry
+    let audit = if settings.audit {
+        audit::ensure_table(&state.store).await?;
+        Some(InsertAudit::new(&collection, caller.as_ref()))
     } else {
-        let placeholders: Vec<&str> = columns.iter().map(|_| "?").collect();
-        let sql = format!(
-            "INSERT INTO {} ({}) VALUES ({})",
-            collection,
-            columns.join(", "),
-            placeholders.join(", ")
-        );
+        None
+    };
+    let label = state.perf.label(&collection);
+    let started = Instant::now();
 
-        for payload in &payloads {
-            let obj = payload.as_object().ok_or_else(|| {
-                VibeError::InvalidPayload("Each item must be a JSON object".to_string())
-            })?;
+    let result: Result<(), VibeError> = async {
+        if columns.is_empty() {
+            // Insert with only default values
+            for _ in &payloads {
+                let sql = format!("INSERT INTO {} DEFAULT VALUES", collection);
+                audit::insert_row(&state.store, &label, sql, Vec::new(), audit.clone()).await?;
+                inserted += 1;
+            }
+        } else {
+            let placeholders: Vec<&str> = columns.iter().map(|_| "?").collect();
+            let sql = format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                collection,
+                columns.join(", "),
+                placeholders.join(", ")
+            );
 
-            let params: Vec<SqlValue> = columns
-                .iter()
-                .map(|col| {
-                    obj.get(col)
-                        .map(json_to_sql_value)
-                        .unwrap_or(SqlValue::Null)
-                })
-                .collect();
+            for (index, payload) in payloads.iter().enumerate() {
+                let obj = payload.as_object().ok_or_else(|| {
+                    VibeError::InvalidPayload("Each item must be a JSON object".to_string()).at_index(index)
+                })?;
 
-            state.store.execute(sql.clone(), params).await?;
-            inserted += 1;
+                let params: Vec<SqlValue> = columns
+                    .iter()
+                    .map(|col| {
+                        obj.get(col)
+                            .map(json_to_sql_value)
+                            .unwrap_or(SqlValue::Null)
+                    })
+                    .collect();
+
+                audit::insert_row(&state.store, &label, sql.clone(), params, audit.clone())
+                    .await
+                    .map_err(|e| e.at_index(index))?;
+                inserted += 1;
+            }
         }
+        Ok(())
     }
+    .await;
+    state.perf.record_write(&collection, started.elapsed(), inserted, result.is_ok());
+    // Rows inserted before a failing item stay, and so do their files
+    state.row_counts.adjust(&collection, inserted as i64);
+    for files in files.drain(..inserted as usize) {
+        files.keep();
+    }
+    result?;
 
     // Broadcast batch insert
-    let tx = state.get_broadcaster(&collection);
-    let _ = tx.send(json!({
+    state.broadcast(&collection, json!({
         "event": "batch_insert",
-        "count": inserted
+        "count": inserted,
+        "actor": AuthUser::actor(caller.as_ref())
     }));
 
     let response = ApiResponse::success(BatchPushResponse {
         inserted,
         collection,
         columns_added: columns,
+        aliases,
+        inference,
+        skipped_fields: skipped_fields.into_iter().collect(),
+        warnings,
     });
 
     Ok((StatusCode::CREATED, Json(response)))
 }
 
 /// GET /v1/query/:collection - Query documents with filters
+///
+/// Responds with the standard JSON envelope by default. When the client sends
+/// `Accept: application/x-ndjson` or `?stream=true`, rows are streamed as
+/// newline-delimited JSON straight from SQLite instead, and `limit` is only
+/// applied when given explicitly. With `X-Vibe-Snapshot`, rows are read
+/// from that snapshot (see `snapshots`), which can't be streamed.
 async fn query_handler(
     State(state): State<AppState>,
     Path(collection): Path<String>,
+    caller: Option<AuthUser>,
+    headers: HeaderMap,
     Query(params): Query<QueryParams>,
-) -> Result<impl IntoResponse, VibeError> {
+) -> Result<Response, VibeError> {
     debug!("🔍 Querying collection: {}", collection);
+    let collection = state.guard.resolve_table(&collection, false).await?;
+    state.perf.record_read(&collection);
 
-    // Check if table exists
-    let _stats = state.guard.get_table_stats(&collection).await?;
-
-    // Build query
-    let mut sql = format!("SELECT * FROM {}", collection);
-    let mut query_params: Vec<SqlValue> = Vec::new();
-
-    // Add WHERE clauses from filters (excluding reserved params)
-    let reserved = ["limit", "offset", "order_by", "order_dir"];
-    let filters: Vec<_> = params
-        .filters
-        .iter()
-        .filter(|(k, _)| !reserved.contains(&k.as_str()))
-        .collect();
+    let mask = state.column_mask(&collection, caller.as_ref()).await?;
 
-    if !filters.is_empty() {
-        let conditions: Vec<String> = filters.iter().map(|(k, _)| format!("{} = ?", k)).collect();
-        sql.push_str(" WHERE ");
-        sql.push_str(&conditions.join(" AND "));
+    let streaming = params.stream.unwrap_or(false)
+        || headers
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.contains(NDJSON_CONTENT_TYPE))
+            .unwrap_or(false);
 
-        for (_, v) in filters {
-            query_params.push(SqlValue::Text(v.clone()));
-        }
+    // Streams are uncapped unless a limit is given
+    let limit = if streaming {
+        params.limit.map(i64::from).unwrap_or(-1)
+    } else {
+        i64::from(params.limit.unwrap_or(100).min(1000))
+    };
+    let (sql, query_params, column_uses) =
+        select_statement(&state, &collection, &mask, &params, limit).await?;
+    let snapshot = request_snapshot(&state, &headers, caller.as_ref())?;
+    if snapshot.is_some() && (streaming || params.expand.is_some()) {
+        return Err(VibeError::InvalidPayload(
+            "Snapshot reads can't be streamed or expanded".to_string(),
+        ));
     }
 
+    if streaming {
+        if params.expand.is_some() {
+            return Err(VibeError::InvalidPayload(
+                "expand is not supported for streamed queries".to_string(),
+            ));
+        }
+        let original_keys = params.original_keys.unwrap_or(false);
+        return Ok(stream_ndjson(
+            &state,
+            collection,
+            sql,
+            query_params,
+            mask,
+            original_keys,
+            column_uses,
+        ));
+    }
+
+    // Execute query
+    let rows = match snapshot {
+        Some(session) => session.query(sql, query_params).await?,
+        None => state.store.query(sql, query_params).await?,
+    };
+    record_column_uses(&state.perf, &collection, &column_uses);
+
+    let mut results: Vec<Value> = rows
+        .into_iter()
+        .map(|row| {
+            let mut value = row_to_json(row);
+            mask.apply_value(&mut value);
+            value
+        })
+        .collect();
+    expand_rows(&state, &collection, caller.as_ref(), &mut results, params.expand.as_deref())
+        .await?;
+    if params.original_keys.unwrap_or(false) {
+        for row in &mut results {
+            state.guard.restore_original_keys(&collection, row).await?;
+        }
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": results,
+        "count": results.len(),
+        "collection": collection
+    }))
+    .into_response())
+}
+
+/// SELECT of the columns of `collection` the mask leaves visible, or of
+/// `params.select`, with the filters, order and offset of `params`
+async fn select_statement(
+    state: &AppState,
+    collection: &str,
+    mask: &ColumnMask,
+    params: &QueryParams,
+    limit: i64,
+) -> Result<(String, Vec<SqlValue>, Vec<(String, ColumnUse)>), VibeError> {
+    // Check if table exists
+    let stats = state.guard.get_table_stats(collection).await?;
+
+    let filters = aliased_filters(state, collection, &params.filters).await?;
+    let casts = cast_hints(filters.iter())?;
+
+    // Build query, leaving out columns the caller may not see
+    let columns: Vec<String> = stats.columns.into_iter().map(|c| c.name).collect();
+    let (projection, mut query_params) = match &params.select {
+        Some(select) => shaped_projection(state, collection, select, &columns, &casts, mask).await?,
+        None => (mask.projection(&columns), Vec::new()),
+    };
+    let mut sql = format!("SELECT {} FROM {}", projection, collection);
+
+    // Add WHERE clauses from filters (excluding reserved params)
+    let reserved = ["limit", "offset", "order_by", "order_dir", "stream", "expand", "original_keys", "select"];
+    let (conditions, filter_params) =
+        filter_clause(&filters, &reserved, &casts, state.store.capabilities(), mask)?;
+    if !conditions.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&conditions);
+        query_params.extend(filter_params);
+    }
+
+    // Columns the query used, counted by the caller once it has run
+    let mut column_uses = Vec::new();
+
     // Add ORDER BY
     if let Some(order_by) = &params.order_by {
-        SchemaGuard::validate_identifier(order_by)?;
+        let order_by = state.guard.resolve_column(collection, order_by).await?;
+        SchemaGuard::validate_identifier(&order_by)?;
+        mask.check([order_by.as_str()])?;
         let dir = params.order_dir.as_deref().unwrap_or("ASC").to_uppercase();
         if dir != "ASC" && dir != "DESC" {
             return Err(VibeError::InvalidPayload(
                 "order_dir must be ASC or DESC".to_string(),
             ));
         }
-        sql.push_str(&format!(" ORDER BY {} {}", order_by, dir));
+        column_uses.push((order_by.clone(), ColumnUse::OrderBy));
+        let order_expr = match casts.get(&order_by) {
+            Some(target) => target.cast(&order_by),
+            None => order_by,
+        };
+        sql.push_str(&format!(" ORDER BY {} {}", order_expr, dir));
+    }
+    for (key, _) in filters.iter() {
+        if reserved.contains(&key.as_str()) || key.starts_with(CAST_PREFIX) || key.ends_with("[contains]") {
+            continue;
+        }
+        let column = key.strip_suffix("[null]").unwrap_or(key);
+        if columns.iter().any(|c| c == column) {
+            column_uses.push((column.to_string(), ColumnUse::Filter));
+        }
     }
 
     // Add LIMIT and OFFSET
-    let limit = params.limit.unwrap_or(100).min(1000);
     sql.push_str(&format!(" LIMIT {}", limit));
     if let Some(offset) = params.offset {
         sql.push_str(&format!(" OFFSET {}", offset));
     }
+    Ok((sql, query_params, column_uses))
+}
 
-    // Execute query
-    let rows = state.store.query(sql, query_params).await?;
+/// Count the column uses of a query on `collection` that succeeded
+fn record_column_uses(perf: &PerfRegistry, collection: &str, column_uses: &[(String, ColumnUse)]) {
+    for (column, usage) in column_uses {
+        perf.record_query_column(collection, column, *usage);
+    }
+}
+
+/// SELECT list and parameters of a `?select=` on `collection`
+///
+/// Column references are resolved like filters, must exist and be visible
+/// under `mask`, and are read through their cast hint if they have one.
+async fn shaped_projection(
+    state: &AppState,
+    collection: &str,
+    select: &str,
+    columns: &[String],
+    casts: &HashMap<String, SqliteType>,
+    mask: &ColumnMask,
+) -> Result<(String, Vec<SqlValue>), VibeError> {
+    let mut selection = Selection::parse(select)
+        .map_err(|message| VibeError::InvalidPayload(format!("Invalid select: {}", message)))?;
+    for alias in selection.items.iter().filter_map(|item| item.alias.as_deref()) {
+        SchemaGuard::validate_identifier(alias)?;
+    }
+    let referenced: Vec<String> = selection.columns().into_iter().map(str::to_string).collect();
+    let mut resolved = HashMap::new();
+    for column in referenced {
+        let stored = state.guard.resolve_column(collection, &column).await?;
+        if !columns.contains(&stored) {
+            return Err(VibeError::InvalidFields {
+                message: format!("select refers to unknown column '{}'", column),
+                fields: vec![column],
+            });
+        }
+        mask.check([stored.as_str()])?;
+        resolved.insert(column, stored);
+    }
+    selection.rename_columns(|column| resolved[column].clone());
+    Ok(selection.to_sql(|column| match casts.get(column) {
+        Some(target) => target.cast(column),
+        None => column.to_string(),
+    }))
+}
+
+/// Query-string filters with aliased column names replaced by the stored
+/// ones (see `SchemaGuard::resolve_column`)
+async fn aliased_filters(
+    state: &AppState,
+    collection: &str,
+    filters: &Filters,
+) -> Result<Filters, VibeError> {
+    let mut aliased = Filters::default();
+    for (key, value) in filters.iter() {
+        let (prefix, key) = match key.strip_prefix(CAST_PREFIX) {
+            Some(column) => (CAST_PREFIX, column),
+            None => ("", key.as_str()),
+        };
+        let (column, suffix) = FILTER_SUFFIXES
+            .iter()
+            .find_map(|suffix| Some((key.strip_suffix(suffix)?, *suffix)))
+            .unwrap_or((key, ""));
+        let column = state.guard.resolve_column(collection, column).await?;
+        aliased.push(format!("{}{}{}", prefix, column, suffix), value.clone());
+    }
+    Ok(aliased)
+}
+
+/// Key suffixes of filters other than value comparisons
+const FILTER_SUFFIXES: [&str; 2] = ["[contains]", "[null]"];
+
+/// Compiles query-string filters into a `WHERE` body (empty without
+/// filters) and its parameters, skipping the `reserved` keys and cast hints
+///
+/// Shared by reads and filtered deletes, so a delete's count precheck and
+/// the delete itself always select the same rows.
+fn filter_clause(
+    filters: &Filters,
+    reserved: &[&str],
+    casts: &HashMap<String, SqliteType>,
+    capabilities: &Capabilities,
+    mask: &ColumnMask,
+) -> Result<(String, Vec<SqlValue>), VibeError> {
+    let mut filters: Vec<_> = filters
+        .iter()
+        .filter(|(k, _)| !reserved.contains(&k.as_str()) && !k.starts_with(CAST_PREFIX))
+        .collect();
+    // Stable SQL for the same filters
+    filters.sort();
+
+    let mut conditions = Vec::with_capacity(filters.len());
+    let mut params = Vec::with_capacity(filters.len());
+    for (key, value) in filters {
+        let (condition, column, values) = filter_condition(key, value, casts, capabilities)?;
+        mask.check([column])?;
+        conditions.push(condition);
+        params.extend(values);
+    }
+    Ok((conditions.join(" AND "), params))
+}
+
+/// Compiles a query filter into a condition and the values to bind to its
+/// placeholders
+///
+/// `column=value` is an equality test unless the value starts with an
+/// operator (`in.(a,b)`, `not.eq.x`, ...; see the `filters` module).
+/// `column[contains]=value` matches rows whose JSON array in `column` has an
+/// element equal to `value`, taken literally; elements are compared as
+/// text, so `?n[contains]=3` matches the number 3. `column[null]=true`
+/// matches rows where `column` is NULL and `column[null]=false` those where
+/// it isn't.
+///
+/// Comparisons go through a cast when the value ends in a `::type` hint
+/// (`?price=100::int`, `?price=in.(1,2)::int`) or the column has a `cast.`
+/// hint; the value's own hint wins. See `cast_hints`.
+fn filter_condition<'a>(
+    key: &'a str,
+    value: &'a str,
+    casts: &HashMap<String, SqliteType>,
+    capabilities: &Capabilities,
+) -> Result<(String, &'a str, Vec<SqlValue>), VibeError> {
+    if let Some(column) = key.strip_suffix("[null]") {
+        SchemaGuard::validate_identifier(column)?;
+        let condition = match value {
+            "true" => format!("{} IS NULL", column),
+            "false" => format!("{} IS NOT NULL", column),
+            _ => {
+                return Err(VibeError::InvalidFields {
+                    message: format!("{} takes true or false, got '{}'", key, value),
+                    fields: vec![column.to_string()],
+                })
+            }
+        };
+        return Ok((condition, column, Vec::new()));
+    }
+    match key.strip_suffix("[contains]") {
+        Some(column) => {
+            SchemaGuard::validate_identifier(column)?;
+            capabilities.require(Capability::Json1, "The [contains] filter")?;
+            let condition = format!(
+                "EXISTS (SELECT 1 FROM json_each(CASE WHEN json_valid({c}) THEN {c} END) \
+                 WHERE CAST(value AS TEXT) = ?)",
+                c = column
+            );
+            Ok((condition, column, vec![SqlValue::Text(value.to_string())]))
+        }
+        None => {
+            SchemaGuard::validate_identifier(key)?;
+            let (value, hint) = split_cast_hint(value);
+            let filter = Filter::parse(value).map_err(|message| VibeError::InvalidFields {
+                message: format!("Invalid filter on {}: {}", key, message),
+                fields: vec![key.to_string()],
+            })?;
+            let (condition, params) = match hint.as_ref().or_else(|| casts.get(key)) {
+                Some(target) => filter.condition(&target.cast(key), &target.cast("?")),
+                None => filter.condition(key, "?"),
+            };
+            Ok((condition, key, params))
+        }
+    }
+}
+
+/// Prefix of `?cast.<column>=<type>` query hints
+const CAST_PREFIX: &str = "cast.";
+
+/// Collects the `?cast.<column>=<type>` hints among query parameters
+///
+/// A column created as TEXT keeps comparing as text after numbers arrive,
+/// so `"9" > "100"`. A hint makes equality filters, `order_by` and
+/// aggregate metrics read the column through `CAST(column AS type)`
+/// instead. Indexes on the column cannot serve a cast expression.
+fn cast_hints<'a>(
+    params: impl IntoIterator<Item = (&'a String, &'a String)>,
+) -> Result<HashMap<String, SqliteType>, VibeError> {
+    let mut casts = HashMap::new();
+    for (key, target) in params {
+        if let Some(column) = key.strip_prefix(CAST_PREFIX) {
+            SchemaGuard::validate_identifier(column)?;
+            casts.insert(column.to_string(), SqliteType::parse_cast(target)?);
+        }
+    }
+    Ok(casts)
+}
+
+/// Splits a trailing `::type` cast hint off a filter value
+///
+/// Only a cast target (`int`, `real`, `text` and their aliases) after the
+/// last `::` is a hint; anything else, as in `::1` or `fe80::abc`, is part
+/// of the value.
+fn split_cast_hint(value: &str) -> (&str, Option<SqliteType>) {
+    match value.rsplit_once("::") {
+        Some((literal, target)) => match SqliteType::parse_cast(target) {
+            Ok(hint) => (literal, Some(hint)),
+            Err(_) => (value, None),
+        },
+        None => (value, None),
+    }
+}
+
+/// Nests the exploded arrays named in `?expand=` under each row
+async fn expand_rows(
+    state: &AppState,
+    collection: &str,
+    caller: Option<&AuthUser>,
+    rows: &mut [Value],
+    expand: Option<&str>,
+) -> Result<(), VibeError> {
+    let Some(expand) = expand else {
+        return Ok(());
+    };
+
+    let settings = state.settings.get(collection).await?;
+    for field in expand.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+        let mask = match settings.explode_arrays.get(field) {
+            Some(child) => state.column_mask(child, caller).await?,
+            None => ColumnMask::default(),
+        };
+        explode::expand(&state.store, collection, &settings, rows, field, &mask).await?;
+    }
+    Ok(())
+}
+
+/// Converts a store row into a JSON object
+fn row_to_json(row: Vec<(String, Value)>) -> Value {
+    Value::Object(row.into_iter().collect())
+}
 
-    let results: Vec<Value> = rows
+/// Top-level keys of a JSON object payload
+fn payload_keys(payload: &Value) -> impl Iterator<Item = &str> {
+    payload
+        .as_object()
         .into_iter()
-        .map(|row| {
-            let mut obj = serde_json::Map::new();
-            for (key, value) in row {
-                obj.insert(key, value);
+        .flat_map(|obj| obj.keys().map(String::as_str))
+}
+
+/// Streams query results as NDJSON, one object per line
+///
+/// The HTTP status has already been sent by the time a mid-stream SQL error
+/// surfaces, so the error is logged and the body is aborted instead.
+fn stream_ndjson(
+    state: &AppState,
+    collection: String,
+    sql: String,
+    params: Vec<SqlValue>,
+    mask: ColumnMask,
+    original_keys: bool,
+    column_uses: Vec<(String, ColumnUse)>,
+) -> Response {
+    let mut rx = state
+        .store
+        .query_streaming(sql, params, state.config.query.stream_buffer_rows);
+    let guard = Arc::clone(&state.guard);
+    let perf = Arc::clone(&state.perf);
+
+    let body = async_stream::stream! {
+        while let Some(row) = rx.recv().await {
+            match row {
+                Ok(row) => {
+                    let mut value = row_to_json(row);
+                    mask.apply_value(&mut value);
+                    if original_keys {
+                        if let Err(e) = guard.restore_original_keys(&collection, &mut value).await {
+                            yield Err(std::io::Error::other(e.to_string()));
+                            return;
+                        }
+                    }
+                    let mut line = value.to_string();
+                    line.push('\n');
+                    yield Ok(Bytes::from(line));
+                }
+                Err(e) => {
+                    warn!("NDJSON stream for {} aborted: {}", collection, e);
+                    yield Err(std::io::Error::other(e.to_string()));
+                    return;
+                }
             }
-            Value::Object(obj)
-        })
-        .collect();
+        }
+        record_column_uses(&perf, &collection, &column_uses);
+    };
 
-    Ok(Json(json!({
-        "success": true,
-        "data": results,
-        "count": results.len(),
-        "collection": collection
-    })))
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, NDJSON_CONTENT_TYPE)
+        .body(Body::from_stream(body))
+        .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
 }
 
 /// GET /v1/query/:collection/:id - Get single document by ID
 async fn get_by_id_handler(
     State(state): State<AppState>,
     Path((collection, id)): Path<(String, i64)>,
+    caller: Option<AuthUser>,
+    Query(params): Query<ExpandParams>,
 ) -> Result<impl IntoResponse, VibeError> {
     debug!("🔍 Getting {} from {}", id, collection);
+    let collection = state.guard.resolve_table(&collection, false).await?;
+    state.perf.record_read(&collection);
 
-    let _stats = state.guard.get_table_stats(&collection).await?;
+    // Existence comes from the schema cache; no row count is needed
+    let schema = state.guard.require_columns(&collection).await?;
+    let mask = state.column_mask(&collection, caller.as_ref()).await?;
 
-    let sql = format!("SELECT * FROM {} WHERE id = ?", collection);
-    let rows = state.store.query(sql, vec![SqlValue::Integer(id)]).await?;
+    let row = match state.row_cache.get(&collection, id, schema.len()) {
+        Some(row) => Some(row),
+        None if state.row_cache.is_enabled() => {
+            // Cached rows are unmasked, so they serve every caller
+            let ticket = state.row_cache.ticket();
+            let sql = format!("SELECT * FROM {} WHERE id = ?", collection);
+            let rows = state.store.query(sql, vec![SqlValue::Integer(id)]).await?;
+            rows.into_iter().next().map(row_to_json).inspect(|row| {
+                state.row_cache.insert(ticket, &collection, id, schema.len(), row.clone());
+            })
+        }
+        None => {
+            let columns: Vec<String> = schema.into_iter().map(|c| c.name).collect();
+            let sql = format!(
+                "SELECT {} FROM {} WHERE id = ?",
+                mask.projection(&columns),
+                collection
+            );
+            let rows = state.store.query(sql, vec![SqlValue::Integer(id)]).await?;
+            rows.into_iter().next().map(row_to_json)
+        }
+    };
 
-    if let Some(row) = rows.into_iter().next() {
-        let mut obj = serde_json::Map::new();
-        for (key, value) in row {
-            obj.insert(key, value);
+    if let Some(mut obj) = row {
+        mask.apply_value(&mut obj);
+        let rows = std::slice::from_mut(&mut obj);
+        expand_rows(&state, &collection, caller.as_ref(), rows, params.expand.as_deref()).await?;
+        if params.original_keys {
+            state.guard.restore_original_keys(&collection, &mut obj).await?;
         }
 
         Ok(Json(json!({
             "success": true,
-            "data": Value::Object(obj)
+            "data": obj
         })))
     } else {
         Err(VibeError::TableNotFound(format!(
@@ -461,35 +2049,235 @@ async fn get_by_id_handler(
     }
 }
 
+/// GET /v1/query/:collection/:id/asof?ts= - Document as it was at a time
+async fn get_as_of_handler(
+    State(state): State<AppState>,
+    Path((collection, id)): Path<(String, i64)>,
+    caller: Option<AuthUser>,
+    Query(params): Query<AsOfParams>,
+) -> Result<impl IntoResponse, VibeError> {
+    debug!("⏳ Getting {} from {} as of {}", id, collection, params.ts);
+    let collection = state.guard.resolve_table(&collection, false).await?;
+    let _stats = state.guard.get_table_stats(&collection).await?;
+    state.perf.record_read(&collection);
+    let settings = state.settings.get(&collection).await?;
+    let ts = audit::check_history(&collection, &settings, &params.ts)?;
+
+    let mut row = audit::row_as_of(&state.store, &collection, id, &ts)
+        .await?
+        .ok_or_else(|| {
+            VibeError::NotFound(format!(
+                "Document with id {} did not exist in {} at {}",
+                id, collection, ts
+            ))
+        })?;
+    settings.mask_for(caller.as_ref()).apply_value(&mut row);
+
+    Ok(Json(json!({
+        "success": true,
+        "data": row,
+        "as_of": ts
+    })))
+}
+
+/// GET /v1/query/:collection/aggregate - Grouped metrics
+///
+/// `metric` may repeat (defaults to `count`), `group_by` takes a
+/// comma-separated column list and every other parameter is a filter, as for
+/// queries. See `aggregate` for the metric syntax.
+async fn aggregate_handler(
+    State(state): State<AppState>,
+    Path(collection): Path<String>,
+    caller: Option<AuthUser>,
+    Query(params): Query<Vec<(String, String)>>,
+) -> Result<impl IntoResponse, VibeError> {
+    debug!("📊 Aggregating collection: {}", collection);
+    let collection = state.guard.resolve_table(&collection, false).await?;
+    let stats = state.guard.get_table_stats(&collection).await?;
+    state.perf.record_read(&collection);
+    let mask = state.column_mask(&collection, caller.as_ref()).await?;
+
+    let casts = cast_hints(params.iter().map(|(k, v)| (k, v)))?;
+    let mut metrics = Vec::new();
+    let mut group_by = Vec::new();
+    let mut conditions = Vec::new();
+    let mut filter_params = Vec::new();
+    for (key, value) in &params {
+        match key.as_str() {
+            _ if key.starts_with(CAST_PREFIX) => {}
+            "metric" => metrics.push((value.clone(), Metric::parse(value)?)),
+            "group_by" => group_by.extend(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|c| !c.is_empty())
+                    .map(String::from),
+            ),
+            _ => {
+                let (condition, column, values) =
+                    filter_condition(key, value, &casts, state.store.capabilities())?;
+                mask.check([column])?;
+                conditions.push(condition);
+                filter_params.extend(values);
+            }
+        }
+    }
+    if metrics.is_empty() {
+        metrics.push(("count".to_string(), Metric::Count(None)));
+    }
+
+    let read: Vec<&str> = group_by
+        .iter()
+        .map(String::as_str)
+        .chain(metrics.iter().filter_map(|(_, m)| m.column()))
+        .collect();
+    for column in &read {
+        SchemaGuard::validate_identifier(column)?;
+        if !stats.columns.iter().any(|c| c.name.eq_ignore_ascii_case(column)) {
+            return Err(VibeError::InvalidFields {
+                message: format!("Column {} does not exist in {}", column, collection),
+                fields: vec![column.to_string()],
+            });
+        }
+    }
+    mask.check(read)?;
+    for (_, metric) in &mut metrics {
+        if let Some(target) = metric.column().and_then(|column| casts.get(column)) {
+            let target = target.clone();
+            metric.cast(&target);
+        }
+    }
+
+    let uses_percentiles = metrics
+        .iter()
+        .any(|(_, m)| matches!(m, Metric::Percentile { .. }));
+    let method = PercentileMethod::for_capabilities(state.store.capabilities());
+    let aggregation = Aggregation {
+        collection: collection.clone(),
+        group_by,
+        metrics,
+        conditions,
+        params: filter_params,
+    };
+    let groups = aggregation.run(&state.store, method).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": groups,
+        "count": groups.len(),
+        "collection": collection,
+        "percentile_method": uses_percentiles.then_some(method)
+    })))
+}
+
+/// GET /v1/query/:collection/asof?ts= - Documents as they were at a time
+///
+/// Filters are equality matches against the historical values.
+async fn query_as_of_handler(
+    State(state): State<AppState>,
+    Path(collection): Path<String>,
+    caller: Option<AuthUser>,
+    Query(params): Query<AsOfParams>,
+) -> Result<impl IntoResponse, VibeError> {
+    debug!("⏳ Querying {} as of {}", collection, params.ts);
+    let collection = state.guard.resolve_table(&collection, false).await?;
+    let _stats = state.guard.get_table_stats(&collection).await?;
+    state.perf.record_read(&collection);
+    let settings = state.settings.get(&collection).await?;
+    let ts = audit::check_history(&collection, &settings, &params.ts)?;
+    let mask = settings.mask_for(caller.as_ref());
+
+    let reserved = ["ts", "limit", "offset"];
+    let filters: Vec<(String, String)> = params
+        .filters
+        .into_iter()
+        .filter(|(k, _)| !reserved.contains(&k.as_str()))
+        .collect();
+    for (k, _) in &filters {
+        SchemaGuard::validate_identifier(k)?;
+    }
+    mask.check(filters.iter().map(|(k, _)| k.as_str()))?;
+
+    let limit = (params.limit.unwrap_or(100) as usize).min(audit::MAX_AS_OF_ROWS);
+    let offset = params.offset.unwrap_or(0) as usize;
+    let mut rows =
+        audit::rows_as_of(&state.store, &collection, &ts, &filters, limit, offset).await?;
+    for row in &mut rows {
+        mask.apply_value(row);
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": rows,
+        "count": rows.len(),
+        "collection": collection,
+        "as_of": ts
+    })))
+}
+
 /// POST /v1/update/:collection/:id - Update a document
+///
+/// The update event carries only the fields whose values changed, listed
+/// in `changed`; `noop: true` marks an update that changed nothing. Values
+/// are compared by SQLite in the same transaction as the write, so a `"5"`
+/// sent to an INTEGER column holding 5 counts as unchanged. Keys naming
+/// system columns, in any case, are not written and come back in
+/// `skipped_fields`.
 async fn update_handler(
     State(state): State<AppState>,
     Path((collection, id)): Path<(String, i64)>,
-    Json(payload): Json<Value>,
+    caller: Option<AuthUser>,
+    headers: HeaderMap,
+    Json(mut payload): Json<Value>,
 ) -> Result<impl IntoResponse, VibeError> {
-    info!("📝 Updating {} in {}", id, collection);
+    info!("📝 Updating {} in {} (by {})", id, collection, AuthUser::label(caller.as_ref()));
+
+    let collection = state.guard.resolve_table(&collection, false).await?;
+    state.guard.require_table(&collection).await?;
+    check_row_lock(&state, &collection, id, caller.as_ref(), &headers).await?;
+    let hints = type_hints(&state, &collection, &mut payload).await?;
+    let aliases = state.guard.apply_column_aliases(&collection, &mut payload).await?;
+    let mask = state.column_mask(&collection, caller.as_ref()).await?;
+    mask.check(payload_keys(&payload))?;
 
-    // Ensure columns exist
-    let columns = state.guard.ensure_columns(&collection, &payload).await?;
+    // Ensure columns exist; nulls clear existing columns
+    let columns = state.guard.ensure_update_columns(&collection, &payload, &hints).await?;
+    let skipped_fields = guard::system_fields(&payload);
+    let warnings = number_warnings(&state, &collection, &payload).await?;
 
     if columns.is_empty() {
-        return Ok(Json(json!({
+        let mut response = json!({
             "success": true,
             "message": "No updates provided"
-        })));
+        });
+        if !skipped_fields.is_empty() {
+            response["skipped_fields"] = json!(skipped_fields);
+        }
+        return Ok(Json(response));
     }
 
     let obj = payload.as_object().ok_or_else(|| {
         VibeError::InvalidPayload("Payload must be a JSON object".to_string())
     })?;
 
-    // Build UPDATE statement
+    // Build UPDATE statement, and a SELECT of which values it leaves as they are
     let set_clauses: Vec<String> = columns.iter().map(|c| format!("{} = ?", c)).collect();
     let sql = format!(
         "UPDATE {} SET {}, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
         collection,
         set_clauses.join(", ")
     );
+    let same_clauses: Vec<String> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, c)| format!("{} IS ?{}", c, i + 1))
+        .collect();
+    let compare_sql = format!(
+        "SELECT {} FROM {} WHERE id = ?{}",
+        same_clauses.join(", "),
+        collection,
+        columns.len() + 1
+    );
 
     let mut params: Vec<SqlValue> = columns
         .iter()
@@ -501,240 +2289,6533 @@ async fn update_handler(
         .collect();
     params.push(SqlValue::Integer(id));
 
-    let affected = state.store.execute(sql, params).await?;
+    let settings = state.settings.get(&collection).await?;
+    let audited = settings.audit;
+    if audited {
+        audit::ensure_table(&state.store).await?;
+    }
+
+    // Audited rows are read before and after the write, and the entry
+    // recorded, in the write's transaction
+    let column_count = columns.len();
+    let table = collection.clone();
+    let actor = caller.clone();
+    let started = Instant::now();
+    let result = state
+        .store
+        .with_transaction_labeled(&state.perf.label(&collection), move |conn| {
+            let unchanged: Option<Vec<bool>> = conn
+                .query_row(&compare_sql, rusqlite::params_from_iter(params.iter()), |row| {
+                    (0..column_count).map(|i| row.get(i)).collect()
+                })
+                .optional()?;
+            let before = if audited { audit::snapshot_in(conn, &table, id)? } else { None };
+            let affected = conn.execute(&sql, rusqlite::params_from_iter(params.iter()))?;
+            let mut after = None;
+            if audited && affected > 0 {
+                after = audit::snapshot_in(conn, &table, id)?;
+                audit::record_in(conn, &table, id, AuditOp::Update, before.as_ref(), after.as_ref(), actor.as_ref())?;
+            }
+            Ok((affected as u64, unchanged, after))
+        })
+        .await;
+    let written = result.as_ref().map(|(affected, _, _)| *affected).unwrap_or(0);
+    state.perf.record_write(&collection, started.elapsed(), written, result.is_ok());
+    state.row_cache.invalidate(&collection, id);
+    let (affected, unchanged, after) = result?;
+
+    let changed: Vec<&String> = match &unchanged {
+        Some(unchanged) => columns
+            .iter()
+            .zip(unchanged)
+            .filter(|(_, same)| !**same)
+            .map(|(column, _)| column)
+            .collect(),
+        None => Vec::new(),
+    };
+    let changes: serde_json::Map<String, Value> = changed
+        .iter()
+        .filter_map(|column| obj.get(*column).map(|value| ((*column).clone(), value.clone())))
+        .collect();
+
+    let stored = match after {
+        Some(after) => Some(after),
+        None if affected > 0 => stored_row(&state, &collection, id, &settings).await?,
+        None => None,
+    };
 
     // Broadcast update
-    let tx = state.get_broadcaster(&collection);
-    let _ = tx.send(json!({
+    state.broadcast(&collection, json!({
         "event": "update",
         "id": id,
-        "data": payload
+        "data": event_data(&settings, stored, Value::Object(changes)),
+        "changed": changed,
+        "noop": changed.is_empty(),
+        "actor": AuthUser::actor(caller.as_ref())
     }));
 
-    Ok(Json(json!({
+    let mut response = json!({
         "success": true,
         "affected": affected,
         "id": id
-    })))
+    });
+    if !aliases.is_empty() {
+        response["aliases"] = json!(aliases);
+    }
+    if !skipped_fields.is_empty() {
+        response["skipped_fields"] = json!(skipped_fields);
+    }
+    if !warnings.is_empty() {
+        response["warnings"] = json!(warnings);
+    }
+    Ok(Json(response))
 }
 
-/// POST /v1/delete/:collection/:id - Delete a document
-async fn delete_handler(
+/// A one-statement update of a single field, for changes that must not
+/// race a read-modify-write (increments, array appends)
+struct FieldUpdate {
+    field: String,
+    /// New value of the field in terms of its current one, with `?1`
+    /// bound to `param`
+    expression: String,
+    param: SqlValue,
+    /// SQL conditions the row must meet, with `?1` bound to `param`, and
+    /// the error for each when it doesn't
+    conditions: Vec<(String, VibeError)>,
+}
+
+/// Stored column of a `FieldUpdate`, created from `prototype` as
+/// `column_type` when missing
+///
+/// Fails with `InvalidFields` for system and unwritable columns and for
+/// declared types other than `types`.
+async fn field_update_column(
+    state: &AppState,
+    collection: &str,
+    caller: Option<&AuthUser>,
+    field: &str,
+    prototype: Value,
+    column_type: SqliteType,
+    types: &[&str],
+) -> Result<String, VibeError> {
+    SchemaGuard::validate_identifier(field)?;
+    let column = state.guard.resolve_column(collection, field).await?;
+    if guard::is_system_column(&column) {
+        return Err(VibeError::InvalidFields {
+            message: format!("System column {} cannot be written", column),
+            fields: vec![field.to_string()],
+        });
+    }
+    let mask = state.column_mask(collection, caller).await?;
+    mask.check([column.as_str()])?;
+
+    let hints = TypeHints::from([(column.to_lowercase(), column_type)]);
+    let writable = state
+        .guard
+        .ensure_columns_hinted(collection, &json!({ column.clone(): prototype }), &hints)
+        .await?;
+    let declared = state
+        .guard
+        .column_types(collection)
+        .await?
+        .remove(&column.to_lowercase())
+        .unwrap_or_default();
+    if writable.is_empty() || !types.contains(&declared.as_str()) {
+        return Err(VibeError::InvalidFields {
+            message: format!("{} is not a writable {} column", column, types.join(" or ")),
+            fields: vec![field.to_string()],
+        });
+    }
+    Ok(column)
+}
+
+/// Runs a `FieldUpdate` on row `id` and returns the field's new value,
+/// converted with `to_json`
+///
+/// Audits and broadcasts the change like `update_handler`.
+async fn apply_field_update(
+    state: &AppState,
+    collection: &str,
+    id: i64,
+    caller: Option<&AuthUser>,
+    update: FieldUpdate,
+    to_json: fn(rusqlite::types::Value) -> Value,
+) -> Result<Value, VibeError> {
+    let settings = state.settings.get(collection).await?;
+    let audited = settings.audit;
+    if audited {
+        audit::ensure_table(&state.store).await?;
+    }
+
+    let FieldUpdate { field, expression, param, conditions } = update;
+    let (checks, mut mismatches): (Vec<String>, Vec<VibeError>) = conditions.into_iter().unzip();
+    let condition: String = checks.iter().map(|check| format!(" AND ({})", check)).collect();
+    let update_sql = format!(
+        "UPDATE {} SET {} = {}, updated_at = CURRENT_TIMESTAMP WHERE id = ?2{}",
+        collection, field, expression, condition
+    );
+    // Which condition a row that wasn't updated failed
+    let check_sqls: Vec<String> = checks
+        .iter()
+        .map(|check| format!("SELECT COALESCE({}, 0) FROM {} WHERE id = ?2", check, collection))
+        .collect();
+    let select_sql = format!("SELECT {} FROM {} WHERE id = ?1", field, collection);
+    let exists_sql = format!("SELECT 1 FROM {} WHERE id = ?1", collection);
+    let returning_sql = state
+        .store
+        .capabilities()
+        .returning
+        .then(|| format!("{} RETURNING {}", update_sql, field));
+    let table = collection.to_string();
+    let actor = caller.cloned();
+    let started = Instant::now();
+    // Err(exists, failed condition) when no row was updated
+    let result = state
+        .store
+        .with_transaction_labeled(&state.perf.label(collection), move |conn| {
+            let before = if audited { audit::snapshot_in(conn, &table, id)? } else { None };
+            let params = rusqlite::params![param, id];
+            let value = match returning_sql {
+                Some(sql) => conn
+                    .query_row(&sql, params, |row| row.get::<_, rusqlite::types::Value>(0))
+                    .optional()?,
+                None if conn.execute(&update_sql, params)? > 0 => {
+                    Some(conn.query_row(&select_sql, [id], |row| row.get(0))?)
+                }
+                None => None,
+            };
+            let Some(value) = value else {
+                if conn.query_row(&exists_sql, [id], |_| Ok(())).optional()?.is_none() {
+                    return Ok(Err((false, None)));
+                }
+                let params = rusqlite::params![param, id];
+                for (index, sql) in check_sqls.iter().enumerate() {
+                    if !conn.query_row(sql, params, |row| row.get::<_, bool>(0))? {
+                        return Ok(Err((true, Some(index))));
+                    }
+                }
+                return Ok(Err((true, None)));
+            };
+            let mut after = None;
+            if audited {
+                after = audit::snapshot_in(conn, &table, id)?;
+                audit::record_in(conn, &table, id, AuditOp::Update, before.as_ref(), after.as_ref(), actor.as_ref())?;
+            }
+            Ok(Ok((value, after)))
+        })
+        .await;
+    let written = u64::from(matches!(result, Ok(Ok(_))));
+    state.perf.record_write(collection, started.elapsed(), written, result.is_ok());
+    state.row_cache.invalidate(collection, id);
+    let (value, after) = match result? {
+        Ok((value, after)) => (to_json(value), after),
+        Err((true, failed)) => {
+            return Err(match failed {
+                Some(index) => mismatches.swap_remove(index),
+                None => VibeError::InvalidFields {
+                    message: format!("{} cannot be updated", field),
+                    fields: vec![field],
+                },
+            })
+        }
+        Err((false, _)) => {
+            return Err(VibeError::TableNotFound(format!(
+                "Document with id {} not found in {}",
+                id, collection
+            )))
+        }
+    };
+
+    let stored = match after {
+        Some(after) => Some(after),
+        None => stored_row(state, collection, id, &settings).await?,
+    };
+
+    state.broadcast(collection, json!({
+        "event": "update",
+        "id": id,
+        "data": event_data(&settings, stored, json!({ field.clone(): value.clone() })),
+        "changed": [field],
+        "noop": false,
+        "actor": AuthUser::actor(caller)
+    }));
+    Ok(value)
+}
+
+/// POST /v1/update/:collection/:id/increment - Atomically add to a number
+///
+/// Runs `SET field = COALESCE(field, 0) + by` in one statement, so
+/// concurrent increments never lose an update, and returns the new value.
+/// A missing field is created as INTEGER, or REAL for a fractional `by`;
+/// an existing field must be INTEGER or REAL.
+async fn increment_handler(
     State(state): State<AppState>,
     Path((collection, id)): Path<(String, i64)>,
+    caller: Option<AuthUser>,
+    headers: HeaderMap,
+    Json(req): Json<IncrementRequest>,
 ) -> Result<impl IntoResponse, VibeError> {
-    info!("🗑️ Deleting {} from {}", id, collection);
+    info!(
+        "➕ Incrementing {} of {} in {} (by {})",
+        req.field,
+        id,
+        collection,
+        AuthUser::label(caller.as_ref())
+    );
 
-    let sql = format!("DELETE FROM {} WHERE id = ?", collection);
-    let affected = state.store.execute(sql, vec![SqlValue::Integer(id)]).await?;
+    let collection = state.guard.resolve_table(&collection, false).await?;
+    state.guard.require_table(&collection).await?;
+    check_row_lock(&state, &collection, id, caller.as_ref(), &headers).await?;
 
-    // Broadcast delete
-    let tx = state.get_broadcaster(&collection);
-    let _ = tx.send(json!({
-        "event": "delete",
-        "id": id
-    }));
+    let by = req.by.unwrap_or_else(|| json!(1));
+    let delta = match &by {
+        Value::Number(n) if n.as_f64().is_some_and(f64::is_finite) => json_to_sql_value(&by),
+        _ => {
+            return Err(VibeError::InvalidFields {
+                message: "by must be a number".to_string(),
+                fields: vec!["by".to_string()],
+            })
+        }
+    };
+
+    // Create the column if missing, typed after the delta
+    let column_type = match delta {
+        SqlValue::Integer(_) => SqliteType::Integer,
+        _ => SqliteType::Real,
+    };
+    let field = field_update_column(
+        &state,
+        &collection,
+        caller.as_ref(),
+        &req.field,
+        by,
+        column_type,
+        &["INTEGER", "REAL"],
+    )
+    .await?;
+
+    let update = FieldUpdate {
+        expression: format!("COALESCE({}, 0) + ?1", field),
+        field: field.clone(),
+        param: delta,
+        conditions: Vec::new(),
+    };
+    let value = apply_field_update(&state, &collection, id, caller.as_ref(), update, |value| match value {
+        rusqlite::types::Value::Integer(i) => json!(i),
+        rusqlite::types::Value::Real(f) => json!(f),
+        _ => Value::Null,
+    })
+    .await?;
 
     Ok(Json(json!({
         "success": true,
-        "affected": affected,
-        "id": id
+        "id": id,
+        "field": field,
+        "value": value
     })))
 }
 
-/// GET /v1/tables - List all tables
-async fn list_tables_handler(
+/// POST /v1/update/:collection/:id/array-append - Atomically append to a
+/// JSON array
+///
+/// Appends `value` with `json_insert(field, '$[#]', ...)` in one
+/// statement and returns the new array. A missing column is created as
+/// TEXT, and a NULL field counts as `[]`; any other value that isn't a
+/// JSON array is refused. The element and the resulting array are held to
+/// the nesting limits of pushed documents.
+async fn array_append_handler(
     State(state): State<AppState>,
+    Path((collection, id)): Path<(String, i64)>,
+    caller: Option<AuthUser>,
+    headers: HeaderMap,
+    Json(req): Json<ArrayAppendRequest>,
 ) -> Result<impl IntoResponse, VibeError> {
-    let tables = state.store.list_tables().await?;
+    info!(
+        "📎 Appending to {} of {} in {} (by {})",
+        req.field,
+        id,
+        collection,
+        AuthUser::label(caller.as_ref())
+    );
+
+    state.store.capabilities().require(Capability::Json1, "Array appends")?;
+    let collection = state.guard.resolve_table(&collection, false).await?;
+    state.guard.require_table(&collection).await?;
+    check_row_lock(&state, &collection, id, caller.as_ref(), &headers).await?;
+    // The element lands one level below the array
+    let mut appended = serde_json::Map::new();
+    appended.insert(req.field.clone(), json!([req.value]));
+    state.guard.check_nesting(&appended)?;
+
+    let element = SqlValue::Text(req.value.to_string());
+    let field = field_update_column(
+        &state,
+        &collection,
+        caller.as_ref(),
+        &req.field,
+        json!([req.value]),
+        SqliteType::Text,
+        &["TEXT"],
+    )
+    .await?;
+
+    let current = format!("COALESCE({}, '[]')", field);
+    let appended = format!("json_insert({}, '$[#]', json(?1))", current);
+    let max_bytes = state.guard.max_nested_bytes();
+    let update = FieldUpdate {
+        conditions: vec![
+            (
+                format!("CASE WHEN json_valid({c}) THEN json_type({c}) END = 'array'", c = current),
+                VibeError::InvalidFields {
+                    message: format!("{} does not hold a JSON array", field),
+                    fields: vec![field.clone()],
+                },
+            ),
+            // Bounds the stored array like a pushed nested value
+            (
+                format!("length(CAST({} AS BLOB)) <= {}", appended, max_bytes),
+                VibeError::InvalidPayload(format!(
+                    "'{}' is too large; nested values may be at most {} bytes as JSON",
+                    field, max_bytes
+                )),
+            ),
+        ],
+        expression: appended,
+        param: element,
+        field: field.clone(),
+    };
+    let value = apply_field_update(&state, &collection, id, caller.as_ref(), update, |value| match value {
+        rusqlite::types::Value::Text(text) => serde_json::from_str(&text).unwrap_or(Value::Null),
+        _ => Value::Null,
+    })
+    .await?;
 
     Ok(Json(json!({
         "success": true,
-        "tables": tables,
-        "count": tables.len()
+        "id": id,
+        "field": field,
+        "value": value
     })))
 }
 
-/// GET /v1/tables/:collection - Get table stats
-async fn table_stats_handler(
+/// POST /v1/locks/:collection/:id - Take or refresh a row's lock
+///
+/// Broadcasts `{"event": "lock", "id", "holder", "expires_at"}` on the
+/// collection's stream; fails with `423 Locked` while someone else holds
+/// it (see `locks`).
+async fn acquire_lock_handler(
+    State(state): State<AppState>,
+    Path((collection, id)): Path<(String, i64)>,
+    caller: Option<AuthUser>,
+    Query(params): Query<LockParams>,
+) -> Result<impl IntoResponse, VibeError> {
+    let collection = state.guard.resolve_table(&collection, false).await?;
+    state.guard.require_table(&collection).await?;
+    let ttl = match params.ttl_secs {
+        None => DEFAULT_LOCK_TTL,
+        Some(secs) if secs > 0 && secs <= MAX_LOCK_TTL.as_secs() => Duration::from_secs(secs),
+        Some(_) => {
+            return Err(VibeError::InvalidFields {
+                message: format!("ttl_secs must be between 1 and {}", MAX_LOCK_TTL.as_secs()),
+                fields: vec!["ttl_secs".to_string()],
+            })
+        }
+    };
+
+    let exists = state
+        .store
+        .query(format!("SELECT 1 FROM {} WHERE id = ?", collection), vec![SqlValue::Integer(id)])
+        .await?;
+    if exists.is_empty() {
+        return Err(VibeError::NotFound(format!("Document {} not found in {}", id, collection)));
+    }
+
+    let lock = state
+        .locks
+        .acquire(&collection, id, caller.as_ref(), params.session.as_deref(), ttl)?;
+    debug!("🔒 {} locked {} of {}", AuthUser::label(caller.as_ref()), id, collection);
+    state.schedule_lock_expiry(&lock);
+    state.broadcast(&collection, json!({
+        "event": "lock",
+        "id": id,
+        "holder": lock.holder,
+        "expires_at": lock.expires_at
+    }));
+
+    Ok(Json(ApiResponse::success(lock)))
+}
+
+/// DELETE /v1/locks/:collection/:id - Release a row's lock
+///
+/// Only the holder, or an admin, may release it. Broadcasts an `unlock`
+/// event when a lock was released.
+async fn release_lock_handler(
+    State(state): State<AppState>,
+    Path((collection, id)): Path<(String, i64)>,
+    caller: Option<AuthUser>,
+    Query(params): Query<LockParams>,
+) -> Result<impl IntoResponse, VibeError> {
+    let collection = state.guard.resolve_table(&collection, false).await?;
+    let released = state
+        .locks
+        .release(&collection, id, caller.as_ref(), params.session.as_deref())?;
+    if let Some(lock) = &released {
+        state.broadcast_unlock(lock, "released");
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "released": released.is_some()
+    })))
+}
+
+/// GET /v1/locks/:collection/:id - The holder of a row's lock, or null
+async fn get_lock_handler(
+    State(state): State<AppState>,
+    Path((collection, id)): Path<(String, i64)>,
+) -> Result<impl IntoResponse, VibeError> {
+    let collection = state.guard.resolve_table(&collection, false).await?;
+    Ok(Json(ApiResponse::success(state.locks.get(&collection, id))))
+}
+
+/// Stream session named by a write's `x-vibe-session` header
+fn lock_session(headers: &HeaderMap) -> Option<&str> {
+    headers.get(LOCK_SESSION_HEADER).and_then(|value| value.to_str().ok())
+}
+
+/// Rejects a write to a row someone else holds the lock of, when the
+/// collection enforces locks
+async fn check_row_lock(
+    state: &AppState,
+    collection: &str,
+    id: i64,
+    caller: Option<&AuthUser>,
+    headers: &HeaderMap,
+) -> Result<(), VibeError> {
+    if state.settings.get(collection).await?.enforce_locks {
+        state.locks.check(collection, id, caller, lock_session(headers))?;
+    }
+    Ok(())
+}
+
+/// POST /v1/delete/:collection/:id - Delete a document
+async fn delete_handler(
+    State(state): State<AppState>,
+    Path((collection, id)): Path<(String, i64)>,
+    caller: Option<AuthUser>,
+) -> Result<impl IntoResponse, VibeError> {
+    info!("🗑️ Deleting {} from {} (by {})", id, collection, AuthUser::label(caller.as_ref()));
+
+    let collection = state.guard.resolve_table(&collection, false).await?;
+    state.guard.require_table(&collection).await?;
+    let settings = state.settings.get(&collection).await?;
+    let audited = settings.audit;
+    let cascade = settings
+        .inline_files
+        .as_ref()
+        .filter(|config| config.cascade_delete)
+        .zip(state.storage.as_ref());
+    if audited {
+        audit::ensure_table(&state.store).await?;
+    }
+
+    // The row is read, deleted and audited in one transaction
+    let snapshot = audited || cascade.is_some();
+    let sql = format!("DELETE FROM {} WHERE id = ?", collection);
+    let table = collection.clone();
+    let actor = caller.clone();
+    let started = Instant::now();
+    let result = state
+        .store
+        .with_transaction_labeled(&state.perf.label(&collection), move |conn| {
+            let before = if snapshot { audit::snapshot_in(conn, &table, id)? } else { None };
+            let affected = conn.execute(&sql, [id])? as u64;
+            if audited && affected > 0 {
+                audit::record_in(conn, &table, id, AuditOp::Delete, before.as_ref(), None, actor.as_ref())?;
+            }
+            Ok((affected, before))
+        })
+        .await;
+    let written = result.as_ref().map(|(affected, _)| *affected).unwrap_or(0);
+    state.perf.record_write(&collection, started.elapsed(), written, result.is_ok());
+    state.row_cache.invalidate(&collection, id);
+    let (affected, before) = result?;
+    state.row_counts.adjust(&collection, -(affected as i64));
+
+    // Files the row held inline go with it
+    if let (Some((config, storage)), Some(Value::Object(row))) = (cascade, &before) {
+        let bucket = config.bucket_for(&collection);
+        inline_files::cascade(storage, &bucket, &config.references(&bucket, row)).await;
+    }
+
+    // Broadcast delete
+    state.broadcast(&collection, json!({
+        "event": "delete",
+        "id": id,
+        "actor": AuthUser::actor(caller.as_ref())
+    }));
+
+    Ok(Json(json!({
+        "success": true,
+        "affected": affected,
+        "id": id
+    })))
+}
+
+/// Query parameters for filtered deletes
+#[derive(Debug, Deserialize)]
+pub struct FilteredDeleteParams {
+    /// Only report how many rows would be deleted
+    #[serde(default)]
+    pub count_only: Option<String>,
+    #[serde(flatten)]
+    pub filters: Filters,
+}
+
+/// POST /v1/delete/:collection - Delete every document matching the filters
+///
+/// Filters use the query syntax (`column=value`, `column=in.(a,b)`,
+/// `column[contains]=value`, ...) and at least one is required. With `?count_only=true` nothing is
+/// deleted and the response reports how many rows would be, so clients can
+/// confirm first; both phases build the same `WHERE` clause. Inline files
+/// of the deleted rows cascade like those of single deletes.
+async fn filtered_delete_handler(
     State(state): State<AppState>,
     Path(collection): Path<String>,
+    caller: Option<AuthUser>,
+    Query(params): Query<FilteredDeleteParams>,
 ) -> Result<impl IntoResponse, VibeError> {
-    let stats = state.guard.get_table_stats(&collection).await?;
+    let collection = state.guard.resolve_table(&collection, false).await?;
+    state.guard.require_table(&collection).await?;
+    let count_only = match params.count_only.as_deref() {
+        None | Some("false") => false,
+        Some("true") => true,
+        Some(other) => {
+            return Err(VibeError::InvalidPayload(format!(
+                "count_only must be true or false, got '{}'",
+                other
+            )))
+        }
+    };
 
-    let columns: Vec<ColumnResponse> = stats
-        .columns
+    let mask = state.column_mask(&collection, caller.as_ref()).await?;
+    let filters = aliased_filters(&state, &collection, &params.filters).await?;
+    let casts = cast_hints(filters.iter())?;
+    let (conditions, filter_params) =
+        filter_clause(&filters, &["count_only"], &casts, state.store.capabilities(), &mask)?;
+    if conditions.is_empty() {
+        return Err(VibeError::InvalidPayload(
+            "A filtered delete needs at least one filter".to_string(),
+        ));
+    }
+
+    if count_only {
+        let rows = state
+            .store
+            .query(
+                format!("SELECT COUNT(*) AS count FROM {} WHERE {}", collection, conditions),
+                filter_params,
+            )
+            .await?;
+        let count = rows
+            .first()
+            .and_then(|row| row.first())
+            .and_then(|(_, value)| value.as_u64())
+            .unwrap_or(0);
+        return Ok(Json(json!({
+            "success": true,
+            "count_only": true,
+            "count": count,
+            "collection": collection
+        })));
+    }
+
+    info!(
+        "🗑️ Deleting from {} where {} (by {})",
+        collection,
+        conditions,
+        AuthUser::label(caller.as_ref())
+    );
+    let settings = state.settings.get(&collection).await?;
+    let audited = settings.audit;
+    let cascade = settings
+        .inline_files
+        .as_ref()
+        .filter(|config| config.cascade_delete)
+        .zip(state.storage.as_ref());
+    if audited {
+        audit::ensure_table(&state.store).await?;
+    }
+
+    // The rows read for the audit log and the cascade are the rows deleted
+    let snapshot = audited || cascade.is_some();
+    let select = format!("SELECT * FROM {} WHERE {}", collection, conditions);
+    let sql = format!("DELETE FROM {} WHERE {}", collection, conditions);
+    let table = collection.clone();
+    let actor = caller.clone();
+    let started = Instant::now();
+    let result = state
+        .store
+        .with_transaction_labeled(&state.perf.label(&collection), move |conn| {
+            let before: Vec<Value> = if snapshot {
+                VibeStore::read_rows(conn, &select, &filter_params)?
+                    .into_iter()
+                    .map(row_to_json)
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            let affected = conn.execute(&sql, rusqlite::params_from_iter(filter_params.iter()))? as u64;
+            if audited {
+                for row in &before {
+                    if let Some(id) = row.get("id").and_then(Value::as_i64) {
+                        audit::record_in(conn, &table, id, AuditOp::Delete, Some(row), None, actor.as_ref())?;
+                    }
+                }
+            }
+            Ok((affected, before))
+        })
+        .await;
+    let written = result.as_ref().map(|(affected, _)| *affected).unwrap_or(0);
+    state.perf.record_write(&collection, started.elapsed(), written, result.is_ok());
+    state.row_cache.invalidate_collection(&collection);
+    let (affected, before) = result?;
+    state.row_counts.adjust(&collection, -(affected as i64));
+
+    // Files the rows held inline go with them
+    if let Some((config, storage)) = cascade {
+        let bucket = config.bucket_for(&collection);
+        let paths: Vec<String> = before
+            .iter()
+            .filter_map(Value::as_object)
+            .flat_map(|row| config.references(&bucket, row))
+            .collect();
+        inline_files::cascade(storage, &bucket, &paths).await;
+    }
+
+    // Broadcast bulk delete
+    state.broadcast(&collection, json!({
+        "event": "bulk_delete",
+        "count": affected,
+        "actor": AuthUser::actor(caller.as_ref())
+    }));
+
+    Ok(Json(json!({
+        "success": true,
+        "affected": affected,
+        "collection": collection
+    })))
+}
+
+/// GET /v1/tables - List all tables
+///
+/// With the row count cache on, or `?exact=true`, also returns
+/// `row_counts` by table.
+async fn list_tables_handler(
+    State(state): State<AppState>,
+    Query(params): Query<RowCountParams>,
+) -> Result<impl IntoResponse, VibeError> {
+    let tables = state.store.list_tables().await?;
+
+    let mut body = json!({
+        "success": true,
+        "tables": tables,
+        "count": tables.len()
+    });
+    if state.row_counts.is_enabled() || params.exact {
+        let mut row_counts = serde_json::Map::new();
+        let mut approximate = false;
+        for table in &tables {
+            let (count, cached) = state.row_counts.get(&state.store, table, params.exact).await?;
+            approximate |= cached;
+            row_counts.insert(table.clone(), json!(count));
+        }
+        body["row_counts"] = Value::Object(row_counts);
+        body["approximate"] = json!(approximate);
+    }
+    Ok(Json(body))
+}
+
+/// GET /v1/tables/:collection - Get table stats
+///
+/// `row_count` comes from the row count cache when it is on, unless
+/// `?exact=true`; `approximate` says which.
+async fn table_stats_handler(
+    State(state): State<AppState>,
+    Path(collection): Path<String>,
+    Query(params): Query<RowCountParams>,
+) -> Result<impl IntoResponse, VibeError> {
+    let collection = state.guard.resolve_table(&collection, false).await?;
+    let schema = state.guard.get_table_columns(&collection).await?;
+    let (row_count, approximate) = state.row_counts.get(&state.store, &collection, params.exact).await?;
+    let perf = state.perf.snapshot(&collection, &state.store);
+    let descriptions = state.descriptions.get(&collection).await?;
+
+    let columns: Vec<ColumnResponse> = schema
         .iter()
         .map(|c| ColumnResponse {
             name: c.name.clone(),
             col_type: c.col_type.clone(),
             nullable: !c.notnull,
             primary_key: c.pk,
+            is_virtual: c.generated,
+            description: descriptions.get(&c.name).cloned(),
         })
         .collect();
 
     Ok(Json(json!({
         "success": true,
         "data": TableStatsResponse {
-            name: stats.name,
-            column_count: stats.column_count,
-            row_count: stats.row_count,
+            name: collection,
+            column_count: schema.len(),
+            row_count,
+            approximate,
+            reads: perf.reads,
+            writes: perf.writes,
             columns,
         }
     })))
 }
 
-/// GET /v1/stream/:collection - Server-Sent Events stream
-async fn stream_handler(
+/// GET /v1/tables/:collection/perf - Write statistics of a collection
+///
+/// Latency covers the write statements of push, batch, update and delete
+/// requests, from the call into the store until it returns.
+async fn table_perf_handler(
     State(state): State<AppState>,
     Path(collection): Path<String>,
-) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
-    info!("📡 New stream subscriber for: {}", collection);
+) -> Result<impl IntoResponse, VibeError> {
+    SchemaGuard::validate_identifier(&collection)?;
+    Ok(Json(ApiResponse::success(
+        state.perf.snapshot(&collection, &state.store),
+    )))
+}
 
-    let tx = state.get_broadcaster(&collection);
-    let mut rx = tx.subscribe();
+/// Request body for defining a computed column
+#[derive(Debug, Deserialize)]
+pub struct ComputedColumnRequest {
+    pub name: String,
+    /// SQL expression over existing columns, e.g. `first || ' ' || last`
+    pub expression: String,
+    /// Declared type of the result
+    #[serde(rename = "type", default = "default_computed_type")]
+    pub col_type: String,
+}
 
-    let stream = async_stream::stream! {
-        // Send initial connection message
-        yield Ok(Event::default().data(json!({
-            "event": "connected",
-            "collection": collection
-        }).to_string()));
+fn default_computed_type() -> String {
+    "TEXT".to_string()
+}
 
-        // Stream updates
-        loop {
-            match rx.recv().await {
-                Ok(value) => {
-                    yield Ok(Event::default().data(value.to_string()));
-                }
-                Err(broadcast::error::RecvError::Closed) => break,
-                Err(broadcast::error::RecvError::Lagged(n)) => {
-                    yield Ok(Event::default().data(json!({
-                        "event": "warning",
-                        "message": format!("Missed {} messages", n)
-                    }).to_string()));
-                }
+/// POST /v1/tables/:collection/computed - Add a virtual generated column
+///
+/// Requires an admin once authentication is enabled.
+async fn add_computed_column_handler(
+    State(state): State<AppState>,
+    Path(collection): Path<String>,
+    caller: Option<AuthUser>,
+    Json(req): Json<ComputedColumnRequest>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "add computed columns")?;
+
+    info!("🧮 Adding computed column {}.{}", collection, req.name);
+    state
+        .guard
+        .add_generated_column(&collection, &req.name, &req.col_type, &req.expression)
+        .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "success": true,
+            "data": {
+                "collection": collection,
+                "name": req.name,
+                "type": req.col_type.to_uppercase(),
+                "expression": req.expression,
+                "virtual": true
             }
-        }
-    };
+        })),
+    ))
+}
 
-    Sse::new(stream).keep_alive(
-        axum::response::sse::KeepAlive::new()
-            .interval(Duration::from_secs(30))
-            .text("ping"),
-    )
+/// GET /v1/tables/:collection/column-usage - Find columns no longer populated
+///
+/// Columns without a value in any recent row are flagged as removal
+/// candidates and listed as `DROP COLUMN` statements for `/v1/sql/execute`.
+async fn column_usage_handler(
+    State(state): State<AppState>,
+    Path(collection): Path<String>,
+    Query(params): Query<ColumnUsageParams>,
+    caller: Option<AuthUser>,
+) -> Result<impl IntoResponse, VibeError> {
+    let mut report = state
+        .guard
+        .column_usage(
+            &collection,
+            params.recent_days.unwrap_or(DEFAULT_USAGE_RECENT_DAYS),
+            params.sample,
+        )
+        .await?;
+
+    let mask = state.column_mask(&collection, caller.as_ref()).await?;
+    report.columns.retain(|c| !mask.is_hidden(&c.name));
+    let suggested_statements = report.drop_statements();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": report,
+        "suggested_statements": suggested_statements
+    })))
 }
 
-/// SQL Request
+/// Default and largest `sample_size` of a column preview
+const DEFAULT_PREVIEW_SAMPLE: u64 = 100;
+const MAX_PREVIEW_SAMPLE: u64 = 1000;
+
+/// Default and largest `max_length` of a column preview
+const DEFAULT_PREVIEW_LENGTH: usize = 80;
+const MAX_PREVIEW_LENGTH: usize = 1000;
+
+/// GET /v1/tables/:collection/preview - Example values of each column
+///
+/// Up to three non-null values per column from the newest rows, with text
+/// truncated in SQL, for the Explorer's column headers.
+async fn column_preview_handler(
+    State(state): State<AppState>,
+    Path(collection): Path<String>,
+    Query(params): Query<ColumnPreviewParams>,
+    caller: Option<AuthUser>,
+) -> Result<impl IntoResponse, VibeError> {
+    let collection = state.guard.resolve_table(&collection, false).await?;
+    SchemaGuard::validate_identifier(&collection)?;
+    let sample_size = params.sample_size.unwrap_or(DEFAULT_PREVIEW_SAMPLE).clamp(1, MAX_PREVIEW_SAMPLE);
+    let max_length = params.max_length.unwrap_or(DEFAULT_PREVIEW_LENGTH).clamp(1, MAX_PREVIEW_LENGTH);
+
+    let mask = state.column_mask(&collection, caller.as_ref()).await?;
+    let descriptions = state.descriptions.get(&collection).await?;
+    let preview = state
+        .guard
+        .column_preview(&collection, &mask, &descriptions, sample_size, max_length)
+        .await?;
+    Ok(Json(ApiResponse::success(preview)))
+}
+
+/// GET /v1/schema/:collection/jsonschema - Current row shape as JSON Schema
+///
+/// Responds with the bare schema document rather than the usual envelope,
+/// so codegen tools can read the URL directly. Columns hidden from the
+/// caller are left out.
+async fn json_schema_handler(
+    State(state): State<AppState>,
+    Path(collection): Path<String>,
+    caller: Option<AuthUser>,
+) -> Result<impl IntoResponse, VibeError> {
+    let collection = state.guard.resolve_table(&collection, false).await?;
+    SchemaGuard::validate_identifier(&collection)?;
+    let mask = state.column_mask(&collection, caller.as_ref()).await?;
+    let descriptions = state.descriptions.get(&collection).await?;
+    let schema = state.guard.json_schema(&collection, &mask, &descriptions).await?;
+    Ok(Json(schema))
+}
+
+/// Default page of `GET /v1/admin/schema-cache`
+const DEFAULT_CACHE_CHECKS: usize = 50;
+
+/// Query parameters for inspecting the schema cache
 #[derive(Debug, Deserialize)]
-pub struct SqlRequest {
-    pub query: String,
+pub struct SchemaCacheParams {
+    /// Tables checked, at most `MAX_CACHE_CHECKS`
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
 }
 
-/// POST /v1/sql/query - Execute a SQL query and return rows
-async fn sql_query_handler(
+/// GET /v1/admin/schema-cache - Cached schemas checked against the database
+///
+/// Each table of the page costs one PRAGMA; `stale` entries (out-of-band
+/// DDL) are fixed by evicting them.
+async fn inspect_schema_cache_handler(
     State(state): State<AppState>,
-    Json(payload): Json<SqlRequest>,
+    caller: Option<AuthUser>,
+    Query(params): Query<SchemaCacheParams>,
 ) -> Result<impl IntoResponse, VibeError> {
-    info!("🔍 Executing Raw SQL Query: {}", payload.query);
-    
-    // Safety check? For now, we allow everything as requested by "USER: control everything"
-    let rows = state.store.query_simple(payload.query).await?;
-    
-    // Transform specifically to look generic
-    let results: Vec<Value> = rows.into_iter().map(|row| {
-         let mut obj = serde_json::Map::new();
-         for (key, value) in row {
-             obj.insert(key, value);
-         }
-         Value::Object(obj)
-    }).collect();
+    require_admin(&state, caller.as_ref(), "manage the schema cache")?;
+    let offset = params.offset.unwrap_or(0);
+    let limit = params.limit.unwrap_or(DEFAULT_CACHE_CHECKS).min(MAX_CACHE_CHECKS);
+    let (total, entries) = state.guard.inspect_cache(offset, limit).await?;
+    let stale = entries.iter().filter(|e| e.stale).count();
+    info!(
+        target: "vibedb::security",
+        "Schema cache inspected by {}: {} of {} tables checked, {} stale",
+        AuthUser::label(caller.as_ref()), entries.len(), total, stale
+    );
 
     Ok(Json(json!({
         "success": true,
-        "data": results,
-        "count": results.len()
+        "data": entries,
+        "total": total,
+        "offset": offset,
+        "limit": limit
     })))
 }
 
-/// POST /v1/sql/execute - Execute a SQL statement (DDL/DML)
-async fn sql_execute_handler(
+/// DELETE /v1/admin/schema-cache - Clear the whole schema cache
+async fn clear_schema_cache_handler(
     State(state): State<AppState>,
-    Json(payload): Json<SqlRequest>,
+    caller: Option<AuthUser>,
 ) -> Result<impl IntoResponse, VibeError> {
-    info!("⚡ Executing Raw SQL Statement: {}", payload.query);
+    require_admin(&state, caller.as_ref(), "manage the schema cache")?;
+    let cleared = state.guard.clear_cache();
+    info!(
+        target: "vibedb::security",
+        "Schema cache cleared by {} ({} tables)",
+        AuthUser::label(caller.as_ref()), cleared
+    );
 
-    let affected = state.store.execute_simple(payload.query).await?;
-    
     Ok(Json(json!({
         "success": true,
-        "affected": affected
+        "cleared": cleared
     })))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use axum::body::Body;
-    use axum::http::{Request, StatusCode};
-    use tower::util::ServiceExt;
+/// DELETE /v1/admin/schema-cache/:collection - Evict one table
+async fn evict_schema_cache_handler(
+    State(state): State<AppState>,
+    Path(collection): Path<String>,
+    caller: Option<AuthUser>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "manage the schema cache")?;
+    let collection = state.guard.resolve_table(&collection, false).await?;
+    let evicted = state.guard.evict(&collection);
+    info!(
+        target: "vibedb::security",
+        "Schema cache entry {} evicted by {} (cached: {})",
+        collection, AuthUser::label(caller.as_ref()), evicted
+    );
 
-    async fn create_test_app() -> Router {
-        let store = Arc::new(VibeStore::in_memory().await.unwrap());
-        let state = AppState::new(store);
-        create_router(state)
-    }
+    Ok(Json(json!({
+        "success": true,
+        "collection": collection,
+        "evicted": evicted
+    })))
+}
 
-    #[tokio::test]
-    async fn test_health_endpoint() {
-        let app = create_test_app().await;
-
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .uri("/health")
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+/// POST /v1/admin/erasure - Erase a data subject's rows across collections
+///
+/// Returns the (signed) erasure report; see the `erasure` module.
+async fn erasure_handler(
+    State(state): State<AppState>,
+    caller: Option<AuthUser>,
+    Json(req): Json<ErasureRequest>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "erase data")?;
+    info!(
+        "🧹 Erasure ({:?}{}) requested by {}",
+        req.mode,
+        if req.dry_run { ", dry run" } else { "" },
+        AuthUser::label(caller.as_ref())
+    );
+    let report = erasure::erase(&state, req, caller.as_ref()).await?;
+
+    Ok(Json(ApiResponse::success(report)))
+}
+
+/// Body of `PUT /v1/admin/concurrency`; omitted limits are kept
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConcurrencyUpdate {
+    pub global: Option<usize>,
+    pub writes: Option<usize>,
+    pub reads: Option<usize>,
+    pub uploads: Option<usize>,
+    pub acquire_timeout_ms: Option<u64>,
+}
+
+/// GET /v1/admin/concurrency - Requests in flight, limits and refusals
+async fn get_concurrency_handler(
+    State(state): State<AppState>,
+    caller: Option<AuthUser>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "manage concurrency limits")?;
+    Ok(Json(ApiResponse::success(state.limits.stats())))
+}
 
-        assert_eq!(response.status(), StatusCode::OK);
+/// PUT /v1/admin/concurrency - Change limits without a restart
+///
+/// Requests in flight keep their permits; a lowered limit applies as they
+/// finish. Changes last until the server restarts.
+async fn put_concurrency_handler(
+    State(state): State<AppState>,
+    caller: Option<AuthUser>,
+    Json(update): Json<ConcurrencyUpdate>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "manage concurrency limits")?;
+    let mut config = state.limits.config();
+    config.global = update.global.unwrap_or(config.global);
+    config.writes = update.writes.unwrap_or(config.writes);
+    config.reads = update.reads.unwrap_or(config.reads);
+    config.uploads = update.uploads.unwrap_or(config.uploads);
+    if let Some(ms) = update.acquire_timeout_ms {
+        config.acquire_timeout = Duration::from_millis(ms);
     }
+    config.validate().map_err(VibeError::InvalidPayload)?;
 
-    #[tokio::test]
-    async fn test_push_and_query() {
-        let store = Arc::new(VibeStore::in_memory().await.unwrap());
-        let state = AppState::new(store);
-        let app = create_router(state);
+    state.limits.reconfigure(&config);
+    info!(
+        target: "vibedb::security",
+        "Concurrency limits set to {:?} by {}",
+        config,
+        AuthUser::label(caller.as_ref())
+    );
+    Ok(Json(ApiResponse::success(state.limits.stats())))
+}
 
-        // Push data
-        let response = app
-            .clone()
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/v1/push/users")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"name": "Alice", "age": 30}"#))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+/// GET /v1/admin/pragmas - SQLite settings of the main connection
+async fn get_pragmas_handler(
+    State(state): State<AppState>,
+    caller: Option<AuthUser>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "manage database pragmas")?;
+    Ok(Json(ApiResponse::success(state.store.pragmas().await?)))
+}
 
-        assert_eq!(response.status(), StatusCode::CREATED);
+/// POST /v1/admin/pragmas - Tune `cache_size`, `synchronous`,
+/// `busy_timeout` and `wal_autocheckpoint` without a restart
+///
+/// `journal_mode` is refused. Changes last until the server restarts.
+async fn set_pragmas_handler(
+    State(state): State<AppState>,
+    caller: Option<AuthUser>,
+    Json(update): Json<PragmaUpdate>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "manage database pragmas")?;
+    let pragmas = state.store.set_pragmas(update.clone()).await?;
+    info!(
+        target: "vibedb::security",
+        "Pragmas set to {:?} by {}",
+        update,
+        AuthUser::label(caller.as_ref())
+    );
+    Ok(Json(ApiResponse::success(pragmas)))
+}
 
-        // Query data
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .uri("/v1/query/users")
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+/// Body of `POST /v1/admin/checkpoint`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CheckpointRequest {
+    /// `passive` (the default), `full`, `restart` or `truncate`
+    #[serde(default)]
+    pub mode: CheckpointMode,
+}
+
+/// POST /v1/admin/checkpoint - Checkpoint the WAL into the database file
+///
+/// With `{"mode": "truncate"}` the `-wal` file shrinks to zero bytes, e.g.
+/// before copying the database. Responds with SQLite's `busy` flag and
+/// page counts; a busy checkpoint is still a success. Refused with 409 for
+/// in-memory databases.
+async fn checkpoint_handler(
+    State(state): State<AppState>,
+    caller: Option<AuthUser>,
+    Json(request): Json<CheckpointRequest>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "checkpoint the database")?;
+    let checkpoint = state.store.wal_checkpoint(request.mode).await?;
+    info!(
+        target: "vibedb::security",
+        "WAL checkpoint ({:?}) by {}: {:?}",
+        request.mode,
+        AuthUser::label(caller.as_ref()),
+        checkpoint
+    );
+    Ok(Json(ApiResponse::success(checkpoint)))
+}
+
+/// Query parameters of `GET /v1/admin/index-suggestions`
+#[derive(Debug, Deserialize)]
+pub struct IndexSuggestionParams {
+    /// Fewest filter and order-by uses for a column to be suggested
+    pub min_uses: Option<u64>,
+}
+
+/// Default `min_uses` of index suggestions
+const DEFAULT_INDEX_SUGGESTION_USES: u64 = 10;
+
+/// An index worth considering on a column queries use
+#[derive(Debug, Serialize)]
+pub struct IndexSuggestion {
+    pub collection: String,
+    pub column: String,
+    pub filter_uses: u64,
+    pub order_by_uses: u64,
+    /// Statement that would create the index
+    pub sql: String,
+}
+
+/// GET /v1/admin/index-suggestions - Unindexed columns queries use often
+///
+/// Queries and renders count the columns they filter and order by since
+/// startup (see `perf`). A column used at least `?min_uses=` times (10 by
+/// default) that doesn't lead an index of its collection is suggested,
+/// most used first. `[contains]` filters are left out, as an index can't
+/// serve them. Advisory only: nothing is created.
+async fn index_suggestions_handler(
+    State(state): State<AppState>,
+    caller: Option<AuthUser>,
+    Query(params): Query<IndexSuggestionParams>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "read index suggestions")?;
+    let min_uses = params.min_uses.unwrap_or(DEFAULT_INDEX_SUGGESTION_USES).max(1);
+
+    let mut by_collection: BTreeMap<String, Vec<QueryColumnUse>> = BTreeMap::new();
+    for usage in state.perf.query_columns().into_iter().filter(|u| u.total() >= min_uses) {
+        by_collection.entry(usage.collection.clone()).or_default().push(usage);
+    }
+
+    let mut suggestions = Vec::new();
+    for (collection, uses) in by_collection {
+        // Dropped since, or gone with a reset
+        let Ok(columns) = state.guard.get_table_columns(&collection).await else {
+            continue;
+        };
+        let indexed = state.store.indexed_columns(&collection).await?;
+        for usage in uses {
+            if indexed.contains(&usage.column) || !columns.iter().any(|c| c.name == usage.column) {
+                continue;
+            }
+            suggestions.push(IndexSuggestion {
+                sql: format!(
+                    "CREATE INDEX idx_{c}_{col} ON {c} ({col})",
+                    c = collection,
+                    col = usage.column
+                ),
+                collection: usage.collection,
+                column: usage.column,
+                filter_uses: usage.filter_uses,
+                order_by_uses: usage.order_by_uses,
+            });
+        }
+    }
+    suggestions.sort_by(|a, b| {
+        (b.filter_uses + b.order_by_uses)
+            .cmp(&(a.filter_uses + a.order_by_uses))
+            .then_with(|| (&a.collection, &a.column).cmp(&(&b.collection, &b.column)))
+    });
+
+    Ok(Json(ApiResponse::success(json!({
+        "min_uses": min_uses,
+        "suggestions": suggestions,
+    }))))
+}
+
+/// Body of `POST /v1/render`
+#[derive(Debug, Deserialize)]
+pub struct RenderRequest {
+    pub collection: String,
+    /// Filters plus `limit`, `offset`, `order_by` and `order_dir`, as in
+    /// `GET /v1/query/:collection`
+    #[serde(default)]
+    pub query: serde_json::Map<String, Value>,
+    /// Inline template
+    pub template: Option<String>,
+    /// Name of a stored template, instead of `template`
+    pub template_name: Option<String>,
+}
+
+/// Body of `PUT /v1/templates/:name`
+#[derive(Debug, Deserialize)]
+pub struct TemplateRequest {
+    pub template: String,
+}
+
+/// Query parameters of a render's `query` object
+fn render_query(query: serde_json::Map<String, Value>) -> Result<QueryParams, VibeError> {
+    let mut filters = Filters::default();
+    for (key, value) in query {
+        let value = match value {
+            Value::String(s) => s,
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            _ => {
+                return Err(VibeError::InvalidPayload(format!(
+                    "query.{} must be a string, number or boolean",
+                    key
+                )))
+            }
+        };
+        filters.push(key, value);
+    }
+    let number = |filters: &mut Filters, key: &str| {
+        filters
+            .remove(key)
+            .map(|v| {
+                v.parse::<u32>().map_err(|_| {
+                    VibeError::InvalidPayload(format!("query.{} must be a non-negative integer", key))
+                })
+            })
+            .transpose()
+    };
+    Ok(QueryParams {
+        limit: number(&mut filters, "limit")?,
+        offset: number(&mut filters, "offset")?,
+        order_by: filters.remove("order_by"),
+        order_dir: filters.remove("order_dir"),
+        stream: None,
+        expand: None,
+        original_keys: None,
+        select: filters.remove("select"),
+        filters,
+    })
+}
+
+/// POST /v1/render - Render query results through an HTML template
+///
+/// Rows are read like `GET /v1/query/:collection`, with the caller's column
+/// visibility, and rendered with `rows`, `count` and `collection` in scope
+/// (see the `templates` module). Responds with `text/html`.
+async fn render_handler(
+    State(state): State<AppState>,
+    caller: Option<AuthUser>,
+    Json(req): Json<RenderRequest>,
+) -> Result<Response, VibeError> {
+    let source = match (req.template, req.template_name) {
+        (Some(template), None) => template,
+        (None, Some(name)) => {
+            state
+                .templates
+                .get(&name)
+                .await?
+                .ok_or_else(|| VibeError::NotFound(format!("Template '{}' not found", name)))?
+                .template
+        }
+        _ => {
+            return Err(VibeError::InvalidPayload(
+                "Give exactly one of template and template_name".to_string(),
+            ))
+        }
+    };
+    let template = Template::parse(&source)?;
+
+    let params = render_query(req.query)?;
+    let limit = params.limit.unwrap_or(100);
+    if limit as usize > templates::MAX_RENDER_ROWS {
+        return Err(VibeError::InvalidPayload(format!(
+            "Renders are limited to {} rows",
+            templates::MAX_RENDER_ROWS
+        )));
+    }
+
+    let collection = state.guard.resolve_table(&req.collection, false).await?;
+    state.perf.record_read(&collection);
+    let mask = state.column_mask(&collection, caller.as_ref()).await?;
+    let (sql, query_params, column_uses) =
+        select_statement(&state, &collection, &mask, &params, i64::from(limit)).await?;
+    let rows = state.store.query(sql, query_params).await?;
+    record_column_uses(&state.perf, &collection, &column_uses);
+    let rows: Vec<Value> = rows
+        .into_iter()
+        .map(|row| {
+            let mut value = row_to_json(row);
+            mask.apply_value(&mut value);
+            value
+        })
+        .collect();
+    debug!("🖼️ Rendering {} row(s) of {}", rows.len(), collection);
+
+    let context = json!({
+        "count": rows.len(),
+        "rows": rows,
+        "collection": collection
+    });
+    let html = tokio::task::spawn_blocking(move || template.render(&context, &RenderLimits::default()))
+        .await
+        .map_err(|e| VibeError::Internal(anyhow::anyhow!("Render task failed: {}", e)))??;
+
+    Ok((
+        [(header::CONTENT_TYPE, HeaderValue::from_static("text/html; charset=utf-8"))],
+        html,
+    )
+        .into_response())
+}
+
+/// GET /v1/templates - List named HTML templates
+async fn list_templates_handler(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, VibeError> {
+    let templates = state.templates.list().await?;
+    Ok(Json(ApiResponse::success(templates)))
+}
+
+/// GET /v1/templates/:name - Get a named HTML template
+async fn get_template_handler(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, VibeError> {
+    let template = state
+        .templates
+        .get(&name)
+        .await?
+        .ok_or_else(|| VibeError::NotFound(format!("Template '{}' not found", name)))?;
+    Ok(Json(ApiResponse::success(template)))
+}
+
+/// PUT /v1/templates/:name - Create or replace a named HTML template
+///
+/// Requires an admin once authentication is enabled.
+async fn put_template_handler(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    caller: Option<AuthUser>,
+    Json(req): Json<TemplateRequest>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "manage templates")?;
+    let template = state.templates.put(&name, &req.template).await?;
+    info!("🖼️ Stored template '{}' (by {})", name, AuthUser::label(caller.as_ref()));
+
+    Ok(Json(ApiResponse::success(template)))
+}
+
+/// DELETE /v1/templates/:name - Delete a named HTML template
+///
+/// Requires an admin once authentication is enabled.
+async fn delete_template_handler(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    caller: Option<AuthUser>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "manage templates")?;
+    if !state.templates.delete(&name).await? {
+        return Err(VibeError::NotFound(format!("Template '{}' not found", name)));
+    }
+    info!("🗑️ Deleted template '{}'", name);
+
+    Ok(Json(json!({
+        "success": true,
+        "message": format!("Template '{}' deleted", name)
+    })))
+}
+
+/// GET /v1/tables/:collection/settings - Get collection settings
+async fn get_settings_handler(
+    State(state): State<AppState>,
+    Path(collection): Path<String>,
+) -> Result<impl IntoResponse, VibeError> {
+    SchemaGuard::validate_identifier(&collection)?;
+    let settings = state.settings.get(&collection).await?;
+
+    Ok(Json(ApiResponse::success(CollectionSettings::clone(&settings))))
+}
+
+/// PUT /v1/tables/:collection/settings - Replace collection settings
+///
+/// Requires an admin once authentication is enabled.
+async fn put_settings_handler(
+    State(state): State<AppState>,
+    Path(collection): Path<String>,
+    caller: Option<AuthUser>,
+    Json(settings): Json<CollectionSettings>,
+) -> Result<impl IntoResponse, VibeError> {
+    SchemaGuard::validate_identifier(&collection)?;
+    require_admin(&state, caller.as_ref(), "change collection settings")?;
+
+    info!("⚙️ Updating settings for {}", collection);
+    let settings = state.settings.put(&collection, settings).await?;
+
+    Ok(Json(ApiResponse::success(CollectionSettings::clone(&settings))))
+}
+
+/// Request body for describing one column
+#[derive(Debug, Deserialize)]
+pub struct DescriptionRequest {
+    /// New description; `null` or blank removes it
+    pub description: Option<String>,
+}
+
+/// PUT /v1/tables/:collection/columns/:column/description - Describe a column
+///
+/// Requires an admin once authentication is enabled.
+async fn put_description_handler(
+    State(state): State<AppState>,
+    Path((collection, column)): Path<(String, String)>,
+    caller: Option<AuthUser>,
+    Json(request): Json<DescriptionRequest>,
+) -> Result<impl IntoResponse, VibeError> {
+    let changes = BTreeMap::from([(column, request.description)]);
+    let descriptions = set_descriptions(&state, &collection, caller.as_ref(), changes).await?;
+    Ok(Json(ApiResponse::success(descriptions)))
+}
+
+/// PUT /v1/tables/:collection/descriptions - Describe several columns
+///
+/// Takes a map of column to description (`null` removes one) and applies
+/// it in one transaction; columns not in the map are left as they are.
+async fn put_descriptions_handler(
+    State(state): State<AppState>,
+    Path(collection): Path<String>,
+    caller: Option<AuthUser>,
+    Json(changes): Json<BTreeMap<String, Option<String>>>,
+) -> Result<impl IntoResponse, VibeError> {
+    let descriptions = set_descriptions(&state, &collection, caller.as_ref(), changes).await?;
+    Ok(Json(ApiResponse::success(descriptions)))
+}
+
+/// Checks the caller and the columns, then stores the descriptions under
+/// the columns' names as stored
+async fn set_descriptions(
+    state: &AppState,
+    collection: &str,
+    caller: Option<&AuthUser>,
+    changes: BTreeMap<String, Option<String>>,
+) -> Result<BTreeMap<String, String>, VibeError> {
+    require_admin(state, caller, "describe columns")?;
+    if changes.is_empty() {
+        return Err(VibeError::InvalidPayload("No descriptions given".to_string()));
+    }
+    let collection = state.guard.resolve_table(collection, false).await?;
+    let stats = state.guard.get_table_stats(&collection).await?;
+
+    let mut resolved = BTreeMap::new();
+    let mut unknown = Vec::new();
+    for (column, text) in changes {
+        let stored = state.guard.resolve_column(&collection, &column).await?;
+        match stats.columns.iter().find(|c| c.name.eq_ignore_ascii_case(&stored)) {
+            Some(c) => {
+                resolved.insert(c.name.clone(), text);
+            }
+            None => unknown.push(column),
+        }
+    }
+    if !unknown.is_empty() {
+        return Err(VibeError::InvalidFields {
+            message: format!("Unknown columns of {}: {}", collection, unknown.join(", ")),
+            fields: unknown,
+        });
+    }
+
+    info!("📝 Describing {} column(s) of {} (by {})", resolved.len(), collection, AuthUser::label(caller));
+    let descriptions = state.descriptions.set(&collection, resolved).await?;
+    Ok(BTreeMap::clone(&descriptions))
+}
+
+/// Default number of rows moved per transaction
+const DEFAULT_MOVE_BATCH_SIZE: usize = 1000;
+
+/// Request body for moving rows between collections
+#[derive(Debug, Deserialize)]
+pub struct MoveRowsRequest {
+    /// Equality filter selecting the rows to move (required, non-empty)
+    #[serde(rename = "where")]
+    pub filter: serde_json::Map<String, Value>,
+    /// Create the target collection if it doesn't exist
+    #[serde(default)]
+    pub create_target: bool,
+    /// Add source columns missing from the target instead of dropping them
+    #[serde(default)]
+    pub copy_schema: bool,
+    #[serde(default)]
+    pub batch_size: Option<usize>,
+}
+
+/// Move rows response
+#[derive(Debug, Serialize)]
+pub struct MoveRowsResponse {
+    pub source: String,
+    pub target: String,
+    pub moved: u64,
+    pub batches: u64,
+    pub columns_added: Vec<String>,
+    /// Source columns not copied because the target lacks them
+    pub dropped_columns: Vec<String>,
+}
+
+/// Builds a `WHERE` body from an equality filter object
+fn equality_conditions(
+    filter: &serde_json::Map<String, Value>,
+) -> Result<(String, Vec<SqlValue>), VibeError> {
+    let mut conditions = Vec::with_capacity(filter.len());
+    let mut params = Vec::new();
+    for (key, value) in filter {
+        SchemaGuard::validate_identifier(key)?;
+        if value.is_null() {
+            conditions.push(format!("{} IS NULL", key));
+        } else {
+            conditions.push(format!("{} = ?", key));
+            params.push(json_to_sql_value(value));
+        }
+    }
+    Ok((conditions.join(" AND "), params))
+}
+
+/// POST /v1/tables/:source/move-to/:target - Move matching rows to another collection
+///
+/// Rows are moved in batches; each batch copies and deletes inside one
+/// transaction, so a row is never lost or duplicated. If a later batch fails,
+/// the batches already committed stay moved and the request can simply be
+/// retried to move the remainder. Row ids are reassigned by the target.
+/// Callers the source hides columns from are refused. Audited collections
+/// record the move as deletes from the source and inserts into the target.
+async fn move_rows_handler(
+    State(state): State<AppState>,
+    Path((source, target)): Path<(String, String)>,
+    caller: Option<AuthUser>,
+    Json(req): Json<MoveRowsRequest>,
+) -> Result<impl IntoResponse, VibeError> {
+    info!("📦 Moving rows from {} to {} (by {})", source, target, AuthUser::label(caller.as_ref()));
+
+    // Moved rows carry every column, hidden ones included, into a target
+    // whose mask may not hide them
+    if !state.column_mask(&source, caller.as_ref()).await?.is_empty() {
+        return Err(VibeError::Forbidden(format!(
+            "Moving rows requires a caller who can see every column of {}",
+            source
+        )));
+    }
+
+    if req.filter.is_empty() {
+        return Err(VibeError::InvalidPayload(
+            "A non-empty 'where' filter is required".to_string(),
+        ));
+    }
+    if source == target {
+        return Err(VibeError::InvalidPayload(
+            "Source and target must differ".to_string(),
+        ));
+    }
+    SchemaGuard::validate_identifier(&target)?;
+    let source_stats = state.guard.get_table_stats(&source).await?;
+
+    if state.guard.get_table_stats(&target).await.is_err() {
+        if !req.create_target {
+            return Err(VibeError::TableNotFound(target));
+        }
+        state.guard.ensure_table(&target).await?;
+    }
+
+    let columns_added = if req.copy_schema {
+        state
+            .guard
+            .ensure_columns_like(&target, &source_stats.columns)
+            .await?
+    } else {
+        Vec::new()
+    };
+
+    let target_columns: std::collections::HashSet<String> = state
+        .guard
+        .get_table_stats(&target)
+        .await?
+        .columns
+        .into_iter()
+        .filter(|c| !c.generated)
+        .map(|c| c.name.to_lowercase())
+        .collect();
+
+    // Generated columns are recomputed by the target, never copied
+    let (copy_columns, dropped_columns): (Vec<String>, Vec<String>) = source_stats
+        .columns
+        .iter()
+        .filter(|c| !c.generated)
+        .map(|c| c.name.clone())
+        .filter(|name| name != "id")
+        .partition(|name| target_columns.contains(&name.to_lowercase()));
+
+    let (conditions, filter_params) = equality_conditions(&req.filter)?;
+    let batch_size = req
+        .batch_size
+        .unwrap_or(DEFAULT_MOVE_BATCH_SIZE)
+        .clamp(1, DEFAULT_MOVE_BATCH_SIZE);
+
+    let column_list = copy_columns.join(", ");
+    let select_ids = format!(
+        "SELECT id FROM {} WHERE {} ORDER BY id LIMIT {}",
+        source, conditions, batch_size
+    );
+
+    // Audited sides record a delete per source row and an insert per
+    // target row, in the batch's transaction
+    let source_audited = state.settings.get(&source).await?.audit;
+    let target_audit = if state.settings.get(&target).await?.audit {
+        Some(InsertAudit::new(&target, caller.as_ref()))
+    } else {
+        None
+    };
+    if source_audited || target_audit.is_some() {
+        audit::ensure_table(&state.store).await?;
+    }
+
+    let mut moved = 0u64;
+    let mut batches = 0u64;
+    loop {
+        let select_ids = select_ids.clone();
+        let filter_params = filter_params.clone();
+        let source_table = source.clone();
+        let target_table = target.clone();
+        let column_list = column_list.clone();
+        let target_audit = target_audit.clone();
+        let actor = caller.clone();
+
+        let count = state
+            .store
+            .with_transaction(move |conn| {
+                let ids: Vec<i64> = {
+                    let mut stmt = conn.prepare(&select_ids)?;
+                    let params = rusqlite::params_from_iter(filter_params.iter());
+                    let rows = stmt.query_map(params, |row| row.get(0))?;
+                    rows.collect::<Result<_, _>>()?
+                };
+                if ids.is_empty() {
+                    return Ok(0);
+                }
+
+                if source_audited {
+                    for &id in &ids {
+                        let before = audit::snapshot_in(conn, &source_table, id)?;
+                        audit::record_in(conn, &source_table, id, AuditOp::Delete, before.as_ref(), None, actor.as_ref())?;
+                    }
+                }
+
+                let id_list = ids
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                match &target_audit {
+                    // Row by row, for the id each insert is audited under
+                    Some(audit) => {
+                        let copy = format!(
+                            "INSERT INTO {} ({}) SELECT {} FROM {} WHERE id = ?",
+                            target_table, column_list, column_list, source_table
+                        );
+                        for &id in &ids {
+                            conn.execute(&copy, [id])?;
+                            audit.record_in(conn, conn.last_insert_rowid())?;
+                        }
+                    }
+                    None => {
+                        conn.execute(
+                            &format!(
+                                "INSERT INTO {} ({}) SELECT {} FROM {} WHERE id IN ({}) ORDER BY id",
+                                target_table, column_list, column_list, source_table, id_list
+                            ),
+                            [],
+                        )?;
+                    }
+                }
+                conn.execute(
+                    &format!("DELETE FROM {} WHERE id IN ({})", source_table, id_list),
+                    [],
+                )?;
+                Ok(ids.len() as u64)
+            })
+            .await?;
+
+        if count == 0 {
+            break;
+        }
+        state.row_counts.adjust(&source, -(count as i64));
+        state.row_counts.adjust(&target, count as i64);
+        moved += count;
+        batches += 1;
+        debug!("Moved batch {} ({} rows) from {} to {}", batches, count, source, target);
+    }
+
+    state.row_cache.invalidate_collection(&source);
+    let summary = json!({
+        "event": "move",
+        "source": source,
+        "target": target,
+        "count": moved,
+        "actor": AuthUser::actor(caller.as_ref())
+    });
+    state.broadcast(&source, summary.clone());
+    state.broadcast(&target, summary);
+
+    Ok(Json(ApiResponse::success(MoveRowsResponse {
+        source,
+        target,
+        moved,
+        batches,
+        columns_added,
+        dropped_columns,
+    })))
+}
+
+/// Events in one coalesced stream message at most; a full batch is sent
+/// before its window ends
+const MAX_COALESCED_EVENTS: usize = 500;
+
+/// Longest coalescing window a subscriber may ask for
+const MAX_COALESCE_MS: u64 = 10_000;
+
+/// Query parameters for event streams
+#[derive(Debug, Deserialize)]
+pub struct StreamParams {
+    /// Collect events for this many milliseconds and send them as one
+    /// `batch` message
+    pub coalesce_ms: Option<u64>,
+    /// `gzip` or `zstd` to send messages compressed and base64-encoded
+    pub compress: Option<String>,
+}
+
+/// Encodes a stream message, compressed when the subscriber asked for it
+///
+/// Compressed messages are `{"encoding", "payload"}` with the base64 of the
+/// compressed JSON, so they stay valid SSE text.
+fn stream_event(value: &Value, codec: Compression) -> Event {
+    let text = value.to_string();
+    if codec == Compression::None {
+        return Event::default().data(text);
+    }
+    match codec.compress(text.as_bytes()) {
+        Ok(compressed) => {
+            use base64::Engine;
+            Event::default().data(json!({
+                "encoding": codec.as_str(),
+                "payload": base64::engine::general_purpose::STANDARD.encode(compressed)
+            }).to_string())
+        }
+        Err(e) => {
+            warn!("Sending stream message uncompressed: {}", e);
+            Event::default().data(text)
+        }
+    }
+}
+
+/// Takes the collected events into one `batch` message
+fn batch_message(batch: &mut Vec<Value>) -> Value {
+    let events = std::mem::take(batch);
+    json!({
+        "event": "batch",
+        "count": events.len(),
+        "events": events
+    })
+}
+
+/// Lock session of an open stream; releases the session's locks when the
+/// stream is dropped
+struct LockSession {
+    state: AppState,
+    session: String,
+}
+
+impl Drop for LockSession {
+    fn drop(&mut self) {
+        for lock in self.state.locks.close_session(&self.session) {
+            self.state.broadcast_unlock(&lock, "disconnected");
+        }
+    }
+}
+
+/// GET /v1/stream/:collection - Server-Sent Events stream
+///
+/// Event payloads are masked with the collection's visibility rules as they
+/// are delivered, so rule changes apply to open streams too. With
+/// `coalesce_ms` the events of each window are sent in order as one
+/// `{"event": "batch", "count", "events"}` message. The `connected` message
+/// and keep-alives are never compressed. Storage event streams (`_storage`,
+/// `storage:<bucket>`) skip events of private buckets the caller could not
+/// read. The `connected` message carries the `session` row locks can be
+/// tied to (see `locks`).
+async fn stream_handler(
+    State(state): State<AppState>,
+    Path(collection): Path<String>,
+    Query(params): Query<StreamParams>,
+    caller: Option<AuthUser>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, VibeError> {
+    let codec = match params.compress.as_deref() {
+        None | Some("none") => Compression::None,
+        Some("gzip") => Compression::Gzip,
+        Some("zstd") => Compression::Zstd,
+        Some(other) => {
+            return Err(VibeError::InvalidPayload(format!(
+                "Unknown stream compression '{}'. Use gzip or zstd",
+                other
+            )));
+        }
+    };
+    let window = match params.coalesce_ms {
+        Some(ms) if ms > MAX_COALESCE_MS => {
+            return Err(VibeError::InvalidPayload(format!(
+                "coalesce_ms must be at most {}",
+                MAX_COALESCE_MS
+            )));
+        }
+        Some(ms) if ms > 0 => Some(Duration::from_millis(ms)),
+        _ => None,
+    };
+    info!("📡 New stream subscriber for: {}", collection);
+
+    let tx = state.get_broadcaster(&collection);
+    let mut rx = tx.subscribe();
+    let storage_stream = is_storage_stream(&collection);
+    let lock_session = LockSession {
+        session: state.locks.open_session(caller.as_ref()),
+        state: state.clone(),
+    };
+
+    let stream = async_stream::stream! {
+        // Dropped with the stream when the subscriber disconnects
+        let lock_session = lock_session;
+
+        // Send initial connection message
+        let mut connected = json!({
+            "event": "connected",
+            "collection": collection,
+            "session": lock_session.session
+        });
+        if let Some(window) = window {
+            connected["coalesce_ms"] = json!(window.as_millis());
+        }
+        if codec != Compression::None {
+            connected["encoding"] = json!(codec.as_str());
+        }
+        yield Ok(Event::default().data(connected.to_string()));
+
+        // Stream updates
+        let mut batch: Vec<Value> = Vec::new();
+        let mut flush_at: Option<tokio::time::Instant> = None;
+        loop {
+            let received = match flush_at {
+                Some(at) => tokio::select! {
+                    received = rx.recv() => Some(received),
+                    _ = tokio::time::sleep_until(at) => None,
+                },
+                None => Some(rx.recv().await),
+            };
+            let flush = match received {
+                None => true,
+                Some(Ok(mut value)) => {
+                    if storage_stream
+                        && state.auth.is_some()
+                        && !serde_json::from_value::<StorageEvent>(value.clone())
+                            .is_ok_and(|event| event.visible_to(caller.as_ref()))
+                    {
+                        continue;
+                    }
+                    match state.column_mask(&collection, caller.as_ref()).await {
+                        Ok(mask) => mask.apply_event(&mut value),
+                        Err(e) => {
+                            warn!("Dropping {} event, settings unavailable: {}", collection, e);
+                            continue;
+                        }
+                    }
+                    let Some(window) = window else {
+                        yield Ok(stream_event(&value, codec));
+                        continue;
+                    };
+                    batch.push(value);
+                    flush_at.get_or_insert_with(|| tokio::time::Instant::now() + window);
+                    batch.len() == MAX_COALESCED_EVENTS
+                }
+                Some(Err(broadcast::error::RecvError::Closed)) => {
+                    if !batch.is_empty() {
+                        yield Ok(stream_event(&batch_message(&mut batch), codec));
+                    }
+                    break;
+                }
+                Some(Err(broadcast::error::RecvError::Lagged(n))) => {
+                    // Events collected so far came before the missed ones
+                    if !batch.is_empty() {
+                        flush_at = None;
+                        yield Ok(stream_event(&batch_message(&mut batch), codec));
+                    }
+                    yield Ok(stream_event(&json!({
+                        "event": "warning",
+                        "message": format!("Missed {} messages", n)
+                    }), codec));
+                    continue;
+                }
+            };
+            if flush && !batch.is_empty() {
+                flush_at = None;
+                yield Ok(stream_event(&batch_message(&mut batch), codec));
+            }
+        }
+    };
+
+    Ok(Sse::new(stream).keep_alive(
+        axum::response::sse::KeepAlive::new()
+            .interval(Duration::from_secs(30))
+            .text("ping"),
+    ))
+}
+
+/// GET /v1/sync/:collection - Current rows, then live changes, over SSE
+///
+/// After the `connected` message (carrying the captured `seq`), every row
+/// of the collection is sent as `{"event": "snapshot", "seq", "data"}`,
+/// read in one transaction (see `VibeStore::read_snapshot`). A
+/// `snapshot_complete` message with the row count follows, and then the
+/// collection's write events with a `seq` above the captured one.
+///
+/// The subscription is opened before `seq` is captured, and events are
+/// broadcast after their write commits, so every write missing from the
+/// snapshot is delivered live. A write committed between the capture and
+/// the snapshot read is in both, so clients apply events by `id`. When the
+/// subscriber falls too far behind to see every event, the stream ends
+/// with a `resync` message instead of leaving a gap.
+async fn sync_handler(
+    State(state): State<AppState>,
+    Path(collection): Path<String>,
+    caller: Option<AuthUser>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, VibeError> {
+    let collection = state.guard.resolve_table(&collection, false).await?;
+    let mask = state.column_mask(&collection, caller.as_ref()).await?;
+    info!("🔄 New sync subscriber for: {}", collection);
+
+    let mut rx = state.get_broadcaster(&collection).subscribe();
+    // Taken after subscribing: every later event reaches `rx`
+    let seq = state.event_seqs.get(&collection).map(|seq| *seq).unwrap_or(0);
+    let mut rows = state.store.read_snapshot(
+        Some(vec![collection.clone()]),
+        SnapshotShape::default(),
+        state.config.query.stream_buffer_rows,
+    );
+    // Fail with a status code when the snapshot cannot even start
+    match rows.recv().await {
+        Some(Ok(SnapshotItem::Begin { .. })) => {}
+        Some(Err(e)) => return Err(e),
+        _ => return Err(VibeError::Database("Snapshot ended before it began".to_string())),
+    }
+
+    let stream = async_stream::stream! {
+        let event = |value: Value| Ok(Event::default().data(value.to_string()));
+        yield event(json!({"event": "connected", "collection": collection, "seq": seq}));
+
+        let mut count = 0u64;
+        while let Some(item) = rows.recv().await {
+            match item {
+                Ok(SnapshotItem::Row { row, .. }) => {
+                    count += 1;
+                    let mut data = row_to_json(row);
+                    mask.apply_value(&mut data);
+                    yield event(json!({"event": "snapshot", "seq": seq, "data": data}));
+                }
+                Ok(SnapshotItem::Begin { .. }) => {}
+                Err(e) => {
+                    warn!("Sync snapshot of {} failed: {}", collection, e);
+                    yield event(json!({"event": "error", "message": e.to_string()}));
+                    return;
+                }
+            }
+        }
+        yield event(json!({"event": "snapshot_complete", "seq": seq, "count": count}));
+
+        loop {
+            match rx.recv().await {
+                Ok(mut value) => {
+                    if value["seq"].as_u64().is_some_and(|event_seq| event_seq <= seq) {
+                        continue;
+                    }
+                    match state.column_mask(&collection, caller.as_ref()).await {
+                        Ok(mask) => mask.apply_event(&mut value),
+                        Err(e) => {
+                            warn!("Dropping {} event, settings unavailable: {}", collection, e);
+                            continue;
+                        }
+                    }
+                    yield event(value);
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    yield event(json!({
+                        "event": "resync",
+                        "message": format!("Missed {} messages; sync again", n)
+                    }));
+                    break;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    };
+
+    Ok(Sse::new(stream).keep_alive(
+        axum::response::sse::KeepAlive::new()
+            .interval(Duration::from_secs(30))
+            .text("ping"),
+    ))
+}
+
+/// SQL Request
+#[derive(Debug, Deserialize)]
+pub struct SqlRequest {
+    pub query: String,
+}
+
+/// POST /v1/sql/query - Execute a SQL query and return rows
+///
+/// At most `sql.max_rows` rows are read; the response carries
+/// `truncated: true` when more were available (or fails in strict mode).
+async fn sql_query_handler(
+    State(state): State<AppState>,
+    caller: Option<AuthUser>,
+    Json(payload): Json<SqlRequest>,
+) -> Result<impl IntoResponse, VibeError> {
+    info!("🔍 Executing Raw SQL Query: {}", payload.query);
+
+    state
+        .sql_policy
+        .screen(&payload.query, sql_access(&state, caller.as_ref()))?;
+    require_unmasked_sql(&state, caller.as_ref()).await?;
+
+    let sql_config = &state.config.sql;
+    let (rows, truncated) = state
+        .store
+        .query_limited(
+            payload.query,
+            vec![],
+            sql_config.max_rows,
+            sql_config.statement_timeout,
+        )
+        .await?;
+
+    if truncated && sql_config.strict_row_limit {
+        return Err(VibeError::InvalidPayload(format!(
+            "Query returned more than {} rows; add a LIMIT",
+            sql_config.max_rows
+        )));
+    }
+
+    let results: Vec<Value> = rows.into_iter().map(row_to_json).collect();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": results,
+        "count": results.len(),
+        "truncated": truncated
+    })))
+}
+
+/// POST /v1/sql/execute - Execute a SQL statement (DDL/DML)
+///
+/// Admin-only when the approval workflow is enabled.
+async fn sql_execute_handler(
+    State(state): State<AppState>,
+    caller: Option<AuthUser>,
+    Json(payload): Json<SqlRequest>,
+) -> Result<impl IntoResponse, VibeError> {
+    if state.config.sql.approval_required {
+        let admin = require_admin_user(caller.as_ref(), "execute SQL directly; use /v1/sql/propose")?;
+        info!(target: "vibedb::security", "SQL executed directly by {}: {}", admin.email, payload.query);
+    }
+    info!("⚡ Executing Raw SQL Statement: {}", payload.query);
+
+    state
+        .sql_policy
+        .screen(&payload.query, sql_access(&state, caller.as_ref()))?;
+    require_unmasked_sql(&state, caller.as_ref()).await?;
+
+    let affected = state.store.execute_simple(payload.query).await;
+    // Any row may have changed
+    state.row_cache.clear();
+    let affected = affected?;
+    
+    Ok(Json(json!({
+        "success": true,
+        "affected": affected
+    })))
+}
+
+/// What the SQL policy lets the caller run: everything allowed to admins
+/// when auth is disabled
+fn sql_access(state: &AppState, caller: Option<&AuthUser>) -> SqlAccess {
+    if state.auth.is_none() || caller.is_some_and(AuthUser::is_admin) {
+        SqlAccess::Admin
+    } else {
+        SqlAccess::User
+    }
+}
+
+/// Rejects raw SQL from callers some collection hides columns from
+///
+/// SQL reads tables directly, past the column masks of the REST endpoints,
+/// so it is only open to callers who could see every column anyway.
+async fn require_unmasked_sql(state: &AppState, caller: Option<&AuthUser>) -> Result<(), VibeError> {
+    if sql_access(state, caller) == SqlAccess::Admin {
+        return Ok(());
+    }
+    let masked = state.settings.masked_collections(caller).await?;
+    if !masked.is_empty() {
+        return Err(VibeError::Forbidden(format!(
+            "Raw SQL requires an admin while collections hide columns from you ({})",
+            masked.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects non-admin callers when auth is enabled
+fn require_admin(state: &AppState, caller: Option<&AuthUser>, action: &str) -> Result<(), VibeError> {
+    if state.auth.is_some() && !caller.is_some_and(AuthUser::is_admin) {
+        return Err(VibeError::Forbidden(format!("Only admins can {}", action)));
+    }
+    Ok(())
+}
+
+/// Returns the caller if they are an admin, auth or not, for actions
+/// recorded under the admin's name
+fn require_admin_user<'a>(caller: Option<&'a AuthUser>, action: &str) -> Result<&'a AuthUser, VibeError> {
+    match caller {
+        Some(user) if user.is_admin() => Ok(user),
+        Some(_) => Err(VibeError::Forbidden(format!("Only admins can {}", action))),
+        None => Err(VibeError::Unauthorized("Missing or invalid token".to_string())),
+    }
+}
+
+/// POST /v1/sources - Define a pull source
+async fn create_source_handler(
+    State(state): State<AppState>,
+    caller: Option<AuthUser>,
+    Json(definition): Json<SourceDefinition>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "manage pull sources")?;
+    let source = state.sources.create(definition).await?;
+    info!("🛰️ Created source {} for {}", source.id, source.definition.collection);
+
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(source.redacted()))))
+}
+
+/// GET /v1/sources - List pull sources
+async fn list_sources_handler(
+    State(state): State<AppState>,
+    caller: Option<AuthUser>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "manage pull sources")?;
+    let sources: Vec<_> = state
+        .sources
+        .list()
+        .await?
+        .into_iter()
+        .map(|source| source.redacted())
+        .collect();
+
+    Ok(Json(ApiResponse::success(sources)))
+}
+
+/// GET /v1/sources/:id - Get a pull source
+async fn get_source_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    caller: Option<AuthUser>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "manage pull sources")?;
+    let source = state.sources.get(id).await?;
+
+    Ok(Json(ApiResponse::success(source.redacted())))
+}
+
+/// PUT /v1/sources/:id - Replace a pull source's definition
+async fn update_source_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    caller: Option<AuthUser>,
+    Json(definition): Json<SourceDefinition>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "manage pull sources")?;
+    let source = state.sources.update(id, definition).await?;
+
+    Ok(Json(ApiResponse::success(source.redacted())))
+}
+
+/// DELETE /v1/sources/:id - Delete a pull source and its runs
+async fn delete_source_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    caller: Option<AuthUser>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "manage pull sources")?;
+    state.sources.delete(id).await?;
+    info!("🗑️ Deleted source {}", id);
+
+    Ok(Json(json!({
+        "success": true,
+        "message": format!("Source {} deleted", id)
+    })))
+}
+
+/// POST /v1/sources/:id/run - Run a source now, outside its schedule
+async fn run_source_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    caller: Option<AuthUser>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "manage pull sources")?;
+    let run = state.sources.run(&state, id).await?;
+
+    Ok(Json(ApiResponse::success(run)))
+}
+
+/// Query parameters for listing source runs
+#[derive(Debug, Deserialize)]
+pub struct SourceRunsParams {
+    pub limit: Option<u32>,
+}
+
+/// GET /v1/sources/:id/runs - Recent runs of a source, newest first
+async fn source_runs_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    caller: Option<AuthUser>,
+    Query(params): Query<SourceRunsParams>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "manage pull sources")?;
+    let runs = state.sources.runs(id, params.limit.unwrap_or(20).min(100)).await?;
+
+    Ok(Json(ApiResponse::success(runs)))
+}
+
+/// GET /v1/dead-letters - Rejected pushes, newest first
+///
+/// Filters: `collection`, `error_code`, `resolved` and `limit` (max 1000).
+async fn list_dead_letters_handler(
+    State(state): State<AppState>,
+    caller: Option<AuthUser>,
+    Query(filter): Query<DeadLetterFilter>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "manage dead letters")?;
+    let letters = state.dead_letters.list(&filter).await?;
+
+    Ok(Json(ApiResponse::success(letters)))
+}
+
+/// GET /v1/dead-letters/:id - Get a dead letter
+async fn get_dead_letter_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    caller: Option<AuthUser>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "manage dead letters")?;
+    let letter = state.dead_letters.get(id).await?;
+
+    Ok(Json(ApiResponse::success(letter)))
+}
+
+/// POST /v1/dead-letters/:id/retry - Push a dead letter's payload again
+///
+/// Goes through the normal push path as the retrying caller, honouring
+/// `?allow_timestamps=true` like a push. On success the dead letter is
+/// resolved; on failure it keeps the new error. A retry of a dead letter
+/// that is already being retried fails with 409.
+async fn retry_dead_letter_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    caller: Option<AuthUser>,
+    Query(params): Query<PushParams>,
+) -> Result<impl IntoResponse, VibeError> {
+    require_admin(&state, caller.as_ref(), "manage dead letters")?;
+    reject_batch_inference(&params)?;
+    let (letter, claim) = state.dead_letters.begin_retry(id).await?;
+
+    let pushed = push_document(
+        &state,
+        &letter.collection,
+        caller.as_ref(),
+        letter.payload,
+        &params,
+    )
+    .await;
+    match pushed {
+        Ok(pushed) => {
+            let letter = state.dead_letters.resolve(claim, pushed.id).await?;
+            info!("📮 Dead letter {} resolved as {}#{}", id, pushed.collection, pushed.id);
+            Ok(Json(ApiResponse::success(json!({
+                "dead_letter": letter,
+                "push": PushResponse { timings: None, ..pushed }
+            }))))
+        }
+        Err(err) => {
+            state.dead_letters.record_retry_failure(claim, &err).await?;
+            Err(err)
+        }
+    }
+}
+
+/// Query parameters for exports
+#[derive(Debug, Default, Deserialize)]
+pub struct ExportParams {
+    /// Comma-separated collections; every collection when absent
+    pub tables: Option<String>,
+    /// Fields to export, as in queries; needs `tables` to name one collection
+    pub select: Option<String>,
+    /// `cast.<column>=<type>` hints, applied to that column in every table
+    #[serde(flatten)]
+    pub hints: HashMap<String, String>,
+}
+
+/// GET /v1/export - Collections as NDJSON, read at one point in time
+///
+/// The first line describes the snapshot (`schema_version`, `tables`,
+/// `exported_at`), then every row follows as `{"type": "row", "table",
+/// "row"}`, and an `{"type": "end", "rows": n}` line closes a complete
+/// export. All tables come from one read transaction (see
+/// `VibeStore::read_snapshot`), so writes made during the export are
+/// either in every table or in none. `cast.<column>=<type>` hints convert
+/// that column's values wherever it appears, and `select` shapes the rows
+/// of a single exported collection like it does query results. With
+/// `X-Vibe-Snapshot`, the export is read from that snapshot instead.
+/// Admin-only when auth is enabled.
+async fn export_handler(
+    State(state): State<AppState>,
+    caller: Option<AuthUser>,
+    headers: HeaderMap,
+    Query(params): Query<ExportParams>,
+) -> Result<Response, VibeError> {
+    require_admin(&state, caller.as_ref(), "export the database")?;
+    let tables = match params.tables.as_deref() {
+        Some(list) => {
+            let mut tables = Vec::new();
+            for name in list.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+                let table = state.guard.resolve_table(name, false).await?;
+                SchemaGuard::validate_identifier(&table)?;
+                tables.push(table);
+            }
+            Some(tables)
+        }
+        None => None,
+    };
+    info!(
+        "📤 Exporting {} (by {})",
+        params.tables.as_deref().unwrap_or("all collections"),
+        AuthUser::label(caller.as_ref())
+    );
+
+    let mut shape = SnapshotShape {
+        casts: cast_hints(&params.hints)?,
+        ..Default::default()
+    };
+    if let Some(select) = &params.select {
+        let [table] = tables.as_deref().unwrap_or_default() else {
+            return Err(VibeError::InvalidPayload(
+                "select needs tables to name exactly one collection".to_string(),
+            ));
+        };
+        let stats = state.guard.get_table_stats(table).await?;
+        let columns: Vec<String> = stats.columns.into_iter().map(|c| c.name).collect();
+        let projection =
+            shaped_projection(&state, table, select, &columns, &shape.casts, &ColumnMask::default()).await?;
+        shape.projections.insert(table.clone(), projection);
+    }
+    let capacity = state.config.query.stream_buffer_rows;
+    let mut rx = match request_snapshot(&state, &headers, caller.as_ref())? {
+        Some(session) => session.read_snapshot(tables, shape, capacity),
+        None => state.store.read_snapshot(tables, shape, capacity),
+    };
+    // Fail with a status code when the snapshot cannot even start
+    let begin = match rx.recv().await {
+        Some(Ok(SnapshotItem::Begin { schema_version, tables })) => json!({
+            "type": "snapshot",
+            "schema_version": schema_version,
+            "tables": tables,
+            "exported_at": audit::now_timestamp()
+        }),
+        Some(Err(e)) => return Err(e),
+        _ => return Err(VibeError::Database("Snapshot ended before it began".to_string())),
+    };
+
+    let body = async_stream::stream! {
+        yield Ok::<_, std::io::Error>(Bytes::from(format!("{}\n", begin)));
+        let mut rows = 0u64;
+        while let Some(item) = rx.recv().await {
+            match item {
+                Ok(SnapshotItem::Row { table, row }) => {
+                    rows += 1;
+                    let line = json!({"type": "row", "table": table, "row": row_to_json(row)});
+                    yield Ok(Bytes::from(format!("{}\n", line)));
+                }
+                Ok(SnapshotItem::Begin { .. }) => {}
+                Err(e) => {
+                    warn!("Export aborted: {}", e);
+                    yield Err(std::io::Error::other(e.to_string()));
+                    return;
+                }
+            }
+        }
+        yield Ok(Bytes::from(format!("{}\n", json!({"type": "end", "rows": rows}))));
+    };
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, NDJSON_CONTENT_TYPE)
+        .body(Body::from_stream(body))
+        .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response()))
+}
+
+/// Session of the snapshot named by a request's `X-Vibe-Snapshot` header
+fn request_snapshot(
+    state: &AppState,
+    headers: &HeaderMap,
+    caller: Option<&AuthUser>,
+) -> Result<Option<Arc<ReadSession>>, VibeError> {
+    let Some(value) = headers.get(SNAPSHOT_HEADER) else {
+        return Ok(None);
+    };
+    let id = value
+        .to_str()
+        .map_err(|_| VibeError::InvalidPayload("Invalid X-Vibe-Snapshot header".to_string()))?;
+    state.snapshots.get(id.trim(), caller).map(Some)
+}
+
+/// Request body for beginning a snapshot
+#[derive(Debug, Default, Deserialize)]
+pub struct SnapshotRequest {
+    /// Seconds until the snapshot ends on its own
+    pub ttl_secs: Option<u64>,
+}
+
+/// POST /v1/snapshots - Begin a snapshot
+///
+/// Returns the id to send as `X-Vibe-Snapshot` and when the snapshot
+/// expires. Responds 429 while `snapshots.max_open` are open.
+async fn create_snapshot_handler(
+    State(state): State<AppState>,
+    caller: Option<AuthUser>,
+    payload: Option<Json<SnapshotRequest>>,
+) -> Result<impl IntoResponse, VibeError> {
+    let ttl = payload.and_then(|Json(p)| p.ttl_secs).map(Duration::from_secs);
+    let snapshot = state.snapshots.open(ttl, caller.as_ref()).await?;
+    info!(
+        "📸 Snapshot {} opened for {}s (by {})",
+        snapshot.id,
+        snapshot.ttl_secs,
+        AuthUser::label(caller.as_ref())
+    );
+
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(snapshot))))
+}
+
+/// DELETE /v1/snapshots/:id - End a snapshot
+async fn release_snapshot_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    caller: Option<AuthUser>,
+) -> Result<impl IntoResponse, VibeError> {
+    state.snapshots.release(&id, caller.as_ref())?;
+    info!("📸 Snapshot {} released", id);
+
+    Ok(Json(json!({
+        "success": true,
+        "message": format!("Snapshot {} released", id)
+    })))
+}
+
+/// Query parameters for listing SQL proposals
+#[derive(Debug, Deserialize)]
+pub struct ProposalListParams {
+    pub status: Option<String>,
+}
+
+/// Request body for rejecting a SQL proposal
+#[derive(Debug, Default, Deserialize)]
+pub struct RejectProposalRequest {
+    pub reason: Option<String>,
+}
+
+/// POST /v1/sql/propose - Submit a statement for admin approval
+async fn propose_sql_handler(
+    State(state): State<AppState>,
+    caller: Option<AuthUser>,
+    Json(payload): Json<SqlRequest>,
+) -> Result<impl IntoResponse, VibeError> {
+    if !state.config.sql.approval_required {
+        return Err(VibeError::InvalidPayload(
+            "The SQL approval workflow is not enabled".to_string(),
+        ));
+    }
+    let proposer =
+        caller.ok_or_else(|| VibeError::Unauthorized("Missing or invalid token".to_string()))?;
+    // Runs only once an admin approves it
+    state.sql_policy.screen_single(&payload.query, SqlAccess::Admin)?;
+
+    let proposal = state.proposals.create(&payload.query, &proposer).await?;
+    info!("📝 SQL proposal {} submitted by {}", proposal.id, proposer.email);
+
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(proposal))))
+}
+
+/// GET /v1/sql/proposals - List proposals (admins see all, users their own)
+async fn list_proposals_handler(
+    State(state): State<AppState>,
+    caller: Option<AuthUser>,
+    Query(params): Query<ProposalListParams>,
+) -> Result<impl IntoResponse, VibeError> {
+    let caller =
+        caller.ok_or_else(|| VibeError::Unauthorized("Missing or invalid token".to_string()))?;
+    let proposer = (!caller.is_admin()).then_some(caller.id);
+    let proposals = state
+        .proposals
+        .list(params.status.as_deref(), proposer)
+        .await?;
+
+    Ok(Json(ApiResponse::success(proposals)))
+}
+
+/// GET /v1/sql/proposals/:id - Get a proposal and its result
+async fn get_proposal_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    caller: Option<AuthUser>,
+) -> Result<impl IntoResponse, VibeError> {
+    let caller =
+        caller.ok_or_else(|| VibeError::Unauthorized("Missing or invalid token".to_string()))?;
+    let proposal = state.proposals.get(id).await?;
+    if !proposal.visible_to(&caller) {
+        return Err(VibeError::NotFound(format!("SQL proposal {} not found", id)));
+    }
+
+    Ok(Json(ApiResponse::success(proposal)))
+}
+
+/// POST /v1/sql/proposals/:id/approve - Approve and execute a proposal
+///
+/// The statement is screened again against the current policy before the
+/// proposal is claimed, and runs at most once.
+async fn approve_proposal_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    caller: Option<AuthUser>,
+) -> Result<impl IntoResponse, VibeError> {
+    let admin = require_admin_user(caller.as_ref(), "approve SQL proposals")?;
+    let proposal = state.proposals.get(id).await?;
+    state.sql_policy.screen_single(&proposal.statement, SqlAccess::Admin)?;
+
+    let proposal = state.proposals.approve(id, admin).await?;
+    let outcome = state.store.execute_simple(proposal.statement.clone()).await;
+    state.row_cache.clear();
+    let proposal = state.proposals.record_outcome(id, &outcome).await?;
+
+    if proposal.status == STATUS_EXECUTED {
+        info!(
+            target: "vibedb::security",
+            "SQL proposal {} by {} approved and executed by {}: {}",
+            id, proposal.proposer_email, admin.email, proposal.statement
+        );
+    } else {
+        warn!(
+            target: "vibedb::security",
+            "SQL proposal {} by {} approved by {} failed: {}",
+            id, proposal.proposer_email, admin.email, proposal.error.as_deref().unwrap_or_default()
+        );
+    }
+
+    Ok(Json(ApiResponse::success(proposal)))
+}
+
+/// POST /v1/sql/proposals/:id/reject - Reject a pending proposal
+async fn reject_proposal_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    caller: Option<AuthUser>,
+    payload: Option<Json<RejectProposalRequest>>,
+) -> Result<impl IntoResponse, VibeError> {
+    let admin = require_admin_user(caller.as_ref(), "reject SQL proposals")?;
+    let reason = payload.and_then(|Json(p)| p.reason);
+    let proposal = state.proposals.reject(id, admin, reason).await?;
+    info!(target: "vibedb::security", "SQL proposal {} rejected by {}", id, admin.email);
+
+    Ok(Json(ApiResponse::success(proposal)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::MAX_IN_LIST;
+    use crate::guard::KeyCase;
+    use crate::limits::RequestClass;
+    use crate::auth::{ADMIN_ROLE, USER_ROLE};
+    use crate::testing::{ManualClock, TestServer};
+    use axum::http::Method;
+
+    #[tokio::test]
+    async fn test_health_endpoint() {
+        let server = TestServer::spawn().await;
+
+        let health = server.get("/health").send().await.assert_status(StatusCode::OK).json();
+        assert_eq!(health["explorer"], json!({"mode": "embedded"}));
+    }
+
+    #[tokio::test]
+    async fn test_pragmas_are_reported_and_tunable() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(VibeStore::new(dir.path().join("tuned.db")).await.unwrap());
+        let server = TestServer::builder().store(store).spawn().await;
+        let admin = server.create_user("admin@example.com", ADMIN_ROLE).await;
+        let user = server.create_user("user@example.com", USER_ROLE).await;
+
+        server
+            .get("/v1/admin/pragmas")
+            .bearer(&user.token)
+            .send()
+            .await
+            .assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+        let pragmas = server.get("/v1/admin/pragmas").bearer(&admin.token).send().await.assert_success();
+        assert_eq!(pragmas["journal_mode"], "wal");
+        assert_eq!(pragmas["synchronous"], "NORMAL");
+        assert_eq!(pragmas["cache_size"], -64000);
+        assert!(pragmas["page_count"].as_i64().unwrap() > 0);
+
+        for invalid in [
+            json!({"journal_mode": "delete"}),
+            json!({"synchronous": "sometimes"}),
+            json!({"cache_size": 0}),
+            json!({"busy_timeout": 3_600_000}),
+        ] {
+            server
+                .post("/v1/admin/pragmas")
+                .bearer(&admin.token)
+                .json(invalid)
+                .send()
+                .await
+                .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        }
+        let pragmas = server
+            .post("/v1/admin/pragmas")
+            .bearer(&admin.token)
+            .json(json!({"cache_size": -8000, "synchronous": "full", "busy_timeout": 2500, "wal_autocheckpoint": 500}))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(pragmas["cache_size"], -8000);
+        assert_eq!(pragmas["synchronous"], "FULL");
+        assert_eq!(pragmas["busy_timeout"], 2500);
+        assert_eq!(pragmas["wal_autocheckpoint"], 500);
+        assert_eq!(pragmas["journal_mode"], "wal");
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_on_demand() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(VibeStore::new(dir.path().join("wal.db")).await.unwrap());
+        let server = TestServer::builder().store(store).spawn().await;
+        let admin = server.create_user("admin@example.com", ADMIN_ROLE).await;
+        let user = server.create_user("user@example.com", USER_ROLE).await;
+        server.post("/v1/push/events").bearer(&admin.token).json(json!({"n": 1})).send().await.assert_success();
+
+        server
+            .post("/v1/admin/checkpoint")
+            .bearer(&user.token)
+            .json(json!({"mode": "truncate"}))
+            .send()
+            .await
+            .assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+        let checkpoint = server
+            .post("/v1/admin/checkpoint")
+            .bearer(&admin.token)
+            .json(json!({"mode": "truncate"}))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(checkpoint["mode"], "truncate");
+        assert_eq!(checkpoint["busy"], false);
+        assert_eq!(std::fs::metadata(dir.path().join("wal.db-wal")).unwrap().len(), 0);
+        let checkpoint = server.post("/v1/admin/checkpoint").bearer(&admin.token).json(json!({})).send().await.assert_success();
+        assert_eq!(checkpoint["mode"], "passive");
+        assert!(checkpoint["log_pages"].is_i64() && checkpoint["checkpointed_pages"].is_i64());
+        server
+            .post("/v1/admin/checkpoint")
+            .bearer(&admin.token)
+            .json(json!({"mode": "eventually"}))
+            .send()
+            .await
+            .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+        let memory = TestServer::builder().anonymous().spawn().await;
+        memory
+            .post("/v1/admin/checkpoint")
+            .json(json!({"mode": "full"}))
+            .send()
+            .await
+            .assert_error(StatusCode::CONFLICT, "CONFLICT");
+    }
+
+    #[tokio::test]
+    async fn test_index_suggestions_follow_query_patterns() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        server
+            .post("/v1/push/orders")
+            .json(json!({"status": "open", "email": "a@example.com", "total": 3, "tags": "[]"}))
+            .send()
+            .await
+            .assert_success();
+        server
+            .store
+            .execute("CREATE INDEX orders_email ON orders (email)".to_string(), Vec::new())
+            .await
+            .unwrap();
+
+        for _ in 0..3 {
+            server.get("/v1/query/orders?status=open&email=a@example.com&order_by=total").send().await.assert_success();
+        }
+        server.get("/v1/query/orders?tags[contains]=x&total[null]=false").send().await.assert_success();
+        // Failed queries and unknown columns aren't counted
+        for _ in 0..3 {
+            server
+                .get("/v1/query/orders?status=open&missing=1")
+                .send()
+                .await
+                .assert_error(StatusCode::SERVICE_UNAVAILABLE, "DATABASE_ERROR");
+            server
+                .get("/v1/query/orders?status=open&order_by=total&order_dir=sideways")
+                .send()
+                .await
+                .assert_status(StatusCode::BAD_REQUEST);
+        }
+
+        let body = server.get("/v1/admin/index-suggestions?min_uses=3").send().await.assert_success();
+        assert_eq!(body["min_uses"], 3);
+        assert_eq!(
+            body["suggestions"],
+            json!([
+                {"collection": "orders", "column": "total", "filter_uses": 1, "order_by_uses": 3,
+                 "sql": "CREATE INDEX idx_orders_total ON orders (total)"},
+                {"collection": "orders", "column": "status", "filter_uses": 3, "order_by_uses": 0,
+                 "sql": "CREATE INDEX idx_orders_status ON orders (status)"},
+            ])
+        );
+        let body = server.get("/v1/admin/index-suggestions").send().await.assert_success();
+        assert_eq!(body["suggestions"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_push_timings_and_migration_metrics() {
+        let server = TestServer::builder().anonymous().spawn().await;
+
+        let pushed = server
+            .post("/v1/push/sensors")
+            .header(TIMINGS_HEADER, "1")
+            .json(json!({"reading": 1, "unit": "C"}))
+            .send()
+            .await
+            .assert_success();
+        let timings = &pushed["timings"];
+        // CREATE TABLE, then one ALTER TABLE per column
+        assert_eq!(timings["ddl_statements"], 3);
+        assert!(timings["guard_ms"].as_f64().unwrap() >= timings["ddl_ms"].as_f64().unwrap());
+        assert!(timings["insert_ms"].is_f64());
+
+        let pushed = server
+            .post("/v1/push/sensors")
+            .header(TIMINGS_HEADER, "1")
+            .json(json!({"reading": 2}))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(pushed["timings"]["ddl_statements"], 0);
+        let pushed = server.post("/v1/push/sensors").json(json!({"reading": 3})).send().await.assert_success();
+        assert!(pushed.get("timings").is_none());
+
+        let metrics = server.get("/metrics").send().await.text();
+        assert!(metrics.contains("vibedb_migrations_total{collection=\"sensors\"} 2"), "{}", metrics);
+        assert!(metrics.contains("vibedb_migration_duration_seconds_count{collection=\"sensors\"} 2"));
+        assert!(metrics.contains("vibedb_schema_cache_hit_ratio "));
+        let hits = metrics
+            .lines()
+            .find_map(|line| line.strip_prefix("vibedb_schema_cache_hits_total "))
+            .and_then(|value| value.parse::<u64>().ok())
+            .unwrap();
+        assert!(hits > 0);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limits_are_reported_and_adjustable() {
+        let server = TestServer::spawn().await;
+        let admin = server.create_user("admin@example.com", ADMIN_ROLE).await;
+        let user = server.create_user("user@example.com", USER_ROLE).await;
+
+        let health = server.get("/health").send().await.assert_status(StatusCode::OK).json();
+        assert_eq!(health["concurrency"]["write"]["limit"], 256);
+        assert_eq!(health["concurrency"]["read"]["in_flight"], 0);
+        let metrics = server.get("/metrics").send().await.text();
+        assert!(metrics.contains("vibedb_requests_in_flight{class=\"write\"} 0"));
+        assert!(metrics.contains("vibedb_request_limit{class=\"upload\"} 32"));
+
+        let update = json!({"writes": 8, "acquire_timeout_ms": 20});
+        server
+            .put("/v1/admin/concurrency")
+            .bearer(&user.token)
+            .json(update.clone())
+            .send()
+            .await
+            .assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+        server
+            .put("/v1/admin/concurrency")
+            .bearer(&admin.token)
+            .json(json!({"reads": 0}))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        let stats = server.put("/v1/admin/concurrency").bearer(&admin.token).json(update).send().await.assert_success();
+        assert_eq!(stats["write"]["limit"], 8);
+        assert_eq!(stats["read"]["limit"], 512);
+        assert_eq!(stats["acquire_timeout_ms"], 20);
+
+        // The admin request itself holds a read slot
+        let stats = server.get("/v1/admin/concurrency").bearer(&admin.token).send().await.assert_success();
+        assert_eq!(stats["read"]["in_flight"], 1);
+        assert_eq!(stats["global"]["in_flight"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limits_under_a_base_path() {
+        let mut config = VibeConfig::default();
+        config.base_path.prefix = "/vibedb".to_string();
+        config.concurrency.global = 1;
+        config.concurrency.uploads = 1;
+        config.concurrency.acquire_timeout = Duration::from_millis(20);
+        let server = TestServer::builder().anonymous().config(config).spawn().await;
+        let _held = server.state.limits.acquire(RequestClass::Upload).await.unwrap();
+
+        // Probes are never throttled
+        server.get("/vibedb/health/live").send().await.assert_status(StatusCode::OK);
+        server.get("/vibedb/metrics").send().await.assert_status(StatusCode::OK);
+
+        // Uploads count against the upload class
+        let error = server
+            .post("/vibedb/v1/storage/upload/a.txt")
+            .json(json!({}))
+            .send()
+            .await
+            .assert_error(StatusCode::SERVICE_UNAVAILABLE, "OVERLOADED");
+        assert_eq!(error["details"]["class"], "upload");
+    }
+
+    #[tokio::test]
+    async fn test_liveness_survives_database_outage() {
+        let server = TestServer::spawn().await;
+        server.store.conn().close().await.unwrap();
+
+        let status = |uri: &'static str| {
+            let request = server.get(uri);
+            async move { request.send().await.status }
+        };
+
+        assert_eq!(status("/health/live").await, StatusCode::OK);
+        assert_eq!(status("/health/ready").await, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status("/health").await, StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_readiness_fails_when_reopen_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ready.db");
+        let store = VibeStore::new(&path).await.unwrap().with_reopen_policy(crate::db::ReopenPolicy {
+            fault_threshold: 1,
+            ..Default::default()
+        });
+        store.execute_batch("PRAGMA journal_mode=DELETE".to_string()).await.unwrap();
+        let server = TestServer::builder().store(Arc::new(store)).anonymous().spawn().await;
+        server.post("/v1/push/events").json(json!({"n": 1})).send().await.assert_success();
+        let ready = server.get("/health/ready").send().await.assert_status(StatusCode::OK).json();
+        assert_eq!(ready["watchdog"]["healthy"], true);
+
+        // The file is deleted and the path can't be reopened
+        std::fs::remove_file(&path).unwrap();
+        std::fs::create_dir(&path).unwrap();
+        server.post("/v1/push/events").json(json!({"n": 2})).send().await.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+
+        let ready = server.get("/health/ready").send().await.assert_status(StatusCode::SERVICE_UNAVAILABLE).json();
+        assert_eq!(ready["watchdog"]["reopen_failures"], 1);
+        let metrics = server.get("/metrics").send().await.text();
+        assert!(metrics.contains("vibedb_store_reopen_failures_total 1"));
+    }
+
+    #[tokio::test]
+    async fn test_readiness_is_degraded_not_stuck_behind_a_long_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(VibeStore::new(dir.path().join("busy.db")).await.unwrap());
+        let server = TestServer::builder().store(Arc::clone(&store)).anonymous().spawn().await;
+        let ready = server.get("/health/ready").send().await.assert_status(StatusCode::OK).json();
+        assert_eq!(ready["status"], "healthy");
+
+        // Occupy the main connection the way a long write would
+        let conn = store.conn();
+        let writer = tokio::spawn(async move {
+            conn.call(|conn| {
+                conn.execute_batch("BEGIN IMMEDIATE; CREATE TABLE slow (n INTEGER)")?;
+                std::thread::sleep(Duration::from_millis(1500));
+                conn.execute_batch("COMMIT")?;
+                Ok(())
+            })
+            .await
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let started = Instant::now();
+        let ready = server.get("/health/ready").send().await.assert_status(StatusCode::OK).json();
+        assert!(started.elapsed() < Duration::from_secs(1), "{:?}", started.elapsed());
+        assert_eq!(ready["status"], "degraded");
+        assert_eq!(ready["database"], "busy");
+        writer.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_push_and_query() {
+        let server = TestServer::spawn().await;
+
+        // Push data
+        server
+            .post("/v1/push/users")
+            .json(json!({"name": "Alice", "age": 30}))
+            .send()
+            .await
+            .assert_status(StatusCode::CREATED);
+
+        // Query data
+        let data = server.get("/v1/query/users").send().await.assert_success();
+        assert_eq!(data[0]["name"], "Alice");
+    }
+
+    #[tokio::test]
+    async fn test_query_ndjson_stream() {
+        let server = TestServer::spawn().await;
+
+        let batch: Vec<Value> = (0..250).map(|i| json!({"n": i})).collect();
+        server
+            .post("/v1/push/events/batch")
+            .json(Value::Array(batch))
+            .send()
+            .await
+            .assert_status(StatusCode::CREATED);
+
+        let response = server
+            .get("/v1/query/events?stream=true")
+            .send()
+            .await
+            .assert_status(StatusCode::OK);
+        assert_eq!(
+            response.headers.get(header::CONTENT_TYPE).unwrap(),
+            NDJSON_CONTENT_TYPE
+        );
+        // Streams are not subject to the default 100-row page
+        assert_eq!(response.ndjson().len(), 250);
+    }
+
+    #[tokio::test]
+    async fn test_move_rows_between_collections() {
+        let server = TestServer::spawn().await;
+        let store = &server.store;
+
+        let tickets: Vec<Value> = (0..25)
+            .map(|i| json!({"title": format!("t{}", i), "status": if i % 5 == 0 { "open" } else { "resolved" }, "notes": "x"}))
+            .collect();
+        server
+            .post("/v1/push/tickets/batch")
+            .json(Value::Array(tickets))
+            .send()
+            .await
+            .assert_success();
+
+        // Small batches must still converge on the full set
+        let data = server
+            .post("/v1/tables/tickets/move-to/tickets_archive")
+            .json(json!({"where": {"status": "resolved"}, "create_target": true, "copy_schema": true, "batch_size": 3}))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(data["moved"], 20);
+        assert_eq!(data["batches"], 7);
+        assert_eq!(data["dropped_columns"], json!([]));
+
+        let count = |table: &str| {
+            let sql = format!("SELECT COUNT(*) FROM {}", table);
+            async move { store.query_simple(sql).await.unwrap()[0][0].1.as_i64().unwrap() }
+        };
+        assert_eq!(count("tickets").await, 5);
+        assert_eq!(count("tickets_archive").await, 20);
+
+        let archived = store
+            .query_simple("SELECT COUNT(*) FROM tickets_archive WHERE status = 'resolved' AND notes = 'x'".to_string())
+            .await
+            .unwrap();
+        assert_eq!(archived[0][0].1, json!(20));
+
+        // Nothing left to move
+        let data = server
+            .post("/v1/tables/tickets/move-to/tickets_archive")
+            .json(json!({"where": {"status": "resolved"}}))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(data["moved"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_sql_policy_rejects_attach() {
+        let server = TestServer::spawn().await;
+
+        server
+            .post("/v1/sql/execute")
+            .json(json!({"query": "ATTACH DATABASE '/tmp/evil.db' AS evil"}))
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_pragma_policy_depends_on_caller() {
+        let server = TestServer::spawn().await;
+        let admin = server.create_user("admin@vibedb.dev", ADMIN_ROLE).await.token;
+        let user = server.create_user("user@vibedb.dev", USER_ROLE).await.token;
+        server.post("/v1/push/notes").json(json!({"title": "a"})).send().await.assert_success();
+
+        let columns = server
+            .post("/v1/sql/query")
+            .bearer(&user)
+            .json(json!({"query": "PRAGMA table_info(notes)"}))
+            .send()
+            .await
+            .assert_success();
+        assert!(columns.as_array().is_some_and(|c| c.iter().any(|c| c["name"] == "title")));
+
+        let set_version = json!({"query": "PRAGMA user_version = 7"});
+        server
+            .post("/v1/sql/execute")
+            .bearer(&user)
+            .json(set_version.clone())
+            .send()
+            .await
+            .assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+        server.post("/v1/sql/execute").bearer(&admin).json(set_version).send().await.assert_success();
+        let version = server
+            .post("/v1/sql/query")
+            .bearer(&user)
+            .json(json!({"query": "PRAGMA user_version"}))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(version[0]["user_version"], 7);
+
+        let error = server
+            .post("/v1/sql/execute")
+            .bearer(&admin)
+            .json(json!({"query": "PRAGMA synchronous = OFF"}))
+            .send()
+            .await
+            .assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+        assert!(error["message"].as_str().unwrap().contains("configured by the server"));
+    }
+
+    #[tokio::test]
+    async fn test_sql_query_refuses_writes() {
+        let server = TestServer::spawn().await;
+        let admin = server.create_user("admin@vibedb.dev", ADMIN_ROLE).await.token;
+        let user = server.create_user("user@vibedb.dev", USER_ROLE).await.token;
+        server.post("/v1/push/notes").bearer(&admin).json(json!({"title": "a"})).send().await.assert_success();
+
+        for query in ["INSERT INTO notes (title) VALUES ('b')", "DELETE FROM notes", "DROP TABLE notes"] {
+            for token in [&user, &admin] {
+                server
+                    .post("/v1/sql/query")
+                    .bearer(token)
+                    .json(json!({"query": query}))
+                    .send()
+                    .await
+                    .assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+            }
+        }
+        let rows = server.get("/v1/query/notes").bearer(&admin).send().await.assert_success();
+        assert_eq!(rows.as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_hidden_columns_masked_for_non_admin() {
+        let server = TestServer::spawn().await;
+        let admin = server.create_user("admin@vibedb.dev", ADMIN_ROLE).await.token;
+        let user = server.create_user("user@vibedb.dev", USER_ROLE).await.token;
+
+        let send = |method: Method, uri: &str, token: &str, body: Option<Value>| {
+            let mut request = server.request(method, uri).bearer(token);
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+            async move {
+                let response = request.send().await;
+                (response.status, response.json())
+            }
+        };
+
+        let settings = json!({"hidden_columns": ["cost_price"], "visible_to": "admin"});
+        let (status, _) = send(Method::PUT, "/v1/tables/products/settings", &user, Some(settings.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(Method::PUT, "/v1/tables/products/settings", &admin, Some(settings)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _) = send(
+            Method::POST,
+            "/v1/push/products",
+            &admin,
+            Some(json!({"name": "widget", "cost_price": 3})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        // Regular users neither see, filter by, nor write the column
+        let (status, body) = send(Method::GET, "/v1/query/products", &user, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"][0]["name"], "widget");
+        assert!(body["data"][0].get("cost_price").is_none());
+        let (status, _) = send(Method::GET, "/v1/query/products?cost_price=3", &user, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(
+            Method::POST,
+            "/v1/update/products/1",
+            &user,
+            Some(json!({"cost_price": 0})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let sql = json!({"query": "SELECT cost_price FROM products"});
+        let (status, _) = send(Method::POST, "/v1/sql/query", &user, Some(sql.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // Admins see everything
+        let (status, body) = send(Method::POST, "/v1/sql/query", &admin, Some(sql)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"][0]["cost_price"], 3);
+        let (status, body) = send(Method::GET, "/v1/query/products?cost_price=3", &admin, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"][0]["cost_price"], 3);
+        let (_, body) = send(Method::GET, "/v1/query/products/1", &admin, None).await;
+        assert_eq!(body["data"]["cost_price"], 3);
+
+        // Nor move rows somewhere the column isn't hidden
+        let request = json!({"where": {"name": "widget"}, "create_target": true, "copy_schema": true});
+        let (status, _) = send(Method::POST, "/v1/tables/products/move-to/leak", &user, Some(request)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(Method::GET, "/v1/query/leak?cost_price=3", &user, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (_, body) = send(Method::GET, "/v1/query/products", &admin, None).await;
+        assert_eq!(body["data"][0]["cost_price"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_as_of_reads_follow_audit_history() {
+        use chrono::{SecondsFormat, Utc};
+
+        let server = TestServer::builder().anonymous().spawn().await;
+        let at = |minutes: i64| {
+            (Utc::now() + chrono::Duration::minutes(minutes))
+                .to_rfc3339_opts(SecondsFormat::Millis, true)
+        };
+        let call = |method: Method, uri: String, body: Option<Value>| {
+            let mut request = server.request(method, &uri);
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+            async move {
+                let response = request.send().await;
+                (response.status, response.json())
+            }
+        };
+
+        // Row 1 predates auditing and is never touched
+        call(Method::POST, "/v1/push/items".into(), Some(json!({"name": "legacy"}))).await;
+        let (status, _) = call(Method::GET, format!("/v1/query/items/1/asof?ts={}", at(0)), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        call(Method::PUT, "/v1/tables/items/settings".into(), Some(json!({"audit": true}))).await;
+
+        // Row 2: insert, two updates and a delete, backdated one hour apart
+        call(Method::POST, "/v1/push/items".into(), Some(json!({"name": "v1"}))).await;
+        call(Method::POST, "/v1/update/items/2".into(), Some(json!({"name": "v2"}))).await;
+        call(Method::POST, "/v1/update/items/2".into(), Some(json!({"name": "v3"}))).await;
+        call(Method::POST, "/v1/delete/items/2".into(), None).await;
+        for (audit_id, hours) in [(1, 1), (2, 2), (3, 3), (4, 4)] {
+            server
+                .store
+                .execute(
+                    "UPDATE vibe_audit SET changed_at = ? WHERE id = ?".to_string(),
+                    vec![SqlValue::Text(at(hours * 60)), SqlValue::Integer(audit_id)],
+                )
+                .await
+                .unwrap();
+        }
+
+        let row_at = |minutes: i64| call(Method::GET, format!("/v1/query/items/2/asof?ts={}", at(minutes)), None);
+        assert_eq!(row_at(30).await.0, StatusCode::NOT_FOUND);
+        assert_eq!(row_at(61).await.1["data"]["name"], "v1");
+        assert_eq!(row_at(150).await.1["data"]["name"], "v2");
+        assert_eq!(row_at(181).await.1["data"]["name"], "v3");
+        assert_eq!(row_at(241).await.0, StatusCode::NOT_FOUND);
+
+        // Before auditing began there is no history
+        let (status, _) = call(Method::GET, format!("/v1/query/items/2/asof?ts={}", at(-60)), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (_, body) = call(Method::GET, format!("/v1/query/items/asof?ts={}", at(150)), None).await;
+        let names: Vec<&str> = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["legacy", "v2"]);
+
+        let (_, body) = call(Method::GET, format!("/v1/query/items/asof?ts={}&name=v3", at(181)), None).await;
+        assert_eq!(body["count"], 1);
+        let (_, body) = call(Method::GET, format!("/v1/query/items/asof?ts={}", at(241)), None).await;
+        assert_eq!(body["count"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_filtered_delete_is_audited_atomically() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        server.put("/v1/tables/items/settings").json(json!({"audit": true})).send().await;
+        for name in ["a", "b", "c"] {
+            server.post("/v1/push/items").json(json!({"name": name, "kind": "x"})).send().await.assert_success();
+        }
+        server.post("/v1/delete/items?name=a").send().await.assert_success();
+        let entries = server
+            .store
+            .query("SELECT row_id FROM vibe_audit WHERE op = 'delete'".to_string(), vec![])
+            .await
+            .unwrap();
+        assert_eq!(entries, vec![vec![("row_id".to_string(), json!(1))]]);
+
+        // A failed audit insert rolls the delete back
+        server
+            .store
+            .execute_batch(
+                "CREATE TRIGGER no_audit BEFORE INSERT ON vibe_audit BEGIN SELECT RAISE(ABORT, 'no'); END;"
+                    .to_string(),
+            )
+            .await
+            .unwrap();
+        server.post("/v1/delete/items?kind=x").send().await;
+        let rows = server.get("/v1/query/items").send().await.assert_success();
+        assert_eq!(rows.as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_row_writes_are_audited_atomically() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        server.put("/v1/tables/items/settings").json(json!({"audit": true})).send().await;
+        server.post("/v1/push/items").json(json!({"name": "a", "count": 1})).send().await.assert_success();
+        let settings = json!({"audit": true, "batch_writes": true, "explode_arrays": {"parts": "item_parts"}});
+        server.put("/v1/tables/items/settings").json(settings).send().await.assert_success();
+        server.put("/v1/tables/readings/settings").json(json!({"audit": true})).send().await.assert_success();
+        server
+            .store
+            .execute_batch(
+                "CREATE TRIGGER no_audit BEFORE INSERT ON vibe_audit BEGIN SELECT RAISE(ABORT, 'no'); END;"
+                    .to_string(),
+            )
+            .await
+            .unwrap();
+
+        // Each write fails with its audit entry and leaves the row as it was
+        server
+            .post("/v1/update/items/1")
+            .json(json!({"name": "b"}))
+            .send()
+            .await
+            .assert_status(StatusCode::CONFLICT);
+        server
+            .post("/v1/update/items/1/increment")
+            .json(json!({"field": "count", "by": 1}))
+            .send()
+            .await
+            .assert_status(StatusCode::CONFLICT);
+        server.post("/v1/delete/items/1").send().await.assert_status(StatusCode::CONFLICT);
+        let row = server.get("/v1/query/items/1").send().await.assert_success();
+        assert_eq!((row["name"].clone(), row["count"].clone()), (json!("a"), json!(1)));
+
+        // Batched, exploded, batch and pulled inserts leave no row behind
+        server.post("/v1/push/items").json(json!({"name": "c"})).send().await.assert_status(StatusCode::CONFLICT);
+        server
+            .post("/v1/push/items")
+            .json(json!({"name": "d", "parts": [{"sku": "x"}]}))
+            .send()
+            .await
+            .assert_status(StatusCode::CONFLICT);
+        server
+            .post("/v1/push/items/batch")
+            .json(json!([{"name": "e"}]))
+            .send()
+            .await
+            .assert_status(StatusCode::CONFLICT);
+        let rows = server.get("/v1/query/items").send().await.assert_success();
+        assert_eq!(rows.as_array().unwrap().len(), 1);
+        let parts = server.get("/v1/query/item_parts").send().await.assert_success();
+        assert_eq!(parts.as_array().unwrap().len(), 0);
+
+        let feed = spawn_feed().await;
+        let source = server
+            .post("/v1/sources")
+            .json(json!({
+                "url": format!("{}/feed", feed),
+                "headers": {"x-api-key": "s3cret"},
+                "interval_secs": 3600,
+                "collection": "readings",
+                "records_pointer": "/data",
+                "fields": {"station": "station", "ts": "observed_at"}
+            }))
+            .send()
+            .await
+            .assert_success();
+        let run = server
+            .post(&format!("/v1/sources/{}/run", source["id"]))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(run["inserted"], 0);
+        let rows = server.get("/v1/query/readings").send().await.assert_success();
+        assert_eq!(rows.as_array().unwrap().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_moves_are_audited_on_both_sides() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        for collection in ["tickets", "archive"] {
+            server.put(&format!("/v1/tables/{}/settings", collection)).json(json!({"audit": true})).send().await;
+        }
+        server.post("/v1/push/archive").json(json!({"status": "old"})).send().await.assert_success();
+        for status in ["open", "closed", "closed"] {
+            server.post("/v1/push/tickets").json(json!({"status": status})).send().await.assert_success();
+        }
+        let before = audit::now_timestamp();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        let moved = server
+            .post("/v1/tables/tickets/move-to/archive")
+            .json(json!({"where": {"status": "closed"}}))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(moved["moved"], 2);
+
+        // At `before` the rows were still tickets and not yet archived
+        let count = |uri: String| {
+            let request = server.get(&uri);
+            async move { request.send().await.assert_success().as_array().unwrap().len() }
+        };
+        assert_eq!(count(format!("/v1/query/tickets/asof?ts={}", before)).await, 3);
+        assert_eq!(count(format!("/v1/query/archive/asof?ts={}", before)).await, 1);
+        let now = audit::now_timestamp();
+        assert_eq!(count(format!("/v1/query/tickets/asof?ts={}", now)).await, 1);
+        assert_eq!(count(format!("/v1/query/archive/asof?ts={}", now)).await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_as_of_queries_filter_and_bound_audited_rows() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        server.put("/v1/tables/items/settings").json(json!({"audit": true})).send().await;
+        for i in 0..6 {
+            let kind = if i % 2 == 0 { "even" } else { "odd" };
+            server.post("/v1/push/items").json(json!({"kind": kind, "n": i, "flag": i < 3})).send().await.assert_success();
+        }
+        let ts = audit::now_timestamp();
+
+        let rows = server.get(&format!("/v1/query/items/asof?ts={}&kind=even&limit=2", ts)).send().await.json();
+        let ids: Vec<i64> = rows["data"].as_array().unwrap().iter().map(|r| r["id"].as_i64().unwrap()).collect();
+        assert_eq!(ids, vec![1, 3]);
+        let rows = server.get(&format!("/v1/query/items/asof?ts={}&kind=even&limit=2&offset=2", ts)).send().await.json();
+        assert_eq!(rows["data"][0]["id"], 5);
+        let rows = server.get(&format!("/v1/query/items/asof?ts={}&n=4&flag=0", ts)).send().await.json();
+        assert_eq!(rows["count"], 1);
+        assert_eq!(rows["data"][0]["id"], 5);
+    }
+
+    #[tokio::test]
+    async fn test_sql_proposal_workflow() {
+        let mut config = VibeConfig::default();
+        config.sql.approval_required = true;
+        let server = TestServer::builder().config(config).spawn().await;
+        server
+            .store
+            .execute_batch("CREATE TABLE t (x INTEGER); INSERT INTO t VALUES (1);".to_string())
+            .await
+            .unwrap();
+        let admin = server.create_user("admin@vibedb.dev", ADMIN_ROLE).await.token;
+        let user = server.create_user("user@vibedb.dev", USER_ROLE).await.token;
+
+        let send = |method: Method, uri: String, token: &str, body: Option<Value>| {
+            let mut request = server.request(method, &uri).bearer(token);
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+            async move {
+                let response = request.send().await;
+                (response.status, response.json())
+            }
+        };
+        let statement = json!({"query": "UPDATE t SET x = 2"});
+
+        // Direct execution is admin-only; users have to propose
+        let (status, _) = send(Method::POST, "/v1/sql/execute".into(), &user, Some(statement.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(
+            Method::POST,
+            "/v1/sql/propose".into(),
+            &user,
+            Some(json!({"query": "UPDATE t SET x = 2; DROP TABLE t"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, body) = send(Method::POST, "/v1/sql/propose".into(), &user, Some(statement)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["data"]["status"], "pending");
+        let id = body["data"]["id"].as_i64().unwrap();
+        let x = || async { server.store.query_simple("SELECT x FROM t".to_string()).await.unwrap()[0][0].1.clone() };
+        assert_eq!(x().await, json!(1));
+
+        let (status, _) = send(Method::POST, format!("/v1/sql/proposals/{}/approve", id), &user, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) = send(Method::GET, "/v1/sql/proposals?status=pending".into(), &admin, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+
+        let (status, body) = send(Method::POST, format!("/v1/sql/proposals/{}/approve", id), &admin, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["status"], "executed");
+        assert_eq!(x().await, json!(2));
+
+        // The proposer sees the result; a second approval is rejected
+        let (status, body) = send(Method::GET, format!("/v1/sql/proposals/{}", id), &user, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["result"], json!({"affected": 1}));
+        assert_eq!(body["data"]["decided_by"], "admin@vibedb.dev");
+        let (status, _) = send(Method::POST, format!("/v1/sql/proposals/{}/approve", id), &admin, None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_exploded_arrays_round_trip() {
+        let server = TestServer::builder().anonymous().spawn().await;
+
+        let settings = json!({"explode_arrays": {"items": "order_items", "tags": "order_tags"}});
+        server
+            .put("/v1/tables/orders/settings")
+            .json(settings)
+            .send()
+            .await
+            .assert_status(StatusCode::OK);
+
+        let order = json!({
+            "order_no": 1,
+            "items": [{"sku": "a", "qty": 2}, {"sku": "b"}],
+            "tags": ["rush"]
+        });
+        let data = server
+            .post("/v1/push/orders")
+            .json(order.clone())
+            .send()
+            .await
+            .assert_status(StatusCode::CREATED)
+            .assert_success();
+        let id = data["id"].as_i64().unwrap();
+        assert_eq!(data["children"]["order_items"].as_array().unwrap().len(), 2);
+
+        // The parent no longer carries the arrays; children point back at it
+        let data = server.get(&format!("/v1/query/orders/{}", id)).send().await.assert_success();
+        assert!(data.get("items").is_none());
+        let children = server
+            .store
+            .query_simple("SELECT sku, orders_id FROM order_items ORDER BY id".to_string())
+            .await
+            .unwrap();
+        assert_eq!(children.len(), 2);
+        assert!(children.iter().all(|row| row[1].1 == json!(id)));
+
+        let strip = |mut row: Value| {
+            for column in ["id", "created_at", "updated_at"] {
+                row.as_object_mut().unwrap().remove(column);
+            }
+            row
+        };
+        let data = server.get("/v1/query/orders?expand=items,tags").send().await.assert_success();
+        assert_eq!(strip(data[0].clone()), order);
+        let data = server
+            .get(&format!("/v1/query/orders/{}?expand=tags", id))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(data["tags"], json!(["rush"]));
+
+        server
+            .get("/v1/query/orders?expand=order_no")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_inline_data_urls_are_stored_as_files() {
+        use base64::Engine;
+
+        const PNG: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==";
+        let png = base64::engine::general_purpose::STANDARD.decode(PNG).unwrap();
+        let server = TestServer::builder().anonymous().spawn().await;
+        let settings = json!({"inline_files": {"fields": ["avatar"], "max_bytes": 1024, "cascade_delete": true}});
+        server.put("/v1/tables/user_profiles/settings").json(settings).send().await.assert_success();
+
+        let data = server
+            .post("/v1/push/user_profiles")
+            .json(json!({"name": "ada", "avatar": format!("data:image/png;base64,{}", PNG)}))
+            .send()
+            .await
+            .assert_status(StatusCode::CREATED)
+            .assert_success();
+        let id = data["id"].as_i64().unwrap();
+
+        let row = server.get(&format!("/v1/query/user_profiles/{}", id)).send().await.assert_success();
+        let avatar = &row["avatar"];
+        assert_eq!(avatar["bucket"], "user-profiles");
+        assert_eq!(avatar["size"], png.len());
+        assert_eq!(avatar["mime"], "image/png");
+        let path = avatar["path"].as_str().unwrap();
+        assert!(path.ends_with(".png"));
+        let (stored, mime_type) = server.storage.download_object("user-profiles", path).await.unwrap();
+        assert_eq!((stored, mime_type.as_str()), (png, "image/png"));
+
+        // Bad values are refused naming the field, before anything is stored
+        for avatar in ["data:image/png;base64,not base64!", &format!("data:text/plain;base64,{}", "QUJD".repeat(400))] {
+            let error = server
+                .post("/v1/push/user_profiles")
+                .json(json!({"name": "bob", "avatar": avatar}))
+                .send()
+                .await
+                .assert_error(StatusCode::UNPROCESSABLE_ENTITY, "INVALID_FILE");
+            assert_eq!(error["details"]["field"], "avatar");
+        }
+        let objects = server.store.query_simple("SELECT path FROM vibe_objects".to_string()).await.unwrap();
+        assert_eq!(objects.len(), 1);
+
+        server
+            .post(&format!("/v1/delete/user_profiles/{}", id))
+            .send()
+            .await
+            .assert_success();
+        assert!(server.storage.get_object("user-profiles", path).await.is_err());
+
+        // Batch pushes store them too, and filtered deletes cascade
+        let avatar = format!("data:image/png;base64,{}", PNG);
+        let pushed = server
+            .post("/v1/push/user_profiles/batch")
+            .json(json!([{"name": "cy", "avatar": avatar}, {"name": "di", "avatar": avatar}]))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(pushed["inserted"], 2);
+        let rows = server.get("/v1/query/user_profiles?order_by=name").send().await.assert_success();
+        let paths: Vec<String> = rows
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| row["avatar"]["path"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(paths.len(), 2);
+        for path in &paths {
+            assert!(server.storage.get_object("user-profiles", path).await.is_ok());
+        }
+        let error = server
+            .post("/v1/push/user_profiles/batch")
+            .json(json!([{"name": "ed"}, {"name": "fay", "avatar": "data:image/png;base64,not base64!"}]))
+            .send()
+            .await
+            .assert_error(StatusCode::UNPROCESSABLE_ENTITY, "INVALID_FILE");
+        assert_eq!(error["details"]["index"], 1);
+
+        server
+            .post("/v1/delete/user_profiles?name=in.(cy,di)")
+            .send()
+            .await
+            .assert_success();
+        for path in &paths {
+            assert!(server.storage.get_object("user-profiles", path).await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_row_endpoints_on_missing_collection_are_not_found() {
+        let server = TestServer::builder().anonymous().spawn().await;
+
+        server
+            .post("/v1/update/ghosts/1")
+            .json(json!({"name": "casper"}))
+            .send()
+            .await
+            .assert_error(StatusCode::NOT_FOUND, "TABLE_NOT_FOUND");
+        server.post("/v1/delete/ghosts/1").send().await.assert_error(StatusCode::NOT_FOUND, "TABLE_NOT_FOUND");
+        server.get("/v1/query/ghosts/1").send().await.assert_error(StatusCode::NOT_FOUND, "TABLE_NOT_FOUND");
+
+        let tables = server
+            .store
+            .query_simple("SELECT name FROM sqlite_master WHERE name = 'ghosts'".to_string())
+            .await
+            .unwrap();
+        assert!(tables.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_in_lists_and_negated_filters() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        for doc in [
+            json!({"name": "a", "status": "open", "n": 1}),
+            json!({"name": "b", "status": "pending", "n": 2}),
+            json!({"name": "c", "status": "closed", "n": 10}),
+            json!({"name": "d", "status": "Smith, J.", "n": 20}),
+            json!({"name": "e", "n": 3}),
+        ] {
+            server.post("/v1/push/tickets").json(doc).send().await.assert_success();
+        }
+
+        let names = |uri: String| {
+            let request = server.get(&uri);
+            async move {
+                let rows = request.send().await.assert_success();
+                let mut names: Vec<String> = rows
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|row| row["name"].as_str().unwrap().to_string())
+                    .collect();
+                names.sort();
+                names
+            }
+        };
+        let query = |filters: &str| format!("/v1/query/tickets?{}", filters);
+        assert_eq!(names(query("status=in.(open,pending)")).await, ["a", "b"]);
+        assert_eq!(names(query("n=in.(2,10,99)::int")).await, ["b", "c"]);
+        assert_eq!(names(query("n=in.(%201%20,%203%20)")).await, ["a", "e"]);
+        assert_eq!(names(query("status=in.(%22Smith,%20J.%22,closed)")).await, ["c", "d"]);
+        // Negations keep the rows where the column is NULL
+        assert_eq!(names(query("status=not.eq.closed")).await, ["a", "b", "d", "e"]);
+        assert_eq!(names(query("status=not.in.(open,pending)")).await, ["c", "d", "e"]);
+        assert_eq!(names(query("status=not.in.(open,pending)&status=not.is.null")).await, ["c", "d"]);
+        assert_eq!(names(query("n=not.eq.1&n=not.eq.2")).await, ["c", "d", "e"]);
+        assert_eq!(names(query("status=like.*en*&name=not.eq.a")).await, ["b"]);
+        assert_eq!(names(query("status=is.null")).await, ["e"]);
+        assert!(names(query("status=not.sure")).await.is_empty());
+
+        let cap = vec!["x"; MAX_IN_LIST + 1].join(",");
+        let error = server
+            .get(&query(&format!("status=in.({})", cap)))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        assert!(error["message"].as_str().unwrap().contains("at most 100"), "{}", error);
+        assert_eq!(error["details"]["fields"], json!(["status"]));
+
+        let groups = server
+            .get("/v1/query/tickets/aggregate?metric=sum:n&status=not.in.(closed)")
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(groups[0]["sum:n"], 26);
+
+        let counted = server
+            .post("/v1/delete/tickets?status=in.(open,closed)&n=not.eq.1&count_only=true")
+            .send()
+            .await
+            .json();
+        assert_eq!(counted["count"], 1);
+        let deleted = server
+            .post("/v1/delete/tickets?status=in.(open,closed)&n=not.eq.1")
+            .send()
+            .await
+            .json();
+        assert_eq!(deleted["affected"], 1);
+        assert_eq!(names(query("status=in.(open,closed)")).await, ["a"]);
+    }
+
+    #[tokio::test]
+    async fn test_null_filters() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        for doc in [json!({"name": "a"}), json!({"name": "b"}), json!({"name": "c", "nickname": "cc"})] {
+            server.post("/v1/push/members").json(doc).send().await.assert_success();
+        }
+        // Rows pushed before the column existed are NULL in it
+        let names = |filters: &str| {
+            let request = server.get(&format!("/v1/query/members?order_by=id&{}", filters));
+            async move {
+                let rows = request.send().await.assert_success();
+                rows.as_array().unwrap().iter().map(|row| row["name"].clone()).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(names("nickname[null]=true").await, [json!("a"), json!("b")]);
+        assert_eq!(names("nickname[null]=false").await, [json!("c")]);
+        assert_eq!(names("nickname[null]=true&name=b").await, [json!("b")]);
+
+        let error = server
+            .get("/v1/query/members?nickname[null]=yes")
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        assert_eq!(error["details"]["fields"], json!(["nickname"]));
+        server
+            .get("/v1/query/members?nick%20name[null]=true")
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_IDENTIFIER");
+
+        let groups = server.get("/v1/query/members/aggregate?metric=count&nickname[null]=true").send().await.assert_success();
+        assert_eq!(groups[0]["count"], 2);
+        let deleted = server.post("/v1/delete/members?nickname[null]=true").send().await.json();
+        assert_eq!(deleted["affected"], 2);
+        assert_eq!(names("").await, [json!("c")]);
+    }
+
+    #[tokio::test]
+    async fn test_routes_under_a_base_path() {
+        let mut config = VibeConfig::default();
+        config.base_path.prefix = "/vibedb".to_string();
+        let server = TestServer::builder().anonymous().config(config).spawn().await;
+
+        server.post("/vibedb/v1/push/notes").json(json!({"text": "hi"})).send().await.assert_success();
+        let rows = server.get("/vibedb/v1/query/notes").send().await.assert_success();
+        assert_eq!(rows[0]["text"], "hi");
+        server.get("/v1/query/notes").send().await.assert_status(StatusCode::NOT_FOUND);
+
+        let info = server.get("/vibedb").send().await.assert_status(StatusCode::OK).json();
+        assert_eq!(info["base_path"], "/vibedb");
+        assert_eq!(info["endpoints"]["query"], "GET /vibedb/v1/query/:collection");
+        assert_eq!(
+            info["endpoints"]["snapshots"],
+            "POST /vibedb/v1/snapshots, DELETE /vibedb/v1/snapshots/:id"
+        );
+
+        let page = server.get("/vibedb/explore").send().await.assert_status(StatusCode::OK).text();
+        assert!(page.contains(r#"window.VIBEDB_BASE_PATH = "/vibedb""#));
+
+        // Storage URLs work as listed
+        server.upload_fixture_file("assets", "logo.png", b"png").await;
+        let listed = server.get("/vibedb/v1/storage/list/assets").send().await.assert_success();
+        let url = listed[0]["url"].as_str().unwrap();
+        assert_eq!(url, "/vibedb/v1/storage/object/assets/logo.png");
+        assert_eq!(server.get(url).send().await.assert_status(StatusCode::OK).body.to_vec(), b"png");
+    }
+
+    #[tokio::test]
+    async fn test_select_shapes_results() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        for doc in [
+            json!({"first": "Ada", "last": "Lovelace", "price": 10, "nick": "ada"}),
+            json!({"first": "Alan", "last": "Turing", "price": 4.4}),
+        ] {
+            server.post("/v1/push/people").json(doc).send().await.assert_success();
+        }
+        let query = |select: &str| server.get(&format!("/v1/query/people?order_by=id&select={}", select));
+
+        let rows = query("first:name,price").send().await.assert_success();
+        assert_eq!(rows, json!([{"name": "Ada", "price": 10}, {"name": "Alan", "price": 4.4}]));
+
+        let rows = query("concat(first,'%20',last):full_name,round(price*1.25,2):price_with_tax&first=Alan")
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(rows, json!([{"full_name": "Alan Turing", "price_with_tax": 5.5}]));
+
+        let rows = query("lower(first):lo,upper(last):up,length(last):len,coalesce(nick,'-'):nick")
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(rows[0], json!({"lo": "ada", "up": "LOVELACE", "len": 8, "nick": "ada"}));
+        assert_eq!(rows[1], json!({"lo": "alan", "up": "TURING", "len": 6, "nick": "-"}));
+
+        let error = query("load_extension(first):x").send().await.assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        assert!(error["message"].as_str().unwrap().contains("unknown function 'load_extension'"));
+        let error = query("upper(middle):m").send().await.assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        assert_eq!(error["details"]["fields"], json!(["middle"]));
+        query("first:select").send().await.assert_error(StatusCode::BAD_REQUEST, "INVALID_IDENTIFIER");
+
+        // Exports shape the rows of a single collection
+        let lines = server
+            .get("/v1/export?tables=people&select=concat(first,'%20',last):full_name")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .ndjson();
+        assert_eq!(lines[1]["row"], json!({"full_name": "Ada Lovelace"}));
+        server
+            .get("/v1/export?select=first")
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+    }
+
+    #[tokio::test]
+    async fn test_contains_filter_on_json_arrays() {
+        let server = TestServer::spawn().await;
+
+        for doc in [
+            json!({"name": "vibedb", "tags": ["rust", "sqlite"], "sizes": [1, 3]}),
+            json!({"name": "webapp", "tags": ["typescript"], "sizes": [2]}),
+            json!({"name": "notes", "tags": "rust, but not an array"}),
+        ] {
+            server
+                .post("/v1/push/projects")
+                .json(doc)
+                .send()
+                .await
+                .assert_status(StatusCode::CREATED);
+        }
+
+        let names = |uri: &'static str| {
+            let request = server.get(uri);
+            async move {
+                request
+                    .send()
+                    .await
+                    .assert_success()
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|row| row["name"].as_str().unwrap().to_string())
+                    .collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(names("/v1/query/projects?tags%5Bcontains%5D=rust").await, vec!["vibedb"]);
+        assert_eq!(names("/v1/query/projects?sizes[contains]=3&name=vibedb").await, vec!["vibedb"]);
+        assert!(names("/v1/query/projects?tags[contains]=go").await.is_empty());
+
+        server
+            .get("/v1/query/projects?tags[bogus]=x")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_computed_columns() {
+        let server = TestServer::spawn().await;
+        let admin = server.create_user("admin@vibedb.dev", ADMIN_ROLE).await.token;
+
+        server
+            .post("/v1/push/people")
+            .json(json!({"first": "Ada", "last": "Lovelace"}))
+            .send()
+            .await
+            .assert_success();
+        let computed = json!({"name": "full_name", "expression": "first || ' ' || last"});
+        let add = || server.post("/v1/tables/people/computed").json(computed.clone());
+        add().send().await.assert_status(StatusCode::FORBIDDEN);
+        add().bearer(&admin).send().await.assert_status(StatusCode::CREATED);
+        add().bearer(&admin).send().await.assert_status(StatusCode::CONFLICT);
+
+        // Pushed values for the computed column are ignored
+        server
+            .post("/v1/push/people")
+            .json(json!({"first": "Grace", "last": "Hopper", "full_name": "ignored"}))
+            .send()
+            .await
+            .assert_status(StatusCode::CREATED);
+
+        let data = server.get("/v1/query/people?order_by=id").send().await.assert_success();
+        assert_eq!(data[0]["full_name"], "Ada Lovelace");
+        assert_eq!(data[1]["full_name"], "Grace Hopper");
+
+        let data = server.get("/v1/tables/people").send().await.assert_success();
+        let columns = data["columns"].as_array().unwrap();
+        let full_name = columns.iter().find(|c| c["name"] == "full_name").unwrap();
+        assert_eq!(full_name["virtual"], true);
+        assert!(columns.iter().filter(|c| c["name"] != "full_name").all(|c| c["virtual"] == false));
+    }
+
+    #[tokio::test]
+    async fn test_push_skips_generated_columns() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        server
+            .store
+            .execute_batch(
+                "CREATE TABLE order_items (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    orders_id INTEGER,
+                    qty INTEGER,
+                    price REAL,
+                    total REAL GENERATED ALWAYS AS (qty * price) STORED,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                );"
+                .to_string(),
+            )
+            .await
+            .unwrap();
+        server
+            .put("/v1/tables/orders/settings")
+            .json(json!({"explode_arrays": {"items": "order_items"}}))
+            .send()
+            .await
+            .assert_success();
+
+        // Echoed computed values are dropped from parents and children alike
+        server
+            .post("/v1/push/order_items")
+            .json(json!({"qty": 1, "price": 2.0, "total": 99}))
+            .send()
+            .await
+            .assert_status(StatusCode::CREATED);
+        server
+            .post("/v1/push/orders")
+            .json(json!({"items": [{"qty": 2, "price": 2.5, "total": 99}]}))
+            .send()
+            .await
+            .assert_status(StatusCode::CREATED);
+        server
+            .post("/v1/update/order_items/1")
+            .json(json!({"qty": 3, "total": 99}))
+            .send()
+            .await
+            .assert_success();
+
+        let data = server.get("/v1/query/order_items?order_by=id").send().await.assert_success();
+        assert_eq!(data[0]["total"], json!(6.0));
+        assert_eq!(data[1]["total"], json!(5.0));
+    }
+
+    /// Checks percentiles over 1..=100 per service, plus NULL latencies
+    async fn assert_percentiles(server: &TestServer, method: &str) {
+        let mut requests: Vec<Value> = (1..=100)
+            .flat_map(|i| {
+                [
+                    json!({"service": "api", "latency_ms": i}),
+                    json!({"service": "db", "latency_ms": i + 100}),
+                ]
+            })
+            .collect();
+        requests.push(json!({"service": "api", "latency_ms": null}));
+        requests.push(json!({"service": "db"}));
+        server
+            .post("/v1/push/requests/batch")
+            .json(Value::Array(requests))
+            .send()
+            .await
+            .assert_success();
+
+        let body = server
+            .get("/v1/query/requests/aggregate?metric=p50:latency_ms&metric=p95:latency_ms&metric=median:latency_ms&metric=count")
+            .send()
+            .await
+            .json();
+        assert_eq!(body["percentile_method"], method);
+        assert_eq!(
+            body["data"],
+            json!([{"p50:latency_ms": 100, "p95:latency_ms": 190, "median:latency_ms": 100, "count": 202}])
+        );
+
+        let body = server
+            .get("/v1/query/requests/aggregate?metric=p50:latency_ms&metric=p95:latency_ms&metric=count:latency_ms&group_by=service")
+            .send()
+            .await
+            .json();
+        assert_eq!(
+            body["data"],
+            json!([
+                {"service": "api", "p50:latency_ms": 50, "p95:latency_ms": 95, "count:latency_ms": 100},
+                {"service": "db", "p50:latency_ms": 150, "p95:latency_ms": 195, "count:latency_ms": 100}
+            ])
+        );
+
+        // Filters narrow the population
+        let data = server
+            .get("/v1/query/requests/aggregate?metric=p95:latency_ms&service=db")
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(data, json!([{"p95:latency_ms": 195}]));
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_percentiles_with_window_functions() {
+        let server = TestServer::spawn().await;
+        assert_percentiles(&server, "window_functions").await;
+
+        let body = server.get("/v1/query/requests/aggregate?metric=avg:latency_ms").send().await.json();
+        assert_eq!(body["data"][0]["avg:latency_ms"], json!(100.5));
+        assert!(body["percentile_method"].is_null());
+        for bad in ["metric=p101:latency_ms", "metric=p95", "metric=p95:missing", "group_by=missing"] {
+            server
+                .get(&format!("/v1/query/requests/aggregate?{}", bad))
+                .send()
+                .await
+                .assert_status(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_percentiles_two_pass_fallback() {
+        let store = VibeStore::in_memory().await.unwrap();
+        let capabilities = Capabilities {
+            window_functions: false,
+            ..store.capabilities().clone()
+        };
+        let server = TestServer::builder()
+            .store(Arc::new(store.with_capabilities(capabilities)))
+            .spawn()
+            .await;
+        assert_percentiles(&server, "two_pass").await;
+    }
+
+    #[tokio::test]
+    async fn test_capabilities_endpoint() {
+        let server = TestServer::spawn().await;
+
+        let data = server.get("/v1/capabilities").send().await.assert_success();
+        assert_eq!(data, serde_json::to_value(server.store.capabilities()).unwrap());
+        assert_eq!(data["json1"], true);
+        let body = server.get("/health/ready").send().await.json();
+        assert_eq!(body["capabilities"]["sqlite_version"], rusqlite::version());
+
+        let error = server
+            .post("/v1/sql/query")
+            .json(json!({"query": "SELECT no_such_fn(1)"}))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "UNSUPPORTED_FUNCTION");
+        assert!(error["message"].as_str().unwrap().contains("/v1/capabilities"));
+    }
+
+    #[tokio::test]
+    async fn test_contains_filter_requires_json1() {
+        let store = VibeStore::in_memory().await.unwrap();
+        store
+            .execute_simple("CREATE TABLE posts (id INTEGER PRIMARY KEY, tags TEXT)".to_string())
+            .await
+            .unwrap();
+        let capabilities = Capabilities {
+            json1: false,
+            ..store.capabilities().clone()
+        };
+        let server = TestServer::builder()
+            .store(Arc::new(store.with_capabilities(capabilities)))
+            .spawn()
+            .await;
+
+        server
+            .get("/v1/query/posts?tags[contains]=x")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_perf_counters_are_per_collection() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        server.post("/v1/push/quiet").json(json!({"a": 1})).send().await.assert_success();
+        let quiet = server.get("/v1/tables/quiet/perf").send().await.assert_success();
+        assert_eq!(quiet["writes"], 1);
+        assert_eq!(quiet["migrations"], 2);
+
+        // Concurrent first pushes would race to add the same column
+        server.post("/v1/push/busy").json(json!({"n": 0})).send().await.assert_success();
+        let pushes = (0..40).map(|i| server.post("/v1/push/busy").json(json!({"n": i})).send());
+        for response in futures::future::join_all(pushes).await {
+            response.assert_status(StatusCode::CREATED);
+        }
+        server
+            .post("/v1/push/busy/batch")
+            .json(json!([{"n": 1, "extra": true}, {"n": 2}]))
+            .send()
+            .await
+            .assert_status(StatusCode::CREATED);
+        server.post("/v1/update/busy/1").json(json!({"n": 0})).send().await.assert_success();
+        server.post("/v1/delete/busy/2").send().await.assert_success();
+
+        let busy = server.get("/v1/tables/busy/perf").send().await.assert_success();
+        assert_eq!(busy["writes"], 44);
+        assert_eq!(busy["rows_written"], 45);
+        assert_eq!(busy["write_errors"], 0);
+        assert_eq!(busy["migrations"], 3);
+        assert_eq!(busy["tracked_as"], "busy");
+        assert!(busy["writes_per_sec"].as_f64().unwrap() > 0.0);
+        assert!(busy["latency"]["p95_ms"].as_u64().is_some());
+        assert_eq!(
+            server.get("/v1/tables/quiet/perf").send().await.assert_success(),
+            quiet
+        );
+
+        let metrics = server.get("/metrics").send().await.text();
+        assert!(metrics.contains("vibedb_writes_total{collection=\"busy\"} 44"));
+        assert!(metrics.contains("vibedb_writes_total{collection=\"quiet\"} 1"));
+        assert!(metrics.contains("vibedb_write_duration_seconds_count{collection=\"busy\"} 44"));
+    }
+
+    #[tokio::test]
+    async fn test_table_stats_count_reads_and_writes() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        server.post("/v1/push/hot").json(json!({"n": 1})).send().await.assert_success();
+        server.post("/v1/push/hot/batch").json(json!([{"n": 2}, {"n": 3}])).send().await.assert_success();
+        server.post("/v1/update/hot/1").json(json!({"n": 4})).send().await.assert_success();
+        server.post("/v1/push/cold").json(json!({"n": 1})).send().await.assert_success();
+        for uri in ["/v1/query/hot", "/v1/query/hot/1", "/v1/query/hot/aggregate?metric=count"] {
+            server.get(uri).send().await.assert_success();
+        }
+
+        let hot = server.get("/v1/tables/hot").send().await.assert_success();
+        assert_eq!((hot["reads"].as_u64(), hot["writes"].as_u64()), (Some(3), Some(3)));
+        let cold = server.get("/v1/tables/cold").send().await.assert_success();
+        assert_eq!((cold["reads"].as_u64(), cold["writes"].as_u64()), (Some(0), Some(1)));
+
+        let metrics = server.get("/metrics").send().await.text();
+        assert!(metrics.contains("vibedb_reads_total{collection=\"hot\"} 3"));
+        assert!(metrics.contains("vibedb_reads_total{collection=\"cold\"} 0"));
+    }
+
+    #[tokio::test]
+    async fn test_column_limit_is_reported_before_and_when_hit() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        let wide = |from: usize, to: usize| -> Value {
+            Value::Object((from..to).map(|i| (format!("f{}", i), json!(i))).collect())
+        };
+        // 3 system columns plus 900 keys: past 90% of the limit
+        server.post("/v1/push/wide").json(wide(0, 900)).send().await.assert_success();
+        let metrics = server.get("/metrics").send().await.text();
+        assert!(metrics.contains("vibedb_columns{collection=\"wide\"} 903"), "{}", metrics);
+
+        server
+            .post("/v1/push/wide")
+            .json(wide(900, 1000))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "COLUMN_LIMIT_EXCEEDED");
+        let perf = server.get("/v1/tables/wide/perf").send().await.assert_success();
+        assert_eq!(perf["column_limit_rejections"], 1);
+        let metrics = server.get("/metrics").send().await.text();
+        assert!(metrics.contains("vibedb_column_limit_rejections_total{collection=\"wide\"} 1"));
+        assert!(metrics.contains("vibedb_columns{collection=\"wide\"} 903"));
+    }
+
+    #[tokio::test]
+    async fn test_type_hints_pick_column_types() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        server
+            .post("/v1/push/products")
+            .json(json!({"_types": {"price": "real", "sku": "TEXT"}, "price": 10, "sku": 42, "stock": 3}))
+            .send()
+            .await
+            .assert_success();
+        let stats = server.get("/v1/tables/products").send().await.assert_success();
+        let col_type = |name: &str| {
+            stats["columns"].as_array().unwrap().iter().find(|c| c["name"] == name).map(|c| c["col_type"].clone())
+        };
+        assert_eq!(col_type("price"), Some(json!("REAL")));
+        assert_eq!(col_type("sku"), Some(json!("TEXT")));
+        assert_eq!(col_type("stock"), Some(json!("INTEGER")));
+        assert_eq!(col_type("_types"), None);
+        let rows = server.get("/v1/query/products").send().await.assert_success();
+        assert_eq!(rows[0]["price"], 10.0);
+        assert_eq!(rows[0]["sku"], "42");
+
+        // Existing columns keep their type; new ones in a batch follow the hints
+        server
+            .post("/v1/push/products/batch")
+            .json(json!([
+                {"_types": {"stock": "TEXT", "weight": "REAL"}, "stock": 1, "weight": 2},
+                {"_types": {"weight": "REAL"}, "weight": 3}
+            ]))
+            .send()
+            .await
+            .assert_success();
+        let stats = server.get("/v1/tables/products").send().await.assert_success();
+        let col_type = |name: &str| {
+            stats["columns"].as_array().unwrap().iter().find(|c| c["name"] == name).unwrap()["col_type"].clone()
+        };
+        assert_eq!(col_type("stock"), "INTEGER");
+        assert_eq!(col_type("weight"), "REAL");
+
+        let err = server
+            .post("/v1/push/products")
+            .json(json!({"_types": {"size": "VARCHAR"}, "size": 1}))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        assert_eq!(err["details"]["fields"], json!(["size"]));
+        server
+            .post("/v1/push/products")
+            .json(json!({"_types": "REAL", "size": 1}))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        server
+            .post("/v1/push/products/batch")
+            .json(json!([{"_types": {"size": "REAL"}, "size": 1}, {"_types": {"size": "TEXT"}, "size": 2}]))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+    }
+
+    #[tokio::test]
+    async fn test_increment_is_atomic() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        let id = server
+            .post("/v1/push/posts")
+            .json(json!({"title": "hello"}))
+            .send()
+            .await
+            .assert_success()["id"]
+            .as_i64()
+            .unwrap();
+        let uri = format!("/v1/update/posts/{}/increment", id);
+
+        // The missing column is created as INTEGER and starts from 0
+        let data = server.post(&uri).json(json!({"field": "views"})).send().await.json();
+        assert_eq!(data["value"], 1);
+        assert_eq!(data["field"], "views");
+        let stats = server.get("/v1/tables/posts").send().await.assert_success();
+        let views = stats["columns"].as_array().unwrap().iter().find(|c| c["name"] == "views").unwrap().clone();
+        assert_eq!(views["col_type"], "INTEGER");
+
+        let increments = (0..20).map(|_| server.post(&uri).json(json!({"field": "views", "by": 2})).send());
+        for response in futures::future::join_all(increments).await {
+            response.assert_status(StatusCode::OK);
+        }
+        let data = server.post(&uri).json(json!({"field": "views", "by": -1})).send().await.json();
+        assert_eq!(data["value"], 40);
+        let row = server.get(&format!("/v1/query/posts/{}", id)).send().await.assert_success();
+        assert_eq!(row["views"], 40);
+
+        for body in [
+            json!({"field": "views", "by": "2"}),
+            json!({"field": "views; DROP TABLE posts", "by": 1}),
+            json!({"field": "id"}),
+            json!({"field": "title"}),
+        ] {
+            server.post(&uri).json(body).send().await.assert_status(StatusCode::BAD_REQUEST);
+        }
+        server
+            .post(&uri)
+            .json(json!({"field": "views", "step": 1}))
+            .send()
+            .await
+            .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        server
+            .post("/v1/update/posts/999/increment")
+            .json(json!({"field": "views"}))
+            .send()
+            .await
+            .assert_error(StatusCode::NOT_FOUND, "TABLE_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_large_integers_round_trip_exactly() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        let pushed = server
+            .post("/v1/push/tweets")
+            .json(json!({"tweet_id": u64::MAX, "seq": 1_234_567_890_123_456_789i64, "views": 1e20}))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(
+            pushed["warnings"],
+            json!([
+                "tweet_id: 18446744073709551615 exceeds the INTEGER range and was stored as TEXT",
+                "views: 1e+20 arrived as a float and may have lost digits; send large integers as strings"
+            ])
+        );
+        let stats = server.get("/v1/tables/tweets").send().await.assert_success();
+        let col_type = |name: &str| {
+            stats["columns"].as_array().unwrap().iter().find(|c| c["name"] == name).unwrap()["col_type"].clone()
+        };
+        assert_eq!(col_type("tweet_id"), "TEXT");
+        assert_eq!(col_type("seq"), "INTEGER");
+
+        let batch = server
+            .post("/v1/push/tweets/batch")
+            .json(json!([{"tweet_id": i64::MAX as u64 + 1, "seq": i64::MAX}, {"seq": 5}]))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(batch["warnings"].as_array().unwrap().len(), 1);
+        assert!(batch["warnings"][0].as_str().unwrap().starts_with("item 0: tweet_id: 9223372036854775808"));
+
+        let row = server.get("/v1/query/tweets/1").send().await.assert_success();
+        assert_eq!(row["tweet_id"], "18446744073709551615");
+        assert_eq!(row["seq"], 1_234_567_890_123_456_789i64);
+        let rows = server.get("/v1/query/tweets?tweet_id=9223372036854775808").send().await.assert_success();
+        assert_eq!(rows[0]["seq"], i64::MAX);
+        let lines = server.get("/v1/export?tables=tweets").send().await.assert_status(StatusCode::OK).ndjson();
+        let exported: Vec<&Value> = lines.iter().filter(|l| l["type"] == "row").map(|l| &l["row"]).collect();
+        assert_eq!(exported[0]["tweet_id"], "18446744073709551615");
+        assert_eq!(exported[1]["tweet_id"], "9223372036854775808");
+        assert_eq!(exported[1]["seq"], i64::MAX);
+
+        // An INTEGER column can only hold the nearest REAL, and says so
+        let updated = server
+            .post("/v1/update/tweets/2")
+            .json(json!({"seq": u64::MAX}))
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .json();
+        assert!(updated["warnings"][0].as_str().unwrap().contains("the INTEGER column stored the nearest REAL"));
+
+        let mut config = VibeConfig::default();
+        config.payload.strict_numbers = true;
+        let strict = TestServer::builder().anonymous().config(config).spawn().await;
+        let err = strict
+            .post("/v1/push/tweets")
+            .json(json!({"tweet_id": u64::MAX, "views": 1e20, "seq": 1}))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        assert_eq!(err["details"]["fields"], json!(["tweet_id", "views"]));
+        strict.post("/v1/push/tweets").json(json!({"seq": i64::MAX})).send().await.assert_success();
+        let err = strict
+            .post("/v1/push/tweets/batch")
+            .json(json!([{"seq": 1}, {"seq": u64::MAX}]))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        assert_eq!(err["details"]["index"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_array_append_is_atomic() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        server
+            .post("/v1/push/posts")
+            .json(json!({"title": "hello", "score": 3, "tags": ["a"]}))
+            .send()
+            .await
+            .assert_success();
+        let append = |field: &str, value: Value| {
+            server.post("/v1/update/posts/1/array-append").json(json!({"field": field, "value": value})).send()
+        };
+
+        let data = append("tags", json!("b")).await.json();
+        assert_eq!(data["value"], json!(["a", "b"]));
+        let data = append("links", json!({"url": "x"})).await.json();
+        assert_eq!(data["value"], json!([{"url": "x"}]));
+
+        let appends = (0..20).map(|i| append("history", json!(i)));
+        for response in futures::future::join_all(appends).await {
+            response.assert_status(StatusCode::OK);
+        }
+        let row = server.get("/v1/query/posts/1").send().await.assert_success();
+        let mut history: Vec<i64> = serde_json::from_value(row["history"].clone()).unwrap();
+        history.sort();
+        assert_eq!(history, (0..20).collect::<Vec<_>>());
+
+        for field in ["title", "score", "id"] {
+            let err = append(field, json!("x")).await.assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+            assert_eq!(err["details"]["fields"], json!([field]));
+        }
+        server
+            .post("/v1/update/posts/9/array-append")
+            .json(json!({"field": "tags", "value": "c"}))
+            .send()
+            .await
+            .assert_error(StatusCode::NOT_FOUND, "TABLE_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_array_append_respects_payload_limits() {
+        let mut config = VibeConfig::default();
+        config.payload.max_depth = 3;
+        config.payload.max_nested_bytes = 16;
+        let server = TestServer::builder().anonymous().config(config).spawn().await;
+        server.post("/v1/push/posts").json(json!({"tags": ["a"]})).send().await.assert_success();
+        let append = |value: Value| {
+            server.post("/v1/update/posts/1/array-append").json(json!({"field": "tags", "value": value})).send()
+        };
+
+        append(json!({"deep": [1]})).await.assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        append(json!("bb")).await.assert_success();
+        // ["a","bb","ccc"] would be 16 bytes; one more element is not
+        append(json!("ccc")).await.assert_success();
+        let err = append(json!("d")).await.assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        assert!(err["message"].as_str().unwrap().contains("at most 16 bytes"), "{}", err);
+        let row = server.get("/v1/query/posts/1").send().await.assert_success();
+        assert_eq!(row["tags"], json!(["a", "bb", "ccc"]));
+    }
+
+    #[tokio::test]
+    async fn test_row_locks() {
+        let clock = ManualClock::new();
+        let server = TestServer::builder().clock(clock.clone()).spawn().await;
+        let admin = server.create_user("admin@example.com", "admin").await;
+        let alice = server.create_user("alice@example.com", "user").await;
+        let bob = server.create_user("bob@example.com", "user").await;
+        server.post("/v1/push/posts").bearer(&admin.token).json(json!({"title": "a"})).send().await.assert_success();
+        let mut events = server.subscribe("posts", Some(&admin.token)).await;
+        let alice_stream = server.subscribe("posts", Some(&alice.token)).await;
+        let session = alice_stream.connected()["session"].as_str().unwrap().to_string();
+
+        let lock = server
+            .post(&format!("/v1/locks/posts/1?session={}", session))
+            .bearer(&alice.token)
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(lock["holder"]["email"], "alice@example.com");
+        let event = events.next().await;
+        assert_eq!(event["event"], "lock");
+        assert_eq!(event["holder"]["email"], "alice@example.com");
+        assert_eq!(event["expires_at"], lock["expires_at"]);
+
+        let err = server
+            .post("/v1/locks/posts/1")
+            .bearer(&bob.token)
+            .send()
+            .await
+            .assert_error(StatusCode::LOCKED, "LOCKED");
+        assert_eq!(err["details"]["holder"]["email"], "alice@example.com");
+        let held = server.get("/v1/locks/posts/1").send().await.assert_success();
+        assert_eq!(held["holder"]["email"], "alice@example.com");
+        server.post("/v1/locks/posts/1").send().await.assert_status(StatusCode::UNAUTHORIZED);
+
+        // Advisory until the collection enforces locks
+        let update = |token: &str| {
+            server.post("/v1/update/posts/1").bearer(token).json(json!({"title": "b"})).send()
+        };
+        update(&bob.token).await.assert_success();
+        assert_eq!(events.next().await["event"], "update");
+        server
+            .put("/v1/tables/posts/settings")
+            .bearer(&admin.token)
+            .json(json!({"enforce_locks": true}))
+            .send()
+            .await
+            .assert_success();
+        update(&bob.token).await.assert_error(StatusCode::LOCKED, "LOCKED");
+        server
+            .post("/v1/update/posts/1/increment")
+            .bearer(&bob.token)
+            .json(json!({"field": "views"}))
+            .send()
+            .await
+            .assert_error(StatusCode::LOCKED, "LOCKED");
+        update(&alice.token).await.assert_success();
+        assert_eq!(events.next().await["event"], "update");
+
+        server.delete("/v1/locks/posts/1").bearer(&bob.token).send().await.assert_error(StatusCode::LOCKED, "LOCKED");
+        let released = server.delete("/v1/locks/posts/1").bearer(&alice.token).send().await.json();
+        assert_eq!(released["released"], true);
+        let event = events.next().await;
+        assert_eq!((event["event"].as_str(), event["reason"].as_str()), (Some("unlock"), Some("released")));
+        update(&bob.token).await.assert_success();
+        assert_eq!(events.next().await["event"], "update");
+
+        // Expiry frees the row
+        server.post("/v1/locks/posts/1?ttl_secs=10").bearer(&bob.token).send().await.assert_success();
+        assert_eq!(events.next().await["event"], "lock");
+        clock.advance(Duration::from_secs(9));
+        server.post("/v1/locks/posts/1").bearer(&alice.token).send().await.assert_error(StatusCode::LOCKED, "LOCKED");
+        clock.advance(Duration::from_secs(1));
+        let event = events.next().await;
+        assert_eq!((event["event"].as_str(), event["reason"].as_str()), (Some("unlock"), Some("expired")));
+        assert_eq!(server.get("/v1/locks/posts/1").send().await.assert_success(), Value::Null);
+
+        // So does closing the stream the lock was taken with
+        server
+            .post(&format!("/v1/locks/posts/1?session={}", session))
+            .bearer(&alice.token)
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(events.next().await["event"], "lock");
+        drop(alice_stream);
+        let event = events.next().await;
+        assert_eq!((event["event"].as_str(), event["reason"].as_str()), (Some("unlock"), Some("disconnected")));
+        update(&bob.token).await.assert_success();
+
+        server
+            .post(&format!("/v1/locks/posts/1?session={}", session))
+            .bearer(&alice.token)
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        server
+            .post("/v1/locks/posts/1?ttl_secs=0")
+            .bearer(&alice.token)
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        server.post("/v1/locks/posts/2").bearer(&alice.token).send().await.assert_error(StatusCode::NOT_FOUND, "NOT_FOUND");
+
+        // Anonymous holders are shown by label, never by session
+        let guest = server.subscribe("posts", None).await;
+        let guest_session = guest.connected()["session"].as_str().unwrap().to_string();
+        let lock = server
+            .post(&format!("/v1/locks/posts/1?session={}", guest_session))
+            .send()
+            .await
+            .assert_success();
+        assert!(lock["holder"]["anonymous"].is_string());
+        assert!(lock.get("session").is_none());
+        assert_eq!(events.next().await["event"], "update");
+        let event = events.next().await;
+        assert_eq!(event["holder"], lock["holder"]);
+        let held = server.get("/v1/locks/posts/1").send().await.assert_success();
+        let err = update(&bob.token).await.assert_error(StatusCode::LOCKED, "LOCKED");
+        for shown in [event.to_string(), held.to_string(), err.to_string()] {
+            assert!(!shown.contains(&guest_session), "{}", shown);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_system_fields_are_skipped_in_any_case() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        let system = json!({"ID": 99, "Created_At": "2000-01-01T00:00:00Z", "updated_AT": "2000-01-01T00:00:00Z"});
+
+        let mut payload = system.clone();
+        payload["name"] = json!("a");
+        let data = server.post("/v1/push/items").json(payload).send().await.assert_success();
+        assert_eq!(data["skipped_fields"], json!(["Created_At", "ID", "updated_AT"]));
+        let id = data["id"].as_i64().unwrap();
+        assert_ne!(id, 99);
+        let plain = server.post("/v1/push/items").json(json!({"name": "b"})).send().await.assert_success();
+        assert!(plain.get("skipped_fields").is_none());
+
+        let data = server
+            .post("/v1/push/items/batch")
+            .json(json!([{"name": "c", "id": 5}, {"name": "d", "CREATED_AT": "x"}]))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(data["skipped_fields"], json!(["CREATED_AT", "id"]));
+
+        let created_at = server.get("/v1/query/items?order_by=id").send().await.assert_success()[0]["created_at"].clone();
+        let body = server
+            .post(&format!("/v1/update/items/{}", id))
+            .json(json!({"Created_At": "2000-01-01T00:00:00Z", "name": "e"}))
+            .send()
+            .await
+            .json();
+        assert_eq!((&body["affected"], &body["skipped_fields"]), (&json!(1), &json!(["Created_At"])));
+        let body = server.post(&format!("/v1/update/items/{}", id)).json(system).send().await.json();
+        assert_eq!(body["message"], "No updates provided");
+        assert_eq!(body["skipped_fields"], json!(["Created_At", "ID", "updated_AT"]));
+
+        let rows = server.get("/v1/query/items?order_by=id").send().await.assert_success();
+        assert_eq!(rows.as_array().unwrap().len(), 4);
+        assert_eq!((&rows[0]["id"], &rows[0]["name"]), (&json!(id), &json!("e")));
+        assert_eq!(rows[0]["created_at"], created_at);
+        assert!(rows.as_array().unwrap().iter().all(|row| row["created_at"] != "2000-01-01 00:00:00"));
+        let stats = server.get("/v1/tables/items").send().await.assert_success();
+        assert_eq!(stats["column_count"], 4);
+
+        // With the backfill flag any casing of a timestamp is stored
+        let data = server
+            .post("/v1/push/items?allow_timestamps=true")
+            .json(json!({"Created_At": "2000-01-01T00:00:00Z", "name": "f"}))
+            .send()
+            .await
+            .assert_success();
+        assert!(data.get("skipped_fields").is_none());
+        let rows = server.get("/v1/query/items?name=f").send().await.assert_success();
+        assert_eq!(rows[0]["created_at"], "2000-01-01 00:00:00");
+    }
+
+    #[tokio::test]
+    async fn test_backfilled_timestamps_need_the_flag() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        let backfill = json!({"n": 1, "created_at": "2020-05-01T12:00:00+02:00", "updated_at": null});
+
+        server.post("/v1/push/history").json(backfill.clone()).send().await.assert_success();
+        server
+            .post("/v1/push/history?allow_timestamps=true")
+            .json(backfill)
+            .send()
+            .await
+            .assert_success();
+        server
+            .post("/v1/push/history?allow_timestamps=true")
+            .json(json!({"n": 3, "created_at": "yesterday"}))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        server
+            .post("/v1/push/history/batch?allow_timestamps=true")
+            .json(json!([{"n": 4, "updated_at": "2021-01-01T00:00:00Z"}, {"n": 5}]))
+            .send()
+            .await
+            .assert_success();
+
+        let rows = server.get("/v1/query/history?order_by=id").send().await.assert_success();
+        assert_ne!(rows[0]["created_at"], "2020-05-01 10:00:00");
+        assert_eq!(rows[1]["created_at"], "2020-05-01 10:00:00");
+        assert_ne!(rows[1]["updated_at"], Value::Null);
+        assert_eq!(rows[2]["updated_at"], "2021-01-01 00:00:00");
+        assert_ne!(rows[3]["updated_at"], Value::Null);
+        assert_eq!(rows.as_array().unwrap().len(), 4);
+        let stats = server.get("/v1/tables/history").send().await.assert_success();
+        assert_eq!(stats["column_count"], 4);
+    }
+
+    #[tokio::test]
+    async fn test_backfilled_timestamps_need_an_admin() {
+        let server = TestServer::spawn().await;
+        let admin = server.create_user("admin@vibedb.dev", ADMIN_ROLE).await.token;
+        let user = server.create_user("user@vibedb.dev", USER_ROLE).await.token;
+        let backfill = json!({"n": 1, "created_at": "2020-05-01T12:00:00Z"});
+
+        for uri in ["/v1/push/history?allow_timestamps=true", "/v1/push/history/batch?allow_timestamps=true"] {
+            let body = if uri.contains("batch") { json!([backfill]) } else { backfill.clone() };
+            server
+                .post(uri)
+                .bearer(&user)
+                .json(body)
+                .send()
+                .await
+                .assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+        }
+        server
+            .post("/v1/push/history?allow_timestamps=true")
+            .bearer(&admin)
+            .json(backfill)
+            .send()
+            .await
+            .assert_success();
+        let rows = server.get("/v1/query/history").bearer(&user).send().await.assert_success();
+        assert_eq!(rows.as_array().unwrap().len(), 1);
+        assert_eq!(rows[0]["created_at"], "2020-05-01 12:00:00");
+    }
+
+    #[tokio::test]
+    async fn test_confident_batch_push_nulls_outliers() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        let batch: Vec<Value> = (0..1000)
+            .map(|n| match n {
+                0 | 10 | 500 | 998 | 999 => json!({"n": n, "reading": "N/A", "note": n}),
+                _ => json!({"n": n, "reading": n * 2, "note": if n % 2 == 0 { json!(n) } else { json!("odd") }}),
+            })
+            .collect();
+
+        let pushed = server
+            .post("/v1/push/sensors/batch?inference=confident")
+            .json(json!(batch))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(pushed["inserted"], 1000);
+        let report = &pushed["inference"]["columns"];
+        assert_eq!(report.as_object().unwrap().len(), 1, "note is too mixed: {}", report);
+        assert_eq!(report["reading"]["sqlite_type"], "INTEGER");
+        assert_eq!(report["reading"]["outlier_count"], 5);
+        let indexes: Vec<_> = report["reading"]["outliers"].as_array().unwrap().iter().map(|o| o["index"].clone()).collect();
+        assert_eq!(indexes, vec![json!(0), json!(10), json!(500), json!(998), json!(999)]);
+        assert_eq!(report["reading"]["outliers"][0]["value"], "N/A");
+
+        let stats = server.get("/v1/tables/sensors").send().await.assert_success();
+        let col_type = |name: &str| {
+            stats["columns"].as_array().unwrap().iter().find(|c| c["name"] == name).unwrap()["col_type"].clone()
+        };
+        assert_eq!(col_type("reading"), "INTEGER");
+        let rows = server.get("/v1/query/sensors?order_by=n&limit=2").send().await.assert_success();
+        assert_eq!(rows[0]["reading"], Value::Null);
+        assert_eq!(rows[1]["reading"], 2);
+
+        // Columns already declared TEXT keep their strings
+        server.post("/v1/push/labels").json(json!({"code": "A1"})).send().await.assert_success();
+        let mut batch: Vec<Value> = (0..100).map(|n| json!({"code": n})).collect();
+        batch.push(json!({"code": "B2"}));
+        let pushed = server
+            .post("/v1/push/labels/batch?inference=confident")
+            .json(json!(batch))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(pushed["inference"]["columns"], json!({}));
+        let rows = server.get("/v1/query/labels?code=B2").send().await.assert_success();
+        assert_eq!(rows.as_array().unwrap().len(), 1);
+
+        server
+            .post("/v1/push/labels/batch?inference=confident&confidence=0.4")
+            .json(json!([{"code": 1}]))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+
+        // Single pushes have nothing to infer over
+        for query in ["inference=confident", "confidence=0.9"] {
+            server
+                .post(&format!("/v1/push/labels?{}", query))
+                .json(json!({"code": 1}))
+                .send()
+                .await
+                .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_writes_are_attributed_to_the_caller() {
+        let server = TestServer::spawn().await;
+        let admin = server.create_user("ops@example.com", ADMIN_ROLE).await;
+        let writer = server.create_user("writer@example.com", USER_ROLE).await;
+        server.post("/v1/push/notes").json(json!({"text": "seed"})).send().await.assert_success();
+        server
+            .put("/v1/tables/notes/settings")
+            .bearer(&admin.token)
+            .json(json!({"audit": true}))
+            .send()
+            .await
+            .assert_success();
+
+        let mut events = server.subscribe("notes", None).await;
+        server
+            .post("/v1/push/notes")
+            .bearer(&writer.token)
+            .json(json!({"text": "signed"}))
+            .send()
+            .await
+            .assert_success();
+        let event = events.next().await;
+        assert_eq!(event["actor"], json!({"id": writer.id, "email": "writer@example.com"}));
+        server.post("/v1/update/notes/1").json(json!({"text": "edited"})).send().await.assert_success();
+        let event = events.next().await;
+        assert_eq!(event["event"], "update");
+        assert_eq!(event["actor"], Value::Null);
+
+        let audit = server
+            .store
+            .query_simple("SELECT op, actor_id, actor_email FROM vibe_audit ORDER BY id".to_string())
+            .await
+            .unwrap();
+        let actors: Vec<_> = audit.iter().map(|row| (row[0].1.clone(), row[1].1.clone())).collect();
+        assert_eq!(actors, vec![(json!("insert"), json!(writer.id)), (json!("update"), Value::Null)]);
+        assert_eq!(audit[0][2].1, "writer@example.com");
+
+        let bucket = server
+            .post("/v1/storage/buckets")
+            .bearer(&writer.token)
+            .json(json!({"name": "attachments"}))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(bucket["owner_id"], writer.id);
+        let upload = |path: &str, token: Option<&str>| {
+            let mut request = server.post(&format!("/v1/storage/object/attachments/{}", path)).body(
+                "--X\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+                 Content-Type: text/plain\r\n\r\nhello\r\n--X--\r\n",
+                "multipart/form-data; boundary=X",
+            );
+            if let Some(token) = token {
+                request = request.bearer(token);
+            }
+            request
+        };
+        let signed = upload("signed.txt", Some(&writer.token)).send().await.assert_success();
+        assert_eq!(signed["owner_id"], writer.id);
+        let anonymous = upload("anonymous.txt", None).send().await.assert_success();
+        assert_eq!(anonymous["owner_id"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_insert_events_are_coalesced_when_configured() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        server.post("/v1/push/ticks").json(json!({"n": 0})).send().await.assert_success();
+        server
+            .put("/v1/tables/ticks/settings")
+            .json(json!({"coalesce_inserts_ms": 200}))
+            .send()
+            .await
+            .assert_success();
+        let mut events = server.subscribe("ticks", None).await;
+
+        let pushes = (1..=20).map(|n| server.post("/v1/push/ticks").json(json!({"n": n})).send());
+        let mut ids: Vec<i64> = futures::future::join_all(pushes)
+            .await
+            .into_iter()
+            .map(|response| response.assert_success()["id"].as_i64().unwrap())
+            .collect();
+        ids.sort();
+
+        let event = events.next().await;
+        assert_eq!(event["event"], "inserts");
+        assert_eq!(event["count"], 20);
+        let mut sent: Vec<i64> = serde_json::from_value(event["ids"].clone()).unwrap();
+        sent.sort();
+        assert_eq!(sent, ids);
+
+        // Other events are not held back
+        server.post("/v1/update/ticks/1").json(json!({"n": 100})).send().await.assert_success();
+        assert_eq!(events.next().await["event"], "update");
+    }
+
+    #[tokio::test]
+    async fn test_stream_coalesces_events_per_subscriber() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        server.post("/v1/push/ticks").json(json!({"n": 0})).send().await.assert_success();
+        let mut batched = server.subscribe("ticks?coalesce_ms=250", None).await;
+        let mut plain = server.subscribe("ticks", None).await;
+
+        let pushes = (1..=30).map(|n| server.post("/v1/push/ticks").json(json!({"n": n})).send());
+        for response in futures::future::join_all(pushes).await {
+            response.assert_success();
+        }
+
+        let mut seqs = Vec::new();
+        let mut messages = 0;
+        while seqs.len() < 30 {
+            let message = batched.next().await;
+            assert_eq!(message["event"], "batch");
+            let events = message["events"].as_array().unwrap();
+            assert_eq!(message["count"], events.len());
+            assert!(events.len() > 1);
+            seqs.extend(events.iter().map(|event| event["seq"].as_i64().unwrap()));
+            messages += 1;
+        }
+        assert!(messages < 30);
+        assert_eq!(seqs, (2..=31).collect::<Vec<_>>());
+
+        // Other subscribers still get one message per event
+        assert_eq!(plain.next().await["event"], "insert");
+    }
+
+    #[tokio::test]
+    async fn test_stream_messages_are_compressed_on_request() {
+        use base64::Engine;
+
+        let server = TestServer::builder().anonymous().spawn().await;
+        server.post("/v1/push/ticks").json(json!({"n": 0})).send().await.assert_success();
+        server
+            .get("/v1/stream/ticks?compress=brotli")
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+
+        for codec in [Compression::Gzip, Compression::Zstd] {
+            let uri = format!("ticks?compress={}&coalesce_ms=50", codec.as_str());
+            let mut events = server.subscribe(&uri, None).await;
+            server.post("/v1/push/ticks").json(json!({"n": 1})).send().await.assert_success();
+
+            let message = events.next().await;
+            assert_eq!(message["encoding"], codec.as_str());
+            let payload = base64::engine::general_purpose::STANDARD
+                .decode(message["payload"].as_str().unwrap())
+                .unwrap();
+            let batch: Value = serde_json::from_slice(&codec.decompress(&payload).unwrap()).unwrap();
+            assert_eq!(batch["event"], "batch");
+            assert_eq!(batch["events"][0]["data"]["n"], 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_events_carry_only_changed_fields() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        server
+            .post("/v1/push/devices")
+            .json(json!({"name": "probe", "firmware": "1.0", "battery": 80, "meta": {"rack": 4}}))
+            .send()
+            .await
+            .assert_success();
+        let mut events = server.subscribe("devices", None).await;
+
+        server
+            .post("/v1/update/devices/1")
+            .json(json!({"name": "probe", "firmware": "1.1", "meta": {"rack": 4}}))
+            .send()
+            .await
+            .assert_success();
+        let event = events.next().await;
+        assert_eq!(event["data"], json!({"firmware": "1.1"}));
+        assert_eq!(event["changed"], json!(["firmware"]));
+        assert_eq!(event["noop"], false);
+
+        // "80" lands in an INTEGER column as 80, so nothing changes
+        server
+            .post("/v1/update/devices/1")
+            .json(json!({"battery": "80", "firmware": "1.1"}))
+            .send()
+            .await
+            .assert_success();
+        let event = events.next().await;
+        assert_eq!(event["data"], json!({}));
+        assert_eq!(event["changed"], json!([]));
+        assert_eq!(event["noop"], true);
+    }
+
+    #[tokio::test]
+    async fn test_change_lists_omit_hidden_columns() {
+        let server = TestServer::spawn().await;
+        let admin = server.create_user("admin@vibedb.dev", ADMIN_ROLE).await.token;
+        let user = server.create_user("user@vibedb.dev", USER_ROLE).await.token;
+        server
+            .post("/v1/push/products")
+            .bearer(&admin)
+            .json(json!({"name": "widget", "cost_price": 3}))
+            .send()
+            .await
+            .assert_success();
+        server
+            .put("/v1/tables/products/settings")
+            .bearer(&admin)
+            .json(json!({"hidden_columns": ["cost_price"], "visible_to": "admin"}))
+            .send()
+            .await
+            .assert_success();
+
+        let mut admin_events = server.subscribe("products", Some(&admin)).await;
+        let mut user_events = server.subscribe("products", Some(&user)).await;
+        let mut user_sync = server.subscribe_uri("/v1/sync/products", Some(&user)).await;
+        while user_sync.next().await["event"] != "snapshot_complete" {}
+
+        server
+            .post("/v1/update/products/1")
+            .bearer(&admin)
+            .json(json!({"name": "gadget", "cost_price": 4}))
+            .send()
+            .await
+            .assert_success();
+        server
+            .post("/v1/update/products/1/increment")
+            .bearer(&admin)
+            .json(json!({"field": "cost_price"}))
+            .send()
+            .await
+            .assert_success();
+
+        assert_eq!(admin_events.next().await["changed"], json!(["cost_price", "name"]));
+        assert_eq!(admin_events.next().await["changed"], json!(["cost_price"]));
+        for events in [&mut user_events, &mut user_sync] {
+            let update = events.next().await;
+            assert_eq!(update["changed"], json!(["name"]));
+            assert!(update["data"].get("cost_price").is_none());
+            let increment = events.next().await;
+            assert_eq!(increment["changed"], json!([]));
+            assert!(increment["data"].get("cost_price").is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pathological_nesting_is_rejected() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        let mut deep = json!(1);
+        for _ in 0..crate::guard::DEFAULT_MAX_NESTING_DEPTH {
+            deep = json!([deep]);
+        }
+        let error = server
+            .post("/v1/push/docs")
+            .json(json!({"deep": deep}))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        assert!(error["message"].as_str().unwrap().contains("'deep'"));
+
+        let big = vec!["x".repeat(1024); 1100];
+        server
+            .post("/v1/push/docs/batch")
+            .json(json!([{"ok": {"nested": true}}, {"big": big}]))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        server.post("/v1/push/docs").json(json!({"ok": {"nested": [1, 2]}})).send().await.assert_success();
+    }
+
+    #[tokio::test]
+    async fn test_update_can_clear_fields_with_null() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        server
+            .post("/v1/push/members")
+            .json(json!({"name": "ada", "nickname": "countess", "ghost": null}))
+            .send()
+            .await
+            .assert_success();
+        // Pushed nulls still create nothing
+        let stats = server.get("/v1/tables/members").send().await.assert_success();
+        assert!(stats["columns"].as_array().unwrap().iter().all(|c| c["name"] != "ghost"));
+
+        let mut events = server.subscribe("members", None).await;
+        let body = server
+            .post("/v1/update/members/1")
+            .json(json!({"nickname": null, "ghost": null, "name": "ada"}))
+            .send()
+            .await
+            .json();
+        assert_eq!(body["affected"], 1);
+        let event = events.next().await;
+        assert_eq!(event["data"], json!({"nickname": null}));
+        assert_eq!(event["changed"], json!(["nickname"]));
+
+        let member = server.get("/v1/query/members/1").send().await.assert_success();
+        assert_eq!(member["nickname"], Value::Null);
+        assert_eq!(member["name"], "ada");
+
+        // Only nulls for columns that don't exist: nothing to do
+        let body = server.post("/v1/update/members/1").json(json!({"ghost": null})).send().await.json();
+        assert_eq!(body["message"], "No updates provided");
+        let stats = server.get("/v1/tables/members").send().await.assert_success();
+        assert!(stats["columns"].as_array().unwrap().iter().all(|c| c["name"] != "ghost"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_events_carry_stored_rows_when_enabled() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        server.post("/v1/push/feed").json(json!({"a": 1, "b": "x"})).send().await.assert_success();
+
+        let mut lean = server.subscribe("feed", None).await;
+        server.post("/v1/push/feed").json(json!({"a": 2})).send().await.assert_success();
+        let event = lean.next().await;
+        assert_eq!(event["event"], "insert");
+        assert_eq!(event["data"], json!({"a": 2}));
+
+        server
+            .put("/v1/tables/feed/settings")
+            .json(json!({"broadcast_rows": true}))
+            .send()
+            .await
+            .assert_success();
+        let mut full = server.subscribe("feed", None).await;
+        server.post("/v1/push/feed").json(json!({"a": 3})).send().await.assert_success();
+        let event = full.next().await;
+        let data = event["data"].as_object().unwrap();
+        assert_eq!(event["id"], 3);
+        assert_eq!(data["id"], 3);
+        assert_eq!(data["a"], 3);
+        assert_eq!(data["b"], Value::Null);
+        assert!(data["created_at"].is_string());
+        assert!(data["updated_at"].is_string());
+
+        server.post("/v1/update/feed/1").json(json!({"a": 10})).send().await.assert_success();
+        let event = full.next().await;
+        assert_eq!(event["event"], "update");
+        assert_eq!(event["data"]["b"], "x");
+        assert_eq!(event["data"]["a"], 10);
+    }
+
+    #[tokio::test]
+    async fn test_missing_collection_is_not_found_for_every_operation() {
+        let server = TestServer::builder().anonymous().spawn().await;
+
+        for (method, uri, body) in [
+            (Method::GET, "/v1/query/ghosts", None),
+            (Method::GET, "/v1/query/ghosts/1", None),
+            (Method::POST, "/v1/update/ghosts/1", Some(json!({"name": "x"}))),
+            (Method::POST, "/v1/delete/ghosts/1", None),
+            (Method::POST, "/v1/delete/ghosts?name=x", None),
+        ] {
+            let mut request = server.request(method, uri);
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+            request.send().await.assert_error(StatusCode::NOT_FOUND, "TABLE_NOT_FOUND");
+        }
+        // Nothing was created along the way
+        server.get("/v1/tables/ghosts").send().await.assert_error(StatusCode::NOT_FOUND, "TABLE_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_filtered_delete_count_precheck_matches_delete() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        let tickets = json!([
+            {"status": "closed", "team": "a"},
+            {"status": "closed", "team": "b"},
+            {"status": "closed", "team": "a"},
+            {"status": "open", "team": "a"}
+        ]);
+        server.post("/v1/push/tickets/batch").json(tickets).send().await.assert_success();
+
+        let uri = "/v1/delete/tickets?status=closed&team=a";
+        let precheck = server.post(&format!("{}&count_only=true", uri)).send().await.json();
+        assert_eq!(precheck["count"], 2);
+        let rows = server.get("/v1/query/tickets").send().await.assert_success();
+        assert_eq!(rows.as_array().unwrap().len(), 4);
+
+        let deleted = server.post(uri).send().await.json();
+        assert_eq!(deleted["affected"], precheck["count"]);
+        let rows = server.get("/v1/query/tickets?order_by=id").send().await.assert_success();
+        assert_eq!(rows.as_array().unwrap().len(), 2);
+        assert_eq!(rows[0]["team"], "b");
+
+        server
+            .post("/v1/delete/tickets?count_only=true")
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        server
+            .post("/v1/delete/tickets?status=open&count_only=yes")
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+    }
+
+    #[tokio::test]
+    async fn test_long_keys_are_rejected_or_aliased() {
+        let long_key = format!("reading_{}", "x".repeat(192));
+        assert_eq!(long_key.len(), 200);
+
+        let server = TestServer::builder().anonymous().spawn().await;
+        let body = server
+            .post("/v1/push/sensors")
+            .json(json!({ long_key.as_str(): 1 }))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_IDENTIFIER");
+        let message = body["message"].as_str().unwrap().to_string();
+        assert!(message.contains(&long_key) && message.contains("128"), "{}", message);
+
+        let mut config = VibeConfig::default();
+        config.identifiers.truncate = true;
+        let server = TestServer::builder().anonymous().config(config).spawn().await;
+        let pushed = server
+            .post("/v1/push/sensors")
+            .json(json!({ long_key.as_str(): 1, "site": "a" }))
+            .send()
+            .await
+            .assert_success();
+        let stored = pushed["aliases"][&long_key].as_str().unwrap().to_string();
+        assert_eq!(stored.len(), 128);
+        assert!(stored.starts_with("reading_xxx"));
+        assert_eq!(pushed["columns_added"].as_array().unwrap().len(), 2);
+        server
+            .post("/v1/push/sensors")
+            .json(json!({ long_key.as_str(): 2, "site": "b" }))
+            .send()
+            .await
+            .assert_success();
+
+        let rows = server
+            .get(&format!("/v1/query/sensors?{}=2&order_by={}", long_key, long_key))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(rows.as_array().unwrap().len(), 1);
+        assert_eq!(rows[0]["site"], "b");
+        assert_eq!(rows[0][&stored], 2);
+    }
+
+    #[tokio::test]
+    async fn test_pushes_can_sanitize_keys() {
+        let server = TestServer::builder().anonymous().spawn().await;
+
+        // Invalid keys are refused unless sanitizing is asked for
+        let payload = json!({"user-name": "a", "user name": "b", "Score": 1, "score": 2});
+        server
+            .post("/v1/push/people")
+            .json(payload.clone())
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_IDENTIFIER");
+        server
+            .post("/v1/push/people?sanitize_keys=reject")
+            .json(payload.clone())
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        let pushed = server
+            .post("/v1/push/people?sanitize_keys=suffix")
+            .json(payload)
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(
+            pushed["aliases"],
+            json!({"score": "score_2", "user name": "user_name", "user-name": "user_name_2"})
+        );
+
+        let error = server
+            .post("/v1/push/people/batch?sanitize_keys=reject")
+            .json(json!([{"user_name": "c"}, {"Tag": 1, "tag": 2}]))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        assert_eq!(error["details"]["index"], 1);
+
+        let rows = server.get("/v1/query/people").send().await.assert_success();
+        assert_eq!(rows.as_array().unwrap().len(), 1);
+        assert_eq!((&rows[0]["user_name"], &rows[0]["user_name_2"]), (&json!("b"), &json!("a")));
+        assert_eq!((&rows[0]["Score"], &rows[0]["score_2"]), (&json!(1), &json!(2)));
+    }
+
+    #[tokio::test]
+    async fn test_snake_case_keys_round_trip() {
+        let mut config = VibeConfig::default();
+        config.identifiers.key_case = KeyCase::SnakeCase;
+        let server = TestServer::builder().anonymous().config(config).spawn().await;
+
+        let pushed = server
+            .post("/v1/push/requests")
+            .json(json!({"userId": 7, "HTTPStatus": 200, "path": "/"}))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(pushed["aliases"], json!({"HTTPStatus": "http_status", "userId": "user_id"}));
+        server
+            .post("/v1/push/requests")
+            .json(json!({"user_id": 8, "HTTPStatus": 404, "path": "/x"}))
+            .send()
+            .await
+            .assert_success();
+
+        let rows = server.get("/v1/query/requests?userId=7").send().await.assert_success();
+        assert_eq!(rows.as_array().unwrap().len(), 1);
+        assert_eq!(rows[0]["user_id"], 7);
+        assert!(rows[0].get("userId").is_none());
+
+        let rows = server
+            .get("/v1/query/requests?original_keys=true&order_by=HTTPStatus")
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(rows[0]["userId"], 7);
+        assert_eq!(rows[1]["HTTPStatus"], 404);
+        assert_eq!(rows[1]["path"], "/x");
+        let id = rows[1]["id"].as_i64().unwrap();
+        let row = server
+            .get(&format!("/v1/query/requests/{}?original_keys=true", id))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(row["userId"], 8);
+    }
+
+    #[tokio::test]
+    async fn test_frozen_schema_ignores_unknown_keys() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        server.post("/v1/push/events").json(json!({"kind": "a"})).send().await.assert_success();
+        server
+            .put("/v1/tables/events/settings")
+            .json(json!({"freeze": true}))
+            .send()
+            .await
+            .assert_success();
+
+        let pushed = server
+            .post("/v1/push/events")
+            .json(json!({"KIND": "b", "typo": 1, "bad-key": 2}))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(pushed["columns_added"], json!(["KIND"]));
+        server
+            .post("/v1/update/events/1")
+            .json(json!({"kind": "c", "extra": true}))
+            .send()
+            .await
+            .assert_success();
+
+        let rows = server.get("/v1/query/events?order_by=id").send().await.assert_success();
+        assert_eq!(rows[0]["kind"], "c");
+        assert_eq!(rows[1]["kind"], "b");
+        assert!(rows[1].get("typo").is_none());
+        let stats = server.get("/v1/tables/events").send().await.assert_success();
+        assert_eq!(stats["column_count"], 4);
+    }
+
+    #[tokio::test]
+    async fn test_batch_failure_reports_item_index_and_constraint() {
+        let server = TestServer::spawn().await;
+        let admin = server.create_user("admin@vibedb.dev", ADMIN_ROLE).await.token;
+        server.post("/v1/push/products").json(json!({"sku": "A"})).send().await.assert_success();
+        server
+            .post("/v1/sql/execute")
+            .bearer(&admin)
+            .json(json!({"query": "CREATE UNIQUE INDEX idx_products_sku ON products(sku)"}))
+            .send()
+            .await
+            .assert_success();
+
+        let error = server
+            .post("/v1/push/products/batch")
+            .json(json!([{"sku": "B"}, {"sku": "A"}, {"sku": "C"}]))
+            .send()
+            .await
+            .assert_error(StatusCode::CONFLICT, "CONSTRAINT_VIOLATION");
+        assert_eq!(error["retryable"], false);
+        assert!(error.get("retry_after_ms").is_none());
+        assert_eq!(error["details"]["index"], 1);
+        assert_eq!(error["details"]["constraint"], "products.sku");
+        assert_eq!(error["details"]["constraint_type"], "unique");
+
+        let error = server
+            .post("/v1/push/products/batch?allow_timestamps=true")
+            .bearer(&admin)
+            .json(json!([{"sku": "D"}, {"sku": "E", "created_at": "yesterday"}]))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        assert_eq!(error["details"]["index"], 1);
+        assert_eq!(error["details"]["fields"], json!(["created_at"]));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_push_is_dead_lettered_and_retried() {
+        let mut config = VibeConfig::default();
+        config.base_path.trust_forwarded = true;
+        let server = TestServer::builder().config(config).spawn().await;
+        let admin = server.create_user("admin@vibedb.dev", ADMIN_ROLE).await.token;
+        let user = server.create_user("user@vibedb.dev", USER_ROLE).await.token;
+
+        server.post("/v1/push/products").json(json!({"sku": "A"})).send().await.assert_success();
+        server
+            .post("/v1/sql/execute")
+            .bearer(&admin)
+            .json(json!({"query": "CREATE UNIQUE INDEX idx_products_sku ON products(sku)"}))
+            .send()
+            .await
+            .assert_success();
+        server
+            .put("/v1/tables/products/settings")
+            .bearer(&admin)
+            .json(json!({"dead_letter": true}))
+            .send()
+            .await
+            .assert_success();
+
+        server
+            .post("/v1/push/products")
+            .bearer(&user)
+            .header("x-forwarded-for", "203.0.113.7, 10.0.0.1")
+            .json(json!({"sku": "A", "qty": 3}))
+            .send()
+            .await
+            .assert_error(StatusCode::CONFLICT, "CONSTRAINT_VIOLATION");
+
+        // Captured in the background
+        let uri = "/v1/dead-letters?collection=products&error_code=constraint_violation";
+        let mut letters = Value::Null;
+        for _ in 0..50 {
+            letters = server.get(uri).bearer(&admin).send().await.assert_success();
+            if letters.as_array().is_some_and(|l| !l.is_empty()) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let letter = &letters[0];
+        assert_eq!(letter["payload"], json!({"sku": "A", "qty": 3}));
+        assert_eq!(letter["client_ip"], "203.0.113.7");
+        assert_eq!(letter["user_email"], "user@vibedb.dev");
+        assert_eq!(letter["resolved_at"], Value::Null);
+        server.get("/v1/dead-letters").bearer(&user).send().await.assert_status(StatusCode::FORBIDDEN);
+
+        let retry = format!("/v1/dead-letters/{}/retry", letter["id"]);
+        server
+            .post("/v1/sql/execute")
+            .bearer(&admin)
+            .json(json!({"query": "DROP INDEX idx_products_sku"}))
+            .send()
+            .await
+            .assert_success();
+        let retried = server.post(&retry).bearer(&admin).send().await.assert_success();
+        assert_eq!(retried["dead_letter"]["attempts"], 1);
+        assert_eq!(retried["dead_letter"]["resolved_id"], retried["push"]["id"]);
+
+        let rows = server.get("/v1/query/products?sku=A").send().await.assert_success();
+        assert_eq!(rows.as_array().unwrap().len(), 2);
+        assert_eq!(rows[1]["qty"], 3);
+        server.post(&retry).bearer(&admin).send().await.assert_error(StatusCode::CONFLICT, "CONFLICT");
+        let open = server.get("/v1/dead-letters?resolved=false").bearer(&admin).send().await.assert_success();
+        assert_eq!(open, json!([]));
+    }
+
+    /// Serves `/feed` with two readings, plus a third from the second
+    /// request on; `/broken` has no array at `/data`
+    async fn spawn_feed() -> String {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let feed = move |headers: HeaderMap| {
+            let hits = Arc::clone(&hits);
+            async move {
+                assert_eq!(headers["x-api-key"], "s3cret");
+                let mut readings = vec![
+                    json!({"station": "a", "ts": 1, "main": {"temp": 3.5}}),
+                    json!({"station": "b", "ts": 1, "main": {"temp": 4.0}}),
+                    json!("garbage"),
+                ];
+                if hits.fetch_add(1, Ordering::SeqCst) > 0 {
+                    readings.push(json!({"station": "a", "ts": 2, "main": {"temp": 5.0}}));
+                }
+                Json(json!({"data": readings}))
+            }
+        };
+        let app = Router::new()
+            .route("/feed", get(feed))
+            .route("/broken", get(|| async { Json(json!({"data": {"not": "an array"}})) }))
+            .route(
+                "/endless",
+                get(|| async {
+                    let chunk = axum::body::Bytes::from(vec![b' '; 64 * 1024]);
+                    let chunks = futures::StreamExt::map(futures::stream::repeat(chunk), Ok::<_, std::io::Error>);
+                    axum::body::Body::from_stream(chunks)
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_pull_source_dedups_and_logs_runs() {
+        let server = TestServer::spawn().await;
+        let admin = server.create_user("admin@vibedb.dev", "admin").await;
+        let alice = server.create_user("alice@vibedb.dev", "user").await;
+        let feed = spawn_feed().await;
+
+        let definition = json!({
+            "url": format!("{}/feed", feed),
+            "headers": {"x-api-key": "s3cret"},
+            "interval_secs": 3600,
+            "collection": "readings",
+            "records_pointer": "/data",
+            "fields": {"station": "station", "ts": "observed_at", "/main/temp": "temperature"},
+            "dedup_keys": ["station", "observed_at"]
+        });
+        server
+            .post("/v1/sources")
+            .bearer(&alice.token)
+            .json(definition.clone())
+            .send()
+            .await
+            .assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+        let source = server
+            .post("/v1/sources")
+            .bearer(&admin.token)
+            .json(definition.clone())
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(source["headers"]["x-api-key"], "***");
+        let id = source["id"].as_i64().unwrap();
+
+        let run = |path: &'static str| {
+            let uri = format!("/v1/sources/{}/{}", id, path);
+            server.post(&uri).bearer(&admin.token).send()
+        };
+        let first = run("run").await.assert_success();
+        assert_eq!(
+            (&first["status"], &first["fetched"], &first["inserted"], &first["skipped"], &first["errors"]),
+            (&json!("partial"), &json!(3), &json!(2), &json!(0), &json!(1))
+        );
+        assert_eq!(first["error"], "Invalid payload: Record is not an object");
+        let second = run("run").await.assert_success();
+        assert_eq!((&second["fetched"], &second["inserted"], &second["skipped"]), (&json!(4), &json!(1), &json!(2)));
+
+        let rows = server
+            .get("/v1/query/readings?order_by=id")
+            .bearer(&admin.token)
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(rows.as_array().unwrap().len(), 3);
+        assert_eq!(rows[2]["temperature"], 5.0);
+        assert_eq!(rows[2]["observed_at"], 2);
+
+        let runs = server
+            .get(&format!("/v1/sources/{}/runs", id))
+            .bearer(&admin.token)
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(runs.as_array().unwrap().len(), 2);
+        assert_eq!(runs[0]["inserted"], 1);
+
+        // Redacted headers keep their stored value on update
+        let mut broken = definition.clone();
+        broken["headers"]["x-api-key"] = json!("***");
+        broken["url"] = json!(format!("{}/broken", feed));
+        server
+            .put(&format!("/v1/sources/{}", id))
+            .bearer(&admin.token)
+            .json(broken)
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(server.state.sources.get(id).await.unwrap().definition.headers["x-api-key"], "s3cret");
+
+        for _ in 0..crate::sources::MAX_CONSECUTIVE_FAILURES {
+            let failed = run("run").await.assert_success();
+            assert_eq!(failed["status"], "failed");
+            assert_eq!(failed["error"], "Expected an array at '/data'");
+        }
+        let source = server
+            .get(&format!("/v1/sources/{}", id))
+            .bearer(&admin.token)
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(source["enabled"], false);
+        assert_eq!(source["consecutive_failures"], 5);
+        assert!(server.state.sources.due().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pull_source_backs_off_by_the_clock() {
+        use crate::clock::Clock;
+        use crate::sources::MAX_CONSECUTIVE_FAILURES;
+
+        let clock = ManualClock::new();
+        let server = TestServer::builder().clock(clock.clone()).spawn().await;
+        let admin = server.create_user("admin@vibedb.dev", "admin").await;
+        let feed = spawn_feed().await;
+
+        let source = server
+            .post("/v1/sources")
+            .bearer(&admin.token)
+            .json(json!({
+                "url": format!("{}/broken", feed),
+                "interval_secs": 600,
+                "collection": "readings",
+                "records_pointer": "/data"
+            }))
+            .send()
+            .await
+            .assert_success();
+        let id = source["id"].as_i64().unwrap();
+        let sources = &server.state.sources;
+        let due = || async { sources.due().await.unwrap().iter().any(|source| source.id == id) };
+
+        // Doubling from the interval, capped at an hour
+        for (failures, backoff) in (1..=MAX_CONSECUTIVE_FAILURES).zip([1200, 2400, 3600, 3600, 3600]) {
+            assert!(due().await, "source not due before failure {}", failures);
+            let run = sources.run(&server.state, id).await.unwrap();
+            assert_eq!(run.status, "failed");
+
+            let source = sources.get(id).await.unwrap();
+            assert_eq!(source.consecutive_failures, failures);
+            assert_eq!(source.next_run_at, clock.now().timestamp() + backoff);
+            assert_eq!(source.definition.enabled, failures < MAX_CONSECUTIVE_FAILURES);
+
+            clock.advance(Duration::from_secs(backoff as u64 - 1));
+            assert!(!due().await);
+            clock.advance(Duration::from_secs(1));
+        }
+        // Disabled sources are never due again
+        assert!(!due().await);
+        clock.advance(Duration::from_secs(86400));
+        assert!(!due().await);
+    }
+
+    #[tokio::test]
+    async fn test_pull_source_stops_reading_oversized_responses() {
+        let server = TestServer::spawn().await;
+        let admin = server.create_user("admin@vibedb.dev", "admin").await;
+        let feed = spawn_feed().await;
+
+        let source = server
+            .post("/v1/sources")
+            .bearer(&admin.token)
+            .json(json!({
+                "url": format!("{}/endless", feed),
+                "interval_secs": 3600,
+                "collection": "readings",
+                "fields": {"station": "station"}
+            }))
+            .send()
+            .await
+            .assert_success();
+        let run = server
+            .post(&format!("/v1/sources/{}/run", source["id"]))
+            .bearer(&admin.token)
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(run["status"], "failed");
+        assert_eq!(run["error"], "Response is larger than 16777216 bytes");
+    }
+
+    #[tokio::test]
+    async fn test_export_snapshot_ndjson() {
+        let server = TestServer::spawn().await;
+        let admin = server.create_user("ops@example.com", ADMIN_ROLE).await;
+        let user = server.create_user("dev@example.com", USER_ROLE).await;
+        for (collection, n) in [("orders", 3), ("customers", 2)] {
+            let batch: Vec<Value> = (0..n).map(|i| json!({"n": i})).collect();
+            server
+                .post(&format!("/v1/push/{}/batch", collection))
+                .json(Value::Array(batch))
+                .send()
+                .await
+                .assert_success();
+        }
+
+        server.get("/v1/export").bearer(&user.token).send().await.assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+
+        let response = server
+            .get("/v1/export")
+            .bearer(&admin.token)
+            .send()
+            .await
+            .assert_status(StatusCode::OK);
+        let lines = response.ndjson();
+        assert_eq!(lines[0]["type"], "snapshot");
+        assert_eq!(lines[0]["tables"], json!(["customers", "orders"]));
+        let rows: Vec<&Value> = lines.iter().filter(|l| l["type"] == "row").collect();
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[0]["table"], "customers");
+        assert_eq!(rows[0]["row"]["n"], 0);
+        assert_eq!(lines.last().unwrap(), &json!({"type": "end", "rows": 5}));
+
+        let lines = server
+            .get("/v1/export?tables=orders")
+            .bearer(&admin.token)
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .ndjson();
+        assert_eq!(lines.last().unwrap()["rows"], 3);
+        server
+            .get("/v1/export?tables=orders,missing")
+            .bearer(&admin.token)
+            .send()
+            .await
+            .assert_error(StatusCode::NOT_FOUND, "TABLE_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_cast_hints_compare_text_columns_numerically() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        // The column is created as TEXT, so its numbers compare as strings
+        let batch: Vec<Value> = ["9", "100", "25"].iter().map(|p| json!({"price": p})).collect();
+        server
+            .post("/v1/push/products/batch")
+            .json(Value::Array(batch))
+            .send()
+            .await
+            .assert_success();
+
+        let prices = |rows: Value| -> Vec<Value> {
+            rows.as_array().unwrap().iter().map(|r| r["price"].clone()).collect()
+        };
+        let data = server.get("/v1/query/products?order_by=price").send().await.assert_success();
+        assert_eq!(prices(data), vec![json!("100"), json!("25"), json!("9")]);
+        let data = server
+            .get("/v1/query/products?order_by=price&cast.price=int")
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(prices(data), vec![json!("9"), json!("25"), json!("100")]);
+
+        let data = server.get("/v1/query/products?price=9.0").send().await.assert_success();
+        assert_eq!(data.as_array().unwrap().len(), 0);
+        for uri in ["/v1/query/products?price=9.0::real", "/v1/query/products?price=9.0&cast.price=real"] {
+            let data = server.get(uri).send().await.assert_success();
+            assert_eq!(prices(data), vec![json!("9")], "{}", uri);
+        }
+
+        let data = server.get("/v1/query/products/aggregate?metric=max:price").send().await.assert_success();
+        assert_eq!(data[0]["max:price"], "9");
+        let data = server
+            .get("/v1/query/products/aggregate?metric=max:price&metric=p50:price&cast.price=int")
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(data[0]["max:price"], 100);
+        assert_eq!(data[0]["p50:price"], 25);
+
+        let lines = server
+            .get("/v1/export?tables=products&cast.price=real")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .ndjson();
+        assert_eq!(lines[1]["row"]["price"], 9.0);
+
+        let error = server
+            .get("/v1/query/products?cast.price=date")
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        assert!(error["message"].as_str().unwrap().contains("cast target"));
+
+        // A suffix that isn't a cast target is part of the value
+        let data = server.get("/v1/query/products?price=9::decimal").send().await.assert_success();
+        assert_eq!(data.as_array().unwrap().len(), 0);
+        server.post("/v1/push/hosts").json(json!({"ip": "fe80::abc"})).send().await.assert_success();
+        let data = server.get("/v1/query/hosts?ip=fe80::abc").send().await.assert_success();
+        assert_eq!(data[0]["ip"], "fe80::abc");
+    }
+
+    #[test]
+    fn test_split_cast_hint() {
+        assert_eq!(split_cast_hint("100::int"), ("100", Some(SqliteType::Integer)));
+        assert_eq!(split_cast_hint("::1"), ("::1", None));
+        assert_eq!(split_cast_hint("plain"), ("plain", None));
+        assert_eq!(split_cast_hint("1::blob"), ("1::blob", None));
+        assert_eq!(split_cast_hint("fe80::abc"), ("fe80::abc", None));
+    }
+
+    #[tokio::test]
+    async fn test_column_preview_is_truncated_in_sql() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        let huge = "x".repeat(200_000);
+        for (n, doc) in ["old", "mid", "new", "newest"].iter().enumerate() {
+            server
+                .post("/v1/push/docs")
+                .json(json!({"title": doc, "n": n, "body": huge}))
+                .send()
+                .await
+                .assert_success();
+        }
+        server
+            .post("/v1/push/docs")
+            .json(json!({"title": "untitled", "n": 4}))
+            .send()
+            .await
+            .assert_success();
+        server
+            .post("/v1/tables/docs/computed")
+            .json(json!({"name": "label", "expression": "title || '#' || n", "type": "TEXT"}))
+            .send()
+            .await
+            .assert_success();
+        server
+            .post("/v1/sql/execute")
+            .json(json!({"query": "ALTER TABLE docs ADD COLUMN thumb BLOB"}))
+            .send()
+            .await
+            .assert_success();
+
+        let response = server
+            .get("/v1/tables/docs/preview?max_length=10&sample_size=4")
+            .send()
+            .await
+            .assert_status(StatusCode::OK);
+        assert!(response.text().len() < 4096, "{} bytes", response.text().len());
+        let preview = response.assert_success();
+        assert_eq!((preview["sample_size"].as_u64(), preview["max_length"].as_u64()), (Some(4), Some(10)));
+        let column = |name: &str| {
+            preview["columns"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|c| c["name"] == name)
+                .cloned()
+                .unwrap()
+        };
+
+        let body = column("body");
+        assert_eq!(body["type"], "TEXT");
+        assert_eq!(body["origin"], "pushed");
+        assert_eq!(body["examples"].as_array().unwrap().len(), 3);
+        assert_eq!(body["examples"][0], json!({"value": "xxxxxxxxxx", "truncated": true, "length": 200_000}));
+
+        // Newest first; the oldest row falls outside the sample
+        let titles: Vec<Value> = column("title")["examples"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["value"].clone())
+            .collect();
+        assert_eq!(titles, [json!("untitled"), json!("newest"), json!("new")]);
+        assert_eq!(column("title")["examples"][0]["truncated"], false);
+        assert_eq!(column("n")["examples"][0], json!({"value": 4, "truncated": false, "length": null}));
+
+        assert_eq!(column("id")["origin"], "system");
+        assert_eq!(column("label")["origin"], "computed");
+        assert_eq!(column("label")["examples"][0]["value"], "untitled#4");
+        assert_eq!(column("thumb")["placeholder"], "<blob>");
+        assert_eq!(column("thumb")["examples"], json!([]));
+
+        server
+            .get("/v1/tables/missing/preview")
+            .send()
+            .await
+            .assert_error(StatusCode::NOT_FOUND, "TABLE_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_json_schema_of_collection() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        server
+            .post("/v1/push/devices")
+            .json(json!({"name": "probe", "port": 8080, "load": 0.5, "owner": {"team": "ops"}}))
+            .send()
+            .await
+            .assert_success();
+        server
+            .post("/v1/tables/devices/computed")
+            .json(json!({"name": "label", "expression": "name || ':' || port", "type": "TEXT"}))
+            .send()
+            .await
+            .assert_success();
+
+        let schema = server
+            .get("/v1/schema/devices/jsonschema")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .json();
+        assert_eq!(schema["$schema"], "https://json-schema.org/draft/2020-12/schema");
+        assert_eq!(schema["title"], "devices");
+        let properties = &schema["properties"];
+        assert_eq!(properties["id"], json!({"type": "integer", "readOnly": true, "x-vibedb-system": true}));
+        assert_eq!(properties["created_at"]["type"], json!(["string", "null"]));
+        assert_eq!(properties["created_at"]["readOnly"], true);
+        assert_eq!(properties["name"], json!({"type": ["string", "null"]}));
+        assert_eq!(properties["port"], json!({"type": ["integer", "null"]}));
+        assert_eq!(properties["load"], json!({"type": ["number", "null"]}));
+        assert_eq!(properties["owner"], json!({"type": ["string", "null"]}));
+        assert_eq!(properties["label"]["x-vibedb-generated"], true);
+        assert_eq!(schema["required"].as_array().unwrap().len(), 8);
+
+        server
+            .put("/v1/tables/devices/settings")
+            .json(json!({"hidden_columns": ["port"]}))
+            .send()
+            .await
+            .assert_success();
+        let schema = server.get("/v1/schema/devices/jsonschema").send().await.json();
+        assert!(schema["properties"].get("port").is_none());
+        assert!(!schema["required"].as_array().unwrap().contains(&json!("port")));
+
+        server
+            .get("/v1/schema/missing/jsonschema")
+            .send()
+            .await
+            .assert_error(StatusCode::NOT_FOUND, "TABLE_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_column_descriptions_in_stats_and_json_schema() {
+        let server = TestServer::spawn().await;
+        let admin = server.create_user("admin@vibedb.dev", ADMIN_ROLE).await.token;
+        let user = server.create_user("user@vibedb.dev", USER_ROLE).await.token;
+        server.post("/v1/push/orders").json(json!({"flag_x": 1, "total": 250})).send().await.assert_success();
+
+        server
+            .put("/v1/tables/orders/columns/flag_x/description")
+            .bearer(&user)
+            .json(json!({"description": "nope"}))
+            .send()
+            .await
+            .assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+        server
+            .put("/v1/tables/orders/columns/FLAG_X/description")
+            .bearer(&admin)
+            .json(json!({"description": "Set when the order needs a fraud review"}))
+            .send()
+            .await
+            .assert_success();
+        let described = server
+            .put("/v1/tables/orders/descriptions")
+            .bearer(&admin)
+            .json(json!({"total": "Order total in cents"}))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(described["flag_x"], "Set when the order needs a fraud review");
+        assert_eq!(described["total"], "Order total in cents");
+
+        let error = server
+            .put("/v1/tables/orders/descriptions")
+            .bearer(&admin)
+            .json(json!({"total": "Cents", "ghost": "?"}))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        assert_eq!(error["details"]["fields"], json!(["ghost"]));
+
+        async fn check(server: &TestServer) {
+            let stats = server.get("/v1/tables/orders").send().await.assert_success();
+            let column = |name: &str| {
+                stats["columns"].as_array().unwrap().iter().find(|c| c["name"] == name).cloned().unwrap()
+            };
+            assert_eq!(column("flag_x")["description"], "Set when the order needs a fraud review");
+            assert_eq!(column("total")["description"], "Order total in cents");
+            assert!(column("id").get("description").is_none());
+
+            let schema = server.get("/v1/schema/orders/jsonschema").send().await.json();
+            assert_eq!(schema["properties"]["total"]["description"], "Order total in cents");
+            assert!(schema["properties"]["id"].get("description").is_none());
+        }
+        check(&server).await;
+
+        // Schema evolution and a fresh schema read keep them
+        server.post("/v1/push/orders").json(json!({"flag_x": 0, "note": "gift"})).send().await.assert_success();
+        server.request(Method::DELETE, "/v1/admin/schema-cache").bearer(&admin).send().await.assert_success();
+        check(&server).await;
+
+        // A null removes one
+        server
+            .put("/v1/tables/orders/descriptions")
+            .bearer(&admin)
+            .json(json!({"total": null}))
+            .send()
+            .await
+            .assert_success();
+        let stats = server.get("/v1/tables/orders").send().await.assert_success();
+        assert!(stats["columns"].as_array().unwrap().iter().all(|c| c["name"] != "total" || c.get("description").is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_row_count_cache_tracks_writes_and_reconciles() {
+        let config = VibeConfig {
+            query: crate::config::QueryConfig {
+                row_count_cache: true,
+                ..Default::default()
+            },
+            ..VibeConfig::default()
+        };
+        let server = TestServer::builder().config(config).anonymous().spawn().await;
+        let rows: Vec<Value> = (0..4).map(|n| json!({"n": n, "status": if n < 2 { "done" } else { "open" }})).collect();
+        server.post("/v1/push/orders/batch").json(json!(rows)).send().await.assert_success();
+        let server = &server;
+        let count = |exact: bool| async move {
+            let data = server.get(&format!("/v1/tables/orders?exact={}", exact)).send().await.assert_success();
+            (data["row_count"].as_u64().unwrap(), data["approximate"].as_bool().unwrap())
+        };
+
+        // Seeded by the first read, then served from the counter
+        assert_eq!(count(false).await, (4, false));
+        assert_eq!(count(false).await, (4, true));
+        server.post("/v1/push/orders").json(json!({"n": 4, "status": "open"})).send().await.assert_success();
+        server.post("/v1/push/orders/batch").json(json!([{"n": 5}, {"n": 6}])).send().await.assert_success();
+        assert_eq!(count(false).await, (7, true));
+        server.post("/v1/delete/orders/1").send().await.assert_success();
+        server.post("/v1/delete/orders?status=done").send().await.assert_success();
+        assert_eq!(count(false).await, (5, true));
+        let tables = server.get("/v1/tables").send().await.json();
+        assert_eq!(tables["row_counts"]["orders"], 5);
+        assert_eq!(tables["approximate"], true);
+
+        // Raw SQL isn't counted until reconciliation
+        server
+            .post("/v1/sql/execute")
+            .json(json!({"query": "DELETE FROM orders WHERE n > 4"}))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(count(false).await, (5, true));
+        assert_eq!(count(true).await, (3, false));
+        server.store.execute_simple("INSERT INTO orders (n) VALUES (7)".to_string()).await.unwrap();
+        let drifted = server.state.row_counts.reconcile(&server.store).await.unwrap();
+        assert_eq!(
+            drifted,
+            vec![crate::row_counts::Drift { collection: "orders".to_string(), cached: 3, counted: 4 }]
+        );
+        assert_eq!(count(false).await, (4, true));
+        assert!(server.state.row_counts.reconcile(&server.store).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_row_cache_serves_lookups_until_written() {
+        let config = VibeConfig {
+            query: crate::config::QueryConfig {
+                row_cache_size: 16,
+                ..Default::default()
+            },
+            ..VibeConfig::default()
+        };
+        let server = TestServer::builder().config(config).anonymous().spawn().await;
+        server.post("/v1/push/orders").json(json!({"status": "new"})).send().await.assert_success();
+
+        assert_eq!(server.get("/v1/query/orders/1").send().await.assert_success()["status"], "new");
+        // Changed behind the server's back: a cached hit doesn't see it
+        server
+            .store
+            .execute("UPDATE orders SET status = 'edited' WHERE id = 1".to_string(), vec![])
+            .await
+            .unwrap();
+        assert_eq!(server.get("/v1/query/orders/1").send().await.assert_success()["status"], "new");
+        let stats = server.state.row_cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+
+        server.post("/v1/update/orders/1").json(json!({"status": "paid"})).send().await.assert_success();
+        assert_eq!(server.get("/v1/query/orders/1").send().await.assert_success()["status"], "paid");
+        assert_eq!(server.state.row_cache.stats().misses, 2);
+
+        // New columns are never missing from cached rows
+        server.post("/v1/push/orders").json(json!({"status": "new", "note": "gift"})).send().await.assert_success();
+        let order = server.get("/v1/query/orders/1").send().await.assert_success();
+        assert!(order.as_object().unwrap().contains_key("note"));
+
+        server.post("/v1/delete/orders/1").send().await.assert_success();
+        server.get("/v1/query/orders/1").send().await.assert_error(StatusCode::NOT_FOUND, "TABLE_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_erasure_across_collections() {
+        let server = TestServer::spawn().await;
+        let admin = server.create_user("dpo@vibedb.dev", ADMIN_ROLE).await.token;
+        let user = server.create_user("user@vibedb.dev", USER_ROLE).await.token;
+        let subject = "gone@example.com";
+        async fn seed(server: &TestServer, subject: &str) {
+            for email in [subject, "kept@example.com", subject] {
+                server.post("/v1/push/orders").json(json!({"email": email, "total": 10})).send().await.assert_success();
+            }
+            server.post("/v1/push/tickets").json(json!({"email": subject, "body": "help"})).send().await.assert_success();
+            server.post("/v1/push/metrics").json(json!({"cpu": 0.5})).send().await.assert_success();
+        }
+        seed(&server, subject).await;
+        server.put("/v1/tables/orders/settings").bearer(&admin).json(json!({"audit": true})).send().await.assert_success();
+        server.post("/v1/update/orders/1").json(json!({"total": 12})).send().await.assert_success();
+
+        let request = |mode: &str, dry_run: bool| {
+            json!({
+                "match": {"email": subject},
+                "collections": "*",
+                "mode": mode,
+                "anonymize_fields": ["email", "body"],
+                "anonymize_with": "hash",
+                "dry_run": dry_run
+            })
+        };
+        server.post("/v1/admin/erasure").bearer(&user).json(request("delete", true)).send().await.assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+
+        let report = server.post("/v1/admin/erasure").bearer(&admin).json(request("delete", true)).send().await.assert_success();
+        let by_name = |report: &Value, name: &str| {
+            report["collections"].as_array().unwrap().iter().find(|c| c["collection"] == name).cloned().unwrap()
+        };
+        assert_eq!(report["matched"], 3);
+        assert_eq!(report["erased"], 0);
+        assert_eq!(by_name(&report, "orders")["matched"], 2);
+        assert_eq!(by_name(&report, "metrics")["skipped"], "no match columns");
+        assert_eq!(server.get("/v1/tables/orders").send().await.assert_success()["row_count"], 3);
+
+        let report = server.post("/v1/admin/erasure").bearer(&admin).json(request("delete", false)).send().await.assert_success();
+        assert_eq!(report["erased"], 3);
+        assert_eq!(by_name(&report, "orders")["history_purged"], 1);
+        assert_eq!(report["actor"]["email"], "dpo@vibedb.dev");
+        let report: erasure::ErasureReport = serde_json::from_value(report).unwrap();
+        let signature = report.signature.clone().unwrap();
+        assert!(server.auth.verify_report(&report.signed_content(), &signature));
+        let mut forged = report.clone();
+        forged.erased = 0;
+        assert!(!server.auth.verify_report(&forged.signed_content(), &signature));
+
+        let orders = server.get("/v1/query/orders").send().await.assert_success();
+        assert_eq!(orders.as_array().unwrap().len(), 1);
+        assert_eq!(orders[0]["email"], "kept@example.com");
+        assert_eq!(server.get("/v1/tables/tickets").send().await.assert_success()["row_count"], 0);
+
+        // Anonymizing keeps the rows, with hashed fields where present
+        seed(&server, subject).await;
+        let report = server.post("/v1/admin/erasure").bearer(&admin).json(request("anonymize", false)).send().await.assert_success();
+        assert_eq!(report["erased"], 3);
+        let orders = server.get("/v1/query/orders").send().await.assert_success();
+        let erased: Vec<&Value> = orders.as_array().unwrap().iter().filter(|o| o["email"] != "kept@example.com").collect();
+        assert_eq!(erased.len(), 2);
+        assert!(erased.iter().all(|o| o["email"].as_str().unwrap().starts_with(erasure::HASH_PREFIX) && o["total"] == 10));
+        let ticket = &server.get("/v1/query/tickets").send().await.assert_success()[0];
+        assert_ne!(ticket["body"], "help");
+        assert_eq!(server.get("/v1/tables/metrics").send().await.assert_success()["row_count"], 2);
+
+        // Nothing matches any more
+        let report = server.post("/v1/admin/erasure").bearer(&admin).json(request("anonymize", true)).send().await.assert_success();
+        assert_eq!(report["matched"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_rotate_jwt_secret() {
+        let server = TestServer::spawn().await;
+        let admin = server.create_user("ops@example.com", ADMIN_ROLE).await;
+        let user = server.create_user("dev@example.com", USER_ROLE).await;
+
+        server.post("/v1/auth/rotate-secret").bearer(&user.token).send().await.assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+        server
+            .post("/v1/auth/rotate-secret")
+            .bearer(&admin.token)
+            .json(json!({"secret": "short"}))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+
+        let body = server.post("/v1/auth/rotate-secret").bearer(&admin.token).send().await.json();
+        assert_eq!(body["success"], true);
+        assert_eq!(body["generated"], true);
+        assert!(!body.to_string().contains("secret\":"));
+        // Tokens of the replaced secret are honoured for their lifetime
+        server.get("/v1/auth/me").bearer(&user.token).send().await.assert_success();
+
+        server
+            .post("/v1/auth/rotate-secret")
+            .bearer(&admin.token)
+            .json(json!({"secret": "x".repeat(32)}))
+            .send()
+            .await
+            .assert_success();
+        server.get("/v1/auth/me").bearer(&user.token).send().await.assert_error(StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+    }
+
+    #[tokio::test]
+    async fn test_user_export_is_admin_only_and_can_be_encrypted() {
+        let server = TestServer::spawn().await;
+        let admin = server.create_user("ops@example.com", ADMIN_ROLE).await;
+        let user = server.create_user("dev@example.com", USER_ROLE).await;
+
+        server.get("/v1/auth/export").bearer(&user.token).send().await.assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+        server
+            .post("/v1/auth/import")
+            .bearer(&user.token)
+            .json(json!({"data": {"version": 1, "exported_at": "", "users": []}}))
+            .send()
+            .await
+            .assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+
+        let export = server.get("/v1/auth/export").bearer(&admin.token).send().await.assert_success();
+        let users = export["users"].as_array().unwrap();
+        assert_eq!(users.len(), 2);
+        assert!(users[1]["password_hash"].as_str().unwrap().starts_with("$argon2"));
+
+        server
+            .get("/v1/auth/export")
+            .bearer(&admin.token)
+            .header("x-export-passphrase", "short")
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        let sealed = server
+            .get("/v1/auth/export")
+            .bearer(&admin.token)
+            .header("x-export-passphrase", "a long enough passphrase")
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(sealed["cipher"], "AES-256-GCM");
+
+        let body = server
+            .post("/v1/auth/import")
+            .bearer(&admin.token)
+            .json(json!({"data": sealed, "passphrase": "a long enough passphrase"}))
+            .send()
+            .await
+            .json();
+        assert_eq!((body["skipped"].as_u64(), body["created"].as_u64()), (Some(2), Some(0)));
+        assert_eq!(body["data"][1], json!({"email": "dev@example.com", "status": "skipped"}));
+    }
+
+    #[tokio::test]
+    async fn test_schema_cache_inspect_and_evict() {
+        let server = TestServer::spawn().await;
+        let admin = server.create_user("ops@example.com", ADMIN_ROLE).await;
+        let user = server.create_user("dev@example.com", USER_ROLE).await;
+        for collection in ["widgets", "gadgets"] {
+            server.post(&format!("/v1/push/{}", collection)).json(json!({"name": "a"})).send().await.assert_success();
+            // Reads fill the cache
+            server.get(&format!("/v1/query/{}", collection)).send().await.assert_success();
+        }
+
+        // Out-of-band DDL the cache cannot know about
+        server
+            .store
+            .execute_simple("ALTER TABLE widgets ADD COLUMN color TEXT".to_string())
+            .await
+            .unwrap();
+
+        server.get("/v1/admin/schema-cache").bearer(&user.token).send().await.assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+        let body = server.get("/v1/admin/schema-cache").bearer(&admin.token).send().await.json();
+        assert_eq!(body["total"], 2);
+        let entries = body["data"].as_array().unwrap();
+        assert_eq!(entries[0]["table"], "gadgets");
+        assert_eq!(entries[0]["stale"], false);
+        assert_eq!(entries[1]["table"], "widgets");
+        assert_eq!(entries[1]["stale"], true);
+        assert_eq!(entries[1]["added"], json!(["color"]));
+        assert_eq!(entries[1]["live_column_count"], entries[1]["column_count"].as_u64().unwrap() + 1);
+
+        // Pages are bounded
+        let body = server.get("/v1/admin/schema-cache?limit=1&offset=1").bearer(&admin.token).send().await.json();
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+        assert_eq!(body["data"][0]["table"], "widgets");
+
+        let body = server.delete("/v1/admin/schema-cache/widgets").bearer(&admin.token).send().await.json();
+        assert_eq!(body["evicted"], true);
+        server
+            .post("/v1/push/widgets")
+            .json(json!({"name": "b", "color": "red"}))
+            .send()
+            .await
+            .assert_success();
+        let rows = server.get("/v1/query/widgets?color=red").send().await.assert_success();
+        assert_eq!(rows.as_array().unwrap().len(), 1);
+
+        let body = server.delete("/v1/admin/schema-cache").bearer(&admin.token).send().await.json();
+        assert_eq!(body["cleared"], 2);
+        assert!(server.state.guard.cached_tables().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_events_carry_collection_and_sequence() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        let mut notes = server.subscribe("notes", None).await;
+        let mut tasks = server.subscribe("tasks", None).await;
+
+        server.post("/v1/push/notes").json(json!({"text": "a"})).send().await.assert_success();
+        server.post("/v1/push/tasks").json(json!({"title": "t"})).send().await.assert_success();
+        server
+            .post("/v1/push/notes/batch")
+            .json(json!([{"text": "b"}, {"text": "c"}]))
+            .send()
+            .await
+            .assert_success();
+        server.post("/v1/update/notes/1").json(json!({"text": "z"})).send().await.assert_success();
+        server.post("/v1/delete/notes/1").send().await.assert_success();
+
+        let mut previous_ts = String::new();
+        for (seq, event) in (1..).zip(["insert", "batch_insert", "update", "delete"]) {
+            let received = notes.next().await;
+            assert_eq!(received["event"], event);
+            assert_eq!(received["collection"], "notes");
+            assert_eq!(received["seq"], seq);
+            let ts = received["ts"].as_str().unwrap().to_string();
+            assert!(chrono::DateTime::parse_from_rfc3339(&ts).is_ok());
+            assert!(ts >= previous_ts);
+            previous_ts = ts;
+        }
+
+        // Every collection counts on its own
+        let received = tasks.next().await;
+        assert_eq!((received["collection"].as_str(), received["seq"].as_u64()), (Some("tasks"), Some(1)));
+    }
+
+    #[tokio::test]
+    async fn test_sync_sends_rows_then_later_events() {
+        let server = TestServer::builder().anonymous().spawn().await;
+        server
+            .post("/v1/push/notes/batch")
+            .json(json!([{"text": "a"}, {"text": "b"}]))
+            .send()
+            .await
+            .assert_success();
+        server.post("/v1/update/notes/1").json(json!({"text": "z"})).send().await.assert_success();
+
+        let mut sync = server.subscribe_uri("/v1/sync/notes", None).await;
+        server.post("/v1/push/notes").json(json!({"text": "c"})).send().await.assert_success();
+
+        let mut texts = Vec::new();
+        let complete = loop {
+            let message = sync.next().await;
+            if message["event"] != "snapshot" {
+                break message;
+            }
+            assert_eq!(message["seq"], 2);
+            texts.push(message["data"]["text"].as_str().unwrap().to_string());
+        };
+        assert_eq!(complete["event"], "snapshot_complete");
+        assert_eq!(complete["count"], texts.len());
+        // The later insert may be read into the snapshot as well
+        assert!(texts == ["z", "b"] || texts == ["z", "b", "c"], "{:?}", texts);
+
+        // Events already covered by the snapshot are not replayed
+        let live = sync.next().await;
+        assert_eq!((live["event"].as_str(), live["seq"].as_u64()), (Some("insert"), Some(3)));
+        assert_eq!(live["data"]["text"], "c");
+
+        server.get("/v1/sync/missing").send().await.assert_error(StatusCode::NOT_FOUND, "TABLE_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_pushes_share_transactions_when_batched() {
+        let mut config = VibeConfig::default();
+        config.batching.enabled = true;
+        config.batching.window = Duration::from_millis(20);
+        let server = TestServer::builder().anonymous().config(config).spawn().await;
+        // Creates the table and its columns, which are committed on their own
+        server.post("/v1/push/readings").json(json!({"n": -1})).send().await.assert_success();
+        let before = server.store.write_transactions();
+
+        let pushes = (0..100).map(|n| server.post("/v1/push/readings").json(json!({"n": n})).send());
+        let responses = futures::future::join_all(pushes).await;
+
+        let transactions = server.store.write_transactions() - before;
+        assert!(transactions <= 10, "{} transactions for 100 pushes", transactions);
+        let mut ids = std::collections::HashSet::new();
+        for (n, response) in responses.iter().enumerate() {
+            let id = response.assert_success()["id"].as_i64().unwrap();
+            assert!(ids.insert(id));
+            let row = server.get(&format!("/v1/query/readings/{}", id)).send().await.assert_success();
+            assert_eq!(row["n"], n);
+        }
+    }
+
+    #[test]
+    fn test_client_ip_trusts_proxy_headers_only_when_configured() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "203.0.113.7, 10.0.0.1".parse().unwrap());
+        let peer = || Some(ConnectInfo(SocketAddr::from(([192, 0, 2, 1], 4000))));
+
+        assert_eq!(client_ip(&headers, peer(), false).as_deref(), Some("192.0.2.1"));
+        assert_eq!(client_ip(&headers, peer(), true).as_deref(), Some("203.0.113.7"));
+        assert_eq!(client_ip(&HeaderMap::new(), peer(), true).as_deref(), Some("192.0.2.1"));
+        assert_eq!(client_ip(&headers, None, false), None);
+    }
+
+    #[test]
+    fn test_cors_config_rejects_credentials_with_any_origin() {
+        let mut cors = CorsConfig { allow_credentials: true, ..CorsConfig::default() };
+        assert!(cors.validate().is_err());
+        cors.allowed_origins = vec!["*".to_string()];
+        assert!(cors.validate().is_err());
+        cors.allowed_origins = vec!["https://app.example.com".to_string()];
+        assert!(cors.validate().is_ok());
+        for bad in ["app.example.com", "https://app.example.com/", "https://"] {
+            cors.allowed_origins = vec![bad.to_string()];
+            assert!(cors.validate().is_err(), "{} was accepted", bad);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight_with_credentials() {
+        let config = VibeConfig {
+            cors: CorsConfig {
+                allowed_origins: vec!["https://app.example.com".to_string()],
+                allow_credentials: true,
+                max_age: Some(Duration::from_secs(120)),
+            },
+            ..VibeConfig::default()
+        };
+        let server = TestServer::builder().config(config).spawn().await;
+        let preflight = |origin: &'static str| {
+            server
+                .request(Method::OPTIONS, "/v1/auth/me")
+                .header("origin", origin)
+                .header("access-control-request-method", "GET")
+                .header("access-control-request-headers", "authorization")
+                .send()
+        };
+
+        let allowed = preflight("https://app.example.com").await;
+        assert_eq!(allowed.headers["access-control-allow-origin"], "https://app.example.com");
+        assert_eq!(allowed.headers["access-control-allow-credentials"], "true");
+        assert_eq!(allowed.headers["access-control-allow-headers"], "authorization");
+        assert_eq!(allowed.headers["access-control-max-age"], "120");
+
+        let refused = preflight("https://evil.example.com").await;
+        assert!(refused.headers.get("access-control-allow-origin").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_render_query_results_as_html() {
+        let server = TestServer::spawn().await;
+        let admin = server.create_user("admin@vibedb.dev", ADMIN_ROLE).await.token;
+        let user = server.create_user("user@vibedb.dev", USER_ROLE).await.token;
+        for (name, team) in [("Ada", "core"), ("<script>alert(1)</script>", "core"), ("Bob", "ops")] {
+            server.post("/v1/push/people").json(json!({"name": name, "team": team})).send().await.assert_success();
+        }
+
+        let list = "<ul>{{#rows}}<li>{{name}}</li>{{/rows}}</ul>";
+        let response = server
+            .post("/v1/render")
+            .json(json!({
+                "collection": "people",
+                "query": {"team": "core", "order_by": "id", "limit": 10},
+                "template": list
+            }))
+            .send()
+            .await
+            .assert_status(StatusCode::OK);
+        assert_eq!(response.headers[header::CONTENT_TYPE], "text/html; charset=utf-8");
+        assert_eq!(
+            response.text(),
+            "<ul><li>Ada</li><li>&lt;script&gt;alert(1)&lt;/script&gt;</li></ul>"
+        );
+        let raw = server
+            .post("/v1/render")
+            .json(json!({"collection": "people", "query": {"id": 2}, "template": "{{#rows}}{{{name}}}{{/rows}} of {{count}}"}))
+            .send()
+            .await
+            .assert_status(StatusCode::OK);
+        assert_eq!(raw.text(), "<script>alert(1)</script> of 1");
+
+        // Stored templates
+        server.put("/v1/templates/people-list").bearer(&user).json(json!({"template": list})).send().await.assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+        server.put("/v1/templates/people-list").bearer(&admin).json(json!({"template": "{{#rows}}"})).send().await.assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        server.put("/v1/templates/people-list").bearer(&admin).json(json!({"template": list})).send().await.assert_success();
+        assert_eq!(server.get("/v1/templates").send().await.assert_success().as_array().unwrap().len(), 1);
+        let response = server
+            .post("/v1/render")
+            .json(json!({"collection": "people", "query": {"team": "ops"}, "template_name": "people-list"}))
+            .send()
+            .await
+            .assert_status(StatusCode::OK);
+        assert_eq!(response.text(), "<ul><li>Bob</li></ul>");
+        server.delete("/v1/templates/people-list").bearer(&admin).send().await.assert_success();
+        server.get("/v1/templates/people-list").send().await.assert_error(StatusCode::NOT_FOUND, "NOT_FOUND");
+
+        // Caps
+        let oversized = "x".repeat(templates::MAX_TEMPLATE_BYTES + 1);
+        server.post("/v1/render").json(json!({"collection": "people", "template": oversized})).send().await.assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        server
+            .post("/v1/render")
+            .json(json!({"collection": "people", "query": {"limit": templates::MAX_RENDER_ROWS + 1}, "template": list}))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        server.post("/v1/render").json(json!({"collection": "people"})).send().await.assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_pages_ignore_later_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(VibeStore::new(dir.path().join("snapshots.db")).await.unwrap());
+        let config = VibeConfig {
+            snapshots: crate::config::SnapshotConfig {
+                max_open: 1,
+                ..Default::default()
+            },
+            ..VibeConfig::default()
+        };
+        let server = TestServer::builder().store(store).config(config).anonymous().spawn().await;
+        for n in 0..3 {
+            server.post("/v1/push/orders").json(json!({"n": n})).send().await.assert_success();
+        }
+
+        let snapshot = server
+            .post("/v1/snapshots")
+            .json(json!({"ttl_secs": 30}))
+            .send()
+            .await
+            .assert_status(StatusCode::CREATED)
+            .json()["data"]["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        server.post("/v1/snapshots").send().await.assert_error(StatusCode::TOO_MANY_REQUESTS, "RATE_LIMITED");
+        for n in 3..5 {
+            server.post("/v1/push/orders").json(json!({"n": n})).send().await.assert_success();
+        }
+
+        let count = |response: Value| response.as_array().unwrap().len();
+        assert_eq!(count(server.get("/v1/query/orders").send().await.assert_success()), 5);
+        let page = |offset: u32| {
+            server
+                .get(&format!("/v1/query/orders?order_by=id&limit=2&offset={}", offset))
+                .header(SNAPSHOT_HEADER, &snapshot)
+                .send()
+        };
+        assert_eq!(count(page(0).await.assert_success()), 2);
+        assert_eq!(count(page(2).await.assert_success()), 1);
+        assert_eq!(count(page(4).await.assert_success()), 0);
+        let lines = server
+            .get("/v1/export?tables=orders")
+            .header(SNAPSHOT_HEADER, &snapshot)
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .ndjson();
+        assert_eq!(lines.last().unwrap()["rows"], 3);
+        server
+            .get("/v1/query/orders?stream=true")
+            .header(SNAPSHOT_HEADER, &snapshot)
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+
+        server.delete(&format!("/v1/snapshots/{}", snapshot)).send().await.assert_success();
+        page(0).await.assert_error(StatusCode::NOT_FOUND, "NOT_FOUND");
+        server.delete(&format!("/v1/snapshots/{}", snapshot)).send().await.assert_error(StatusCode::NOT_FOUND, "NOT_FOUND");
+        // The released slot is free again
+        server.post("/v1/snapshots").send().await.assert_status(StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn test_snapshots_expire_by_the_clock() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(VibeStore::new(dir.path().join("snapshots.db")).await.unwrap());
+        let clock = ManualClock::new();
+        let config = VibeConfig {
+            snapshots: crate::config::SnapshotConfig {
+                max_open: 1,
+                ..Default::default()
+            },
+            ..VibeConfig::default()
+        };
+        let server = TestServer::builder().store(store).config(config).clock(clock.clone()).anonymous().spawn().await;
+        server.post("/v1/push/orders").json(json!({"n": 1})).send().await.assert_success();
+
+        let snapshot = server
+            .post("/v1/snapshots")
+            .json(json!({"ttl_secs": 30}))
+            .send()
+            .await
+            .assert_status(StatusCode::CREATED)
+            .json()["data"]["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let query = || server.get("/v1/query/orders").header(SNAPSHOT_HEADER, &snapshot).send();
+
+        // A full registry says when the next slot frees up
+        clock.advance(Duration::from_secs(10));
+        let err = server.post("/v1/snapshots").send().await.assert_error(StatusCode::TOO_MANY_REQUESTS, "RATE_LIMITED");
+        assert_eq!(err["retry_after_ms"], 20_000);
+        query().await.assert_success();
 
-        assert_eq!(response.status(), StatusCode::OK);
+        clock.advance(Duration::from_secs(20));
+        query().await.assert_error(StatusCode::NOT_FOUND, "NOT_FOUND");
+        assert_eq!(server.state.snapshots.sweep(), 1);
+        server.post("/v1/snapshots").send().await.assert_status(StatusCode::CREATED);
     }
 }
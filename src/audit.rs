@@ -0,0 +1,424 @@
+//! # Audit Log
+//!
+//! Row history for collections with `audit` enabled in their settings. Every
+//! insert, update and delete made through the data endpoints stores the row
+//! before and after the change, which makes "the row as it was at time T"
+//! a lookup rather than a replay:
+//!
+//! 1. the latest entry at or before T holds the row's state in `after`
+//!    (NULL when it was deleted);
+//! 2. failing that, the first entry after T holds it in `before` (NULL when
+//!    the row was inserted later);
+//! 3. rows without entries have not changed since auditing began, so their
+//!    current state is the answer.
+//!
+//! Entries made by an authenticated caller carry the caller's id and email
+//! in `actor_id`/`actor_email`; anonymous writes leave them NULL.
+//!
+//! Timestamps are RFC 3339 UTC strings with millisecond precision, so they
+//! compare correctly as text.
+//!
+//! ## System Tables
+//! - `vibe_audit` - One entry per audited write
+
+use crate::auth::AuthUser;
+use crate::capabilities::Capability;
+use crate::db::{self, SqlValue, VibeStore};
+use crate::error::{VibeError, VibeResult};
+use crate::settings::CollectionSettings;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Most rows a single time-travel query returns
+pub const MAX_AS_OF_ROWS: usize = 1000;
+
+/// Kind of audited write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditOp {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl AuditOp {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AuditOp::Insert => "insert",
+            AuditOp::Update => "update",
+            AuditOp::Delete => "delete",
+        }
+    }
+}
+
+/// Current time in the audit timestamp format
+pub fn now_timestamp() -> String {
+    format_timestamp(Utc::now())
+}
+
+pub(crate) fn format_timestamp(ts: DateTime<Utc>) -> String {
+    ts.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Parses an RFC 3339 timestamp into the audit format
+pub fn parse_timestamp(ts: &str) -> VibeResult<String> {
+    DateTime::parse_from_rfc3339(ts)
+        .map(|dt| format_timestamp(dt.with_timezone(&Utc)))
+        .map_err(|e| VibeError::InvalidPayload(format!("Invalid timestamp '{}': {}", ts, e)))
+}
+
+/// Creates the audit table if needed
+pub async fn ensure_table(store: &VibeStore) -> VibeResult<()> {
+    store
+        .execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS vibe_audit (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                collection TEXT NOT NULL,
+                row_id INTEGER NOT NULL,
+                op TEXT NOT NULL,
+                before TEXT,
+                after TEXT,
+                changed_at TEXT NOT NULL,
+                actor_id INTEGER,
+                actor_email TEXT
+            );
+            CREATE INDEX IF NOT EXISTS idx_vibe_audit_row
+                ON vibe_audit(collection, row_id, changed_at);
+            "#
+            .to_string(),
+        )
+        .await?;
+
+    // Audit tables created before actors were recorded lack the columns
+    let actor_column = store
+        .query(
+            "SELECT name FROM pragma_table_info('vibe_audit') WHERE name = 'actor_id'".to_string(),
+            vec![],
+        )
+        .await?;
+    if actor_column.is_empty() {
+        store
+            .execute_batch(
+                "ALTER TABLE vibe_audit ADD COLUMN actor_id INTEGER; \
+                 ALTER TABLE vibe_audit ADD COLUMN actor_email TEXT;"
+                    .to_string(),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+/// Current state of a row as a JSON object
+pub async fn snapshot(store: &VibeStore, collection: &str, id: i64) -> VibeResult<Option<Value>> {
+    let rows = store
+        .query(
+            format!("SELECT * FROM {} WHERE id = ?", collection),
+            vec![SqlValue::Integer(id)],
+        )
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .next()
+        .map(|row| Value::Object(row.into_iter().collect())))
+}
+
+/// Current state of a row, read inside a transaction
+pub fn snapshot_in(
+    conn: &rusqlite::Connection,
+    collection: &str,
+    id: i64,
+) -> rusqlite::Result<Option<Value>> {
+    let rows = VibeStore::read_rows(
+        conn,
+        &format!("SELECT * FROM {} WHERE id = ?", collection),
+        &[SqlValue::Integer(id)],
+    )?;
+    Ok(rows
+        .into_iter()
+        .next()
+        .map(|row| Value::Object(row.into_iter().collect())))
+}
+
+const INSERT_ENTRY: &str = "INSERT INTO vibe_audit \
+    (collection, row_id, op, before, after, changed_at, actor_id, actor_email) \
+    VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
+
+fn entry_params(
+    collection: &str,
+    row_id: i64,
+    op: AuditOp,
+    before: Option<&Value>,
+    after: Option<&Value>,
+    actor: Option<&AuthUser>,
+) -> Vec<SqlValue> {
+    let json = |v: Option<&Value>| v.map(|v| SqlValue::Text(v.to_string())).unwrap_or(SqlValue::Null);
+    vec![
+        SqlValue::Text(collection.to_string()),
+        SqlValue::Integer(row_id),
+        SqlValue::Text(op.as_str().to_string()),
+        json(before),
+        json(after),
+        SqlValue::Text(now_timestamp()),
+        actor.map(|user| SqlValue::Integer(user.id)).unwrap_or(SqlValue::Null),
+        actor.map(|user| SqlValue::Text(user.email.clone())).unwrap_or(SqlValue::Null),
+    ]
+}
+
+/// Records a write inside the transaction making it, so the entry and the
+/// write commit or roll back together
+///
+/// `ensure_table` must have run first.
+pub fn record_in(
+    conn: &rusqlite::Connection,
+    collection: &str,
+    row_id: i64,
+    op: AuditOp,
+    before: Option<&Value>,
+    after: Option<&Value>,
+    actor: Option<&AuthUser>,
+) -> rusqlite::Result<()> {
+    let params = entry_params(collection, row_id, op, before, after, actor);
+    conn.execute(INSERT_ENTRY, rusqlite::params_from_iter(params.iter()))?;
+    Ok(())
+}
+
+/// An insert into an audited collection, carried along with the INSERT so
+/// its entry is written in the same transaction
+#[derive(Debug, Clone)]
+pub struct InsertAudit {
+    pub collection: String,
+    pub actor: Option<AuthUser>,
+}
+
+impl InsertAudit {
+    pub fn new(collection: &str, actor: Option<&AuthUser>) -> Self {
+        Self {
+            collection: collection.to_string(),
+            actor: actor.cloned(),
+        }
+    }
+
+    /// Records the insert of `row_id` with the row as the transaction
+    /// sees it
+    ///
+    /// `ensure_table` must have run first.
+    pub fn record_in(&self, conn: &rusqlite::Connection, row_id: i64) -> rusqlite::Result<()> {
+        let after = snapshot_in(conn, &self.collection, row_id)?;
+        record_in(conn, &self.collection, row_id, AuditOp::Insert, None, after.as_ref(), self.actor.as_ref())
+    }
+}
+
+/// Runs several INSERTs in one transaction, auditing those that carry an
+/// `InsertAudit`, and returns the new ids in order; if any of them fails,
+/// none is kept
+///
+/// `ensure_table` must have run first if any insert is audited.
+pub async fn insert_rows(
+    store: &VibeStore,
+    label: &str,
+    rows: Vec<(String, Vec<SqlValue>, Option<InsertAudit>)>,
+) -> VibeResult<Vec<i64>> {
+    let returning = store.capabilities().returning;
+    store
+        .with_transaction_labeled(label, move |conn| {
+            rows.iter()
+                .map(|(sql, params, audit)| {
+                    let id = db::insert_row(conn, returning, sql, params)?;
+                    if let Some(audit) = audit {
+                        audit.record_in(conn, id)?;
+                    }
+                    Ok(id)
+                })
+                .collect()
+        })
+        .await
+}
+
+/// Runs one INSERT and returns its id, auditing it in the same
+/// transaction with `audit`
+pub async fn insert_row(
+    store: &VibeStore,
+    label: &str,
+    sql: String,
+    params: Vec<SqlValue>,
+    audit: Option<InsertAudit>,
+) -> VibeResult<i64> {
+    match audit {
+        Some(audit) => Ok(insert_rows(store, label, vec![(sql, params, Some(audit))]).await?[0]),
+        None => store.insert_returning_id_labeled(label, sql, params).await,
+    }
+}
+
+/// Checks that history for `ts` exists and returns it normalized
+pub fn check_history(
+    collection: &str,
+    settings: &CollectionSettings,
+    ts: &str,
+) -> VibeResult<String> {
+    let ts = parse_timestamp(ts)?;
+    let since = match (settings.audit, &settings.audit_since) {
+        (true, Some(since)) => since,
+        _ => {
+            return Err(VibeError::InvalidPayload(format!(
+                "Auditing is not enabled for {}",
+                collection
+            )))
+        }
+    };
+    if ts < *since {
+        return Err(VibeError::InvalidPayload(format!(
+            "Timestamp {} predates the audit history of {} (available since {})",
+            ts, collection, since
+        )));
+    }
+    Ok(ts)
+}
+
+/// Which audited rows `audited_states` reads
+enum AuditedRows<'a> {
+    /// A single row, existing at `ts` or not
+    One(i64),
+    /// The first `limit` rows by id that existed at `ts` and match the
+    /// equality filters
+    Matching {
+        filters: &'a [(String, String)],
+        limit: usize,
+    },
+}
+
+/// States at `ts` of audited rows, keyed by row id; `None` means the row
+/// did not exist at that time
+///
+/// Each row's entries are ranked so that the latest one at or before `ts`
+/// wins, falling back to the earliest one after it.
+async fn audited_states(
+    store: &VibeStore,
+    collection: &str,
+    ts: &str,
+    rows: AuditedRows<'_>,
+) -> VibeResult<BTreeMap<i64, Option<Value>>> {
+    store
+        .capabilities()
+        .require(Capability::WindowFunctions, "Time-travel reads")?;
+    ensure_table(store).await?;
+
+    let mut sql = r#"
+        SELECT row_id, state FROM (
+            SELECT row_id,
+                   CASE WHEN changed_at <= ?1 THEN after ELSE before END AS state,
+                   ROW_NUMBER() OVER (
+                       PARTITION BY row_id
+                       ORDER BY (changed_at <= ?1) DESC,
+                                CASE WHEN changed_at <= ?1 THEN changed_at END DESC,
+                                CASE WHEN changed_at <= ?1 THEN id END DESC,
+                                changed_at ASC,
+                                id ASC
+                   ) AS rn
+            FROM vibe_audit
+            WHERE collection = ?2"#
+        .to_string();
+    let mut params = vec![
+        SqlValue::Text(ts.to_string()),
+        SqlValue::Text(collection.to_string()),
+    ];
+    match rows {
+        AuditedRows::One(id) => {
+            sql.push_str(" AND row_id = ?3) WHERE rn = 1");
+            params.push(SqlValue::Integer(id));
+        }
+        AuditedRows::Matching { filters, limit } => {
+            sql.push_str(") WHERE rn = 1 AND state IS NOT NULL");
+            // Compared as text the way query filters are: booleans as
+            // true/false, objects and arrays as their JSON
+            for (key, value) in filters {
+                let n = params.len();
+                sql.push_str(&format!(
+                    " AND CASE json_type(state, ?{path}) \
+                       WHEN 'true' THEN 'true' WHEN 'false' THEN 'false' \
+                       ELSE CAST(json_extract(state, ?{path}) AS TEXT) END = ?{value}",
+                    path = n + 1,
+                    value = n + 2
+                ));
+                params.push(SqlValue::Text(format!("$.\"{}\"", key)));
+                params.push(SqlValue::Text(value.clone()));
+            }
+            sql.push_str(&format!(" ORDER BY row_id LIMIT {}", limit));
+        }
+    }
+
+    let rows = store.query(sql, params).await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            let mut row = row.into_iter();
+            let id = row.next()?.1.as_i64()?;
+            let state = row.next().map(|(_, v)| v).filter(|v| !v.is_null());
+            Some((id, state))
+        })
+        .collect())
+}
+
+/// A single row as it was at `ts` (already validated by `check_history`)
+pub async fn row_as_of(
+    store: &VibeStore,
+    collection: &str,
+    id: i64,
+    ts: &str,
+) -> VibeResult<Option<Value>> {
+    match audited_states(store, collection, ts, AuditedRows::One(id)).await?.remove(&id) {
+        Some(state) => Ok(state),
+        None => snapshot(store, collection, id).await,
+    }
+}
+
+/// Rows as they were at `ts` matching equality `filters`, ordered by id
+///
+/// Audited and unaudited rows are each filtered and bounded in SQL before
+/// both sets are merged. `limit` is capped at `MAX_AS_OF_ROWS`.
+pub async fn rows_as_of(
+    store: &VibeStore,
+    collection: &str,
+    ts: &str,
+    filters: &[(String, String)],
+    limit: usize,
+    offset: usize,
+) -> VibeResult<Vec<Value>> {
+    let limit = limit.min(MAX_AS_OF_ROWS);
+    let window = limit.saturating_add(offset);
+    let audited = audited_states(
+        store,
+        collection,
+        ts,
+        AuditedRows::Matching { filters, limit: window },
+    )
+    .await?;
+
+    // Rows never touched since auditing began are unchanged
+    let mut sql = format!(
+        "SELECT * FROM {} WHERE id NOT IN (SELECT row_id FROM vibe_audit WHERE collection = ?)",
+        collection
+    );
+    let mut params = vec![SqlValue::Text(collection.to_string())];
+    for (key, value) in filters {
+        sql.push_str(&format!(" AND {} = ?", key));
+        params.push(SqlValue::Text(value.clone()));
+    }
+    sql.push_str(&format!(" ORDER BY id LIMIT {}", window));
+    let unaudited = store.query(sql, params).await?;
+
+    let mut merged: BTreeMap<i64, Value> = audited
+        .into_iter()
+        .filter_map(|(id, state)| state.map(|s| (id, s)))
+        .collect();
+    for row in unaudited {
+        let row = Value::Object(row.into_iter().collect());
+        if let Some(id) = row.get("id").and_then(Value::as_i64) {
+            merged.insert(id, row);
+        }
+    }
+
+    Ok(merged.into_values().skip(offset).take(limit).collect())
+}
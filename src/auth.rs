@@ -7,11 +7,14 @@
 //! - Argon2id password hashing
 //! - JWT access tokens (short-lived) and refresh tokens (long-lived)
 //! - Session management with token refresh
+//! - User roles (`user` by default, `admin` for operators)
+//! - User export/import (with password hashes) for moving between instances
 //!
 //! ## System Tables
 //! - `vibe_users` - Stores user credentials and metadata
 //! - `vibe_sessions` - Tracks active refresh tokens
 
+use crate::clock::{SharedClock, SystemClock};
 use crate::db::{SqlValue, VibeStore};
 use crate::error::{VibeError, VibeResult};
 
@@ -20,18 +23,23 @@ use argon2::{
     Argon2,
 };
 use axum::{
-    extract::State,
-    http::{header::AUTHORIZATION, StatusCode},
+    async_trait,
+    extract::{FromRef, FromRequestParts, State},
+    http::{header::AUTHORIZATION, request::Parts, StatusCode},
     response::IntoResponse,
     routing::{get, post, put},
     Json, Router,
 };
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{decode, encode, errors::ErrorKind, DecodingKey, EncodingKey, Header, Validation};
 use rand::Rng;
+use ring::{aead, hmac, pbkdf2};
+use rusqlite::OptionalExtension;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::sync::Arc;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::num::NonZeroU32;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
 use tracing::{debug, info};
 
 // ============================================================================
@@ -47,6 +55,48 @@ const DEFAULT_REFRESH_TOKEN_DURATION: Duration = Duration::from_secs(7 * 24 * 36
 /// Minimum password length
 const MIN_PASSWORD_LENGTH: usize = 8;
 
+/// Minimum length of a JWT secret, whether set at startup or supplied to
+/// `/v1/auth/rotate-secret`
+pub const MIN_SECRET_LENGTH: usize = 32;
+
+/// Header carrying the passphrase that encrypts `/v1/auth/export`
+pub const EXPORT_PASSPHRASE_HEADER: &str = "x-export-passphrase";
+
+/// Minimum length of an export passphrase
+const MIN_PASSPHRASE_LENGTH: usize = 12;
+
+/// Format version of user exports
+const EXPORT_VERSION: u32 = 1;
+
+/// PBKDF2-HMAC-SHA256 rounds deriving the key of encrypted exports
+const EXPORT_KDF_ITERATIONS: u32 = 600_000;
+
+/// Most PBKDF2 rounds an imported export may ask for, so a crafted one
+/// can't tie up a thread for minutes
+const MAX_EXPORT_KDF_ITERATIONS: u32 = 5_000_000;
+
+/// Cipher and key derivation named in encrypted exports
+const EXPORT_CIPHER: &str = "AES-256-GCM";
+const EXPORT_KDF: &str = "PBKDF2-HMAC-SHA256";
+
+/// Role assigned to users created through signup
+pub const USER_ROLE: &str = "user";
+
+/// Whether the users table has ever had a row, as an SQL expression
+const USERS_EVER_CREATED: &str = "EXISTS (SELECT 1 FROM vibe_users) \
+    OR EXISTS (SELECT 1 FROM sqlite_sequence WHERE name = 'vibe_users')";
+
+/// Role for operators (e.g. the bootstrap admin)
+pub const ADMIN_ROLE: &str = "admin";
+
+/// Context mixed into the JWT secret to derive the URL signing key, so a
+/// URL signature is never a valid token signature and vice versa
+const URL_SIGNING_CONTEXT: &[u8] = b"vibedb signed urls";
+
+/// Context of the key signing reports (see `sign_report`), kept apart from
+/// the URL key so neither signature can stand in for the other
+const REPORT_SIGNING_CONTEXT: &[u8] = b"vibedb signed reports";
+
 // ============================================================================
 // Core Types
 // ============================================================================
@@ -55,9 +105,38 @@ const MIN_PASSWORD_LENGTH: usize = 8;
 #[derive(Clone)]
 pub struct AuthService {
     store: Arc<VibeStore>,
-    jwt_secret: Vec<u8>,
+    /// Shared by every clone, so a rotation applies server-wide
+    keys: Arc<RwLock<SigningKeys>>,
     access_token_duration: Duration,
     refresh_token_duration: Duration,
+    clock: SharedClock,
+}
+
+/// Signing secrets; new tokens use the current one and tokens signed with
+/// the previous one stay valid until they expire
+struct SigningKeys {
+    current: SigningKey,
+    previous: Option<SigningKey>,
+}
+
+struct SigningKey {
+    jwt_secret: Vec<u8>,
+    /// HMAC key of `sign_url`, derived from the JWT secret
+    url_key: hmac::Key,
+    /// HMAC key of `sign_report`, derived from the JWT secret
+    report_key: hmac::Key,
+}
+
+impl SigningKey {
+    fn new(jwt_secret: Vec<u8>) -> Self {
+        let root = hmac::Key::new(hmac::HMAC_SHA256, &jwt_secret);
+        let derive = |context: &[u8]| hmac::Key::new(hmac::HMAC_SHA256, hmac::sign(&root, context).as_ref());
+        Self {
+            url_key: derive(URL_SIGNING_CONTEXT),
+            report_key: derive(REPORT_SIGNING_CONTEXT),
+            jwt_secret,
+        }
+    }
 }
 
 /// User data returned from authentication endpoints
@@ -65,14 +144,20 @@ pub struct AuthService {
 pub struct User {
     pub id: i64,
     pub email: String,
+    #[serde(default = "default_role")]
+    pub role: String,
     pub created_at: String,
     pub updated_at: String,
     #[serde(default)]
     pub metadata: Value,
 }
 
+fn default_role() -> String {
+    USER_ROLE.to_string()
+}
+
 /// Token pair returned after successful authentication
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AuthTokens {
     pub access_token: String,
     pub refresh_token: String,
@@ -88,6 +173,9 @@ pub struct Claims {
     pub sub: i64,
     /// User email
     pub email: String,
+    /// User role
+    #[serde(default = "default_role")]
+    pub role: String,
     /// Expiration time (Unix timestamp)
     pub exp: u64,
     /// Issued at time (Unix timestamp)
@@ -99,13 +187,58 @@ pub struct Claims {
 pub struct AuthUser {
     pub id: i64,
     pub email: String,
+    pub role: String,
+}
+
+impl AuthUser {
+    /// Whether the user holds the admin role
+    pub fn is_admin(&self) -> bool {
+        self.role == ADMIN_ROLE
+    }
+
+    /// `actor` of events and audit entries: `{id, email}`, or null for
+    /// anonymous writes
+    pub fn actor(caller: Option<&AuthUser>) -> Value {
+        match caller {
+            Some(user) => json!({"id": user.id, "email": user.email}),
+            None => Value::Null,
+        }
+    }
+
+    /// Who made a request, for log lines
+    pub fn label(caller: Option<&AuthUser>) -> &str {
+        caller.map(|user| user.email.as_str()).unwrap_or("anonymous")
+    }
+}
+
+/// Resolves the bearer token of a request; use `Option<AuthUser>` for
+/// endpoints that also serve anonymous callers
+///
+/// Works with any router state that exposes its `Option<AuthService>`
+/// through `FromRef`; `None` means authentication is disabled.
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthUser
+where
+    Option<AuthService>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = VibeError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Option::<AuthService>::from_ref(state) {
+            Some(auth) => auth.authenticate(&parts.headers),
+            None => Err(VibeError::Unauthorized(
+                "Authentication is not enabled".to_string(),
+            )),
+        }
+    }
 }
 
 // ============================================================================
 // Request/Response DTOs
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct SignupRequest {
     pub email: String,
     pub password: String,
@@ -113,17 +246,108 @@ pub struct SignupRequest {
     pub metadata: Option<Value>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct LoginRequest {
     pub email: String,
     pub password: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct RefreshRequest {
     pub refresh_token: String,
 }
 
+#[derive(Debug, Default, Deserialize)]
+pub struct RotateSecretRequest {
+    /// New signing secret; a random one is generated when absent
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+/// A user as exported, with the password hash so logins carry over
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedUser {
+    pub email: String,
+    pub password_hash: String,
+    #[serde(default = "default_role")]
+    pub role: String,
+    #[serde(default)]
+    pub metadata: Value,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+/// Users of an instance, as served by `/v1/auth/export`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserExport {
+    pub version: u32,
+    pub exported_at: String,
+    pub users: Vec<ExportedUser>,
+}
+
+/// A `UserExport` sealed under a passphrase (base64 fields)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedExport {
+    pub cipher: String,
+    pub kdf: String,
+    pub iterations: u32,
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+/// An export in either form
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ExportDocument {
+    Encrypted(EncryptedExport),
+    Plain(UserExport),
+}
+
+/// What an import does with a user whose email already exists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Keep the existing user
+    #[default]
+    Skip,
+    /// Replace the existing user's password, role and metadata
+    Overwrite,
+    /// Import nothing
+    Error,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportUsersRequest {
+    /// The `data` of an export, encrypted or not
+    pub data: ExportDocument,
+    /// Needed when `data` is encrypted
+    #[serde(default)]
+    pub passphrase: Option<String>,
+    #[serde(default)]
+    pub on_conflict: ConflictPolicy,
+    /// Policies for individual emails, taking precedence over `on_conflict`
+    #[serde(default)]
+    pub overrides: BTreeMap<String, ConflictPolicy>,
+}
+
+/// Outcome of importing one user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStatus {
+    Created,
+    Skipped,
+    Overwritten,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportResult {
+    pub email: String,
+    pub status: ImportStatus,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct UpdateUserRequest {
     #[serde(default)]
@@ -139,9 +363,13 @@ impl AuthService {
     pub async fn new(store: Arc<VibeStore>, jwt_secret: Vec<u8>) -> VibeResult<Self> {
         let service = Self {
             store,
-            jwt_secret,
+            keys: Arc::new(RwLock::new(SigningKeys {
+                current: SigningKey::new(jwt_secret),
+                previous: None,
+            })),
             access_token_duration: DEFAULT_ACCESS_TOKEN_DURATION,
             refresh_token_duration: DEFAULT_REFRESH_TOKEN_DURATION,
+            clock: SystemClock::shared(),
         };
 
         // Initialize auth tables
@@ -151,6 +379,39 @@ impl AuthService {
         Ok(service)
     }
 
+    /// Times token and session expiry by `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Keeps accepting tokens signed with the secret used before the
+    /// current one, e.g. across a restart that installs a rotated secret
+    pub fn with_previous_secret(self, jwt_secret: Vec<u8>) -> Self {
+        self.write_keys().previous = Some(SigningKey::new(jwt_secret));
+        self
+    }
+
+    /// Installs a new signing secret; the current one becomes the previous
+    ///
+    /// Tokens and signed URLs made with the old current secret stay valid
+    /// until they expire; those of the old previous secret stop working.
+    /// Rotation is held in memory only.
+    pub fn rotate_secret(&self, jwt_secret: Vec<u8>) {
+        let mut keys = self.write_keys();
+        let current = std::mem::replace(&mut keys.current, SigningKey::new(jwt_secret));
+        keys.previous = Some(current);
+    }
+
+    fn read_keys(&self) -> RwLockReadGuard<'_, SigningKeys> {
+        // The guarded sections cannot panic half-way
+        self.keys.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write_keys(&self) -> RwLockWriteGuard<'_, SigningKeys> {
+        self.keys.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
     /// Initialize authentication tables
     async fn initialize_tables(&self) -> VibeResult<()> {
         // Create users table
@@ -160,6 +421,7 @@ impl AuthService {
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 email TEXT UNIQUE NOT NULL,
                 password_hash TEXT NOT NULL,
+                role TEXT NOT NULL DEFAULT 'user',
                 metadata TEXT DEFAULT '{}',
                 created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
                 updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
@@ -169,6 +431,17 @@ impl AuthService {
             .to_string(),
         ).await?;
 
+        // Databases created before roles existed lack the column
+        let role_column = self.store.query(
+            "SELECT name FROM pragma_table_info('vibe_users') WHERE name = 'role'".to_string(),
+            vec![],
+        ).await?;
+        if role_column.is_empty() {
+            self.store.execute_batch(
+                "ALTER TABLE vibe_users ADD COLUMN role TEXT NOT NULL DEFAULT 'user'".to_string(),
+            ).await?;
+        }
+
         // Create sessions table for refresh tokens
         self.store.execute_batch(
             r#"
@@ -190,6 +463,47 @@ impl AuthService {
         Ok(())
     }
 
+    /// Signs a URL's `message` (HMAC-SHA256, base64url without padding)
+    pub fn sign_url(&self, message: &str) -> String {
+        use base64::Engine;
+        base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .encode(hmac::sign(&self.read_keys().current.url_key, message.as_bytes()))
+    }
+
+    /// Checks a `sign_url` signature in constant time, under the current
+    /// or the previous secret
+    pub fn verify_url(&self, message: &str, signature: &str) -> bool {
+        use base64::Engine;
+        let Ok(tag) = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(signature) else {
+            return false;
+        };
+        let keys = self.read_keys();
+        std::iter::once(&keys.current)
+            .chain(keys.previous.as_ref())
+            .any(|key| hmac::verify(&key.url_key, message.as_bytes(), &tag).is_ok())
+    }
+
+    /// Signs a report (HMAC-SHA256, base64url without padding), so it can
+    /// later be shown to come from this server
+    pub fn sign_report(&self, report: &str) -> String {
+        use base64::Engine;
+        base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .encode(hmac::sign(&self.read_keys().current.report_key, report.as_bytes()))
+    }
+
+    /// Checks a `sign_report` signature under the current or the previous
+    /// secret
+    pub fn verify_report(&self, report: &str, signature: &str) -> bool {
+        use base64::Engine;
+        let Ok(tag) = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(signature) else {
+            return false;
+        };
+        let keys = self.read_keys();
+        std::iter::once(&keys.current)
+            .chain(keys.previous.as_ref())
+            .any(|key| hmac::verify(&key.report_key, report.as_bytes(), &tag).is_ok())
+    }
+
     /// Generate a secure random JWT secret
     pub fn generate_secret() -> Vec<u8> {
         let mut secret = vec![0u8; 64];
@@ -220,21 +534,19 @@ impl AuthService {
 
     /// Generate a JWT access token
     fn generate_access_token(&self, user: &User) -> VibeResult<String> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|e| VibeError::Internal(anyhow::anyhow!("Time error: {}", e)))?;
-
+        let now = self.clock.unix_secs();
         let claims = Claims {
             sub: user.id,
             email: user.email.clone(),
-            iat: now.as_secs(),
-            exp: (now + self.access_token_duration).as_secs(),
+            role: user.role.clone(),
+            iat: now,
+            exp: now + self.access_token_duration.as_secs(),
         };
 
         encode(
             &Header::default(),
             &claims,
-            &EncodingKey::from_secret(&self.jwt_secret),
+            &EncodingKey::from_secret(&self.read_keys().current.jwt_secret),
         )
         .map_err(|e| VibeError::Internal(anyhow::anyhow!("JWT encoding failed: {}", e)))
     }
@@ -248,14 +560,33 @@ impl AuthService {
     }
 
     /// Validate a JWT access token and return claims
+    ///
+    /// A token whose signature does not match the current secret is tried
+    /// against the previous one. Expiry is checked against the service's
+    /// clock, with the usual leeway.
     pub fn validate_token(&self, token: &str) -> VibeResult<Claims> {
-        decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(&self.jwt_secret),
-            &Validation::default(),
-        )
-        .map(|data| data.claims)
-        .map_err(|e| VibeError::Unauthorized(format!("Invalid token: {}", e)))
+        let keys = self.read_keys();
+        let mut validation = Validation::default();
+        validation.validate_exp = false;
+        let decode_with = |key: &SigningKey| {
+            decode::<Claims>(token, &DecodingKey::from_secret(&key.jwt_secret), &validation)
+        };
+        let decoded = match (decode_with(&keys.current), &keys.previous) {
+            (Err(e), Some(previous)) if matches!(e.kind(), ErrorKind::InvalidSignature) => {
+                decode_with(previous)
+            }
+            (decoded, _) => decoded,
+        };
+        let claims = decoded
+            .map(|data| data.claims)
+            .map_err(|e| VibeError::Unauthorized(format!("Invalid token: {}", e)))?;
+        if claims.exp + validation.leeway < self.clock.unix_secs() {
+            return Err(VibeError::Unauthorized(format!(
+                "Invalid token: {}",
+                jsonwebtoken::errors::Error::from(ErrorKind::ExpiredSignature)
+            )));
+        }
+        Ok(claims)
     }
 
     /// Validate email format
@@ -283,49 +614,123 @@ impl AuthService {
 
     /// Register a new user
     pub async fn signup(&self, req: SignupRequest) -> VibeResult<AuthTokens> {
-        // Validate input
-        self.validate_email(&req.email)?;
-        self.validate_password(&req.password)?;
+        let metadata = req.metadata.unwrap_or(json!({}));
+        let user = self
+            .create_user(&req.email, &req.password, USER_ROLE, metadata)
+            .await?;
+        info!("New user registered: {}", user.email);
 
-        // Check if user already exists
-        let existing = self.store.query(
-            "SELECT id FROM vibe_users WHERE email = ?".to_string(),
-            vec![SqlValue::Text(req.email.clone())],
-        ).await?;
+        // Generate tokens
+        self.create_session(user).await
+    }
 
-        if !existing.is_empty() {
-            return Err(VibeError::Conflict("User already exists".to_string()));
-        }
+    /// Create a user with the given role without opening a session
+    pub async fn create_user(
+        &self,
+        email: &str,
+        password: &str,
+        role: &str,
+        metadata: Value,
+    ) -> VibeResult<User> {
+        self.insert_user(email, password, role, metadata, false).await
+    }
 
-        // Hash password
-        let password_hash = self.hash_password(&req.password)?;
-        let metadata = req.metadata.unwrap_or(json!({}));
+    /// Whether no user has ever been created, so the first-run setup
+    /// (`POST /v1/auth/setup`) is open
+    ///
+    /// Deleting every user later doesn't reopen it: `sqlite_sequence` keeps
+    /// the users table's last id.
+    pub async fn needs_setup(&self) -> VibeResult<bool> {
+        let rows = self.store.query(format!("SELECT {} AS used", USERS_EVER_CREATED), vec![]).await?;
+        Ok(rows
+            .first()
+            .and_then(|row| row.first())
+            .and_then(|(_, v)| v.as_i64())
+            == Some(0))
+    }
 
-        // Insert user
-        self.store.execute(
-            "INSERT INTO vibe_users (email, password_hash, metadata) VALUES (?, ?, ?)".to_string(),
-            vec![
-                SqlValue::Text(req.email.clone()),
-                SqlValue::Text(password_hash),
-                SqlValue::Text(metadata.to_string()),
-            ],
-        ).await?;
+    /// Create the first admin and open a session for it
+    ///
+    /// Refused with `Forbidden` once any user has ever existed. The check
+    /// runs in the insert's transaction, so of two racing setups only one
+    /// can succeed.
+    pub async fn create_first_admin(&self, email: &str, password: &str) -> VibeResult<AuthTokens> {
+        let user = self.insert_user(email, password, ADMIN_ROLE, json!({}), true).await?;
+        info!("First admin created: {}", user.email);
+        self.create_session(user).await
+    }
 
-        let user_id = self.store.last_insert_rowid().await?;
-        info!("New user registered: {}", req.email);
+    /// Insert a user; with `first_only`, only while no user has ever existed
+    async fn insert_user(
+        &self,
+        email: &str,
+        password: &str,
+        role: &str,
+        metadata: Value,
+        first_only: bool,
+    ) -> VibeResult<User> {
+        // Validate input
+        self.validate_email(email)?;
+        self.validate_password(password)?;
+
+        // Hash outside the transaction so the write lock is held briefly
+        let password_hash = self.hash_password(password)?;
+
+        // Existence check and insert in one transaction, so concurrent
+        // signups for the same email can't both pass the check
+        let params = [
+            SqlValue::Text(email.to_string()),
+            SqlValue::Text(password_hash),
+            SqlValue::Text(role.to_string()),
+            SqlValue::Text(metadata.to_string()),
+        ];
+        let user_id = self
+            .store
+            .with_transaction(move |conn| {
+                if first_only {
+                    let used: bool = conn.query_row(&format!("SELECT {}", USERS_EVER_CREATED), [], |row| row.get(0))?;
+                    if used {
+                        return Ok(Err(VibeError::Forbidden(
+                            "Setup is complete; sign in instead".to_string(),
+                        )));
+                    }
+                }
+                let exists = conn
+                    .prepare("SELECT 1 FROM vibe_users WHERE email = ?")?
+                    .exists([&params[0]])?;
+                if exists {
+                    return Ok(Err(VibeError::Conflict("User already exists".to_string())));
+                }
+                conn.execute(
+                    "INSERT INTO vibe_users (email, password_hash, role, metadata) VALUES (?, ?, ?, ?)",
+                    rusqlite::params_from_iter(params.iter()),
+                )?;
+                Ok(Ok(conn.last_insert_rowid()))
+            })
+            .await??;
 
-        // Get the created user
-        let user = self.get_user_by_id(user_id).await?;
+        self.get_user_by_id(user_id).await
+    }
 
-        // Generate tokens
-        self.create_session(user).await
+    /// Number of registered users
+    pub async fn user_count(&self) -> VibeResult<i64> {
+        let rows = self.store.query(
+            "SELECT COUNT(*) AS count FROM vibe_users".to_string(),
+            vec![],
+        ).await?;
+
+        Ok(rows
+            .first()
+            .and_then(|row| row.first())
+            .and_then(|(_, v)| v.as_i64())
+            .unwrap_or(0))
     }
 
     /// Authenticate a user and return tokens
     pub async fn login(&self, req: LoginRequest) -> VibeResult<AuthTokens> {
         // Find user by email
         let rows = self.store.query(
-            "SELECT id, email, password_hash, metadata, created_at, updated_at FROM vibe_users WHERE email = ?"
+            "SELECT id, email, password_hash, role, metadata, created_at, updated_at FROM vibe_users WHERE email = ?"
                 .to_string(),
             vec![SqlValue::Text(req.email.clone())],
         ).await?;
@@ -359,14 +764,10 @@ impl AuthService {
         let refresh_token = self.generate_refresh_token();
 
         // Calculate expiry
-        let expires_at = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|e| VibeError::Internal(anyhow::anyhow!("Time error: {}", e)))?
-            + self.refresh_token_duration;
-
-        let expires_at_str = chrono::DateTime::from_timestamp(expires_at.as_secs() as i64, 0)
-            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
-            .unwrap_or_default();
+        let expires_at = self.clock.now()
+            + chrono::Duration::from_std(self.refresh_token_duration)
+                .map_err(|e| VibeError::Internal(anyhow::anyhow!("Time error: {}", e)))?;
+        let expires_at_str = format_session_time(expires_at);
 
         // Store refresh token
         self.store.execute(
@@ -406,6 +807,11 @@ impl AuthService {
             .find(|(k, _)| k == "user_id")
             .and_then(|(_, v)| v.as_i64())
             .ok_or_else(|| VibeError::Internal(anyhow::anyhow!("Missing user_id")))?;
+        let expires_at = row
+            .iter()
+            .find(|(k, _)| k == "expires_at")
+            .and_then(|(_, v)| v.as_str())
+            .unwrap_or_default();
 
         // Delete old session
         self.store.execute(
@@ -413,6 +819,11 @@ impl AuthService {
             vec![SqlValue::Text(req.refresh_token)],
         ).await?;
 
+        // Both are in the same format, which sorts chronologically
+        if expires_at <= format_session_time(self.clock.now()).as_str() {
+            return Err(VibeError::Unauthorized("Refresh token expired".to_string()));
+        }
+
         // Get user and create new session
         let user = self.get_user_by_id(user_id).await?;
         self.create_session(user).await
@@ -430,7 +841,7 @@ impl AuthService {
     /// Get user by ID
     pub async fn get_user_by_id(&self, id: i64) -> VibeResult<User> {
         let rows = self.store.query(
-            "SELECT id, email, metadata, created_at, updated_at FROM vibe_users WHERE id = ?"
+            "SELECT id, email, role, metadata, created_at, updated_at FROM vibe_users WHERE id = ?"
                 .to_string(),
             vec![SqlValue::Integer(id)],
         ).await?;
@@ -455,6 +866,134 @@ impl AuthService {
         self.get_user_by_id(user_id).await
     }
 
+    /// Every user with their password hash, for moving them to another
+    /// instance; sessions are not exported
+    pub async fn export_users(&self) -> VibeResult<UserExport> {
+        let rows = self.store.query(
+            "SELECT email, password_hash, role, metadata, created_at, updated_at FROM vibe_users ORDER BY id"
+                .to_string(),
+            vec![],
+        ).await?;
+
+        let users = rows
+            .into_iter()
+            .map(|row| {
+                let row: HashMap<String, Value> = row.into_iter().collect();
+                let text = |key: &str| row.get(key).and_then(Value::as_str).map(String::from);
+                ExportedUser {
+                    email: text("email").unwrap_or_default(),
+                    password_hash: text("password_hash").unwrap_or_default(),
+                    role: text("role").unwrap_or_else(default_role),
+                    metadata: text("metadata")
+                        .and_then(|m| serde_json::from_str(&m).ok())
+                        .unwrap_or(json!({})),
+                    created_at: text("created_at"),
+                    updated_at: text("updated_at"),
+                }
+            })
+            .collect();
+
+        Ok(UserExport {
+            version: EXPORT_VERSION,
+            exported_at: self.clock.now().to_rfc3339(),
+            users,
+        })
+    }
+
+    /// Merges exported users into this instance
+    ///
+    /// Users are matched by email; `overrides` picks the conflict policy
+    /// per email and `on_conflict` applies to the rest. New users get new
+    /// ids. Overwriting a user ends their sessions. Everything is written
+    /// in one transaction, and nothing is written if an entry is invalid or
+    /// an `error` policy meets an existing email.
+    pub async fn import_users(
+        &self,
+        users: Vec<ExportedUser>,
+        on_conflict: ConflictPolicy,
+        overrides: &BTreeMap<String, ConflictPolicy>,
+    ) -> VibeResult<Vec<ImportResult>> {
+        let mut seen = HashSet::new();
+        for user in &users {
+            self.validate_email(&user.email)
+                .map_err(|_| VibeError::InvalidPayload(format!("Invalid email: {}", user.email)))?;
+            if !seen.insert(user.email.as_str()) {
+                return Err(VibeError::InvalidPayload(format!("Duplicate email: {}", user.email)));
+            }
+            if PasswordHash::new(&user.password_hash).is_err() {
+                return Err(VibeError::InvalidPayload(format!("Invalid password hash for {}", user.email)));
+            }
+            if user.role != USER_ROLE && user.role != ADMIN_ROLE {
+                return Err(VibeError::InvalidPayload(format!(
+                    "Invalid role '{}' for {}; use {} or {}",
+                    user.role, user.email, USER_ROLE, ADMIN_ROLE
+                )));
+            }
+        }
+
+        let planned: Vec<(ExportedUser, ConflictPolicy)> = users
+            .into_iter()
+            .map(|user| {
+                let policy = overrides.get(&user.email).copied().unwrap_or(on_conflict);
+                (user, policy)
+            })
+            .collect();
+
+        self.store
+            .with_transaction(move |conn| {
+                let mut existing = Vec::with_capacity(planned.len());
+                let mut conflicts = Vec::new();
+                for (user, policy) in &planned {
+                    let id: Option<i64> = conn
+                        .query_row("SELECT id FROM vibe_users WHERE email = ?", [&user.email], |row| row.get(0))
+                        .optional()?;
+                    if id.is_some() && *policy == ConflictPolicy::Error {
+                        conflicts.push(user.email.clone());
+                    }
+                    existing.push(id);
+                }
+                if !conflicts.is_empty() {
+                    return Ok(Err(conflicts));
+                }
+
+                let mut results = Vec::with_capacity(planned.len());
+                for ((user, policy), id) in planned.into_iter().zip(existing) {
+                    let metadata = if user.metadata.is_null() { json!({}) } else { user.metadata };
+                    let status = match (id, policy) {
+                        (None, _) => {
+                            conn.execute(
+                                "INSERT INTO vibe_users (email, password_hash, role, metadata, created_at, updated_at)
+                                 VALUES (?, ?, ?, ?, COALESCE(?, CURRENT_TIMESTAMP), COALESCE(?, CURRENT_TIMESTAMP))",
+                                rusqlite::params![
+                                    user.email,
+                                    user.password_hash,
+                                    user.role,
+                                    metadata.to_string(),
+                                    user.created_at,
+                                    user.updated_at
+                                ],
+                            )?;
+                            ImportStatus::Created
+                        }
+                        (Some(id), ConflictPolicy::Overwrite) => {
+                            conn.execute(
+                                "UPDATE vibe_users SET password_hash = ?, role = ?, metadata = ?,
+                                 updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+                                rusqlite::params![user.password_hash, user.role, metadata.to_string(), id],
+                            )?;
+                            conn.execute("DELETE FROM vibe_sessions WHERE user_id = ?", [id])?;
+                            ImportStatus::Overwritten
+                        }
+                        (Some(_), _) => ImportStatus::Skipped,
+                    };
+                    results.push(ImportResult { email: user.email, status });
+                }
+                Ok(Ok(results))
+            })
+            .await?
+            .map_err(|emails| VibeError::Conflict(format!("Users already exist: {}", emails.join(", "))))
+    }
+
     /// Convert database row to User struct
     fn row_to_user(&self, row: &[(String, Value)]) -> VibeResult<User> {
         let get_str = |key: &str| -> VibeResult<String> {
@@ -477,6 +1016,7 @@ impl AuthService {
         Ok(User {
             id: get_i64("id")?,
             email: get_str("email")?,
+            role: get_str("role").unwrap_or_else(|_| default_role()),
             created_at: get_str("created_at")?,
             updated_at: get_str("updated_at")?,
             metadata,
@@ -484,6 +1024,96 @@ impl AuthService {
     }
 }
 
+impl ExportDocument {
+    /// Encrypts an export with AES-256-GCM under a key derived from
+    /// `passphrase` with PBKDF2
+    pub async fn seal(export: &UserExport, passphrase: &str) -> VibeResult<Self> {
+        use base64::Engine;
+        let mut salt = [0u8; 16];
+        let mut nonce = [0u8; aead::NONCE_LEN];
+        rand::thread_rng().fill(&mut salt);
+        rand::thread_rng().fill(&mut nonce);
+
+        let key = export_key(passphrase, &salt, EXPORT_KDF_ITERATIONS).await?;
+        let mut sealed = serde_json::to_vec(export)?;
+        key.seal_in_place_append_tag(aead::Nonce::assume_unique_for_key(nonce), aead::Aad::empty(), &mut sealed)
+            .map_err(|_| VibeError::Internal(anyhow::anyhow!("Export encryption failed")))?;
+
+        let b64 = base64::engine::general_purpose::STANDARD;
+        Ok(Self::Encrypted(EncryptedExport {
+            cipher: EXPORT_CIPHER.to_string(),
+            kdf: EXPORT_KDF.to_string(),
+            iterations: EXPORT_KDF_ITERATIONS,
+            salt: b64.encode(salt),
+            nonce: b64.encode(nonce),
+            ciphertext: b64.encode(sealed),
+        }))
+    }
+
+    /// The export, decrypted with `passphrase` if it is encrypted
+    pub async fn open(self, passphrase: Option<&str>) -> VibeResult<UserExport> {
+        use base64::Engine;
+        let encrypted = match self {
+            Self::Plain(export) => return Ok(export),
+            Self::Encrypted(encrypted) => encrypted,
+        };
+        let passphrase = passphrase
+            .ok_or_else(|| VibeError::InvalidPayload("The export is encrypted; a passphrase is required".to_string()))?;
+        if encrypted.cipher != EXPORT_CIPHER || encrypted.kdf != EXPORT_KDF {
+            return Err(VibeError::InvalidPayload(format!(
+                "Unsupported export encryption: {} with {}",
+                encrypted.cipher, encrypted.kdf
+            )));
+        }
+
+        let b64 = base64::engine::general_purpose::STANDARD;
+        let decode = |field: &str| {
+            b64.decode(field)
+                .map_err(|_| VibeError::InvalidPayload("Malformed encrypted export".to_string()))
+        };
+        let salt = decode(&encrypted.salt)?;
+        let nonce = aead::Nonce::try_assume_unique_for_key(&decode(&encrypted.nonce)?)
+            .map_err(|_| VibeError::InvalidPayload("Malformed encrypted export".to_string()))?;
+        let mut sealed = decode(&encrypted.ciphertext)?;
+
+        let key = export_key(passphrase, &salt, encrypted.iterations).await?;
+        let plain = key
+            .open_in_place(nonce, aead::Aad::empty(), &mut sealed)
+            .map_err(|_| VibeError::InvalidPayload("Could not decrypt the export; wrong passphrase?".to_string()))?;
+        Ok(serde_json::from_slice(plain)?)
+    }
+}
+
+/// Format of `vibe_sessions.expires_at`
+fn format_session_time(time: chrono::DateTime<chrono::Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// AES-256-GCM key for an export passphrase
+///
+/// The derivation runs off the async runtime.
+async fn export_key(passphrase: &str, salt: &[u8], iterations: u32) -> VibeResult<aead::LessSafeKey> {
+    if iterations > MAX_EXPORT_KDF_ITERATIONS {
+        return Err(VibeError::InvalidPayload(format!(
+            "Encrypted exports may use at most {} key derivation iterations",
+            MAX_EXPORT_KDF_ITERATIONS
+        )));
+    }
+    let iterations = NonZeroU32::new(iterations)
+        .ok_or_else(|| VibeError::InvalidPayload("Malformed encrypted export".to_string()))?;
+    let (passphrase, salt) = (passphrase.to_string(), salt.to_vec());
+    let key = tokio::task::spawn_blocking(move || {
+        let mut key = [0u8; 32];
+        pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, iterations, &salt, passphrase.as_bytes(), &mut key);
+        key
+    })
+    .await
+    .map_err(|e| VibeError::Internal(anyhow::anyhow!("Key derivation task failed: {}", e)))?;
+    let key = aead::UnboundKey::new(&aead::AES_256_GCM, &key)
+        .map_err(|_| VibeError::Internal(anyhow::anyhow!("Invalid export key")))?;
+    Ok(aead::LessSafeKey::new(key))
+}
+
 // ============================================================================
 // Auth Middleware Extractor
 // ============================================================================
@@ -496,21 +1126,42 @@ pub struct AuthState {
 
 /// Extract and validate JWT token from Authorization header
 fn extract_auth_user(auth_state: &AuthState, headers: &axum::http::HeaderMap) -> Result<AuthUser, VibeError> {
-    let auth_header = headers
-        .get(AUTHORIZATION)
-        .and_then(|h| h.to_str().ok())
-        .ok_or_else(|| VibeError::Unauthorized("Missing authorization header".to_string()))?;
-
-    let token = auth_header
-        .strip_prefix("Bearer ")
-        .ok_or_else(|| VibeError::Unauthorized("Invalid authorization format".to_string()))?;
+    auth_state.auth.authenticate(headers)
+}
 
-    let claims = auth_state.auth.validate_token(token)?;
+/// `extract_auth_user` for endpoints only admins may use
+fn extract_admin(
+    auth_state: &AuthState,
+    headers: &axum::http::HeaderMap,
+    action: &str,
+) -> Result<AuthUser, VibeError> {
+    let auth_user = extract_auth_user(auth_state, headers)?;
+    if !auth_user.is_admin() {
+        return Err(VibeError::Forbidden(format!("{} requires an admin", action)));
+    }
+    Ok(auth_user)
+}
 
-    Ok(AuthUser {
-        id: claims.sub,
-        email: claims.email,
-    })
+impl AuthService {
+    /// Resolves the bearer token in the request headers to a user
+    pub fn authenticate(&self, headers: &axum::http::HeaderMap) -> VibeResult<AuthUser> {
+        let auth_header = headers
+            .get(AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .ok_or_else(|| VibeError::Unauthorized("Missing authorization header".to_string()))?;
+
+        let token = auth_header
+            .strip_prefix("Bearer ")
+            .ok_or_else(|| VibeError::Unauthorized("Invalid authorization format".to_string()))?;
+
+        let claims = self.validate_token(token)?;
+
+        Ok(AuthUser {
+            id: claims.sub,
+            email: claims.email,
+            role: claims.role,
+        })
+    }
 }
 
 // ============================================================================
@@ -592,6 +1243,117 @@ async fn update_user_handler(
     })))
 }
 
+/// POST /v1/auth/rotate-secret (admin only)
+///
+/// The secret itself is never returned; supply one to be able to persist it
+/// through `VIBEDB_JWT_SECRET`.
+async fn rotate_secret_handler(
+    State(state): State<AuthState>,
+    headers: axum::http::HeaderMap,
+    body: Option<Json<RotateSecretRequest>>,
+) -> Result<impl IntoResponse, VibeError> {
+    let auth_user = extract_admin(&state, &headers, "Rotating the JWT secret")?;
+
+    let Json(req) = body.unwrap_or_default();
+    let (secret, generated) = match req.secret {
+        Some(secret) if secret.len() < MIN_SECRET_LENGTH => {
+            return Err(VibeError::InvalidPayload(format!(
+                "Secret must be at least {} bytes",
+                MIN_SECRET_LENGTH
+            )));
+        }
+        Some(secret) => (secret.into_bytes(), false),
+        None => (AuthService::generate_secret(), true),
+    };
+    state.auth.rotate_secret(secret);
+    info!(
+        target: "vibedb::security",
+        "JWT secret rotated by {} ({})",
+        auth_user.email,
+        if generated { "generated" } else { "supplied" }
+    );
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "JWT secret rotated; tokens signed with the previous secret remain valid until they expire",
+        "generated": generated
+    })))
+}
+
+/// GET /v1/auth/export (admin only)
+///
+/// Includes password hashes. With an `X-Export-Passphrase` header the
+/// document is encrypted (see `ExportDocument::seal`).
+async fn export_users_handler(
+    State(state): State<AuthState>,
+    headers: axum::http::HeaderMap,
+) -> Result<impl IntoResponse, VibeError> {
+    let auth_user = extract_admin(&state, &headers, "Exporting users")?;
+    let passphrase = headers
+        .get(EXPORT_PASSPHRASE_HEADER)
+        .map(|v| {
+            v.to_str()
+                .map_err(|_| VibeError::InvalidPayload("Invalid export passphrase".to_string()))
+        })
+        .transpose()?;
+    if passphrase.is_some_and(|p| p.len() < MIN_PASSPHRASE_LENGTH) {
+        return Err(VibeError::InvalidPayload(format!(
+            "Export passphrase must be at least {} characters",
+            MIN_PASSPHRASE_LENGTH
+        )));
+    }
+
+    let export = state.auth.export_users().await?;
+    let count = export.users.len();
+    let document = match passphrase {
+        Some(passphrase) => ExportDocument::seal(&export, passphrase).await?,
+        None => ExportDocument::Plain(export),
+    };
+    info!(
+        target: "vibedb::security",
+        "{} users exported by {} ({})",
+        count,
+        auth_user.email,
+        if passphrase.is_some() { "encrypted" } else { "plaintext" }
+    );
+
+    Ok(Json(json!({
+        "success": true,
+        "data": document
+    })))
+}
+
+/// POST /v1/auth/import (admin only)
+async fn import_users_handler(
+    State(state): State<AuthState>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<ImportUsersRequest>,
+) -> Result<impl IntoResponse, VibeError> {
+    let auth_user = extract_admin(&state, &headers, "Importing users")?;
+    let export = req.data.open(req.passphrase.as_deref()).await?;
+    let results = state.auth.import_users(export.users, req.on_conflict, &req.overrides).await?;
+
+    let count = |status| results.iter().filter(|r| r.status == status).count();
+    let (created, overwritten, skipped) = (
+        count(ImportStatus::Created),
+        count(ImportStatus::Overwritten),
+        count(ImportStatus::Skipped),
+    );
+    info!(
+        target: "vibedb::security",
+        "Users imported by {}: {} created, {} overwritten, {} skipped",
+        auth_user.email, created, overwritten, skipped
+    );
+
+    Ok(Json(json!({
+        "success": true,
+        "data": results,
+        "created": created,
+        "overwritten": overwritten,
+        "skipped": skipped
+    })))
+}
+
 // ============================================================================
 // Router
 // ============================================================================
@@ -605,6 +1367,9 @@ pub fn create_auth_router(auth_state: AuthState) -> Router {
         .route("/logout", post(logout_handler))
         .route("/me", get(me_handler))
         .route("/user", put(update_user_handler))
+        .route("/rotate-secret", post(rotate_secret_handler))
+        .route("/export", get(export_users_handler))
+        .route("/import", post(import_users_handler))
         .with_state(auth_state)
 }
 
@@ -636,6 +1401,7 @@ impl VibeError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::ManualClock;
 
     async fn create_test_service() -> AuthService {
         let store = Arc::new(VibeStore::in_memory().await.unwrap());
@@ -668,6 +1434,25 @@ mod tests {
         assert_eq!(tokens.user.email, "test@vibedb.dev");
     }
 
+    #[tokio::test]
+    async fn test_concurrent_signups_conflict_cleanly() {
+        let service = create_test_service().await;
+        let signups = (0..8).map(|_| {
+            service.signup(SignupRequest {
+                email: "race@vibedb.dev".to_string(),
+                password: "password123".to_string(),
+                metadata: None,
+            })
+        });
+
+        let results = futures::future::join_all(signups).await;
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+        for err in results.into_iter().filter_map(Result::err) {
+            assert_eq!(err.error_code(), "CONFLICT");
+        }
+        assert_eq!(service.user_count().await.unwrap(), 1);
+    }
+
     #[tokio::test]
     async fn test_login_flow() {
         let service = create_test_service().await;
@@ -703,8 +1488,120 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_refresh_flow() {
+    async fn test_rotated_secret_keeps_one_generation_valid() {
         let service = create_test_service().await;
+        let tokens = service.signup(SignupRequest {
+            email: "test@vibedb.dev".to_string(),
+            password: "password123".to_string(),
+            metadata: None,
+        }).await.unwrap();
+        let url_signature = service.sign_url("files/a.txt\n60");
+
+        service.rotate_secret(AuthService::generate_secret());
+        assert!(service.validate_token(&tokens.access_token).is_ok());
+        assert!(service.verify_url("files/a.txt\n60", &url_signature));
+        let user = service.get_user_by_id(tokens.user.id).await.unwrap();
+        let fresh = service.generate_access_token(&user).unwrap();
+
+        service.rotate_secret(AuthService::generate_secret());
+        assert!(service.validate_token(&tokens.access_token).is_err());
+        assert!(!service.verify_url("files/a.txt\n60", &url_signature));
+        assert!(service.validate_token(&fresh).is_ok());
+    }
+
+    async fn signup(service: &AuthService, email: &str, password: &str) {
+        service.signup(SignupRequest {
+            email: email.to_string(),
+            password: password.to_string(),
+            metadata: Some(json!({"from": "source"})),
+        }).await.unwrap();
+    }
+
+    async fn can_login(service: &AuthService, email: &str, password: &str) -> bool {
+        service.login(LoginRequest {
+            email: email.to_string(),
+            password: password.to_string(),
+        }).await.is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_import_users_under_each_conflict_policy() {
+        let source = create_test_service().await;
+        signup(&source, "alice@vibedb.dev", "alice-source").await;
+        signup(&source, "bob@vibedb.dev", "bob-source").await;
+        let export = source.export_users().await.unwrap();
+        assert_eq!(export.users.len(), 2);
+
+        let no_overrides = BTreeMap::new();
+        for policy in [ConflictPolicy::Skip, ConflictPolicy::Overwrite, ConflictPolicy::Error] {
+            let target = create_test_service().await;
+            signup(&target, "bob@vibedb.dev", "bob-target").await;
+            let result = target.import_users(export.users.clone(), policy, &no_overrides).await;
+
+            match policy {
+                ConflictPolicy::Error => {
+                    assert_eq!(result.unwrap_err().error_code(), "CONFLICT");
+                    assert!(!can_login(&target, "alice@vibedb.dev", "alice-source").await);
+                    continue;
+                }
+                ConflictPolicy::Skip => {
+                    assert_eq!(result.unwrap()[1].status, ImportStatus::Skipped);
+                    assert!(can_login(&target, "bob@vibedb.dev", "bob-target").await);
+                }
+                ConflictPolicy::Overwrite => {
+                    assert_eq!(result.unwrap()[1].status, ImportStatus::Overwritten);
+                    assert!(can_login(&target, "bob@vibedb.dev", "bob-source").await);
+                }
+            }
+            assert!(can_login(&target, "alice@vibedb.dev", "alice-source").await);
+        }
+
+        // Per-email policies win over the default
+        let target = create_test_service().await;
+        signup(&target, "bob@vibedb.dev", "bob-target").await;
+        let overrides = BTreeMap::from([("bob@vibedb.dev".to_string(), ConflictPolicy::Overwrite)]);
+        let results = target.import_users(export.users, ConflictPolicy::Error, &overrides).await.unwrap();
+        assert_eq!(results[0].status, ImportStatus::Created);
+        assert_eq!(results[1].status, ImportStatus::Overwritten);
+
+        // Only known roles are imported
+        let mut users = source.export_users().await.unwrap().users;
+        users[0].role = "superuser".to_string();
+        let target = create_test_service().await;
+        let error = target.import_users(users, ConflictPolicy::Skip, &no_overrides).await.unwrap_err();
+        assert_eq!(error.error_code(), "INVALID_PAYLOAD");
+        assert!(!can_login(&target, "alice@vibedb.dev", "alice-source").await);
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_export_round_trip() {
+        let source = create_test_service().await;
+        signup(&source, "alice@vibedb.dev", "alice-source").await;
+        let export = source.export_users().await.unwrap();
+
+        let sealed = ExportDocument::seal(&export, "correct horse battery").await.unwrap();
+        let document: ExportDocument = serde_json::from_value(serde_json::to_value(&sealed).unwrap()).unwrap();
+        assert!(!serde_json::to_string(&document).unwrap().contains("argon2"));
+        let error = |result: VibeResult<UserExport>| result.unwrap_err().error_code();
+        assert_eq!(error(document.clone().open(Some("wrong passphrase!")).await), "INVALID_PAYLOAD");
+        assert_eq!(error(document.clone().open(None).await), "INVALID_PAYLOAD");
+        let ExportDocument::Encrypted(mut costly) = document.clone() else {
+            panic!("expected an encrypted export");
+        };
+        costly.iterations = u32::MAX;
+        let costly = ExportDocument::Encrypted(costly).open(Some("correct horse battery")).await;
+        assert!(costly.unwrap_err().to_string().contains("at most"));
+
+        let opened = document.open(Some("correct horse battery")).await.unwrap();
+        let target = create_test_service().await;
+        target.import_users(opened.users, ConflictPolicy::Skip, &BTreeMap::new()).await.unwrap();
+        assert!(can_login(&target, "alice@vibedb.dev", "alice-source").await);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_flow() {
+        let clock = ManualClock::new();
+        let service = create_test_service().await.with_clock(Arc::new(clock.clone()));
         
         let tokens = service.signup(SignupRequest {
             email: "test@vibedb.dev".to_string(),
@@ -712,15 +1609,47 @@ mod tests {
             metadata: None,
         }).await.unwrap();
 
-        // Wait for 1 second to ensure new token has different timestamp (iat is in seconds)
-        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        // iat is in seconds, so the new token differs once a second passes
+        clock.advance(Duration::from_secs(1));
 
         let new_tokens = service.refresh(RefreshRequest {
-            refresh_token: tokens.refresh_token,
+            refresh_token: tokens.refresh_token.clone(),
         }).await.unwrap();
 
         assert!(!new_tokens.access_token.is_empty());
         assert_ne!(new_tokens.access_token, tokens.access_token);
+
+        // A refresh token is used up by refreshing
+        let reused = service.refresh(RefreshRequest { refresh_token: tokens.refresh_token }).await;
+        assert!(reused.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tokens_expire_by_the_clock() {
+        let clock = ManualClock::new();
+        let service = create_test_service().await.with_clock(Arc::new(clock.clone()));
+        let tokens = service.signup(SignupRequest {
+            email: "test@vibedb.dev".to_string(),
+            password: "password123".to_string(),
+            metadata: None,
+        }).await.unwrap();
+
+        // Access tokens get a minute of leeway past their expiry
+        clock.advance(DEFAULT_ACCESS_TOKEN_DURATION + Duration::from_secs(60));
+        assert!(service.validate_token(&tokens.access_token).is_ok());
+        clock.advance(Duration::from_secs(1));
+        let err = service.validate_token(&tokens.access_token).unwrap_err();
+        assert!(err.to_string().contains("ExpiredSignature"), "{}", err);
+
+        // The refresh token outlives it, but not forever
+        let refreshed = service.refresh(RefreshRequest { refresh_token: tokens.refresh_token }).await.unwrap();
+        assert!(service.validate_token(&refreshed.access_token).is_ok());
+        clock.advance(DEFAULT_REFRESH_TOKEN_DURATION);
+        let err = service
+            .refresh(RefreshRequest { refresh_token: refreshed.refresh_token })
+            .await
+            .unwrap_err();
+        assert_eq!(err.error_code(), "UNAUTHORIZED");
     }
 
     #[tokio::test]
@@ -0,0 +1,204 @@
+//! # Write Batching
+//!
+//! Opt-in coalescing of single-document pushes. Each push to a collection
+//! with batching on hands its INSERT to that collection's batching task,
+//! which collects whatever arrives within `BatchConfig::window` (or until
+//! `BatchConfig::max_writes` are waiting) and commits them in one
+//! transaction. The push is answered once its row is committed, with its
+//! real id, so a request waits at most one window longer than it would
+//! unbatched, plus the time to commit the batch.
+//!
+//! If the coalesced transaction fails, nothing of it is kept and its rows
+//! are inserted one by one instead, so a bad payload only fails its own
+//! request. Audit entries of inserts into audited collections are written
+//! in whichever transaction commits the row.
+//!
+//! Enabled for every collection with `VIBEDB_BATCH_WRITES`, or per
+//! collection with `{"batch_writes": true}` in its settings. Pushes with
+//! exploded arrays already run in their own transaction and are not
+//! batched.
+
+use crate::audit::{self, InsertAudit};
+use crate::config::BatchConfig;
+use crate::db::{SqlValue, VibeStore};
+use crate::error::{VibeError, VibeResult};
+
+use dashmap::DashMap;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+use tracing::debug;
+
+/// An INSERT waiting for its batch to commit
+struct PendingInsert {
+    sql: String,
+    params: Vec<SqlValue>,
+    audit: Option<InsertAudit>,
+    reply: oneshot::Sender<VibeResult<i64>>,
+}
+
+/// Batching tasks, one per collection that has seen a batched push
+pub struct WriteBatcher {
+    store: Arc<VibeStore>,
+    config: BatchConfig,
+    queues: DashMap<String, mpsc::UnboundedSender<PendingInsert>>,
+}
+
+impl WriteBatcher {
+    pub fn new(store: Arc<VibeStore>, config: BatchConfig) -> Self {
+        Self {
+            store,
+            config,
+            queues: DashMap::new(),
+        }
+    }
+
+    /// Whether pushes are batched for every collection
+    pub fn enabled_globally(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Inserts a row along with the other pushes to `collection` arriving
+    /// in the same window and returns its id once committed
+    ///
+    /// Busy retries are counted under `label`, as for unbatched writes.
+    /// With `audit`, the insert's audit entry commits with it.
+    pub async fn insert(
+        &self,
+        collection: &str,
+        label: &str,
+        sql: String,
+        params: Vec<SqlValue>,
+        audit: Option<InsertAudit>,
+    ) -> VibeResult<i64> {
+        let (reply, committed) = oneshot::channel();
+        let queue = self
+            .queues
+            .entry(collection.to_string())
+            .or_insert_with(|| self.spawn_queue(label))
+            .clone();
+        queue
+            .send(PendingInsert { sql, params, audit, reply })
+            .map_err(|_| VibeError::Internal(anyhow::anyhow!("Write batching task for {} stopped", collection)))?;
+        committed
+            .await
+            .map_err(|_| VibeError::Internal(anyhow::anyhow!("Write batching task for {} stopped", collection)))?
+    }
+
+    fn spawn_queue(&self, label: &str) -> mpsc::UnboundedSender<PendingInsert> {
+        let (queue, pending) = mpsc::unbounded_channel();
+        tokio::spawn(run_queue(
+            Arc::clone(&self.store),
+            self.config.clone(),
+            label.to_string(),
+            pending,
+        ));
+        queue
+    }
+}
+
+/// Collects a window of inserts at a time and commits each window together
+async fn run_queue(
+    store: Arc<VibeStore>,
+    config: BatchConfig,
+    label: String,
+    mut pending: mpsc::UnboundedReceiver<PendingInsert>,
+) {
+    while let Some(first) = pending.recv().await {
+        let deadline = Instant::now() + config.window;
+        let mut batch = vec![first];
+        while batch.len() < config.max_writes {
+            match tokio::time::timeout_at(deadline, pending.recv()).await {
+                Ok(Some(insert)) => batch.push(insert),
+                // Window over, or the batcher is gone
+                _ => break,
+            }
+        }
+        flush(&store, &label, batch).await;
+    }
+}
+
+/// Commits a batch in one transaction, falling back to one transaction
+/// per row when that fails
+async fn flush(store: &VibeStore, label: &str, batch: Vec<PendingInsert>) {
+    if batch.len() > 1 {
+        let rows = batch
+            .iter()
+            .map(|insert| (insert.sql.clone(), insert.params.clone(), insert.audit.clone()))
+            .collect();
+        match audit::insert_rows(store, label, rows).await {
+            Ok(ids) => {
+                for (insert, id) in batch.into_iter().zip(ids) {
+                    // The request may have been dropped; its row stays
+                    let _ = insert.reply.send(Ok(id));
+                }
+                return;
+            }
+            Err(e) => debug!("Batch of {} inserts failed ({}), inserting one by one", batch.len(), e),
+        }
+    }
+    for insert in batch {
+        let result = audit::insert_row(store, label, insert.sql, insert.params, insert.audit).await;
+        let _ = insert.reply.send(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    async fn batcher() -> WriteBatcher {
+        let store = Arc::new(VibeStore::in_memory().await.unwrap());
+        store
+            .execute_simple("CREATE TABLE items (id INTEGER PRIMARY KEY, sku TEXT UNIQUE)".to_string())
+            .await
+            .unwrap();
+        let config = BatchConfig {
+            enabled: true,
+            window: Duration::from_millis(50),
+            max_writes: 64,
+        };
+        WriteBatcher::new(store, config)
+    }
+
+    fn insert_sku(sku: &str) -> (String, Vec<SqlValue>) {
+        ("INSERT INTO items (sku) VALUES (?)".to_string(), vec![SqlValue::Text(sku.to_string())])
+    }
+
+    #[tokio::test]
+    async fn test_failed_row_does_not_fail_its_batch() {
+        let batcher = batcher().await;
+        batcher.store.execute_simple("INSERT INTO items (sku) VALUES ('taken')".to_string()).await.unwrap();
+        let before = batcher.store.write_transactions();
+
+        let inserts = ["a", "taken", "b"].map(|sku| {
+            let (sql, params) = insert_sku(sku);
+            batcher.insert("items", "items", sql, params, None)
+        });
+        let mut results = futures::future::join_all(inserts).await;
+
+        assert!(results[0].is_ok() && results[2].is_ok());
+        assert_eq!(results.remove(1).unwrap_err().error_code(), "CONSTRAINT_VIOLATION");
+        // The batch was rolled back and each good row committed on its own
+        assert_eq!(batcher.store.write_transactions() - before, 2);
+        let rows = batcher.store.query_simple("SELECT sku FROM items ORDER BY id".to_string()).await.unwrap();
+        assert_eq!(rows.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_batches_are_capped_at_max_writes() {
+        let mut batcher = batcher().await;
+        batcher.config.max_writes = 4;
+        let before = batcher.store.write_transactions();
+
+        let inserts = (0..10).map(|i| {
+            let (sql, params) = insert_sku(&format!("sku-{}", i));
+            batcher.insert("items", "items", sql, params, None)
+        });
+        for result in futures::future::join_all(inserts).await {
+            result.unwrap();
+        }
+        assert_eq!(batcher.store.write_transactions() - before, 3);
+    }
+}
@@ -0,0 +1,444 @@
+//! # Bootstrap
+//!
+//! Declarative provisioning for fresh instances: seeds the first admin user
+//! and the buckets an application expects, so a container can come up ready
+//! to use without manual API calls.
+//!
+//! Bootstrap never overwrites anything. The admin is only created while the
+//! user table is empty, existing buckets are left untouched, and the outcome
+//! is recorded under the `bootstrap` key of `vibe_meta`. Once an admin has
+//! been seeded it is not recreated, even if every user is later deleted.
+//!
+//! ## First-Run Setup
+//! Instances started without a bootstrap admin can be claimed from the
+//! Explorer instead: `GET /v1/auth/config` reports `needs_setup` while no
+//! user has ever existed, and `POST /v1/auth/setup` then creates the first
+//! admin (and optionally some buckets) and signs it in. Setup closes for
+//! good with the first user, however it was created.
+//!
+//! ## System Tables
+//! - `vibe_meta` - Instance-level key/value metadata
+
+use crate::auth::{AuthService, ADMIN_ROLE};
+use crate::config::{BootstrapConfig, BucketSeed};
+use crate::db::{SqlValue, VibeStore};
+use crate::error::{VibeError, VibeResult};
+use crate::storage::{Compression, CreateBucketRequest, StorageService};
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{info, warn};
+
+/// `vibe_meta` key holding the latest bootstrap report
+const BOOTSTRAP_META_KEY: &str = "bootstrap";
+
+/// What a bootstrap run did
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BootstrapReport {
+    /// Email of the admin seeded by this or an earlier run
+    pub admin_seeded: Option<String>,
+    /// Buckets created by this run
+    pub buckets_created: Vec<String>,
+    /// Requested buckets that already existed
+    pub buckets_existing: Vec<String>,
+}
+
+/// Parses a bucket list such as `public-assets:public,uploads:private`
+///
+/// Visibility defaults to private when omitted.
+pub fn parse_bucket_specs(spec: &str) -> VibeResult<Vec<BucketSeed>> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (name, visibility) = entry.split_once(':').unwrap_or((entry, "private"));
+            let public = match visibility.trim().to_lowercase().as_str() {
+                "public" => true,
+                "private" => false,
+                other => {
+                    return Err(VibeError::InvalidPayload(format!(
+                        "Invalid visibility '{}' for bucket '{}' (expected public or private)",
+                        other, name
+                    )))
+                }
+            };
+            Ok(BucketSeed {
+                name: name.trim().to_string(),
+                public,
+            })
+        })
+        .collect()
+}
+
+/// Applies the bootstrap config; safe to run on every startup
+pub async fn run_bootstrap(
+    store: &VibeStore,
+    auth: &AuthService,
+    storage: &StorageService,
+    config: &BootstrapConfig,
+) -> VibeResult<BootstrapReport> {
+    store
+        .execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS vibe_meta (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+            "#
+            .to_string(),
+        )
+        .await?;
+
+    let previous = load_report(store).await?;
+    let mut report = BootstrapReport {
+        admin_seeded: previous.as_ref().and_then(|r| r.admin_seeded.clone()),
+        ..Default::default()
+    };
+
+    if let (Some(email), Some(password)) = (&config.admin_email, &config.admin_password) {
+        if let Some(seeded) = &report.admin_seeded {
+            info!("🌱 Bootstrap admin already seeded ({}), skipping", seeded);
+        } else if auth.user_count().await? > 0 {
+            info!("🌱 Users already exist, skipping bootstrap admin {}", email);
+        } else {
+            auth.create_user(email, password, ADMIN_ROLE, json!({})).await?;
+            info!("🌱 Bootstrap created admin user {}", email);
+            report.admin_seeded = Some(email.clone());
+        }
+    }
+
+    seed_buckets(storage, &config.buckets, &mut report).await?;
+
+    store
+        .execute(
+            "INSERT INTO vibe_meta (key, value) VALUES (?, ?) \
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = CURRENT_TIMESTAMP"
+                .to_string(),
+            vec![
+                SqlValue::Text(BOOTSTRAP_META_KEY.to_string()),
+                SqlValue::Text(serde_json::to_string(&report)?),
+            ],
+        )
+        .await?;
+
+    Ok(report)
+}
+
+/// Creates the buckets of `seeds` that don't exist yet, noting each in
+/// `report`
+async fn seed_buckets(
+    storage: &StorageService,
+    seeds: &[BucketSeed],
+    report: &mut BootstrapReport,
+) -> VibeResult<()> {
+    for seed in seeds {
+        match storage.get_bucket(&seed.name).await {
+            Ok(bucket) => {
+                if bucket.public != seed.public {
+                    info!(
+                        "🌱 Bucket {} exists with different visibility, leaving it unchanged",
+                        seed.name
+                    );
+                }
+                report.buckets_existing.push(seed.name.clone());
+            }
+            Err(VibeError::NotFound(_)) => {
+                storage
+                    .create_bucket(
+                        CreateBucketRequest {
+                            name: seed.name.clone(),
+                            public: seed.public,
+                            compression: Compression::None,
+                        },
+                        None,
+                    )
+                    .await?;
+                info!(
+                    "🌱 Bootstrap created {} bucket {}",
+                    if seed.public { "public" } else { "private" },
+                    seed.name
+                );
+                report.buckets_created.push(seed.name.clone());
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Reads the report stored by a previous run
+async fn load_report(store: &VibeStore) -> VibeResult<Option<BootstrapReport>> {
+    let rows = store
+        .query(
+            "SELECT value FROM vibe_meta WHERE key = ?".to_string(),
+            vec![SqlValue::Text(BOOTSTRAP_META_KEY.to_string())],
+        )
+        .await?;
+
+    // JSON text comes back already decoded by the store
+    Ok(rows
+        .into_iter()
+        .next()
+        .and_then(|row| row.into_iter().next())
+        .and_then(|(_, value)| serde_json::from_value(value).ok()))
+}
+
+/// State of the first-run setup endpoints
+#[derive(Clone)]
+pub struct SetupState {
+    pub auth: AuthService,
+    pub storage: StorageService,
+}
+
+/// Body of `POST /v1/auth/setup`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SetupRequest {
+    pub email: String,
+    pub password: String,
+    /// Buckets to create along with the admin
+    #[serde(default)]
+    pub buckets: Vec<SetupBucket>,
+}
+
+/// A bucket requested during setup; private unless `public` is set
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SetupBucket {
+    pub name: String,
+    #[serde(default)]
+    pub public: bool,
+}
+
+/// GET /v1/auth/config - What a client needs before signing in
+///
+/// `needs_setup` is true while no user has ever existed.
+async fn auth_config_handler(State(state): State<SetupState>) -> Result<impl IntoResponse, VibeError> {
+    let needs_setup = state.auth.needs_setup().await?;
+    Ok(Json(json!({
+        "success": true,
+        "data": { "needs_setup": needs_setup }
+    })))
+}
+
+/// POST /v1/auth/setup - Create the first admin of a fresh instance
+///
+/// Responds like a signup, with the admin's tokens, plus the buckets
+/// created and those that already existed. Refused with 403 once any user
+/// has ever existed (see `AuthService::create_first_admin`). Buckets are
+/// created after the admin, so a failing one leaves setup complete.
+async fn setup_handler(
+    State(state): State<SetupState>,
+    Json(req): Json<SetupRequest>,
+) -> Result<impl IntoResponse, VibeError> {
+    let tokens = match state.auth.create_first_admin(&req.email, &req.password).await {
+        Ok(tokens) => tokens,
+        Err(e @ VibeError::Forbidden(_)) => {
+            warn!(target: "vibedb::security", "Setup attempt after setup was complete ({})", req.email);
+            return Err(e);
+        }
+        Err(e) => return Err(e),
+    };
+    info!(target: "vibedb::security", "First-run setup created admin {}", tokens.user.email);
+
+    let seeds: Vec<BucketSeed> = req
+        .buckets
+        .into_iter()
+        .map(|bucket| BucketSeed {
+            name: bucket.name,
+            public: bucket.public,
+        })
+        .collect();
+    let mut report = BootstrapReport::default();
+    seed_buckets(&state.storage, &seeds, &mut report).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "success": true,
+            "data": tokens,
+            "buckets_created": report.buckets_created,
+            "buckets_existing": report.buckets_existing
+        })),
+    ))
+}
+
+/// Creates the first-run setup router, nested under `/v1/auth` next to
+/// `auth::create_auth_router`
+pub fn create_setup_router(state: SetupState) -> Router {
+    Router::new()
+        .route("/config", get(auth_config_handler))
+        .route("/setup", post(setup_handler))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::LoginRequest;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    async fn services() -> (Arc<VibeStore>, AuthService, StorageService) {
+        let store = Arc::new(VibeStore::in_memory().await.unwrap());
+        let auth = AuthService::new(Arc::clone(&store), AuthService::generate_secret())
+            .await
+            .unwrap();
+        let storage = StorageService::new(Arc::clone(&store), Some(tempdir().unwrap().keep()))
+            .await
+            .unwrap();
+        (store, auth, storage)
+    }
+
+    fn config() -> BootstrapConfig {
+        BootstrapConfig {
+            admin_email: Some("admin@vibedb.dev".to_string()),
+            admin_password: Some("bootstrap-secret".to_string()),
+            buckets: parse_bucket_specs("public-assets:public,uploads:private").unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_parse_bucket_specs() {
+        let seeds = parse_bucket_specs(" a:public , b ,").unwrap();
+        assert_eq!(seeds.len(), 2);
+        assert!(seeds[0].public);
+        assert!(!seeds[1].public);
+        assert!(parse_bucket_specs("a:secret").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_fresh_and_idempotent() {
+        let (store, auth, storage) = services().await;
+
+        let report = run_bootstrap(&store, &auth, &storage, &config()).await.unwrap();
+        assert_eq!(report.admin_seeded.as_deref(), Some("admin@vibedb.dev"));
+        assert_eq!(report.buckets_created, vec!["public-assets", "uploads"]);
+        assert!(storage.is_bucket_public("public-assets").await.unwrap());
+
+        let tokens = auth
+            .login(LoginRequest {
+                email: "admin@vibedb.dev".to_string(),
+                password: "bootstrap-secret".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(tokens.user.role, ADMIN_ROLE);
+
+        // A second run changes nothing
+        let again = run_bootstrap(&store, &auth, &storage, &config()).await.unwrap();
+        assert!(again.buckets_created.is_empty());
+        assert_eq!(again.buckets_existing.len(), 2);
+        assert_eq!(auth.user_count().await.unwrap(), 1);
+
+        // Deleting the admin does not resurrect it
+        store
+            .execute("DELETE FROM vibe_users".to_string(), vec![])
+            .await
+            .unwrap();
+        run_bootstrap(&store, &auth, &storage, &config()).await.unwrap();
+        assert_eq!(auth.user_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_skips_provisioned_instance() {
+        let (store, auth, storage) = services().await;
+        auth.create_user("owner@vibedb.dev", "password123", "user", json!({}))
+            .await
+            .unwrap();
+        storage
+            .create_bucket(
+                CreateBucketRequest {
+                    name: "uploads".to_string(),
+                    public: true,
+                    compression: Compression::None,
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let report = run_bootstrap(&store, &auth, &storage, &config()).await.unwrap();
+        assert!(report.admin_seeded.is_none());
+        assert_eq!(report.buckets_created, vec!["public-assets"]);
+        assert_eq!(report.buckets_existing, vec!["uploads"]);
+        assert_eq!(auth.user_count().await.unwrap(), 1);
+        // Existing visibility is left alone
+        assert!(storage.is_bucket_public("uploads").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_first_run_setup_creates_one_admin() {
+        use crate::testing::TestServer;
+        use axum::http::StatusCode;
+
+        let server = TestServer::spawn().await;
+        let config = server.get("/v1/auth/config").send().await.assert_success();
+        assert_eq!(config["needs_setup"], true);
+
+        let body = server
+            .post("/v1/auth/setup")
+            .json(json!({
+                "email": "owner@vibedb.dev",
+                "password": "password123",
+                "buckets": [{"name": "avatars", "public": true}]
+            }))
+            .send()
+            .await
+            .assert_status(StatusCode::CREATED)
+            .json();
+        assert_eq!(body["data"]["user"]["role"], ADMIN_ROLE);
+        assert_eq!(body["buckets_created"], json!(["avatars"]));
+        assert!(server.storage.is_bucket_public("avatars").await.unwrap());
+        let token = body["data"]["access_token"].as_str().unwrap();
+        server.get("/v1/admin/pragmas").bearer(token).send().await.assert_success();
+
+        let config = server.get("/v1/auth/config").send().await.assert_success();
+        assert_eq!(config["needs_setup"], false);
+        let again = json!({"email": "second@vibedb.dev", "password": "password123"});
+        server
+            .post("/v1/auth/setup")
+            .json(again.clone())
+            .send()
+            .await
+            .assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+
+        // Emptying the users table doesn't reopen setup
+        server.store.execute("DELETE FROM vibe_users".to_string(), vec![]).await.unwrap();
+        let config = server.get("/v1/auth/config").send().await.assert_success();
+        assert_eq!(config["needs_setup"], false);
+        server
+            .post("/v1/auth/setup")
+            .json(again)
+            .send()
+            .await
+            .assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+    }
+
+    #[tokio::test]
+    async fn test_racing_setups_create_one_admin() {
+        use crate::testing::TestServer;
+
+        let server = TestServer::spawn().await;
+        let setups = (0..4).map(|i| {
+            server
+                .post("/v1/auth/setup")
+                .json(json!({"email": format!("owner{}@vibedb.dev", i), "password": "password123"}))
+                .send()
+        });
+        let responses = futures::future::join_all(setups).await;
+
+        let statuses: Vec<u16> = responses.iter().map(|r| r.status.as_u16()).collect();
+        assert_eq!(statuses.iter().filter(|s| **s == 201).count(), 1, "{:?}", statuses);
+        assert_eq!(statuses.iter().filter(|s| **s == 403).count(), 3, "{:?}", statuses);
+        assert_eq!(server.auth.user_count().await.unwrap(), 1);
+    }
+}
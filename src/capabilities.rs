@@ -0,0 +1,125 @@
+//! # SQLite Capabilities
+//!
+//! Which optional SQLite features the linked build supports. The answer
+//! depends on compile options, so each feature is probed once when the store
+//! opens (by preparing a cheap statement) and cached on `VibeStore`.
+//!
+//! Features that depend on one of them check it first and either fall back
+//! (inserts without `RETURNING` use `last_insert_rowid`) or fail with a clear
+//! error instead of SQLite's "no such function".
+
+use crate::error::{VibeError, VibeResult};
+
+use serde::Serialize;
+
+/// First SQLite version with `RETURNING` (3.35.0)
+const RETURNING_MIN_VERSION: i32 = 3_035_000;
+
+/// An optional SQLite feature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    Json1,
+    Fts5,
+    MathFunctions,
+    WindowFunctions,
+    Returning,
+    Dbstat,
+}
+
+impl Capability {
+    fn name(&self) -> &'static str {
+        match self {
+            Capability::Json1 => "JSON1",
+            Capability::Fts5 => "FTS5",
+            Capability::MathFunctions => "math functions",
+            Capability::WindowFunctions => "window functions",
+            Capability::Returning => "RETURNING",
+            Capability::Dbstat => "dbstat",
+        }
+    }
+}
+
+/// Features detected in the linked SQLite build
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Capabilities {
+    pub sqlite_version: String,
+    pub json1: bool,
+    pub fts5: bool,
+    pub math_functions: bool,
+    pub window_functions: bool,
+    pub returning: bool,
+    pub dbstat: bool,
+}
+
+impl Capabilities {
+    /// Probes the connection's SQLite build
+    pub fn probe(conn: &rusqlite::Connection) -> Self {
+        let prepares = |sql: &str| conn.prepare(sql).is_ok();
+        // Virtual table modules are only resolved when the table is created
+        let fts5 = conn
+            .execute_batch(
+                "CREATE VIRTUAL TABLE temp.vibe_capability_probe USING fts5(x);
+                 DROP TABLE temp.vibe_capability_probe;",
+            )
+            .is_ok();
+
+        Self {
+            sqlite_version: rusqlite::version().to_string(),
+            json1: prepares("SELECT json('[]')"),
+            fts5,
+            math_functions: prepares("SELECT sqrt(4.0)"),
+            window_functions: prepares("SELECT row_number() OVER () FROM (SELECT 1)"),
+            returning: rusqlite::version_number() >= RETURNING_MIN_VERSION,
+            dbstat: prepares("SELECT 1 FROM dbstat LIMIT 1"),
+        }
+    }
+
+    pub fn has(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Json1 => self.json1,
+            Capability::Fts5 => self.fts5,
+            Capability::MathFunctions => self.math_functions,
+            Capability::WindowFunctions => self.window_functions,
+            Capability::Returning => self.returning,
+            Capability::Dbstat => self.dbstat,
+        }
+    }
+
+    /// Fails with a readable error when a feature is missing
+    pub fn require(&self, capability: Capability, feature: &str) -> VibeResult<()> {
+        if self.has(capability) {
+            return Ok(());
+        }
+        Err(VibeError::InvalidPayload(format!(
+            "{} requires SQLite {}, which this build (SQLite {}) lacks; see GET /v1/capabilities",
+            feature,
+            capability.name(),
+            self.sqlite_version
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_bundled_build() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        let capabilities = Capabilities::probe(&conn);
+
+        // The bundled build ships with these
+        assert!(capabilities.json1);
+        assert!(capabilities.window_functions);
+        assert!(capabilities.returning);
+        assert_eq!(capabilities.sqlite_version, rusqlite::version());
+
+        let missing = Capabilities {
+            json1: false,
+            ..capabilities
+        };
+        let err = missing.require(Capability::Json1, "The [contains] filter").unwrap_err();
+        assert!(err.to_string().contains("/v1/capabilities"));
+        assert!(missing.require(Capability::Returning, "Inserts").is_ok());
+    }
+}
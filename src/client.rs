@@ -0,0 +1,345 @@
+//! # Vibe-Client
+//!
+//! Typed HTTP client for a running VibeDB, behind the `client` feature.
+//! Requests and responses reuse the server's DTOs, so the shapes can't
+//! drift apart.
+//!
+//! ```no_run
+//! # async fn example() -> vibedb::client::ClientResult<()> {
+//! use vibedb::auth::LoginRequest;
+//! use vibedb::client::VibeClient;
+//! use serde_json::{json, Value};
+//!
+//! let mut client = VibeClient::new("http://localhost:3000");
+//! client
+//!     .login(LoginRequest {
+//!         email: "admin@vibedb.dev".to_string(),
+//!         password: "password123".to_string(),
+//!     })
+//!     .await?;
+//! let pushed = client.push("users", &json!({"name": "Alice"})).await?;
+//! let alice: Value = client.get("users", pushed.id).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Error envelopes come back as [`ClientError::Api`] with the server's
+//! status, code, message, retry hints and details.
+
+use crate::api::{BatchPushResponse, PushResponse};
+use crate::auth::{AuthTokens, LoginRequest, SignupRequest, User};
+use crate::storage::{Bucket, CreateBucketRequest, StorageObject};
+
+use reqwest::{Method, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+/// Errors returned by [`VibeClient`]
+#[derive(Error, Debug)]
+pub enum ClientError {
+    /// The request never produced a response, or the body was malformed
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// The server answered with an error envelope
+    ///
+    /// `retry_after` is the server's suggested wait, when it gave one.
+    #[error("{status} {code}: {message}")]
+    Api {
+        status: u16,
+        code: String,
+        message: String,
+        retryable: bool,
+        retry_after: Option<std::time::Duration>,
+        details: Option<Value>,
+    },
+}
+
+pub type ClientResult<T> = Result<T, ClientError>;
+
+/// `{"success": true, "data": ...}`
+#[derive(Deserialize)]
+struct Envelope<T> {
+    data: T,
+}
+
+/// Rows changed by an update or delete
+#[derive(Deserialize)]
+struct Affected {
+    #[serde(default)]
+    affected: u64,
+}
+
+/// Client for one VibeDB server
+#[derive(Debug, Clone)]
+pub struct VibeClient {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+}
+
+impl VibeClient {
+    /// Creates a client for `base_url`, e.g. `http://localhost:3000`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            token: None,
+        }
+    }
+
+    /// Sends `token` as the bearer token on every request
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// The bearer token in use, if any
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self.http.request(method, format!("{}{}", self.base_url, path));
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Fails with the error envelope of a non-2xx response
+    async fn check(response: Response) -> ClientResult<Response> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        let field = |name: &str| {
+            body["error"][name]
+                .as_str()
+                .unwrap_or_default()
+                .to_string()
+        };
+        let error = &body["error"];
+        Err(ClientError::Api {
+            status: status.as_u16(),
+            code: field("code"),
+            message: field("message"),
+            retryable: error["retryable"].as_bool().unwrap_or(false),
+            retry_after: error["retry_after_ms"].as_u64().map(std::time::Duration::from_millis),
+            details: error.get("details").cloned(),
+        })
+    }
+
+    async fn send<T: DeserializeOwned>(request: RequestBuilder) -> ClientResult<T> {
+        let response = Self::check(request.send().await?).await?;
+        Ok(response.json().await?)
+    }
+
+    async fn data<T: DeserializeOwned>(request: RequestBuilder) -> ClientResult<T> {
+        Ok(Self::send::<Envelope<T>>(request).await?.data)
+    }
+
+    // ========================================================================
+    // Data
+    // ========================================================================
+
+    /// POST /v1/push/:collection
+    pub async fn push(&self, collection: &str, document: &impl Serialize) -> ClientResult<PushResponse> {
+        let path = format!("/v1/push/{}", collection);
+        Self::data(self.request(Method::POST, &path).json(document)).await
+    }
+
+    /// POST /v1/push/:collection/batch
+    pub async fn push_batch<D: Serialize>(
+        &self,
+        collection: &str,
+        documents: &[D],
+    ) -> ClientResult<BatchPushResponse> {
+        let path = format!("/v1/push/{}/batch", collection);
+        Self::data(self.request(Method::POST, &path).json(documents)).await
+    }
+
+    /// GET /v1/query/:collection with filters and options such as `limit`
+    pub async fn query<T: DeserializeOwned>(
+        &self,
+        collection: &str,
+        params: &[(&str, &str)],
+    ) -> ClientResult<Vec<T>> {
+        let path = format!("/v1/query/{}", collection);
+        Self::data(self.request(Method::GET, &path).query(params)).await
+    }
+
+    /// GET /v1/query/:collection/:id
+    pub async fn get<T: DeserializeOwned>(&self, collection: &str, id: i64) -> ClientResult<T> {
+        let path = format!("/v1/query/{}/{}", collection, id);
+        Self::data(self.request(Method::GET, &path)).await
+    }
+
+    /// POST /v1/update/:collection/:id, returning the rows changed
+    pub async fn update(&self, collection: &str, id: i64, changes: &impl Serialize) -> ClientResult<u64> {
+        let path = format!("/v1/update/{}/{}", collection, id);
+        let result: Affected = Self::send(self.request(Method::POST, &path).json(changes)).await?;
+        Ok(result.affected)
+    }
+
+    /// POST /v1/delete/:collection/:id, returning the rows removed
+    pub async fn delete(&self, collection: &str, id: i64) -> ClientResult<u64> {
+        let path = format!("/v1/delete/{}/{}", collection, id);
+        let result: Affected = Self::send(self.request(Method::POST, &path)).await?;
+        Ok(result.affected)
+    }
+
+    // ========================================================================
+    // Auth
+    // ========================================================================
+
+    /// POST /v1/auth/signup; the client keeps the new access token
+    pub async fn signup(&mut self, request: SignupRequest) -> ClientResult<AuthTokens> {
+        let tokens: AuthTokens =
+            Self::data(self.request(Method::POST, "/v1/auth/signup").json(&request)).await?;
+        self.token = Some(tokens.access_token.clone());
+        Ok(tokens)
+    }
+
+    /// POST /v1/auth/login; the client keeps the new access token
+    pub async fn login(&mut self, request: LoginRequest) -> ClientResult<AuthTokens> {
+        let tokens: AuthTokens =
+            Self::data(self.request(Method::POST, "/v1/auth/login").json(&request)).await?;
+        self.token = Some(tokens.access_token.clone());
+        Ok(tokens)
+    }
+
+    /// GET /v1/auth/me
+    pub async fn me(&self) -> ClientResult<User> {
+        Self::data(self.request(Method::GET, "/v1/auth/me")).await
+    }
+
+    // ========================================================================
+    // Storage
+    // ========================================================================
+
+    /// POST /v1/storage/buckets
+    pub async fn create_bucket(&self, request: CreateBucketRequest) -> ClientResult<Bucket> {
+        Self::data(self.request(Method::POST, "/v1/storage/buckets").json(&request)).await
+    }
+
+    /// POST /v1/storage/object/:bucket/*path
+    pub async fn upload(
+        &self,
+        bucket: &str,
+        path: &str,
+        data: Vec<u8>,
+        mime_type: &str,
+    ) -> ClientResult<StorageObject> {
+        let file_name = path.rsplit('/').next().unwrap_or(path).to_string();
+        let part = reqwest::multipart::Part::bytes(data)
+            .file_name(file_name)
+            .mime_str(mime_type)?;
+        let form = reqwest::multipart::Form::new().part("file", part);
+        let url = format!("/v1/storage/object/{}/{}", bucket, path);
+        Self::data(self.request(Method::POST, &url).multipart(form)).await
+    }
+
+    /// GET /v1/storage/object/:bucket/*path
+    pub async fn download(&self, bucket: &str, path: &str) -> ClientResult<Vec<u8>> {
+        let url = format!("/v1/storage/object/{}/{}", bucket, path);
+        let response = Self::check(self.request(Method::GET, &url).send().await?).await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// GET /v1/storage/list/:bucket
+    pub async fn list_objects(&self, bucket: &str, prefix: Option<&str>) -> ClientResult<Vec<StorageObject>> {
+        let url = format!("/v1/storage/list/{}", bucket);
+        let mut request = self.request(Method::GET, &url);
+        if let Some(prefix) = prefix {
+            request = request.query(&[("prefix", prefix)]);
+        }
+        Self::data(request).await
+    }
+
+    /// DELETE /v1/storage/object/:bucket/*path
+    pub async fn delete_object(&self, bucket: &str, path: &str) -> ClientResult<()> {
+        let url = format!("/v1/storage/object/{}/{}", bucket, path);
+        Self::check(self.request(Method::DELETE, &url).send().await?).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{json, TestServer, TEST_PASSWORD};
+
+    #[tokio::test]
+    async fn test_client_round_trip() {
+        let server = TestServer::spawn().await;
+        let admin = server.create_user("admin@vibedb.dev", "admin").await;
+        let base_url = format!("http://{}", server.listen().await);
+
+        let mut client = VibeClient::new(&base_url);
+        let tokens = client
+            .signup(SignupRequest {
+                email: "alice@vibedb.dev".to_string(),
+                password: TEST_PASSWORD.to_string(),
+                metadata: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(client.me().await.unwrap().id, tokens.user.id);
+        let mut relogged = VibeClient::new(&base_url);
+        relogged
+            .login(LoginRequest {
+                email: "alice@vibedb.dev".to_string(),
+                password: TEST_PASSWORD.to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(relogged.me().await.unwrap().email, "alice@vibedb.dev");
+
+        let pushed = client.push("notes", &json!({"text": "hi", "n": 1})).await.unwrap();
+        assert_eq!(pushed.columns_added.len(), 2);
+        let batch = client.push_batch("notes", &[json!({"n": 2}), json!({"n": 3})]).await.unwrap();
+        assert_eq!(batch.inserted, 2);
+
+        assert_eq!(client.update("notes", pushed.id, &json!({"text": "bye"})).await.unwrap(), 1);
+        let note: Value = client.get("notes", pushed.id).await.unwrap();
+        assert_eq!(note["text"], "bye");
+        let rows: Vec<Value> = client.query("notes", &[("n", "2")]).await.unwrap();
+        assert_eq!(rows.len(), 1);
+        let rows: Vec<Value> = client.query("notes", &[("order_by", "n"), ("limit", "2")]).await.unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(client.delete("notes", pushed.id).await.unwrap(), 1);
+
+        match client.get::<Value>("notes", pushed.id).await {
+            Err(ClientError::Api { status, code, .. }) => {
+                assert_eq!(status, 404);
+                assert_eq!(code, "TABLE_NOT_FOUND");
+            }
+            other => panic!("expected an API error, got {:?}", other),
+        }
+
+        let admin_client = VibeClient::new(&base_url).with_token(admin.token);
+        let bucket = admin_client
+            .create_bucket(CreateBucketRequest {
+                name: "docs".to_string(),
+                public: false,
+                compression: Default::default(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(bucket.name, "docs");
+        let object = admin_client
+            .upload("docs", "a/readme.txt", b"hello".to_vec(), "text/plain")
+            .await
+            .unwrap();
+        assert_eq!(object.size, 5);
+        assert_eq!(admin_client.download("docs", "a/readme.txt").await.unwrap(), b"hello");
+        assert_eq!(admin_client.list_objects("docs", Some("a/")).await.unwrap().len(), 1);
+        admin_client.delete_object("docs", "a/readme.txt").await.unwrap();
+        assert!(admin_client.list_objects("docs", None).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,61 @@
+//! # Clock
+//!
+//! Where time-dependent services read the time: token and refresh-session
+//! expiry in `AuthService`, signed URL expiry in `StorageService`, snapshot
+//! TTLs and the dead-letter retention sweep, row lock expiry, and the
+//! schedule and backoff of pull sources. They all hold a [`SharedClock`],
+//! [`SystemClock`] unless one is injected, so tests can swap in
+//! `testing::ManualClock` and advance time instead of sleeping.
+//!
+//! A clock has two faces: wall-clock time for timestamps that are stored
+//! or sent to clients, and monotonic time for in-memory deadlines.
+
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Source of wall-clock and monotonic time
+pub trait Clock: Send + Sync + 'static {
+    /// The current wall-clock time
+    fn now(&self) -> DateTime<Utc>;
+
+    /// The current monotonic time, for deadlines
+    fn instant(&self) -> Instant;
+
+    /// Resolves once [`Clock::instant`] reaches `deadline`
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<'static, ()>;
+
+    /// Seconds since the Unix epoch
+    fn unix_secs(&self) -> u64 {
+        self.now().timestamp().max(0) as u64
+    }
+}
+
+/// A clock shared by the services of one server
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The operating system's clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    /// A shared system clock
+    pub fn shared() -> SharedClock {
+        Arc::new(SystemClock)
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep_until(deadline.into()))
+    }
+}
@@ -0,0 +1,424 @@
+//! # Configuration Module
+//!
+//! Runtime settings shared by the API layer. Every field has a default that
+//! matches the out-of-the-box behaviour, so `VibeConfig::default()` is always
+//! a valid configuration; `main.rs` overrides individual fields from CLI flags
+//! and environment variables.
+
+use crate::explorer::ExplorerMode;
+use crate::guard::{KeyCase, DEFAULT_MAX_IDENTIFIER_LENGTH, DEFAULT_MAX_NESTED_BYTES, DEFAULT_MAX_NESTING_DEPTH};
+use crate::sql_policy::{DEFAULT_BLOCKLIST, DEFAULT_PRAGMA_ALLOWLIST};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Top-level configuration for a VibeDB instance
+#[derive(Debug, Clone, Default)]
+pub struct VibeConfig {
+    /// Raw SQL endpoint settings
+    pub sql: SqlConfig,
+    /// Collection query settings
+    pub query: QueryConfig,
+    /// Rejected push settings
+    pub dead_letters: DeadLetterConfig,
+    /// Limits for the names of created tables and columns
+    pub identifiers: IdentifierConfig,
+    /// Limits for nested values in written documents
+    pub payload: PayloadConfig,
+    /// When a request's schema migrations are logged as slow
+    pub migrations: MigrationConfig,
+    /// Where `/explore` is served from
+    pub explorer: ExplorerMode,
+    /// Path prefix of every route, behind a reverse proxy
+    pub base_path: BasePathConfig,
+    /// Coalescing of single-document pushes
+    pub batching: BatchConfig,
+    /// Loading of collection schemas ahead of first use
+    pub schema_cache: SchemaCacheConfig,
+    /// Read transactions shared by several requests
+    pub snapshots: SnapshotConfig,
+    /// Cross-origin access for browser clients
+    pub cors: CorsConfig,
+    /// Requests handled at once, overall and per route class
+    pub concurrency: ConcurrencyConfig,
+}
+
+/// Settings for the raw SQL endpoints (`/v1/sql/*`)
+#[derive(Debug, Clone)]
+pub struct SqlConfig {
+    /// Statement patterns rejected before execution (see `sql_policy`)
+    pub blocklist: Vec<String>,
+    /// Pragmas admins may change through the raw SQL endpoints
+    pub pragma_allowlist: Vec<String>,
+    /// Maximum rows returned by `/v1/sql/query`
+    pub max_rows: usize,
+    /// Reject over-limit queries instead of truncating them
+    pub strict_row_limit: bool,
+    /// Interrupt `/v1/sql/query` statements running longer than this
+    pub statement_timeout: Option<Duration>,
+    /// Restrict `/v1/sql/execute` to admins; others go through proposals
+    pub approval_required: bool,
+}
+
+impl Default for SqlConfig {
+    fn default() -> Self {
+        Self {
+            blocklist: DEFAULT_BLOCKLIST.iter().map(|s| s.to_string()).collect(),
+            pragma_allowlist: DEFAULT_PRAGMA_ALLOWLIST.iter().map(|s| s.to_string()).collect(),
+            max_rows: 10_000,
+            strict_row_limit: false,
+            statement_timeout: None,
+            approval_required: false,
+        }
+    }
+}
+
+/// Settings for the collection query endpoints (`/v1/query/*`)
+#[derive(Debug, Clone)]
+pub struct QueryConfig {
+    /// Rows buffered between SQLite and the client when streaming NDJSON
+    pub stream_buffer_rows: usize,
+    /// Rows kept by the get-by-id cache (see `row_cache`); 0 turns it off
+    pub row_cache_size: usize,
+    /// Serve table row counts from maintained counters (see `row_counts`)
+    pub row_count_cache: bool,
+}
+
+impl Default for QueryConfig {
+    fn default() -> Self {
+        Self {
+            stream_buffer_rows: 256,
+            row_cache_size: 0,
+            row_count_cache: false,
+        }
+    }
+}
+
+/// Settings for the schema cache of `SchemaGuard`
+#[derive(Debug, Clone, Default)]
+pub struct SchemaCacheConfig {
+    /// Load every collection's schema at startup instead of on first use
+    pub warm_up: bool,
+}
+
+/// Settings for snapshot sessions (see `snapshots`)
+#[derive(Debug, Clone)]
+pub struct SnapshotConfig {
+    /// Snapshots open at once at most; each holds a connection and keeps
+    /// the WAL from being checkpointed past its start
+    pub max_open: usize,
+    /// Lifetime of a snapshot opened without a `ttl_secs`
+    pub default_ttl: Duration,
+    /// Longest lifetime a snapshot may ask for
+    pub max_ttl: Duration,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            max_open: 4,
+            default_ttl: Duration::from_secs(60),
+            max_ttl: Duration::from_secs(15 * 60),
+        }
+    }
+}
+
+/// Requests handled at once (see `limits`)
+///
+/// Every request takes a permit of its class and one of `global`; one that
+/// can't get both within `acquire_timeout` is refused with 503.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConcurrencyConfig {
+    /// Requests of any class in flight at most
+    pub global: usize,
+    /// Writes (POST, PUT, PATCH and DELETE outside storage uploads) at most
+    pub writes: usize,
+    /// Reads (GET and HEAD) at most
+    pub reads: usize,
+    /// Storage uploads at most
+    pub uploads: usize,
+    /// How long a request waits for its permits
+    pub acquire_timeout: Duration,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            global: 1024,
+            writes: 256,
+            reads: 512,
+            uploads: 32,
+            acquire_timeout: Duration::from_millis(100),
+        }
+    }
+}
+
+impl ConcurrencyConfig {
+    /// Rejects limits of zero, which would refuse every request
+    pub fn validate(&self) -> Result<(), String> {
+        if [self.global, self.writes, self.reads, self.uploads].contains(&0) {
+            return Err("concurrency limits must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Bucket targeted by uploads that name none (`POST /v1/storage/upload/*path`)
+#[derive(Debug, Clone)]
+pub struct DefaultBucketConfig {
+    pub name: String,
+    /// Visibility when the bucket is created; an existing bucket keeps its own
+    pub public: bool,
+}
+
+/// Caps on stored file bytes (see `storage`), checked on upload
+#[derive(Debug, Clone, Default)]
+pub struct StorageQuotaConfig {
+    /// Bytes each user's objects may take up together
+    pub per_user: Option<u64>,
+    /// Bytes all objects may take up together
+    pub global: Option<u64>,
+}
+
+impl StorageQuotaConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.per_user.is_some() || self.global.is_some()
+    }
+}
+
+/// Settings for dead-lettered pushes (see `dead_letters`)
+#[derive(Debug, Clone)]
+pub struct DeadLetterConfig {
+    /// Dead letters older than this are purged by the sweeper
+    pub retention: Duration,
+}
+
+impl Default for DeadLetterConfig {
+    fn default() -> Self {
+        Self {
+            retention: Duration::from_secs(30 * 24 * 3600),
+        }
+    }
+}
+
+/// Settings for write batching (see `batching`)
+#[derive(Debug, Clone)]
+pub struct BatchConfig {
+    /// Batch pushes to every collection, not only those whose settings
+    /// ask for it
+    pub enabled: bool,
+    /// How long a batch collects pushes after its first one
+    pub window: Duration,
+    /// Pushes committed in one batch at most
+    pub max_writes: usize,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window: Duration::from_millis(5),
+            max_writes: 64,
+        }
+    }
+}
+
+/// Cross-origin (CORS) settings, applied by `api::cors_layer`
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+    /// Origins allowed to call the API, e.g. `https://app.example.com`;
+    /// empty (or `*`) allows any origin
+    pub allowed_origins: Vec<String>,
+    /// Send `Access-Control-Allow-Credentials: true`, so browsers include
+    /// cookies and `Authorization` headers
+    pub allow_credentials: bool,
+    /// How long browsers may cache a preflight response
+    pub max_age: Option<Duration>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allow_credentials: false,
+            max_age: Some(Duration::from_secs(600)),
+        }
+    }
+}
+
+impl CorsConfig {
+    /// Whether every origin is allowed
+    pub fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.is_empty() || self.allowed_origins.iter().any(|origin| origin == "*")
+    }
+
+    /// Rejects settings browsers would refuse: credentials with a wildcard
+    /// origin, and origins that are not `scheme://host[:port]`
+    pub fn validate(&self) -> Result<(), String> {
+        if self.allow_credentials && self.allows_any_origin() {
+            return Err("credentials cannot be allowed for every origin; list the allowed origins".to_string());
+        }
+        for origin in self.allowed_origins.iter().filter(|origin| *origin != "*") {
+            let host = origin
+                .strip_prefix("https://")
+                .or_else(|| origin.strip_prefix("http://"))
+                .ok_or_else(|| format!("origin {} must start with http:// or https://", origin))?;
+            if host.is_empty() || host.contains('/') || !origin.is_ascii() {
+                return Err(format!("origin {} must be scheme://host[:port] without a path", origin));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Path prefix the server is reached under, for reverse proxies that serve
+/// it at e.g. `https://example.com/vibedb/`
+///
+/// A configured `prefix` moves every route under it (see
+/// `api::nest_under_base_path`). A proxy that strips the prefix instead can
+/// name it in `X-Forwarded-Prefix`, which is believed with `trust_forwarded`.
+/// Either way the prefix ends up in the explorer's API base and the URLs the
+/// root endpoint advertises.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BasePathConfig {
+    /// Empty, or `/`-led without a trailing `/` (see `normalize`)
+    pub prefix: String,
+    /// Believe `X-Forwarded-Prefix` over `prefix`
+    pub trust_forwarded: bool,
+}
+
+impl BasePathConfig {
+    /// Normalizes `vibedb`, `/vibedb/` and `/vibedb` to `/vibedb`, and `/`
+    /// to the empty prefix
+    ///
+    /// Segments may hold ASCII letters, digits and `-._~`, since the prefix
+    /// is written into HTML and URLs unescaped.
+    pub fn normalize(path: &str) -> Result<String, String> {
+        let trimmed = path.trim().trim_matches('/');
+        if trimmed.is_empty() {
+            return Ok(String::new());
+        }
+        for segment in trimmed.split('/') {
+            let valid = !segment.is_empty()
+                && segment != "."
+                && segment != ".."
+                && segment.chars().all(|c| c.is_ascii_alphanumeric() || "-._~".contains(c));
+            if !valid {
+                return Err(format!(
+                    "base path {} may only hold letters, digits and -._~ between single slashes",
+                    path
+                ));
+            }
+        }
+        Ok(format!("/{}", trimmed))
+    }
+
+    /// Prefix a client reached the server under, given the request's
+    /// `X-Forwarded-Prefix`; an invalid forwarded prefix is ignored
+    pub fn public_prefix(&self, forwarded: Option<&str>) -> String {
+        forwarded
+            .filter(|_| self.trust_forwarded)
+            .and_then(|prefix| Self::normalize(prefix).ok())
+            .unwrap_or_else(|| self.prefix.clone())
+    }
+}
+
+/// Length limit and key naming for the tables and columns VibeDB creates
+#[derive(Debug, Clone)]
+pub struct IdentifierConfig {
+    /// Longest table or column name (at most `guard::MAX_IDENTIFIER_LENGTH`)
+    pub max_length: usize,
+    /// Shorten over-long names and keep them as aliases instead of
+    /// rejecting the write
+    pub truncate: bool,
+    /// Convention payload keys are converted to before columns are created
+    pub key_case: KeyCase,
+}
+
+impl Default for IdentifierConfig {
+    fn default() -> Self {
+        Self {
+            max_length: DEFAULT_MAX_IDENTIFIER_LENGTH,
+            truncate: false,
+            key_case: KeyCase::Preserve,
+        }
+    }
+}
+
+/// Limits for nested objects and arrays in written documents, which are
+/// stored as JSON text
+#[derive(Debug, Clone)]
+pub struct PayloadConfig {
+    /// Deepest nesting of a document, counting the document itself as 1
+    pub max_depth: usize,
+    /// Largest serialized size of a nested value, in bytes
+    pub max_nested_bytes: usize,
+    /// Reject numbers that can't be stored exactly (see
+    /// `inference::InexactNumber`) instead of storing them with a warning
+    pub strict_numbers: bool,
+}
+
+impl Default for PayloadConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_NESTING_DEPTH,
+            max_nested_bytes: DEFAULT_MAX_NESTED_BYTES,
+            strict_numbers: false,
+        }
+    }
+}
+
+/// Thresholds past which the migrations of one write request are logged
+/// at warn level (see `guard::tally_migrations`)
+#[derive(Debug, Clone)]
+pub struct MigrationConfig {
+    /// Time spent migrating
+    pub slow_after: Duration,
+    /// `CREATE TABLE` and `ALTER TABLE` statements run
+    pub slow_ddl_statements: u64,
+}
+
+impl Default for MigrationConfig {
+    fn default() -> Self {
+        Self {
+            slow_after: Duration::from_millis(100),
+            slow_ddl_statements: 10,
+        }
+    }
+}
+
+/// First-start provisioning applied by `bootstrap::run_bootstrap`
+///
+/// Kept out of `VibeConfig` so the admin password is not carried around in
+/// the shared application state.
+#[derive(Clone, Default)]
+pub struct BootstrapConfig {
+    /// Email of the admin created when no users exist
+    pub admin_email: Option<String>,
+    /// Password for the bootstrap admin
+    pub admin_password: Option<String>,
+    /// Buckets created if absent
+    pub buckets: Vec<BucketSeed>,
+}
+
+/// A bucket to create during bootstrap
+#[derive(Debug, Clone, PartialEq)]
+pub struct BucketSeed {
+    pub name: String,
+    pub public: bool,
+}
+
+/// What `preflight::run_preflight` checks before the server starts
+///
+/// Kept out of `VibeConfig` like `BootstrapConfig`, since it holds the JWT
+/// secret.
+#[derive(Clone, Default)]
+pub struct PreflightConfig {
+    /// Database file; `None` for an in-memory database
+    pub db_path: Option<PathBuf>,
+    /// Create a missing database directory instead of failing
+    pub create_db_dir: bool,
+    /// Directory of stored files
+    pub storage_path: PathBuf,
+    /// JWT secret set by the operator; a generated one is always long enough
+    pub jwt_secret: Option<Vec<u8>>,
+}
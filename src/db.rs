@@ -3,20 +3,381 @@
 //! Manages the persistent .db file using WAL mode for concurrent high-throughput.
 //! This module handles database initialization, connection management, and provides
 //! utilities for executing queries safely.
+//!
+//! ## Watchdog
+//!
+//! A connection whose file was deleted, or whose (network) volume went away,
+//! fails every call with `SQLITE_IOERR`/`SQLITE_READONLY`/`SQLITE_NOTADB`
+//! and never recovers by itself. (Under WAL, writes to a deleted file may
+//! keep succeeding; nothing can be noticed until SQLite reports an error.) When such faults repeat (see
+//! `ReopenPolicy`), the store opens the path again, re-runs the pragmas and
+//! swaps the new connection in; calls already running finish on the old
+//! one. Reopens are serialized, so concurrent failures reopen once. The
+//! store registers no SQL functions and attaches no databases, so the
+//! pragmas are all there is to restore. A failed reopen marks the store
+//! unhealthy (see `VibeStore::health`) until one succeeds.
 
+use crate::capabilities::Capabilities;
 use crate::error::{VibeError, VibeResult};
+use crate::inference::SqliteType;
+use dashmap::DashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 use tokio_rusqlite::Connection;
-use rusqlite::TransactionBehavior;
-use tracing::{debug, info};
+use rusqlite::{OptionalExtension, TransactionBehavior};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
 
 /// Row data returned from queries
 pub type RowData = Vec<(String, rusqlite::types::Value)>;
 
+/// Row as column names and JSON values
+type JsonRow = Vec<(String, serde_json::Value)>;
+
+/// How long a snapshot reader waits on a locked database before failing
+const SNAPSHOT_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// SQLite virtual machine steps between deadline checks of `query_limited`
+const QUERY_PROGRESS_OPS: std::os::raw::c_int = 1000;
+
+/// An item of `VibeStore::read_snapshot`
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnapshotItem {
+    /// Sent first: the schema version the snapshot was taken at and the
+    /// tables it covers, in the order they follow
+    Begin { schema_version: i64, tables: Vec<String> },
+    /// A row of `table`
+    Row {
+        table: String,
+        row: Vec<(String, serde_json::Value)>,
+    },
+}
+
+/// How `VibeStore::read_snapshot` reads the rows of each table
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotShape {
+    /// Columns read as `CAST(column AS type)` in whichever tables have them
+    pub casts: HashMap<String, SqliteType>,
+    /// SELECT list and its parameters replacing `*` for a table; casts
+    /// don't apply to such tables
+    pub projections: HashMap<String, (String, Vec<SqlValue>)>,
+}
+
+/// Retry policy for writes that hit `SQLITE_BUSY` / `SQLITE_LOCKED`
+///
+/// Applied on top of SQLite's own `busy_timeout`. Attempt `n` waits
+/// `base_delay * 2^(n-1)` before trying again; after `max_attempts` the
+/// original error is returned.
+#[derive(Debug, Clone, Copy)]
+pub struct BusyRetry {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+}
+
+impl Default for BusyRetry {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(10),
+        }
+    }
+}
+
+impl BusyRetry {
+    /// Runs `op`, retrying it while it fails with a busy/locked code
+    ///
+    /// Runs on the connection thread, so the backoff sleeps block only that
+    /// thread (which could not make progress anyway). Every retry is added
+    /// to `tally`.
+    fn run<T>(&self, tally: &RetryTally, mut op: impl FnMut() -> rusqlite::Result<T>) -> rusqlite::Result<T> {
+        let mut attempt = 1;
+        loop {
+            match op() {
+                Err(e) if is_busy(&e) && attempt < self.max_attempts => {
+                    let delay = self.base_delay * 2u32.saturating_pow(attempt - 1);
+                    debug!("Database busy (attempt {}), retrying in {:?}", attempt, delay);
+                    tally.add();
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Where a write's busy retries are counted: the label given to the
+/// `*_labeled` writes, or nowhere
+struct RetryTally {
+    retries: Arc<DashMap<String, u64>>,
+    label: Option<String>,
+}
+
+impl RetryTally {
+    fn add(&self) {
+        if let Some(label) = &self.label {
+            *self.retries.entry(label.clone()).or_insert(0) += 1;
+        }
+    }
+}
+
+/// Whether an error is a transient lock conflict
+fn is_busy(err: &rusqlite::Error) -> bool {
+    matches!(
+        err.sqlite_error_code(),
+        Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+    )
+}
+
+/// Error classes the connection does not recover from by itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fault {
+    /// `SQLITE_IOERR`: the file or its volume is gone
+    IoError,
+    /// `SQLITE_NOTADB`: the path no longer holds a database
+    NotADatabase,
+    /// `SQLITE_READONLY` on a store opened for writing, e.g. after the
+    /// file was deleted or moved
+    ReadOnly,
+}
+
+impl Fault {
+    /// The fault class of an error, if any
+    pub fn classify(err: &VibeError) -> Option<Self> {
+        match err {
+            VibeError::Sqlite { code, .. } => match code {
+                rusqlite::ErrorCode::SystemIoFailure => Some(Fault::IoError),
+                rusqlite::ErrorCode::NotADatabase => Some(Fault::NotADatabase),
+                rusqlite::ErrorCode::ReadOnly => Some(Fault::ReadOnly),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// When the watchdog reopens the connection: after `fault_threshold`
+/// faults with at most `window` between one and the next
+///
+/// Successful calls don't reset the count, since reads of a deleted file
+/// keep working while every write fails.
+#[derive(Debug, Clone, Copy)]
+pub struct ReopenPolicy {
+    pub fault_threshold: u32,
+    pub window: Duration,
+}
+
+impl Default for ReopenPolicy {
+    fn default() -> Self {
+        Self {
+            fault_threshold: 3,
+            window: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Fault tracking and reopen bookkeeping of a store
+#[derive(Default)]
+struct Watchdog {
+    policy: ReopenPolicy,
+    /// Faults counted towards the next reopen, and when the last one was
+    streak: Mutex<(u32, Option<Instant>)>,
+    /// Held while reopening
+    reopen_lock: tokio::sync::Mutex<()>,
+    /// Successful reopens; a fault seen before one doesn't trigger another
+    generation: AtomicU64,
+    faults: AtomicU64,
+    attempts: AtomicU64,
+    failures: AtomicU64,
+    /// The last reopen failed, so the connection is still broken
+    failed: std::sync::atomic::AtomicBool,
+    last_fault: Mutex<Option<String>>,
+}
+
+impl Watchdog {
+    /// Counts a fault; true once the streak reaches the threshold
+    fn record(&self, err: &VibeError) -> bool {
+        self.faults.fetch_add(1, Ordering::Relaxed);
+        *self.last_fault.lock().unwrap_or_else(|e| e.into_inner()) = Some(err.to_string());
+        let mut streak = self.streak.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let continues = streak.1.is_some_and(|last| now.duration_since(last) <= self.policy.window);
+        *streak = (if continues { streak.0 + 1 } else { 1 }, Some(now));
+        streak.0 >= self.policy.fault_threshold
+    }
+
+    fn reset(&self) {
+        *self.streak.lock().unwrap_or_else(|e| e.into_inner()) = (0, None);
+    }
+}
+
+/// Watchdog state of a store, for health checks and metrics
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreHealth {
+    /// False after a failed reopen, until one succeeds
+    pub healthy: bool,
+    /// Unrecoverable errors seen (see `Fault`)
+    pub faults: u64,
+    pub reopen_attempts: u64,
+    pub reopen_failures: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_fault: Option<String>,
+}
+
+/// `PRAGMA synchronous` levels, by value
+const SYNCHRONOUS_LEVELS: [&str; 4] = ["OFF", "NORMAL", "FULL", "EXTRA"];
+
+/// Largest `cache_size` either way: pages when positive, KiB when negative
+const MAX_CACHE_SIZE: i64 = 1024 * 1024;
+
+/// Largest `busy_timeout` in milliseconds
+const MAX_BUSY_TIMEOUT_MS: u64 = 60_000;
+
+/// Largest `wal_autocheckpoint` in pages
+const MAX_WAL_AUTOCHECKPOINT: u64 = 1_000_000;
+
+/// Pragmas of the main connection, for `GET /v1/admin/pragmas`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Pragmas {
+    pub journal_mode: String,
+    pub synchronous: String,
+    /// Pages when positive, KiB when negative
+    pub cache_size: i64,
+    pub wal_autocheckpoint: i64,
+    pub busy_timeout: i64,
+    pub page_count: i64,
+}
+
+/// Pragmas to change at runtime; omitted ones are kept
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PragmaUpdate {
+    pub cache_size: Option<i64>,
+    /// `OFF`, `NORMAL`, `FULL` or `EXTRA`
+    pub synchronous: Option<String>,
+    pub busy_timeout: Option<u64>,
+    pub wal_autocheckpoint: Option<u64>,
+    /// Always refused: the journal mode is set when the store opens
+    pub journal_mode: Option<String>,
+}
+
+impl PragmaUpdate {
+    /// Checks the values before any is applied
+    pub fn validate(&self) -> Result<(), String> {
+        if self.journal_mode.is_some() {
+            return Err("journal_mode is set when the database opens and can't be changed at runtime".to_string());
+        }
+        if let Some(size) = self.cache_size {
+            if size == 0 || size.abs() > MAX_CACHE_SIZE {
+                return Err(format!("cache_size must be non-zero and at most {} either way", MAX_CACHE_SIZE));
+            }
+        }
+        if let Some(level) = &self.synchronous {
+            if !SYNCHRONOUS_LEVELS.contains(&level.to_ascii_uppercase().as_str()) {
+                return Err(format!("synchronous must be one of {}", SYNCHRONOUS_LEVELS.join(", ")));
+            }
+        }
+        if self.busy_timeout.is_some_and(|ms| ms > MAX_BUSY_TIMEOUT_MS) {
+            return Err(format!("busy_timeout must be at most {}ms", MAX_BUSY_TIMEOUT_MS));
+        }
+        if self.wal_autocheckpoint.is_some_and(|pages| pages > MAX_WAL_AUTOCHECKPOINT) {
+            return Err(format!("wal_autocheckpoint must be at most {} pages", MAX_WAL_AUTOCHECKPOINT));
+        }
+        Ok(())
+    }
+
+    /// Adds the values set in `other`, replacing earlier ones
+    fn merge(&mut self, other: &PragmaUpdate) {
+        self.cache_size = other.cache_size.or(self.cache_size);
+        self.synchronous = other.synchronous.clone().or(self.synchronous.take());
+        self.busy_timeout = other.busy_timeout.or(self.busy_timeout);
+        self.wal_autocheckpoint = other.wal_autocheckpoint.or(self.wal_autocheckpoint);
+    }
+
+    /// `PRAGMA` statements of a validated update
+    fn statements(&self) -> String {
+        let mut sql = String::new();
+        if let Some(size) = self.cache_size {
+            sql.push_str(&format!("PRAGMA cache_size = {};", size));
+        }
+        if let Some(level) = &self.synchronous {
+            sql.push_str(&format!("PRAGMA synchronous = {};", level.to_ascii_uppercase()));
+        }
+        if let Some(ms) = self.busy_timeout {
+            sql.push_str(&format!("PRAGMA busy_timeout = {};", ms));
+        }
+        if let Some(pages) = self.wal_autocheckpoint {
+            sql.push_str(&format!("PRAGMA wal_autocheckpoint = {};", pages));
+        }
+        sql
+    }
+}
+
+/// Mode of `VibeStore::wal_checkpoint`, as in `PRAGMA wal_checkpoint(MODE)`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckpointMode {
+    /// Copies what it can without waiting for readers or writers
+    #[default]
+    Passive,
+    /// Waits for writers, then copies the whole WAL
+    Full,
+    /// `Full`, then waits for readers so the WAL starts over
+    Restart,
+    /// `Restart`, then truncates the `-wal` file to zero bytes
+    Truncate,
+}
+
+impl CheckpointMode {
+    fn as_sql(self) -> &'static str {
+        match self {
+            CheckpointMode::Passive => "PASSIVE",
+            CheckpointMode::Full => "FULL",
+            CheckpointMode::Restart => "RESTART",
+            CheckpointMode::Truncate => "TRUNCATE",
+        }
+    }
+}
+
+/// What a WAL checkpoint reports
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Checkpoint {
+    pub mode: CheckpointMode,
+    /// Whether readers or writers kept the checkpoint from completing
+    pub busy: bool,
+    /// Pages in the WAL
+    pub log_pages: i64,
+    /// Pages of the WAL copied into the database
+    pub checkpointed_pages: i64,
+}
+
 /// The Vibe-Store: manages database connections and provides query utilities
 pub struct VibeStore {
-    conn: Connection,
+    /// Replaced by the watchdog on reopen
+    conn: RwLock<Connection>,
     path: String,
+    /// Shared-cache URI of an in-memory database, through which streaming
+    /// reads get a connection of their own
+    memory_uri: Option<String>,
+    busy_retry: BusyRetry,
+    watchdog: Watchdog,
+    /// Busy retries per write label
+    retries: Arc<DashMap<String, u64>>,
+    /// Write transactions committed so far
+    transactions: AtomicU64,
+    /// Rows stepped by `query_streaming` so far
+    streamed: Arc<AtomicU64>,
+    capabilities: Capabilities,
+    /// Read-only connection of `probe`, opened on first use and dropped on
+    /// reopen, since it may point at a file that is gone
+    probe_conn: tokio::sync::Mutex<Option<Connection>>,
+    /// Pragmas set through `set_pragmas`, run again after a reopen
+    tuning: Mutex<PragmaUpdate>,
 }
 
 impl VibeStore {
@@ -33,16 +394,26 @@ impl VibeStore {
 
         let conn = Connection::open(&path_str)
             .await
-            .map_err(|e| VibeError::Database(format!("Failed to open database: {}", e)))?;
+            .map_err(|e| VibeError::from_connection("Failed to open database", e))?;
 
         // Initialize with production-ready pragmas
         Self::initialize_pragmas(&conn).await?;
+        let capabilities = Self::probe_capabilities(&conn).await?;
 
         info!("✨ VibeDB initialized successfully with WAL mode");
 
         Ok(Self {
-            conn,
+            conn: RwLock::new(conn),
             path: path_str,
+            memory_uri: None,
+            busy_retry: BusyRetry::default(),
+            watchdog: Watchdog::default(),
+            retries: Arc::new(DashMap::new()),
+            transactions: AtomicU64::new(0),
+            streamed: Arc::new(AtomicU64::new(0)),
+            capabilities,
+            probe_conn: tokio::sync::Mutex::new(None),
+            tuning: Mutex::new(PragmaUpdate::default()),
         })
     }
 
@@ -50,18 +421,101 @@ impl VibeStore {
     pub async fn in_memory() -> VibeResult<Self> {
         info!("Initializing in-memory VibeDB");
 
-        let conn = Connection::open_in_memory()
-            .await
-            .map_err(|e| VibeError::Database(format!("Failed to create database: {}", e)))?;
+        let memory_uri = format!("file:vibedb-{}?mode=memory&cache=shared", uuid::Uuid::new_v4());
+        let conn = Connection::open_with_flags(
+            &memory_uri,
+            rusqlite::OpenFlags::SQLITE_OPEN_READ_WRITE
+                | rusqlite::OpenFlags::SQLITE_OPEN_CREATE
+                | rusqlite::OpenFlags::SQLITE_OPEN_URI
+                | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .await
+        .map_err(|e| VibeError::from_connection("Failed to create database", e))?;
 
         Self::initialize_pragmas(&conn).await?;
+        let capabilities = Self::probe_capabilities(&conn).await?;
 
         Ok(Self {
-            conn,
+            conn: RwLock::new(conn),
             path: ":memory:".to_string(),
+            memory_uri: Some(memory_uri),
+            busy_retry: BusyRetry::default(),
+            watchdog: Watchdog::default(),
+            retries: Arc::new(DashMap::new()),
+            transactions: AtomicU64::new(0),
+            streamed: Arc::new(AtomicU64::new(0)),
+            capabilities,
+            probe_conn: tokio::sync::Mutex::new(None),
+            tuning: Mutex::new(PragmaUpdate::default()),
         })
     }
 
+    /// Detects optional SQLite features once per store
+    async fn probe_capabilities(conn: &Connection) -> VibeResult<Capabilities> {
+        let capabilities = conn
+            .call(|conn| Ok(Capabilities::probe(conn)))
+            .await
+            .map_err(|e| VibeError::from_connection("Failed to probe capabilities", e))?;
+        debug!("SQLite capabilities: {:?}", capabilities);
+        Ok(capabilities)
+    }
+
+    /// Optional SQLite features available to this store
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    /// Overrides the detected capabilities (e.g. to exercise fallbacks)
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Sets the retry policy for busy/locked writes
+    pub fn with_busy_retry(mut self, busy_retry: BusyRetry) -> Self {
+        self.busy_retry = busy_retry;
+        self
+    }
+
+    /// Sets when the connection is reopened after unrecoverable errors
+    pub fn with_reopen_policy(mut self, policy: ReopenPolicy) -> Self {
+        self.watchdog.policy = policy;
+        self
+    }
+
+    /// Busy retries of the writes labeled `label`
+    pub fn busy_retries(&self, label: &str) -> u64 {
+        self.retries.get(label).map(|r| *r).unwrap_or(0)
+    }
+
+    /// Write transactions committed through `execute*`,
+    /// `insert_returning_id*` and `with_transaction*`
+    ///
+    /// A single statement outside a transaction counts as one.
+    pub fn write_transactions(&self) -> u64 {
+        self.transactions.load(Ordering::Relaxed)
+    }
+
+    /// Rows read from SQLite by `query_streaming`, whether or not the
+    /// receiver has taken them yet
+    pub fn streamed_rows(&self) -> u64 {
+        self.streamed.load(Ordering::Relaxed)
+    }
+
+    fn count_transaction<T>(&self, result: VibeResult<T>) -> VibeResult<T> {
+        if result.is_ok() {
+            self.transactions.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    fn tally(&self, label: Option<&str>) -> RetryTally {
+        RetryTally {
+            retries: Arc::clone(&self.retries),
+            label: label.map(str::to_string),
+        }
+    }
+
     /// Initialize database with production-ready pragmas
     ///
     /// As per the specification:
@@ -80,52 +534,293 @@ impl VibeStore {
             Ok(())
         })
         .await
-        .map_err(|e| VibeError::Database(format!("Failed to set pragmas: {}", e)))?;
+        .map_err(|e| VibeError::from_connection("Failed to set pragmas", e))?;
 
         debug!("Database pragmas configured successfully");
         Ok(())
     }
 
     /// Get the connection
-    pub fn conn(&self) -> &Connection {
-        &self.conn
+    ///
+    /// After a reopen this is a new connection; callers should not keep it.
+    pub fn conn(&self) -> Connection {
+        self.conn.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Current pragmas of the main connection
+    pub async fn pragmas(&self) -> VibeResult<Pragmas> {
+        let result = self
+            .conn()
+            .call(|conn| {
+                let int = |pragma: &str| conn.query_row(&format!("PRAGMA {}", pragma), [], |row| row.get::<_, i64>(0));
+                let synchronous = int("synchronous")?;
+                Ok(Pragmas {
+                    journal_mode: conn.query_row("PRAGMA journal_mode", [], |row| row.get(0))?,
+                    synchronous: SYNCHRONOUS_LEVELS
+                        .get(synchronous as usize)
+                        .map_or_else(|| synchronous.to_string(), |level| level.to_string()),
+                    cache_size: int("cache_size")?,
+                    wal_autocheckpoint: int("wal_autocheckpoint")?,
+                    busy_timeout: int("busy_timeout")?,
+                    page_count: int("page_count")?,
+                })
+            })
+            .await
+            .map_err(|e| VibeError::from_connection("Failed to read pragmas", e));
+        self.watch(result).await
+    }
+
+    /// Applies `update` to the main connection, returning the pragmas after
+    ///
+    /// The values last until the server restarts and survive a reopen.
+    pub async fn set_pragmas(&self, update: PragmaUpdate) -> VibeResult<Pragmas> {
+        update.validate().map_err(VibeError::InvalidPayload)?;
+        let sql = update.statements();
+        let result = self
+            .conn()
+            .call(move |conn| Ok(conn.execute_batch(&sql)?))
+            .await
+            .map_err(|e| VibeError::from_connection("Failed to set pragmas", e));
+        self.watch(result).await?;
+        self.tuning.lock().unwrap_or_else(|e| e.into_inner()).merge(&update);
+        self.pragmas().await
+    }
+
+    /// Checkpoints the WAL into the database file on the main connection
+    ///
+    /// A `busy` result is not an error: the checkpoint did what it could,
+    /// and `Restart` and `Truncate` leave the WAL as it was. In-memory
+    /// databases have no WAL.
+    pub async fn wal_checkpoint(&self, mode: CheckpointMode) -> VibeResult<Checkpoint> {
+        if self.is_in_memory() {
+            return Err(VibeError::Conflict(
+                "Checkpoints need a file database".to_string(),
+            ));
+        }
+        let sql = format!("PRAGMA wal_checkpoint({})", mode.as_sql());
+        let result = self
+            .conn()
+            .call(move |conn| {
+                Ok(conn.query_row(&sql, [], |row| {
+                    Ok(Checkpoint {
+                        mode,
+                        busy: row.get::<_, i64>(0)? != 0,
+                        log_pages: row.get(1)?,
+                        checkpointed_pages: row.get(2)?,
+                    })
+                })?)
+            })
+            .await
+            .map_err(|e| VibeError::from_connection("Checkpoint failed", e));
+        self.watch(result).await
+    }
+
+    /// Columns of `table` that lead an index, including its primary key
+    ///
+    /// Only an index's first column counts: SQLite can't use `(a, b)` for a
+    /// lookup on `b` alone. Expression indexes are left out.
+    pub async fn indexed_columns(&self, table: &str) -> VibeResult<BTreeSet<String>> {
+        let quoted = table.replace('"', "\"\"");
+        let result = self
+            .conn()
+            .call(move |conn| {
+                let mut columns = BTreeSet::new();
+                let mut info = conn.prepare(&format!("PRAGMA table_info(\"{}\")", quoted))?;
+                let keys = info.query_map([], |row| Ok((row.get::<_, String>(1)?, row.get::<_, i64>(5)?)))?;
+                for key in keys {
+                    let (name, pk) = key?;
+                    if pk == 1 {
+                        columns.insert(name);
+                    }
+                }
+                let mut list = conn.prepare(&format!("PRAGMA index_list(\"{}\")", quoted))?;
+                let indexes = list
+                    .query_map([], |row| row.get::<_, String>(1))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                for index in indexes {
+                    let sql = format!("PRAGMA index_info(\"{}\")", index.replace('"', "\"\""));
+                    let leading: Option<Option<String>> = conn
+                        .query_row(&sql, [], |row| row.get(2))
+                        .optional()?;
+                    if let Some(Some(column)) = leading {
+                        columns.insert(column);
+                    }
+                }
+                Ok(columns)
+            })
+            .await
+            .map_err(|e| VibeError::from_connection("Failed to list indexes", e));
+        self.watch(result).await
+    }
+
+    /// Watchdog state: faults seen and reopens attempted
+    pub fn health(&self) -> StoreHealth {
+        let watchdog = &self.watchdog;
+        StoreHealth {
+            healthy: !watchdog.failed.load(Ordering::Relaxed),
+            faults: watchdog.faults.load(Ordering::Relaxed),
+            reopen_attempts: watchdog.attempts.load(Ordering::Relaxed),
+            reopen_failures: watchdog.failures.load(Ordering::Relaxed),
+            last_fault: watchdog.last_fault.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+        }
+    }
+
+    /// Times the connection has been reopened; caches of the schema are
+    /// stale once it changes, as the file may have been replaced
+    pub fn reopens(&self) -> u64 {
+        self.watchdog.generation.load(Ordering::Acquire)
+    }
+
+    /// Passes a result through, counting faults and reopening the
+    /// connection once they reach the policy's threshold
+    async fn watch<T>(&self, result: VibeResult<T>) -> VibeResult<T> {
+        if let Err(err) = &result {
+            if let Some(fault) = Fault::classify(err) {
+                let generation = self.watchdog.generation.load(Ordering::Acquire);
+                warn!("Database fault ({:?}): {}", fault, err);
+                if self.watchdog.record(err) {
+                    self.reopen(generation).await;
+                }
+            }
+        }
+        result
+    }
+
+    /// Replaces the connection with a new one to the same path, unless
+    /// another caller already did since `generation`
+    async fn reopen(&self, generation: u64) {
+        let _reopening = self.watchdog.reopen_lock.lock().await;
+        if self.watchdog.generation.load(Ordering::Acquire) != generation {
+            return;
+        }
+        self.watchdog.reset();
+        if self.is_in_memory() {
+            warn!("In-memory database faulted; it can't be reopened");
+            return;
+        }
+
+        self.watchdog.attempts.fetch_add(1, Ordering::Relaxed);
+        warn!("🔁 Reopening database at {}", self.path);
+        let tuning = self.tuning.lock().unwrap_or_else(|e| e.into_inner()).statements();
+        match Self::open_checked(&self.path, tuning).await {
+            Ok(conn) => {
+                let old = std::mem::replace(&mut *self.conn.write().unwrap_or_else(|e| e.into_inner()), conn);
+                if let Err(e) = old.close().await {
+                    debug!("Closing the replaced connection failed: {}", e);
+                }
+                // The probe opens the new file on its next run
+                self.probe_conn.lock().await.take();
+                self.watchdog.failed.store(false, Ordering::Relaxed);
+                self.watchdog.generation.fetch_add(1, Ordering::Release);
+                info!("✨ Database reopened at {}", self.path);
+            }
+            Err(e) => {
+                self.watchdog.failures.fetch_add(1, Ordering::Relaxed);
+                self.watchdog.failed.store(true, Ordering::Relaxed);
+                error!("Reopening database at {} failed: {}", self.path, e);
+            }
+        }
+    }
+
+    /// Opens `path` with the store's pragmas and the runtime `tuning`, and
+    /// checks that it reads
+    async fn open_checked(path: &str, tuning: String) -> VibeResult<Connection> {
+        let conn = Connection::open(path)
+            .await
+            .map_err(|e| VibeError::from_connection("Failed to open database", e))?;
+        Self::initialize_pragmas(&conn).await?;
+        conn.call(move |conn| Ok(conn.execute_batch(&tuning)?))
+            .await
+            .map_err(|e| VibeError::from_connection("Failed to set pragmas", e))?;
+        conn.call(|conn| Ok(conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(()))?))
+            .await
+            .map_err(|e| VibeError::from_connection("Failed to read database", e))?;
+        Ok(conn)
     }
 
     /// Execute a write query (INSERT, UPDATE, DELETE, ALTER)
+    ///
+    /// Retried according to the store's `BusyRetry` policy.
     pub async fn execute(&self, sql: String, params: Vec<SqlValue>) -> VibeResult<u64> {
-        self.conn
+        self.execute_tallied(self.tally(None), sql, params).await
+    }
+
+    /// `execute`, counting busy retries under `label` (see `busy_retries`)
+    pub async fn execute_labeled(&self, label: &str, sql: String, params: Vec<SqlValue>) -> VibeResult<u64> {
+        self.execute_tallied(self.tally(Some(label)), sql, params).await
+    }
+
+    async fn execute_tallied(&self, tally: RetryTally, sql: String, params: Vec<SqlValue>) -> VibeResult<u64> {
+        let busy_retry = self.busy_retry;
+        let result = self
+            .conn()
             .call(move |conn| {
                 let params_refs: Vec<&dyn rusqlite::ToSql> = params
                     .iter()
                     .map(|p| p as &dyn rusqlite::ToSql)
                     .collect();
-                let affected = conn.execute(&sql, params_refs.as_slice())?;
+                let affected = busy_retry.run(&tally, || conn.execute(&sql, params_refs.as_slice()))?;
                 Ok(affected as u64)
             })
             .await
-            .map_err(|e| VibeError::Database(format!("Execute failed: {}", e)))
+            .map_err(|e| VibeError::from_connection("Execute failed", e));
+        self.count_transaction(self.watch(result).await)
+    }
+
+    /// Executes an INSERT and returns the new row's id
+    ///
+    /// Uses `RETURNING id` when SQLite supports it and falls back to
+    /// `last_insert_rowid()` otherwise. Either way the id is read in the same
+    /// call as the insert, so concurrent writes cannot interleave.
+    pub async fn insert_returning_id(&self, sql: String, params: Vec<SqlValue>) -> VibeResult<i64> {
+        self.insert_tallied(self.tally(None), sql, params).await
+    }
+
+    /// `insert_returning_id`, counting busy retries under `label`
+    pub async fn insert_returning_id_labeled(
+        &self,
+        label: &str,
+        sql: String,
+        params: Vec<SqlValue>,
+    ) -> VibeResult<i64> {
+        self.insert_tallied(self.tally(Some(label)), sql, params).await
+    }
+
+    async fn insert_tallied(&self, tally: RetryTally, sql: String, params: Vec<SqlValue>) -> VibeResult<i64> {
+        let busy_retry = self.busy_retry;
+        let returning = self.capabilities.returning;
+        let result = self
+            .conn()
+            .call(move |conn| Ok(busy_retry.run(&tally, || insert_row(conn, returning, &sql, &params))?))
+            .await
+            .map_err(|e| VibeError::from_connection("Insert failed", e));
+        self.count_transaction(self.watch(result).await)
     }
 
     /// Execute a simple query without parameters
     pub async fn execute_simple(&self, sql: String) -> VibeResult<u64> {
-        self.conn
+        let result = self
+            .conn()
             .call(move |conn| {
                 let affected = conn.execute(&sql, [])?;
                 Ok(affected as u64)
             })
             .await
-            .map_err(|e| VibeError::Database(format!("Execute failed: {}", e)))
+            .map_err(|e| VibeError::from_connection("Execute failed", e));
+        self.watch(result).await
     }
 
     /// Execute batch SQL
     pub async fn execute_batch(&self, sql: String) -> VibeResult<()> {
-        self.conn
+        let result = self
+            .conn()
             .call(move |conn| {
                 conn.execute_batch(&sql)?;
                 Ok(())
             })
             .await
-            .map_err(|e| VibeError::Database(format!("Batch execution failed: {}", e)))
+            .map_err(|e| VibeError::from_connection("Batch execution failed", e));
+        self.watch(result).await
     }
 
     /// Query and return rows as JSON-like structure
@@ -134,37 +829,441 @@ impl VibeStore {
         sql: String,
         params: Vec<SqlValue>,
     ) -> VibeResult<Vec<Vec<(String, serde_json::Value)>>> {
-        self.conn
+        let result = self
+            .conn()
+            .call(move |conn| Ok(Self::read_rows(conn, &sql, &params)?))
+            .await
+            .map_err(|e| VibeError::from_connection("Query failed", e));
+        self.watch(result).await
+    }
+
+    /// Runs a query, reading every row as JSON values
+    pub fn read_rows(
+        conn: &rusqlite::Connection,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> rusqlite::Result<Vec<Vec<(String, serde_json::Value)>>> {
+        let mut stmt = conn.prepare(sql)?;
+        let column_names: Vec<String> = stmt
+            .column_names()
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params
+            .iter()
+            .map(|p| p as &dyn rusqlite::ToSql)
+            .collect();
+
+        let mut rows_result = Vec::new();
+        let mut rows = stmt.query(params_refs.as_slice())?;
+
+        while let Some(row) = rows.next()? {
+            let mut row_data = Vec::new();
+            for (i, name) in column_names.iter().enumerate() {
+                let value = Self::get_value_from_row(row, i);
+                row_data.push((name.clone(), value));
+            }
+            rows_result.push(row_data);
+        }
+
+        Ok(rows_result)
+    }
+
+    /// Query that stops reading after `max_rows` rows
+    ///
+    /// Returns the rows and whether more were available. When `timeout` is
+    /// set, the statement is interrupted once it has run that long (time
+    /// spent waiting for the connection doesn't count) and a
+    /// `VibeError::Timeout` is returned. Statements that could write are
+    /// refused with `VibeError::Forbidden` before they run.
+    pub async fn query_limited(
+        &self,
+        sql: String,
+        params: Vec<SqlValue>,
+        max_rows: usize,
+        timeout: Option<Duration>,
+    ) -> VibeResult<(Vec<Vec<(String, serde_json::Value)>>, bool)> {
+        let result = self
+            .conn()
             .call(move |conn| {
-                let mut stmt = conn.prepare(&sql)?;
-                let column_names: Vec<String> = stmt
-                    .column_names()
-                    .iter()
-                    .map(|s| s.to_string())
-                    .collect();
+                // The deadline starts once the statement has the connection,
+                // not while it waits behind other work
+                if let Some(timeout) = timeout {
+                    let deadline = Instant::now() + timeout;
+                    conn.progress_handler(QUERY_PROGRESS_OPS, Some(move || Instant::now() >= deadline));
+                }
+                let result = Self::read_limited(conn, &sql, &params, max_rows);
+                conn.progress_handler(0, None::<fn() -> bool>);
+                Ok(result?)
+            })
+            .await;
 
-                let params_refs: Vec<&dyn rusqlite::ToSql> = params
+        let result = result.map_err(|e| match VibeError::from_connection("Query failed", e) {
+            VibeError::Sqlite {
+                code: rusqlite::ErrorCode::OperationInterrupted,
+                ..
+            } => VibeError::Timeout {
+                operation: "Query".to_string(),
+                timeout_ms: timeout.unwrap_or_default().as_millis() as u64,
+            },
+            other => other,
+        });
+        self.watch(result).await?.ok_or_else(|| {
+            VibeError::Forbidden("Only read-only statements can be queried; use /v1/sql/execute to write".to_string())
+        })
+    }
+
+    /// Reads up to `max_rows` rows of `sql`, or `None` if it could write
+    fn read_limited(
+        conn: &rusqlite::Connection,
+        sql: &str,
+        params: &[SqlValue],
+        max_rows: usize,
+    ) -> rusqlite::Result<Option<(Vec<JsonRow>, bool)>> {
+        let mut stmt = conn.prepare(sql)?;
+        if !stmt.readonly() {
+            return Ok(None);
+        }
+        let column_names: Vec<String> = stmt
+            .column_names()
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params
+            .iter()
+            .map(|p| p as &dyn rusqlite::ToSql)
+            .collect();
+
+        let mut rows_result = Vec::new();
+        let mut truncated = false;
+        let mut rows = stmt.query(params_refs.as_slice())?;
+        while let Some(row) = rows.next()? {
+            if rows_result.len() == max_rows {
+                truncated = true;
+                break;
+            }
+            rows_result.push(
+                column_names
                     .iter()
-                    .map(|p| p as &dyn rusqlite::ToSql)
-                    .collect();
+                    .enumerate()
+                    .map(|(i, name)| (name.clone(), Self::get_value_from_row(row, i)))
+                    .collect(),
+            );
+        }
+        Ok(Some((rows_result, truncated)))
+    }
 
-                let mut rows_result = Vec::new();
-                let rows = stmt.query(params_refs.as_slice())?;
-                let mut rows = rows;
+    /// Query that yields rows through a bounded channel as SQLite steps them
+    ///
+    /// The query runs on a dedicated read-only connection that waits for the
+    /// receiver when `capacity` rows are buffered, so a slow reader holds up
+    /// nothing else. In-memory databases are shared with that connection in
+    /// read-uncommitted mode, so that it takes no table locks a writer would
+    /// wait on; their streams may therefore see rows of transactions still
+    /// in progress, and schema changes fail as `DatabaseLocked` (once the
+    /// busy retries run out) while one is open. A SQL error (including one
+    /// raised mid-way through the result set) is delivered as the final
+    /// item, after the watchdog has seen it. Dropping the receiver stops
+    /// the query early.
+    pub fn query_streaming(
+        self: &Arc<Self>,
+        sql: String,
+        params: Vec<SqlValue>,
+        capacity: usize,
+    ) -> mpsc::Receiver<VibeResult<Vec<(String, serde_json::Value)>>> {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        let err_tx = tx.clone();
+        let store = Arc::clone(self);
 
-                while let Some(row) = rows.next()? {
-                    let mut row_data = Vec::new();
-                    for (i, name) in column_names.iter().enumerate() {
-                        let value = Self::get_value_from_row(row, i);
-                        row_data.push((name.clone(), value));
+        let mut flags = rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX;
+        let (path, shared) = match &self.memory_uri {
+            Some(uri) => {
+                flags |= rusqlite::OpenFlags::SQLITE_OPEN_URI;
+                (uri.clone(), true)
+            }
+            None => (self.path.clone(), false),
+        };
+        let streamed = Arc::clone(&self.streamed);
+        let read = tokio::task::spawn_blocking(move || {
+            rusqlite::Connection::open_with_flags(&path, flags)
+                .and_then(|conn| {
+                    conn.busy_timeout(SNAPSHOT_BUSY_TIMEOUT)?;
+                    if shared {
+                        conn.pragma_update(None, "read_uncommitted", true)?;
                     }
-                    rows_result.push(row_data);
-                }
+                    Self::step_rows(&conn, &sql, &params, |row| {
+                        streamed.fetch_add(1, Ordering::Relaxed);
+                        tx.blocking_send(Ok(row)).is_ok()
+                    })
+                })
+                .map_err(|e| VibeError::from_sqlite("Query failed", &e))
+        });
+        tokio::spawn(store.report_read_failure(read, err_tx));
 
-                Ok(rows_result)
-            })
+        rx
+    }
+
+    /// Waits for a blocking read and sends its error, once the watchdog
+    /// has seen it, as the final item of the read's channel
+    async fn report_read_failure<T>(
+        self: Arc<Self>,
+        read: tokio::task::JoinHandle<VibeResult<()>>,
+        err_tx: mpsc::Sender<VibeResult<T>>,
+    ) {
+        let result = read
             .await
-            .map_err(|e| VibeError::Database(format!("Query failed: {}", e)))
+            .unwrap_or_else(|e| Err(VibeError::Internal(anyhow::anyhow!("Read task failed: {}", e))));
+        if let Err(e) = self.watch(result).await {
+            let _ = err_tx.send(Err(e)).await;
+        }
+    }
+
+    /// Steps `sql`, handing each row to `emit` until it returns false
+    fn step_rows(
+        conn: &rusqlite::Connection,
+        sql: &str,
+        params: &[SqlValue],
+        mut emit: impl FnMut(Vec<(String, serde_json::Value)>) -> bool,
+    ) -> rusqlite::Result<()> {
+        let mut stmt = conn.prepare(sql)?;
+        let column_names: Vec<String> = stmt
+            .column_names()
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params
+            .iter()
+            .map(|p| p as &dyn rusqlite::ToSql)
+            .collect();
+
+        let mut rows = stmt.query(params_refs.as_slice())?;
+        while let Some(row) = rows.next()? {
+            let row_data: Vec<(String, serde_json::Value)> = column_names
+                .iter()
+                .enumerate()
+                .map(|(i, name)| (name.clone(), Self::get_value_from_row(row, i)))
+                .collect();
+            if !emit(row_data) {
+                debug!("Stream receiver dropped, stopping query");
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads whole tables at a single point in time
+    ///
+    /// Every table is read inside one deferred read transaction, so rows
+    /// written while the snapshot is being read are left out of all tables
+    /// alike. File databases are read on a dedicated read-only connection;
+    /// under WAL writers carry on meanwhile. In-memory databases have only
+    /// the main connection, which serves nothing else until the read is
+    /// done.
+    ///
+    /// `tables` defaults to every collection (tables not prefixed `vibe_`).
+    /// `shape` sets the columns read from each table. Items flow through a
+    /// channel of `capacity` like `query_streaming`;
+    /// an error is the final item, and dropping the receiver ends the read.
+    pub fn read_snapshot(
+        self: &Arc<Self>,
+        tables: Option<Vec<String>>,
+        shape: SnapshotShape,
+        capacity: usize,
+    ) -> mpsc::Receiver<VibeResult<SnapshotItem>> {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        let err_tx = tx.clone();
+        let store = Arc::clone(self);
+
+        if self.is_in_memory() {
+            let conn = self.conn();
+            tokio::spawn(async move {
+                let result = conn
+                    .call(move |conn| Ok(Self::send_snapshot(conn, tables, &shape, &tx)?))
+                    .await
+                    .map_err(|e| VibeError::from_connection("Snapshot failed", e));
+                if let Err(e) = store.watch(result).await {
+                    let _ = err_tx.send(Err(e)).await;
+                }
+            });
+        } else {
+            let path = self.path.clone();
+            let read = tokio::task::spawn_blocking(move || {
+                rusqlite::Connection::open_with_flags(
+                    &path,
+                    rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
+                )
+                .and_then(|conn| {
+                    conn.busy_timeout(SNAPSHOT_BUSY_TIMEOUT)?;
+                    Self::send_snapshot(&conn, tables, &shape, &tx)
+                })
+                .map_err(|e| VibeError::from_sqlite("Snapshot failed", &e))
+            });
+            tokio::spawn(store.report_read_failure(read, err_tx));
+        }
+
+        rx
+    }
+
+    /// Checks that the database answers reads, on a read-only connection
+    /// of its own
+    ///
+    /// Under WAL a long write on the main connection doesn't delay it, so
+    /// health checks stay responsive while a writer is stuck. In-memory
+    /// databases can't be opened twice and are probed on the main
+    /// connection.
+    pub async fn probe(&self) -> VibeResult<()> {
+        if self.is_in_memory() {
+            return self.query_simple("SELECT 1".to_string()).await.map(|_| ());
+        }
+        let conn = {
+            let mut probe_conn = self.probe_conn.lock().await;
+            match &*probe_conn {
+                Some(conn) => conn.clone(),
+                None => {
+                    let conn = Connection::open_with_flags(
+                        &self.path,
+                        rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
+                    )
+                    .await
+                    .map_err(|e| VibeError::from_connection("Failed to open probe connection", e))?;
+                    probe_conn.insert(conn).clone()
+                }
+            }
+        };
+        conn.call(|conn| {
+            // Reads the database header, unlike `SELECT 1`
+            conn.query_row("PRAGMA schema_version", [], |row| row.get::<_, i64>(0))?;
+            Ok(())
+        })
+        .await
+        .map_err(|e| VibeError::from_connection("Probe failed", e))
+    }
+
+    /// Begins a read transaction on a dedicated read-only connection
+    ///
+    /// Everything read through the returned session sees the database as it
+    /// was when the session began, however long it is kept; under WAL
+    /// writers carry on meanwhile. The transaction ends when the session is
+    /// dropped. File databases only, since an in-memory database
+    /// can't be opened twice.
+    pub async fn begin_read_session(&self) -> VibeResult<ReadSession> {
+        if self.is_in_memory() {
+            return Err(VibeError::Conflict(
+                "Read sessions need a file database".to_string(),
+            ));
+        }
+        let conn = Connection::open_with_flags(
+            &self.path,
+            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .await
+        .map_err(|e| VibeError::from_connection("Failed to open read session", e))?;
+        conn.call(|conn| {
+            conn.busy_timeout(SNAPSHOT_BUSY_TIMEOUT)?;
+            conn.execute_batch("BEGIN DEFERRED")?;
+            // The first read fixes the snapshot
+            conn.query_row("PRAGMA schema_version", [], |row| row.get::<_, i64>(0))?;
+            Ok(())
+        })
+        .await
+        .map_err(|e| VibeError::from_connection("Failed to begin read session", e))?;
+        Ok(ReadSession { conn })
+    }
+
+    fn send_snapshot(
+        conn: &rusqlite::Connection,
+        tables: Option<Vec<String>>,
+        shape: &SnapshotShape,
+        tx: &mpsc::Sender<VibeResult<SnapshotItem>>,
+    ) -> rusqlite::Result<()> {
+        conn.execute_batch("BEGIN DEFERRED")?;
+        let result = Self::send_tables(conn, tables, shape, tx);
+        // Nothing was written, so ending the transaction cannot lose data
+        let _ = conn.execute_batch("COMMIT");
+        result
+    }
+
+    /// Sends the `Begin` item and the rows of `tables` as the open
+    /// transaction of `conn` sees them
+    fn send_tables(
+        conn: &rusqlite::Connection,
+        tables: Option<Vec<String>>,
+        shape: &SnapshotShape,
+        tx: &mpsc::Sender<VibeResult<SnapshotItem>>,
+    ) -> rusqlite::Result<()> {
+        // The first read fixes the snapshot
+        let schema_version: i64 = conn.query_row("PRAGMA schema_version", [], |row| row.get(0))?;
+        let existing: Vec<String> = conn
+            .prepare(
+                "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+            )?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        let tables = match tables {
+            Some(tables) => {
+                if let Some(missing) = tables.iter().find(|t| !existing.contains(t)) {
+                    let _ = tx.blocking_send(Err(VibeError::TableNotFound(format!(
+                        "Table '{}' not found",
+                        missing
+                    ))));
+                    return Ok(());
+                }
+                tables
+            }
+            None => existing.into_iter().filter(|t| !t.starts_with("vibe_")).collect(),
+        };
+
+        let begin = SnapshotItem::Begin {
+            schema_version,
+            tables: tables.clone(),
+        };
+        if tx.blocking_send(Ok(begin)).is_err() {
+            return Ok(());
+        }
+        for table in tables {
+            let from = format!("FROM \"{}\"", table.replace('"', "\"\""));
+            let projection = shape.projections.get(&table);
+            let mut stmt = match projection {
+                Some((list, _)) => conn.prepare(&format!("SELECT {} {}", list, from))?,
+                None => conn.prepare(&format!("SELECT * {}", from))?,
+            };
+            let column_names: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
+            let casts = &shape.casts;
+            if projection.is_none() && column_names.iter().any(|name| casts.contains_key(name)) {
+                let projection: Vec<String> = column_names
+                    .iter()
+                    .map(|name| {
+                        let quoted = format!("\"{}\"", name.replace('"', "\"\""));
+                        match casts.get(name) {
+                            Some(target) => format!("{} AS {}", target.cast(&quoted), quoted),
+                            None => quoted,
+                        }
+                    })
+                    .collect();
+                stmt = conn.prepare(&format!("SELECT {} {}", projection.join(", "), from))?;
+            }
+            let params = projection.map(|(_, params)| params.as_slice()).unwrap_or_default();
+            let mut rows = stmt.query(rusqlite::params_from_iter(params))?;
+            while let Some(row) = rows.next()? {
+                let row = column_names
+                    .iter()
+                    .enumerate()
+                    .map(|(i, name)| (name.clone(), Self::get_value_from_row(row, i)))
+                    .collect();
+                let item = SnapshotItem::Row {
+                    table: table.clone(),
+                    row,
+                };
+                if tx.blocking_send(Ok(item)).is_err() {
+                    debug!("Snapshot receiver dropped, stopping read");
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
     }
 
     /// Query without parameters
@@ -232,34 +1331,136 @@ impl VibeStore {
         Ok(tables)
     }
 
+    /// Counts a table's rows, which scans the whole table
+    pub async fn count_rows(&self, table: &str) -> VibeResult<u64> {
+        let sql = format!("SELECT COUNT(*) as count FROM {}", table);
+        let rows = self.query_simple(sql).await?;
+        let count = rows
+            .first()
+            .and_then(|r| r.first())
+            .and_then(|(_, v)| v.as_i64())
+            .unwrap_or(0);
+        Ok(count as u64)
+    }
+
     /// Get last insert rowid
     pub async fn last_insert_rowid(&self) -> VibeResult<i64> {
-        self.conn
+        self.conn()
             .call(|conn| Ok(conn.last_insert_rowid()))
             .await
-            .map_err(|e| VibeError::Database(format!("Failed to get last rowid: {}", e)))
+            .map_err(|e| VibeError::from_connection("Failed to get last rowid", e))
     }
 
     /// Execute with transaction
+    ///
+    /// The write lock is taken up front (`BEGIN IMMEDIATE`), so busy/locked
+    /// conflicts surface there and only that step is retried; `f` runs once.
     pub async fn with_transaction<F, T>(&self, f: F) -> VibeResult<T>
     where
         F: FnOnce(&rusqlite::Connection) -> Result<T, rusqlite::Error> + Send + 'static,
         T: Send + 'static,
     {
-        self.conn
+        self.transaction_tallied(self.tally(None), f).await
+    }
+
+    /// `with_transaction`, counting busy retries under `label`
+    pub async fn with_transaction_labeled<F, T>(&self, label: &str, f: F) -> VibeResult<T>
+    where
+        F: FnOnce(&rusqlite::Connection) -> Result<T, rusqlite::Error> + Send + 'static,
+        T: Send + 'static,
+    {
+        self.transaction_tallied(self.tally(Some(label)), f).await
+    }
+
+    async fn transaction_tallied<F, T>(&self, tally: RetryTally, f: F) -> VibeResult<T>
+    where
+        F: FnOnce(&rusqlite::Connection) -> Result<T, rusqlite::Error> + Send + 'static,
+        T: Send + 'static,
+    {
+        let busy_retry = self.busy_retry;
+        let result = self
+            .conn()
             .call(move |conn| {
-                let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+                let conn: &rusqlite::Connection = conn;
+                let tx = busy_retry.run(&tally, || {
+                    rusqlite::Transaction::new_unchecked(conn, TransactionBehavior::Immediate)
+                })?;
                 let result = f(&tx)?;
                 tx.commit()?;
                 Ok(result)
             })
             .await
-            .map_err(|e| VibeError::Database(format!("Transaction failed: {}", e)))
+            .map_err(|e| VibeError::from_connection("Transaction failed", e));
+        self.count_transaction(self.watch(result).await)
+    }
+}
+
+/// A read transaction held open on its own connection (see
+/// `VibeStore::begin_read_session`)
+pub struct ReadSession {
+    conn: Connection,
+}
+
+impl ReadSession {
+    /// `VibeStore::query`, as of the session's snapshot
+    pub async fn query(
+        &self,
+        sql: String,
+        params: Vec<SqlValue>,
+    ) -> VibeResult<Vec<Vec<(String, serde_json::Value)>>> {
+        self.conn
+            .call(move |conn| Ok(VibeStore::read_rows(conn, &sql, &params)?))
+            .await
+            .map_err(|e| VibeError::from_connection("Query failed", e))
+    }
+
+    /// `VibeStore::read_snapshot`, as of the session's snapshot
+    ///
+    /// The session serves nothing else until the read is done.
+    pub fn read_snapshot(
+        &self,
+        tables: Option<Vec<String>>,
+        shape: SnapshotShape,
+        capacity: usize,
+    ) -> mpsc::Receiver<VibeResult<SnapshotItem>> {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        let err_tx = tx.clone();
+        let conn = self.conn.clone();
+        tokio::spawn(async move {
+            let result = conn
+                .call(move |conn| Ok(VibeStore::send_tables(conn, tables, &shape, &tx)?))
+                .await;
+            if let Err(e) = result {
+                let _ = err_tx
+                    .send(Err(VibeError::from_connection("Snapshot failed", e)))
+                    .await;
+            }
+        });
+        rx
+    }
+}
+
+/// Runs one INSERT and reads back its id
+///
+/// Uses `RETURNING id` when SQLite supports it and `last_insert_rowid()`
+/// otherwise.
+pub(crate) fn insert_row(
+    conn: &rusqlite::Connection,
+    returning: bool,
+    sql: &str,
+    params: &[SqlValue],
+) -> rusqlite::Result<i64> {
+    let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p as &dyn rusqlite::ToSql).collect();
+    if returning {
+        conn.query_row(&format!("{} RETURNING id", sql), params_refs.as_slice(), |row| row.get(0))
+    } else {
+        conn.execute(sql, params_refs.as_slice())?;
+        Ok(conn.last_insert_rowid())
     }
 }
 
 /// SQL Value wrapper for parameters
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum SqlValue {
     Null,
     Integer(i64),
@@ -295,15 +1496,7 @@ pub fn json_to_sql_value(value: &serde_json::Value) -> SqlValue {
     match value {
         serde_json::Value::Null => SqlValue::Null,
         serde_json::Value::Bool(b) => SqlValue::Integer(if *b { 1 } else { 0 }),
-        serde_json::Value::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                SqlValue::Integer(i)
-            } else if let Some(f) = n.as_f64() {
-                SqlValue::Real(f)
-            } else {
-                SqlValue::Text(n.to_string())
-            }
-        }
+        serde_json::Value::Number(n) => number_to_sql_value(n),
         serde_json::Value::String(s) => SqlValue::Text(s.clone()),
         serde_json::Value::Array(_) | serde_json::Value::Object(_) => {
             SqlValue::Text(serde_json::to_string(value).unwrap_or_default())
@@ -311,6 +1504,25 @@ pub fn json_to_sql_value(value: &serde_json::Value) -> SqlValue {
     }
 }
 
+/// Convert a JSON number to SqlValue
+///
+/// - integers within `i64` are stored as INTEGER
+/// - integers beyond `i64` (`u64` above `i64::MAX`) are bound as their
+///   decimal TEXT, so a TEXT column keeps every digit; INTEGER and REAL
+///   columns still convert them to the nearest REAL (see
+///   `inference::InexactNumber`)
+/// - everything else is stored as REAL
+fn number_to_sql_value(n: &serde_json::Number) -> SqlValue {
+    if let Some(i) = n.as_i64() {
+        return SqlValue::Integer(i);
+    }
+    if n.is_u64() {
+        debug!("Integer {} exceeds i64, binding it as TEXT", n);
+        return SqlValue::Text(n.to_string());
+    }
+    SqlValue::Real(n.as_f64().unwrap_or(f64::NAN))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -355,4 +1567,416 @@ mod tests {
         assert_eq!(rows.len(), 1);
         assert_eq!(rows[0][0].1, serde_json::json!("VibeDB"));
     }
+
+    #[test]
+    fn test_number_conversion_boundaries() {
+        use serde_json::json;
+
+        let convert = |v: serde_json::Value| json_to_sql_value(&v);
+        assert!(matches!(convert(json!(i64::MAX)), SqlValue::Integer(i64::MAX)));
+        assert!(matches!(convert(json!(i64::MIN)), SqlValue::Integer(i64::MIN)));
+        assert!(matches!(convert(json!(0)), SqlValue::Integer(0)));
+
+        // Just past i64 and the top of u64 keep every digit as TEXT
+        assert_eq!(convert(json!(i64::MAX as u64 + 1)), SqlValue::Text("9223372036854775808".to_string()));
+        assert_eq!(convert(json!(u64::MAX)), SqlValue::Text("18446744073709551615".to_string()));
+        assert!(matches!(convert(json!(1.5)), SqlValue::Real(f) if f == 1.5));
+        assert!(matches!(convert(json!(f64::MAX)), SqlValue::Real(f) if f == f64::MAX));
+    }
+
+    #[tokio::test]
+    async fn test_large_integers_in_integer_columns_filter_numerically() {
+        use serde_json::json;
+
+        let store = VibeStore::in_memory().await.unwrap();
+        store
+            .execute_simple("CREATE TABLE t (n INTEGER)".to_string())
+            .await
+            .unwrap();
+        for v in [json!(5), json!(u64::MAX)] {
+            store
+                .execute("INSERT INTO t (n) VALUES (?)".to_string(), vec![json_to_sql_value(&v)])
+                .await
+                .unwrap();
+        }
+
+        let rows = store
+            .query(
+                "SELECT n FROM t WHERE n > ? ORDER BY n".to_string(),
+                vec![json_to_sql_value(&json!(i64::MAX))],
+            )
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][0].1, json!(u64::MAX as f64));
+    }
+
+    #[tokio::test]
+    async fn test_query_limited_truncates_and_times_out() {
+        let store = VibeStore::in_memory().await.unwrap();
+
+        let sql = "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c WHERE x < 50) SELECT x FROM c";
+        let (rows, truncated) = store
+            .query_limited(sql.to_string(), vec![], 10, None)
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 10);
+        assert!(truncated);
+
+        let (rows, truncated) = store
+            .query_limited(sql.to_string(), vec![], 50, None)
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 50);
+        assert!(!truncated);
+
+        // An effectively endless aggregate is interrupted
+        let endless = "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c) SELECT COUNT(*) FROM c";
+        let err = store
+            .query_limited(endless.to_string(), vec![], 10, Some(Duration::from_millis(50)))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, VibeError::Timeout { timeout_ms: 50, .. }));
+
+        // The connection is still usable afterwards
+        assert_eq!(store.query_simple("SELECT 1".to_string()).await.unwrap().len(), 1);
+
+        // Waiting behind other work doesn't use up the budget
+        let conn = store.conn();
+        let busy = tokio::spawn(async move {
+            conn.call(|_| {
+                std::thread::sleep(Duration::from_millis(200));
+                Ok(())
+            })
+            .await
+        });
+        tokio::task::yield_now().await;
+        let (rows, _) = store
+            .query_limited(sql.to_string(), vec![], 50, Some(Duration::from_millis(100)))
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 50);
+        busy.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_query_streaming_bounded() {
+        let store = Arc::new(VibeStore::in_memory().await.unwrap());
+        store
+            .execute_batch(
+                "CREATE TABLE big (id INTEGER PRIMARY KEY, n INTEGER);
+                 WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c WHERE x < 10000)
+                 INSERT INTO big (n) SELECT x FROM c;"
+                    .to_string(),
+            )
+            .await
+            .unwrap();
+
+        let mut rx = store.query_streaming("SELECT n FROM big ORDER BY id".to_string(), vec![], 8);
+        assert_eq!(rx.max_capacity(), 8);
+
+        // A stalled reader holds SQLite to the channel's capacity, plus the
+        // row waiting to be sent
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(store.streamed_rows(), 9);
+
+        let mut received = 0i64;
+        while let Some(row) = rx.recv().await {
+            received += 1;
+            assert_eq!(row.unwrap()[0].1, serde_json::json!(received));
+        }
+        assert_eq!(received, 10000);
+        assert_eq!(store.streamed_rows(), 10000);
+    }
+
+    #[tokio::test]
+    async fn test_query_streaming_slow_reader() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = Arc::new(VibeStore::new(dir.path().join("stream.db")).await.unwrap());
+        let memory = Arc::new(VibeStore::in_memory().await.unwrap());
+        let rows = 20_000;
+        for store in [&file, &memory] {
+            store
+                .execute_batch(format!(
+                    "CREATE TABLE big (id INTEGER PRIMARY KEY, n INTEGER);
+                     WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c WHERE x < {})
+                     INSERT INTO big (n) SELECT x FROM c;",
+                    rows
+                ))
+                .await
+                .unwrap();
+
+            // The stream waits for its reader on a connection of its own,
+            // leaving the main one free for reads and writes
+            let mut rx = store.query_streaming("SELECT n FROM big".to_string(), vec![], 1);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            tokio::time::timeout(Duration::from_secs(5), async {
+                store.query_simple("SELECT 1".to_string()).await.unwrap();
+                store
+                    .execute_batch("INSERT INTO big (n) VALUES (0)".to_string())
+                    .await
+                    .unwrap();
+            })
+            .await
+            .expect("main connection blocked by a stalled stream");
+            assert_eq!(store.streamed_rows(), 2);
+
+            let mut received = 0;
+            while let Some(row) = rx.recv().await {
+                row.unwrap();
+                received += 1;
+            }
+            assert!(received >= rows);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_streaming_mid_stream_error() {
+        let store = Arc::new(VibeStore::in_memory().await.unwrap());
+        store
+            .execute_batch(
+                r#"CREATE TABLE docs (id INTEGER PRIMARY KEY, body TEXT);
+                   INSERT INTO docs (body) VALUES ('{"a":1}'), ('{"a":2}'), ('not json'), ('{"a":4}');"#
+                    .to_string(),
+            )
+            .await
+            .unwrap();
+
+        let mut rx = store.query_streaming(
+            "SELECT json(body) AS body FROM docs ORDER BY id".to_string(),
+            vec![],
+            2,
+        );
+
+        let mut ok = 0;
+        let mut errors = 0;
+        while let Some(row) = rx.recv().await {
+            match row {
+                Ok(_) => ok += 1,
+                Err(_) => errors += 1,
+            }
+        }
+        assert_eq!(ok, 2);
+        assert_eq!(errors, 1);
+    }
+
+    #[tokio::test]
+    async fn test_busy_writes_are_retried() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("busy.db");
+
+        let holder = rusqlite::Connection::open(&path).unwrap();
+        holder.execute_batch("CREATE TABLE t (x INTEGER)").unwrap();
+
+        async fn open(path: &Path, max_attempts: u32) -> VibeStore {
+            let store = VibeStore::new(path).await.unwrap().with_busy_retry(BusyRetry {
+                max_attempts,
+                base_delay: Duration::from_millis(25),
+            });
+            // Rely on the retry loop alone
+            store.execute_batch("PRAGMA busy_timeout = 0".to_string()).await.unwrap();
+            store
+        }
+        let patient = open(&path, 5).await;
+        let impatient = open(&path, 1).await;
+
+        holder.execute_batch("BEGIN IMMEDIATE").unwrap();
+        let err = impatient
+            .execute("INSERT INTO t VALUES (1)".to_string(), vec![])
+            .await
+            .unwrap_err();
+        assert_eq!(err.error_code(), "DATABASE_BUSY");
+
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(60));
+            holder.execute_batch("COMMIT").unwrap();
+        });
+        patient
+            .execute_labeled("t", "INSERT INTO t VALUES (1)".to_string(), vec![])
+            .await
+            .unwrap();
+        release.join().unwrap();
+        assert!(patient.busy_retries("t") > 0);
+        assert_eq!(patient.busy_retries("other"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_insert_returning_id_falls_back_without_returning() {
+        let store = VibeStore::in_memory().await.unwrap();
+        assert!(store.capabilities().returning);
+        store
+            .execute_batch("CREATE TABLE plain (x INTEGER); CREATE TABLE keyed (id INTEGER PRIMARY KEY, x INTEGER);".to_string())
+            .await
+            .unwrap();
+
+        let insert = |table: &str| (format!("INSERT INTO {} (x) VALUES (?)", table), vec![SqlValue::Integer(7)]);
+        let (sql, params) = insert("keyed");
+        assert_eq!(store.insert_returning_id(sql, params).await.unwrap(), 1);
+
+        // `plain` has no id column, so only the last_insert_rowid path succeeds
+        let (sql, params) = insert("plain");
+        assert!(store.insert_returning_id(sql, params).await.is_err());
+
+        let capabilities = Capabilities {
+            returning: false,
+            ..store.capabilities().clone()
+        };
+        let store = store.with_capabilities(capabilities);
+        let (sql, params) = insert("plain");
+        assert_eq!(store.insert_returning_id(sql, params).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_ignores_concurrent_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(VibeStore::new(dir.path().join("snap.db")).await.unwrap());
+        store
+            .execute_batch(
+                "CREATE TABLE a (id INTEGER PRIMARY KEY, n INTEGER);
+                 CREATE TABLE b (id INTEGER PRIMARY KEY, n INTEGER);
+                 CREATE TABLE vibe_internal (x INTEGER);
+                 INSERT INTO a (n) VALUES (1), (2), (3);
+                 INSERT INTO b (n) VALUES (1), (2);"
+                    .to_string(),
+            )
+            .await
+            .unwrap();
+
+        let mut rx = store.read_snapshot(None, SnapshotShape::default(), 1);
+        match rx.recv().await.unwrap().unwrap() {
+            SnapshotItem::Begin { tables, .. } => assert_eq!(tables, vec!["a", "b"]),
+            other => panic!("expected Begin, got {:?}", other),
+        }
+        assert!(matches!(rx.recv().await.unwrap().unwrap(), SnapshotItem::Row { .. }));
+
+        // Writers are not blocked, and the snapshot does not see them
+        store
+            .execute_batch("INSERT INTO a (n) VALUES (4); INSERT INTO b (n) VALUES (3);".to_string())
+            .await
+            .unwrap();
+
+        let mut counts = std::collections::HashMap::new();
+        counts.insert("a".to_string(), 1);
+        while let Some(item) = rx.recv().await {
+            if let SnapshotItem::Row { table, .. } = item.unwrap() {
+                *counts.entry(table).or_insert(0) += 1;
+            }
+        }
+        assert_eq!(counts["a"], 3);
+        assert_eq!(counts["b"], 2);
+
+        let mut rx = store.read_snapshot(Some(vec!["missing".to_string()]), SnapshotShape::default(), 1);
+        let err = rx.recv().await.unwrap().unwrap_err();
+        assert_eq!(err.error_code(), "TABLE_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_wal_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wal.db");
+        let store = VibeStore::new(&path).await.unwrap();
+        store
+            .execute_batch("CREATE TABLE t (n INTEGER); INSERT INTO t (n) VALUES (1), (2);".to_string())
+            .await
+            .unwrap();
+        let wal = dir.path().join("wal.db-wal");
+        assert!(std::fs::metadata(&wal).unwrap().len() > 0);
+
+        let passive = store.wal_checkpoint(CheckpointMode::Passive).await.unwrap();
+        assert!(!passive.busy);
+        assert!(passive.log_pages > 0);
+        assert_eq!(passive.checkpointed_pages, passive.log_pages);
+
+        let truncated = store.wal_checkpoint(CheckpointMode::Truncate).await.unwrap();
+        assert_eq!((truncated.mode, truncated.busy), (CheckpointMode::Truncate, false));
+        assert_eq!(std::fs::metadata(&wal).unwrap().len(), 0);
+
+        let memory = VibeStore::in_memory().await.unwrap();
+        let err = memory.wal_checkpoint(CheckpointMode::Full).await.unwrap_err();
+        assert_eq!(err.error_code(), "CONFLICT");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stream_faults_reach_the_watchdog() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("garbled.db");
+        let store = Arc::new(VibeStore::new(&path).await.unwrap().with_reopen_policy(ReopenPolicy {
+            fault_threshold: 10,
+            ..ReopenPolicy::default()
+        }));
+        store
+            .execute_batch("PRAGMA journal_mode=DELETE; CREATE TABLE t (x INTEGER);".to_string())
+            .await
+            .unwrap();
+        std::fs::write(&path, vec![0x5a; 8192]).unwrap();
+
+        let mut rx = store.query_streaming("SELECT x FROM t".to_string(), vec![], 1);
+        let err = rx.recv().await.unwrap().unwrap_err();
+        assert_eq!(Fault::classify(&err), Some(Fault::NotADatabase));
+        let mut rx = store.read_snapshot(None, SnapshotShape::default(), 1);
+        assert!(rx.recv().await.unwrap().is_err());
+        assert_eq!(store.health().faults, 2);
+    }
+
+    #[tokio::test]
+    async fn test_deleted_file_is_reopened() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gone.db");
+        let store = VibeStore::new(&path).await.unwrap().with_reopen_policy(ReopenPolicy {
+            fault_threshold: 2,
+            ..ReopenPolicy::default()
+        });
+        // Under WAL, writes to a deleted file succeed silently; with a
+        // rollback journal SQLite notices and reports SQLITE_READONLY_DBMOVED
+        store
+            .execute_batch("PRAGMA journal_mode=DELETE; CREATE TABLE t (x INTEGER); INSERT INTO t VALUES (1);".to_string())
+            .await
+            .unwrap();
+
+        let update = PragmaUpdate {
+            cache_size: Some(-2000),
+            ..PragmaUpdate::default()
+        };
+        store.set_pragmas(update).await.unwrap();
+        store.probe().await.unwrap();
+        assert!(store.probe_conn.lock().await.is_some());
+
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+        // Writes to the unlinked file fail until the second fault reopens it
+        for _ in 0..2 {
+            let err = store.execute("INSERT INTO t VALUES (2)".to_string(), vec![]).await.unwrap_err();
+            assert!(Fault::classify(&err).is_some(), "unexpected error: {}", err);
+        }
+        let health = store.health();
+        assert!(health.healthy);
+        assert_eq!((health.faults, health.reopen_attempts, health.reopen_failures), (2, 1, 0));
+        // The probe no longer reads the unlinked file
+        assert!(store.probe_conn.lock().await.is_none());
+
+        store.execute_batch("CREATE TABLE t (x INTEGER); INSERT INTO t VALUES (3);".to_string()).await.unwrap();
+        assert!(path.exists());
+        // The pragmas were run again
+        let mode = store.query_simple("PRAGMA journal_mode".to_string()).await.unwrap();
+        assert_eq!(mode[0][0].1, serde_json::json!("wal"));
+        assert_eq!(store.pragmas().await.unwrap().cache_size, -2000);
+        assert_eq!(rusqlite::Connection::open(&path).unwrap().query_row("SELECT x FROM t", [], |row| row.get::<_, i64>(0)).unwrap(), 3);
+
+        // A path that can't be opened leaves the store unhealthy
+        let store = store.with_reopen_policy(ReopenPolicy {
+            fault_threshold: 1,
+            ..ReopenPolicy::default()
+        });
+        store.execute_batch("PRAGMA journal_mode=DELETE".to_string()).await.unwrap();
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+        std::fs::create_dir(&path).unwrap();
+        assert!(store.execute("INSERT INTO t VALUES (4)".to_string(), vec![]).await.is_err());
+        let health = store.health();
+        assert!(!health.healthy);
+        assert_eq!(health.reopen_failures, 1);
+    }
 }
@@ -0,0 +1,455 @@
+//! # Dead Letters
+//!
+//! Keeps pushes that were rejected instead of losing them. With
+//! `{"dead_letter": true}` in a collection's settings, a single-document
+//! push that fails with a client error (bad identifier, hidden column,
+//! constraint violation, ...) is stored here with the raw payload, the error
+//! and where it came from.
+//!
+//! Capturing is best-effort: it runs after the error response is decided
+//! and a failure to store is only logged. A dead letter can be retried
+//! through the normal push path once the cause is fixed; a successful retry
+//! marks it resolved with the id of the new row. A dead letter is claimed
+//! for the length of a retry, so concurrent retries can't push it twice.
+//!
+//! ## System Tables
+//! - `vibe_dead_letters` - One row per rejected payload
+
+use crate::api::AppState;
+use crate::audit::format_timestamp;
+use crate::auth::AuthUser;
+use crate::clock::{SharedClock, SystemClock};
+use crate::db::{SqlValue, VibeStore};
+use crate::error::{VibeError, VibeResult};
+
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// How often expired dead letters are purged
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// A rejected payload and why it was rejected
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub id: i64,
+    pub collection: String,
+    pub payload: Value,
+    pub error_code: String,
+    pub error_message: String,
+    pub client_ip: Option<String>,
+    pub user_email: Option<String>,
+    pub created_at: String,
+    /// Retries attempted so far
+    pub attempts: i64,
+    pub resolved_at: Option<String>,
+    /// Id of the row created by the successful retry
+    pub resolved_id: Option<i64>,
+}
+
+/// Where a rejected payload came from
+#[derive(Debug, Clone, Default)]
+pub struct Origin {
+    pub client_ip: Option<String>,
+    pub user_email: Option<String>,
+}
+
+impl Origin {
+    pub fn new(client_ip: Option<String>, caller: Option<&AuthUser>) -> Self {
+        Self {
+            client_ip,
+            user_email: caller.map(|user| user.email.clone()),
+        }
+    }
+}
+
+/// Filters for listing dead letters
+#[derive(Debug, Default, Deserialize)]
+pub struct DeadLetterFilter {
+    pub collection: Option<String>,
+    pub error_code: Option<String>,
+    /// Only resolved (`true`) or unresolved (`false`) dead letters
+    pub resolved: Option<bool>,
+    pub limit: Option<u32>,
+}
+
+/// Whether an error means the payload itself was refused
+///
+/// Server-side failures (database unavailable, timeouts) and anything else
+/// marked retryable (a locked database, rate limiting) are not captured;
+/// the producer is expected to retry on its own.
+pub fn is_rejection(err: &VibeError) -> bool {
+    let status = err.status_code();
+    status.is_client_error() && status != StatusCode::UNAUTHORIZED && !err.retryable()
+}
+
+/// Persistent store of dead letters
+pub struct DeadLetterStore {
+    store: Arc<VibeStore>,
+    initialized: OnceCell<()>,
+    clock: SharedClock,
+}
+
+impl DeadLetterStore {
+    pub fn new(store: Arc<VibeStore>) -> Self {
+        Self {
+            store,
+            initialized: OnceCell::new(),
+            clock: SystemClock::shared(),
+        }
+    }
+
+    /// Stamps and ages dead letters by `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    async fn ensure_table(&self) -> VibeResult<()> {
+        self.initialized
+            .get_or_try_init(|| async {
+                self.store
+                    .execute_batch(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS vibe_dead_letters (
+                            id INTEGER PRIMARY KEY AUTOINCREMENT,
+                            collection TEXT NOT NULL,
+                            payload TEXT NOT NULL,
+                            error_code TEXT NOT NULL,
+                            error_message TEXT NOT NULL,
+                            client_ip TEXT,
+                            user_email TEXT,
+                            created_at TEXT NOT NULL,
+                            attempts INTEGER NOT NULL DEFAULT 0,
+                            resolved_at TEXT,
+                            resolved_id INTEGER,
+                            retrying INTEGER NOT NULL DEFAULT 0
+                        );
+                        CREATE INDEX IF NOT EXISTS idx_vibe_dead_letters_collection
+                            ON vibe_dead_letters(collection, error_code);
+                        CREATE INDEX IF NOT EXISTS idx_vibe_dead_letters_created
+                            ON vibe_dead_letters(created_at);
+                        "#
+                        .to_string(),
+                    )
+                    .await?;
+
+                // Tables from before retries were claimed lack the flag;
+                // claims left by a previous run are released
+                let columns = self
+                    .store
+                    .query(
+                        "SELECT name FROM pragma_table_info('vibe_dead_letters') WHERE name = 'retrying'"
+                            .to_string(),
+                        vec![],
+                    )
+                    .await?;
+                let reset = if columns.is_empty() {
+                    "ALTER TABLE vibe_dead_letters ADD COLUMN retrying INTEGER NOT NULL DEFAULT 0;"
+                } else {
+                    "UPDATE vibe_dead_letters SET retrying = 0 WHERE retrying = 1;"
+                };
+                self.store.execute_batch(reset.to_string()).await
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Stores a rejected payload
+    pub async fn record(
+        &self,
+        collection: &str,
+        payload: &Value,
+        err: &VibeError,
+        origin: &Origin,
+    ) -> VibeResult<i64> {
+        self.insert(collection, payload, err.error_code(), &err.to_string(), origin)
+            .await
+    }
+
+    /// Stores a rejected payload in the background, logging failures
+    pub fn capture(self: &Arc<Self>, collection: &str, payload: Value, err: &VibeError, origin: Origin) {
+        let store = Arc::clone(self);
+        let collection = collection.to_string();
+        let code = err.error_code();
+        let message = err.to_string();
+        tokio::spawn(async move {
+            match store.insert(&collection, &payload, code, &message, &origin).await {
+                Ok(id) => info!("📮 Dead-lettered push to {} as #{} ({})", collection, id, code),
+                Err(e) => warn!("⚠️ Could not dead-letter push to {}: {}", collection, e),
+            }
+        });
+    }
+
+    async fn insert(
+        &self,
+        collection: &str,
+        payload: &Value,
+        code: &str,
+        message: &str,
+        origin: &Origin,
+    ) -> VibeResult<i64> {
+        self.ensure_table().await?;
+        self.store
+            .insert_returning_id(
+                "INSERT INTO vibe_dead_letters \
+                 (collection, payload, error_code, error_message, client_ip, user_email, created_at) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?)"
+                    .to_string(),
+                vec![
+                    SqlValue::Text(collection.to_string()),
+                    SqlValue::Text(payload.to_string()),
+                    SqlValue::Text(code.to_string()),
+                    SqlValue::Text(message.to_string()),
+                    origin.client_ip.clone().map(SqlValue::Text).unwrap_or(SqlValue::Null),
+                    origin.user_email.clone().map(SqlValue::Text).unwrap_or(SqlValue::Null),
+                    SqlValue::Text(format_timestamp(self.clock.now())),
+                ],
+            )
+            .await
+    }
+
+    /// Loads a dead letter by id
+    pub async fn get(&self, id: i64) -> VibeResult<DeadLetter> {
+        self.ensure_table().await?;
+        let rows = self
+            .store
+            .query(
+                "SELECT * FROM vibe_dead_letters WHERE id = ?".to_string(),
+                vec![SqlValue::Integer(id)],
+            )
+            .await?;
+
+        match rows.into_iter().next() {
+            Some(row) => Ok(serde_json::from_value(Value::Object(row.into_iter().collect()))?),
+            None => Err(VibeError::NotFound(format!("Dead letter {} not found", id))),
+        }
+    }
+
+    /// Lists dead letters, newest first
+    pub async fn list(&self, filter: &DeadLetterFilter) -> VibeResult<Vec<DeadLetter>> {
+        self.ensure_table().await?;
+
+        let mut sql = "SELECT * FROM vibe_dead_letters WHERE 1 = 1".to_string();
+        let mut params = Vec::new();
+        if let Some(collection) = &filter.collection {
+            sql.push_str(" AND collection = ?");
+            params.push(SqlValue::Text(collection.clone()));
+        }
+        if let Some(code) = &filter.error_code {
+            sql.push_str(" AND error_code = ?");
+            params.push(SqlValue::Text(code.to_ascii_uppercase()));
+        }
+        match filter.resolved {
+            Some(true) => sql.push_str(" AND resolved_at IS NOT NULL"),
+            Some(false) => sql.push_str(" AND resolved_at IS NULL"),
+            None => {}
+        }
+        sql.push_str(" ORDER BY id DESC LIMIT ?");
+        params.push(SqlValue::Integer(filter.limit.unwrap_or(100).min(1000) as i64));
+
+        self.store
+            .query(sql, params)
+            .await?
+            .into_iter()
+            .map(|row| Ok(serde_json::from_value(Value::Object(row.into_iter().collect()))?))
+            .collect()
+    }
+
+    /// Claims an unresolved dead letter for a retry and counts the attempt
+    ///
+    /// Fails with `Conflict` while another retry holds the claim. The claim
+    /// ends with `resolve` or `record_retry_failure`, or when it is dropped.
+    pub async fn begin_retry(&self, id: i64) -> VibeResult<(DeadLetter, RetryClaim)> {
+        self.ensure_table().await?;
+        let claimed = self
+            .store
+            .execute(
+                "UPDATE vibe_dead_letters SET retrying = 1, attempts = attempts + 1 \
+                 WHERE id = ? AND resolved_at IS NULL AND retrying = 0"
+                    .to_string(),
+                vec![SqlValue::Integer(id)],
+            )
+            .await?;
+        if claimed == 0 {
+            let letter = self.get(id).await?;
+            return Err(VibeError::Conflict(match letter.resolved_at {
+                Some(_) => format!("Dead letter {} is already resolved", id),
+                None => format!("Dead letter {} is already being retried", id),
+            }));
+        }
+        let claim = RetryClaim {
+            store: Arc::clone(&self.store),
+            id,
+            released: false,
+        };
+        Ok((self.get(id).await?, claim))
+    }
+
+    /// Marks a claimed dead letter resolved by the row `resolved_id`
+    pub async fn resolve(&self, mut claim: RetryClaim, resolved_id: i64) -> VibeResult<DeadLetter> {
+        claim.released = true;
+        self.store
+            .execute(
+                "UPDATE vibe_dead_letters SET resolved_at = ?, resolved_id = ?, retrying = 0 WHERE id = ?"
+                    .to_string(),
+                vec![
+                    SqlValue::Text(format_timestamp(self.clock.now())),
+                    SqlValue::Integer(resolved_id),
+                    SqlValue::Integer(claim.id),
+                ],
+            )
+            .await?;
+        self.get(claim.id).await
+    }
+
+    /// Keeps the latest error of a failed retry and releases its claim
+    pub async fn record_retry_failure(&self, mut claim: RetryClaim, err: &VibeError) -> VibeResult<()> {
+        claim.released = true;
+        self.store
+            .execute(
+                "UPDATE vibe_dead_letters SET error_code = ?, error_message = ?, retrying = 0 WHERE id = ?"
+                    .to_string(),
+                vec![
+                    SqlValue::Text(err.error_code().to_string()),
+                    SqlValue::Text(err.to_string()),
+                    SqlValue::Integer(claim.id),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Deletes dead letters older than `retention`
+    pub async fn purge_older_than(&self, retention: Duration) -> VibeResult<u64> {
+        self.ensure_table().await?;
+        let retention = chrono::Duration::from_std(retention)
+            .map_err(|e| VibeError::InvalidPayload(format!("Invalid retention: {}", e)))?;
+        let cutoff = format_timestamp(self.clock.now() - retention);
+        self.store
+            .execute(
+                "DELETE FROM vibe_dead_letters WHERE created_at < ?".to_string(),
+                vec![SqlValue::Text(cutoff)],
+            )
+            .await
+    }
+}
+
+/// A dead letter held by one retry
+///
+/// Dropping it without `resolve` or `record_retry_failure` (say, when the
+/// client goes away mid-retry) releases the dead letter in the background.
+pub struct RetryClaim {
+    store: Arc<VibeStore>,
+    id: i64,
+    released: bool,
+}
+
+impl Drop for RetryClaim {
+    fn drop(&mut self) {
+        if self.released {
+            return;
+        }
+        let (store, id) = (Arc::clone(&self.store), self.id);
+        tokio::spawn(async move {
+            let released = store
+                .execute(
+                    "UPDATE vibe_dead_letters SET retrying = 0 WHERE id = ?".to_string(),
+                    vec![SqlValue::Integer(id)],
+                )
+                .await;
+            if let Err(e) = released {
+                warn!("⚠️ Could not release dead letter {}: {}", id, e);
+            }
+        });
+    }
+}
+
+/// Purges dead letters past `dead_letters.retention` every `tick`
+pub fn spawn_sweeper(state: AppState, tick: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tick);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            match state
+                .dead_letters
+                .purge_older_than(state.config.dead_letters.retention)
+                .await
+            {
+                Ok(0) => {}
+                Ok(purged) => info!("🧹 Purged {} expired dead letters", purged),
+                Err(e) => warn!("⚠️ Could not purge dead letters: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ManualClock;
+
+    #[tokio::test]
+    async fn test_purge_keeps_recent_dead_letters() {
+        let store = Arc::new(VibeStore::in_memory().await.unwrap());
+        let clock = ManualClock::new();
+        let letters = DeadLetterStore::new(Arc::clone(&store)).with_clock(Arc::new(clock.clone()));
+        let err = VibeError::InvalidPayload("nope".to_string());
+        letters
+            .record("orders", &serde_json::json!({"a": 1}), &err, &Origin::default())
+            .await
+            .unwrap();
+        clock.advance(Duration::from_secs(2 * 86_400));
+        letters
+            .record("orders", &serde_json::json!({"a": 2}), &err, &Origin::default())
+            .await
+            .unwrap();
+
+        let purged = letters.purge_older_than(Duration::from_secs(86_400)).await.unwrap();
+        assert_eq!(purged, 1);
+        let remaining = letters.list(&DeadLetterFilter::default()).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].payload, serde_json::json!({"a": 2}));
+        assert_eq!(remaining[0].error_code, "INVALID_PAYLOAD");
+    }
+
+    #[tokio::test]
+    async fn test_retries_claim_the_dead_letter() {
+        let store = Arc::new(VibeStore::in_memory().await.unwrap());
+        let letters = DeadLetterStore::new(store);
+        let err = VibeError::InvalidPayload("nope".to_string());
+        let id = letters
+            .record("orders", &serde_json::json!({"a": 1}), &err, &Origin::default())
+            .await
+            .unwrap();
+
+        let (letter, claim) = letters.begin_retry(id).await.unwrap();
+        assert_eq!(letter.attempts, 1);
+        let busy = letters.begin_retry(id).await.err().unwrap();
+        assert!(busy.to_string().contains("already being retried"));
+
+        // A failure releases the claim; so does dropping it
+        letters.record_retry_failure(claim, &err).await.unwrap();
+        let (_, claim) = letters.begin_retry(id).await.unwrap();
+        drop(claim);
+        let mut claim = None;
+        for _ in 0..50 {
+            if let Ok((_, held)) = letters.begin_retry(id).await {
+                claim = Some(held);
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+
+        let letter = letters.resolve(claim.expect("claim released"), 7).await.unwrap();
+        assert_eq!((letter.attempts, letter.resolved_id), (3, Some(7)));
+        let resolved = letters.begin_retry(id).await.err().unwrap();
+        assert!(resolved.to_string().contains("already resolved"));
+    }
+}
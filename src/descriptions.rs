@@ -0,0 +1,184 @@
+//! # Column Descriptions
+//!
+//! Free-text descriptions of columns, for when nobody remembers what
+//! `flag_x` means. SQLite has no `COMMENT`, so they live in
+//! `vibe_column_descriptions`, keyed by collection and column name, and are
+//! cached per collection. Anything that keeps a column's name keeps its
+//! description.
+//!
+//! Descriptions are returned by `GET /v1/tables/:collection`, exported as
+//! `description` in JSON Schema and shown in the Explorer.
+
+use crate::db::{SqlValue, VibeStore};
+use crate::error::{VibeError, VibeResult};
+
+use dashmap::DashMap;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+/// Longest description accepted, in characters
+pub const MAX_DESCRIPTION_LENGTH: usize = 2000;
+
+/// Descriptions of one collection's columns, by column name
+pub type ColumnDescriptions = BTreeMap<String, String>;
+
+/// Persistent, cached store of column descriptions
+pub struct DescriptionStore {
+    store: Arc<VibeStore>,
+    cache: DashMap<String, Arc<ColumnDescriptions>>,
+    initialized: OnceCell<()>,
+}
+
+impl DescriptionStore {
+    pub fn new(store: Arc<VibeStore>) -> Self {
+        Self {
+            store,
+            cache: DashMap::new(),
+            initialized: OnceCell::new(),
+        }
+    }
+
+    async fn ensure_table(&self) -> VibeResult<()> {
+        self.initialized
+            .get_or_try_init(|| async {
+                self.store
+                    .execute_batch(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS vibe_column_descriptions (
+                            collection TEXT NOT NULL,
+                            column_name TEXT NOT NULL COLLATE NOCASE,
+                            description TEXT NOT NULL,
+                            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                            PRIMARY KEY (collection, column_name)
+                        );
+                        "#
+                        .to_string(),
+                    )
+                    .await
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Descriptions of a collection's columns
+    pub async fn get(&self, collection: &str) -> VibeResult<Arc<ColumnDescriptions>> {
+        if let Some(descriptions) = self.cache.get(collection) {
+            return Ok(Arc::clone(&descriptions));
+        }
+
+        self.ensure_table().await?;
+        let rows = self
+            .store
+            .query(
+                "SELECT column_name, description FROM vibe_column_descriptions WHERE collection = ?".to_string(),
+                vec![SqlValue::Text(collection.to_string())],
+            )
+            .await?;
+        let descriptions: ColumnDescriptions = rows
+            .into_iter()
+            .filter_map(|row| {
+                let mut values = row.into_iter().map(|(_, v)| v);
+                let column = values.next()?.as_str()?.to_string();
+                let description = values.next()?.as_str()?.to_string();
+                Some((column, description))
+            })
+            .collect();
+
+        let descriptions = Arc::new(descriptions);
+        self.cache
+            .insert(collection.to_string(), Arc::clone(&descriptions));
+        Ok(descriptions)
+    }
+
+    /// Sets or, for `None` and blank text, removes the descriptions of the
+    /// given columns in one transaction
+    ///
+    /// Column names are taken as given; callers check that they exist.
+    pub async fn set(
+        &self,
+        collection: &str,
+        changes: BTreeMap<String, Option<String>>,
+    ) -> VibeResult<Arc<ColumnDescriptions>> {
+        let changes: Vec<(String, Option<String>)> = changes
+            .into_iter()
+            .map(|(column, text)| {
+                let text = text.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+                (column, text)
+            })
+            .collect();
+        let too_long: Vec<String> = changes
+            .iter()
+            .filter(|(_, text)| text.as_ref().is_some_and(|t| t.chars().count() > MAX_DESCRIPTION_LENGTH))
+            .map(|(column, _)| column.clone())
+            .collect();
+        if !too_long.is_empty() {
+            return Err(VibeError::InvalidFields {
+                message: format!(
+                    "Descriptions are limited to {} characters",
+                    MAX_DESCRIPTION_LENGTH
+                ),
+                fields: too_long,
+            });
+        }
+
+        self.ensure_table().await?;
+        let owner = collection.to_string();
+        self.store
+            .with_transaction(move |conn| {
+                for (column, text) in &changes {
+                    match text {
+                        Some(text) => conn.execute(
+                            "INSERT INTO vibe_column_descriptions (collection, column_name, description) \
+                             VALUES (?1, ?2, ?3) ON CONFLICT(collection, column_name) DO UPDATE SET \
+                             column_name = excluded.column_name, description = excluded.description, \
+                             updated_at = CURRENT_TIMESTAMP",
+                            rusqlite::params![owner, column, text],
+                        )?,
+                        None => conn.execute(
+                            "DELETE FROM vibe_column_descriptions WHERE collection = ?1 AND column_name = ?2",
+                            rusqlite::params![owner, column],
+                        )?,
+                    };
+                }
+                Ok(())
+            })
+            .await?;
+
+        self.cache.remove(collection);
+        self.get(collection).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_set_replaces_and_removes_descriptions() {
+        let store = DescriptionStore::new(Arc::new(VibeStore::in_memory().await.unwrap()));
+        let set = |pairs: &[(&str, Option<&str>)]| {
+            pairs
+                .iter()
+                .map(|(column, text)| (column.to_string(), text.map(str::to_string)))
+                .collect::<BTreeMap<_, _>>()
+        };
+
+        store
+            .set("orders", set(&[("flag_x", Some("Fraud review pending")), ("total", Some("Cents"))]))
+            .await
+            .unwrap();
+        // Column names are case-insensitive, as in SQLite
+        let descriptions = store
+            .set("orders", set(&[("FLAG_X", Some("  Manual review  ")), ("total", None)]))
+            .await
+            .unwrap();
+        assert_eq!(descriptions.len(), 1);
+        assert_eq!(descriptions["FLAG_X"], "Manual review");
+        assert!(store.get("customers").await.unwrap().is_empty());
+
+        let long = "x".repeat(MAX_DESCRIPTION_LENGTH + 1);
+        let err = store.set("orders", set(&[("total", Some(long.as_str()))])).await.unwrap_err();
+        assert_eq!(err.details().unwrap()["fields"], serde_json::json!(["total"]));
+    }
+}
@@ -0,0 +1,447 @@
+//! # Erasure
+//!
+//! Erases a data subject's rows across collections, for GDPR-style
+//! requests: every row where any of the `match` fields equals one of the
+//! given values is deleted, or has its `anonymize_fields` nulled or
+//! replaced by a salted hash. Collections lacking all of the match columns
+//! (or, when anonymizing, all of the fields to anonymize) are skipped and
+//! reported rather than failing the request.
+//!
+//! Rows are processed in batches of [`BATCH_SIZE`], one transaction each,
+//! so a large erasure never holds the write lock for long; a failed batch
+//! leaves earlier batches erased, and the request can simply be rerun.
+//! The audit history and cached copies of erased rows go with them, since
+//! they hold the same data.
+//!
+//! Every run, dry or not, produces an [`ErasureReport`] that is logged to
+//! the security log and returned. With auth enabled the report is signed
+//! with a key derived from the JWT secret (see `AuthService::sign_report`)
+//! over its JSON without `signature`, keys sorted, so it can later be shown
+//! to come from this server.
+
+use crate::api::AppState;
+use crate::audit::{self, now_timestamp};
+use crate::auth::AuthUser;
+use crate::db::{json_to_sql_value, SqlValue};
+use crate::error::{VibeError, VibeResult};
+use crate::guard::SchemaGuard;
+
+use rand::RngCore;
+use ring::digest;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use tracing::{debug, info};
+
+/// Rows erased per transaction
+pub const BATCH_SIZE: usize = 500;
+
+/// Prefix of hashed values, so they are recognizable as erased
+pub const HASH_PREFIX: &str = "erased:";
+
+/// What happens to matching rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErasureMode {
+    Delete,
+    Anonymize,
+}
+
+/// What anonymized fields are replaced with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Replacement {
+    #[default]
+    Null,
+    /// `erased:` and the hex SHA-256 of a per-request salt and the value;
+    /// equal values stay equal within one erasure only
+    Hash,
+}
+
+/// Collections to search: `"*"` for every user collection, or a list
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum CollectionSelection {
+    All(String),
+    Named(Vec<String>),
+}
+
+/// Body of `POST /v1/admin/erasure`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ErasureRequest {
+    /// Field to the value (or values) identifying the subject
+    #[serde(rename = "match")]
+    pub match_fields: BTreeMap<String, Value>,
+    pub collections: CollectionSelection,
+    pub mode: ErasureMode,
+    /// Fields to clear in `anonymize` mode
+    #[serde(default)]
+    pub anonymize_fields: Vec<String>,
+    #[serde(default)]
+    pub anonymize_with: Replacement,
+    /// Count matching rows without changing anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Outcome of an erasure in one collection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollectionErasure {
+    pub collection: String,
+    /// Rows matching the request
+    pub matched: u64,
+    /// Rows deleted or anonymized; 0 for dry runs
+    pub erased: u64,
+    /// Audit entries of erased rows removed
+    pub history_purged: u64,
+    /// Why the collection was left alone
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<String>,
+}
+
+/// Record of an erasure, signed when auth is enabled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErasureReport {
+    pub id: String,
+    pub mode: ErasureMode,
+    pub dry_run: bool,
+    /// `{id, email}` of the admin, or null without auth
+    pub actor: Value,
+    /// The match fields, without their values
+    pub fields: Vec<String>,
+    pub collections: Vec<CollectionErasure>,
+    pub matched: u64,
+    pub erased: u64,
+    pub at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl ErasureReport {
+    /// What the signature covers: the report's JSON without `signature`
+    pub fn signed_content(&self) -> String {
+        let mut value = serde_json::to_value(self).unwrap_or(Value::Null);
+        if let Value::Object(fields) = &mut value {
+            fields.remove("signature");
+        }
+        value.to_string()
+    }
+}
+
+/// A collection's resolved columns for one erasure
+struct Plan {
+    condition: String,
+    params: Vec<SqlValue>,
+    anonymize: Vec<String>,
+}
+
+/// Runs an erasure and returns its report
+pub async fn erase(
+    state: &AppState,
+    request: ErasureRequest,
+    caller: Option<&AuthUser>,
+) -> VibeResult<ErasureReport> {
+    let values = match_values(&request.match_fields)?;
+    if request.mode == ErasureMode::Anonymize && request.anonymize_fields.is_empty() {
+        return Err(VibeError::InvalidPayload(
+            "'anonymize_fields' is required in anonymize mode".to_string(),
+        ));
+    }
+    for field in &request.anonymize_fields {
+        SchemaGuard::validate_identifier(field)?;
+    }
+
+    let collections = match &request.collections {
+        CollectionSelection::All(all) if all == "*" => state
+            .store
+            .list_tables()
+            .await?
+            .into_iter()
+            .filter(|t| !t.starts_with("vibe_"))
+            .collect(),
+        CollectionSelection::All(other) => {
+            return Err(VibeError::InvalidPayload(format!(
+                "'collections' must be \"*\" or a list, got \"{}\"",
+                other
+            )))
+        }
+        CollectionSelection::Named(names) => names.clone(),
+    };
+
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+
+    let mut results = Vec::with_capacity(collections.len());
+    for collection in collections {
+        let plan = match plan(state, &collection, &values, &request).await? {
+            Ok(plan) => plan,
+            Err(reason) => {
+                results.push(CollectionErasure {
+                    collection,
+                    matched: 0,
+                    erased: 0,
+                    history_purged: 0,
+                    skipped: Some(reason),
+                });
+                continue;
+            }
+        };
+        results.push(run(state, collection, plan, &request, salt).await?);
+    }
+
+    let mut report = ErasureReport {
+        id: uuid::Uuid::new_v4().to_string(),
+        mode: request.mode,
+        dry_run: request.dry_run,
+        actor: AuthUser::actor(caller),
+        fields: request.match_fields.keys().cloned().collect(),
+        matched: results.iter().map(|c| c.matched).sum(),
+        erased: results.iter().map(|c| c.erased).sum(),
+        collections: results,
+        at: now_timestamp(),
+        signature: None,
+    };
+    if let Some(auth) = &state.auth {
+        report.signature = Some(auth.sign_report(&report.signed_content()));
+    }
+
+    info!(
+        target: "vibedb::security",
+        "Erasure {} by {}: {}",
+        report.id, AuthUser::label(caller), serde_json::to_string(&report).unwrap_or_default()
+    );
+    for collection in report.collections.iter().filter(|c| c.erased > 0) {
+        state.broadcast(
+            &collection.collection,
+            json!({
+                "event": "erasure",
+                "mode": report.mode,
+                "count": collection.erased,
+                "actor": report.actor
+            }),
+        );
+    }
+    Ok(report)
+}
+
+/// Match values by field, flattening arrays
+fn match_values(match_fields: &BTreeMap<String, Value>) -> VibeResult<BTreeMap<String, Vec<SqlValue>>> {
+    if match_fields.is_empty() {
+        return Err(VibeError::InvalidPayload("'match' must name at least one field".to_string()));
+    }
+
+    let mut values = BTreeMap::new();
+    let mut invalid = Vec::new();
+    for (field, value) in match_fields {
+        SchemaGuard::validate_identifier(field)?;
+        let items: Vec<&Value> = match value {
+            Value::Array(items) => items.iter().collect(),
+            other => vec![other],
+        };
+        if items.is_empty() || items.iter().any(|v| v.is_null() || v.is_object() || v.is_array()) {
+            invalid.push(field.clone());
+            continue;
+        }
+        values.insert(field.clone(), items.into_iter().map(json_to_sql_value).collect());
+    }
+    if !invalid.is_empty() {
+        return Err(VibeError::InvalidFields {
+            message: "Match values must be non-null scalars or non-empty lists of them".to_string(),
+            fields: invalid,
+        });
+    }
+    Ok(values)
+}
+
+/// Resolves the columns of one collection, or why it is skipped
+async fn plan(
+    state: &AppState,
+    collection: &str,
+    values: &BTreeMap<String, Vec<SqlValue>>,
+    request: &ErasureRequest,
+) -> VibeResult<Result<Plan, String>> {
+    SchemaGuard::validate_identifier(collection)?;
+    let table = state.guard.resolve_table(collection, false).await?;
+    let stats = match state.guard.get_table_stats(&table).await {
+        Ok(stats) => stats,
+        Err(VibeError::TableNotFound(_)) => return Ok(Err("collection not found".to_string())),
+        Err(e) => return Err(e),
+    };
+    let column = |name: &str, writable: bool| {
+        stats
+            .columns
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name) && !(writable && (c.generated || c.pk)))
+            .map(|c| c.name.clone())
+    };
+
+    let mut conditions = Vec::new();
+    let mut params = Vec::new();
+    for (field, field_values) in values {
+        let Some(name) = column(&state.guard.resolve_column(&table, field).await?, false) else {
+            continue;
+        };
+        conditions.push(format!(
+            "{} IN ({})",
+            name,
+            vec!["?"; field_values.len()].join(", ")
+        ));
+        params.extend(field_values.iter().cloned());
+    }
+    if conditions.is_empty() {
+        return Ok(Err("no match columns".to_string()));
+    }
+
+    let mut anonymize = Vec::new();
+    if request.mode == ErasureMode::Anonymize {
+        for field in &request.anonymize_fields {
+            if let Some(name) = column(&state.guard.resolve_column(&table, field).await?, true) {
+                anonymize.push(name);
+            }
+        }
+        if anonymize.is_empty() {
+            return Ok(Err("no fields to anonymize".to_string()));
+        }
+    }
+
+    Ok(Ok(Plan {
+        condition: format!("({})", conditions.join(" OR ")),
+        params,
+        anonymize,
+    }))
+}
+
+/// Erases the matching rows of one collection, batch by batch
+async fn run(
+    state: &AppState,
+    collection: String,
+    plan: Plan,
+    request: &ErasureRequest,
+    salt: [u8; 16],
+) -> VibeResult<CollectionErasure> {
+    let table = state.guard.resolve_table(&collection, false).await?;
+    let rows = state
+        .store
+        .query(
+            format!("SELECT COUNT(*) FROM {} WHERE {}", table, plan.condition),
+            plan.params.clone(),
+        )
+        .await?;
+    let matched = rows
+        .first()
+        .and_then(|row| row.first())
+        .and_then(|(_, v)| v.as_u64())
+        .unwrap_or(0);
+
+    let mut result = CollectionErasure {
+        collection,
+        matched,
+        erased: 0,
+        history_purged: 0,
+        skipped: None,
+    };
+    if request.dry_run || matched == 0 {
+        return Ok(result);
+    }
+
+    audit::ensure_table(&state.store).await?;
+    // Anonymized rows may keep matching, so batches walk the ids upwards
+    let select_ids = format!(
+        "SELECT id FROM {} WHERE {} AND id > ? ORDER BY id LIMIT {}",
+        table, plan.condition, BATCH_SIZE
+    );
+    let mode = request.mode;
+    let replacement = request.anonymize_with;
+    let cached_as = table.clone();
+    let mut after = 0i64;
+    loop {
+        let select_ids = select_ids.clone();
+        let mut params = plan.params.clone();
+        params.push(SqlValue::Integer(after));
+        let table = table.clone();
+        let owner = result.collection.clone();
+        let anonymize = plan.anonymize.clone();
+
+        let batch = state
+            .store
+            .with_transaction(move |conn| {
+                let ids: Vec<i64> = {
+                    let mut stmt = conn.prepare(&select_ids)?;
+                    let rows = stmt.query_map(rusqlite::params_from_iter(params.iter()), |row| row.get(0))?;
+                    rows.collect::<Result<_, _>>()?
+                };
+                if ids.is_empty() {
+                    return Ok((ids, 0));
+                }
+
+                let id_list = ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ");
+                match (mode, replacement) {
+                    (ErasureMode::Delete, _) => {
+                        conn.execute(&format!("DELETE FROM {} WHERE id IN ({})", table, id_list), [])?;
+                    }
+                    (ErasureMode::Anonymize, Replacement::Null) => {
+                        let assignments = anonymize
+                            .iter()
+                            .map(|c| format!("{} = NULL", c))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        conn.execute(
+                            &format!("UPDATE {} SET {} WHERE id IN ({})", table, assignments, id_list),
+                            [],
+                        )?;
+                    }
+                    (ErasureMode::Anonymize, Replacement::Hash) => {
+                        for column in &anonymize {
+                            let values: Vec<(i64, Option<String>)> = {
+                                let mut stmt = conn.prepare(&format!(
+                                    "SELECT id, CAST({} AS TEXT) FROM {} WHERE id IN ({})",
+                                    column, table, id_list
+                                ))?;
+                                let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                                rows.collect::<Result<_, _>>()?
+                            };
+                            let mut update =
+                                conn.prepare(&format!("UPDATE {} SET {} = ?1 WHERE id = ?2", table, column))?;
+                            for (id, value) in values {
+                                let hashed = value.map(|v| hash_value(&salt, &v));
+                                update.execute(rusqlite::params![hashed, id])?;
+                            }
+                        }
+                    }
+                }
+                let purged = conn.execute(
+                    &format!("DELETE FROM vibe_audit WHERE collection = ?1 AND row_id IN ({})", id_list),
+                    [&owner],
+                )?;
+                Ok((ids, purged as u64))
+            })
+            .await;
+        // Committed batches are gone even if this one failed
+        state.row_cache.invalidate_collection(&cached_as);
+        state.row_counts.forget(&cached_as);
+        let (ids, purged) = batch?;
+
+        let Some(&last) = ids.last() else {
+            break;
+        };
+        after = last;
+        result.erased += ids.len() as u64;
+        result.history_purged += purged;
+        debug!("Erased {} rows of {} (through id {})", ids.len(), result.collection, last);
+    }
+    Ok(result)
+}
+
+/// Salted SHA-256 of a value, hex encoded and prefixed with [`HASH_PREFIX`]
+fn hash_value(salt: &[u8], value: &str) -> String {
+    let mut context = digest::Context::new(&digest::SHA256);
+    context.update(salt);
+    context.update(value.as_bytes());
+    let hex: String = context
+        .finish()
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("{}{}", HASH_PREFIX, hex)
+}
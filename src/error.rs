@@ -8,9 +8,20 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
+use axum::http::{header, HeaderValue};
 use serde_json::json;
+use std::time::Duration;
 use thiserror::Error;
 
+/// SQLite's message for calls to functions missing from the build
+const NO_SUCH_FUNCTION: &str = "no such function";
+
+/// What SQLite puts before the constraint name in constraint errors
+const CONSTRAINT_FAILED: &str = "constraint failed: ";
+
+/// Suggested wait after a write gave up on a busy or locked database
+const BUSY_RETRY_AFTER: Duration = Duration::from_millis(250);
+
 /// Result type alias for VibeDB operations
 pub type VibeResult<T> = Result<T, VibeError>;
 
@@ -21,6 +32,19 @@ pub enum VibeError {
     #[error("Database error: {0}")]
     Database(String),
 
+    /// SQLite failure with its primary and extended result codes preserved
+    ///
+    /// For constraint violations, `constraint` is what SQLite names as the
+    /// failed constraint (`table.column` for UNIQUE and NOT NULL, the
+    /// constraint name for CHECK).
+    #[error("Database error: {message}")]
+    Sqlite {
+        code: rusqlite::ErrorCode,
+        extended_code: i32,
+        message: String,
+        constraint: Option<String>,
+    },
+
     /// JSON parsing or serialization errors
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
@@ -34,8 +58,16 @@ pub enum VibeError {
     Schema(String),
 
     /// Column limit exceeded (max 1000 per table)
-    #[error("Column limit exceeded: {message}")]
-    ColumnLimitExceeded { message: String },
+    #[error(
+        "Column limit exceeded: table '{table}' would exceed the {limit} column limit ({existing} existing + {} new)",
+        .new_columns.len()
+    )]
+    ColumnLimitExceeded {
+        table: String,
+        limit: usize,
+        existing: usize,
+        new_columns: Vec<String>,
+    },
 
     /// Table not found
     #[error("Table not found: {0}")]
@@ -45,6 +77,10 @@ pub enum VibeError {
     #[error("Invalid payload: {0}")]
     InvalidPayload(String),
 
+    /// Invalid payload, blamed on specific fields
+    #[error("Invalid payload: {message}")]
+    InvalidFields { message: String, fields: Vec<String> },
+
     /// Migration error
     #[error("Migration failed: {0}")]
     MigrationFailed(String),
@@ -67,9 +103,69 @@ pub enum VibeError {
     #[error("Not found: {0}")]
     NotFound(String),
 
-    /// Storage error
+    /// Operation exceeded its time budget of `timeout_ms`
+    #[error("Timeout: {operation} exceeded the {timeout_ms}ms limit")]
+    Timeout { operation: String, timeout_ms: u64 },
+
+    /// Caller is sending requests faster than allowed
+    #[error("Rate limited: {message}")]
+    RateLimited { message: String, retry_after: Duration },
+
+    /// The `class` of requests already has `limit` in flight (see `limits`)
+    #[error("Overloaded: {limit} {class} requests are already in flight")]
+    Overloaded {
+        class: &'static str,
+        limit: usize,
+        retry_after: Duration,
+    },
+
+    /// Operation not permitted for this caller or by policy
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    /// Row is locked by `holder` until `expires_at` (see `locks`)
+    #[error("Locked: {message}")]
+    Locked {
+        message: String,
+        holder: serde_json::Value,
+        expires_at: String,
+    },
+
+    /// Storage error: reading or writing stored files failed
     #[error("Storage error: {0}")]
     Storage(String),
+
+    /// An uploaded file, or all files of one request, went past `limit`
+    /// bytes
+    #[error("Too large: {message}")]
+    FileTooLarge { message: String, limit: u64 },
+
+    /// An object path that can't be stored under (see
+    /// `StorageService::validate_object_path`)
+    #[error("Invalid object path: {0}")]
+    InvalidObjectPath(String),
+
+    /// An upload would take `scope` (`user` or `global`) storage past its
+    /// quota of `limit` bytes, with `used` bytes already stored
+    #[error(
+        "Quota exceeded: {requested} more bytes would take {scope} storage past its {limit} byte quota ({used} in use)"
+    )]
+    QuotaExceeded {
+        scope: &'static str,
+        used: u64,
+        requested: u64,
+        limit: u64,
+    },
+
+    /// A data URL in `field` couldn't be stored as a file (see `inline_files`)
+    #[error("Invalid file in '{field}': {message}")]
+    InvalidFile { field: String, message: String },
+
+    /// Failure of one item of a batch, at `index` in the request
+    ///
+    /// Reported with the status and code of the underlying error.
+    #[error("Item {index}: {source}")]
+    BatchItem { index: usize, source: Box<VibeError> },
 }
 
 impl VibeError {
@@ -77,18 +173,35 @@ impl VibeError {
     pub fn status_code(&self) -> StatusCode {
         match self {
             VibeError::Database(_) => StatusCode::SERVICE_UNAVAILABLE,
+            VibeError::Sqlite { code, message, .. } => match code {
+                rusqlite::ErrorCode::DatabaseLocked => StatusCode::LOCKED,
+                rusqlite::ErrorCode::ConstraintViolation => StatusCode::CONFLICT,
+                _ if message.contains(NO_SUCH_FUNCTION) => StatusCode::BAD_REQUEST,
+                _ => StatusCode::SERVICE_UNAVAILABLE,
+            },
             VibeError::Json(_) => StatusCode::BAD_REQUEST,
             VibeError::InvalidIdentifier(_) => StatusCode::BAD_REQUEST,
             VibeError::Schema(_) => StatusCode::UNPROCESSABLE_ENTITY,
             VibeError::ColumnLimitExceeded { .. } => StatusCode::BAD_REQUEST,
             VibeError::TableNotFound(_) => StatusCode::NOT_FOUND,
             VibeError::InvalidPayload(_) => StatusCode::BAD_REQUEST,
+            VibeError::InvalidFields { .. } => StatusCode::BAD_REQUEST,
             VibeError::MigrationFailed(_) => StatusCode::INTERNAL_SERVER_ERROR,
             VibeError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
             VibeError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
             VibeError::Conflict(_) => StatusCode::CONFLICT,
             VibeError::NotFound(_) => StatusCode::NOT_FOUND,
+            VibeError::Timeout { .. } => StatusCode::GATEWAY_TIMEOUT,
+            VibeError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            VibeError::Overloaded { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            VibeError::Forbidden(_) => StatusCode::FORBIDDEN,
+            VibeError::Locked { .. } => StatusCode::LOCKED,
             VibeError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            VibeError::FileTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            VibeError::InvalidObjectPath(_) => StatusCode::BAD_REQUEST,
+            VibeError::QuotaExceeded { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            VibeError::InvalidFile { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            VibeError::BatchItem { source, .. } => source.status_code(),
         }
     }
 
@@ -96,48 +209,333 @@ impl VibeError {
     pub fn error_code(&self) -> &'static str {
         match self {
             VibeError::Database(_) => "DATABASE_ERROR",
+            VibeError::Sqlite { code, message, .. } => match code {
+                _ if message.contains(NO_SUCH_FUNCTION) => "UNSUPPORTED_FUNCTION",
+                rusqlite::ErrorCode::DatabaseBusy => "DATABASE_BUSY",
+                rusqlite::ErrorCode::DatabaseLocked => "DATABASE_LOCKED",
+                rusqlite::ErrorCode::ConstraintViolation => "CONSTRAINT_VIOLATION",
+                rusqlite::ErrorCode::ReadOnly => "DATABASE_READONLY",
+                _ => "DATABASE_ERROR",
+            },
             VibeError::Json(_) => "JSON_ERROR",
             VibeError::InvalidIdentifier(_) => "INVALID_IDENTIFIER",
             VibeError::Schema(_) => "SCHEMA_ERROR",
             VibeError::ColumnLimitExceeded { .. } => "COLUMN_LIMIT_EXCEEDED",
             VibeError::TableNotFound(_) => "TABLE_NOT_FOUND",
-            VibeError::InvalidPayload(_) => "INVALID_PAYLOAD",
+            VibeError::InvalidPayload(_) | VibeError::InvalidFields { .. } => "INVALID_PAYLOAD",
             VibeError::MigrationFailed(_) => "MIGRATION_FAILED",
             VibeError::Internal(_) => "INTERNAL_ERROR",
             VibeError::Unauthorized(_) => "UNAUTHORIZED",
             VibeError::Conflict(_) => "CONFLICT",
             VibeError::NotFound(_) => "NOT_FOUND",
+            VibeError::Timeout { .. } => "TIMEOUT",
+            VibeError::RateLimited { .. } => "RATE_LIMITED",
+            VibeError::Overloaded { .. } => "OVERLOADED",
+            VibeError::Forbidden(_) => "FORBIDDEN",
+            VibeError::Locked { .. } => "LOCKED",
             VibeError::Storage(_) => "STORAGE_ERROR",
+            VibeError::FileTooLarge { .. } => "FILE_TOO_LARGE",
+            VibeError::InvalidObjectPath(_) => "INVALID_PATH",
+            VibeError::QuotaExceeded { .. } => "QUOTA_EXCEEDED",
+            VibeError::InvalidFile { .. } => "INVALID_FILE",
+            VibeError::BatchItem { source, .. } => source.error_code(),
         }
     }
+
+    /// Whether the same request may succeed if sent again unchanged
+    pub fn retryable(&self) -> bool {
+        match self {
+            VibeError::Database(_)
+            | VibeError::Timeout { .. }
+            | VibeError::RateLimited { .. }
+            | VibeError::Overloaded { .. } => true,
+            VibeError::Sqlite { code, .. } => matches!(
+                code,
+                rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked
+            ),
+            VibeError::BatchItem { source, .. } => source.retryable(),
+            _ => false,
+        }
+    }
+
+    /// How long to wait before retrying, when there is a useful hint
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            VibeError::RateLimited { retry_after, .. } | VibeError::Overloaded { retry_after, .. } => {
+                Some(*retry_after)
+            }
+            VibeError::Sqlite { .. } if self.retryable() => Some(BUSY_RETRY_AFTER),
+            VibeError::BatchItem { source, .. } => source.retry_after(),
+            _ => None,
+        }
+    }
+
+    /// Attributes this error to item `index` of a batch request
+    pub fn at_index(self, index: usize) -> Self {
+        VibeError::BatchItem {
+            index,
+            source: Box::new(self),
+        }
+    }
+
+    /// Structured extras included in the error body, if any
+    pub fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            VibeError::Sqlite {
+                code,
+                extended_code,
+                constraint,
+                ..
+            } => {
+                let mut details = json!({
+                    "sqlite_code": format!("{:?}", code),
+                    "sqlite_extended_code": extended_code,
+                });
+                if let Some(constraint) = constraint {
+                    details["constraint"] = json!(constraint);
+                }
+                if let Some(kind) = constraint_type(*extended_code) {
+                    details["constraint_type"] = json!(kind);
+                }
+                Some(details)
+            }
+            VibeError::ColumnLimitExceeded {
+                table,
+                limit,
+                existing,
+                new_columns,
+            } => Some(json!({
+                "table": table,
+                "limit": limit,
+                "existing": existing,
+                "new_columns": new_columns,
+            })),
+            VibeError::InvalidFields { fields, .. } => Some(json!({ "fields": fields })),
+            VibeError::QuotaExceeded {
+                scope,
+                used,
+                requested,
+                limit,
+            } => Some(json!({
+                "scope": scope,
+                "used": used,
+                "requested": requested,
+                "limit": limit,
+            })),
+            VibeError::FileTooLarge { limit, .. } => Some(json!({ "limit": limit })),
+            VibeError::InvalidFile { field, .. } => Some(json!({ "field": field })),
+            VibeError::Locked { holder, expires_at, .. } => Some(json!({
+                "holder": holder,
+                "expires_at": expires_at,
+            })),
+            VibeError::Overloaded { class, limit, .. } => Some(json!({
+                "class": class,
+                "limit": limit,
+            })),
+            VibeError::Timeout { operation, timeout_ms } => Some(json!({
+                "operation": operation,
+                "timeout_ms": timeout_ms,
+            })),
+            VibeError::BatchItem { index, source } => {
+                let mut details = source.details().unwrap_or_else(|| json!({}));
+                details["index"] = json!(index);
+                Some(details)
+            }
+            _ => None,
+        }
+    }
+
+    /// Builds a database error, preserving SQLite result codes when available
+    pub fn from_sqlite(context: &str, err: &rusqlite::Error) -> Self {
+        let mut message = if context.is_empty() {
+            err.to_string()
+        } else {
+            format!("{}: {}", context, err)
+        };
+        if message.contains(NO_SUCH_FUNCTION) {
+            message.push_str(" (see GET /v1/capabilities for the features of this SQLite build)");
+        }
+
+        match err {
+            rusqlite::Error::SqliteFailure(failure, _)
+            | rusqlite::Error::SqlInputError { error: failure, .. } => VibeError::Sqlite {
+                code: failure.code,
+                extended_code: failure.extended_code,
+                constraint: (failure.code == rusqlite::ErrorCode::ConstraintViolation)
+                    .then(|| failure_constraint(&message))
+                    .flatten(),
+                message,
+            },
+            _ => VibeError::Database(message),
+        }
+    }
+
+    /// Builds a database error from a connection-level failure
+    pub fn from_connection(context: &str, err: tokio_rusqlite::Error) -> Self {
+        match &err {
+            tokio_rusqlite::Error::Rusqlite(e) => Self::from_sqlite(context, e),
+            _ => VibeError::Database(format!("{}: {}", context, err)),
+        }
+    }
+}
+
+/// The constraint named in a SQLite constraint error message, if any
+fn failure_constraint(message: &str) -> Option<String> {
+    let (_, constraint) = message.split_once(CONSTRAINT_FAILED)?;
+    let constraint = constraint.trim();
+    (!constraint.is_empty()).then(|| constraint.to_string())
+}
+
+/// The kind of constraint behind an extended SQLite result code
+fn constraint_type(extended_code: i32) -> Option<&'static str> {
+    use rusqlite::ffi;
+    match extended_code {
+        ffi::SQLITE_CONSTRAINT_UNIQUE => Some("unique"),
+        ffi::SQLITE_CONSTRAINT_PRIMARYKEY => Some("primary_key"),
+        ffi::SQLITE_CONSTRAINT_NOTNULL => Some("not_null"),
+        ffi::SQLITE_CONSTRAINT_CHECK => Some("check"),
+        ffi::SQLITE_CONSTRAINT_FOREIGNKEY => Some("foreign_key"),
+        _ => None,
+    }
 }
 
 /// Converts VibeError into an Axum HTTP response
+///
+/// `retryable` is always present; `retry_after_ms` (and a `Retry-After`
+/// header, in whole seconds) only when there is a hint.
 impl IntoResponse for VibeError {
     fn into_response(self) -> Response {
         let status = self.status_code();
+        let retry_after = self.retry_after();
+        let mut error = json!({
+            "code": self.error_code(),
+            "message": self.to_string(),
+            "retryable": self.retryable(),
+        });
+        if let Some(retry_after) = retry_after {
+            error["retry_after_ms"] = json!(retry_after.as_millis() as u64);
+        }
+        if let Some(details) = self.details() {
+            error["details"] = details;
+        }
         let body = Json(json!({
-            "error": {
-                "code": self.error_code(),
-                "message": self.to_string(),
-            },
+            "error": error,
             "success": false,
         }));
 
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        if let Some(retry_after) = retry_after {
+            let seconds = retry_after.as_millis().div_ceil(1000);
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(seconds as u64));
+        }
+        response
     }
 }
 
 /// Convert rusqlite errors to VibeError
 impl From<rusqlite::Error> for VibeError {
     fn from(err: rusqlite::Error) -> Self {
-        VibeError::Database(err.to_string())
+        VibeError::from_sqlite("", &err)
     }
 }
 
 /// Convert tokio-rusqlite errors to VibeError
 impl From<tokio_rusqlite::Error> for VibeError {
     fn from(err: tokio_rusqlite::Error) -> Self {
-        VibeError::Database(err.to_string())
+        VibeError::from_connection("", err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sqlite_error(code: std::os::raw::c_int) -> rusqlite::Error {
+        rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(code), None)
+    }
+
+    #[test]
+    fn test_sqlite_codes_map_to_statuses() {
+        let busy = VibeError::from_sqlite("Execute failed", &sqlite_error(rusqlite::ffi::SQLITE_BUSY));
+        assert_eq!(busy.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(busy.error_code(), "DATABASE_BUSY");
+        assert!(busy.retryable());
+        assert_eq!(busy.retry_after(), Some(BUSY_RETRY_AFTER));
+
+        let locked = VibeError::from_sqlite("", &sqlite_error(rusqlite::ffi::SQLITE_LOCKED));
+        assert_eq!(locked.status_code(), StatusCode::LOCKED);
+
+        let unique = VibeError::from_sqlite(
+            "",
+            &sqlite_error(rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE),
+        );
+        assert_eq!(unique.status_code(), StatusCode::CONFLICT);
+        assert_eq!(unique.error_code(), "CONSTRAINT_VIOLATION");
+        assert!(!unique.retryable());
+        let details = unique.details().unwrap();
+        assert_eq!(details["sqlite_code"], "ConstraintViolation");
+        assert_eq!(details["sqlite_extended_code"], rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE);
+    }
+
+    #[test]
+    fn test_storage_errors_map_to_statuses() {
+        let large = VibeError::FileTooLarge { message: "Files may be at most 10 bytes".to_string(), limit: 10 };
+        assert_eq!((large.status_code(), large.error_code()), (StatusCode::PAYLOAD_TOO_LARGE, "FILE_TOO_LARGE"));
+        assert_eq!(large.details().unwrap()["limit"], 10);
+        let path = VibeError::InvalidObjectPath("may not contain '..' or start with '/'".to_string());
+        assert_eq!((path.status_code(), path.error_code()), (StatusCode::BAD_REQUEST, "INVALID_PATH"));
+        let disk = VibeError::Storage("Failed to write file: disk full".to_string());
+        assert_eq!(disk.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_constraint_name_is_kept() {
+        let err = rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT_NOTNULL),
+            Some("NOT NULL constraint failed: items.sku".to_string()),
+        );
+        let details = VibeError::from_sqlite("Insert failed", &err).details().unwrap();
+        assert_eq!(details["constraint"], "items.sku");
+        assert_eq!(details["constraint_type"], "not_null");
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_response_carries_retry_hints() {
+        let err = VibeError::RateLimited {
+            message: "Too many pushes".to_string(),
+            retry_after: Duration::from_millis(1500),
+        };
+        let response = err.into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "2");
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "error": {
+                    "code": "RATE_LIMITED",
+                    "message": "Rate limited: Too many pushes",
+                    "retryable": true,
+                    "retry_after_ms": 1500,
+                },
+                "success": false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_missing_function_points_at_capabilities() {
+        let err = rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_ERROR),
+            Some("no such function: sqrtx".to_string()),
+        );
+        let err = VibeError::from_sqlite("Query failed", &err);
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.error_code(), "UNSUPPORTED_FUNCTION");
+        assert!(err.to_string().contains("/v1/capabilities"));
     }
 }
@@ -0,0 +1,285 @@
+//! # Array Explosion
+//!
+//! Stores selected JSON array fields as rows of a child collection instead
+//! of a JSON TEXT column. Configured per parent collection:
+//!
+//! ```json
+//! {"explode_arrays": {"items": "order_items"}}
+//! ```
+//!
+//! Pushing `{"order_no": 1, "items": [{"sku": "a"}, {"sku": "b"}]}` to
+//! `orders` inserts the order and two `order_items` rows carrying an
+//! `orders_id` column, all in one transaction. Elements that are not objects
+//! are stored as `{"value": ...}`. Queries with `?expand=items` nest the
+//! children back under their parent.
+
+use crate::audit::InsertAudit;
+use crate::db::{json_to_sql_value, SqlValue, VibeStore};
+use crate::error::{VibeError, VibeResult};
+use crate::guard::SchemaGuard;
+use crate::settings::{CollectionSettings, ColumnMask};
+
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+
+/// Columns managed by VibeDB, left out of inserts and expanded children
+const SYSTEM_COLUMNS: [&str; 3] = ["id", "created_at", "updated_at"];
+
+/// Key used to store array elements that are not objects
+const SCALAR_KEY: &str = "value";
+
+/// Name of the column linking child rows to their parent
+pub fn foreign_key_column(parent: &str) -> String {
+    format!("{}_id", parent)
+}
+
+/// Elements of one exploded array field
+#[derive(Debug, Clone)]
+pub struct ChildRows {
+    pub field: String,
+    pub collection: String,
+    pub foreign_key: String,
+    pub rows: Vec<Map<String, Value>>,
+    /// Set when the child collection is audited
+    pub audit: Option<InsertAudit>,
+}
+
+impl ChildRows {
+    /// Object covering every column of the child rows, for schema evolution
+    ///
+    /// The first non-null value of each key decides its type; the foreign
+    /// key is always an integer.
+    pub fn schema_probe(&self) -> Value {
+        let mut probe = Map::new();
+        for row in &self.rows {
+            for (key, value) in row {
+                if !value.is_null() && !probe.contains_key(key) {
+                    probe.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        probe.insert(self.foreign_key.clone(), json!(0));
+        Value::Object(probe)
+    }
+
+    /// Drops keys outside `columns`, the insertable set from
+    /// `SchemaGuard::ensure_columns`
+    pub fn retain_columns(&mut self, columns: &[String]) {
+        for row in &mut self.rows {
+            row.retain(|key, _| columns.contains(key));
+        }
+    }
+
+    /// INSERT statements whose first parameter is the parent id
+    fn inserts(&self) -> Vec<(String, Vec<SqlValue>)> {
+        self.rows
+            .iter()
+            .map(|row| {
+                let mut columns = vec![self.foreign_key.as_str()];
+                let mut params = Vec::new();
+                for (key, value) in row {
+                    if !value.is_null() && !SYSTEM_COLUMNS.contains(&key.as_str()) {
+                        columns.push(key);
+                        params.push(json_to_sql_value(value));
+                    }
+                }
+                let placeholders = vec!["?"; columns.len()].join(", ");
+                let sql = format!(
+                    "INSERT INTO {} ({}) VALUES ({})",
+                    self.collection,
+                    columns.join(", "),
+                    placeholders
+                );
+                (sql, params)
+            })
+            .collect()
+    }
+}
+
+/// Removes the configured array fields from a payload
+///
+/// Fields that are missing or not arrays stay on the parent untouched.
+pub fn split(parent: &str, settings: &CollectionSettings, payload: &mut Value) -> Vec<ChildRows> {
+    let Some(object) = payload.as_object_mut() else {
+        return Vec::new();
+    };
+
+    let mut children = Vec::new();
+    for (field, collection) in &settings.explode_arrays {
+        if !matches!(object.get(field), Some(Value::Array(_))) {
+            continue;
+        }
+        let Some(Value::Array(elements)) = object.remove(field) else {
+            continue;
+        };
+
+        let foreign_key = foreign_key_column(parent);
+        let rows = elements
+            .into_iter()
+            .map(|element| {
+                let mut row = match element {
+                    Value::Object(map) => map,
+                    other => Map::from_iter([(SCALAR_KEY.to_string(), other)]),
+                };
+                row.remove(&foreign_key);
+                row
+            })
+            .collect();
+
+        children.push(ChildRows {
+            field: field.clone(),
+            collection: collection.clone(),
+            foreign_key,
+            rows,
+            audit: None,
+        });
+    }
+    children
+}
+
+/// Inserts a parent row and its children in one transaction
+///
+/// Returns the parent id and the inserted child ids per child collection.
+/// Audit entries of the parent (with `parent_audit`) and of audited
+/// children commit with the rows. Busy retries are counted under `label`.
+pub async fn insert_with_children(
+    store: &VibeStore,
+    label: &str,
+    parent_sql: String,
+    parent_params: Vec<SqlValue>,
+    parent_audit: Option<InsertAudit>,
+    children: &[ChildRows],
+) -> VibeResult<(i64, Vec<(String, Vec<i64>)>)> {
+    let child_inserts: Vec<_> = children
+        .iter()
+        .map(|child| (child.collection.clone(), child.inserts(), child.audit.clone()))
+        .collect();
+
+    store
+        .with_transaction_labeled(label, move |conn| {
+            conn.execute(&parent_sql, rusqlite::params_from_iter(parent_params.iter()))?;
+            let parent_id = conn.last_insert_rowid();
+            if let Some(audit) = &parent_audit {
+                audit.record_in(conn, parent_id)?;
+            }
+
+            let mut inserted = Vec::with_capacity(child_inserts.len());
+            for (collection, inserts, audit) in child_inserts {
+                let mut ids = Vec::with_capacity(inserts.len());
+                for (sql, params) in inserts {
+                    let params = std::iter::once(SqlValue::Integer(parent_id)).chain(params);
+                    conn.execute(&sql, rusqlite::params_from_iter(params))?;
+                    let id = conn.last_insert_rowid();
+                    if let Some(audit) = &audit {
+                        audit.record_in(conn, id)?;
+                    }
+                    ids.push(id);
+                }
+                inserted.push((collection, ids));
+            }
+            Ok((parent_id, inserted))
+        })
+        .await
+}
+
+/// Nests the children of `field` under each parent row as an array
+///
+/// Children lose their system columns, foreign key and null values, so an
+/// exploded push reads back in its original shape.
+pub async fn expand(
+    store: &VibeStore,
+    parent: &str,
+    settings: &CollectionSettings,
+    rows: &mut [Value],
+    field: &str,
+    mask: &ColumnMask,
+) -> VibeResult<()> {
+    let child = settings.explode_arrays.get(field).ok_or_else(|| {
+        VibeError::InvalidPayload(format!("{} is not an exploded array of {}", field, parent))
+    })?;
+    let foreign_key = foreign_key_column(parent);
+
+    let ids: Vec<i64> = rows
+        .iter()
+        .filter_map(|row| row.get("id").and_then(Value::as_i64))
+        .collect();
+    let mut grouped: HashMap<i64, Vec<Value>> = HashMap::new();
+
+    // Nothing has been exploded into a child collection that doesn't exist yet
+    if !ids.is_empty() && store.list_tables().await?.contains(child) {
+        let sql = format!(
+            "SELECT * FROM {} WHERE {} IN ({}) ORDER BY id",
+            child,
+            foreign_key,
+            vec!["?"; ids.len()].join(", ")
+        );
+        let params = ids.iter().map(|id| SqlValue::Integer(*id)).collect();
+
+        for row in store.query(sql, params).await? {
+            let mut object: Map<String, Value> = row.into_iter().collect();
+            let Some(parent_id) = object.remove(&foreign_key).and_then(|v| v.as_i64()) else {
+                continue;
+            };
+            object.retain(|key, value| !value.is_null() && !SYSTEM_COLUMNS.contains(&key.as_str()));
+            mask.apply(&mut object);
+
+            let element = match object.len() {
+                1 if object.contains_key(SCALAR_KEY) => object.remove(SCALAR_KEY).unwrap_or_default(),
+                _ => Value::Object(object),
+            };
+            grouped.entry(parent_id).or_default().push(element);
+        }
+    }
+
+    for row in rows.iter_mut() {
+        let children = row
+            .get("id")
+            .and_then(Value::as_i64)
+            .and_then(|id| grouped.remove(&id))
+            .unwrap_or_default();
+        if let Value::Object(object) = row {
+            object.insert(field.to_string(), Value::Array(children));
+        }
+    }
+    Ok(())
+}
+
+/// Validates an `explode_arrays` mapping
+pub fn validate_mapping(mapping: &BTreeMap<String, String>) -> VibeResult<()> {
+    for (field, child) in mapping {
+        SchemaGuard::validate_identifier(field)?;
+        SchemaGuard::validate_identifier(child)?;
+        if SYSTEM_COLUMNS.contains(&field.as_str()) {
+            return Err(VibeError::InvalidPayload(format!(
+                "System column {} cannot be exploded",
+                field
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_wraps_scalars() {
+        let settings = CollectionSettings {
+            explode_arrays: BTreeMap::from([
+                ("items".to_string(), "order_items".to_string()),
+                ("tags".to_string(), "order_tags".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let mut payload = json!({"order_no": 1, "items": [{"sku": "a", "orders_id": 9}], "tags": ["x"]});
+
+        let children = split("orders", &settings, &mut payload);
+        assert_eq!(payload, json!({"order_no": 1}));
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0].foreign_key, "orders_id");
+        assert_eq!(Value::Object(children[0].rows[0].clone()), json!({"sku": "a"}));
+        assert_eq!(Value::Object(children[1].rows[0].clone()), json!({"value": "x"}));
+        assert_eq!(children[1].schema_probe(), json!({"value": "x", "orders_id": 0}));
+    }
+}
@@ -6,17 +6,83 @@
 //! ## Key Features
 //! - Auto-detection of data types for visualization
 //! - Live streaming of data changes via SSE
+//! - Live feed of the latest inserts and updates of a collection, with
+//!   pause/resume and a filter (rows as stored with `broadcast_rows`)
 //! - No configuration required
+//!
+//! ## Development Modes
+//! For UI work without rebuilding the binary, `ExplorerMode::Directory`
+//! serves `/explore` and `/assets` from a built `ui/dist` on disk, and
+//! `ExplorerMode::DevProxy` passes them (and Vite's own module paths) through
+//! to a running Vite dev server. Both keep the SPA fallback of the embedded
+//! assets: unknown paths without an extension get `index.html`, other
+//! unknown paths a 404.
+//!
+//! ## Base Path
+//! Pages are served with `window.VIBEDB_BASE_PATH` set to the prefix the
+//! client reached the server under (see `BasePathConfig`), and their
+//! root-relative `src`/`href` attributes moved under it, so fetch and
+//! EventSource URLs and asset paths work behind a reverse proxy at e.g.
+//! `/vibedb/`. The dev proxy passes pages through untouched.
+
+use crate::config::BasePathConfig;
 
 use axum::{
     body::Body,
-    extract::Path,
-    http::{header, Response, StatusCode},
+    extract::{Path, Request, State},
+    http::{header, HeaderMap, HeaderName, Response, StatusCode},
     response::IntoResponse,
     routing::get,
     Router,
 };
+use lazy_static::lazy_static;
+use regex::Regex;
 use rust_embed::RustEmbed;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::ServiceExt;
+use tower_http::services::ServeDir;
+use tracing::warn;
+
+/// Where the explorer UI is served from
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(tag = "mode", content = "source", rename_all = "snake_case")]
+pub enum ExplorerMode {
+    /// Assets compiled into the binary
+    #[default]
+    Embedded,
+    /// A built UI on disk (`--explorer-dir`)
+    Directory(PathBuf),
+    /// A Vite dev server base URL (`--explorer-dev-proxy`)
+    DevProxy(String),
+}
+
+/// Paths the Vite dev server serves besides `/explore` and `/assets`
+const VITE_DEV_PATHS: [&str; 6] = [
+    "/@vite/*path",
+    "/@id/*path",
+    "/@fs/*path",
+    "/@react-refresh",
+    "/src/*path",
+    "/node_modules/*path",
+];
+
+/// Request headers passed through to the dev server
+const PROXIED_REQUEST_HEADERS: [HeaderName; 4] = [
+    header::ACCEPT,
+    header::ACCEPT_LANGUAGE,
+    header::IF_NONE_MATCH,
+    header::IF_MODIFIED_SINCE,
+];
+
+/// Request header naming the prefix a proxy stripped from the path
+pub const FORWARDED_PREFIX: &str = "x-forwarded-prefix";
+
+lazy_static! {
+    /// Root-relative `src` and `href` attributes, but not protocol-relative ones
+    static ref ROOT_RELATIVE: Regex = Regex::new(r#"\b(src|href)="/([^/"])"#).unwrap();
+}
 
 /// Embedded UI assets from ./ui/dist
 #[derive(RustEmbed)]
@@ -24,26 +90,70 @@ use rust_embed::RustEmbed;
 #[prefix = ""]
 pub struct ExplorerAssets;
 
-/// Creates the explorer router
+/// Creates the explorer router serving the embedded assets at the root
 pub fn create_explorer_router() -> Router {
-    Router::new()
-        .route("/explore", get(serve_index))
-        .route("/explore/", get(serve_index))
-        .route("/explore/*path", get(serve_static))
-        .route("/assets/*path", get(serve_asset))
+    create_explorer_router_for(&ExplorerMode::Embedded, &BasePathConfig::default())
 }
 
-/// Serve the main index.html
-async fn serve_index() -> impl IntoResponse {
-    if ExplorerAssets::get("index.html").is_some() {
-        serve_file("index.html").into_response()
-    } else {
-        axum::response::Html(fallback_explorer_html()).into_response()
+/// Creates the explorer router for a serving mode
+pub fn create_explorer_router_for(mode: &ExplorerMode, base_path: &BasePathConfig) -> Router {
+    match mode {
+        ExplorerMode::Embedded => Router::new()
+            .route("/explore", get(serve_index))
+            .route("/explore/", get(serve_index))
+            .route("/explore/*path", get(serve_static))
+            .route("/assets/*path", get(serve_asset))
+            .with_state(Arc::new(base_path.clone())),
+        ExplorerMode::Directory(dir) => {
+            if !dir.join("index.html").is_file() {
+                warn!("⚠️ No index.html in explorer directory {}", dir.display());
+            }
+            Router::new()
+                .route("/explore", get(serve_dir))
+                .route("/explore/", get(serve_dir))
+                .route("/explore/*path", get(serve_dir))
+                .route("/assets/*path", get(serve_dir))
+                .with_state(Arc::new(ExplorerDir {
+                    dir: dir.clone(),
+                    base_path: base_path.clone(),
+                }))
+        }
+        ExplorerMode::DevProxy(base) => {
+            let proxy = Arc::new(DevProxy {
+                client: reqwest::Client::new(),
+                base: base.trim_end_matches('/').to_string(),
+            });
+            let router = Router::new()
+                .route("/explore", get(proxy_to_dev_server))
+                .route("/explore/*path", get(proxy_to_dev_server))
+                .route("/assets/*path", get(proxy_to_dev_server));
+            VITE_DEV_PATHS
+                .iter()
+                .fold(router, |router, path| router.route(path, get(proxy_to_dev_server)))
+                .with_state(proxy)
+        }
+    }
+}
+
+/// Serve the main index.html, or the fallback page when no UI is built
+async fn serve_index(State(base_path): State<Arc<BasePathConfig>>, headers: HeaderMap) -> Response<Body> {
+    match ExplorerAssets::get("index.html") {
+        Some(content) => index_response(&String::from_utf8_lossy(&content.data), &base_path, &headers),
+        None => index_response(fallback_explorer_html(), &base_path, &headers),
     }
 }
 
 /// Serve static files
-async fn serve_static(Path(path): Path<String>) -> impl IntoResponse {
+async fn serve_static(
+    State(base_path): State<Arc<BasePathConfig>>,
+    headers: HeaderMap,
+    Path(path): Path<String>,
+) -> Response<Body> {
+    // Try index.html for SPA routing
+    let spa_route = !path.contains('.') && ExplorerAssets::get(&path).is_none();
+    if (path == "index.html" || spa_route) && ExplorerAssets::get("index.html").is_some() {
+        return serve_index(State(base_path), headers).await;
+    }
     serve_file(&path)
 }
 
@@ -64,23 +174,172 @@ fn serve_file(path: &str) -> Response<Body> {
                 .body(Body::from(content.data.into_owned()))
                 .unwrap()
         }
-        None => {
-            // Try index.html for SPA routing
-            if !path.contains('.') {
-                if let Some(content) = ExplorerAssets::get("index.html") {
-                    return Response::builder()
-                        .status(StatusCode::OK)
-                        .header(header::CONTENT_TYPE, "text/html")
-                        .body(Body::from(content.data.into_owned()))
-                        .unwrap();
+        None => not_found(),
+    }
+}
+
+/// An explorer page pointed at the API under the request's public prefix
+///
+/// Not cached, since the page depends on the prefix; it varies with
+/// `X-Forwarded-Prefix` when that is believed.
+fn index_response(html: &str, base_path: &BasePathConfig, headers: &HeaderMap) -> Response<Body> {
+    let forwarded = headers.get(FORWARDED_PREFIX).and_then(|v| v.to_str().ok());
+    let prefix = base_path.public_prefix(forwarded);
+    let mut response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+        .header(header::CACHE_CONTROL, "no-cache");
+    if base_path.trust_forwarded {
+        response = response.header(header::VARY, FORWARDED_PREFIX);
+    }
+    response.body(Body::from(with_base_path(html, &prefix))).unwrap()
+}
+
+/// Sets `window.VIBEDB_BASE_PATH` in a page and moves its root-relative
+/// `src` and `href` attributes (Vite's `/assets/...`) under `prefix`
+pub fn with_base_path(html: &str, prefix: &str) -> String {
+    let mut html = if prefix.is_empty() {
+        html.to_string()
+    } else {
+        ROOT_RELATIVE
+            .replace_all(html, format!("$1=\"{}/$2", prefix).as_str())
+            .into_owned()
+    };
+    let script = format!(
+        "<script>window.VIBEDB_BASE_PATH = {};</script>\n",
+        serde_json::Value::from(prefix)
+    );
+    let at = html.find("</head>").unwrap_or(0);
+    html.insert_str(at, &script);
+    html
+}
+
+/// Directory and base path of `ExplorerMode::Directory`
+struct ExplorerDir {
+    dir: PathBuf,
+    base_path: BasePathConfig,
+}
+
+/// Serves `/explore/...` and `/assets/...` from a directory
+///
+/// `/explore/x` maps to `<dir>/x` and `/assets/x` to `<dir>/assets/x`, as
+/// in the embedded layout. Nothing is cached, so a rebuild shows up on the
+/// next reload.
+async fn serve_dir(State(site): State<Arc<ExplorerDir>>, request: Request) -> Response<Body> {
+    let path = request.uri().path();
+    let relative = match path.strip_prefix("/explore") {
+        Some("" | "/") => "/index.html",
+        Some(rest) => rest,
+        None => path,
+    }
+    .to_string();
+    let index = tokio::fs::read_to_string(site.dir.join("index.html"));
+
+    if relative == "/index.html" {
+        let html = index.await.unwrap_or_else(|_| fallback_explorer_html().to_string());
+        return index_response(&html, &site.base_path, request.headers());
+    }
+    let mut response = match dir_file(&site.dir, &relative).await {
+        Some(response) => response,
+        // Try index.html for SPA routing
+        None if !relative.contains('.') => match index.await {
+            Ok(html) => return index_response(&html, &site.base_path, request.headers()),
+            Err(_) => return not_found(),
+        },
+        None => return not_found(),
+    };
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, header::HeaderValue::from_static("no-cache"));
+    response
+}
+
+/// A file under `dir`, or `None` when it does not exist
+async fn dir_file(dir: &std::path::Path, relative: &str) -> Option<Response<Body>> {
+    let request = Request::builder().uri(relative).body(Body::empty()).ok()?;
+    let response = ServeDir::new(dir).oneshot(request).await.ok()?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return None;
+    }
+    Some(response.map(Body::new))
+}
+
+fn not_found() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .body(Body::from("Not Found"))
+        .unwrap()
+}
+
+/// Target of `ExplorerMode::DevProxy`
+struct DevProxy {
+    client: reqwest::Client,
+    /// Base URL without a trailing slash
+    base: String,
+}
+
+/// Passes a GET through to the dev server, streaming the body back
+async fn proxy_to_dev_server(State(proxy): State<Arc<DevProxy>>, request: Request) -> Response<Body> {
+    let path = request
+        .uri()
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or("/");
+    let url = format!("{}{}", proxy.base, path);
+
+    let mut upstream = proxy.client.get(&url);
+    for name in PROXIED_REQUEST_HEADERS {
+        if let Some(value) = request.headers().get(&name) {
+            upstream = upstream.header(name, value);
+        }
+    }
+    let mut upstream = match upstream.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            warn!("⚠️ Explorer dev server {} unreachable: {}", proxy.base, e);
+            return Response::builder()
+                .status(StatusCode::BAD_GATEWAY)
+                .header(header::CONTENT_TYPE, "text/plain")
+                .body(Body::from(format!(
+                    "Explorer dev server at {} is not reachable: {}",
+                    proxy.base, e
+                )))
+                .unwrap();
+        }
+    };
+
+    let mut response = Response::builder().status(upstream.status());
+    for (name, value) in upstream.headers() {
+        if !is_hop_by_hop(name) {
+            response = response.header(name, value);
+        }
+    }
+    let body = async_stream::stream! {
+        loop {
+            match upstream.chunk().await {
+                Ok(Some(chunk)) => yield Ok(chunk),
+                Ok(None) => break,
+                Err(e) => {
+                    yield Err(std::io::Error::other(e.to_string()));
+                    break;
                 }
             }
-            Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .body(Body::from("Not Found"))
-                .unwrap()
         }
-    }
+    };
+    response
+        .body(Body::from_stream(body))
+        .unwrap_or_else(|_| StatusCode::BAD_GATEWAY.into_response())
+}
+
+/// Headers that describe one connection and are not forwarded
+fn is_hop_by_hop(name: &HeaderName) -> bool {
+    name == header::CONNECTION
+        || name == header::TRANSFER_ENCODING
+        || name == header::UPGRADE
+        || name == header::TE
+        || name == header::TRAILER
+        || name == header::PROXY_AUTHENTICATE
+        || name.as_str() == "keep-alive"
 }
 
 /// Fallback HTML when no UI is built yet
@@ -251,6 +510,12 @@ pub fn fallback_explorer_html() -> &'static str {
             font-family: monospace;
         }
         
+        .column-description {
+            display: block;
+            color: var(--text-muted);
+            font-size: 0.75rem;
+        }
+        
         .empty-state {
             text-align: center;
             padding: 4rem 2rem;
@@ -334,6 +599,72 @@ pub fn fallback_explorer_html() -> &'static str {
             border-radius: 50%;
             animation: pulse 1s infinite;
         }
+        
+        .card-footer {
+            display: flex;
+            align-items: center;
+            justify-content: space-between;
+            margin-top: 1rem;
+        }
+        
+        .btn-small {
+            padding: 0.375rem 0.75rem;
+            font-size: 0.75rem;
+        }
+        
+        .btn-secondary {
+            background: var(--bg);
+            border: 1px solid var(--border);
+        }
+        
+        #feed-panel {
+            display: none;
+            margin-top: 2rem;
+        }
+        
+        .feed-toolbar {
+            display: flex;
+            align-items: center;
+            gap: 0.75rem;
+            margin-bottom: 1rem;
+        }
+        
+        .feed-toolbar input {
+            flex: 1;
+            padding: 0.5rem 0.75rem;
+            background: var(--bg);
+            border: 1px solid var(--border);
+            border-radius: 0.5rem;
+            color: var(--text);
+        }
+        
+        .feed-list {
+            max-height: 480px;
+            overflow-y: auto;
+        }
+        
+        .feed-row {
+            padding: 0.75rem 0;
+            border-bottom: 1px solid var(--border);
+        }
+        
+        .feed-meta {
+            display: flex;
+            gap: 0.75rem;
+            font-size: 0.75rem;
+            color: var(--text-muted);
+            margin-bottom: 0.25rem;
+        }
+        
+        .feed-event-insert { color: var(--success); }
+        .feed-event-update { color: var(--warning); }
+        
+        .feed-row pre {
+            font-family: 'Fira Code', monospace;
+            font-size: 0.8rem;
+            white-space: pre-wrap;
+            word-break: break-all;
+        }
     </style>
 </head>
 <body>
@@ -354,10 +685,31 @@ pub fn fallback_explorer_html() -> &'static str {
                 <div class="spinner"></div>
             </div>
         </div>
+        
+        <div id="feed-panel" class="card">
+            <div class="card-header">
+                <h3 class="card-title">Live feed: <span id="feed-collection"></span></h3>
+                <span class="card-badge" id="feed-status">Live</span>
+            </div>
+            <div class="feed-toolbar">
+                <input id="feed-filter" type="text" placeholder="Filter events..." oninput="renderFeed()">
+                <button class="btn btn-small" id="feed-pause" onclick="toggleFeedPause()">Pause</button>
+                <button class="btn btn-small btn-secondary" onclick="clearFeed()">Clear</button>
+                <button class="btn btn-small btn-secondary" onclick="closeFeed()">Close</button>
+            </div>
+            <div class="feed-list" id="feed-list"></div>
+        </div>
     </div>
     
     <script>
-        const API_BASE = window.location.origin;
+        const API_BASE = window.location.origin + (window.VIBEDB_BASE_PATH || '');
+        
+        // Column descriptions are free text
+        function escapeHtml(text) {
+            const div = document.createElement('div');
+            div.textContent = text;
+            return div.innerHTML;
+        }
         
         async function fetchTables() {
             try {
@@ -405,7 +757,7 @@ pub fn fallback_explorer_html() -> &'static str {
                         </div>
                         <div class="stats">
                             <div class="stat">
-                                <div class="stat-value">${stats.row_count.toLocaleString()}</div>
+                                <div class="stat-value">${stats.approximate ? '~' : ''}${stats.row_count.toLocaleString()}</div>
                                 <div class="stat-label">Documents</div>
                             </div>
                             <div class="stat">
@@ -416,14 +768,20 @@ pub fn fallback_explorer_html() -> &'static str {
                         <div class="columns-list">
                             ${stats.columns.map(col => `
                                 <div class="column-item">
-                                    <span>${col.name}</span>
+                                    <span>
+                                        ${col.name}
+                                        ${col.description ? `<span class="column-description">${escapeHtml(col.description)}</span>` : ''}
+                                    </span>
                                     <span class="column-type">${col.col_type || 'ANY'}</span>
                                 </div>
                             `).join('')}
                         </div>
-                        <div class="live-indicator" style="margin-top: 1rem;">
-                            <div class="live-dot"></div>
-                            <span>Live updates</span>
+                        <div class="card-footer">
+                            <div class="live-indicator">
+                                <div class="live-dot"></div>
+                                <span>Live updates</span>
+                            </div>
+                            <button class="btn btn-small" onclick="openFeed('${stats.name}')">Live feed</button>
                         </div>
                     </div>
                 `;
@@ -443,7 +801,7 @@ pub fn fallback_explorer_html() -> &'static str {
                 const data = JSON.parse(event.data);
                 console.log(`[${table}] Event:`, data);
                 
-                if (data.event === 'insert' || data.event === 'batch_insert') {
+                if (['insert', 'inserts', 'batch_insert'].includes(data.event)) {
                     // Refresh table stats
                     fetchTableStats(table).then(stats => {
                         if (stats) {
@@ -451,7 +809,7 @@ pub fn fallback_explorer_html() -> &'static str {
                             if (card) {
                                 const rowCountEl = card.querySelector('.stat-value');
                                 if (rowCountEl) {
-                                    rowCountEl.textContent = stats.row_count.toLocaleString();
+                                    rowCountEl.textContent = (stats.approximate ? '~' : '') + stats.row_count.toLocaleString();
                                     rowCountEl.style.color = '#10b981';
                                     setTimeout(() => {
                                         rowCountEl.style.color = '';
@@ -468,6 +826,89 @@ pub fn fallback_explorer_html() -> &'static str {
             };
         }
         
+        // Live feed: the last FEED_LIMIT insert/update events of one collection
+        const FEED_LIMIT = 100;
+        const feed = { source: null, collection: null, events: [], paused: false, missed: 0 };
+        
+        function openFeed(table) {
+            closeFeed();
+            feed.collection = table;
+            feed.source = new EventSource(`${API_BASE}/v1/stream/${table}`);
+            feed.source.onmessage = (event) => {
+                const data = JSON.parse(event.data);
+                if (data.event !== 'insert' && data.event !== 'update') return;
+                if (feed.paused) {
+                    feed.missed++;
+                    updateFeedStatus();
+                    return;
+                }
+                feed.events.unshift({ at: new Date(), event: data });
+                feed.events.length = Math.min(feed.events.length, FEED_LIMIT);
+                renderFeed();
+            };
+            document.getElementById('feed-collection').textContent = table;
+            document.getElementById('feed-panel').style.display = 'block';
+            updateFeedStatus();
+            renderFeed();
+        }
+        
+        function closeFeed() {
+            if (feed.source) feed.source.close();
+            Object.assign(feed, { source: null, collection: null, events: [], paused: false, missed: 0 });
+            document.getElementById('feed-panel').style.display = 'none';
+            document.getElementById('feed-pause').textContent = 'Pause';
+        }
+        
+        function toggleFeedPause() {
+            feed.paused = !feed.paused;
+            if (!feed.paused) feed.missed = 0;
+            document.getElementById('feed-pause').textContent = feed.paused ? 'Resume' : 'Pause';
+            updateFeedStatus();
+        }
+        
+        function clearFeed() {
+            feed.events = [];
+            renderFeed();
+        }
+        
+        function updateFeedStatus() {
+            document.getElementById('feed-status').textContent = feed.paused
+                ? `Paused (${feed.missed} skipped)`
+                : 'Live';
+        }
+        
+        function renderFeed() {
+            const list = document.getElementById('feed-list');
+            const filter = document.getElementById('feed-filter').value.toLowerCase();
+            list.replaceChildren();
+            for (const { at, event } of feed.events) {
+                const json = JSON.stringify(event.data, null, 2);
+                if (filter && !json.toLowerCase().includes(filter)) continue;
+        
+                const row = document.createElement('div');
+                row.className = 'feed-row';
+                const meta = document.createElement('div');
+                meta.className = 'feed-meta';
+                for (const [text, cls] of [
+                    [at.toLocaleTimeString(), ''],
+                    [event.event, `feed-event-${event.event}`],
+                    [`#${event.id}`, ''],
+                ]) {
+                    const span = document.createElement('span');
+                    span.textContent = text;
+                    if (cls) span.className = cls;
+                    meta.appendChild(span);
+                }
+                const pre = document.createElement('pre');
+                pre.textContent = json;
+                row.append(meta, pre);
+                list.appendChild(row);
+            }
+            if (!list.children.length) {
+                list.innerHTML = '<div class="empty-state">Waiting for inserts and updates...</div>';
+            }
+        }
+        
         function renderEmptyState() {
             document.getElementById('tables-container').innerHTML = `
                 <div class="empty-state">
@@ -513,3 +954,129 @@ pub fn create_fallback_explorer_router() -> Router {
 async fn serve_fallback() -> impl IntoResponse {
     axum::response::Html(fallback_explorer_html())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn fetch(router: &Router, uri: &str) -> (StatusCode, Option<String>, String) {
+        let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, content_type, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    #[tokio::test]
+    async fn test_directory_mode_serves_files_with_spa_fallback() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("index.html"), "<h1>dev build</h1>").unwrap();
+        std::fs::create_dir(dir.path().join("assets")).unwrap();
+        std::fs::write(dir.path().join("assets/app.js"), "console.log(1)").unwrap();
+        let router = create_explorer_router_for(&ExplorerMode::Directory(dir.path().to_path_buf()), &BasePathConfig::default());
+
+        for uri in ["/explore", "/explore/", "/explore/collections/orders"] {
+            let (status, content_type, body) = fetch(&router, uri).await;
+            assert_eq!(status, StatusCode::OK, "{}", uri);
+            assert!(content_type.unwrap().starts_with("text/html"), "{}", uri);
+            assert!(body.ends_with("<h1>dev build</h1>"), "{}", body);
+        }
+        let (status, content_type, body) = fetch(&router, "/assets/app.js").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(content_type.unwrap().contains("javascript"));
+        assert_eq!(body, "console.log(1)");
+
+        // Edits show up without restarting
+        std::fs::write(dir.path().join("assets/app.js"), "console.log(2)").unwrap();
+        assert_eq!(fetch(&router, "/assets/app.js").await.2, "console.log(2)");
+
+        // Missing files with an extension are 404s, as with embedded assets
+        let embedded = create_explorer_router();
+        for uri in ["/assets/missing.js", "/explore/missing.css"] {
+            assert_eq!(fetch(&router, uri).await.0, StatusCode::NOT_FOUND, "{}", uri);
+            assert_eq!(fetch(&embedded, uri).await.0, StatusCode::NOT_FOUND, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pages_carry_the_base_path() {
+        let base_path = BasePathConfig {
+            prefix: BasePathConfig::normalize("vibedb/").unwrap(),
+            trust_forwarded: false,
+        };
+        let router = create_explorer_router_for(&ExplorerMode::Embedded, &base_path);
+        let request = Request::builder().uri("/explore").body(Body::empty()).unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-cache");
+        let (status, _, body) = fetch(&router, "/explore/").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains(r#"<script>window.VIBEDB_BASE_PATH = "/vibedb";</script>"#));
+        assert!(body.contains("const API_BASE = window.location.origin + (window.VIBEDB_BASE_PATH || '');"));
+
+        // X-Forwarded-Prefix counts only from a trusted proxy
+        let forwarded = |router: &Router| {
+            let request = Request::builder()
+                .uri("/explore")
+                .header(FORWARDED_PREFIX, "/proxied")
+                .body(Body::empty())
+                .unwrap();
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                String::from_utf8_lossy(&body).into_owned()
+            }
+        };
+        assert!(forwarded(&router).await.contains(r#"VIBEDB_BASE_PATH = "/vibedb""#));
+        let trusted = BasePathConfig { trust_forwarded: true, ..BasePathConfig::default() };
+        let router = create_explorer_router_for(&ExplorerMode::Embedded, &trusted);
+        assert!(forwarded(&router).await.contains(r#"VIBEDB_BASE_PATH = "/proxied""#));
+        assert!(fetch(&router, "/explore").await.2.contains(r#"VIBEDB_BASE_PATH = """#));
+
+        let page = with_base_path(
+            r#"<head><script src="/assets/app.js"></script><link href="//cdn.example.com/x.css"></head>"#,
+            "/vibedb",
+        );
+        assert!(page.contains(r#"src="/vibedb/assets/app.js""#));
+        assert!(page.contains(r#"href="//cdn.example.com/x.css""#));
+
+        assert_eq!(BasePathConfig::normalize("/").unwrap(), "");
+        assert_eq!(BasePathConfig::normalize("/a/b-1/").unwrap(), "/a/b-1");
+        for invalid in ["/a//b", "/../x", "/a b", "/<script>"] {
+            assert!(BasePathConfig::normalize(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dev_proxy_passes_requests_through() {
+        let upstream = Router::new()
+            .route("/explore", get(|| async { axum::response::Html("<h1>vite</h1>") }))
+            .route(
+                "/@vite/client",
+                get(|request: Request| async move {
+                    ([(header::CONTENT_TYPE, "text/javascript")], request.uri().to_string())
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, upstream).await.unwrap() });
+        let router = create_explorer_router_for(&ExplorerMode::DevProxy(format!("http://{}/", addr)), &BasePathConfig::default());
+
+        let (status, content_type, body) = fetch(&router, "/explore").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(content_type.unwrap().starts_with("text/html"));
+        assert_eq!(body, "<h1>vite</h1>");
+        let (_, content_type, body) = fetch(&router, "/@vite/client?t=1").await;
+        assert_eq!(content_type.as_deref(), Some("text/javascript"));
+        assert_eq!(body, "/@vite/client?t=1");
+        assert_eq!(fetch(&router, "/assets/gone.js").await.0, StatusCode::NOT_FOUND);
+
+        let closed = create_explorer_router_for(&ExplorerMode::DevProxy("http://127.0.0.1:9".to_string()), &BasePathConfig::default());
+        assert_eq!(fetch(&closed, "/explore").await.0, StatusCode::BAD_GATEWAY);
+    }
+}
@@ -0,0 +1,282 @@
+//! # Filter Operators
+//!
+//! Query-string filters are `column=value` equality tests unless the value
+//! starts with an operator:
+//!
+//! | Value | Condition |
+//! |-------|-----------|
+//! | `eq.open` | `column = 'open'` |
+//! | `like.ab*` | `column LIKE 'ab%'` (`*` or `%` as wildcard) |
+//! | `in.(open,pending)` | `column IN ('open', 'pending')` |
+//! | `is.null` | `column IS NULL` |
+//! | `not.eq.closed`, `not.in.(a,b)`, `not.like.x*`, `not.is.null` | the complement |
+//!
+//! A negated condition is the exact complement of the plain one, so
+//! `not.eq.closed` also matches rows where the column is NULL.
+//!
+//! Conditions on different keys, and a repeated key
+//! (`?n=not.eq.1&n=not.eq.2`), must all hold. Alternatives for one column
+//! are written as an `in.` list.
+//!
+//! List elements are separated by commas, and spaces around unquoted ones
+//! are ignored. An element in double quotes is taken as is, commas and
+//! parentheses included, with `\"` and `\\` as escapes:
+//! `in.("Smith, J.",Doe)`. A `%2C` in the URL is decoded before the list is
+//! split, so it separates elements like a plain comma. Lists hold at most
+//! [`MAX_IN_LIST`] elements, one bound parameter each.
+//!
+//! A value that merely looks like an operator (`not.sure`) is compared as
+//! is; to match a value that is an operator (`eq.5`), prefix it with `eq.`.
+
+use crate::db::SqlValue;
+
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::Deserialize;
+use std::fmt;
+
+/// Most elements of an `in.` list
+pub const MAX_IN_LIST: usize = 100;
+
+/// Comparison of a filter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterOp {
+    Eq(String),
+    /// Pattern with `*` already turned into `%`
+    Like(String),
+    In(Vec<String>),
+    IsNull,
+}
+
+/// A parsed filter value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    pub negated: bool,
+    pub op: FilterOp,
+}
+
+impl Filter {
+    /// Parses a filter value; plain values are equality tests
+    pub fn parse(value: &str) -> Result<Self, String> {
+        if let Some(rest) = value.strip_prefix("not.") {
+            if let Some(op) = Self::operator(rest)? {
+                return Ok(Self { negated: true, op });
+            }
+        }
+        let op = Self::operator(value)?.unwrap_or_else(|| FilterOp::Eq(value.to_string()));
+        Ok(Self { negated: false, op })
+    }
+
+    /// The operator `value` starts with, if any
+    fn operator(value: &str) -> Result<Option<FilterOp>, String> {
+        if let Some(operand) = value.strip_prefix("eq.") {
+            return Ok(Some(FilterOp::Eq(operand.to_string())));
+        }
+        if let Some(pattern) = value.strip_prefix("like.") {
+            return Ok(Some(FilterOp::Like(pattern.replace('*', "%"))));
+        }
+        if value == "is.null" {
+            return Ok(Some(FilterOp::IsNull));
+        }
+        if let Some(list) = value.strip_prefix("in.(").and_then(|list| list.strip_suffix(')')) {
+            return parse_list(list).map(|items| Some(FilterOp::In(items)));
+        }
+        if value.starts_with("in.(") {
+            return Err("an in. list must end with ')'".to_string());
+        }
+        Ok(None)
+    }
+
+    /// SQL condition on `column` (a column or a cast of one) and its
+    /// parameters
+    ///
+    /// `placeholder` stands for each bound value, e.g. `CAST(? AS INTEGER)`;
+    /// patterns are always bound as plain text.
+    pub fn condition(&self, column: &str, placeholder: &str) -> (String, Vec<SqlValue>) {
+        let (condition, values) = match &self.op {
+            FilterOp::Eq(value) => (format!("{} = {}", column, placeholder), vec![value.clone()]),
+            FilterOp::Like(pattern) => (format!("{} LIKE ?", column), vec![pattern.clone()]),
+            FilterOp::In(items) => (
+                format!("{} IN ({})", column, vec![placeholder; items.len()].join(", ")),
+                items.clone(),
+            ),
+            FilterOp::IsNull => (format!("{} IS NULL", column), Vec::new()),
+        };
+        // A comparison with NULL is no match, so its negation is one
+        let condition = if self.negated {
+            format!("NOT COALESCE({}, 0)", condition)
+        } else {
+            condition
+        };
+        (condition, values.into_iter().map(SqlValue::Text).collect())
+    }
+}
+
+/// Splits the inside of `in.(...)` into its elements
+fn parse_list(list: &str) -> Result<Vec<String>, String> {
+    if list.trim().is_empty() {
+        return Err("an in. list needs at least one element".to_string());
+    }
+    let mut items = Vec::new();
+    let mut chars = list.chars().peekable();
+    loop {
+        while chars.next_if(|c| *c == ' ').is_some() {}
+        let item = if chars.next_if_eq(&'"').is_some() {
+            let mut item = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => item.push(chars.next().ok_or("unterminated quoted element")?),
+                    Some(c) => item.push(c),
+                    None => return Err("unterminated quoted element".to_string()),
+                }
+            }
+            while chars.next_if(|c| *c == ' ').is_some() {}
+            if chars.peek().is_some_and(|c| *c != ',') {
+                return Err("expected ',' after a quoted element".to_string());
+            }
+            item
+        } else {
+            let mut item = String::new();
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                item.push(c);
+            }
+            item.trim_end().to_string()
+        };
+        items.push(item);
+        if items.len() > MAX_IN_LIST {
+            return Err(format!("an in. list holds at most {} elements", MAX_IN_LIST));
+        }
+        if chars.next().is_none() {
+            break;
+        }
+    }
+    Ok(items)
+}
+
+/// Query-string filters in request order, repeated keys included
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filters(Vec<(String, String)>);
+
+impl Filters {
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.0.iter().map(|(key, value)| (key, value))
+    }
+
+    pub fn push(&mut self, key: String, value: String) {
+        self.0.push((key, value));
+    }
+
+    /// Takes every value of `key` out, returning the last
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let mut last = None;
+        self.0.retain_mut(|(k, value)| {
+            if k == key {
+                last = Some(std::mem::take(value));
+            }
+            k != key
+        });
+        last
+    }
+}
+
+impl FromIterator<(String, String)> for Filters {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<'de> Deserialize<'de> for Filters {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FiltersVisitor;
+
+        impl<'de> Visitor<'de> for FiltersVisitor {
+            type Value = Filters;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("query parameters")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Filters, A::Error> {
+                let mut filters = Vec::new();
+                while let Some(entry) = map.next_entry::<String, String>()? {
+                    filters.push(entry);
+                }
+                Ok(Filters(filters))
+            }
+        }
+
+        deserializer.deserialize_map(FiltersVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::Query;
+
+    fn parse(value: &str) -> Filter {
+        Filter::parse(value).unwrap()
+    }
+
+    #[test]
+    fn test_parse_operators() {
+        let eq = |value: &str| FilterOp::Eq(value.to_string());
+        assert_eq!(parse("open"), Filter { negated: false, op: eq("open") });
+        assert_eq!(parse("eq.open").op, eq("open"));
+        assert_eq!(parse("eq.eq.5").op, eq("eq.5"));
+        assert_eq!(parse("not.sure").op, eq("not.sure"));
+        assert_eq!(parse("not.eq.closed"), Filter { negated: true, op: eq("closed") });
+        assert_eq!(parse("like.ab*").op, FilterOp::Like("ab%".to_string()));
+        assert_eq!(parse("not.is.null"), Filter { negated: true, op: FilterOp::IsNull });
+        assert_eq!(parse("is.nullable").op, eq("is.nullable"));
+
+        let list = |items: &[&str]| FilterOp::In(items.iter().map(|s| s.to_string()).collect());
+        assert_eq!(parse("in.(1,2, 3)").op, list(&["1", "2", "3"]));
+        assert_eq!(parse(r#"in.("Smith, J.", Doe,"say \"hi\"")"#).op, list(&["Smith, J.", "Doe", "say \"hi\""]));
+        assert_eq!(parse(r#"in.("",x)"#).op, list(&["", "x"]));
+        assert_eq!(parse(r#"in.("")"#).op, list(&[""]));
+        assert_eq!(parse("not.in.(a)"), Filter { negated: true, op: list(&["a"]) });
+
+        let max = format!("in.({})", vec!["x"; MAX_IN_LIST].join(","));
+        assert!(Filter::parse(&max).is_ok());
+        for (value, error) in [
+            ("in.()", "at least one"),
+            ("in.(a,b", "must end with ')'"),
+            (r#"in.("a,b)"#, "unterminated"),
+            (r#"in.("a"b)"#, "expected ','"),
+            (&format!("in.({})", vec!["x"; MAX_IN_LIST + 1].join(",")), "at most 100"),
+        ] {
+            let message = Filter::parse(value).unwrap_err();
+            assert!(message.contains(error), "{}: {}", value, message);
+        }
+    }
+
+    #[test]
+    fn test_conditions() {
+        let (sql, params) = parse("not.in.(a,b)").condition("status", "?");
+        assert_eq!(sql, "NOT COALESCE(status IN (?, ?), 0)");
+        assert_eq!(params.len(), 2);
+        let (sql, params) = parse("in.(1,2)").condition("CAST(n AS INTEGER)", "CAST(? AS INTEGER)");
+        assert_eq!(sql, "CAST(n AS INTEGER) IN (CAST(? AS INTEGER), CAST(? AS INTEGER))");
+        assert_eq!(params.len(), 2);
+        let (sql, params) = parse("is.null").condition("n", "?");
+        assert_eq!((sql.as_str(), params.len()), ("n IS NULL", 0));
+    }
+
+    #[test]
+    fn test_filters_keep_repeated_keys() {
+        #[derive(Deserialize)]
+        struct Params {
+            order_by: Option<String>,
+            #[serde(flatten)]
+            filters: Filters,
+        }
+        let uri: axum::http::Uri = "/?n=not.eq.1&order_by=n&n=not.eq.2&s=x".parse().unwrap();
+        let Query(params) = Query::<Params>::try_from_uri(&uri).unwrap();
+        assert_eq!(params.order_by.as_deref(), Some("n"));
+        let mut filters = params.filters;
+        assert_eq!(filters.iter().count(), 3);
+        assert_eq!(filters.remove("n").as_deref(), Some("not.eq.2"));
+        assert_eq!(filters.iter().collect::<Vec<_>>(), [(&"s".to_string(), &"x".to_string())]);
+    }
+}
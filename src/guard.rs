@@ -12,21 +12,203 @@
 //! 3. **Diffing**: Compare payload keys against existing columns
 //! 4. **Auto-Migration**: Generate ALTER TABLE for missing columns
 //! 5. **Validation**: Ensure keys are valid SQL identifiers
+//!
+//! Payload keys are converted to `identifiers.key_case` (off by default).
+//! Names longer than `identifiers.max_length` are rejected, or with
+//! `identifiers.truncate` shortened. Renamed keys are recorded in
+//! `vibe_column_aliases` so the original name keeps working on reads and
+//! writes.
 
-use crate::db::VibeStore;
+use crate::config::{IdentifierConfig, PayloadConfig};
+use crate::db::{SqlValue, VibeStore};
+use crate::descriptions::ColumnDescriptions;
 use crate::error::{VibeError, VibeResult};
-use crate::inference::infer_type;
+use crate::inference::{infer_type, InexactNumber, SqliteType};
+use crate::perf::PerfRegistry;
+use crate::settings::{ColumnMask, SettingsStore};
+use crate::sql_policy::{tokenize, Token};
+use crate::storage::STORAGE_STREAM;
 use dashmap::DashMap;
 use lazy_static::lazy_static;
 use regex::Regex;
-use serde_json::Value;
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tracing::{debug, info, warn};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+use tracing::{debug, field, info, info_span, warn, Instrument, Span};
 
 /// Maximum columns per table (prevents "Schema Bloat" attacks)
 const MAX_COLUMNS_PER_TABLE: usize = 1000;
 
+/// Share of `MAX_COLUMNS_PER_TABLE`, in percent, from which every column
+/// addition is logged as a warning
+const COLUMN_WARNING_PERCENT: usize = 90;
+
+/// New columns above which an evolution is split into several transactions
+const WIDE_MIGRATION_THRESHOLD: usize = 64;
+
+/// Columns added per transaction in a split evolution
+const ALTER_CHUNK_SIZE: usize = 50;
+
+/// `vibe_migrations.strategy` of an evolution applied in one transaction
+pub const MIGRATION_SINGLE: &str = "single_transaction";
+
+/// `vibe_migrations.strategy` of an evolution split into chunks
+pub const MIGRATION_CHUNKED: &str = "chunked";
+
+/// Default longest table or column name VibeDB creates
+pub const DEFAULT_MAX_IDENTIFIER_LENGTH: usize = 128;
+
+/// Longest identifier accepted anywhere; `identifiers.max_length` is
+/// clamped to it
+pub const MAX_IDENTIFIER_LENGTH: usize = 1024;
+
+/// Hex digits of the hash that keeps shortened identifiers unique
+const SHORTENED_HASH_DIGITS: usize = 8;
+
+/// Default of `PayloadConfig::max_depth`
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 32;
+
+/// Default of `PayloadConfig::max_nested_bytes` (1 MiB)
+pub const DEFAULT_MAX_NESTED_BYTES: usize = 1024 * 1024;
+
+/// `vibe_column_aliases.table_name` of collection name aliases
+const TABLE_ALIAS_SCOPE: &str = "";
+
+/// Columns managed by VibeDB itself
+const SYSTEM_COLUMNS: [&str; 3] = ["id", "created_at", "updated_at"];
+
+/// Whether `key` names a system column; SQLite column names are
+/// case-insensitive, so `ID` and `Created_At` do too
+pub fn is_system_column(key: &str) -> bool {
+    SYSTEM_COLUMNS.iter().any(|column| column.eq_ignore_ascii_case(key))
+}
+
+/// Keys of a payload that name system columns, which writes skip
+pub fn system_fields(payload: &Value) -> Vec<String> {
+    payload
+        .as_object()
+        .map(|obj| obj.keys().filter(|key| is_system_column(key)).cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Payload key of per-request column types, e.g.
+/// `{"_types": {"price": "REAL"}, "price": 10}`
+pub const TYPE_HINTS_KEY: &str = "_types";
+
+/// Column types requested by a payload's `_types`, keyed by lowercased
+/// column name
+pub type TypeHints = HashMap<String, SqliteType>;
+
+/// Removes `_types` from a payload and parses it (see `TYPE_HINTS_KEY`)
+///
+/// A hint only decides the type of a column the payload creates; columns
+/// that already exist keep theirs. Fails with `InvalidFields` on a type
+/// other than INTEGER, REAL, TEXT or BLOB.
+pub fn take_type_hints(payload: &mut Value) -> VibeResult<TypeHints> {
+    let Some(hints) = payload.as_object_mut().and_then(|obj| obj.remove(TYPE_HINTS_KEY)) else {
+        return Ok(TypeHints::new());
+    };
+    let Value::Object(hints) = hints else {
+        return Err(VibeError::InvalidPayload(format!(
+            "{} must be an object of column types",
+            TYPE_HINTS_KEY
+        )));
+    };
+    hints
+        .into_iter()
+        .map(|(column, name)| {
+            SchemaGuard::validate_identifier(&column)?;
+            let sql_type = name.as_str().and_then(SqliteType::parse_column_type).ok_or_else(|| {
+                VibeError::InvalidFields {
+                    message: format!(
+                        "Invalid type {} for {} in {}; use INTEGER, REAL, TEXT or BLOB",
+                        name, column, TYPE_HINTS_KEY
+                    ),
+                    fields: vec![column.clone()],
+                }
+            })?;
+            Ok((column.to_lowercase(), sql_type))
+        })
+        .collect()
+}
+
+/// DDL run on behalf of one request (see `tally_migrations`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MigrationTally {
+    /// `CREATE TABLE` and `ALTER TABLE` statements
+    pub ddl_statements: u64,
+    /// Time spent migrating, including the transactions around the DDL
+    pub ddl_time: Duration,
+}
+
+tokio::task_local! {
+    static MIGRATION_TALLY: Cell<MigrationTally>;
+}
+
+/// Runs `fut`, tallying the migrations the guard performs for it
+///
+/// Migrations outside such a scope, e.g. during startup, aren't tallied.
+pub async fn tally_migrations<F: Future>(fut: F) -> (F::Output, MigrationTally) {
+    MIGRATION_TALLY
+        .scope(Cell::new(MigrationTally::default()), async {
+            let output = fut.await;
+            (output, MIGRATION_TALLY.with(Cell::get))
+        })
+        .await
+}
+
+/// Migrations tallied so far by the enclosing `tally_migrations`, if any
+pub fn current_migrations() -> MigrationTally {
+    MIGRATION_TALLY.try_with(Cell::get).unwrap_or_default()
+}
+
+/// System columns a backfill may set explicitly
+pub const TIMESTAMP_COLUMNS: [&str; 2] = ["created_at", "updated_at"];
+
+/// SQLite's `CURRENT_TIMESTAMP` format
+pub(crate) const SQLITE_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Columns aggregated per usage query (three expressions each)
+const USAGE_CHUNK_SIZE: usize = 200;
+
+/// Columns sampled per preview query
+const PREVIEW_CHUNK_SIZE: usize = 50;
+
+/// Example values of a column in a preview
+pub const PREVIEW_EXAMPLES: usize = 3;
+
+/// Stands in for the examples of BLOB columns, which are never sampled
+pub const BLOB_PLACEHOLDER: &str = "<blob>";
+
+/// Most cached schemas compared with the live ones per inspection
+pub const MAX_CACHE_CHECKS: usize = 200;
+
+/// Declared types allowed for generated columns
+const GENERATED_COLUMN_TYPES: [&str; 5] = ["TEXT", "INTEGER", "REAL", "NUMERIC", "BLOB"];
+
+/// Keywords allowed in generated column expressions
+const EXPRESSION_KEYWORDS: &[&str] = &[
+    "AND", "OR", "NOT", "IS", "NULL", "IN", "LIKE", "GLOB", "BETWEEN", "CASE", "WHEN",
+    "THEN", "ELSE", "END", "CAST", "AS", "COLLATE", "NOCASE", "TRUE", "FALSE", "TEXT",
+    "INTEGER", "REAL", "NUMERIC", "BLOB",
+];
+
+/// Deterministic functions allowed in generated column expressions
+const EXPRESSION_FUNCTIONS: &[&str] = &[
+    "ABS", "COALESCE", "IFNULL", "IIF", "INSTR", "JSON_ARRAY_LENGTH", "JSON_EXTRACT",
+    "LENGTH", "LOWER", "LTRIM", "MAX", "MIN", "NULLIF", "PRINTF", "FORMAT", "REPLACE",
+    "ROUND", "RTRIM", "SUBSTR", "SUBSTRING", "TRIM", "TYPEOF", "UPPER",
+];
+
+/// Operator characters allowed in generated column expressions
+const EXPRESSION_PUNCTUATION: &str = "+-*/%|&<>=!(),.~";
+
 lazy_static! {
     /// Regex for validating SQL identifiers
     /// Only alphanumeric characters and underscores, must start with letter or underscore
@@ -94,20 +276,157 @@ lazy_static! {
 }
 
 /// Column metadata stored in cache
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ColumnInfo {
     pub name: String,
     pub col_type: String,
     pub notnull: bool,
     pub pk: bool,
+    /// Generated (computed) column; never written directly
+    pub generated: bool,
+}
+
+impl ColumnInfo {
+    /// Whether inserts and updates may write this column
+    ///
+    /// Generated columns are computed by SQLite and an INTEGER PRIMARY KEY
+    /// is the rowid; the timestamps are maintained by VibeDB.
+    pub fn is_insertable(&self) -> bool {
+        let rowid = self.pk && self.col_type.eq_ignore_ascii_case("INTEGER");
+        !self.generated && !rowid && !SYSTEM_COLUMNS.contains(&self.name.as_str())
+    }
+
+    /// JSON Schema of the column's values as queries return them
+    ///
+    /// INTEGER maps to `integer`, REAL to `number` and TEXT (and the
+    /// timestamps) to `string`; other declared types accept any value.
+    /// Nullable columns are a type union with `null`. System columns and
+    /// generated columns are `readOnly`.
+    pub fn json_schema(&self) -> Value {
+        let declared = self.col_type.to_ascii_uppercase();
+        // SQLite's affinity rules, in its order of precedence
+        let json_type = if declared.contains("INT") {
+            Some("integer")
+        } else if ["CHAR", "CLOB", "TEXT", "DATE", "TIME", "BLOB"].iter().any(|t| declared.contains(t)) {
+            Some("string")
+        } else if ["REAL", "FLOA", "DOUB"].iter().any(|t| declared.contains(t)) {
+            Some("number")
+        } else {
+            None
+        };
+
+        let mut schema = Map::new();
+        if let Some(json_type) = json_type {
+            let json_type = if self.notnull || self.pk {
+                json!(json_type)
+            } else {
+                json!([json_type, "null"])
+            };
+            schema.insert("type".to_string(), json_type);
+        }
+        let system = SYSTEM_COLUMNS.contains(&self.name.as_str());
+        if system || self.generated {
+            schema.insert("readOnly".to_string(), json!(true));
+        }
+        if system {
+            schema.insert("x-vibedb-system".to_string(), json!(true));
+        }
+        if self.generated {
+            schema.insert("x-vibedb-generated".to_string(), json!(true));
+        }
+        Value::Object(schema)
+    }
+}
+
+/// What `SchemaGuard::sanitize_keys` does when two keys sanitize to the
+/// same column name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyCollision {
+    /// Append `_2`, `_3`, ... to the later key
+    Suffix,
+    /// Refuse the payload
+    Reject,
+}
+
+/// Naming convention applied to payload keys before columns are created
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyCase {
+    /// Keys become columns as sent
+    #[default]
+    Preserve,
+    /// `userId` and `User-Name` become `user_id` and `user_name`
+    SnakeCase,
+    /// `userId` becomes `userid`
+    Lowercase,
+}
+
+impl KeyCase {
+    /// Parses `preserve`, `snake_case` or `lowercase`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "preserve" | "" => Some(Self::Preserve),
+            "snake_case" | "snake" => Some(Self::SnakeCase),
+            "lowercase" | "lower" => Some(Self::Lowercase),
+            _ => None,
+        }
+    }
+
+    /// Converts a key to this convention
+    pub fn apply(self, key: &str) -> String {
+        match self {
+            Self::Preserve => key.to_string(),
+            Self::Lowercase => key.to_lowercase(),
+            Self::SnakeCase => {
+                let chars: Vec<char> = key.chars().collect();
+                let mut snake = String::with_capacity(key.len() + 4);
+                for (i, &c) in chars.iter().enumerate() {
+                    if c == '-' || c == ' ' || c == '.' {
+                        snake.push('_');
+                    } else if c.is_uppercase() {
+                        // A word starts at `aB` and at the `B` of `ABc`
+                        let prev = i.checked_sub(1).map(|p| chars[p]);
+                        let next = chars.get(i + 1);
+                        let boundary = prev.is_some_and(|p| p.is_lowercase() || p.is_ascii_digit())
+                            || (prev.is_some_and(char::is_uppercase) && next.is_some_and(|n| n.is_lowercase()));
+                        if boundary && !snake.ends_with('_') {
+                            snake.push('_');
+                        }
+                        snake.extend(c.to_lowercase());
+                    } else {
+                        snake.push(c);
+                    }
+                }
+                snake
+            }
+        }
+    }
 }
 
 /// Schema Guard - manages automatic schema evolution
 pub struct SchemaGuard {
     /// Thread-safe schema cache: table_name -> Vec<column_names>
     schema_cache: DashMap<String, Vec<ColumnInfo>>,
+    /// `VibeStore::reopens` the cached schemas were read under
+    cache_reopens: AtomicU64,
     /// Reference to the database store
     store: Arc<VibeStore>,
+    /// Counts migrations per collection, when set
+    perf: Option<Arc<PerfRegistry>>,
+    /// Per-collection settings consulted for `freeze`, when set
+    settings: Option<Arc<SettingsStore>>,
+    /// Creates `vibe_migrations` on first use
+    migrations_table: OnceCell<()>,
+    /// Length limit and truncation policy for created identifiers
+    identifiers: IdentifierConfig,
+    /// Depth and size limits for nested values
+    payload_limits: PayloadConfig,
+    /// Aliases of shortened names: (table or "", lowercase original) -> stored
+    aliases: DashMap<(String, String), String>,
+    /// First original of each aliased column: (table, lowercase stored) -> original
+    originals: DashMap<(String, String), String>,
+    /// Creates and loads `vibe_column_aliases` on first use
+    aliases_loaded: OnceCell<()>,
 }
 
 impl SchemaGuard {
@@ -115,8 +434,156 @@ impl SchemaGuard {
     pub fn new(store: Arc<VibeStore>) -> Self {
         Self {
             schema_cache: DashMap::new(),
+            cache_reopens: AtomicU64::new(0),
             store,
+            perf: None,
+            settings: None,
+            migrations_table: OnceCell::new(),
+            identifiers: IdentifierConfig::default(),
+            payload_limits: PayloadConfig::default(),
+            aliases: DashMap::new(),
+            originals: DashMap::new(),
+            aliases_loaded: OnceCell::new(),
+        }
+    }
+
+    /// Applies a length limit and truncation policy to created identifiers
+    pub fn with_identifiers(mut self, mut identifiers: IdentifierConfig) -> Self {
+        identifiers.max_length = identifiers.max_length.clamp(SHORTENED_HASH_DIGITS + 2, MAX_IDENTIFIER_LENGTH);
+        self.identifiers = identifiers;
+        self
+    }
+
+    /// Applies depth and size limits to nested values of written documents
+    pub fn with_payload_limits(mut self, limits: PayloadConfig) -> Self {
+        self.payload_limits = limits;
+        self
+    }
+
+    /// Largest JSON size, in bytes, of a nested value of a written document
+    pub fn max_nested_bytes(&self) -> usize {
+        self.payload_limits.max_nested_bytes
+    }
+
+    /// Rejects documents nested deeper than the configured depth or with a
+    /// nested value whose JSON is larger than the configured size
+    ///
+    /// Both checks stop as soon as the limit is passed, so a pathological
+    /// value costs no more than the limit to reject.
+    pub fn check_nesting(&self, obj: &Map<String, Value>) -> VibeResult<()> {
+        let PayloadConfig { max_depth, max_nested_bytes, .. } = self.payload_limits;
+        for (key, value) in obj {
+            if !(value.is_object() || value.is_array()) {
+                continue;
+            }
+            // The document is level 1, so its values start at level 2
+            if nested_deeper_than(value, max_depth.saturating_sub(1)) {
+                return Err(VibeError::InvalidPayload(format!(
+                    "'{}' is nested too deeply; documents may be at most {} levels deep",
+                    key, max_depth
+                )));
+            }
+            let mut size = SizeLimit {
+                written: 0,
+                limit: max_nested_bytes,
+            };
+            if serde_json::to_writer(&mut size, value).is_err() {
+                return Err(VibeError::InvalidPayload(format!(
+                    "'{}' is too large; nested values may be at most {} bytes as JSON",
+                    key, max_nested_bytes
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// With `strict_numbers`, rejects top-level numbers that can't be
+    /// stored exactly, naming their keys
+    pub fn check_numbers(&self, obj: &Map<String, Value>) -> VibeResult<()> {
+        if !self.payload_limits.strict_numbers {
+            return Ok(());
+        }
+        let inexact: Vec<String> = obj
+            .iter()
+            .filter(|(_, value)| value.as_number().and_then(InexactNumber::classify).is_some())
+            .map(|(key, _)| key.clone())
+            .collect();
+        if inexact.is_empty() {
+            return Ok(());
         }
+        Err(VibeError::InvalidFields {
+            message: format!(
+                "{} can't be stored exactly; send integers beyond {} as strings",
+                inexact.join(", "),
+                i64::MAX
+            ),
+            fields: inexact,
+        })
+    }
+
+    /// Honors the `freeze` setting of each collection
+    pub fn with_settings(mut self, settings: Arc<SettingsStore>) -> Self {
+        self.settings = Some(settings);
+        self
+    }
+
+    /// Counts table creations and column additions in `perf`
+    pub fn with_perf(mut self, perf: Arc<PerfRegistry>) -> Self {
+        self.perf = Some(perf);
+        self
+    }
+
+    /// Counts a migration of `statements` DDL statements in `perf`, the
+    /// request's tally and the current span
+    fn record_migration(&self, table: &str, statements: u64, elapsed: Duration) {
+        if let Some(perf) = &self.perf {
+            perf.record_migration(table, elapsed);
+        }
+        let _ = MIGRATION_TALLY.try_with(|tally| {
+            let mut sum = tally.get();
+            sum.ddl_statements += statements;
+            sum.ddl_time += elapsed;
+            tally.set(sum);
+        });
+        Span::current().record("ddl_ms", elapsed.as_secs_f64() * 1000.0);
+    }
+
+    /// Error for a payload that would take `table` past
+    /// `MAX_COLUMNS_PER_TABLE`, logged and counted since it may be an attack
+    fn column_limit_exceeded(&self, table: &str, existing: usize, new_columns: Vec<String>) -> VibeError {
+        warn!(
+            target: "vibedb::security",
+            "Rejected {} new column(s) for {}: {} of {} columns exist",
+            new_columns.len(),
+            table,
+            existing,
+            MAX_COLUMNS_PER_TABLE
+        );
+        if let Some(perf) = &self.perf {
+            perf.record_column_limit_rejection(table);
+        }
+        VibeError::ColumnLimitExceeded {
+            table: table.to_string(),
+            limit: MAX_COLUMNS_PER_TABLE,
+            existing,
+            new_columns,
+        }
+    }
+
+    /// Records the column count of `table` after a migration and warns once
+    /// it nears `MAX_COLUMNS_PER_TABLE`
+    async fn check_column_headroom(&self, table: &str) -> VibeResult<()> {
+        let columns = self.get_table_schema(table).await?.len();
+        if let Some(perf) = &self.perf {
+            perf.record_columns(table, columns);
+        }
+        if columns * 100 >= MAX_COLUMNS_PER_TABLE * COLUMN_WARNING_PERCENT {
+            warn!(
+                "⚠️ {} has {} of {} columns; payloads adding more will be rejected",
+                table, columns, MAX_COLUMNS_PER_TABLE
+            );
+        }
+        Ok(())
     }
 
     /// Validates that an identifier is safe for use as a table/column name
@@ -124,13 +591,14 @@ impl SchemaGuard {
     /// # Rules
     /// - Must match: `^[a-zA-Z_][a-zA-Z0-9_]*$`
     /// - Must not be a SQL reserved keyword
-    /// - Maximum length: 128 characters
+    /// - Maximum length: `MAX_IDENTIFIER_LENGTH` characters (created tables
+    ///   and columns are held to `identifiers.max_length` on top)
     pub fn validate_identifier(name: &str) -> VibeResult<()> {
         // Length check
-        if name.is_empty() || name.len() > 128 {
+        if name.is_empty() || name.len() > MAX_IDENTIFIER_LENGTH {
             return Err(VibeError::InvalidIdentifier(format!(
-                "Identifier '{}' must be 1-128 characters",
-                name
+                "Identifier '{}' must be 1-{} characters",
+                name, MAX_IDENTIFIER_LENGTH
             )));
         }
 
@@ -156,6 +624,15 @@ impl SchemaGuard {
     /// Sanitizes a string to be a valid SQL identifier
     /// Replaces invalid characters with underscores
     pub fn sanitize_identifier(name: &str) -> String {
+        Self::sanitize_identifier_to(name, DEFAULT_MAX_IDENTIFIER_LENGTH)
+    }
+
+    /// Sanitizes like `sanitize_identifier`, shortening to `max_length`
+    ///
+    /// A name over the limit keeps a prefix and ends in `_` plus a hash of
+    /// the whole original name, so distinct long names with a common prefix
+    /// stay distinct and the same name always shortens the same way.
+    pub fn sanitize_identifier_to(name: &str, max_length: usize) -> String {
         let sanitized: String = name
             .chars()
             .enumerate()
@@ -174,16 +651,258 @@ impl SchemaGuard {
             })
             .collect();
 
-        // Truncate to max length
-        sanitized.chars().take(128).collect()
+        if sanitized.len() <= max_length {
+            return sanitized;
+        }
+        // FNV-1a: stable across builds, unlike the std hasher
+        let hash = name.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        });
+        let prefix_len = max_length.saturating_sub(SHORTENED_HASH_DIGITS + 1);
+        format!(
+            "{}_{:0width$x}",
+            &sanitized[..prefix_len],
+            hash & 0xffff_ffff,
+            width = SHORTENED_HASH_DIGITS
+        )
+    }
+
+    /// Rejects names over `identifiers.max_length`
+    fn check_length(&self, name: &str, kind: &str) -> VibeResult<()> {
+        let max = self.identifiers.max_length;
+        if name.len() > max {
+            return Err(VibeError::InvalidIdentifier(format!(
+                "{} '{}' is {} characters, over the limit of {} (enable identifier truncation to shorten long names)",
+                kind,
+                name,
+                name.len(),
+                max
+            )));
+        }
+        Ok(())
+    }
+
+    async fn load_aliases(&self) -> VibeResult<()> {
+        self.aliases_loaded
+            .get_or_try_init(|| async {
+                self.store
+                    .execute_batch(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS vibe_column_aliases (
+                            table_name TEXT NOT NULL,
+                            original TEXT NOT NULL,
+                            stored TEXT NOT NULL,
+                            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                            PRIMARY KEY (table_name, original),
+                            UNIQUE (table_name, stored)
+                        );
+                        "#
+                        .to_string(),
+                    )
+                    .await?;
+                let rows = self
+                    .store
+                    .query_simple(
+                        "SELECT table_name, original, stored FROM vibe_column_aliases ORDER BY rowid".to_string(),
+                    )
+                    .await?;
+                for row in rows {
+                    let text = |i: usize| row.get(i).and_then(|(_, v)| v.as_str()).unwrap_or_default().to_string();
+                    self.remember_alias(&text(0), &text(1), &text(2));
+                }
+                Ok::<(), VibeError>(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    fn remember_alias(&self, scope: &str, original: &str, stored: &str) {
+        self.aliases
+            .insert((scope.to_string(), original.to_lowercase()), stored.to_string());
+        self.originals
+            .entry((scope.to_string(), stored.to_lowercase()))
+            .or_insert_with(|| original.to_string());
+    }
+
+    /// Stored name of `name` in `scope`, creating an alias if allowed
+    ///
+    /// Keys are converted to `identifiers.key_case` first. Names that need
+    /// neither conversion nor shortening are returned unchanged.
+    async fn resolve_alias(&self, scope: &str, name: &str, kind: &str, create: bool) -> VibeResult<String> {
+        self.load_aliases().await?;
+        if let Some(stored) = self.aliases.get(&(scope.to_string(), name.to_lowercase())) {
+            return Ok(stored.clone());
+        }
+        if !create {
+            return Ok(name.to_string());
+        }
+
+        let mut stored = if scope == TABLE_ALIAS_SCOPE {
+            name.to_string()
+        } else {
+            self.identifiers.key_case.apply(name)
+        };
+        if stored.len() > self.identifiers.max_length {
+            if !self.identifiers.truncate {
+                self.check_length(name, kind)?;
+            }
+            stored = Self::sanitize_identifier_to(&stored, self.identifiers.max_length);
+        }
+        if stored == name {
+            return Ok(stored);
+        }
+
+        self.store
+            .execute(
+                "INSERT OR IGNORE INTO vibe_column_aliases (table_name, original, stored) VALUES (?, ?, ?)"
+                    .to_string(),
+                vec![
+                    SqlValue::Text(scope.to_string()),
+                    SqlValue::Text(name.to_string()),
+                    SqlValue::Text(stored.clone()),
+                ],
+            )
+            .await?;
+        info!("✂️ {} '{}' stored as '{}'", kind, name, stored);
+        self.remember_alias(scope, name, &stored);
+        Ok(stored)
+    }
+
+    /// Stored name of a collection
+    ///
+    /// With `create`, an over-long name is shortened and recorded (when
+    /// truncation is on) or rejected; without it, only existing aliases
+    /// apply, for reads.
+    pub async fn resolve_table(&self, table: &str, create: bool) -> VibeResult<String> {
+        self.resolve_alias(TABLE_ALIAS_SCOPE, table, "Collection name", create)
+            .await
+    }
+
+    /// Stored name of a column of `table`, following existing aliases only
+    pub async fn resolve_column(&self, table: &str, column: &str) -> VibeResult<String> {
+        self.resolve_alias(table, column, "Key", false).await
+    }
+
+    /// Renames the aliased columns of a row back to the key first pushed
+    /// for them
+    pub async fn restore_original_keys(&self, table: &str, row: &mut Value) -> VibeResult<()> {
+        self.load_aliases().await?;
+        let Some(obj) = row.as_object_mut() else {
+            return Ok(());
+        };
+        for column in obj.keys().cloned().collect::<Vec<_>>() {
+            let original = self
+                .originals
+                .get(&(table.to_string(), column.to_lowercase()))
+                .map(|original| original.clone());
+            if let Some(original) = original.filter(|original| !obj.contains_key(original)) {
+                if let Some(value) = obj.remove(&column) {
+                    obj.insert(original, value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Renames aliased, differently cased and over-long keys of a payload
+    /// to their stored names
+    ///
+    /// Keys are converted to `identifiers.key_case`, and over-long keys are
+    /// shortened and recorded when truncation is on and rejected otherwise. Returns the renames made, original -> stored.
+    pub async fn apply_column_aliases(
+        &self,
+        table: &str,
+        payload: &mut Value,
+    ) -> VibeResult<BTreeMap<String, String>> {
+        let mut applied = BTreeMap::new();
+        let Some(obj) = payload.as_object_mut() else {
+            return Ok(applied);
+        };
+        for key in obj.keys().cloned().collect::<Vec<_>>() {
+            let stored = self.resolve_alias(table, &key, "Key", true).await?;
+            if stored != key {
+                if let Some(value) = obj.remove(&key) {
+                    obj.insert(stored.clone(), value);
+                }
+                applied.insert(key, stored);
+            }
+        }
+        Ok(applied)
+    }
+
+    /// Sanitizes every key of a payload object, returning it with the
+    /// renames made (original -> new)
+    ///
+    /// Distinct keys can sanitize to the same identifier (`user-name` and
+    /// `user name` both become `user_name`), and SQLite compares column
+    /// names case-insensitively, so `Name` and `name` collide as well. Keys
+    /// that are already valid keep their name, the first of a case-only
+    /// pair winning; with `KeyCollision::Suffix` the others get `_2`, `_3`,
+    /// ... until they are unique, with `KeyCollision::Reject` the payload is
+    /// refused. Values are never merged.
+    pub fn sanitize_keys(
+        object: &Map<String, Value>,
+        on_collision: KeyCollision,
+    ) -> VibeResult<(Map<String, Value>, BTreeMap<String, String>)> {
+        let mut taken: HashMap<String, &str> = HashMap::new();
+        for key in object.keys().filter(|key| Self::sanitize_identifier(key) == **key) {
+            taken.entry(key.to_lowercase()).or_insert(key.as_str());
+        }
+        let mut sanitized = Map::new();
+        let mut renames = BTreeMap::new();
+
+        for (key, value) in object {
+            let mut name = Self::sanitize_identifier(key);
+            let owner = taken.get(&name.to_lowercase()).copied();
+            if owner != Some(key.as_str()) {
+                if let Some(other) = owner {
+                    match on_collision {
+                        KeyCollision::Reject => {
+                            return Err(VibeError::InvalidFields {
+                                message: format!(
+                                    "Keys '{}' and '{}' both sanitize to '{}'",
+                                    other, key, name
+                                ),
+                                fields: vec![other.to_string(), key.clone()],
+                            })
+                        }
+                        KeyCollision::Suffix => {
+                            let base: String = name.chars().take(124).collect();
+                            name = (2..)
+                                .map(|n| format!("{}_{}", base, n))
+                                .find(|candidate| !taken.contains_key(&candidate.to_lowercase()))
+                                .unwrap_or_default();
+                        }
+                    }
+                }
+                taken.insert(name.to_lowercase(), key);
+            }
+            if name != *key {
+                renames.insert(key.clone(), name.clone());
+            }
+            sanitized.insert(name, value.clone());
+        }
+        Ok((sanitized, renames))
     }
 
     /// Gets the current schema for a table from cache or database
     async fn get_table_schema(&self, table: &str) -> VibeResult<Vec<ColumnInfo>> {
+        Ok(self.lookup_schema(table).await?.0)
+    }
+
+    /// `get_table_schema`, also telling whether the cache had it
+    ///
+    /// Hits and misses are counted in `perf`.
+    async fn lookup_schema(&self, table: &str) -> VibeResult<(Vec<ColumnInfo>, bool)> {
+        self.forget_stale_schemas();
+        let cached = self.schema_cache.get(table).map(|cached| cached.clone());
+        if let Some(perf) = &self.perf {
+            perf.record_schema_cache(cached.is_some());
+        }
         // Cache check first
-        if let Some(cached) = self.schema_cache.get(table) {
+        if let Some(cached) = cached {
             debug!("Schema cache hit for table: {}", table);
-            return Ok(cached.clone());
+            return Ok((cached, true));
         }
 
         // Cache miss - query database
@@ -195,12 +914,14 @@ impl SchemaGuard {
             self.schema_cache.insert(table.to_string(), columns.clone());
         }
 
-        Ok(columns)
+        Ok((columns, false))
     }
 
-    /// Fetches table info using PRAGMA table_info
+    /// Fetches table info using PRAGMA table_xinfo
+    ///
+    /// Unlike `table_info`, this lists generated columns (`hidden` 2 or 3).
     async fn fetch_table_info(&self, table: &str) -> VibeResult<Vec<ColumnInfo>> {
-        let sql = format!("PRAGMA table_info({})", table);
+        let sql = format!("PRAGMA table_xinfo({})", table);
         let rows = self.store.query_simple(sql).await?;
 
         let mut columns = Vec::new();
@@ -229,13 +950,20 @@ impl SchemaGuard {
                 .and_then(|(_, v)| v.as_i64())
                 .unwrap_or(0)
                 != 0;
+            let hidden = row
+                .iter()
+                .find(|(k, _)| k == "hidden")
+                .and_then(|(_, v)| v.as_i64())
+                .unwrap_or(0);
 
-            if !name.is_empty() {
+            // hidden = 1 marks virtual table internals, not real columns
+            if !name.is_empty() && hidden != 1 {
                 columns.push(ColumnInfo {
                     name,
                     col_type,
                     notnull,
                     pk,
+                    generated: hidden == 2 || hidden == 3,
                 });
             }
         }
@@ -245,11 +973,28 @@ impl SchemaGuard {
 
     /// Ensures a table exists with the base schema
     /// Creates: id, created_at, updated_at columns
+    ///
+    /// Runs in an `ensure_table` span carrying the collection, whether the
+    /// schema cache had it and the DDL time of a creation.
     pub async fn ensure_table(&self, table: &str) -> VibeResult<()> {
+        let span = info_span!("ensure_table", collection = table, cache_hit = field::Empty, ddl_ms = field::Empty);
+        self.create_table_if_missing(table).instrument(span).await
+    }
+
+    async fn create_table_if_missing(&self, table: &str) -> VibeResult<()> {
         Self::validate_identifier(table)?;
+        self.check_length(table, "Collection name")?;
+        if table == STORAGE_STREAM {
+            // Its stream carries storage events; rows pushed here could spoof them
+            return Err(VibeError::InvalidIdentifier(format!(
+                "Collection name '{}' is reserved for storage events",
+                table
+            )));
+        }
 
         // Check if table exists
-        let schema = self.get_table_schema(table).await?;
+        let (schema, cache_hit) = self.lookup_schema(table).await?;
+        Span::current().record("cache_hit", cache_hit);
         if !schema.is_empty() {
             debug!("Table '{}' already exists with {} columns", table, schema.len());
             return Ok(());
@@ -265,8 +1010,10 @@ impl SchemaGuard {
             table
         );
 
+        let started = Instant::now();
         self.store.execute_simple(create_sql).await?;
         info!("✨ Created table: {}", table);
+        self.record_migration(table, 1, started.elapsed());
 
         // Invalidate cache so next call fetches fresh schema
         self.schema_cache.remove(table);
@@ -274,59 +1021,199 @@ impl SchemaGuard {
         Ok(())
     }
 
+    /// Moves explicit `created_at`/`updated_at` values out of a payload
+    ///
+    /// For backfills, which would otherwise get the time of the import.
+    /// Values must be RFC 3339 and come back in UTC in SQLite's
+    /// `CURRENT_TIMESTAMP` format, so they sort with defaulted timestamps.
+    /// Fails if the table has no such column. Null values are dropped and
+    /// leave the default in place.
+    pub async fn take_timestamps(
+        &self,
+        table: &str,
+        payload: &mut Value,
+    ) -> VibeResult<Vec<(String, String)>> {
+        let obj = payload.as_object_mut().ok_or_else(|| {
+            VibeError::InvalidPayload("Payload must be a JSON object".to_string())
+        })?;
+        let mut present: Vec<(&str, Value)> = Vec::new();
+        for column in TIMESTAMP_COLUMNS {
+            // Any casing of the key names the column
+            let keys: Vec<String> = obj.keys().filter(|key| key.eq_ignore_ascii_case(column)).cloned().collect();
+            let values: Vec<Value> = keys
+                .iter()
+                .filter_map(|key| obj.remove(key))
+                .filter(|value| !value.is_null())
+                .collect();
+            if values.len() > 1 {
+                return Err(VibeError::InvalidFields {
+                    message: format!("{} is given more than once", column),
+                    fields: keys,
+                });
+            }
+            present.extend(values.into_iter().map(|value| (column, value)));
+        }
+        if present.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let schema = self.get_table_schema(table).await?;
+        present
+            .into_iter()
+            .map(|(column, value)| {
+                if !schema.iter().any(|c| c.name.eq_ignore_ascii_case(column)) {
+                    return Err(VibeError::InvalidFields {
+                        message: format!("Table '{}' has no {} column", table, column),
+                        fields: vec![column.to_string()],
+                    });
+                }
+                let parsed = value
+                    .as_str()
+                    .and_then(|text| chrono::DateTime::parse_from_rfc3339(text).ok())
+                    .ok_or_else(|| VibeError::InvalidFields {
+                        message: format!("{} must be an RFC 3339 timestamp, got {}", column, value),
+                        fields: vec![column.to_string()],
+                    })?;
+                let utc = parsed.with_timezone(&chrono::Utc);
+                Ok((column.to_string(), utc.format(SQLITE_TIMESTAMP_FORMAT).to_string()))
+            })
+            .collect()
+    }
+
+    /// Current time in the format of `take_timestamps`
+    pub fn now_timestamp() -> String {
+        chrono::Utc::now().format(SQLITE_TIMESTAMP_FORMAT).to_string()
+    }
+
     /// Ensures all columns from the payload exist in the table
     /// Returns the list of column names that can be used for insertion
+    ///
+    /// Keys naming system columns, in any case, are skipped (see
+    /// `system_fields`).
+    ///
+    /// When the collection's settings have `freeze` on, keys without a
+    /// column are ignored (and logged) instead of added. Fails with
+    /// `TableNotFound` if the table doesn't exist; only `ensure_table`
+    /// creates tables.
     pub async fn ensure_columns(
         &self,
         table: &str,
         payload: &Value,
+    ) -> VibeResult<Vec<String>> {
+        self.ensure_columns_hinted(table, payload, &TypeHints::new()).await
+    }
+
+    /// `ensure_columns`, creating the columns named in `hints` with the
+    /// hinted type instead of the inferred one
+    ///
+    /// Runs in an `ensure_columns` span carrying the collection, whether
+    /// the schema cache had it, the number of new columns and the DDL time
+    /// of adding them.
+    pub async fn ensure_columns_hinted(
+        &self,
+        table: &str,
+        payload: &Value,
+        hints: &TypeHints,
+    ) -> VibeResult<Vec<String>> {
+        let span = info_span!(
+            "ensure_columns",
+            collection = table,
+            cache_hit = field::Empty,
+            new_columns = field::Empty,
+            ddl_ms = field::Empty
+        );
+        self.evolve_columns(table, payload, hints).instrument(span).await
+    }
+
+    async fn evolve_columns(
+        &self,
+        table: &str,
+        payload: &Value,
+        hints: &TypeHints,
     ) -> VibeResult<Vec<String>> {
         let obj = payload.as_object().ok_or_else(|| {
             VibeError::InvalidPayload("Payload must be a JSON object".to_string())
         })?;
+        self.check_nesting(obj)?;
+        self.check_numbers(obj)?;
 
-        // Validate all keys first
-        for key in obj.keys() {
-            Self::validate_identifier(key)?;
+        // Get current schema; DDL against a missing table would only fail in SQLite
+        let (current_schema, cache_hit) = self.lookup_schema(table).await?;
+        Span::current().record("cache_hit", cache_hit);
+        if current_schema.is_empty() {
+            return Err(VibeError::TableNotFound(table.to_string()));
         }
-
-        // Get current schema
-        let current_schema = self.get_table_schema(table).await?;
+        // SQLite column names are case-insensitive
         let existing_columns: HashSet<String> = current_schema
             .iter()
-            .map(|c| c.name.clone())
+            .map(|c| c.name.to_lowercase())
+            .collect();
+        let non_insertable: HashSet<String> = current_schema
+            .iter()
+            .filter(|c| !c.is_insertable())
+            .map(|c| c.name.to_lowercase())
             .collect();
 
+        let frozen = match &self.settings {
+            Some(settings) => settings.get(table).await?.freeze,
+            None => false,
+        };
+        let mut ignored = Vec::new();
+        if frozen {
+            for key in obj.keys() {
+                if !existing_columns.contains(&key.to_lowercase()) {
+                    ignored.push(key.as_str());
+                }
+            }
+            if !ignored.is_empty() {
+                warn!("🧊 Schema of {} is frozen, ignoring keys: {}", table, ignored.join(", "));
+            }
+        }
+
+        // Validate all keys first
+        for key in obj.keys().filter(|key| !ignored.contains(&key.as_str())) {
+            Self::validate_identifier(key)?;
+            if !existing_columns.contains(&key.to_lowercase()) {
+                self.check_length(key, "Key")?;
+            }
+        }
+
         // Check column limit
         let new_columns: Vec<_> = obj
             .iter()
-            .filter(|(key, val)| !val.is_null() && !existing_columns.contains(*key))
+            .filter(|(key, val)| {
+                !val.is_null()
+                    && !existing_columns.contains(&key.to_lowercase())
+                    && !is_system_column(key)
+                    && !ignored.contains(&key.as_str())
+            })
             .collect();
 
+        Span::current().record("new_columns", new_columns.len());
         let total_columns = existing_columns.len() + new_columns.len();
         if total_columns > MAX_COLUMNS_PER_TABLE {
-            return Err(VibeError::ColumnLimitExceeded {
-                message: format!(
-                    "Table '{}' would exceed {} column limit ({} existing + {} new = {})",
-                    table,
-                    MAX_COLUMNS_PER_TABLE,
-                    existing_columns.len(),
-                    new_columns.len(),
-                    total_columns
-                ),
-            });
+            return Err(self.column_limit_exceeded(
+                table,
+                existing_columns.len(),
+                new_columns.iter().map(|(key, _)| key.to_string()).collect(),
+            ));
         }
 
         // Add missing columns
         if !new_columns.is_empty() {
-            self.add_columns(table, &new_columns).await?;
+            self.add_columns(table, &new_columns, hints).await?;
         }
 
-        // Return column names for insertion (excluding null values and system columns)
+        // Return column names for insertion (excluding null values, system
+        // columns, ignored keys and columns that can't be written, per
+        // table_xinfo)
         let insert_columns: Vec<String> = obj
             .iter()
             .filter(|(key, val)| {
-                !val.is_null() && *key != "id" && *key != "created_at" && *key != "updated_at"
+                !val.is_null()
+                    && !is_system_column(key)
+                    && !non_insertable.contains(&key.to_lowercase())
+                    && !ignored.contains(&key.as_str())
             })
             .map(|(key, _)| key.clone())
             .collect();
@@ -334,72 +1221,664 @@ impl SchemaGuard {
         Ok(insert_columns)
     }
 
+    /// `ensure_columns` for updates: keys set to null are kept as well when
+    /// their column exists and can be written, so an update can clear a
+    /// field
+    pub async fn ensure_update_columns(
+        &self,
+        table: &str,
+        payload: &Value,
+        hints: &TypeHints,
+    ) -> VibeResult<Vec<String>> {
+        let mut columns = self.ensure_columns_hinted(table, payload, hints).await?;
+        let Some(obj) = payload.as_object() else {
+            return Ok(columns);
+        };
+
+        let schema = self.get_table_schema(table).await?;
+        let cleared = obj.iter().filter(|(key, val)| {
+            val.is_null()
+                && !is_system_column(key)
+                && schema
+                    .iter()
+                    .any(|c| c.name.eq_ignore_ascii_case(key) && c.is_insertable())
+        });
+        columns.extend(cleared.map(|(key, _)| key.clone()));
+        Ok(columns)
+    }
+
     /// Adds new columns to a table
     async fn add_columns(
         &self,
         table: &str,
         columns: &[(&String, &Value)],
+        hints: &TypeHints,
     ) -> VibeResult<()> {
-        let mut migrations = Vec::new();
-        let table_name = table.to_string();
-
-        for (key, val) in columns {
-            let sqlite_type = infer_type(val);
-            let alter_sql = format!(
-                "ALTER TABLE {} ADD COLUMN {} {} DEFAULT NULL",
-                table_name,
-                key,
-                sqlite_type.as_sql()
-            );
-            migrations.push((key.to_string(), sqlite_type.as_sql().to_string(), alter_sql));
-        }
+        let typed: Vec<(String, String)> = columns
+            .iter()
+            .map(|(key, val)| {
+                let sql_type = hints.get(&key.to_lowercase()).cloned().unwrap_or_else(|| infer_type(val));
+                (key.to_string(), sql_type.as_sql().to_string())
+            })
+            .collect();
+        self.add_typed_columns(table, typed).await
+    }
+
+    /// Adds the given columns to `table` unless they already exist, keeping
+    /// their declared types. Used to clone a schema from another table.
+    ///
+    /// Returns the names of the columns that were added.
+    pub async fn ensure_columns_like(
+        &self,
+        table: &str,
+        columns: &[ColumnInfo],
+    ) -> VibeResult<Vec<String>> {
+        let current_schema = self.get_table_schema(table).await?;
+        if current_schema.is_empty() {
+            return Err(VibeError::TableNotFound(table.to_string()));
+        }
+        let existing: HashSet<String> = current_schema
+            .iter()
+            .map(|c| c.name.to_lowercase())
+            .collect();
+
+        let missing: Vec<(String, String)> = columns
+            .iter()
+            .filter(|c| !c.generated && !existing.contains(&c.name.to_lowercase()))
+            .map(|c| (c.name.clone(), c.col_type.clone()))
+            .collect();
+
+        if existing.len() + missing.len() > MAX_COLUMNS_PER_TABLE {
+            return Err(self.column_limit_exceeded(
+                table,
+                existing.len(),
+                missing.into_iter().map(|(name, _)| name).collect(),
+            ));
+        }
+
+        let added: Vec<String> = missing.iter().map(|(name, _)| name.clone()).collect();
+        if !missing.is_empty() {
+            self.add_typed_columns(table, missing).await?;
+        }
+        Ok(added)
+    }
+
+    /// Adds `(name, sql_type)` columns
+    ///
+    /// Up to `WIDE_MIGRATION_THRESHOLD` columns are added in one transaction.
+    /// Wider evolutions are split into transactions of `ALTER_CHUNK_SIZE`
+    /// columns, yielding in between so other writes can take the write lock;
+    /// a failure then leaves the chunks before it applied.
+    async fn add_typed_columns(&self, table: &str, columns: Vec<(String, String)>) -> VibeResult<()> {
+        let chunk_size = if columns.len() > WIDE_MIGRATION_THRESHOLD {
+            ALTER_CHUNK_SIZE
+        } else {
+            columns.len().max(1)
+        };
+        self.add_typed_columns_in_chunks(table, columns, chunk_size).await
+    }
+
+    async fn add_typed_columns_in_chunks(
+        &self,
+        table: &str,
+        columns: Vec<(String, String)>,
+        chunk_size: usize,
+    ) -> VibeResult<()> {
+        let started = Instant::now();
+        let total = columns.len();
+        let chunks: Vec<Vec<(String, String)>> =
+            columns.chunks(chunk_size).map(|chunk| chunk.to_vec()).collect();
+        let strategy = if chunks.len() > 1 {
+            info!(
+                "📊 Adding {} columns to {} in {} transactions of up to {}",
+                total,
+                table,
+                chunks.len(),
+                chunk_size
+            );
+            MIGRATION_CHUNKED
+        } else {
+            MIGRATION_SINGLE
+        };
 
-        self.store.with_transaction(move |conn| {
-            for (col_name, col_type, sql) in migrations {
-                debug!("Executing migration: {}", sql);
-                if let Err(e) = conn.execute(&sql, []) {
-                    warn!("Failed to add column '{}': {}", col_name, e);
-                    return Err(e);
+        let chunk_count = chunks.len();
+        let mut added = 0;
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let table_name = table.to_string();
+            let migrations: Vec<(String, String, String)> = chunk
+                .into_iter()
+                .map(|(name, col_type)| {
+                    let alter_sql = format!(
+                        "ALTER TABLE {} ADD COLUMN {} {} DEFAULT NULL",
+                        table_name, name, col_type
+                    );
+                    (name, col_type, alter_sql)
+                })
+                .collect();
+
+            added += self.store.with_transaction(move |conn| {
+                let mut added = 0;
+                for (col_name, col_type, sql) in migrations {
+                    // A concurrent evolution may have added it already
+                    let exists = conn
+                        .prepare("SELECT 1 FROM pragma_table_xinfo(?) WHERE name = ? COLLATE NOCASE")?
+                        .exists([&table_name, &col_name])?;
+                    if exists {
+                        continue;
+                    }
+                    debug!("Executing migration: {}", sql);
+                    if let Err(e) = conn.execute(&sql, []) {
+                        warn!("Failed to add column '{}': {}", col_name, e);
+                        return Err(e);
+                    }
+                    added += 1;
+                    info!("📊 Added column in tx: {}.{} ({})", table_name, col_name, col_type);
                 }
-                info!("📊 Added column in tx: {}.{} ({})", table_name, col_name, col_type);
+                Ok(added)
+            }).await?;
+
+            // Invalidate cache
+            self.schema_cache.remove(table);
+            if chunk_count > 1 {
+                info!("📊 {}: {}/{} column chunks applied", table, i + 1, chunk_count);
+                tokio::task::yield_now().await;
             }
-            Ok(())
-        }).await?;
+        }
+        let elapsed = started.elapsed();
+        self.record_migration(table, added as u64, elapsed);
+        self.log_migration(table, strategy, added, chunk_count, elapsed).await?;
+        self.check_column_headroom(table).await
+    }
 
-        // Invalidate cache
+    /// Records a schema evolution in `vibe_migrations`
+    async fn log_migration(
+        &self,
+        table: &str,
+        strategy: &str,
+        columns: usize,
+        transactions: usize,
+        elapsed: Duration,
+    ) -> VibeResult<()> {
+        self.migrations_table
+            .get_or_try_init(|| async {
+                self.store
+                    .execute_batch(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS vibe_migrations (
+                            id INTEGER PRIMARY KEY AUTOINCREMENT,
+                            table_name TEXT NOT NULL,
+                            strategy TEXT NOT NULL,
+                            columns_added INTEGER NOT NULL,
+                            transactions INTEGER NOT NULL,
+                            duration_ms INTEGER NOT NULL,
+                            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                        );
+                        "#
+                        .to_string(),
+                    )
+                    .await
+            })
+            .await?;
+        self.store
+            .execute(
+                "INSERT INTO vibe_migrations (table_name, strategy, columns_added, transactions, duration_ms) \
+                 VALUES (?, ?, ?, ?, ?)"
+                    .to_string(),
+                vec![
+                    SqlValue::Text(table.to_string()),
+                    SqlValue::Text(strategy.to_string()),
+                    SqlValue::Integer(columns as i64),
+                    SqlValue::Integer(transactions as i64),
+                    SqlValue::Integer(elapsed.as_millis() as i64),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Adds a virtual generated column computed from `expression`
+    ///
+    /// The expression may only reference existing columns of the table,
+    /// literals, operators and a small set of deterministic functions.
+    pub async fn add_generated_column(
+        &self,
+        table: &str,
+        name: &str,
+        col_type: &str,
+        expression: &str,
+    ) -> VibeResult<()> {
+        Self::validate_identifier(table)?;
+        Self::validate_identifier(name)?;
+        self.check_length(name, "Column name")?;
+
+        let col_type = col_type.trim().to_uppercase();
+        if !GENERATED_COLUMN_TYPES.contains(&col_type.as_str()) {
+            return Err(VibeError::InvalidPayload(format!(
+                "Unsupported column type '{}' (expected one of {})",
+                col_type,
+                GENERATED_COLUMN_TYPES.join(", ")
+            )));
+        }
+
+        let schema = self.fetch_table_info(table).await?;
+        if schema.is_empty() {
+            return Err(VibeError::TableNotFound(table.to_string()));
+        }
+        if schema.iter().any(|c| c.name.eq_ignore_ascii_case(name)) {
+            return Err(VibeError::Conflict(format!(
+                "Column '{}' already exists in {}",
+                name, table
+            )));
+        }
+        let columns: Vec<&str> = schema.iter().map(|c| c.name.as_str()).collect();
+        Self::validate_expression(expression, &columns)?;
+
+        let sql = format!(
+            "ALTER TABLE {} ADD COLUMN {} {} GENERATED ALWAYS AS ({}) VIRTUAL",
+            table, name, col_type, expression
+        );
+        debug!("Executing migration: {}", sql);
+        self.store.execute_simple(sql).await?;
         self.schema_cache.remove(table);
 
+        info!("🧮 Added generated column: {}.{} ({})", table, name, col_type);
         Ok(())
     }
 
-    /// Gets table statistics
-    pub async fn get_table_stats(&self, table: &str) -> VibeResult<TableStats> {
+    /// Checks that an expression only uses known columns, literals,
+    /// operators and allowed functions
+    fn validate_expression(expression: &str, columns: &[&str]) -> VibeResult<()> {
+        let invalid = |reason: String| {
+            Err(VibeError::InvalidPayload(format!(
+                "Invalid generated column expression: {}",
+                reason
+            )))
+        };
+
+        let statements = tokenize(expression);
+        let tokens = match statements.as_slice() {
+            [tokens] => tokens,
+            [] => return invalid("expression is empty".to_string()),
+            _ => return invalid("';' is not allowed".to_string()),
+        };
+
+        for (i, token) in tokens.iter().enumerate() {
+            match token {
+                Token::Word(word) => {
+                    let is_call = tokens.get(i + 1) == Some(&Token::Punct('('));
+                    let allowed = word.starts_with(|c: char| c.is_ascii_digit())
+                        || (is_call && EXPRESSION_FUNCTIONS.contains(&word.as_str()))
+                        || (!is_call && EXPRESSION_KEYWORDS.contains(&word.as_str()))
+                        || (!is_call && columns.iter().any(|c| c.eq_ignore_ascii_case(word)));
+                    if !allowed {
+                        return invalid(format!(
+                            "'{}' is not a column of this table or an allowed function",
+                            word.to_lowercase()
+                        ));
+                    }
+                }
+                Token::Punct(c) if EXPRESSION_PUNCTUATION.contains(*c) => {}
+                Token::Punct(c) => return invalid(format!("'{}' is not allowed", c)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails with `TableNotFound` unless the table exists
+    ///
+    /// Checked against the schema cache, so handlers that interpolate a
+    /// collection into SQL can call it up front without a round trip.
+    pub async fn require_table(&self, table: &str) -> VibeResult<()> {
+        self.require_columns(table).await.map(|_| ())
+    }
+
+    /// Columns of a table, failing with `TableNotFound` like `require_table`
+    pub async fn require_columns(&self, table: &str) -> VibeResult<Vec<ColumnInfo>> {
+        Self::validate_identifier(table)?;
         let schema = self.get_table_schema(table).await?;
+        if schema.is_empty() {
+            return Err(VibeError::TableNotFound(table.to_string()));
+        }
+        Ok(schema)
+    }
 
+    /// Declared types of a table's columns by lowercased name, from the
+    /// schema cache
+    pub async fn column_types(&self, table: &str) -> VibeResult<HashMap<String, String>> {
+        let schema = self.get_table_schema(table).await?;
+        Ok(schema
+            .into_iter()
+            .map(|c| (c.name.to_lowercase(), c.col_type.to_uppercase()))
+            .collect())
+    }
+
+    /// Columns of an existing table, from the schema cache
+    pub async fn get_table_columns(&self, table: &str) -> VibeResult<Vec<ColumnInfo>> {
+        let schema = self.get_table_schema(table).await?;
         if schema.is_empty() {
             return Err(VibeError::TableNotFound(table.to_string()));
         }
+        Ok(schema)
+    }
 
-        // Get row count
-        let sql = format!("SELECT COUNT(*) as count FROM {}", table);
-        let rows = self.store.query_simple(sql).await?;
-        let row_count: i64 = rows
-            .first()
-            .and_then(|r| r.first())
-            .and_then(|(_, v)| v.as_i64())
-            .unwrap_or(0);
+    /// Gets table statistics
+    pub async fn get_table_stats(&self, table: &str) -> VibeResult<TableStats> {
+        let schema = self.get_table_columns(table).await?;
 
         Ok(TableStats {
             name: table.to_string(),
             column_count: schema.len(),
-            row_count: row_count as u64,
+            row_count: self.store.count_rows(table).await?,
             columns: schema,
         })
     }
 
-    /// Clears the schema cache (useful for testing)
-    pub fn clear_cache(&self) {
+    /// JSON Schema (draft 2020-12) of a collection's rows, from the cached
+    /// schema
+    ///
+    /// Columns hidden by `mask` are left out. Every other column is listed
+    /// in `required`, as every row read carries it, if only as `null`.
+    /// Described columns carry their `description`.
+    pub async fn json_schema(
+        &self,
+        table: &str,
+        mask: &ColumnMask,
+        descriptions: &ColumnDescriptions,
+    ) -> VibeResult<Value> {
+        let schema = self.get_table_schema(table).await?;
+        if schema.is_empty() {
+            return Err(VibeError::TableNotFound(table.to_string()));
+        }
+
+        let columns: Vec<&ColumnInfo> = schema.iter().filter(|c| !mask.is_hidden(&c.name)).collect();
+        let properties: Map<String, Value> = columns
+            .iter()
+            .map(|c| {
+                let mut schema = c.json_schema();
+                if let Some(description) = descriptions.get(&c.name) {
+                    schema["description"] = json!(description);
+                }
+                (c.name.clone(), schema)
+            })
+            .collect();
+        Ok(json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": table,
+            "type": "object",
+            "properties": properties,
+            "required": columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>()
+        }))
+    }
+
+    /// Reports how populated each non-system column is
+    ///
+    /// Each chunk of columns is measured with a single aggregate SELECT.
+    /// "Recent" means `created_at` within the last `recent_days` days. With
+    /// `sample` only the newest `sample` rows (by id) are scanned, which keeps
+    /// recent counts exact while totals cover the sample only.
+    pub async fn column_usage(
+        &self,
+        table: &str,
+        recent_days: u32,
+        sample: Option<u64>,
+    ) -> VibeResult<ColumnUsageReport> {
+        Self::validate_identifier(table)?;
+        let schema = self.fetch_table_info(table).await?;
+        if schema.is_empty() {
+            return Err(VibeError::TableNotFound(table.to_string()));
+        }
+
+        let has_created_at = schema.iter().any(|c| c.name == "created_at");
+        let columns: Vec<&str> = schema
+            .iter()
+            .map(|c| c.name.as_str())
+            .filter(|name| !SYSTEM_COLUMNS.contains(name))
+            .collect();
+
+        let source = match sample {
+            Some(n) => format!("(SELECT * FROM {} ORDER BY id DESC LIMIT {})", table, n),
+            None => table.to_string(),
+        };
+        let cutoff = format!("datetime('now', '-{} days')", recent_days);
+
+        let mut rows_scanned = 0;
+        let mut usage = Vec::with_capacity(columns.len());
+        for chunk in columns.chunks(USAGE_CHUNK_SIZE) {
+            let mut exprs = vec!["COUNT(*)".to_string()];
+            for column in chunk {
+                exprs.push(format!("COUNT({})", column));
+                if has_created_at {
+                    exprs.push(format!(
+                        "COALESCE(SUM({c} IS NOT NULL AND created_at >= {cutoff}), 0)",
+                        c = column,
+                        cutoff = cutoff
+                    ));
+                    exprs.push(format!(
+                        "MAX(CASE WHEN {} IS NOT NULL THEN created_at END)",
+                        column
+                    ));
+                }
+            }
+
+            let sql = format!("SELECT {} FROM {}", exprs.join(", "), source);
+            let row = self.store.query_simple(sql).await?.into_iter().next().unwrap_or_default();
+            let mut values = row.into_iter().map(|(_, v)| v);
+
+            rows_scanned = values.next().and_then(|v| v.as_u64()).unwrap_or(0);
+            for column in chunk {
+                let non_null = values.next().and_then(|v| v.as_u64()).unwrap_or(0);
+                let (recent_non_null, last_seen) = if has_created_at {
+                    let recent = values.next().and_then(|v| v.as_u64()).unwrap_or(0);
+                    let last = values.next().and_then(|v| v.as_str().map(str::to_string));
+                    (Some(recent), last)
+                } else {
+                    (None, None)
+                };
+
+                usage.push(ColumnUsage {
+                    name: column.to_string(),
+                    non_null,
+                    removal_candidate: recent_non_null.unwrap_or(non_null) == 0,
+                    recent_non_null,
+                    last_seen,
+                });
+            }
+        }
+
+        Ok(ColumnUsageReport {
+            table: table.to_string(),
+            rows_scanned,
+            sampled: sample.is_some(),
+            recent_days,
+            columns: usage,
+        })
+    }
+
+    /// Example values of each column of the newest `sample_size` rows
+    ///
+    /// Text is cut to `max_length` characters with `substr()` in SQL, so
+    /// large values never leave the database, and one query covers a batch
+    /// of columns. BLOB columns get [`BLOB_PLACEHOLDER`] instead of
+    /// examples; columns the mask hides are left out.
+    pub async fn column_preview(
+        &self,
+        table: &str,
+        mask: &ColumnMask,
+        descriptions: &ColumnDescriptions,
+        sample_size: u64,
+        max_length: usize,
+    ) -> VibeResult<ColumnPreviewReport> {
+        let schema = self.require_columns(table).await?;
+        self.load_aliases().await?;
+
+        let order = if schema.iter().any(|c| c.name == "id") { "id" } else { "rowid" };
+        let columns: Vec<&ColumnInfo> = schema.iter().filter(|c| !mask.is_hidden(&c.name)).collect();
+        let sampled: Vec<&str> = columns
+            .iter()
+            .filter(|c| !c.col_type.to_ascii_uppercase().contains("BLOB"))
+            .map(|c| c.name.as_str())
+            .collect();
+
+        let mut examples: HashMap<&str, Vec<PreviewValue>> = HashMap::new();
+        for chunk in sampled.chunks(PREVIEW_CHUNK_SIZE) {
+            let exprs: Vec<String> = chunk
+                .iter()
+                .map(|c| {
+                    format!(
+                        "(SELECT json_group_array(json_array(\
+                         CASE typeof({c}) WHEN 'text' THEN substr({c}, 1, {len}) ELSE {c} END, \
+                         CASE typeof({c}) WHEN 'text' THEN length({c}) END)) \
+                         FROM (SELECT {c} FROM sample WHERE {c} IS NOT NULL AND typeof({c}) != 'blob' \
+                         ORDER BY {order} DESC LIMIT {examples}))",
+                        c = c,
+                        len = max_length,
+                        order = order,
+                        examples = PREVIEW_EXAMPLES
+                    )
+                })
+                .collect();
+            let sql = format!(
+                "WITH sample AS (SELECT {order}, {columns} FROM {table} ORDER BY {order} DESC LIMIT {n}) SELECT {exprs}",
+                order = order,
+                columns = chunk.join(", "),
+                table = table,
+                n = sample_size,
+                exprs = exprs.join(", ")
+            );
+            let row = self.store.query_simple(sql).await?.into_iter().next().unwrap_or_default();
+            // The store decodes the JSON arrays
+            for (column, (_, value)) in chunk.iter().zip(row) {
+                let pairs: Vec<(Value, Option<usize>)> = serde_json::from_value(value).unwrap_or_default();
+                let values = pairs
+                    .into_iter()
+                    .map(|(value, length)| PreviewValue {
+                        value,
+                        truncated: length.is_some_and(|length| length > max_length),
+                        length,
+                    })
+                    .collect();
+                examples.insert(column, values);
+            }
+        }
+
+        let columns = columns
+            .into_iter()
+            .map(|c| {
+                let origin = if SYSTEM_COLUMNS.contains(&c.name.as_str()) {
+                    ColumnOrigin::System
+                } else if c.generated {
+                    ColumnOrigin::Computed
+                } else {
+                    ColumnOrigin::Pushed
+                };
+                let examples = examples.remove(c.name.as_str());
+                ColumnPreview {
+                    name: c.name.clone(),
+                    col_type: c.col_type.clone(),
+                    origin,
+                    original_name: self
+                        .originals
+                        .get(&(table.to_string(), c.name.to_lowercase()))
+                        .map(|original| original.clone()),
+                    description: descriptions.get(&c.name).cloned(),
+                    placeholder: examples.is_none().then(|| BLOB_PLACEHOLDER.to_string()),
+                    examples: examples.unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        Ok(ColumnPreviewReport {
+            table: table.to_string(),
+            sample_size,
+            max_length,
+            columns,
+        })
+    }
+
+    /// Loads the schema of every collection not cached yet, returning how
+    /// many were loaded
+    ///
+    /// Costs one `PRAGMA table_xinfo` per collection, so it is meant for
+    /// startup, to spare each collection's first push that round trip.
+    /// Internal `vibe_` tables are left to load on first use.
+    pub async fn warm_cache(&self) -> VibeResult<usize> {
+        let mut loaded = 0;
+        for table in self.store.list_tables().await? {
+            if table.starts_with("vibe_") || self.schema_cache.contains_key(&table) {
+                continue;
+            }
+            let columns = self.fetch_table_info(&table).await?;
+            if columns.is_empty() {
+                continue;
+            }
+            // A write may have cached a newer schema meanwhile
+            self.schema_cache.entry(table).or_insert(columns);
+            loaded += 1;
+        }
+        Ok(loaded)
+    }
+
+    /// Clears the schema cache if the store was reopened since it was filled
+    fn forget_stale_schemas(&self) {
+        let reopens = self.store.reopens();
+        if self.cache_reopens.swap(reopens, Ordering::AcqRel) != reopens {
+            let cleared = self.clear_cache();
+            info!("🧹 Database reopened; dropped {} cached schemas", cleared);
+        }
+    }
+
+    /// Clears the schema cache, returning how many tables were cached
+    pub fn clear_cache(&self) -> usize {
+        let cached = self.schema_cache.len();
         self.schema_cache.clear();
+        cached
+    }
+
+    /// Drops one table from the schema cache; `false` if it was not cached
+    pub fn evict(&self, table: &str) -> bool {
+        self.schema_cache.remove(table).is_some()
+    }
+
+    /// Compares cached schemas with `PRAGMA table_xinfo`, one page at a time
+    ///
+    /// Tables are taken in name order, `limit` (at most `MAX_CACHE_CHECKS`)
+    /// from `offset`, each costing one PRAGMA. Returns the number of cached
+    /// tables and the page. A stale entry is left in place.
+    pub async fn inspect_cache(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> VibeResult<(usize, Vec<CacheEntryStatus>)> {
+        let mut tables = self.cached_tables();
+        tables.sort();
+        let total = tables.len();
+
+        let mut page = Vec::new();
+        for table in tables.into_iter().skip(offset).take(limit.min(MAX_CACHE_CHECKS)) {
+            // Evicted since the listing
+            let Some(cached) = self.schema_cache.get(&table).map(|c| c.clone()) else {
+                continue;
+            };
+            let live = self.fetch_table_info(&table).await?;
+            let names = |columns: &[ColumnInfo]| -> HashSet<String> {
+                columns.iter().map(|c| c.name.clone()).collect()
+            };
+            let (cached_names, live_names) = (names(&cached), names(&live));
+            let mut added: Vec<String> = live_names.difference(&cached_names).cloned().collect();
+            let mut removed: Vec<String> = cached_names.difference(&live_names).cloned().collect();
+            added.sort();
+            removed.sort();
+
+            page.push(CacheEntryStatus {
+                stale: cached != live,
+                table,
+                column_count: cached.len(),
+                live_column_count: live.len(),
+                added,
+                removed,
+            });
+        }
+        Ok((total, page))
     }
 
     /// Gets a list of all cached table names
@@ -408,6 +1887,36 @@ impl SchemaGuard {
     }
 }
 
+/// Whether an object or array nests more than `levels` levels below
+/// itself; scalars nest none
+fn nested_deeper_than(value: &Value, levels: usize) -> bool {
+    match value {
+        Value::Object(map) => levels == 0 || map.values().any(|v| nested_deeper_than(v, levels - 1)),
+        Value::Array(items) => levels == 0 || items.iter().any(|v| nested_deeper_than(v, levels - 1)),
+        _ => false,
+    }
+}
+
+/// Writer that only counts, failing once more than `limit` bytes arrive
+struct SizeLimit {
+    written: usize,
+    limit: usize,
+}
+
+impl std::io::Write for SizeLimit {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.written += buf.len();
+        if self.written > self.limit {
+            return Err(std::io::Error::other("size limit exceeded"));
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 /// Table statistics
 #[derive(Debug, Clone)]
 pub struct TableStats {
@@ -417,6 +1926,104 @@ pub struct TableStats {
     pub columns: Vec<ColumnInfo>,
 }
 
+/// A cached table schema checked against the database
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheEntryStatus {
+    pub table: String,
+    /// Columns in the cache
+    pub column_count: usize,
+    /// Columns in the database; 0 once the table was dropped
+    pub live_column_count: usize,
+    /// The cache no longer matches the database (names, types or flags)
+    pub stale: bool,
+    /// Live columns the cache lacks
+    pub added: Vec<String>,
+    /// Cached columns the table no longer has
+    pub removed: Vec<String>,
+}
+
+/// Population of a single column
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnUsage {
+    pub name: String,
+    /// Rows with a value in this column
+    pub non_null: u64,
+    /// Recent rows with a value (`None` when the table has no `created_at`)
+    pub recent_non_null: Option<u64>,
+    /// `created_at` of the newest row with a value
+    pub last_seen: Option<String>,
+    /// No recent row populates this column
+    pub removal_candidate: bool,
+}
+
+/// Column usage for a table
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnUsageReport {
+    pub table: String,
+    pub rows_scanned: u64,
+    pub sampled: bool,
+    pub recent_days: u32,
+    pub columns: Vec<ColumnUsage>,
+}
+
+impl ColumnUsageReport {
+    /// `ALTER TABLE ... DROP COLUMN` for every removal candidate
+    pub fn drop_statements(&self) -> Vec<String> {
+        self.columns
+            .iter()
+            .filter(|c| c.removal_candidate)
+            .map(|c| format!("ALTER TABLE {} DROP COLUMN {}", self.table, c.name))
+            .collect()
+    }
+}
+
+/// Where a column came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnOrigin {
+    /// `id` and the timestamps, maintained by VibeDB
+    System,
+    /// Generated column defined through `/v1/tables/:collection/computed`
+    Computed,
+    /// Added by schema evolution for a pushed key
+    Pushed,
+}
+
+/// An example value, cut to the preview's `max_length`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PreviewValue {
+    pub value: Value,
+    /// The stored text is longer than `value`
+    pub truncated: bool,
+    /// Characters in the stored text (`None` for other values)
+    pub length: Option<usize>,
+}
+
+/// Declared type, origin and examples of a column
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnPreview {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub col_type: String,
+    pub origin: ColumnOrigin,
+    /// Key first pushed for an aliased column
+    pub original_name: Option<String>,
+    pub description: Option<String>,
+    /// Shown instead of examples for BLOB columns
+    pub placeholder: Option<String>,
+    /// Newest non-null values first
+    pub examples: Vec<PreviewValue>,
+}
+
+/// Column preview of a table
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnPreviewReport {
+    pub table: String,
+    pub sample_size: u64,
+    pub max_length: usize,
+    pub columns: Vec<ColumnPreview>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -445,6 +2052,102 @@ mod tests {
         assert_eq!(SchemaGuard::sanitize_identifier("user name"), "user_name");
     }
 
+    #[test]
+    fn test_sanitize_identifier_shortens_with_hash() {
+        let a = format!("{}a", "k".repeat(199));
+        let b = format!("{}b", "k".repeat(199));
+        let short_a = SchemaGuard::sanitize_identifier_to(&a, 64);
+        assert_eq!(short_a.len(), 64);
+        assert!(short_a.starts_with(&"k".repeat(55)));
+        assert_eq!(short_a, SchemaGuard::sanitize_identifier_to(&a, 64));
+        assert_ne!(short_a, SchemaGuard::sanitize_identifier_to(&b, 64));
+        assert!(SchemaGuard::validate_identifier(&short_a).is_ok());
+        assert_eq!(SchemaGuard::sanitize_identifier_to("short", 64), "short");
+    }
+
+    #[test]
+    fn test_key_case_conversion() {
+        let snake = KeyCase::SnakeCase;
+        assert_eq!(snake.apply("userId"), "user_id");
+        assert_eq!(snake.apply("HTTPStatus"), "http_status");
+        assert_eq!(snake.apply("userID"), "user_id");
+        assert_eq!(snake.apply("Order-Total"), "order_total");
+        assert_eq!(snake.apply("address2Line"), "address2_line");
+        assert_eq!(snake.apply("already_snake"), "already_snake");
+        assert_eq!(KeyCase::Lowercase.apply("userId"), "userid");
+        assert_eq!(KeyCase::Preserve.apply("userId"), "userId");
+        assert_eq!(KeyCase::parse("SNAKE_CASE"), Some(KeyCase::SnakeCase));
+        assert_eq!(KeyCase::parse("kebab"), None);
+    }
+
+    #[test]
+    fn test_sanitize_keys_handles_collisions() {
+        let payload = serde_json::json!({
+            "user-name": 1,
+            "user name": 2,
+            "User_Name": 3,
+            "user_name_2": 4,
+            "ok": 5
+        });
+        let object = payload.as_object().unwrap();
+
+        let (suffixed, renames) = SchemaGuard::sanitize_keys(object, KeyCollision::Suffix).unwrap();
+        assert_eq!(renames.len(), 2);
+        assert_eq!(renames["user name"], "user_name_3");
+        assert_eq!(
+            Value::Object(suffixed),
+            serde_json::json!({
+                "User_Name": 3,
+                "user_name_3": 2,
+                "user_name_4": 1,
+                "user_name_2": 4,
+                "ok": 5
+            })
+        );
+
+        let err = SchemaGuard::sanitize_keys(object, KeyCollision::Reject).unwrap_err();
+        assert!(err.to_string().contains("both sanitize to 'user_name'"), "{}", err);
+
+        let clean = serde_json::json!({"a-b": 1, "c": 2});
+        let (sanitized, _) = SchemaGuard::sanitize_keys(clean.as_object().unwrap(), KeyCollision::Reject).unwrap();
+        assert_eq!(Value::Object(sanitized), serde_json::json!({"a_b": 1, "c": 2}));
+
+        // Valid keys differing only in case collide too
+        let cased = serde_json::json!({"Name": 1, "name": 2});
+        let err = SchemaGuard::sanitize_keys(cased.as_object().unwrap(), KeyCollision::Reject).unwrap_err();
+        assert!(err.to_string().contains("'Name' and 'name'"), "{}", err);
+        let (suffixed, renames) = SchemaGuard::sanitize_keys(cased.as_object().unwrap(), KeyCollision::Suffix).unwrap();
+        assert_eq!(Value::Object(suffixed), serde_json::json!({"Name": 1, "name_2": 2}));
+        assert_eq!(renames["name"], "name_2");
+    }
+
+    #[tokio::test]
+    async fn test_reopen_clears_cached_schemas() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("replaced.db");
+        let store = VibeStore::new(&path).await.unwrap().with_reopen_policy(crate::db::ReopenPolicy {
+            fault_threshold: 1,
+            ..Default::default()
+        });
+        let store = Arc::new(store);
+        store.execute_batch("PRAGMA journal_mode=DELETE".to_string()).await.unwrap();
+        let guard = SchemaGuard::new(store.clone());
+        guard.ensure_table("orders").await.unwrap();
+        assert!(guard.get_table_columns("orders").await.is_ok());
+
+        // The file goes away; the failed write reopens a fresh one
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+        assert!(store.execute("INSERT INTO orders DEFAULT VALUES".to_string(), vec![]).await.is_err());
+        assert_eq!(store.reopens(), 1);
+
+        let err = guard.get_table_columns("orders").await.unwrap_err();
+        assert_eq!(err.error_code(), "TABLE_NOT_FOUND");
+        guard.ensure_table("orders").await.unwrap();
+        store.execute("INSERT INTO orders DEFAULT VALUES".to_string(), vec![]).await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_ensure_table() {
         let store = Arc::new(VibeStore::in_memory().await.unwrap());
@@ -462,6 +2165,42 @@ mod tests {
         assert_eq!(stats.column_count, 3); // id, created_at, updated_at
     }
 
+    #[tokio::test]
+    async fn test_ensure_columns_never_creates_tables() {
+        let store = Arc::new(VibeStore::in_memory().await.unwrap());
+        let guard = SchemaGuard::new(store);
+
+        let err = guard.ensure_columns("ghosts", &json!({"name": "casper"})).await.unwrap_err();
+        assert!(matches!(err, VibeError::TableNotFound(_)), "{:?}", err);
+        let err = guard.ensure_update_columns("ghosts", &json!({"name": null}), &TypeHints::new()).await.unwrap_err();
+        assert!(matches!(err, VibeError::TableNotFound(_)), "{:?}", err);
+        assert!(guard.get_table_schema("ghosts").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_migrations_are_tallied_per_scope() {
+        let store = Arc::new(VibeStore::in_memory().await.unwrap());
+        let guard = SchemaGuard::new(store);
+
+        let ((), tally) = tally_migrations(async {
+            guard.ensure_table("events").await.unwrap();
+            guard.ensure_columns("events", &json!({"a": 1, "b": "x"})).await.unwrap();
+            assert_eq!(current_migrations().ddl_statements, 3);
+        })
+        .await;
+        assert_eq!(tally.ddl_statements, 3);
+        assert!(tally.ddl_time > Duration::ZERO);
+
+        // Nothing new to add, and nothing tallied outside a scope
+        let ((), tally) = tally_migrations(async {
+            guard.ensure_columns("events", &json!({"a": 2})).await.unwrap();
+        })
+        .await;
+        assert_eq!(tally, MigrationTally::default());
+        guard.ensure_columns("events", &json!({"c": 1})).await.unwrap();
+        assert_eq!(current_migrations(), MigrationTally::default());
+    }
+
     #[tokio::test]
     async fn test_ensure_columns() {
         let store = Arc::new(VibeStore::in_memory().await.unwrap());
@@ -482,4 +2221,292 @@ mod tests {
         let stats = guard.get_table_stats("products").await.unwrap();
         assert_eq!(stats.column_count, 6); // 3 base + 3 new
     }
+
+    #[tokio::test]
+    async fn test_system_columns_are_skipped_in_any_case() {
+        let store = Arc::new(VibeStore::in_memory().await.unwrap());
+        let guard = SchemaGuard::new(store.clone());
+        guard.ensure_table("products").await.unwrap();
+        // A table without timestamps gets no column for them either
+        store.execute_batch("CREATE TABLE legacy (id INTEGER PRIMARY KEY, name TEXT)".to_string()).await.unwrap();
+
+        let payload = json!({"ID": 7, "Created_At": "2020-01-01", "updated_AT": "x", "name": "a"});
+        assert_eq!(system_fields(&payload), ["Created_At", "ID", "updated_AT"]);
+        for table in ["products", "legacy"] {
+            let columns = guard.ensure_columns(table, &payload).await.unwrap();
+            assert_eq!(columns, ["name"], "{}", table);
+        }
+        assert_eq!(guard.get_table_schema("legacy").await.unwrap().len(), 2);
+        let columns = guard.ensure_update_columns("products", &json!({"UPDATED_AT": null, "name": null}), &TypeHints::new()).await.unwrap();
+        assert_eq!(columns, ["name"]);
+
+        let mut backfill = json!({"Created_At": "2020-01-01T00:00:00Z", "name": "a"});
+        let timestamps = guard.take_timestamps("products", &mut backfill).await.unwrap();
+        assert_eq!(timestamps, [("created_at".to_string(), "2020-01-01 00:00:00".to_string())]);
+        assert_eq!(backfill, json!({"name": "a"}));
+        let mut twice = json!({"created_at": "2020-01-01T00:00:00Z", "CREATED_AT": "2021-01-01T00:00:00Z"});
+        let err = guard.take_timestamps("products", &mut twice).await.unwrap_err();
+        assert!(matches!(err, VibeError::InvalidFields { .. }), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_warm_cache_loads_collections() {
+        let store = Arc::new(VibeStore::in_memory().await.unwrap());
+        SchemaGuard::new(store.clone()).ensure_table("orders").await.unwrap();
+        store
+            .execute_batch("CREATE TABLE vibe_internal (id INTEGER PRIMARY KEY);".to_string())
+            .await
+            .unwrap();
+
+        let guard = SchemaGuard::new(store);
+        assert!(guard.cached_tables().is_empty());
+        assert_eq!(guard.warm_cache().await.unwrap(), 1);
+        assert_eq!(guard.cached_tables(), vec!["orders".to_string()]);
+        assert_eq!(guard.warm_cache().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_nesting_limits() {
+        let store = Arc::new(VibeStore::in_memory().await.unwrap());
+        let guard = SchemaGuard::new(store).with_payload_limits(PayloadConfig {
+            max_depth: 3,
+            max_nested_bytes: 32,
+            ..PayloadConfig::default()
+        });
+        guard.ensure_table("docs").await.unwrap();
+
+        // Three levels: the document, `meta` and `tags`
+        let columns = guard
+            .ensure_columns("docs", &json!({"meta": {"tags": ["a", "b"]}, "title": "x".repeat(100)}))
+            .await
+            .unwrap();
+        assert_eq!(columns.len(), 2);
+
+        let err = guard.ensure_columns("docs", &json!({"meta": {"tags": [[]]}})).await.unwrap_err();
+        assert!(matches!(&err, VibeError::InvalidPayload(m) if m.contains("'meta'") && m.contains("3 levels")));
+        let err = guard.ensure_columns("docs", &json!({"tags": ["y".repeat(40)]})).await.unwrap_err();
+        assert!(matches!(&err, VibeError::InvalidPayload(m) if m.contains("'tags'") && m.contains("32 bytes")));
+        // Nothing was added for rejected documents
+        assert_eq!(guard.get_table_stats("docs").await.unwrap().column_count, 5);
+    }
+
+    #[tokio::test]
+    async fn test_column_usage() {
+        let store = Arc::new(VibeStore::in_memory().await.unwrap());
+        let guard = SchemaGuard::new(store.clone());
+
+        guard.ensure_table("users").await.unwrap();
+        guard
+            .ensure_columns("users", &serde_json::json!({"fax": "x", "email": "x", "unused": "x"}))
+            .await
+            .unwrap();
+
+        store
+            .execute_batch(
+                "INSERT INTO users (fax, email, created_at) VALUES ('555', 'a@x', datetime('now', '-400 days'));
+                 INSERT INTO users (fax, created_at) VALUES ('556', datetime('now', '-200 days'));
+                 INSERT INTO users (email) VALUES ('b@x');
+                 INSERT INTO users (email) VALUES ('c@x');"
+                    .to_string(),
+            )
+            .await
+            .unwrap();
+
+        let report = guard.column_usage("users", 90, None).await.unwrap();
+        assert_eq!(report.rows_scanned, 4);
+        let usage = |name: &str| report.columns.iter().find(|c| c.name == name).unwrap();
+
+        assert_eq!(usage("fax").non_null, 2);
+        assert_eq!(usage("fax").recent_non_null, Some(0));
+        assert!(usage("fax").last_seen.is_some());
+        assert!(usage("fax").removal_candidate);
+
+        assert_eq!(usage("email").non_null, 3);
+        assert_eq!(usage("email").recent_non_null, Some(2));
+        assert!(!usage("email").removal_candidate);
+
+        assert_eq!(usage("unused").non_null, 0);
+        assert!(usage("unused").last_seen.is_none());
+        assert_eq!(
+            report.drop_statements(),
+            vec![
+                "ALTER TABLE users DROP COLUMN fax".to_string(),
+                "ALTER TABLE users DROP COLUMN unused".to_string(),
+            ]
+        );
+
+        // The sample only sees the two newest rows
+        let sampled = guard.column_usage("users", 90, Some(2)).await.unwrap();
+        assert_eq!(sampled.rows_scanned, 2);
+        assert_eq!(sampled.columns.iter().find(|c| c.name == "fax").unwrap().non_null, 0);
+    }
+
+    #[tokio::test]
+    async fn test_generated_columns() {
+        let store = Arc::new(VibeStore::in_memory().await.unwrap());
+        let guard = SchemaGuard::new(store.clone());
+
+        guard.ensure_table("people").await.unwrap();
+        guard
+            .ensure_columns("people", &serde_json::json!({"first": "Ada", "last": "Lovelace"}))
+            .await
+            .unwrap();
+
+        for expression in [
+            "first || ' ' || missing",
+            "(SELECT first FROM people)",
+            "first; DROP TABLE people",
+            "random()",
+        ] {
+            assert!(guard
+                .add_generated_column("people", "full_name", "TEXT", expression)
+                .await
+                .is_err());
+        }
+        guard
+            .add_generated_column("people", "full_name", "text", "first || ' ' || upper(last)")
+            .await
+            .unwrap();
+
+        let stats = guard.get_table_stats("people").await.unwrap();
+        let full_name = stats.columns.iter().find(|c| c.name == "full_name").unwrap();
+        assert!(full_name.generated);
+
+        // Generated columns are computed, never inserted
+        let payload = serde_json::json!({"first": "Grace", "last": "Hopper", "full_name": "x"});
+        let columns = guard.ensure_columns("people", &payload).await.unwrap();
+        assert!(!columns.contains(&"full_name".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_insertable_columns_follow_table_xinfo() {
+        let store = Arc::new(VibeStore::in_memory().await.unwrap());
+        let guard = SchemaGuard::new(store.clone());
+
+        // Created outside VibeDB: stored and virtual generated columns
+        store
+            .execute_batch(
+                "CREATE TABLE lines (
+                    line_no INTEGER PRIMARY KEY,
+                    qty INTEGER,
+                    price REAL,
+                    total REAL GENERATED ALWAYS AS (qty * price) STORED,
+                    label TEXT AS ('#' || line_no)
+                );"
+                .to_string(),
+            )
+            .await
+            .unwrap();
+
+        let payload = serde_json::json!({"line_no": 7, "qty": 2, "price": 1.5, "TOTAL": 0, "label": "x"});
+        let columns = guard.ensure_columns("lines", &payload).await.unwrap();
+        assert_eq!(columns, vec!["price", "qty"]);
+
+        let sql = format!("INSERT INTO lines ({}) VALUES (?, ?)", columns.join(", "));
+        store
+            .execute(sql, vec![crate::db::SqlValue::Real(1.5), crate::db::SqlValue::Integer(2)])
+            .await
+            .unwrap();
+        let rows = store
+            .query_simple("SELECT total, label FROM lines".to_string())
+            .await
+            .unwrap();
+        assert_eq!(rows[0][0].1, serde_json::json!(3.0));
+        assert_eq!(rows[0][1].1, serde_json::json!("#1"));
+    }
+
+    #[tokio::test]
+    async fn test_wide_evolution_is_chunked_and_logged() {
+        let store = Arc::new(VibeStore::in_memory().await.unwrap());
+        let guard = SchemaGuard::new(store.clone());
+
+        guard.ensure_table("wide").await.unwrap();
+        let columns = guard.ensure_columns("wide", &serde_json::json!({"seed": 1})).await.unwrap();
+        store
+            .execute(
+                format!("INSERT INTO wide ({}) VALUES (?)", columns.join(", ")),
+                vec![SqlValue::Integer(1)],
+            )
+            .await
+            .unwrap();
+
+        let payload: Map<String, Value> = (0..300).map(|i| (format!("k{}", i), Value::from(i))).collect();
+        let columns = guard.ensure_columns("wide", &Value::Object(payload)).await.unwrap();
+        assert_eq!(columns.len(), 300);
+
+        let stats = guard.get_table_stats("wide").await.unwrap();
+        assert_eq!(stats.column_count, 3 + 1 + 300);
+        let rows = store.query_simple("SELECT seed, k299 FROM wide".to_string()).await.unwrap();
+        assert_eq!(rows[0][0].1, serde_json::json!(1));
+        assert_eq!(rows[0][1].1, Value::Null);
+
+        let rows = store
+            .query_simple(
+                "SELECT strategy, columns_added, transactions FROM vibe_migrations \
+                 WHERE table_name = 'wide' ORDER BY id"
+                    .to_string(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0][0].1, serde_json::json!(MIGRATION_SINGLE));
+        assert_eq!(rows[1][0].1, serde_json::json!(MIGRATION_CHUNKED));
+        assert_eq!(rows[1][1].1, serde_json::json!(300));
+        assert_eq!(rows[1][2].1, serde_json::json!(6));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_evolutions_skip_existing_columns() {
+        let store = Arc::new(VibeStore::in_memory().await.unwrap());
+        let guard = Arc::new(SchemaGuard::new(store));
+        guard.ensure_table("race").await.unwrap();
+
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let guard = guard.clone();
+                tokio::spawn(async move {
+                    guard
+                        .ensure_columns("race", &serde_json::json!({"a": 1, "b": "x"}))
+                        .await
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+        let stats = guard.get_table_stats("race").await.unwrap();
+        assert_eq!(stats.column_count, 5);
+    }
+
+    /// Rough single-transaction vs chunked timing for a 400-column evolution;
+    /// run with `cargo test --release -- --ignored bench_wide_evolution --nocapture`
+    #[tokio::test]
+    #[ignore]
+    async fn bench_wide_evolution() {
+        let wide: Vec<(String, String)> = (0..400).map(|i| (format!("c{}", i), "INTEGER".to_string())).collect();
+        for (label, chunk_size) in [("single", wide.len()), ("chunked", ALTER_CHUNK_SIZE)] {
+            let store = Arc::new(VibeStore::in_memory().await.unwrap());
+            let guard = SchemaGuard::new(store.clone());
+            guard.ensure_table("bench").await.unwrap();
+            guard
+                .add_typed_columns(
+                    "bench",
+                    vec![("payload".to_string(), "TEXT".to_string())],
+                )
+                .await
+                .unwrap();
+            store
+                .execute_batch(
+                    "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 50000) \
+                     INSERT INTO bench (payload) SELECT hex(randomblob(64)) FROM n;"
+                        .to_string(),
+                )
+                .await
+                .unwrap();
+
+            let started = Instant::now();
+            guard.add_typed_columns_in_chunks("bench", wide.clone(), chunk_size).await.unwrap();
+            println!("{}: {:?}", label, started.elapsed());
+        }
+    }
 }
@@ -7,7 +7,7 @@
 //!
 //! | JSON Type       | SQLite Affinity | Logic/Constraint              |
 //! |----------------|-----------------|------------------------------|
-//! | Number (Int)   | INTEGER         | Check if `is_i64()`          |
+//! | Number (Int)   | INTEGER         | Check if `is_i64()`; larger `u64` values are TEXT |
 //! | Number (Float) | REAL            | Default for any decimal      |
 //! | Boolean        | INTEGER         | Store as 1 or 0              |
 //! | String         | TEXT            | Standard UTF-8               |
@@ -15,7 +15,16 @@
 //! | Null           | NULL            | Ignored during column creation |
 
 use crate::error::{VibeError, VibeResult};
-use serde_json::Value;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Number, Value};
+use std::collections::{BTreeMap, HashMap};
+
+/// Share of a column's values the dominant type needs by default
+pub const DEFAULT_CONFIDENCE: f64 = 0.98;
+
+/// Outlier values listed per column in an `InferenceReport`; the count
+/// covers all of them
+pub const MAX_REPORTED_OUTLIERS: usize = 100;
 
 /// SQLite type affinity for column definitions
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -39,6 +48,36 @@ impl SqliteType {
         }
     }
 
+    /// Parses the target of a query-time cast hint: `int`, `real` or `text`
+    pub fn parse_cast(target: &str) -> VibeResult<Self> {
+        match target.to_ascii_lowercase().as_str() {
+            "int" | "integer" => Ok(SqliteType::Integer),
+            "real" | "float" => Ok(SqliteType::Real),
+            "text" => Ok(SqliteType::Text),
+            _ => Err(VibeError::InvalidPayload(format!(
+                "Unknown cast target '{}'; use int, real or text",
+                target
+            ))),
+        }
+    }
+
+    /// Parses a column type named in a `_types` push hint: `INTEGER`,
+    /// `REAL`, `TEXT` or `BLOB`, in any case
+    pub fn parse_column_type(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "INTEGER" => Some(SqliteType::Integer),
+            "REAL" => Some(SqliteType::Real),
+            "TEXT" => Some(SqliteType::Text),
+            "BLOB" => Some(SqliteType::Blob),
+            _ => None,
+        }
+    }
+
+    /// Wraps a SQL expression in `CAST(expr AS <type>)`
+    pub fn cast(&self, expr: &str) -> String {
+        format!("CAST({} AS {})", expr, self.as_sql())
+    }
+
     /// Determines if this type can be promoted to another type
     /// Used for schema evolution when types conflict
     pub fn can_promote_to(&self, other: &SqliteType) -> bool {
@@ -72,6 +111,36 @@ impl SqliteType {
     }
 }
 
+/// Integers from 2^53 up can't all be held exactly by a REAL
+pub const MAX_SAFE_FLOAT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// A JSON number that neither INTEGER nor REAL holds exactly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InexactNumber {
+    /// An integer above `i64::MAX` within `u64`; stored as its decimal
+    /// text so no digit is lost
+    BigInteger,
+    /// A whole number of 2^53 or more parsed as a float, such as `1e20`
+    /// or an integer beyond `u64`; its digits past the 16th or so are
+    /// already lost
+    ImpreciseInteger,
+}
+
+impl InexactNumber {
+    /// Classifies a number, `None` when it is stored exactly
+    pub fn classify(n: &Number) -> Option<Self> {
+        if n.is_i64() {
+            return None;
+        }
+        if n.is_u64() {
+            return Some(InexactNumber::BigInteger);
+        }
+        n.as_f64()
+            .filter(|f| f.fract() == 0.0 && f.abs() >= MAX_SAFE_FLOAT_INTEGER)
+            .map(|_| InexactNumber::ImpreciseInteger)
+    }
+}
+
 /// Infers the SQLite type from a JSON value
 ///
 /// # Arguments
@@ -83,9 +152,12 @@ pub fn infer_type(value: &Value) -> SqliteType {
     match value {
         Value::Null => SqliteType::Null,
         Value::Bool(_) => SqliteType::Integer,
+        // Integers beyond i64 are stored as TEXT (see `db::json_to_sql_value`)
         Value::Number(n) => {
-            if n.is_i64() || n.is_u64() {
+            if n.is_i64() {
                 SqliteType::Integer
+            } else if n.is_u64() {
+                SqliteType::Text
             } else {
                 SqliteType::Real
             }
@@ -173,15 +245,167 @@ pub fn infer_batch_schema(values: &[Value]) -> VibeResult<Vec<InferredColumn>> {
     Ok(unified_columns.into_values().collect())
 }
 
+/// How a batch settles columns whose values have different types
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InferenceMode {
+    /// Any disagreement widens the column (see `SqliteType::common_type`)
+    #[default]
+    Strict,
+    /// A dominant numeric type wins; the other values become NULL (see
+    /// `infer_batch_schema_confident`)
+    Confident,
+}
+
+/// A value set to NULL because it didn't fit its column's dominant type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Outlier {
+    /// Position of the item in the batch
+    pub index: usize,
+    /// The value as sent
+    pub value: Value,
+}
+
+/// Type chosen for a column over the values that didn't fit it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnReport {
+    /// `INTEGER` or `REAL`
+    pub sqlite_type: String,
+    /// Share of the column's non-null values that had this type
+    pub share: f64,
+    pub outlier_count: usize,
+    /// The first `MAX_REPORTED_OUTLIERS` outliers
+    pub outliers: Vec<Outlier>,
+    /// Batch indexes of all outliers
+    #[serde(skip)]
+    pub indexes: Vec<usize>,
+}
+
+/// Columns of a batch whose type was chosen by `infer_batch_schema_confident`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InferenceReport {
+    /// Share the dominant type needed
+    pub confidence: f64,
+    pub columns: BTreeMap<String, ColumnReport>,
+}
+
+impl InferenceReport {
+    /// Sets the outliers to NULL in the batch the report was made from
+    pub fn null_outliers(&self, values: &mut [Value]) {
+        for (column, report) in &self.columns {
+            for &index in &report.indexes {
+                if let Some(obj) = values.get_mut(index).and_then(Value::as_object_mut) {
+                    obj.insert(column.clone(), Value::Null);
+                }
+            }
+        }
+    }
+
+    /// A payload with a value of each reported column's chosen type, so the
+    /// columns are created with it whatever order the rows come in
+    pub fn schema_probe(&self) -> Value {
+        let probe = self
+            .columns
+            .iter()
+            .map(|(name, column)| {
+                let value = if column.sqlite_type == SqliteType::Real.as_sql() { json!(0.5) } else { json!(0) };
+                (name.clone(), value)
+            })
+            .collect();
+        Value::Object(probe)
+    }
+}
+
+/// `infer_batch_schema`, except that a column whose non-null values are
+/// numbers at least `confidence` of the time gets the numeric type. Its
+/// other values (strings, objects, arrays) are outliers, listed in the
+/// report for `InferenceReport::null_outliers`. Below the threshold, mixed
+/// columns fall back to TEXT as in strict mode.
+pub fn infer_batch_schema_confident(
+    values: &[Value],
+    confidence: f64,
+) -> VibeResult<(Vec<InferredColumn>, InferenceReport)> {
+    if !(confidence > 0.5 && confidence <= 1.0) {
+        return Err(VibeError::InvalidPayload(
+            "confidence must be above 0.5 and at most 1".to_string(),
+        ));
+    }
+    let mut columns = infer_batch_schema(values)?;
+
+    // Non-null values of each column, by whether they are numbers
+    #[derive(Default)]
+    struct Tally {
+        integers: usize,
+        reals: usize,
+        others: Vec<usize>,
+    }
+    let mut tallies: HashMap<&str, Tally> = HashMap::new();
+    for (index, value) in values.iter().enumerate() {
+        for (key, val) in value.as_object().into_iter().flatten() {
+            let tally = tallies.entry(key.as_str()).or_default();
+            match (val, infer_type(val)) {
+                (Value::Null, _) => {}
+                (Value::Number(_) | Value::Bool(_), SqliteType::Integer) => tally.integers += 1,
+                (Value::Number(_), _) => tally.reals += 1,
+                _ => tally.others.push(index),
+            }
+        }
+    }
+
+    let mut report = InferenceReport {
+        confidence,
+        columns: BTreeMap::new(),
+    };
+    for column in &mut columns {
+        let Some(tally) = tallies.remove(column.name.as_str()) else {
+            continue;
+        };
+        let numbers = tally.integers + tally.reals;
+        if tally.others.is_empty() || numbers == 0 {
+            continue;
+        }
+        let share = numbers as f64 / (numbers + tally.others.len()) as f64;
+        if share < confidence {
+            continue;
+        }
+
+        column.sqlite_type = if tally.reals > 0 { SqliteType::Real } else { SqliteType::Integer };
+        column.is_nested = false;
+        report.columns.insert(
+            column.name.clone(),
+            ColumnReport {
+                sqlite_type: column.sqlite_type.as_sql().to_string(),
+                share,
+                outlier_count: tally.others.len(),
+                outliers: tally
+                    .others
+                    .iter()
+                    .take(MAX_REPORTED_OUTLIERS)
+                    .map(|&index| Outlier {
+                        index,
+                        value: values[index][&column.name].clone(),
+                    })
+                    .collect(),
+                indexes: tally.others,
+            },
+        );
+    }
+
+    Ok((columns, report))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde_json::json;
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_type_inference() {
         assert_eq!(infer_type(&json!(42)), SqliteType::Integer);
         assert_eq!(infer_type(&json!(3.14)), SqliteType::Real);
+        assert_eq!(infer_type(&json!(u64::MAX)), SqliteType::Text);
+        assert_eq!(infer_type(&json!(1e20)), SqliteType::Real);
         assert_eq!(infer_type(&json!("hello")), SqliteType::Text);
         assert_eq!(infer_type(&json!(true)), SqliteType::Integer);
         assert_eq!(infer_type(&json!(null)), SqliteType::Null);
@@ -189,6 +413,21 @@ mod tests {
         assert_eq!(infer_type(&json!([1, 2, 3])), SqliteType::Text);
     }
 
+    #[test]
+    fn test_inexact_numbers() {
+        let classify = |v: Value| InexactNumber::classify(v.as_number().unwrap());
+        assert_eq!(classify(json!(i64::MAX)), None);
+        assert_eq!(classify(json!(i64::MIN)), None);
+        assert_eq!(classify(json!(1.5)), None);
+        assert_eq!(classify(json!(9_007_199_254_740_991.0)), None);
+        assert_eq!(classify(json!(i64::MAX as u64 + 1)), Some(InexactNumber::BigInteger));
+        assert_eq!(classify(json!(u64::MAX)), Some(InexactNumber::BigInteger));
+        assert_eq!(classify(json!(1e20)), Some(InexactNumber::ImpreciseInteger));
+        assert_eq!(classify(json!(-1e19)), Some(InexactNumber::ImpreciseInteger));
+        let beyond_u64: Value = serde_json::from_str("123456789012345678901").unwrap();
+        assert_eq!(classify(beyond_u64), Some(InexactNumber::ImpreciseInteger));
+    }
+
     #[test]
     fn test_schema_inference() {
         let payload = json!({
@@ -210,4 +449,33 @@ mod tests {
         assert!(!SqliteType::Text.can_promote_to(&SqliteType::Integer));
         assert!(SqliteType::Null.can_promote_to(&SqliteType::Integer));
     }
+
+    #[test]
+    fn test_confident_batch_inference() {
+        let mut batch: Vec<Value> = (0..1000)
+            .map(|n| if n % 200 == 7 { json!({"v": "N/A"}) } else { json!({"v": n}) })
+            .collect();
+        let (columns, report) = infer_batch_schema_confident(&batch, DEFAULT_CONFIDENCE).unwrap();
+        assert_eq!(columns[0].sqlite_type, SqliteType::Integer);
+        let column = &report.columns["v"];
+        assert_eq!(column.outlier_count, 5);
+        assert_eq!(column.share, 0.995);
+        assert_eq!(column.outliers[1], Outlier { index: 207, value: json!("N/A") });
+        report.null_outliers(&mut batch);
+        assert_eq!(batch[207]["v"], Value::Null);
+        assert_eq!(batch[208]["v"], 208);
+
+        // Real values make the column REAL; strict inference still widens
+        batch[1] = json!({"v": 1.5});
+        let (columns, _) = infer_batch_schema_confident(&batch, DEFAULT_CONFIDENCE).unwrap();
+        assert_eq!(columns[0].sqlite_type, SqliteType::Real);
+        batch[7] = json!({"v": "N/A"});
+        assert_eq!(infer_batch_schema(&batch).unwrap()[0].sqlite_type, SqliteType::Text);
+
+        // Below the threshold the column falls back to TEXT
+        let mixed: Vec<Value> = (0..100).map(|n| if n < 5 { json!({"v": "x"}) } else { json!({"v": n}) }).collect();
+        let (columns, report) = infer_batch_schema_confident(&mixed, DEFAULT_CONFIDENCE).unwrap();
+        assert_eq!(columns[0].sqlite_type, SqliteType::Text);
+        assert!(report.columns.is_empty());
+    }
 }
@@ -0,0 +1,353 @@
+//! # Inline Files
+//!
+//! Moves small files that clients embed in documents as data URLs into
+//! file storage, so TEXT columns don't fill up with base64. Configured per
+//! collection:
+//!
+//! ```json
+//! {"inline_files": {"fields": ["avatar"], "max_bytes": 65536, "reference": "object", "cascade_delete": true}}
+//! ```
+//!
+//! Pushing `{"name": "ada", "avatar": "data:image/png;base64,iVBOR..."}` to
+//! `profiles` stores the decoded bytes in the private `profiles` bucket
+//! (created on first use) under a generated path, owned by the caller. The
+//! `avatar` column then holds `{"bucket", "path", "size", "mime"}`, or the
+//! object's `/v1/storage/object/...` path with `"reference": "url"`. Values
+//! of the fields that aren't data URLs are stored as sent.
+//!
+//! With `cascade_delete`, deleting the row by id also deletes the objects
+//! its fields reference in the collection's bucket.
+
+use crate::error::{VibeError, VibeResult};
+use crate::guard::SchemaGuard;
+use crate::storage::{Compression, CreateBucketRequest, StorageObject, StorageService};
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tracing::{info, warn};
+
+/// Default size cap of a decoded file
+pub const DEFAULT_MAX_BYTES: usize = 64 * 1024;
+
+/// Largest `max_bytes` a collection may allow
+pub const MAX_INLINE_FILE_BYTES: usize = 1024 * 1024;
+
+/// Prefix of the download path of an object
+const OBJECT_URL_PREFIX: &str = "/v1/storage/object/";
+
+/// What replaces a data URL in the stored row
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferenceStyle {
+    /// `{"bucket", "path", "size", "mime"}`
+    #[default]
+    Object,
+    /// `/v1/storage/object/<bucket>/<path>`
+    Url,
+}
+
+/// `inline_files` settings of a collection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InlineFiles {
+    /// Top-level fields whose data URLs become objects
+    pub fields: Vec<String>,
+    /// Size cap of each decoded file
+    pub max_bytes: usize,
+    pub reference: ReferenceStyle,
+    /// Bucket to store into; derived from the collection name when `None`
+    pub bucket: Option<String>,
+    /// Delete the referenced objects with the row
+    pub cascade_delete: bool,
+}
+
+impl Default for InlineFiles {
+    fn default() -> Self {
+        Self {
+            fields: Vec::new(),
+            max_bytes: DEFAULT_MAX_BYTES,
+            reference: ReferenceStyle::Object,
+            bucket: None,
+            cascade_delete: false,
+        }
+    }
+}
+
+impl InlineFiles {
+    /// Checks the settings before they are stored
+    pub fn validate(&self) -> VibeResult<()> {
+        for field in &self.fields {
+            SchemaGuard::validate_identifier(field)?;
+        }
+        if self.max_bytes == 0 || self.max_bytes > MAX_INLINE_FILE_BYTES {
+            return Err(VibeError::InvalidPayload(format!(
+                "inline_files.max_bytes must be between 1 and {}",
+                MAX_INLINE_FILE_BYTES
+            )));
+        }
+        Ok(())
+    }
+
+    /// Bucket the collection's files go to: `bucket`, or the collection
+    /// name in lowercase with underscores as hyphens
+    pub fn bucket_for(&self, collection: &str) -> String {
+        match &self.bucket {
+            Some(bucket) => bucket.clone(),
+            None => collection.to_ascii_lowercase().replace('_', "-"),
+        }
+    }
+
+    /// Objects of `bucket` the fields of a stored row refer to
+    pub fn references(&self, bucket: &str, row: &Map<String, Value>) -> Vec<String> {
+        let url_prefix = format!("{}{}/", OBJECT_URL_PREFIX, bucket);
+        self.fields
+            .iter()
+            .filter_map(|field| match row.get(field)? {
+                Value::Object(reference) if reference.get("bucket")?.as_str() == Some(bucket) => {
+                    reference.get("path")?.as_str().map(str::to_string)
+                }
+                Value::String(url) => url.strip_prefix(&url_prefix).map(str::to_string),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn reference(&self, object: &StorageObject) -> Value {
+        match self.reference {
+            ReferenceStyle::Object => json!({
+                "bucket": object.bucket_name,
+                "path": object.path,
+                "size": object.size,
+                "mime": object.mime_type
+            }),
+            ReferenceStyle::Url => {
+                json!(format!("{}{}/{}", OBJECT_URL_PREFIX, object.bucket_name, object.path))
+            }
+        }
+    }
+}
+
+/// A decoded `data:<mime>;base64,<data>` URL
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataUrl {
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+impl DataUrl {
+    /// Decodes `value` if it is a data URL; `None` for any other string
+    ///
+    /// Only base64 data URLs are accepted. Files over `max_bytes` are
+    /// refused before they are decoded.
+    pub fn parse(value: &str, max_bytes: usize) -> Option<Result<Self, String>> {
+        let rest = value.strip_prefix("data:")?;
+        Some(Self::decode(rest, max_bytes))
+    }
+
+    fn decode(rest: &str, max_bytes: usize) -> Result<Self, String> {
+        let (header, encoded) = rest
+            .split_once(',')
+            .ok_or_else(|| "data URL has no ',' before its data".to_string())?;
+        let media_type = header
+            .strip_suffix(";base64")
+            .ok_or_else(|| "only base64 data URLs are supported".to_string())?;
+        // Parameters such as `;charset=utf-8` are not kept
+        let mime_type = match media_type.split(';').next().unwrap_or("").trim() {
+            "" => "text/plain".to_string(),
+            mime if mime.contains('/') => mime.to_ascii_lowercase(),
+            mime => return Err(format!("'{}' is not a MIME type", mime)),
+        };
+
+        if encoded.len() / 4 * 3 > max_bytes + 2 {
+            return Err(format!("file is larger than {} bytes", max_bytes));
+        }
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(|e| format!("invalid base64: {}", e))?;
+        if data.len() > max_bytes {
+            return Err(format!("file is larger than {} bytes", max_bytes));
+        }
+        Ok(Self { mime_type, data })
+    }
+
+    /// Generated object path, with an extension for the MIME type when one
+    /// is known
+    fn object_path(&self) -> String {
+        let id = uuid::Uuid::new_v4();
+        match mime_guess::get_mime_extensions_str(&self.mime_type).and_then(|exts| exts.first()) {
+            Some(ext) => format!("{}.{}", id, ext),
+            None => id.to_string(),
+        }
+    }
+}
+
+/// Objects stored by [`extract`] for a row that is yet to be written
+///
+/// Dropping it deletes them again in the background, so a push that fails
+/// or is abandoned after the files were stored leaves none behind; call
+/// [`StoredFiles::keep`] once the row is written.
+pub struct StoredFiles {
+    storage: Option<StorageService>,
+    objects: Vec<StorageObject>,
+}
+
+impl StoredFiles {
+    /// No files
+    pub fn none() -> Self {
+        Self {
+            storage: None,
+            objects: Vec::new(),
+        }
+    }
+
+    /// Keeps the objects: the row referencing them was written
+    pub fn keep(mut self) {
+        self.objects.clear();
+    }
+}
+
+impl Drop for StoredFiles {
+    fn drop(&mut self) {
+        let (Some(storage), false) = (self.storage.take(), self.objects.is_empty()) else {
+            return;
+        };
+        let objects = std::mem::take(&mut self.objects);
+        tokio::spawn(async move {
+            for object in objects {
+                if let Err(e) = storage.delete_object(&object.bucket_name, &object.path).await {
+                    warn!("Failed to discard inline file {}/{}: {}", object.bucket_name, object.path, e);
+                }
+            }
+        });
+    }
+}
+
+/// Stores the data URLs of the configured fields of `payload` and puts
+/// references in their place
+///
+/// Every field is decoded before anything is stored, so a bad value leaves
+/// storage untouched.
+pub async fn extract(
+    storage: &StorageService,
+    collection: &str,
+    config: &InlineFiles,
+    payload: &mut Value,
+    owner_id: Option<i64>,
+) -> VibeResult<StoredFiles> {
+    let Some(obj) = payload.as_object_mut() else {
+        return Ok(StoredFiles::none());
+    };
+
+    let mut files = Vec::new();
+    for field in &config.fields {
+        let Some(Value::String(value)) = obj.get(field) else {
+            continue;
+        };
+        match DataUrl::parse(value, config.max_bytes) {
+            Some(Ok(file)) => files.push((field.clone(), file)),
+            Some(Err(message)) => {
+                return Err(VibeError::InvalidFile {
+                    field: field.clone(),
+                    message,
+                });
+            }
+            None => {}
+        }
+    }
+    if files.is_empty() {
+        return Ok(StoredFiles::none());
+    }
+
+    let bucket = config.bucket_for(collection);
+    ensure_bucket(storage, &bucket, owner_id).await?;
+
+    let mut stored = StoredFiles {
+        storage: Some(storage.clone()),
+        objects: Vec::new(),
+    };
+    for (field, file) in files {
+        let path = file.object_path();
+        let object = storage
+            .upload_object(&bucket, &path, file.data, &file.mime_type, owner_id)
+            .await?;
+        obj.insert(field, config.reference(&object));
+        stored.objects.push(object);
+    }
+    Ok(stored)
+}
+
+/// Deletes the objects of `bucket` that a deleted row referenced
+pub async fn cascade(storage: &StorageService, bucket: &str, paths: &[String]) {
+    for path in paths {
+        match storage.delete_object(bucket, path).await {
+            Ok(()) | Err(VibeError::NotFound(_)) => {}
+            Err(e) => warn!("Failed to delete inline file {}/{}: {}", bucket, path, e),
+        }
+    }
+}
+
+/// Creates the private bucket of a collection on first use
+async fn ensure_bucket(storage: &StorageService, bucket: &str, owner_id: Option<i64>) -> VibeResult<()> {
+    if storage.get_bucket(bucket).await.is_ok() {
+        return Ok(());
+    }
+    let request = CreateBucketRequest {
+        name: bucket.to_string(),
+        public: false,
+        compression: Compression::None,
+    };
+    match storage.create_bucket(request, owner_id).await {
+        Ok(_) => {
+            info!("🪣 Created bucket {} for inline files", bucket);
+            Ok(())
+        }
+        Err(VibeError::Conflict(_)) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_data_urls() {
+        let file = DataUrl::parse("data:image/PNG;base64,iVBORw==", 64).unwrap().unwrap();
+        assert_eq!(file.mime_type, "image/png");
+        assert_eq!(file.data, [0x89, b'P', b'N', b'G']);
+        assert!(file.object_path().ends_with(".png"));
+
+        let text = DataUrl::parse("data:;charset=utf-8;base64,aGk=", 64).unwrap().unwrap();
+        assert_eq!((text.mime_type.as_str(), text.data.as_slice()), ("text/plain", &b"hi"[..]));
+
+        assert!(DataUrl::parse("https://example.com/a.png", 64).is_none());
+        for (value, error) in [
+            ("data:text/plain,hi", "only base64"),
+            ("data:image/png;base64,!!!", "invalid base64"),
+            ("data:image/png;base64", "no ','"),
+            ("data:png;base64,aGk=", "not a MIME type"),
+            ("data:text/plain;base64,aGVsbG8gd29ybGQ=", "larger than 8 bytes"),
+        ] {
+            let message = DataUrl::parse(value, 8).unwrap().unwrap_err();
+            assert!(message.contains(error), "{}: {}", value, message);
+        }
+    }
+
+    #[test]
+    fn test_references_of_a_row() {
+        let config = InlineFiles {
+            fields: vec!["avatar".to_string(), "banner".to_string(), "cv".to_string()],
+            ..Default::default()
+        };
+        let row = json!({
+            "avatar": {"bucket": "profiles", "path": "a.png"},
+            "banner": "/v1/storage/object/profiles/b.png",
+            "cv": {"bucket": "elsewhere", "path": "c.pdf"},
+            "other": "/v1/storage/object/profiles/d.png"
+        });
+        let paths = config.references("profiles", row.as_object().unwrap());
+        assert_eq!(paths, ["a.png", "b.png"]);
+        assert_eq!(config.bucket_for("user_profiles"), "user-profiles");
+    }
+}
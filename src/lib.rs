@@ -9,17 +9,77 @@
 //! - **Schema-Brain (Inference)**: Inspects JSON and generates delta-migrations
 //! - **Migration-Automaton (Guard)**: Executes ALTER TABLE statements safely
 //! - **Vibe-Store**: Manages the persistent .db file with WAL mode
+//! - **Capabilities**: Detects optional features of the linked SQLite build
 //! - **Vibe-Explorer**: Embedded WASM dashboard for real-time visualization
 //! - **Vibe-Auth**: JWT-based authentication with Argon2 password hashing
 //! - **Vibe-Storage**: Bucket-based file storage with SQLite metadata
+//! - **Bootstrap**: Seeds the first admin user and buckets on startup
+//! - **Preflight**: Startup checks of the database and storage paths and the JWT secret
+//! - **Offline Admin**: Vacuum, export, user creation and integrity checks without the server
+//! - **Audit Log**: Row history for time-travel reads
+//! - **Array Explosion**: Stores nested arrays as child collection rows
+//! - **Inline Files**: Moves data URLs in pushed documents into storage
+//! - **SQL Proposals**: Admin approval for raw SQL from non-admins
+//! - **Pull Sources**: Scheduled ingestion from remote JSON endpoints
+//! - **Dead Letters**: Rejected pushes kept for inspection and retry
+//! - **Column Descriptions**: Free-text documentation of columns
+//! - **Erasure**: Signed, batched erasure of a data subject's rows
+//! - **Filter Operators**: `eq.`, `like.`, `in.(...)`, `is.null` and `not.` in query filters
+//! - **Result Shaping**: Renamed and computed output fields with `?select=`
+//! - **Aggregation**: Grouped metrics and percentiles over collections
+//! - **HTML Templates**: Logic-less rendering of query results
+//! - **Snapshots**: Read transactions shared by paginated requests
+//! - **Row Cache**: Optional LRU cache of lookups by id
+//! - **Row Counts**: Optional maintained row counts for table stats
+//! - **Row Locks**: Advisory per-row locks for concurrent editing
+//! - **Write Batching**: Coalesces concurrent pushes into shared transactions
+//! - **Concurrency Limits**: Caps requests in flight, overall and per class
+//! - **Clock**: Injectable time source of expiry and TTL checks
+//! - **Write Performance**: Per-collection write latency, retries and migrations
+//! - **Vibe-Client**: Typed HTTP client (`client` feature)
+//! - **Testing**: In-process server and fixtures (`test-support` feature)
 
+// The root endpoint listing is one large `json!` literal
+#![recursion_limit = "256"]
+
+pub mod admin;
+pub mod aggregate;
 pub mod api;
+pub mod audit;
 pub mod auth;
+pub mod batching;
+pub mod bootstrap;
+pub mod capabilities;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod clock;
+pub mod config;
 pub mod db;
+pub mod dead_letters;
+pub mod descriptions;
+pub mod erasure;
 pub mod error;
+pub mod explode;
 pub mod explorer;
+pub mod filters;
 pub mod guard;
 pub mod inference;
+pub mod inline_files;
+pub mod limits;
+pub mod locks;
+pub mod perf;
+pub mod preflight;
+pub mod projection;
+pub mod proposals;
+pub mod row_cache;
+pub mod row_counts;
+pub mod settings;
+pub mod snapshots;
+pub mod sources;
+pub mod sql_policy;
 pub mod storage;
+pub mod templates;
+#[cfg(any(test, feature = "test-support"))]
+pub mod testing;
 
 pub use error::{VibeError, VibeResult};
@@ -0,0 +1,426 @@
+//! # Concurrency Limits
+//!
+//! Caps how many requests are handled at once, so a burst of slow batch
+//! pushes waits in front of the server instead of queueing on the single
+//! writer connection with every body buffered. Each request takes a permit
+//! of its class (`write`, `read` or `upload`) and a `global` one. A request
+//! that can't get both within `acquire_timeout` is refused with 503
+//! `OVERLOADED` and a retry hint. Health checks, metrics and CORS preflights
+//! are never limited.
+//!
+//! Permits are held until the handler returns its response, so streamed
+//! bodies (NDJSON queries, SSE, downloads) don't hold one while they drain.
+//!
+//! Limits can be changed while the server runs (`PUT /v1/admin/concurrency`);
+//! requests in flight keep their permits, so a lowered limit takes effect as
+//! they finish.
+
+use crate::config::ConcurrencyConfig;
+use crate::error::{VibeError, VibeResult};
+
+use axum::extract::{Request, State};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::warn;
+
+/// Suggested wait for a request refused for lack of a permit
+pub const OVERLOADED_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Which limit a request counts against besides the global one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestClass {
+    Write,
+    Read,
+    Upload,
+}
+
+impl RequestClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestClass::Write => "write",
+            RequestClass::Read => "read",
+            RequestClass::Upload => "upload",
+        }
+    }
+
+    /// Class of a request, or `None` for requests that are never limited
+    ///
+    /// `path` is the path below the base path prefix.
+    pub fn of(method: &Method, path: &str) -> Option<Self> {
+        if *method == Method::OPTIONS || path.starts_with("/health") || path == "/metrics" {
+            return None;
+        }
+        if matches!(*method, Method::GET | Method::HEAD) {
+            Some(RequestClass::Read)
+        } else if matches!(*method, Method::POST | Method::PUT)
+            && (path.starts_with("/v1/storage/object/") || path.starts_with("/v1/storage/upload/"))
+        {
+            Some(RequestClass::Upload)
+        } else {
+            Some(RequestClass::Write)
+        }
+    }
+}
+
+/// Requests in flight against one limit
+#[derive(Default)]
+struct Gate {
+    limit: AtomicUsize,
+    in_flight: AtomicUsize,
+    rejected: AtomicU64,
+}
+
+impl Gate {
+    fn try_enter(&self) -> bool {
+        let limit = self.limit.load(Ordering::Relaxed);
+        self.in_flight
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| (n < limit).then_some(n + 1))
+            .is_ok()
+    }
+
+    fn leave(&self) {
+        self.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+
+    fn stats(&self) -> GateStats {
+        GateStats {
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            limit: self.limit.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// One limit as reported by `/health` and the admin endpoint
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct GateStats {
+    pub in_flight: usize,
+    pub limit: usize,
+    /// Requests refused on this limit since startup
+    pub rejected: u64,
+}
+
+/// Reads one series of `render_prometheus` off a limit
+type StatField = fn(&GateStats) -> u64;
+
+/// Every limit, with the wait for permits
+#[derive(Debug, Clone, Serialize)]
+pub struct ConcurrencyStats {
+    pub global: GateStats,
+    pub write: GateStats,
+    pub read: GateStats,
+    pub upload: GateStats,
+    pub acquire_timeout_ms: u64,
+}
+
+/// Global and per-class request limits
+pub struct ConcurrencyLimiter {
+    global: Gate,
+    writes: Gate,
+    reads: Gate,
+    uploads: Gate,
+    acquire_timeout: RwLock<Duration>,
+    /// Woken whenever a permit is returned or the limits change
+    released: Notify,
+}
+
+impl ConcurrencyLimiter {
+    pub fn new(config: &ConcurrencyConfig) -> Self {
+        let limiter = Self {
+            global: Gate::default(),
+            writes: Gate::default(),
+            reads: Gate::default(),
+            uploads: Gate::default(),
+            acquire_timeout: RwLock::new(config.acquire_timeout),
+            released: Notify::new(),
+        };
+        limiter.reconfigure(config);
+        limiter
+    }
+
+    fn gate(&self, class: RequestClass) -> &Gate {
+        match class {
+            RequestClass::Write => &self.writes,
+            RequestClass::Read => &self.reads,
+            RequestClass::Upload => &self.uploads,
+        }
+    }
+
+    /// Applies new limits to the running server
+    pub fn reconfigure(&self, config: &ConcurrencyConfig) {
+        self.global.limit.store(config.global, Ordering::Relaxed);
+        self.writes.limit.store(config.writes, Ordering::Relaxed);
+        self.reads.limit.store(config.reads, Ordering::Relaxed);
+        self.uploads.limit.store(config.uploads, Ordering::Relaxed);
+        *self.acquire_timeout.write().unwrap_or_else(|e| e.into_inner()) = config.acquire_timeout;
+        // Raised limits may let waiting requests in
+        self.released.notify_waiters();
+    }
+
+    /// The limits in effect
+    pub fn config(&self) -> ConcurrencyConfig {
+        ConcurrencyConfig {
+            global: self.global.limit.load(Ordering::Relaxed),
+            writes: self.writes.limit.load(Ordering::Relaxed),
+            reads: self.reads.limit.load(Ordering::Relaxed),
+            uploads: self.uploads.limit.load(Ordering::Relaxed),
+            acquire_timeout: *self.acquire_timeout.read().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+
+    /// Takes a permit of `class` and a global one, or returns the gate that
+    /// is full
+    fn try_acquire(&self, class: RequestClass) -> Result<(), &Gate> {
+        let gate = self.gate(class);
+        if !gate.try_enter() {
+            return Err(gate);
+        }
+        if !self.global.try_enter() {
+            // No wake-up: whoever this slot turned away needs a global one
+            // too, and is woken when one is returned
+            gate.leave();
+            return Err(&self.global);
+        }
+        Ok(())
+    }
+
+    /// Waits up to `acquire_timeout` for the permits of a `class` request
+    pub async fn acquire(self: &Arc<Self>, class: RequestClass) -> VibeResult<Permit> {
+        let timeout = *self.acquire_timeout.read().unwrap_or_else(|e| e.into_inner());
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            // Registered before trying, so a release in between isn't missed
+            let released = self.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+
+            let full = match self.try_acquire(class) {
+                Ok(()) => {
+                    return Ok(Permit {
+                        limiter: Arc::clone(self),
+                        class,
+                    })
+                }
+                Err(gate) => gate,
+            };
+            if tokio::time::timeout_at(deadline, released).await.is_err() {
+                full.rejected.fetch_add(1, Ordering::Relaxed);
+                let scope = if std::ptr::eq(full, &self.global) { "global" } else { class.as_str() };
+                return Err(VibeError::Overloaded {
+                    class: scope,
+                    limit: full.limit.load(Ordering::Relaxed),
+                    retry_after: OVERLOADED_RETRY_AFTER,
+                });
+            }
+        }
+    }
+
+    pub fn stats(&self) -> ConcurrencyStats {
+        ConcurrencyStats {
+            global: self.global.stats(),
+            write: self.writes.stats(),
+            read: self.reads.stats(),
+            upload: self.uploads.stats(),
+            acquire_timeout_ms: self.config().acquire_timeout.as_millis() as u64,
+        }
+    }
+
+    /// In-flight, limit and rejection series per class in Prometheus text
+    /// exposition format
+    pub fn render_prometheus(&self) -> String {
+        let gates = [
+            ("global", &self.global),
+            ("write", &self.writes),
+            ("read", &self.reads),
+            ("upload", &self.uploads),
+        ];
+        let mut out = String::new();
+        let series: [(&str, &str, &str, StatField); 3] = [
+            ("vibedb_requests_in_flight", "Requests being handled per class", "gauge", |s| s.in_flight as u64),
+            ("vibedb_request_limit", "Requests handled at once at most per class", "gauge", |s| s.limit as u64),
+            (
+                "vibedb_requests_rejected_total",
+                "Requests refused for lack of a permit per class",
+                "counter",
+                |s| s.rejected,
+            ),
+        ];
+        for (name, help, kind, value) in series {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+            for (class, gate) in gates {
+                let _ = writeln!(out, "{}{{class=\"{}\"}} {}", name, class, value(&gate.stats()));
+            }
+        }
+        out
+    }
+}
+
+/// Permits of one request, returned when dropped
+pub struct Permit {
+    limiter: Arc<ConcurrencyLimiter>,
+    class: RequestClass,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.limiter.gate(self.class).leave();
+        self.limiter.global.leave();
+        self.limiter.released.notify_waiters();
+    }
+}
+
+/// Middleware holding a request's permits while its handler runs
+///
+/// Apply with `axum::middleware::from_fn_with_state(limiter, limit_concurrency)`
+/// around the whole application before it is nested under the base path,
+/// so requests are classified by their paths without the prefix, and
+/// inside the CORS layer so refusals carry CORS headers.
+pub async fn limit_concurrency(
+    State(limiter): State<Arc<ConcurrencyLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(class) = RequestClass::of(request.method(), request.uri().path()) else {
+        return next.run(request).await;
+    };
+    match limiter.acquire(class).await {
+        Ok(_permit) => next.run(request).await,
+        Err(e) => {
+            warn!("Refused {} {}: {}", request.method(), request.uri().path(), e);
+            e.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use axum::routing::{get, post};
+    use axum::Router;
+    use tower::util::ServiceExt;
+
+    fn limiter(writes: usize) -> Arc<ConcurrencyLimiter> {
+        Arc::new(ConcurrencyLimiter::new(&ConcurrencyConfig {
+            writes,
+            acquire_timeout: Duration::from_millis(50),
+            ..ConcurrencyConfig::default()
+        }))
+    }
+
+    /// A write route that takes a while, next to a fast read route
+    fn slow_router(limiter: &Arc<ConcurrencyLimiter>) -> Router {
+        Router::new()
+            .route(
+                "/v1/push/slow",
+                post(|| async {
+                    tokio::time::sleep(Duration::from_millis(400)).await;
+                    "done"
+                }),
+            )
+            .route("/v1/query/fast", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(Arc::clone(limiter), limit_concurrency))
+    }
+
+    async fn send(router: &Router, method: Method, uri: &str) -> Response {
+        let request = Request::builder().method(method).uri(uri).body(Body::empty()).unwrap();
+        router.clone().oneshot(request).await.unwrap()
+    }
+
+    #[test]
+    fn test_request_classes() {
+        assert_eq!(RequestClass::of(&Method::GET, "/v1/query/users"), Some(RequestClass::Read));
+        assert_eq!(RequestClass::of(&Method::POST, "/v1/push/users"), Some(RequestClass::Write));
+        assert_eq!(RequestClass::of(&Method::POST, "/v1/storage/object/b/a.txt"), Some(RequestClass::Upload));
+        assert_eq!(RequestClass::of(&Method::POST, "/v1/storage/upload/a.txt"), Some(RequestClass::Upload));
+        assert_eq!(RequestClass::of(&Method::DELETE, "/v1/storage/object/b/a.txt"), Some(RequestClass::Write));
+        assert_eq!(RequestClass::of(&Method::GET, "/v1/storage/object/b/a.txt"), Some(RequestClass::Read));
+        assert_eq!(RequestClass::of(&Method::GET, "/health/ready"), None);
+        assert_eq!(RequestClass::of(&Method::GET, "/metrics"), None);
+        assert_eq!(RequestClass::of(&Method::OPTIONS, "/v1/push/users"), None);
+    }
+
+    #[tokio::test]
+    async fn test_saturated_writes_are_refused_while_reads_succeed() {
+        let limiter = limiter(2);
+        let router = slow_router(&limiter);
+
+        let slow: Vec<_> = (0..2)
+            .map(|_| {
+                let router = router.clone();
+                tokio::spawn(async move { send(&router, Method::POST, "/v1/push/slow").await.status() })
+            })
+            .collect();
+        while limiter.stats().write.in_flight < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        let refused = send(&router, Method::POST, "/v1/push/slow").await;
+        assert_eq!(refused.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(refused.headers()["retry-after"], "1");
+        let body = axum::body::to_bytes(refused.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["code"], "OVERLOADED");
+        assert_eq!(body["error"]["retryable"], true);
+        assert_eq!(body["error"]["retry_after_ms"], 1000);
+        assert_eq!(body["error"]["details"], serde_json::json!({"class": "write", "limit": 2}));
+
+        assert_eq!(send(&router, Method::GET, "/v1/query/fast").await.status(), StatusCode::OK);
+        let stats = limiter.stats();
+        assert_eq!((stats.write.in_flight, stats.write.rejected), (2, 1));
+        assert_eq!(stats.global.in_flight, 2);
+
+        for task in slow {
+            assert_eq!(task.await.unwrap(), StatusCode::OK);
+        }
+        assert_eq!(limiter.stats().global.in_flight, 0);
+        assert_eq!(send(&router, Method::POST, "/v1/push/slow").await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_waiting_request_gets_a_released_permit() {
+        let limiter = Arc::new(ConcurrencyLimiter::new(&ConcurrencyConfig {
+            writes: 1,
+            acquire_timeout: Duration::from_secs(2),
+            ..ConcurrencyConfig::default()
+        }));
+        let router = slow_router(&limiter);
+
+        let first = tokio::spawn({
+            let router = router.clone();
+            async move { send(&router, Method::POST, "/v1/push/slow").await.status() }
+        });
+        while limiter.stats().write.in_flight < 1 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(send(&router, Method::POST, "/v1/push/slow").await.status(), StatusCode::OK);
+        assert_eq!(first.await.unwrap(), StatusCode::OK);
+        assert_eq!(limiter.stats().write.rejected, 0);
+    }
+
+    #[tokio::test]
+    async fn test_reconfigure_applies_to_the_running_limiter() {
+        let limiter = limiter(1);
+        let held = limiter.acquire(RequestClass::Write).await.unwrap();
+        assert!(limiter.acquire(RequestClass::Write).await.is_err());
+
+        limiter.reconfigure(&ConcurrencyConfig {
+            writes: 2,
+            global: 2,
+            ..limiter.config()
+        });
+        let second = limiter.acquire(RequestClass::Write).await.unwrap();
+        let error = limiter.acquire(RequestClass::Read).await.err().unwrap();
+        assert_eq!(error.details().unwrap(), serde_json::json!({"class": "global", "limit": 2}));
+        drop((held, second));
+        assert!(limiter.acquire(RequestClass::Read).await.is_ok());
+    }
+}
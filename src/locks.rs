@@ -0,0 +1,449 @@
+//! # Row Locks
+//!
+//! Advisory locks on single rows, so people editing the same row in the
+//! Explorer see each other instead of overwriting each other. Locks live in
+//! memory only and are gone after a restart.
+//!
+//! A lock is held by the signed-in caller who took it; anonymous callers
+//! hold locks through the stream session they name. Since a session lets
+//! its bearer write as the holder, it is never shown to others: anonymous
+//! holders appear under an opaque label of their session instead. Locks
+//! can only be taken on rows that exist, and one holder may hold at most
+//! `MAX_LOCKS_PER_HOLDER` at a time. Every lock expires
+//! after its TTL unless the holder takes it again, which refreshes it. A
+//! lock taken with the `session` of an open `/v1/stream/:collection`
+//! connection (sent in its `connected` message) is also released when that
+//! connection closes.
+//!
+//! Locks are advisory: writes ignore them unless the collection's settings
+//! have `{"enforce_locks": true}`, in which case updates of a row locked by
+//! someone else fail with `423 Locked`.
+
+use crate::audit;
+use crate::auth::AuthUser;
+use crate::clock::{SharedClock, SystemClock};
+use crate::error::{VibeError, VibeResult};
+
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// TTL of a lock taken without `ttl_secs`
+pub const DEFAULT_LOCK_TTL: Duration = Duration::from_secs(30);
+
+/// Longest TTL a lock may be taken for
+pub const MAX_LOCK_TTL: Duration = Duration::from_secs(600);
+
+/// Unexpired locks one user or session may hold at a time
+pub const MAX_LOCKS_PER_HOLDER: usize = 100;
+
+/// Header naming the stream session an anonymous write is made from, so
+/// it may update rows the session holds locks on
+pub const LOCK_SESSION_HEADER: &str = "x-vibe-session";
+
+/// Who holds a lock
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HolderKey {
+    User(i64),
+    Session(String),
+}
+
+/// An advisory lock on one row
+#[derive(Debug, Clone, Serialize)]
+pub struct RowLock {
+    pub collection: String,
+    pub id: i64,
+    /// `{id, email}` of the holder, or `{anonymous}` with the label of
+    /// their session for anonymous holders
+    pub holder: Value,
+    /// Stream session the lock is released with, if any
+    #[serde(skip)]
+    pub session: Option<String>,
+    pub acquired_at: String,
+    pub expires_at: String,
+    #[serde(skip)]
+    key: HolderKey,
+    #[serde(skip)]
+    deadline: Instant,
+    /// Distinguishes each acquisition, so a stale expiry timer can't
+    /// release a refreshed lock
+    #[serde(skip)]
+    token: u64,
+}
+
+impl RowLock {
+    /// When the lock expires unless refreshed
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    pub fn token(&self) -> u64 {
+        self.token
+    }
+
+    fn expired(&self, now: Instant) -> bool {
+        self.deadline <= now
+    }
+
+    fn held_by(&self, key: &HolderKey) -> bool {
+        &self.key == key
+    }
+
+    fn locked_error(&self) -> VibeError {
+        VibeError::Locked {
+            message: format!(
+                "Row {} of {} is locked by {}",
+                self.id,
+                self.collection,
+                holder_label(&self.holder)
+            ),
+            holder: self.holder.clone(),
+            expires_at: self.expires_at.clone(),
+        }
+    }
+}
+
+fn holder_label(holder: &Value) -> String {
+    match (holder.get("email"), holder.get("anonymous")) {
+        (Some(Value::String(email)), _) => email.clone(),
+        (_, Some(Value::String(label))) => format!("anonymous editor {}", label),
+        _ => "someone else".to_string(),
+    }
+}
+
+/// An open stream session
+struct SessionInfo {
+    owner: Option<i64>,
+    label: String,
+}
+
+/// Locks by collection and row id, and the open stream sessions they may
+/// be tied to
+pub struct LockRegistry {
+    locks: DashMap<(String, i64), RowLock>,
+    /// Open stream sessions: the user who opened each, if signed in, and
+    /// the label anonymous holders are shown under
+    sessions: DashMap<String, SessionInfo>,
+    tokens: AtomicU64,
+    clock: SharedClock,
+}
+
+impl Default for LockRegistry {
+    fn default() -> Self {
+        Self {
+            locks: DashMap::new(),
+            sessions: DashMap::new(),
+            tokens: AtomicU64::new(0),
+            clock: SystemClock::shared(),
+        }
+    }
+}
+
+impl LockRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Times locks by `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Registers an open stream connection and returns its session id
+    pub fn open_session(&self, owner: Option<&AuthUser>) -> String {
+        let session = uuid::Uuid::new_v4().to_string();
+        // Unrelated to the session, so showing it grants nothing
+        let label = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+        self.sessions.insert(
+            session.clone(),
+            SessionInfo {
+                owner: owner.map(|user| user.id),
+                label,
+            },
+        );
+        session
+    }
+
+    /// Forgets a closed stream connection and releases the locks tied to it
+    pub fn close_session(&self, session: &str) -> Vec<RowLock> {
+        self.sessions.remove(session);
+        let keys: Vec<(String, i64)> = self
+            .locks
+            .iter()
+            .filter(|lock| lock.session.as_deref() == Some(session))
+            .map(|lock| lock.key().clone())
+            .collect();
+        keys.into_iter()
+            .filter_map(|key| {
+                self.locks
+                    .remove_if(&key, |_, lock| lock.session.as_deref() == Some(session))
+                    .map(|(_, lock)| lock)
+            })
+            .collect()
+    }
+
+    /// Identity a caller holds locks under: the signed-in user, or else
+    /// the session named
+    fn holder(&self, caller: Option<&AuthUser>, session: Option<&str>) -> VibeResult<(HolderKey, Value)> {
+        let label = match session {
+            Some(session) => {
+                let (owner, label) = self
+                    .sessions
+                    .get(session)
+                    .map(|info| (info.owner, info.label.clone()))
+                    .ok_or_else(|| VibeError::InvalidFields {
+                        message: "No open stream has that session".to_string(),
+                        fields: vec!["session".to_string()],
+                    })?;
+                if owner.is_some_and(|owner| caller.map(|user| user.id) != Some(owner)) {
+                    return Err(VibeError::Forbidden(
+                        "The session belongs to another user's stream".to_string(),
+                    ));
+                }
+                Some(label)
+            }
+            None => None,
+        };
+        match (caller, session, label) {
+            (Some(user), _, _) => Ok((HolderKey::User(user.id), AuthUser::actor(Some(user)))),
+            (None, Some(session), Some(label)) => Ok((
+                HolderKey::Session(session.to_string()),
+                json!({ "anonymous": label }),
+            )),
+            (None, _, _) => Err(VibeError::Unauthorized(
+                "Sign in, or pass the session of an open stream, to lock rows".to_string(),
+            )),
+        }
+    }
+
+    /// Takes the lock on a row, or refreshes it when the caller holds it
+    ///
+    /// Fails with `Locked` while someone else holds an unexpired lock, and
+    /// with `Conflict` when the caller already holds
+    /// `MAX_LOCKS_PER_HOLDER` others. The caller checks that the row exists.
+    pub fn acquire(
+        &self,
+        collection: &str,
+        id: i64,
+        caller: Option<&AuthUser>,
+        session: Option<&str>,
+        ttl: Duration,
+    ) -> VibeResult<RowLock> {
+        let (key, holder) = self.holder(caller, session)?;
+        let (now, wall) = (self.clock.instant(), self.clock.now());
+        let held = self
+            .locks
+            .iter()
+            .filter(|lock| lock.held_by(&key) && !lock.expired(now))
+            .filter(|lock| lock.key() != &(collection.to_string(), id))
+            .count();
+        if held >= MAX_LOCKS_PER_HOLDER {
+            return Err(VibeError::Conflict(format!(
+                "A holder may hold at most {} row locks; release some first",
+                MAX_LOCKS_PER_HOLDER
+            )));
+        }
+        let entry = self.locks.entry((collection.to_string(), id));
+        let acquired_at = match &entry {
+            Entry::Occupied(current) => {
+                let current = current.get();
+                if !current.expired(now) && !current.held_by(&key) {
+                    return Err(current.locked_error());
+                }
+                (!current.expired(now)).then(|| current.acquired_at.clone())
+            }
+            Entry::Vacant(_) => None,
+        };
+        let expires_at = wall + chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::zero());
+        let lock = RowLock {
+            collection: collection.to_string(),
+            id,
+            holder,
+            session: session.map(str::to_string),
+            acquired_at: acquired_at.unwrap_or_else(|| audit::format_timestamp(wall)),
+            expires_at: audit::format_timestamp(expires_at),
+            key,
+            deadline: now + ttl,
+            token: self.tokens.fetch_add(1, Ordering::Relaxed),
+        };
+        entry.insert(lock.clone());
+        Ok(lock)
+    }
+
+    /// Releases a lock; admins may release anyone's
+    ///
+    /// Returns the released lock, or `None` when the row wasn't locked.
+    pub fn release(
+        &self,
+        collection: &str,
+        id: i64,
+        caller: Option<&AuthUser>,
+        session: Option<&str>,
+    ) -> VibeResult<Option<RowLock>> {
+        let key = (collection.to_string(), id);
+        let Some(current) = self.get(collection, id) else {
+            return Ok(None);
+        };
+        let admin = caller.is_some_and(AuthUser::is_admin);
+        if !admin && !current.held_by(&self.holder(caller, session)?.0) {
+            return Err(current.locked_error());
+        }
+        Ok(self
+            .locks
+            .remove_if(&key, |_, lock| lock.token == current.token)
+            .map(|(_, lock)| lock))
+    }
+
+    /// The unexpired lock on a row, if any
+    pub fn get(&self, collection: &str, id: i64) -> Option<RowLock> {
+        self.locks
+            .get(&(collection.to_string(), id))
+            .filter(|lock| !lock.expired(self.clock.instant()))
+            .map(|lock| lock.clone())
+    }
+
+    /// Fails with `Locked` when someone other than the caller holds the
+    /// row's lock
+    pub fn check(
+        &self,
+        collection: &str,
+        id: i64,
+        caller: Option<&AuthUser>,
+        session: Option<&str>,
+    ) -> VibeResult<()> {
+        let Some(lock) = self.get(collection, id) else {
+            return Ok(());
+        };
+        let own = match &lock.key {
+            HolderKey::User(holder) => caller.is_some_and(|user| user.id == *holder),
+            HolderKey::Session(holder) => session == Some(holder.as_str()),
+        };
+        if own {
+            Ok(())
+        } else {
+            Err(lock.locked_error())
+        }
+    }
+
+    /// Removes the lock taken as `token` once it has expired
+    pub fn expire(&self, collection: &str, id: i64, token: u64) -> Option<RowLock> {
+        self.locks
+            .remove_if(&(collection.to_string(), id), |_, lock| {
+                lock.token == token && lock.expired(self.clock.instant())
+            })
+            .map(|(_, lock)| lock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ManualClock;
+    use std::sync::Arc;
+
+    fn user(id: i64, email: &str) -> AuthUser {
+        AuthUser {
+            id,
+            email: email.to_string(),
+            role: "user".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_acquire_refresh_release() {
+        let locks = LockRegistry::new();
+        let (alice, bob) = (user(1, "alice@example.com"), user(2, "bob@example.com"));
+
+        let first = locks.acquire("posts", 1, Some(&alice), None, DEFAULT_LOCK_TTL).unwrap();
+        assert_eq!(first.holder["email"], "alice@example.com");
+        let err = locks.acquire("posts", 1, Some(&bob), None, DEFAULT_LOCK_TTL).unwrap_err();
+        assert!(matches!(err, VibeError::Locked { .. }), "{}", err);
+        assert!(locks.check("posts", 1, Some(&bob), None).is_err());
+        assert!(locks.check("posts", 1, Some(&alice), None).is_ok());
+        assert!(locks.check("posts", 2, Some(&bob), None).is_ok());
+
+        let refreshed = locks.acquire("posts", 1, Some(&alice), None, DEFAULT_LOCK_TTL).unwrap();
+        assert_eq!(refreshed.acquired_at, first.acquired_at);
+        assert_ne!(refreshed.token(), first.token());
+        assert!(locks.expire("posts", 1, first.token()).is_none());
+
+        assert!(locks.release("posts", 1, Some(&bob), None).is_err());
+        assert!(locks.release("posts", 1, Some(&alice), None).unwrap().is_some());
+        assert!(locks.get("posts", 1).is_none());
+        assert!(locks.release("posts", 1, Some(&alice), None).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sessions() {
+        let locks = LockRegistry::new();
+        assert!(locks.acquire("posts", 1, None, None, DEFAULT_LOCK_TTL).is_err());
+        assert!(locks.acquire("posts", 1, None, Some("nope"), DEFAULT_LOCK_TTL).is_err());
+
+        let session = locks.open_session(None);
+        let lock = locks.acquire("posts", 1, None, Some(&session), DEFAULT_LOCK_TTL).unwrap();
+        assert_eq!(lock.holder.as_object().unwrap().keys().collect::<Vec<_>>(), ["anonymous"]);
+        assert!(!lock.holder.to_string().contains(&session));
+        assert!(!serde_json::to_string(&lock).unwrap().contains(&session));
+        let carol = user(3, "carol@example.com");
+        let err = locks.acquire("posts", 1, Some(&carol), None, DEFAULT_LOCK_TTL).unwrap_err();
+        let VibeError::Locked { holder, message, .. } = err else {
+            panic!("expected Locked, got {}", err);
+        };
+        assert_eq!(holder, lock.holder);
+        assert!(!message.contains(&session));
+        assert!(locks.check("posts", 1, None, None).is_err());
+        assert!(locks.check("posts", 1, None, Some(&session)).is_ok());
+
+        let alice = user(1, "alice@example.com");
+        let owned = locks.open_session(Some(&alice));
+        let bob = user(2, "bob@example.com");
+        assert!(matches!(
+            locks.acquire("posts", 2, Some(&bob), Some(&owned), DEFAULT_LOCK_TTL),
+            Err(VibeError::Forbidden(_))
+        ));
+
+        let released = locks.close_session(&session);
+        assert_eq!(released.len(), 1);
+        assert!(locks.get("posts", 1).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_locks_per_holder_are_capped() {
+        let locks = LockRegistry::new();
+        let (alice, bob) = (user(1, "alice@example.com"), user(2, "bob@example.com"));
+        for id in 0..MAX_LOCKS_PER_HOLDER as i64 {
+            locks.acquire("posts", id, Some(&alice), None, DEFAULT_LOCK_TTL).unwrap();
+        }
+        let err = locks
+            .acquire("posts", MAX_LOCKS_PER_HOLDER as i64, Some(&alice), None, DEFAULT_LOCK_TTL)
+            .unwrap_err();
+        assert!(matches!(err, VibeError::Conflict(_)), "{}", err);
+
+        // Refreshing a held lock and other holders are unaffected
+        locks.acquire("posts", 0, Some(&alice), None, DEFAULT_LOCK_TTL).unwrap();
+        locks.acquire("posts", MAX_LOCKS_PER_HOLDER as i64, Some(&bob), None, DEFAULT_LOCK_TTL).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_expiry() {
+        let clock = ManualClock::new();
+        let locks = LockRegistry::new().with_clock(Arc::new(clock.clone()));
+        let (alice, bob) = (user(1, "alice@example.com"), user(2, "bob@example.com"));
+
+        let lock = locks.acquire("posts", 1, Some(&alice), None, Duration::from_secs(10)).unwrap();
+        clock.advance(Duration::from_secs(9));
+        assert!(locks.expire("posts", 1, lock.token()).is_none());
+        assert!(locks.acquire("posts", 1, Some(&bob), None, DEFAULT_LOCK_TTL).is_err());
+
+        clock.advance(Duration::from_secs(1));
+        assert!(locks.get("posts", 1).is_none());
+        assert!(locks.check("posts", 1, Some(&bob), None).is_ok());
+        assert!(locks.expire("posts", 1, lock.token()).is_some());
+        let taken = locks.acquire("posts", 1, Some(&bob), None, DEFAULT_LOCK_TTL).unwrap();
+        assert_ne!(taken.acquired_at, lock.acquired_at);
+    }
+}
@@ -14,6 +14,12 @@
 //!
 //! # In-memory mode (for testing)
 //! vibedb --memory
+//!
+//! # Check paths and secrets without starting
+//! vibedb --check
+//!
+//! # Offline maintenance, no server needed
+//! vibedb admin vacuum --db mydata.db
 //! ```
 //!
 //! ## API Usage
@@ -35,16 +41,27 @@ use std::env;
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
-use tracing::{info, Level};
+use tracing::{error, info, warn, Level};
 use tracing_subscriber::FmtSubscriber;
 
-use vibedb::api::{create_router, AppState};
+use vibedb::admin::AdminCommand;
+use vibedb::api::{cors_layer, create_router, nest_under_base_path, AppState};
 use vibedb::auth::{AuthService, AuthState, create_auth_router};
-use vibedb::db::VibeStore;
-use vibedb::explorer::create_explorer_router;
-use vibedb::storage::{StorageService, StorageState, create_storage_router};
+use vibedb::bootstrap::{create_setup_router, parse_bucket_specs, run_bootstrap, SetupState};
+use vibedb::config::{BasePathConfig, BootstrapConfig, DefaultBucketConfig, PreflightConfig, StorageQuotaConfig, VibeConfig};
+use vibedb::db::{BusyRetry, ReopenPolicy, VibeStore};
+use vibedb::explorer::{create_explorer_router_for, ExplorerMode};
+use vibedb::guard::KeyCase;
+use vibedb::limits::limit_concurrency;
+use vibedb::preflight::run_preflight;
+use vibedb::dead_letters;
+use vibedb::row_counts;
+use vibedb::snapshots;
+use vibedb::sources;
+use vibedb::storage::{self, StorageService, StorageState, create_storage_router, DEFAULT_STORAGE_PATH};
 
 /// CLI arguments
 struct Args {
@@ -54,12 +71,106 @@ struct Args {
     port: u16,
     /// Use in-memory database
     in_memory: bool,
+    /// Create the database file's directory if it is missing
+    create_db_dir: bool,
+    /// Run the startup checks and exit
+    check: bool,
     /// Host to bind to
     host: String,
     /// JWT secret for authentication
     jwt_secret: Option<String>,
+    /// Secret replaced by the last rotation; its tokens stay valid
+    jwt_previous_secret: Option<String>,
     /// Storage path for file storage
     storage_path: Option<String>,
+    /// Blocklist override for the raw SQL endpoints
+    sql_blocklist: Option<Vec<String>>,
+    /// Pragmas admins may change through the raw SQL endpoints
+    sql_pragma_allowlist: Option<Vec<String>>,
+    /// Row cap for /v1/sql/query
+    sql_max_rows: Option<usize>,
+    /// Fail over-limit SQL queries instead of truncating
+    sql_strict_rows: bool,
+    /// Require admin approval for raw SQL writes by non-admins
+    sql_approval: bool,
+    /// Statement timeout for /v1/sql/query in milliseconds
+    sql_timeout_ms: Option<u64>,
+    /// Admin email seeded on first start
+    admin_email: Option<String>,
+    /// Admin password seeded on first start
+    admin_password: Option<String>,
+    /// Buckets seeded on first start (`name:public|private,...`)
+    buckets: Option<String>,
+    /// Attempts for writes that hit SQLITE_BUSY
+    busy_retries: Option<u32>,
+    /// Initial backoff between busy retries in milliseconds
+    busy_backoff_ms: Option<u64>,
+    /// Database faults in a row before the connection is reopened
+    reopen_threshold: Option<u32>,
+    /// Days dead letters are kept
+    dead_letter_retention_days: Option<u64>,
+    /// Longest table or column name created
+    max_identifier_length: Option<usize>,
+    /// Deepest nesting of a written document
+    max_nesting_depth: Option<usize>,
+    /// Largest nested value of a written document, in bytes
+    max_nested_bytes: Option<usize>,
+    /// Reject numbers that can't be stored exactly
+    strict_numbers: bool,
+    /// Migration time of one request before it is logged as slow
+    slow_migration_ms: Option<u64>,
+    /// DDL statements of one request before it is logged as slow
+    slow_migration_ddl: Option<u64>,
+    /// Shorten over-long names instead of rejecting them
+    truncate_identifiers: bool,
+    /// Convention payload keys are converted to
+    key_case: Option<KeyCase>,
+    /// Serve the explorer from this directory instead of the binary
+    explorer_dir: Option<String>,
+    /// Proxy the explorer to this Vite dev server
+    explorer_dev_proxy: Option<String>,
+    /// Believe X-Forwarded-Proto/Host/Prefix when building URLs
+    trust_proxy: bool,
+    /// Path prefix every route is served under
+    base_path: Option<String>,
+    /// Rows kept by the get-by-id cache
+    row_cache_size: Option<usize>,
+    /// Serve table row counts from maintained counters
+    row_count_cache: bool,
+    /// Load every collection's schema at startup
+    warm_schema_cache: bool,
+    /// Snapshots open at once at most
+    max_snapshots: Option<usize>,
+    /// Bucket for uploads that name none
+    default_bucket: Option<String>,
+    /// Create the default bucket as public
+    default_bucket_public: bool,
+    /// Bytes of stored files per user at most
+    user_storage_quota: Option<u64>,
+    /// Bytes of stored files at most
+    storage_quota: Option<u64>,
+    /// Requests handled at once at most
+    max_requests: Option<usize>,
+    /// Write requests handled at once at most
+    max_write_requests: Option<usize>,
+    /// Read requests handled at once at most
+    max_read_requests: Option<usize>,
+    /// Storage uploads handled at once at most
+    max_uploads: Option<usize>,
+    /// How long a request waits for a free slot, in milliseconds
+    request_queue_ms: Option<u64>,
+    /// Batch pushes to every collection
+    batch_writes: bool,
+    /// How long a write batch collects pushes, in milliseconds
+    batch_window_ms: Option<u64>,
+    /// Pushes per write batch at most
+    batch_max_writes: Option<usize>,
+    /// Origins allowed by CORS (comma-separated)
+    cors_origins: Option<Vec<String>>,
+    /// Allow credentialed cross-origin requests
+    cors_credentials: bool,
+    /// Preflight cache lifetime in seconds
+    cors_max_age_secs: Option<u64>,
 }
 
 impl Default for Args {
@@ -68,14 +179,62 @@ impl Default for Args {
             db_path: "vibedb.db".to_string(),
             port: 3000,
             in_memory: false,
+            create_db_dir: false,
+            check: false,
             host: "0.0.0.0".to_string(),
             jwt_secret: None,
+            jwt_previous_secret: None,
             storage_path: None,
+            sql_blocklist: None,
+            sql_pragma_allowlist: None,
+            sql_max_rows: None,
+            sql_strict_rows: false,
+            sql_approval: false,
+            sql_timeout_ms: None,
+            admin_email: None,
+            admin_password: None,
+            buckets: None,
+            busy_retries: None,
+            busy_backoff_ms: None,
+            reopen_threshold: None,
+            dead_letter_retention_days: None,
+            max_identifier_length: None,
+            max_nesting_depth: None,
+            max_nested_bytes: None,
+            strict_numbers: false,
+            slow_migration_ms: None,
+            slow_migration_ddl: None,
+            truncate_identifiers: false,
+            key_case: None,
+            explorer_dir: None,
+            explorer_dev_proxy: None,
+            trust_proxy: false,
+            base_path: None,
+            row_cache_size: None,
+            row_count_cache: false,
+            warm_schema_cache: false,
+            max_snapshots: None,
+            default_bucket: None,
+            default_bucket_public: false,
+            user_storage_quota: None,
+            storage_quota: None,
+            max_requests: None,
+            max_write_requests: None,
+            max_read_requests: None,
+            max_uploads: None,
+            request_queue_ms: None,
+            batch_writes: false,
+            batch_window_ms: None,
+            batch_max_writes: None,
+            cors_origins: None,
+            cors_credentials: false,
+            cors_max_age_secs: None,
         }
     }
 }
 
 impl Args {
+    #[allow(clippy::collapsible_match)]
     fn from_env() -> Self {
         let mut args = Args::default();
         let env_args: Vec<String> = env::args().collect();
@@ -104,6 +263,27 @@ impl Args {
                 "--memory" | "-m" => {
                     args.in_memory = true;
                 }
+                "--create-db-dir" => {
+                    args.create_db_dir = true;
+                }
+                "--check" => {
+                    args.check = true;
+                }
+                "--explorer-dir" if i + 1 < env_args.len() => {
+                    args.explorer_dir = Some(env_args[i + 1].clone());
+                    i += 1;
+                }
+                "--explorer-dev-proxy" if i + 1 < env_args.len() => {
+                    args.explorer_dev_proxy = Some(env_args[i + 1].clone());
+                    i += 1;
+                }
+                "--trust-proxy" => {
+                    args.trust_proxy = true;
+                }
+                "--base-path" if i + 1 < env_args.len() => {
+                    args.base_path = Some(env_args[i + 1].clone());
+                    i += 1;
+                }
                 "--help" => {
                     print_help();
                     std::process::exit(0);
@@ -126,12 +306,168 @@ impl Args {
         if env::var("VIBEDB_MEMORY").is_ok() {
             args.in_memory = true;
         }
+        if env::var("VIBEDB_CREATE_DB_DIR").is_ok() {
+            args.create_db_dir = true;
+        }
         if let Ok(secret) = env::var("VIBEDB_JWT_SECRET") {
             args.jwt_secret = Some(secret);
         }
+        if let Ok(secret) = env::var("VIBEDB_JWT_PREVIOUS_SECRET") {
+            args.jwt_previous_secret = Some(secret);
+        }
         if let Ok(storage) = env::var("VIBEDB_STORAGE_PATH") {
             args.storage_path = Some(storage);
         }
+        if let Ok(blocklist) = env::var("VIBEDB_SQL_BLOCKLIST") {
+            args.sql_blocklist = Some(
+                blocklist
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect(),
+            );
+        }
+        if let Ok(allowlist) = env::var("VIBEDB_SQL_PRAGMA_ALLOWLIST") {
+            args.sql_pragma_allowlist = Some(
+                allowlist
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect(),
+            );
+        }
+        if let Ok(max_rows) = env::var("VIBEDB_SQL_MAX_ROWS") {
+            args.sql_max_rows = max_rows.parse().ok();
+        }
+        if env::var("VIBEDB_SQL_STRICT_ROWS").is_ok() {
+            args.sql_strict_rows = true;
+        }
+        if env::var("VIBEDB_SQL_APPROVAL").is_ok() {
+            args.sql_approval = true;
+        }
+        if let Ok(timeout) = env::var("VIBEDB_SQL_TIMEOUT_MS") {
+            args.sql_timeout_ms = timeout.parse().ok();
+        }
+        if let Ok(email) = env::var("VIBEDB_ADMIN_EMAIL") {
+            args.admin_email = Some(email);
+        }
+        if let Ok(password) = env::var("VIBEDB_ADMIN_PASSWORD") {
+            args.admin_password = Some(password);
+        }
+        if let Ok(buckets) = env::var("VIBEDB_BUCKETS") {
+            args.buckets = Some(buckets);
+        }
+        if let Ok(retries) = env::var("VIBEDB_BUSY_RETRIES") {
+            args.busy_retries = retries.parse().ok();
+        }
+        if let Ok(backoff) = env::var("VIBEDB_BUSY_BACKOFF_MS") {
+            args.busy_backoff_ms = backoff.parse().ok();
+        }
+        if let Ok(threshold) = env::var("VIBEDB_REOPEN_THRESHOLD") {
+            args.reopen_threshold = threshold.parse().ok().filter(|&n| n > 0);
+        }
+        if let Ok(days) = env::var("VIBEDB_DEAD_LETTER_RETENTION_DAYS") {
+            args.dead_letter_retention_days = days.parse().ok();
+        }
+        if let Ok(length) = env::var("VIBEDB_MAX_IDENTIFIER_LENGTH") {
+            args.max_identifier_length = length.parse().ok();
+        }
+        if let Ok(depth) = env::var("VIBEDB_MAX_NESTING_DEPTH") {
+            args.max_nesting_depth = depth.parse().ok().filter(|&n| n > 0);
+        }
+        if let Ok(bytes) = env::var("VIBEDB_MAX_NESTED_BYTES") {
+            args.max_nested_bytes = bytes.parse().ok().filter(|&n| n > 0);
+        }
+        if env::var("VIBEDB_STRICT_NUMBERS").is_ok() {
+            args.strict_numbers = true;
+        }
+        if let Ok(ms) = env::var("VIBEDB_SLOW_MIGRATION_MS") {
+            args.slow_migration_ms = ms.parse().ok();
+        }
+        if let Ok(statements) = env::var("VIBEDB_SLOW_MIGRATION_DDL") {
+            args.slow_migration_ddl = statements.parse().ok();
+        }
+        if env::var("VIBEDB_TRUNCATE_IDENTIFIERS").is_ok() {
+            args.truncate_identifiers = true;
+        }
+        if let Ok(case) = env::var("VIBEDB_KEY_CASE") {
+            args.key_case = KeyCase::parse(&case);
+        }
+        if let Ok(dir) = env::var("VIBEDB_EXPLORER_DIR") {
+            args.explorer_dir = Some(dir);
+        }
+        if let Ok(url) = env::var("VIBEDB_EXPLORER_DEV_PROXY") {
+            args.explorer_dev_proxy = Some(url);
+        }
+        if env::var("VIBEDB_TRUST_PROXY").is_ok() {
+            args.trust_proxy = true;
+        }
+        if let Ok(path) = env::var("VIBEDB_BASE_PATH") {
+            args.base_path = Some(path);
+        }
+        if let Ok(size) = env::var("VIBEDB_ROW_CACHE_SIZE") {
+            args.row_cache_size = size.parse().ok();
+        }
+        if env::var("VIBEDB_ROW_COUNT_CACHE").is_ok() {
+            args.row_count_cache = true;
+        }
+        if env::var("VIBEDB_WARM_SCHEMA_CACHE").is_ok() {
+            args.warm_schema_cache = true;
+        }
+        if let Ok(max) = env::var("VIBEDB_MAX_SNAPSHOTS") {
+            args.max_snapshots = max.parse().ok();
+        }
+        if let Ok(name) = env::var("VIBEDB_DEFAULT_BUCKET") {
+            args.default_bucket = Some(name).filter(|name| !name.is_empty());
+        }
+        if env::var("VIBEDB_DEFAULT_BUCKET_PUBLIC").is_ok() {
+            args.default_bucket_public = true;
+        }
+        if let Ok(bytes) = env::var("VIBEDB_USER_STORAGE_QUOTA") {
+            args.user_storage_quota = bytes.parse().ok();
+        }
+        if let Ok(bytes) = env::var("VIBEDB_STORAGE_QUOTA") {
+            args.storage_quota = bytes.parse().ok();
+        }
+        if let Ok(max) = env::var("VIBEDB_MAX_REQUESTS") {
+            args.max_requests = max.parse().ok();
+        }
+        if let Ok(max) = env::var("VIBEDB_MAX_WRITE_REQUESTS") {
+            args.max_write_requests = max.parse().ok();
+        }
+        if let Ok(max) = env::var("VIBEDB_MAX_READ_REQUESTS") {
+            args.max_read_requests = max.parse().ok();
+        }
+        if let Ok(max) = env::var("VIBEDB_MAX_UPLOADS") {
+            args.max_uploads = max.parse().ok();
+        }
+        if let Ok(wait) = env::var("VIBEDB_REQUEST_QUEUE_MS") {
+            args.request_queue_ms = wait.parse().ok();
+        }
+        if env::var("VIBEDB_BATCH_WRITES").is_ok() {
+            args.batch_writes = true;
+        }
+        if let Ok(window) = env::var("VIBEDB_BATCH_WINDOW_MS") {
+            args.batch_window_ms = window.parse().ok();
+        }
+        if let Ok(max_writes) = env::var("VIBEDB_BATCH_MAX_WRITES") {
+            args.batch_max_writes = max_writes.parse().ok().filter(|&n| n > 0);
+        }
+        if let Ok(origins) = env::var("VIBEDB_CORS_ORIGINS") {
+            args.cors_origins = Some(
+                origins
+                    .split(',')
+                    .map(|s| s.trim().trim_end_matches('/').to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect(),
+            );
+        }
+        if env::var("VIBEDB_CORS_CREDENTIALS").is_ok() {
+            args.cors_credentials = true;
+        }
+        if let Ok(max_age) = env::var("VIBEDB_CORS_MAX_AGE_SECS") {
+            args.cors_max_age_secs = max_age.parse().ok();
+        }
 
         args
     }
@@ -144,12 +480,19 @@ fn print_help() {
 
 USAGE:
     vibedb [OPTIONS]
+    vibedb admin <COMMAND> [--db <PATH>] ...   (see `vibedb admin --help`)
 
 OPTIONS:
     -d, --db <PATH>      Database file path [default: vibedb.db]
     -p, --port <PORT>    Server port [default: 3000]
     -h, --host <HOST>    Host to bind to [default: 0.0.0.0]
     -m, --memory         Use in-memory database
+        --create-db-dir  Create the database file's directory if it is missing
+        --check          Check the database and storage paths and the JWT secret, then exit
+        --explorer-dir <DIR>       Serve the explorer from a built ui/dist instead of the binary
+        --explorer-dev-proxy <URL> Proxy the explorer to a Vite dev server, e.g. http://localhost:5173
+        --trust-proxy    Believe X-Forwarded-* and X-Real-IP headers (behind a reverse proxy)
+        --base-path <PATH>         Serve every route under a prefix, e.g. /vibedb
         --help           Print this help message
 
 ENVIRONMENT VARIABLES:
@@ -157,6 +500,53 @@ ENVIRONMENT VARIABLES:
     VIBEDB_PATH          Database file path
     VIBEDB_HOST          Host to bind to
     VIBEDB_MEMORY        Set to use in-memory database
+    VIBEDB_CREATE_DB_DIR Same as --create-db-dir
+    VIBEDB_JWT_SECRET    JWT signing secret, at least 32 bytes (random if not set)
+    VIBEDB_JWT_PREVIOUS_SECRET Secret replaced by the last rotation, still accepted
+    VIBEDB_SQL_BLOCKLIST Comma-separated statements/functions rejected by /v1/sql/*
+    VIBEDB_SQL_PRAGMA_ALLOWLIST Comma-separated pragmas admins may change [default: user_version,application_id,optimize]
+    VIBEDB_SQL_MAX_ROWS  Row cap for /v1/sql/query [default: 10000]
+    VIBEDB_SQL_STRICT_ROWS Set to fail over-limit queries instead of truncating
+    VIBEDB_SQL_TIMEOUT_MS Statement timeout for /v1/sql/query
+    VIBEDB_SQL_APPROVAL  Set to make /v1/sql/execute admin-only (others use /v1/sql/propose)
+    VIBEDB_ADMIN_EMAIL   Admin user created on first start (with VIBEDB_ADMIN_PASSWORD)
+    VIBEDB_ADMIN_PASSWORD Password for the bootstrap admin
+    VIBEDB_BUCKETS       Buckets created if absent, e.g. "assets:public,uploads:private"
+    VIBEDB_BUSY_RETRIES  Attempts for writes that hit SQLITE_BUSY [default: 3]
+    VIBEDB_BUSY_BACKOFF_MS Initial backoff between busy retries, doubled each time [default: 10]
+    VIBEDB_REOPEN_THRESHOLD Database I/O faults within a minute before the connection is reopened [default: 3]
+    VIBEDB_DEAD_LETTER_RETENTION_DAYS Days rejected pushes are kept [default: 30]
+    VIBEDB_MAX_IDENTIFIER_LENGTH Longest collection or key name created [default: 128]
+    VIBEDB_MAX_NESTING_DEPTH Deepest nesting of a written document [default: 32]
+    VIBEDB_MAX_NESTED_BYTES Largest nested object or array of a written document, as JSON [default: 1048576]
+    VIBEDB_STRICT_NUMBERS Set to reject integers beyond i64 and large float integers instead of storing them with a warning
+    VIBEDB_SLOW_MIGRATION_MS Warn when a request spends longer migrating schemas [default: 100]
+    VIBEDB_SLOW_MIGRATION_DDL Warn when a request runs more DDL statements [default: 10]
+    VIBEDB_TRUNCATE_IDENTIFIERS Set to shorten longer names (kept as aliases) instead of rejecting them
+    VIBEDB_KEY_CASE      Convert pushed keys to snake_case or lowercase columns [default: preserve]
+    VIBEDB_EXPLORER_DIR  Same as --explorer-dir
+    VIBEDB_EXPLORER_DEV_PROXY Same as --explorer-dev-proxy
+    VIBEDB_TRUST_PROXY   Same as --trust-proxy
+    VIBEDB_BASE_PATH     Same as --base-path
+    VIBEDB_ROW_CACHE_SIZE Rows cached for lookups by id, 0 to disable [default: 0]
+    VIBEDB_ROW_COUNT_CACHE Set to serve table row counts from counters kept by writes instead of COUNT(*)
+    VIBEDB_WARM_SCHEMA_CACHE Set to load every collection's schema at startup instead of on first use
+    VIBEDB_MAX_SNAPSHOTS Snapshots (POST /v1/snapshots) open at once at most [default: 4]
+    VIBEDB_DEFAULT_BUCKET Bucket created at startup for POST /v1/storage/upload/*path
+    VIBEDB_DEFAULT_BUCKET_PUBLIC Set to create the default bucket as public
+    VIBEDB_USER_STORAGE_QUOTA Bytes of stored files each user may own [default: unlimited]
+    VIBEDB_STORAGE_QUOTA Bytes of stored files in total [default: unlimited]
+    VIBEDB_MAX_REQUESTS  Requests handled at once at most [default: 1024]
+    VIBEDB_MAX_WRITE_REQUESTS Write requests handled at once at most [default: 256]
+    VIBEDB_MAX_READ_REQUESTS Read requests handled at once at most [default: 512]
+    VIBEDB_MAX_UPLOADS   Storage uploads handled at once at most [default: 32]
+    VIBEDB_REQUEST_QUEUE_MS How long a request waits for a slot before a 503 [default: 100]
+    VIBEDB_BATCH_WRITES  Set to batch concurrent pushes to every collection
+    VIBEDB_BATCH_WINDOW_MS How long a write batch collects pushes [default: 5]
+    VIBEDB_BATCH_MAX_WRITES Pushes per write batch at most [default: 64]
+    VIBEDB_CORS_ORIGINS  Comma-separated origins allowed by CORS [default: any]
+    VIBEDB_CORS_CREDENTIALS Set to allow credentialed requests (needs VIBEDB_CORS_ORIGINS)
+    VIBEDB_CORS_MAX_AGE_SECS Preflight cache lifetime, 0 to disable [default: 600]
 
 EXAMPLES:
     # Start with default settings
@@ -173,17 +563,97 @@ API ENDPOINTS:
     POST /v1/push/:collection/batch Batch insert
     GET  /v1/query/:collection      Query data with filters
     GET  /v1/query/:collection/:id  Get by ID
+    GET  /v1/query/:collection/aggregate  Grouped metrics and percentiles
     POST /v1/update/:collection/:id Update document
     POST /v1/delete/:collection/:id Delete document
+    POST /v1/delete/:collection     Delete by filters (count_only=true to count first)
     GET  /v1/tables                 List all tables
     GET  /v1/tables/:collection     Get table stats
+    GET  /v1/tables/:collection/perf  Write latency, retries and migrations
+    POST /v1/tables/:collection/computed  Add a virtual generated column
+    GET  /v1/tables/:collection/column-usage  Find columns no longer populated
+    GET  /v1/schema/:collection/jsonschema  Row shape as JSON Schema
+    GET  /v1/admin/schema-cache     Find stale cached schemas (DELETE to clear, admin)
+    POST /v1/tables/:src/move-to/:dst Move matching rows to another table
+    GET  /v1/export                 Consistent NDJSON snapshot of all tables (admin)
+    POST /v1/sql/propose            Submit SQL for admin approval
+    GET  /v1/sql/proposals          List SQL proposals
+    POST /v1/sql/proposals/:id/approve  Approve and execute a proposal (admin)
+    POST /v1/sql/proposals/:id/reject   Reject a proposal (admin)
+    GET  /v1/sources                List pull sources (admin)
+    POST /v1/sources                Poll a remote JSON endpoint into a collection
+    POST /v1/sources/:id/run        Run a source now
+    GET  /v1/sources/:id/runs       Fetched/inserted/skipped/error counts per run
+    GET  /v1/dead-letters           Rejected pushes of dead_letter collections (admin)
+    POST /v1/dead-letters/:id/retry Push a dead letter again
     GET  /v1/stream/:collection     SSE stream for real-time updates
     GET  /explore                   Vibe-Explorer dashboard
-    GET  /health                    Health check
+    GET  /health                    Readiness check (alias of /health/ready)
+    GET  /health/live               Liveness probe (process is up)
+    GET  /health/ready              Readiness probe (database reachable, 503 otherwise)
+    GET  /v1/capabilities           Optional SQLite features of this build
+    GET  /metrics                   Per-collection write stats (Prometheus)
 "#
     );
 }
 
+fn print_admin_help() {
+    println!(
+        r#"
+🛸 VibeDB - Offline admin commands (no server needed)
+
+USAGE:
+    vibedb admin <COMMAND> [--db <PATH>] [OPTIONS]
+
+COMMANDS:
+    vacuum            Rebuild the database file and return free space
+    export            Write a collection as NDJSON (the /v1/export line format)
+                          --collection <NAME> --out <FILE> [--format ndjson]
+    create-user       Create a user
+                          --email <EMAIL> --password <PASSWORD> [--role user|admin]
+                          (--password defaults to VIBEDB_ADMIN_PASSWORD)
+    integrity-check   Run SQLite's integrity check; exits 1 on problems
+
+    --db defaults to VIBEDB_PATH, then vibedb.db. A running server may keep the
+    file open; commands that write give up after 2s waiting for its lock.
+"#
+    );
+}
+
+/// Runs `vibedb admin ...` against the database file and exits
+async fn run_admin(args: &[String]) -> Result<()> {
+    // Logs go to stderr, the summary to stdout
+    FmtSubscriber::builder()
+        .with_max_level(Level::WARN)
+        .with_writer(std::io::stderr)
+        .with_target(false)
+        .compact()
+        .init();
+
+    if args.is_empty() || args.iter().any(|arg| arg == "--help") {
+        print_admin_help();
+        return Ok(());
+    }
+    let default_db = env::var("VIBEDB_PATH").unwrap_or_else(|_| Args::default().db_path);
+    let command = match AdminCommand::parse(args, &default_db) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("error: {}\n\nRun `vibedb admin --help` for usage.", message);
+            std::process::exit(2);
+        }
+    };
+    match command.run().await {
+        Ok(summary) => {
+            println!("{}", summary);
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
 fn print_banner(port: u16, in_memory: bool, db_path: &str) {
     println!(
         r#"
@@ -212,8 +682,24 @@ fn print_banner(port: u16, in_memory: bool, db_path: &str) {
     );
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
+fn main() -> Result<()> {
+    let argv: Vec<String> = env::args().collect();
+    if argv.get(1).map(String::as_str) == Some("admin") {
+        return tokio::runtime::Runtime::new()?.block_on(run_admin(&argv[2..]));
+    }
+
+    // Parse arguments
+    let args = Args::from_env();
+    // Keep the password out of the environment of anything we spawn. The
+    // environment is only safe to change while this is the only thread, so
+    // before the runtime starts.
+    env::remove_var("VIBEDB_ADMIN_PASSWORD");
+
+    tokio::runtime::Runtime::new()?.block_on(serve(args))
+}
+
+/// Runs the server until it is shut down
+async fn serve(args: Args) -> Result<()> {
     // Initialize logging
     FmtSubscriber::builder()
         .with_max_level(Level::INFO)
@@ -224,17 +710,49 @@ async fn main() -> Result<()> {
         .compact()
         .init();
 
-    // Parse arguments
-    let args = Args::from_env();
+    // Fail on unusable paths or secrets before opening anything
+    let preflight = PreflightConfig {
+        db_path: (!args.in_memory).then(|| PathBuf::from(&args.db_path)),
+        create_db_dir: args.create_db_dir,
+        storage_path: PathBuf::from(args.storage_path.as_deref().unwrap_or(DEFAULT_STORAGE_PATH)),
+        jwt_secret: args.jwt_secret.as_ref().map(|secret| secret.as_bytes().to_vec()),
+    };
+    let checked = run_preflight(&preflight);
+    drop(preflight);
+    match checked {
+        Ok(()) if args.check => {
+            println!("✅ Startup checks passed");
+            return Ok(());
+        }
+        Ok(()) => {}
+        Err(e) => {
+            error!("❌ {}", e);
+            std::process::exit(1);
+        }
+    }
 
     // Initialize database
+    let mut busy_retry = BusyRetry::default();
+    if let Some(retries) = args.busy_retries {
+        busy_retry.max_attempts = retries.max(1);
+    }
+    if let Some(backoff) = args.busy_backoff_ms {
+        busy_retry.base_delay = Duration::from_millis(backoff);
+    }
+
     let store = if args.in_memory {
         info!("🧪 Using in-memory database");
-        Arc::new(VibeStore::in_memory().await?)
+        VibeStore::in_memory().await?
     } else {
         info!("💾 Using database file: {}", args.db_path);
-        Arc::new(VibeStore::new(&args.db_path).await?)
+        VibeStore::new(&args.db_path).await?
     };
+    let mut reopen_policy = ReopenPolicy::default();
+    if let Some(threshold) = args.reopen_threshold {
+        reopen_policy.fault_threshold = threshold;
+    }
+    let store = Arc::new(store.with_busy_retry(busy_retry).with_reopen_policy(reopen_policy));
+    info!("🧩 SQLite capabilities: {:?}", store.capabilities());
 
     // Initialize JWT secret (use provided or generate new)
     let jwt_secret = args
@@ -246,22 +764,221 @@ async fn main() -> Result<()> {
         });
 
     // Initialize Auth Service
-    let auth_service = AuthService::new(Arc::clone(&store), jwt_secret).await?;
-    let auth_state = AuthState { auth: auth_service };
+    let mut auth_service = AuthService::new(Arc::clone(&store), jwt_secret).await?;
+    if let Some(previous) = args.jwt_previous_secret {
+        auth_service = auth_service.with_previous_secret(previous.into_bytes());
+    }
 
     // Initialize Storage Service
     let storage_path = args.storage_path.map(PathBuf::from);
-    let storage_service = StorageService::new(Arc::clone(&store), storage_path).await?;
-    let storage_state = StorageState { storage: storage_service };
+    let mut storage_service = StorageService::new(Arc::clone(&store), storage_path)
+        .await?
+        .with_quotas(StorageQuotaConfig {
+            per_user: args.user_storage_quota,
+            global: args.storage_quota,
+        });
+    if let Some(name) = args.default_bucket {
+        storage_service = storage_service
+            .with_default_bucket(DefaultBucketConfig {
+                name,
+                public: args.default_bucket_public,
+            })
+            .await?;
+    }
+
+    // Seed admin user and buckets
+    let bootstrap = BootstrapConfig {
+        admin_email: args.admin_email,
+        admin_password: args.admin_password,
+        buckets: match args.buckets {
+            Some(spec) => parse_bucket_specs(&spec)?,
+            None => Vec::new(),
+        },
+    };
+    let report = run_bootstrap(&store, &auth_service, &storage_service, &bootstrap).await?;
+    drop(bootstrap);
+    info!(
+        "🌱 Bootstrap complete: admin={}, buckets created={:?}, existing={:?}",
+        report.admin_seeded.as_deref().unwrap_or("none"),
+        report.buckets_created,
+        report.buckets_existing
+    );
+
+    let mut storage_state = StorageState {
+        storage: storage_service,
+        auth: Some(auth_service.clone()),
+        trust_proxy: args.trust_proxy,
+        base_path: Default::default(),
+    };
+    let setup_state = SetupState {
+        auth: auth_service.clone(),
+        storage: storage_state.storage.clone(),
+    };
+    let auth_state = AuthState { auth: auth_service };
+
+    // Build runtime configuration
+    let mut config = VibeConfig::default();
+    if let Some(blocklist) = args.sql_blocklist {
+        config.sql.blocklist = blocklist;
+    }
+    if let Some(allowlist) = args.sql_pragma_allowlist {
+        config.sql.pragma_allowlist = allowlist;
+    }
+    if let Some(max_rows) = args.sql_max_rows {
+        config.sql.max_rows = max_rows;
+    }
+    config.sql.strict_row_limit = args.sql_strict_rows;
+    config.sql.approval_required = args.sql_approval;
+    config.sql.statement_timeout = args.sql_timeout_ms.map(Duration::from_millis);
+    if let Some(days) = args.dead_letter_retention_days {
+        config.dead_letters.retention = Duration::from_secs(days * 24 * 3600);
+    }
+    if let Some(length) = args.max_identifier_length {
+        config.identifiers.max_length = length;
+    }
+    config.identifiers.truncate = args.truncate_identifiers;
+    if let Some(depth) = args.max_nesting_depth {
+        config.payload.max_depth = depth;
+    }
+    if let Some(bytes) = args.max_nested_bytes {
+        config.payload.max_nested_bytes = bytes;
+    }
+    config.payload.strict_numbers = args.strict_numbers;
+    if let Some(ms) = args.slow_migration_ms {
+        config.migrations.slow_after = Duration::from_millis(ms);
+    }
+    if let Some(statements) = args.slow_migration_ddl {
+        config.migrations.slow_ddl_statements = statements;
+    }
+    if let Some(size) = args.row_cache_size {
+        config.query.row_cache_size = size;
+    }
+    config.query.row_count_cache = args.row_count_cache;
+    config.schema_cache.warm_up = args.warm_schema_cache;
+    if let Some(max) = args.max_snapshots {
+        config.snapshots.max_open = max;
+    }
+    if let Some(max) = args.max_requests {
+        config.concurrency.global = max;
+    }
+    if let Some(max) = args.max_write_requests {
+        config.concurrency.writes = max;
+    }
+    if let Some(max) = args.max_read_requests {
+        config.concurrency.reads = max;
+    }
+    if let Some(max) = args.max_uploads {
+        config.concurrency.uploads = max;
+    }
+    if let Some(wait) = args.request_queue_ms {
+        config.concurrency.acquire_timeout = Duration::from_millis(wait);
+    }
+    config
+        .concurrency
+        .validate()
+        .map_err(|e| anyhow::anyhow!("Invalid concurrency limits: {}", e))?;
+    config.batching.enabled = args.batch_writes;
+    if let Some(window) = args.batch_window_ms {
+        config.batching.window = Duration::from_millis(window);
+    }
+    if let Some(max_writes) = args.batch_max_writes {
+        config.batching.max_writes = max_writes;
+    }
+    if config.query.row_cache_size > 0 {
+        info!("🗃️ Row cache on ({} rows)", config.query.row_cache_size);
+    }
+    if config.batching.enabled {
+        info!(
+            "📦 Write batching on for all collections ({:?} window, up to {} writes)",
+            config.batching.window, config.batching.max_writes
+        );
+    }
+    if let Some(case) = args.key_case {
+        config.identifiers.key_case = case;
+    }
+    if let Some(origins) = args.cors_origins {
+        config.cors.allowed_origins = origins;
+    }
+    config.cors.allow_credentials = args.cors_credentials;
+    if let Some(max_age) = args.cors_max_age_secs {
+        config.cors.max_age = (max_age > 0).then(|| Duration::from_secs(max_age));
+    }
+    config
+        .cors
+        .validate()
+        .map_err(|e| anyhow::anyhow!("Invalid CORS configuration: {}", e))?;
+    let cors = cors_layer(&config.cors);
+    config.explorer = match (args.explorer_dev_proxy, args.explorer_dir) {
+        (Some(url), dir) => {
+            if dir.is_some() {
+                warn!("⚠️ Both an explorer directory and a dev proxy are set; using the proxy");
+            }
+            info!("🧪 Explorer proxied to {}", url);
+            ExplorerMode::DevProxy(url)
+        }
+        (None, Some(dir)) => {
+            info!("🧪 Explorer served from {}", dir);
+            ExplorerMode::Directory(dir.into())
+        }
+        (None, None) => ExplorerMode::Embedded,
+    };
+    if let Some(path) = &args.base_path {
+        config.base_path.prefix =
+            BasePathConfig::normalize(path).map_err(|e| anyhow::anyhow!("Invalid base path: {}", e))?;
+        if !config.base_path.prefix.is_empty() {
+            info!("🧭 Serving under {}", config.base_path.prefix);
+        }
+    }
+    config.base_path.trust_forwarded = args.trust_proxy;
+    storage_state.base_path = config.base_path.clone();
+    let explorer = create_explorer_router_for(&config.explorer, &config.base_path);
 
     // Create application state
-    let state = AppState::new(Arc::clone(&store));
+    let warm_up = config.schema_cache.warm_up;
+    let state = AppState::with_config(Arc::clone(&store), config)
+        .with_auth(auth_state.auth.clone())
+        .with_storage(storage_state.storage.clone());
+
+    // Load collection schemas before the first pushes need them
+    if warm_up {
+        let started = Instant::now();
+        match state.guard.warm_cache().await {
+            Ok(loaded) => info!(
+                "🔥 Schema cache warmed: {} collection(s) in {:?}",
+                loaded,
+                started.elapsed()
+            ),
+            Err(e) => warn!("⚠️ Schema cache warm-up failed after {:?}: {}", started.elapsed(), e),
+        }
+    }
+
+    // Poll pull sources in the background
+    let _scheduler = sources::spawn_scheduler(state.clone(), sources::SCHEDULER_TICK);
+
+    // Purge expired dead letters
+    let _sweeper = dead_letters::spawn_sweeper(state.clone(), dead_letters::SWEEP_INTERVAL);
+
+    // End expired snapshots
+    let _snapshot_sweeper = snapshots::spawn_sweeper(state.clone(), snapshots::SWEEP_INTERVAL);
+
+    // Correct drifted row counts
+    let _count_sweeper = state
+        .row_counts
+        .is_enabled()
+        .then(|| row_counts::spawn_sweeper(state.clone(), row_counts::RECONCILE_INTERVAL));
+
+    // Feed storage events into the /v1/stream/_storage pipeline
+    let _storage_forwarder = storage::spawn_event_forwarder(state.clone(), &storage_state.storage);
 
     // Build router with API, Auth, Storage, and Explorer
+    let limits = Arc::clone(&state.limits);
+    let base_path = state.config.base_path.clone();
     let app = create_router(state)
-        .nest("/v1/auth", create_auth_router(auth_state))
+        .nest("/v1/auth", create_auth_router(auth_state).merge(create_setup_router(setup_state)))
         .nest("/v1/storage", create_storage_router(storage_state))
-        .merge(create_explorer_router());
+        .merge(explorer)
+        .layer(axum::middleware::from_fn_with_state(limits, limit_concurrency));
+    let app = nest_under_base_path(app, &base_path).layer(cors);
 
     // Print banner
     print_banner(args.port, args.in_memory, &args.db_path);
@@ -274,7 +991,7 @@ async fn main() -> Result<()> {
     info!("🚀 VibeDB listening on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app)
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .with_graceful_shutdown(async {
             tokio::signal::ctrl_c()
                 .await
@@ -0,0 +1,590 @@
+//! # Write Performance
+//!
+//! Per-collection write statistics, so slow ingestion can be pinned on
+//! SQLite lock contention, schema migrations or the clients themselves.
+//! The push, batch, update and delete handlers time their writes; the
+//! schema guard counts and times migrations and tallies hits of its schema
+//! cache; busy retries come from `VibeStore`,
+//! which tallies them per write label (see `VibeStore::execute_labeled`).
+//! Read requests (queries, single-document and as-of reads, aggregates) are
+//! only counted, to show which collections are hot. Queries also count the
+//! columns they filter and order by, which `GET /v1/admin/index-suggestions`
+//! checks against the collection's indexes.
+//!
+//! Served per collection by `GET /v1/tables/:collection/perf` and rolled
+//! up in Prometheus text format by `GET /metrics`.
+//!
+//! Only the first `MAX_TRACKED_COLLECTIONS` collections get their own
+//! counters; writes to any further collection are counted under
+//! `OVERFLOW_LABEL`, so a client inventing collection names can't grow the
+//! registry without bound.
+
+use crate::db::VibeStore;
+
+use dashmap::DashMap;
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Most collections tracked individually
+pub const MAX_TRACKED_COLLECTIONS: usize = 256;
+
+/// Most columns whose query use is counted per collection
+pub const MAX_TRACKED_QUERY_COLUMNS: usize = 64;
+
+/// Label shared by collections beyond `MAX_TRACKED_COLLECTIONS`
+pub const OVERFLOW_LABEL: &str = "_other";
+
+/// Upper bounds of the latency histogram buckets, in milliseconds; a final
+/// bucket catches everything slower
+const LATENCY_BUCKETS_MS: [u64; 10] = [1, 2, 5, 10, 25, 50, 100, 250, 500, 1000];
+
+/// Seconds covered by `writes_per_sec`
+const RATE_WINDOW_SECS: u64 = 60;
+
+/// Index of the latency bucket holding `elapsed`
+fn latency_bucket(elapsed: Duration) -> usize {
+    let ms = elapsed.as_millis() as u64;
+    LATENCY_BUCKETS_MS
+        .iter()
+        .position(|bound| ms < *bound)
+        .unwrap_or(LATENCY_BUCKETS_MS.len())
+}
+
+/// A counter of `CollectionPerf` exported as-is
+type CounterField = fn(&CollectionPerf) -> &AtomicU64;
+
+/// Bucket counts and microsecond sum of a `CollectionPerf` histogram
+type HistogramFields = fn(&CollectionPerf) -> (Vec<u64>, &AtomicU64);
+
+/// Counters for one collection (or the overflow bucket)
+#[derive(Debug)]
+pub struct CollectionPerf {
+    reads: AtomicU64,
+    writes: AtomicU64,
+    rows: AtomicU64,
+    errors: AtomicU64,
+    latency_us: AtomicU64,
+    buckets: [AtomicU64; LATENCY_BUCKETS_MS.len() + 1],
+    migrations: AtomicU64,
+    /// Total and histogram of the time spent in migration DDL
+    migration_us: AtomicU64,
+    migration_buckets: [AtomicU64; LATENCY_BUCKETS_MS.len() + 1],
+    /// Payloads rejected for exceeding the column limit
+    column_limit_rejections: AtomicU64,
+    /// Columns after the latest migration; 0 until one was seen
+    columns: AtomicU64,
+    /// Writes per second over the last minute, as `(second, count)` slots
+    ///
+    /// A slot is reset by the first write of a new second; a write racing
+    /// that reset may be lost, which is fine for a rate.
+    recent: [(AtomicU64, AtomicU64); RATE_WINDOW_SECS as usize],
+    /// Query use per column, for at most `MAX_TRACKED_QUERY_COLUMNS`
+    query_columns: DashMap<String, ColumnCounters>,
+}
+
+/// Query use of one column
+#[derive(Debug, Default)]
+struct ColumnCounters {
+    filters: AtomicU64,
+    order_by: AtomicU64,
+}
+
+/// How a query used a column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnUse {
+    Filter,
+    OrderBy,
+}
+
+/// Query use of a column since startup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QueryColumnUse {
+    pub collection: String,
+    pub column: String,
+    pub filter_uses: u64,
+    pub order_by_uses: u64,
+}
+
+impl QueryColumnUse {
+    pub fn total(&self) -> u64 {
+        self.filter_uses + self.order_by_uses
+    }
+}
+
+impl Default for CollectionPerf {
+    fn default() -> Self {
+        Self {
+            reads: AtomicU64::new(0),
+            writes: AtomicU64::new(0),
+            rows: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            latency_us: AtomicU64::new(0),
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            migrations: AtomicU64::new(0),
+            migration_us: AtomicU64::new(0),
+            migration_buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            column_limit_rejections: AtomicU64::new(0),
+            columns: AtomicU64::new(0),
+            recent: std::array::from_fn(|_| (AtomicU64::new(u64::MAX), AtomicU64::new(0))),
+            query_columns: DashMap::new(),
+        }
+    }
+}
+
+impl CollectionPerf {
+    fn record_write(&self, second: u64, elapsed: Duration, rows: u64, ok: bool) {
+        self.writes.fetch_add(1, Ordering::Relaxed);
+        if !ok {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.rows.fetch_add(rows, Ordering::Relaxed);
+        self.latency_us
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+        self.buckets[latency_bucket(elapsed)].fetch_add(1, Ordering::Relaxed);
+
+        let (stamp, count) = &self.recent[(second % RATE_WINDOW_SECS) as usize];
+        if stamp.swap(second, Ordering::Relaxed) == second {
+            count.fetch_add(1, Ordering::Relaxed);
+        } else {
+            count.store(1, Ordering::Relaxed);
+        }
+    }
+
+    fn writes_per_sec(&self, second: u64) -> f64 {
+        let recent: u64 = self
+            .recent
+            .iter()
+            .filter(|(stamp, _)| {
+                let stamp = stamp.load(Ordering::Relaxed);
+                stamp <= second && second - stamp < RATE_WINDOW_SECS
+            })
+            .map(|(_, count)| count.load(Ordering::Relaxed))
+            .sum();
+        recent as f64 / RATE_WINDOW_SECS as f64
+    }
+
+    fn bucket_counts(&self) -> Vec<u64> {
+        self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).collect()
+    }
+
+    fn migration_bucket_counts(&self) -> Vec<u64> {
+        self.migration_buckets.iter().map(|b| b.load(Ordering::Relaxed)).collect()
+    }
+
+    /// Upper bound of the bucket holding the `percent`th fastest write
+    fn percentile_ms(counts: &[u64], percent: u64) -> Option<u64> {
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return None;
+        }
+        let rank = (percent * total).div_ceil(100).max(1);
+        let mut seen = 0;
+        for (i, count) in counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                // The overflow bucket reports the largest finite bound
+                return Some(LATENCY_BUCKETS_MS[i.min(LATENCY_BUCKETS_MS.len() - 1)]);
+            }
+        }
+        None
+    }
+}
+
+/// Write latency summary in milliseconds
+///
+/// Percentiles are bucket upper bounds, so `p95_ms: 10` means 95% of
+/// writes finished in under 10ms.
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencySummary {
+    pub avg_ms: Option<f64>,
+    pub p50_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+    pub p99_ms: Option<u64>,
+}
+
+/// Write statistics of one collection, as served by the perf endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct PerfSnapshot {
+    pub collection: String,
+    /// `OVERFLOW_LABEL` when the collection shares the overflow bucket
+    pub tracked_as: String,
+    /// Read requests since startup
+    pub reads: u64,
+    pub writes: u64,
+    pub rows_written: u64,
+    pub write_errors: u64,
+    pub writes_per_sec: f64,
+    pub latency: LatencySummary,
+    /// Table creations and column additions
+    pub migrations: u64,
+    /// Time spent in their DDL
+    pub migration_ms: f64,
+    /// Payloads rejected for exceeding the column limit
+    pub column_limit_rejections: u64,
+    /// Busy/locked writes retried by `VibeStore`
+    pub busy_retries: u64,
+}
+
+/// Per-collection write statistics, shared through `AppState`
+#[derive(Debug)]
+pub struct PerfRegistry {
+    collections: DashMap<String, Arc<CollectionPerf>>,
+    max_collections: usize,
+    started: Instant,
+    /// Schema lookups of the guard answered from its cache, and not
+    schema_cache_hits: AtomicU64,
+    schema_cache_misses: AtomicU64,
+}
+
+impl Default for PerfRegistry {
+    fn default() -> Self {
+        Self::new(MAX_TRACKED_COLLECTIONS)
+    }
+}
+
+impl PerfRegistry {
+    /// Creates a registry tracking at most `max_collections` individually
+    pub fn new(max_collections: usize) -> Self {
+        Self {
+            collections: DashMap::new(),
+            max_collections,
+            started: Instant::now(),
+            schema_cache_hits: AtomicU64::new(0),
+            schema_cache_misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Name a collection's counters are kept under
+    ///
+    /// Collections already tracked keep their own name; new ones get one
+    /// while there is room and `OVERFLOW_LABEL` after that. Pass this to
+    /// the labeled `VibeStore` writes so retries line up with the rest.
+    pub fn label(&self, collection: &str) -> String {
+        if self.collections.contains_key(collection) || self.collections.len() < self.max_collections {
+            collection.to_string()
+        } else {
+            OVERFLOW_LABEL.to_string()
+        }
+    }
+
+    fn entry(&self, collection: &str) -> Arc<CollectionPerf> {
+        let label = self.label(collection);
+        self.collections.entry(label).or_default().clone()
+    }
+
+    fn second(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+
+    /// Records one write request of `rows` rows that took `elapsed`
+    pub fn record_write(&self, collection: &str, elapsed: Duration, rows: u64, ok: bool) {
+        self.entry(collection)
+            .record_write(self.second(), elapsed, rows, ok);
+    }
+
+    /// Records one read request
+    pub fn record_read(&self, collection: &str) {
+        self.entry(collection).reads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a column filtered or ordered by in a query
+    ///
+    /// Columns beyond `MAX_TRACKED_QUERY_COLUMNS` of a collection are not
+    /// counted.
+    pub fn record_query_column(&self, collection: &str, column: &str, usage: ColumnUse) {
+        let perf = self.entry(collection);
+        if !perf.query_columns.contains_key(column) && perf.query_columns.len() >= MAX_TRACKED_QUERY_COLUMNS {
+            return;
+        }
+        let counters = perf.query_columns.entry(column.to_string()).or_default();
+        let counter = match usage {
+            ColumnUse::Filter => &counters.filters,
+            ColumnUse::OrderBy => &counters.order_by,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Query use of every counted column of the individually tracked
+    /// collections, by collection and column
+    pub fn query_columns(&self) -> Vec<QueryColumnUse> {
+        let mut uses: Vec<QueryColumnUse> = self
+            .collections
+            .iter()
+            .filter(|entry| entry.key() != OVERFLOW_LABEL)
+            .flat_map(|entry| {
+                let collection = entry.key().clone();
+                entry
+                    .value()
+                    .query_columns
+                    .iter()
+                    .map(|column| QueryColumnUse {
+                        collection: collection.clone(),
+                        column: column.key().clone(),
+                        filter_uses: column.filters.load(Ordering::Relaxed),
+                        order_by_uses: column.order_by.load(Ordering::Relaxed),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        uses.sort_by(|a, b| (&a.collection, &a.column).cmp(&(&b.collection, &b.column)));
+        uses
+    }
+
+    /// Records a table creation or a set of added columns whose DDL took
+    /// `elapsed`
+    pub fn record_migration(&self, collection: &str, elapsed: Duration) {
+        let perf = self.entry(collection);
+        perf.migrations.fetch_add(1, Ordering::Relaxed);
+        perf.migration_us.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+        perf.migration_buckets[latency_bucket(elapsed)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a schema lookup of the guard, answered from its cache or not
+    pub fn record_schema_cache(&self, hit: bool) {
+        let counter = if hit { &self.schema_cache_hits } else { &self.schema_cache_misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a collection's column count after a migration
+    pub fn record_columns(&self, collection: &str, columns: usize) {
+        self.entry(collection).columns.store(columns as u64, Ordering::Relaxed);
+    }
+
+    /// Records a payload rejected for exceeding the column limit
+    pub fn record_column_limit_rejection(&self, collection: &str) {
+        self.entry(collection)
+            .column_limit_rejections
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Statistics for a collection; all zero if it has seen no writes
+    pub fn snapshot(&self, collection: &str, store: &VibeStore) -> PerfSnapshot {
+        let tracked_as = self.label(collection);
+        let perf = self
+            .collections
+            .get(&tracked_as)
+            .map(|p| p.clone())
+            .unwrap_or_default();
+
+        let counts = perf.bucket_counts();
+        let ok_writes: u64 = counts.iter().sum();
+        let latency_us = perf.latency_us.load(Ordering::Relaxed);
+        PerfSnapshot {
+            collection: collection.to_string(),
+            reads: perf.reads.load(Ordering::Relaxed),
+            writes: perf.writes.load(Ordering::Relaxed),
+            rows_written: perf.rows.load(Ordering::Relaxed),
+            write_errors: perf.errors.load(Ordering::Relaxed),
+            writes_per_sec: perf.writes_per_sec(self.second()),
+            latency: LatencySummary {
+                avg_ms: (ok_writes > 0).then(|| latency_us as f64 / ok_writes as f64 / 1000.0),
+                p50_ms: CollectionPerf::percentile_ms(&counts, 50),
+                p95_ms: CollectionPerf::percentile_ms(&counts, 95),
+                p99_ms: CollectionPerf::percentile_ms(&counts, 99),
+            },
+            migrations: perf.migrations.load(Ordering::Relaxed),
+            migration_ms: perf.migration_us.load(Ordering::Relaxed) as f64 / 1000.0,
+            column_limit_rejections: perf.column_limit_rejections.load(Ordering::Relaxed),
+            busy_retries: store.busy_retries(&tracked_as),
+            tracked_as,
+        }
+    }
+
+    /// Every tracked collection in Prometheus text exposition format
+    pub fn render_prometheus(&self, store: &VibeStore) -> String {
+        let mut labels: Vec<String> = self.collections.iter().map(|e| e.key().clone()).collect();
+        labels.sort();
+        // Collection names are quoted as label values
+        let perfs: Vec<(String, String, Arc<CollectionPerf>)> = labels
+            .into_iter()
+            .filter_map(|label| {
+                let perf = self.collections.get(&label)?.clone();
+                Some((escape_label_value(&label), label, perf))
+            })
+            .collect();
+
+        let mut out = String::new();
+        let counters: [(&str, &str, CounterField); 6] = [
+            ("vibedb_reads_total", "Read requests per collection", |p| &p.reads),
+            ("vibedb_writes_total", "Write requests per collection", |p| &p.writes),
+            ("vibedb_rows_written_total", "Rows written per collection", |p| &p.rows),
+            ("vibedb_write_errors_total", "Failed write requests per collection", |p| &p.errors),
+            ("vibedb_migrations_total", "Schema migrations per collection", |p| &p.migrations),
+            (
+                "vibedb_column_limit_rejections_total",
+                "Payloads rejected for exceeding the column limit per collection",
+                |p| &p.column_limit_rejections,
+            ),
+        ];
+        for (name, help, counter) in counters {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} counter", name, help, name);
+            for (value, _, perf) in &perfs {
+                let count = counter(perf).load(Ordering::Relaxed);
+                let _ = writeln!(out, "{}{{collection=\"{}\"}} {}", name, value, count);
+            }
+        }
+
+        // Only collections migrated since startup have a known column count
+        let _ = writeln!(
+            out,
+            "# HELP vibedb_columns Columns per collection after its latest migration\n# TYPE vibedb_columns gauge"
+        );
+        for (value, label, perf) in &perfs {
+            let columns = perf.columns.load(Ordering::Relaxed);
+            if columns > 0 && label != OVERFLOW_LABEL {
+                let _ = writeln!(out, "vibedb_columns{{collection=\"{}\"}} {}", value, columns);
+            }
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP vibedb_busy_retries_total Busy/locked writes retried per collection\n# TYPE vibedb_busy_retries_total counter"
+        );
+        for (value, label, _) in &perfs {
+            let _ = writeln!(
+                out,
+                "vibedb_busy_retries_total{{collection=\"{}\"}} {}",
+                value,
+                store.busy_retries(label)
+            );
+        }
+
+        let health = store.health();
+        let store_counters = [
+            ("vibedb_store_faults_total", "Unrecoverable database errors (I/O, not a database, read-only)", health.faults),
+            ("vibedb_store_reopen_attempts_total", "Attempts to reopen the database connection", health.reopen_attempts),
+            ("vibedb_store_reopen_failures_total", "Failed attempts to reopen the database connection", health.reopen_failures),
+        ];
+        for (name, help, value) in store_counters {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} counter\n{} {}", name, help, name, name, value);
+        }
+
+        let hits = self.schema_cache_hits.load(Ordering::Relaxed);
+        let misses = self.schema_cache_misses.load(Ordering::Relaxed);
+        let cache_counters = [
+            ("vibedb_schema_cache_hits_total", "Schema lookups answered from the guard's cache", hits),
+            ("vibedb_schema_cache_misses_total", "Schema lookups that read the table info from SQLite", misses),
+        ];
+        for (name, help, value) in cache_counters {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} counter\n{} {}", name, help, name, name, value);
+        }
+        // No ratio before the first lookup
+        if hits + misses > 0 {
+            let _ = writeln!(
+                out,
+                "# HELP vibedb_schema_cache_hit_ratio Share of schema lookups answered from the cache\n# TYPE vibedb_schema_cache_hit_ratio gauge\nvibedb_schema_cache_hit_ratio {}",
+                hits as f64 / (hits + misses) as f64
+            );
+        }
+
+        let histograms: [(&str, &str, HistogramFields); 2] = [
+            ("vibedb_write_duration_seconds", "Write latency per collection", |p| {
+                (p.bucket_counts(), &p.latency_us)
+            }),
+            ("vibedb_migration_duration_seconds", "Schema migration DDL time per collection", |p| {
+                (p.migration_bucket_counts(), &p.migration_us)
+            }),
+        ];
+        for (name, help, fields) in histograms {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} histogram", name, help, name);
+            for (value, _, perf) in &perfs {
+                let (counts, sum_us) = fields(perf);
+                let mut cumulative = 0;
+                for (bound, count) in LATENCY_BUCKETS_MS.iter().zip(&counts) {
+                    cumulative += count;
+                    let _ = writeln!(
+                        out,
+                        "{}_bucket{{collection=\"{}\",le=\"{}\"}} {}",
+                        name,
+                        value,
+                        *bound as f64 / 1000.0,
+                        cumulative
+                    );
+                }
+                let total: u64 = counts.iter().sum();
+                let _ = writeln!(out, "{}_bucket{{collection=\"{}\",le=\"+Inf\"}} {}", name, value, total);
+                let _ = writeln!(
+                    out,
+                    "{}_sum{{collection=\"{}\"}} {}",
+                    name,
+                    value,
+                    sum_us.load(Ordering::Relaxed) as f64 / 1_000_000.0
+                );
+                let _ = writeln!(out, "{}_count{{collection=\"{}\"}} {}", name, value, total);
+            }
+        }
+        out
+    }
+}
+
+/// Escapes a Prometheus label value: backslash, double quote and newline
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_overflow_and_percentiles() {
+        let store = VibeStore::in_memory().await.unwrap();
+        let registry = PerfRegistry::new(2);
+        for (collection, ms) in [("a", 3), ("a", 3), ("a", 30), ("b", 1), ("c", 1), ("d", 1)] {
+            registry.record_write(collection, Duration::from_millis(ms), 1, true);
+        }
+        registry.record_write("a", Duration::from_millis(1), 0, false);
+        registry.record_read("a");
+
+        let a = registry.snapshot("a", &store);
+        assert_eq!((a.reads, a.writes, a.rows_written, a.write_errors), (1, 4, 3, 1));
+        assert_eq!(a.latency.p50_ms, Some(5));
+        assert_eq!(a.latency.p99_ms, Some(50));
+        assert!(a.writes_per_sec > 0.0);
+
+        let d = registry.snapshot("d", &store);
+        assert_eq!(d.tracked_as, OVERFLOW_LABEL);
+        assert_eq!(d.writes, 2);
+        assert_eq!(registry.collections.len(), 3);
+
+        let metrics = registry.render_prometheus(&store);
+        assert!(metrics.contains("vibedb_writes_total{collection=\"_other\"} 2"));
+        assert!(metrics.contains("vibedb_reads_total{collection=\"a\"} 1"));
+        assert!(metrics.contains("vibedb_write_duration_seconds_bucket{collection=\"a\",le=\"0.005\"} 2"));
+    }
+
+    #[tokio::test]
+    async fn test_label_values_are_escaped() {
+        let store = VibeStore::in_memory().await.unwrap();
+        let registry = PerfRegistry::new(4);
+        registry.record_read("a\"b\\c\nd");
+
+        let metrics = registry.render_prometheus(&store);
+        assert!(metrics.contains(r#"vibedb_reads_total{collection="a\"b\\c\nd"} 1"#));
+    }
+
+    #[test]
+    fn test_query_columns_are_counted_per_collection() {
+        let registry = PerfRegistry::new(1);
+        registry.record_query_column("a", "status", ColumnUse::Filter);
+        registry.record_query_column("a", "status", ColumnUse::Filter);
+        registry.record_query_column("a", "created_at", ColumnUse::OrderBy);
+        registry.record_query_column("b", "status", ColumnUse::Filter);
+        for i in 0..MAX_TRACKED_QUERY_COLUMNS {
+            registry.record_query_column("a", &format!("c{}", i), ColumnUse::Filter);
+        }
+
+        let uses = registry.query_columns();
+        // The overflow bucket and columns past the cap are left out
+        assert!(uses.iter().all(|u| u.collection == "a"));
+        assert_eq!(uses.len(), MAX_TRACKED_QUERY_COLUMNS);
+        let status = uses.iter().find(|u| u.column == "status").unwrap();
+        assert_eq!((status.filter_uses, status.order_by_uses, status.total()), (2, 0, 2));
+        let created = uses.iter().find(|u| u.column == "created_at").unwrap();
+        assert_eq!(created.order_by_uses, 1);
+    }
+}
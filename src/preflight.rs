@@ -0,0 +1,176 @@
+//! # Preflight
+//!
+//! Startup checks that fail fast on a configuration the server could only
+//! discover on the first write: a database directory that is missing or
+//! read-only, a storage directory that can't be written, or a JWT secret
+//! too short to sign with. `main` runs them before opening the database or
+//! binding the port, and `vibedb --check` runs them on their own.
+//!
+//! Directories are probed by creating and deleting a file, which also
+//! catches read-only mounts that permission bits don't show.
+
+use crate::auth::MIN_SECRET_LENGTH;
+use crate::config::PreflightConfig;
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// A failed startup check
+#[derive(Error, Debug)]
+pub enum PreflightError {
+    #[error("database directory check failed: {dir} does not exist (pass --create-db-dir or set VIBEDB_CREATE_DB_DIR to create it)")]
+    DatabaseDirMissing { dir: PathBuf },
+
+    #[error("database directory check failed: {dir} is not writable: {source}")]
+    DatabaseDirNotWritable { dir: PathBuf, source: io::Error },
+
+    #[error("storage path check failed: {dir} is not writable: {source}")]
+    StorageNotWritable { dir: PathBuf, source: io::Error },
+
+    #[error("JWT secret check failed: VIBEDB_JWT_SECRET has {length} bytes, at least {min} are needed")]
+    JwtSecretTooShort { length: usize, min: usize },
+}
+
+/// Runs every check, returning the first failure
+pub fn run_preflight(config: &PreflightConfig) -> Result<(), PreflightError> {
+    if let Some(db_path) = &config.db_path {
+        check_db_dir(db_path, config.create_db_dir)?;
+    }
+    check_storage(&config.storage_path)?;
+    if let Some(secret) = &config.jwt_secret {
+        if secret.len() < MIN_SECRET_LENGTH {
+            return Err(PreflightError::JwtSecretTooShort {
+                length: secret.len(),
+                min: MIN_SECRET_LENGTH,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// SQLite writes `-wal` and `-shm` files next to the database, so its
+/// directory must be writable, not just the file
+fn check_db_dir(db_path: &Path, create: bool) -> Result<(), PreflightError> {
+    let dir = match db_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    if !dir.is_dir() {
+        if !create {
+            return Err(PreflightError::DatabaseDirMissing { dir });
+        }
+        fs::create_dir_all(&dir).map_err(|source| PreflightError::DatabaseDirNotWritable {
+            dir: dir.clone(),
+            source,
+        })?;
+    }
+    probe_write(&dir).map_err(|source| PreflightError::DatabaseDirNotWritable { dir, source })
+}
+
+/// The storage directory is created like `StorageService` would on the
+/// first upload
+fn check_storage(dir: &Path) -> Result<(), PreflightError> {
+    fs::create_dir_all(dir)
+        .and_then(|_| probe_write(dir))
+        .map_err(|source| PreflightError::StorageNotWritable {
+            dir: dir.to_path_buf(),
+            source,
+        })
+}
+
+/// Creates, writes and deletes a file in `dir`
+fn probe_write(dir: &Path) -> io::Result<()> {
+    let probe = dir.join(format!(".vibedb-preflight-{}", std::process::id()));
+    let written = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .and_then(|mut file| file.write_all(b"ok").and_then(|_| file.sync_all()));
+    let removed = fs::remove_file(&probe);
+    written.and(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(dir: &Path) -> PreflightConfig {
+        PreflightConfig {
+            db_path: Some(dir.join("vibedb.db")),
+            create_db_dir: false,
+            storage_path: dir.join("storage"),
+            jwt_secret: Some(vec![b'k'; MIN_SECRET_LENGTH]),
+        }
+    }
+
+    #[test]
+    fn test_usable_paths_pass() {
+        let dir = tempfile::tempdir().unwrap();
+        run_preflight(&config(dir.path())).unwrap();
+        assert!(dir.path().join("storage").is_dir());
+        assert_eq!(fs::read_dir(dir.path().join("storage")).unwrap().count(), 0);
+
+        let memory = PreflightConfig { db_path: None, ..config(dir.path()) };
+        run_preflight(&memory).unwrap();
+    }
+
+    #[test]
+    fn test_missing_db_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("missing").join("deeper");
+        let mut config = config(dir.path());
+        config.db_path = Some(nested.join("vibedb.db"));
+
+        let err = run_preflight(&config).unwrap_err();
+        assert!(matches!(&err, PreflightError::DatabaseDirMissing { dir } if *dir == nested), "{:?}", err);
+        assert!(err.to_string().starts_with("database directory check failed"), "{}", err);
+        assert!(!nested.exists());
+
+        config.create_db_dir = true;
+        run_preflight(&config).unwrap();
+        assert!(nested.is_dir());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_only_storage() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let storage = dir.path().join("storage");
+        fs::create_dir(&storage).unwrap();
+        fs::set_permissions(&storage, fs::Permissions::from_mode(0o555)).unwrap();
+        // Root ignores permission bits; the probe can't fail then
+        let privileged = fs::write(storage.join("root"), b"").is_ok();
+        let result = run_preflight(&config(dir.path()));
+        fs::set_permissions(&storage, fs::Permissions::from_mode(0o755)).unwrap();
+        if privileged {
+            result.unwrap();
+        } else {
+            let err = result.unwrap_err();
+            assert!(matches!(&err, PreflightError::StorageNotWritable { dir, .. } if *dir == storage), "{:?}", err);
+        }
+
+        // A storage path below a file can't be created by anyone
+        let file = dir.path().join("file");
+        fs::write(&file, b"").unwrap();
+        let mut config = config(dir.path());
+        config.storage_path = file.join("storage");
+        let err = run_preflight(&config).unwrap_err();
+        assert!(matches!(err, PreflightError::StorageNotWritable { .. }), "{:?}", err);
+        assert!(err.to_string().starts_with("storage path check failed"), "{}", err);
+    }
+
+    #[test]
+    fn test_short_jwt_secret() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = config(dir.path());
+        config.jwt_secret = Some(b"hunter2".to_vec());
+
+        let err = run_preflight(&config).unwrap_err();
+        assert!(matches!(err, PreflightError::JwtSecretTooShort { length: 7, min: MIN_SECRET_LENGTH }), "{:?}", err);
+        assert!(err.to_string().contains("VIBEDB_JWT_SECRET has 7 bytes"), "{}", err);
+    }
+}
@@ -0,0 +1,506 @@
+//! # Result Shaping
+//!
+//! `?select=` picks, renames and computes the fields of query results:
+//!
+//! | Item | Output field |
+//! |------|--------------|
+//! | `price` | `price` |
+//! | `name:display_name` | `display_name`, holding `name` |
+//! | `concat(first,' ',last):full_name` | `full_name` |
+//! | `round(price*1.25,2):price_with_tax` | `price_with_tax` |
+//!
+//! Expressions are column references, numbers, `'text'` literals (`''`
+//! for a quote), `+ - * /` with parentheses, and the functions
+//! `concat`, `lower`, `upper`, `round`, `length` and `coalesce`. They are
+//! parsed into an [`Expr`] tree and compiled into SQL from it, with
+//! literals bound as parameters, so no client text reaches SQLite as is.
+//! Computed items need an alias; `concat` reads NULL as an empty string.
+
+use crate::db::SqlValue;
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Deepest nesting of calls, operators and parentheses in one item
+pub const MAX_DEPTH: usize = 16;
+
+/// Functions allowed in computed fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Concat,
+    Lower,
+    Upper,
+    Round,
+    Length,
+    Coalesce,
+}
+
+impl Function {
+    pub const ALL: [Function; 6] = [
+        Function::Concat,
+        Function::Lower,
+        Function::Upper,
+        Function::Round,
+        Function::Length,
+        Function::Coalesce,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Function::Concat => "concat",
+            Function::Lower => "lower",
+            Function::Upper => "upper",
+            Function::Round => "round",
+            Function::Length => "length",
+            Function::Coalesce => "coalesce",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Smallest and largest number of arguments
+    fn arity(self) -> (usize, usize) {
+        match self {
+            Function::Concat => (1, usize::MAX),
+            Function::Lower | Function::Upper | Function::Length => (1, 1),
+            Function::Round => (1, 2),
+            Function::Coalesce => (2, usize::MAX),
+        }
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Arithmetic operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl BinaryOp {
+    fn sql(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+        }
+    }
+}
+
+/// Expression of a selected field
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Column(String),
+    Text(String),
+    Integer(i64),
+    Real(f64),
+    Neg(Box<Expr>),
+    Binary(Box<Expr>, BinaryOp, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+impl Expr {
+    fn visit_columns<'a>(&'a self, out: &mut BTreeSet<&'a str>) {
+        match self {
+            Expr::Column(name) => {
+                out.insert(name);
+            }
+            Expr::Neg(inner) => inner.visit_columns(out),
+            Expr::Binary(left, _, right) => {
+                left.visit_columns(out);
+                right.visit_columns(out);
+            }
+            Expr::Call(_, args) => args.iter().for_each(|arg| arg.visit_columns(out)),
+            Expr::Text(_) | Expr::Integer(_) | Expr::Real(_) => {}
+        }
+    }
+
+    fn rename_columns(&mut self, rename: &impl Fn(&str) -> String) {
+        match self {
+            Expr::Column(name) => *name = rename(name),
+            Expr::Neg(inner) => inner.rename_columns(rename),
+            Expr::Binary(left, _, right) => {
+                left.rename_columns(rename);
+                right.rename_columns(rename);
+            }
+            Expr::Call(_, args) => args.iter_mut().for_each(|arg| arg.rename_columns(rename)),
+            Expr::Text(_) | Expr::Integer(_) | Expr::Real(_) => {}
+        }
+    }
+
+    fn to_sql(&self, column: &impl Fn(&str) -> String, params: &mut Vec<SqlValue>) -> String {
+        match self {
+            Expr::Column(name) => column(name),
+            Expr::Text(text) => {
+                params.push(SqlValue::Text(text.clone()));
+                "?".to_string()
+            }
+            Expr::Integer(n) => {
+                params.push(SqlValue::Integer(*n));
+                "?".to_string()
+            }
+            Expr::Real(n) => {
+                params.push(SqlValue::Real(*n));
+                "?".to_string()
+            }
+            Expr::Neg(inner) => format!("(-{})", inner.to_sql(column, params)),
+            Expr::Binary(left, op, right) => format!(
+                "({} {} {})",
+                left.to_sql(column, params),
+                op.sql(),
+                right.to_sql(column, params)
+            ),
+            Expr::Call(Function::Concat, args) => {
+                let parts: Vec<String> = args
+                    .iter()
+                    .map(|arg| format!("COALESCE({}, '')", arg.to_sql(column, params)))
+                    .collect();
+                format!("({})", parts.join(" || "))
+            }
+            Expr::Call(function, args) => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_sql(column, params)).collect();
+                format!("{}({})", function.name().to_uppercase(), args.join(", "))
+            }
+        }
+    }
+}
+
+/// One field of a selection
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectItem {
+    pub expr: Expr,
+    pub alias: Option<String>,
+}
+
+impl SelectItem {
+    /// Key of the field in output rows
+    pub fn name(&self) -> &str {
+        match (&self.alias, &self.expr) {
+            (Some(alias), _) => alias,
+            (None, Expr::Column(column)) => column,
+            // `Selection::parse` rejects computed items without an alias
+            (None, _) => "",
+        }
+    }
+}
+
+/// A parsed `?select=` list
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub items: Vec<SelectItem>,
+}
+
+impl Selection {
+    /// Parses a comma-separated list of `expr[:alias]` items
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let mut items = Vec::new();
+        loop {
+            let expr = parser.expr(0)?;
+            let alias = if parser.eat(&Token::Colon) {
+                match parser.next() {
+                    Some(Token::Ident(alias)) => Some(alias),
+                    _ => return Err("an alias must be an identifier".to_string()),
+                }
+            } else {
+                None
+            };
+            if alias.is_none() && !matches!(expr, Expr::Column(_)) {
+                return Err("computed fields need an alias, as in round(price,2):rounded".to_string());
+            }
+            items.push(SelectItem { expr, alias });
+            match parser.next() {
+                None => break,
+                Some(Token::Comma) => {}
+                Some(token) => return Err(format!("unexpected {}", token)),
+            }
+        }
+
+        let mut names = BTreeSet::new();
+        for item in &items {
+            if !names.insert(item.name().to_lowercase()) {
+                return Err(format!("'{}' is selected more than once", item.name()));
+            }
+        }
+        Ok(Self { items })
+    }
+
+    /// Columns the selection reads
+    pub fn columns(&self) -> BTreeSet<&str> {
+        let mut columns = BTreeSet::new();
+        for item in &self.items {
+            item.expr.visit_columns(&mut columns);
+        }
+        columns
+    }
+
+    /// Output keys, in order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.items.iter().map(SelectItem::name)
+    }
+
+    /// Replaces every column reference, keeping the output key of items
+    /// without an alias in step
+    pub fn rename_columns(&mut self, rename: impl Fn(&str) -> String) {
+        for item in &mut self.items {
+            item.expr.rename_columns(&rename);
+        }
+    }
+
+    /// SELECT list and its parameters; `column` renders a column
+    /// reference, e.g. as a cast of it
+    pub fn to_sql(&self, column: impl Fn(&str) -> String) -> (String, Vec<SqlValue>) {
+        let mut params = Vec::new();
+        let list: Vec<String> = self
+            .items
+            .iter()
+            .map(|item| format!("{} AS \"{}\"", item.expr.to_sql(&column, &mut params), item.name()))
+            .collect();
+        (list.join(", "), params)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Text(String),
+    Integer(i64),
+    Real(f64),
+    Op(BinaryOp),
+    Open,
+    Close,
+    Comma,
+    Colon,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "'{}'", name),
+            Token::Text(_) => f.write_str("text literal"),
+            Token::Integer(n) => write!(f, "'{}'", n),
+            Token::Real(n) => write!(f, "'{}'", n),
+            Token::Op(op) => write!(f, "'{}'", op.sql()),
+            Token::Open => f.write_str("'('"),
+            Token::Close => f.write_str("')'"),
+            Token::Comma => f.write_str("','"),
+            Token::Colon => f.write_str("':'"),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            ' ' => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            ':' => Token::Colon,
+            '+' => Token::Op(BinaryOp::Add),
+            '-' => Token::Op(BinaryOp::Sub),
+            '*' => Token::Op(BinaryOp::Mul),
+            '/' => Token::Op(BinaryOp::Div),
+            '\'' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\'') if chars.next_if_eq(&'\'').is_some() => text.push('\''),
+                        Some('\'') => break,
+                        Some(c) => text.push(c),
+                        None => return Err("unterminated text literal".to_string()),
+                    }
+                }
+                Token::Text(text)
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut number = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || *c == '.') {
+                    number.push(c);
+                }
+                if let Ok(n) = number.parse::<i64>() {
+                    Token::Integer(n)
+                } else {
+                    Token::Real(number.parse().map_err(|_| format!("invalid number '{}'", number))?)
+                }
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut name = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+                Token::Ident(name)
+            }
+            c => return Err(format!("unexpected '{}'", c)),
+        };
+        tokens.push(token);
+    }
+    if tokens.is_empty() {
+        return Err("select needs at least one field".to_string());
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let found = self.peek() == Some(token);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), String> {
+        match self.next() {
+            Some(found) if found == token => Ok(()),
+            Some(found) => Err(format!("expected {}, found {}", token, found)),
+            None => Err(format!("expected {} at the end", token)),
+        }
+    }
+
+    /// `term (('+' | '-') term)*`
+    fn expr(&mut self, depth: usize) -> Result<Expr, String> {
+        if depth > MAX_DEPTH {
+            return Err(format!("expressions nest at most {} levels deep", MAX_DEPTH));
+        }
+        let mut left = self.term(depth)?;
+        while let Some(Token::Op(op @ (BinaryOp::Add | BinaryOp::Sub))) = self.peek().cloned() {
+            self.pos += 1;
+            left = Expr::Binary(Box::new(left), op, Box::new(self.term(depth)?));
+        }
+        Ok(left)
+    }
+
+    /// `factor (('*' | '/') factor)*`
+    fn term(&mut self, depth: usize) -> Result<Expr, String> {
+        let mut left = self.factor(depth)?;
+        while let Some(Token::Op(op @ (BinaryOp::Mul | BinaryOp::Div))) = self.peek().cloned() {
+            self.pos += 1;
+            left = Expr::Binary(Box::new(left), op, Box::new(self.factor(depth)?));
+        }
+        Ok(left)
+    }
+
+    fn factor(&mut self, depth: usize) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Integer(n)) => Ok(Expr::Integer(n)),
+            Some(Token::Real(n)) => Ok(Expr::Real(n)),
+            Some(Token::Text(text)) => Ok(Expr::Text(text)),
+            Some(Token::Op(BinaryOp::Sub)) => Ok(Expr::Neg(Box::new(self.factor(depth + 1)?))),
+            Some(Token::Open) => {
+                let inner = self.expr(depth + 1)?;
+                self.expect(Token::Close)?;
+                Ok(inner)
+            }
+            Some(Token::Ident(name)) if self.eat(&Token::Open) => {
+                let function = Function::parse(&name).ok_or_else(|| {
+                    let allowed: Vec<&str> = Function::ALL.iter().map(|f| f.name()).collect();
+                    format!("unknown function '{}' (allowed: {})", name, allowed.join(", "))
+                })?;
+                let mut args = Vec::new();
+                if !self.eat(&Token::Close) {
+                    loop {
+                        args.push(self.expr(depth + 1)?);
+                        if self.eat(&Token::Close) {
+                            break;
+                        }
+                        self.expect(Token::Comma)?;
+                    }
+                }
+                let (min, max) = function.arity();
+                if args.len() < min || args.len() > max {
+                    let expected = match (min, max) {
+                        (min, max) if min == max => format!("{}", min),
+                        (min, usize::MAX) => format!("at least {}", min),
+                        (min, max) => format!("{} to {}", min, max),
+                    };
+                    return Err(format!("{} takes {} argument(s), got {}", function, expected, args.len()));
+                }
+                Ok(Expr::Call(function, args))
+            }
+            Some(Token::Ident(name)) => Ok(Expr::Column(name)),
+            Some(token) => Err(format!("unexpected {}", token)),
+            None => Err("unexpected end of select".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sql(input: &str) -> (String, Vec<SqlValue>) {
+        Selection::parse(input).unwrap().to_sql(|c| c.to_string())
+    }
+
+    #[test]
+    fn test_parse_and_compile() {
+        let (list, params) = sql("name:display_name,price");
+        assert_eq!(list, r#"name AS "display_name", price AS "price""#);
+        assert!(params.is_empty());
+
+        let (list, params) = sql("concat(first,' ',last):full_name, round(price*1.25,2):price_with_tax");
+        assert_eq!(
+            list,
+            r#"(COALESCE(first, '') || COALESCE(?, '') || COALESCE(last, '')) AS "full_name", ROUND((price * ?), ?) AS "price_with_tax""#
+        );
+        assert_eq!(params, vec![SqlValue::Text(" ".to_string()), SqlValue::Real(1.25), SqlValue::Integer(2)]);
+
+        let (list, _) = sql("-(a+b)*c:x,coalesce(nick,'it''s'):y");
+        assert_eq!(list, r#"((-(a + b)) * c) AS "x", COALESCE(nick, ?) AS "y""#);
+
+        let selection = Selection::parse("upper(a):b,a,lower(c):d").unwrap();
+        assert_eq!(selection.columns().into_iter().collect::<Vec<_>>(), ["a", "c"]);
+        assert_eq!(selection.names().collect::<Vec<_>>(), ["b", "a", "d"]);
+    }
+
+    #[test]
+    fn test_rejections() {
+        for (input, error) in [
+            ("", "at least one field"),
+            ("exec(x):y", "unknown function 'exec'"),
+            ("lower(a)", "need an alias"),
+            ("a:1b", "alias must be an identifier"),
+            ("a,a", "more than once"),
+            ("a:b,c:B", "more than once"),
+            ("round(a,1,2):r", "round takes 1 to 2"),
+            ("coalesce(a):c", "at least 2"),
+            ("a;drop", "unexpected ';'"),
+            ("'open:x", "unterminated"),
+            ("(a:x", "expected ')'"),
+            ("a b", "unexpected 'b'"),
+            (&format!("{}a{}:x", "(".repeat(20), ")".repeat(20)), "at most 16"),
+        ] {
+            let message = Selection::parse(input).unwrap_err();
+            assert!(message.contains(error), "{}: {}", input, message);
+        }
+    }
+}
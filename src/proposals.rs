@@ -0,0 +1,290 @@
+//! # SQL Proposals
+//!
+//! Approval workflow for the raw SQL execute endpoint. With
+//! `sql.approval_required` enabled, only admins may call `/v1/sql/execute`
+//! directly; everyone else proposes a statement, which an admin approves or
+//! rejects.
+//!
+//! ## Lifecycle
+//! `pending` → `rejected`, or `pending` → `approved` → `executed` | `failed`.
+//! Approval claims the proposal with a conditional UPDATE before running it,
+//! so a statement is executed at most once even under concurrent approvals.
+//! A proposal left `approved` (the server stopped mid-execution) is never
+//! retried.
+//!
+//! ## System Tables
+//! - `vibe_sql_proposals` - One row per proposed statement
+
+use crate::audit::now_timestamp;
+use crate::auth::AuthUser;
+use crate::db::{SqlValue, VibeStore};
+use crate::error::{VibeError, VibeResult};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_APPROVED: &str = "approved";
+pub const STATUS_REJECTED: &str = "rejected";
+pub const STATUS_EXECUTED: &str = "executed";
+pub const STATUS_FAILED: &str = "failed";
+
+/// A proposed SQL statement and its outcome
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SqlProposal {
+    pub id: i64,
+    pub statement: String,
+    pub proposer_id: i64,
+    pub proposer_email: String,
+    pub status: String,
+    pub created_at: String,
+    pub decided_by: Option<String>,
+    pub decided_at: Option<String>,
+    pub reason: Option<String>,
+    /// Execution result (`{"affected": n}`) once executed
+    pub result: Option<Value>,
+    /// Execution error when the statement failed
+    pub error: Option<String>,
+}
+
+impl SqlProposal {
+    /// Admins see every proposal; users only their own
+    pub fn visible_to(&self, user: &AuthUser) -> bool {
+        user.is_admin() || user.id == self.proposer_id
+    }
+}
+
+/// Persistent store of SQL proposals
+pub struct ProposalStore {
+    store: Arc<VibeStore>,
+    initialized: OnceCell<()>,
+}
+
+impl ProposalStore {
+    pub fn new(store: Arc<VibeStore>) -> Self {
+        Self {
+            store,
+            initialized: OnceCell::new(),
+        }
+    }
+
+    async fn ensure_table(&self) -> VibeResult<()> {
+        self.initialized
+            .get_or_try_init(|| async {
+                self.store
+                    .execute_batch(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS vibe_sql_proposals (
+                            id INTEGER PRIMARY KEY AUTOINCREMENT,
+                            statement TEXT NOT NULL,
+                            proposer_id INTEGER NOT NULL,
+                            proposer_email TEXT NOT NULL,
+                            status TEXT NOT NULL DEFAULT 'pending',
+                            created_at TEXT NOT NULL,
+                            decided_by TEXT,
+                            decided_at TEXT,
+                            reason TEXT,
+                            result TEXT,
+                            error TEXT
+                        );
+                        CREATE INDEX IF NOT EXISTS idx_vibe_sql_proposals_status
+                            ON vibe_sql_proposals(status);
+                        "#
+                        .to_string(),
+                    )
+                    .await
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Records a new pending proposal
+    pub async fn create(&self, statement: &str, proposer: &AuthUser) -> VibeResult<SqlProposal> {
+        self.ensure_table().await?;
+        self.store
+            .execute(
+                "INSERT INTO vibe_sql_proposals (statement, proposer_id, proposer_email, created_at) \
+                 VALUES (?, ?, ?, ?)"
+                    .to_string(),
+                vec![
+                    SqlValue::Text(statement.to_string()),
+                    SqlValue::Integer(proposer.id),
+                    SqlValue::Text(proposer.email.clone()),
+                    SqlValue::Text(now_timestamp()),
+                ],
+            )
+            .await?;
+
+        let id = self.store.last_insert_rowid().await?;
+        self.get(id).await
+    }
+
+    /// Loads a proposal by id
+    pub async fn get(&self, id: i64) -> VibeResult<SqlProposal> {
+        self.ensure_table().await?;
+        let rows = self
+            .store
+            .query(
+                "SELECT * FROM vibe_sql_proposals WHERE id = ?".to_string(),
+                vec![SqlValue::Integer(id)],
+            )
+            .await?;
+
+        match rows.into_iter().next() {
+            Some(row) => Ok(serde_json::from_value(Value::Object(row.into_iter().collect()))?),
+            None => Err(VibeError::NotFound(format!("SQL proposal {} not found", id))),
+        }
+    }
+
+    /// Lists proposals, newest first, optionally by status and proposer
+    pub async fn list(
+        &self,
+        status: Option<&str>,
+        proposer_id: Option<i64>,
+    ) -> VibeResult<Vec<SqlProposal>> {
+        self.ensure_table().await?;
+
+        let mut sql = "SELECT * FROM vibe_sql_proposals WHERE 1 = 1".to_string();
+        let mut params = Vec::new();
+        if let Some(status) = status {
+            sql.push_str(" AND status = ?");
+            params.push(SqlValue::Text(status.to_string()));
+        }
+        if let Some(id) = proposer_id {
+            sql.push_str(" AND proposer_id = ?");
+            params.push(SqlValue::Integer(id));
+        }
+        sql.push_str(" ORDER BY id DESC");
+
+        self.store
+            .query(sql, params)
+            .await?
+            .into_iter()
+            .map(|row| Ok(serde_json::from_value(Value::Object(row.into_iter().collect()))?))
+            .collect()
+    }
+
+    /// Moves a pending proposal to `status`, failing if it was already decided
+    async fn decide(
+        &self,
+        id: i64,
+        status: &str,
+        admin: &AuthUser,
+        reason: Option<String>,
+    ) -> VibeResult<SqlProposal> {
+        let proposal = self.get(id).await?;
+        let claimed = self
+            .store
+            .execute(
+                "UPDATE vibe_sql_proposals SET status = ?, decided_by = ?, decided_at = ?, reason = ? \
+                 WHERE id = ? AND status = 'pending'"
+                    .to_string(),
+                vec![
+                    SqlValue::Text(status.to_string()),
+                    SqlValue::Text(admin.email.clone()),
+                    SqlValue::Text(now_timestamp()),
+                    reason.map(SqlValue::Text).unwrap_or(SqlValue::Null),
+                    SqlValue::Integer(id),
+                ],
+            )
+            .await?;
+
+        if claimed == 0 {
+            let current = self.get(id).await.map(|p| p.status).unwrap_or(proposal.status);
+            return Err(VibeError::Conflict(format!(
+                "SQL proposal {} is already {}",
+                id, current
+            )));
+        }
+        self.get(id).await
+    }
+
+    /// Claims a pending proposal for execution
+    pub async fn approve(&self, id: i64, admin: &AuthUser) -> VibeResult<SqlProposal> {
+        self.decide(id, STATUS_APPROVED, admin, None).await
+    }
+
+    /// Rejects a pending proposal
+    pub async fn reject(
+        &self,
+        id: i64,
+        admin: &AuthUser,
+        reason: Option<String>,
+    ) -> VibeResult<SqlProposal> {
+        self.decide(id, STATUS_REJECTED, admin, reason).await
+    }
+
+    /// Stores the outcome of an approved proposal
+    pub async fn record_outcome(
+        &self,
+        id: i64,
+        outcome: &VibeResult<u64>,
+    ) -> VibeResult<SqlProposal> {
+        let (status, result, error) = match outcome {
+            Ok(affected) => (
+                STATUS_EXECUTED,
+                SqlValue::Text(serde_json::json!({ "affected": affected }).to_string()),
+                SqlValue::Null,
+            ),
+            Err(e) => (STATUS_FAILED, SqlValue::Null, SqlValue::Text(e.to_string())),
+        };
+
+        self.store
+            .execute(
+                "UPDATE vibe_sql_proposals SET status = ?, result = ?, error = ? \
+                 WHERE id = ? AND status = 'approved'"
+                    .to_string(),
+                vec![
+                    SqlValue::Text(status.to_string()),
+                    result,
+                    error,
+                    SqlValue::Integer(id),
+                ],
+            )
+            .await?;
+        self.get(id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{ADMIN_ROLE, USER_ROLE};
+
+    fn user(id: i64, role: &str) -> AuthUser {
+        AuthUser {
+            id,
+            email: format!("{}@vibedb.dev", role),
+            role: role.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_proposal_is_decided_once() {
+        let store = Arc::new(VibeStore::in_memory().await.unwrap());
+        let proposals = ProposalStore::new(store);
+        let (alice, admin) = (user(1, USER_ROLE), user(2, ADMIN_ROLE));
+
+        let proposal = proposals.create("DELETE FROM t", &alice).await.unwrap();
+        assert_eq!(proposal.status, STATUS_PENDING);
+        assert!(proposal.visible_to(&alice));
+        assert!(!proposal.visible_to(&user(3, USER_ROLE)));
+
+        proposals.approve(proposal.id, &admin).await.unwrap();
+        let executed = proposals.record_outcome(proposal.id, &Ok(4)).await.unwrap();
+        assert_eq!(executed.status, STATUS_EXECUTED);
+        assert_eq!(executed.result, Some(serde_json::json!({"affected": 4})));
+
+        assert!(matches!(
+            proposals.approve(proposal.id, &admin).await,
+            Err(VibeError::Conflict(_))
+        ));
+        assert!(matches!(
+            proposals.reject(proposal.id, &admin, None).await,
+            Err(VibeError::Conflict(_))
+        ));
+        assert_eq!(proposals.list(Some(STATUS_PENDING), None).await.unwrap().len(), 0);
+    }
+}
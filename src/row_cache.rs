@@ -0,0 +1,232 @@
+//! # Row Cache
+//!
+//! Optional read-through LRU cache of `GET /v1/query/:collection/:id`
+//! lookups, keyed by collection and id. Off unless `VIBEDB_ROW_CACHE_SIZE`
+//! is set.
+//!
+//! Rows are cached as stored, before column masking, so one entry serves
+//! every caller. An entry is dropped when its row is updated or deleted;
+//! bulk writes (filtered deletes, row moves, erasures) drop the whole
+//! collection and raw SQL writes drop everything. An entry cached under a
+//! different column count than the table now has is a miss, so schema
+//! evolution never serves rows without their new columns.
+//!
+//! A lookup takes a [`Ticket`] before reading the database; rows read
+//! under a ticket that predates an invalidation are not cached, so a
+//! concurrent write can't leave a stale row behind.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+type Key = (String, i64);
+
+/// Invalidation generation a row was read under
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ticket(u64);
+
+/// Counters of a row cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RowCacheStats {
+    pub capacity: usize,
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+struct Entry {
+    /// Position in `Lru::order`
+    used: u64,
+    columns: usize,
+    row: Value,
+}
+
+#[derive(Default)]
+struct Lru {
+    entries: HashMap<Key, Entry>,
+    /// Keys by last use, oldest first
+    order: BTreeMap<u64, Key>,
+    clock: u64,
+    generation: u64,
+}
+
+impl Lru {
+    fn touch(&mut self, key: &Key) -> Option<&Entry> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(key)?;
+        self.order.remove(&entry.used);
+        entry.used = self.clock;
+        self.order.insert(self.clock, key.clone());
+        Some(entry)
+    }
+
+    fn remove(&mut self, key: &Key) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.order.remove(&entry.used);
+        }
+    }
+}
+
+/// LRU cache of rows by collection and id; every operation is a no-op at
+/// capacity 0
+pub struct RowCache {
+    capacity: usize,
+    lru: Mutex<Lru>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl RowCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lru: Mutex::new(Lru::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Lru> {
+        self.lru.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Cached row of `collection` with `columns` columns
+    pub fn get(&self, collection: &str, id: i64, columns: usize) -> Option<Value> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let key = (collection.to_string(), id);
+        let mut lru = self.lock();
+        let row = match lru.touch(&key) {
+            Some(entry) if entry.columns == columns => Some(entry.row.clone()),
+            Some(_) => {
+                lru.remove(&key);
+                None
+            }
+            None => None,
+        };
+        let counter = if row.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        row
+    }
+
+    /// Ticket to take before reading a row from the database
+    pub fn ticket(&self) -> Ticket {
+        Ticket(self.lock().generation)
+    }
+
+    /// Caches a row read under `ticket`, unless something was invalidated
+    /// since
+    pub fn insert(&self, ticket: Ticket, collection: &str, id: i64, columns: usize, row: Value) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut lru = self.lock();
+        if lru.generation != ticket.0 {
+            return;
+        }
+        let key = (collection.to_string(), id);
+        lru.remove(&key);
+        while lru.entries.len() >= self.capacity {
+            let Some((_, oldest)) = lru.order.pop_first() else {
+                break;
+            };
+            lru.entries.remove(&oldest);
+        }
+        lru.clock += 1;
+        let used = lru.clock;
+        lru.order.insert(used, key.clone());
+        lru.entries.insert(key, Entry { used, columns, row });
+    }
+
+    /// Drops one row
+    pub fn invalidate(&self, collection: &str, id: i64) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut lru = self.lock();
+        lru.generation += 1;
+        lru.remove(&(collection.to_string(), id));
+    }
+
+    /// Drops every row of a collection
+    pub fn invalidate_collection(&self, collection: &str) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut lru = self.lock();
+        lru.generation += 1;
+        let keys: Vec<Key> = lru
+            .entries
+            .keys()
+            .filter(|(c, _)| c == collection)
+            .cloned()
+            .collect();
+        for key in &keys {
+            lru.remove(key);
+        }
+    }
+
+    /// Drops every row
+    pub fn clear(&self) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut lru = self.lock();
+        lru.generation += 1;
+        lru.entries.clear();
+        lru.order.clear();
+    }
+
+    pub fn stats(&self) -> RowCacheStats {
+        RowCacheStats {
+            capacity: self.capacity,
+            entries: self.lock().entries.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_least_recently_used_row_is_evicted() {
+        let cache = RowCache::new(2);
+        for id in 1..=2 {
+            cache.insert(cache.ticket(), "orders", id, 3, json!({"id": id}));
+        }
+        assert!(cache.get("orders", 1, 3).is_some());
+        cache.insert(cache.ticket(), "orders", 3, 3, json!({"id": 3}));
+
+        assert!(cache.get("orders", 2, 3).is_none());
+        assert!(cache.get("orders", 1, 3).is_some());
+        assert!(cache.get("orders", 3, 3).is_some());
+        // A changed column count is a miss
+        assert!(cache.get("orders", 3, 4).is_none());
+        assert_eq!(cache.stats().entries, 1);
+    }
+
+    #[test]
+    fn test_rows_read_before_an_invalidation_are_not_cached() {
+        let cache = RowCache::new(8);
+        let ticket = cache.ticket();
+        cache.invalidate("orders", 1);
+        cache.insert(ticket, "orders", 1, 3, json!({"id": 1, "status": "stale"}));
+        assert!(cache.get("orders", 1, 3).is_none());
+
+        let disabled = RowCache::new(0);
+        disabled.insert(disabled.ticket(), "orders", 1, 3, json!({"id": 1}));
+        assert!(disabled.get("orders", 1, 3).is_none());
+        assert_eq!(disabled.stats().misses, 0);
+    }
+}
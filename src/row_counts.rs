@@ -0,0 +1,152 @@
+//! # Row Counts
+//!
+//! Optional cache of per-collection row counts, so `GET /v1/tables` and
+//! `GET /v1/tables/:collection` don't scan hot collections with
+//! `COUNT(*)` on every Explorer refresh. Off unless
+//! `VIBEDB_ROW_COUNT_CACHE` is set.
+//!
+//! A collection's count is seeded with one `COUNT(*)` the first time it is
+//! asked for, then moved by the write handlers: pushes, batches, exploded
+//! child rows and pull sources add, deletes by id and by filter subtract,
+//! row moves do both. Writes that bypass them — raw SQL, approved
+//! proposals, other processes — make the count drift, and so do writes
+//! racing the seeding `COUNT(*)`. The sweeper re-counts every cached
+//! collection each `RECONCILE_INTERVAL`, which bounds how long drift lasts;
+//! erasures drop the count, to be re-seeded on the next read.
+//!
+//! Counts served from the cache are marked `approximate`; `?exact=true`
+//! counts instead.
+
+use crate::api::AppState;
+use crate::db::VibeStore;
+use crate::error::VibeResult;
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// How often the sweeper re-counts cached collections
+pub const RECONCILE_INTERVAL: Duration = Duration::from_secs(300);
+
+/// A count corrected by reconciliation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Drift {
+    pub collection: String,
+    /// Count the cache held
+    pub cached: u64,
+    /// Count of the table
+    pub counted: u64,
+}
+
+/// Row counts by collection
+pub struct RowCounts {
+    enabled: bool,
+    counts: Mutex<HashMap<String, i64>>,
+}
+
+impl RowCounts {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            counts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, i64>> {
+        self.counts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn cached(&self, collection: &str) -> Option<u64> {
+        self.lock().get(collection).map(|&count| count.max(0) as u64)
+    }
+
+    /// Rows of a collection and whether the count came from the cache
+    ///
+    /// Counts the table when the cache is off, `exact` is asked for or the
+    /// collection has no count yet; the last seeds the cache.
+    pub async fn get(&self, store: &VibeStore, collection: &str, exact: bool) -> VibeResult<(u64, bool)> {
+        if !self.enabled {
+            return Ok((store.count_rows(collection).await?, false));
+        }
+        if !exact {
+            if let Some(count) = self.cached(collection) {
+                return Ok((count, true));
+            }
+        }
+        let count = store.count_rows(collection).await?;
+        self.lock().insert(collection.to_string(), count as i64);
+        Ok((count, false))
+    }
+
+    /// Moves a cached count by `delta` rows; collections without a count
+    /// are left to be seeded
+    pub fn adjust(&self, collection: &str, delta: i64) {
+        if !self.enabled || delta == 0 {
+            return;
+        }
+        if let Some(count) = self.lock().get_mut(collection) {
+            *count += delta;
+        }
+    }
+
+    /// Drops a collection's count, to be counted again when next asked for
+    pub fn forget(&self, collection: &str) {
+        self.lock().remove(collection);
+    }
+
+    /// Re-counts every cached collection, returning the counts that had
+    /// drifted
+    ///
+    /// Collections that no longer exist are dropped.
+    pub async fn reconcile(&self, store: &VibeStore) -> VibeResult<Vec<Drift>> {
+        let collections: Vec<String> = self.lock().keys().cloned().collect();
+        if collections.is_empty() {
+            return Ok(Vec::new());
+        }
+        let tables: HashSet<String> = store.list_tables().await?.into_iter().collect();
+        let mut drifted = Vec::new();
+        for collection in collections {
+            if !tables.contains(&collection) {
+                self.forget(&collection);
+                continue;
+            }
+            let counted = store.count_rows(&collection).await?;
+            let mut counts = self.lock();
+            let Some(cached) = counts.get_mut(&collection) else {
+                continue;
+            };
+            if *cached != counted as i64 {
+                drifted.push(Drift {
+                    collection: collection.clone(),
+                    cached: (*cached).max(0) as u64,
+                    counted,
+                });
+                *cached = counted as i64;
+            }
+        }
+        Ok(drifted)
+    }
+}
+
+/// Re-counts cached collections every `tick`
+pub fn spawn_sweeper(state: AppState, tick: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tick);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            match state.row_counts.reconcile(&state.store).await {
+                Ok(drifted) if drifted.is_empty() => debug!("🔢 Row counts reconciled"),
+                Ok(drifted) => info!("🔢 Corrected {} drifted row count(s)", drifted.len()),
+                Err(e) => warn!("⚠️ Could not reconcile row counts: {}", e),
+            }
+        }
+    })
+}
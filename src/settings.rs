@@ -0,0 +1,433 @@
+//! # Collection Settings
+//!
+//! Per-collection behaviour that the schema itself cannot express, stored as
+//! a JSON document per collection in `vibe_collection_settings` and cached in
+//! memory. Collections without a row use `CollectionSettings::default()`.
+//!
+//! ## Column Visibility
+//! ```json
+//! {"hidden_columns": ["cost_price"], "visible_to": "admin", "placeholder": "***"}
+//! ```
+//! Hidden columns are not selected for other callers, and any attempt to
+//! write or filter by them is rejected. With a `placeholder` the key is kept
+//! in responses with that value; without one it is omitted entirely.
+//!
+//! ## Auditing
+//! `{"audit": true}` records every write through the data endpoints in
+//! `vibe_audit` (see `audit`). `audit_since` is maintained by the server and
+//! marks how far back history reaches.
+//!
+//! ## Array Explosion
+//! `{"explode_arrays": {"items": "order_items"}}` stores pushed `items`
+//! arrays as rows of `order_items` (see `explode`).
+//!
+//! ## Schema Freeze
+//! `{"freeze": true}` stops schema evolution: pushed keys without a column
+//! are dropped (with a warning in the log) and the rest of the row is
+//! stored as usual.
+//!
+//! ## Dead Letters
+//! `{"dead_letter": true}` keeps rejected pushes in `vibe_dead_letters`
+//! for inspection and retry (see `dead_letters`).
+//!
+//! ## Stream Events
+//! By default insert and update events on `/v1/stream/:collection` carry
+//! the payload as sent. `{"broadcast_rows": true}` sends the row as stored
+//! instead, with its id, timestamps and every column, at the cost of one
+//! read per write.
+//!
+//! With `{"coalesce_inserts_ms": 50}`, insert events are held for up to
+//! that many milliseconds and sent as one
+//! `{"event": "inserts", "count": N, "ids": [...]}` summary without row
+//! data, so bursts of pushes don't overrun slow subscribers. Other events
+//! are still delivered immediately.
+//!
+//! ## Write Batching
+//! `{"batch_writes": true}` commits concurrent single-document pushes
+//! together (see `batching`).
+//!
+//! ## Inline Files
+//! `{"inline_files": {"fields": ["avatar"]}}` stores data URLs pushed in
+//! `avatar` as files and keeps a reference in the row (see `inline_files`).
+//!
+//! ## Row Locks
+//! `{"enforce_locks": true}` rejects updates of a row locked by someone
+//! else with `423 Locked` (see `locks`); without it locks are advisory.
+
+use crate::auth::{AuthUser, ADMIN_ROLE};
+use crate::db::{SqlValue, VibeStore};
+use crate::error::{VibeError, VibeResult};
+use crate::guard::SchemaGuard;
+use crate::inline_files::InlineFiles;
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+/// Longest window insert events may be held for
+pub const MAX_COALESCE_INSERTS_MS: u64 = 10_000;
+
+/// Settings document for a single collection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CollectionSettings {
+    /// Columns only visible to `visible_to` (and admins)
+    pub hidden_columns: Vec<String>,
+    /// Role allowed to see hidden columns
+    pub visible_to: String,
+    /// Value shown in place of hidden columns; omitted when `None`
+    pub placeholder: Option<Value>,
+    /// Record row history for time-travel reads
+    pub audit: bool,
+    /// When auditing was last switched on (server-managed)
+    pub audit_since: Option<String>,
+    /// Array fields stored in child collections (field -> collection)
+    pub explode_arrays: BTreeMap<String, String>,
+    /// Ignore unknown keys instead of adding columns
+    pub freeze: bool,
+    /// Keep rejected pushes as dead letters
+    pub dead_letter: bool,
+    /// Stream the stored row instead of the request payload
+    pub broadcast_rows: bool,
+    /// Coalesce concurrent pushes into shared transactions
+    pub batch_writes: bool,
+    /// Summarize insert events over this window instead of sending each
+    pub coalesce_inserts_ms: Option<u64>,
+    /// Fields whose data URLs are stored as files
+    pub inline_files: Option<InlineFiles>,
+    /// Reject updates of rows locked by someone else
+    pub enforce_locks: bool,
+}
+
+impl Default for CollectionSettings {
+    fn default() -> Self {
+        Self {
+            hidden_columns: Vec::new(),
+            visible_to: ADMIN_ROLE.to_string(),
+            placeholder: None,
+            audit: false,
+            audit_since: None,
+            explode_arrays: BTreeMap::new(),
+            freeze: false,
+            dead_letter: false,
+            broadcast_rows: false,
+            batch_writes: false,
+            coalesce_inserts_ms: None,
+            inline_files: None,
+            enforce_locks: false,
+        }
+    }
+}
+
+impl CollectionSettings {
+    /// Checks the document before it is stored
+    pub fn validate(&self) -> VibeResult<()> {
+        for column in &self.hidden_columns {
+            SchemaGuard::validate_identifier(column)?;
+            if column == "id" {
+                return Err(VibeError::InvalidPayload(
+                    "The id column cannot be hidden".to_string(),
+                ));
+            }
+        }
+        if self.visible_to.trim().is_empty() {
+            return Err(VibeError::InvalidPayload(
+                "visible_to must name a role".to_string(),
+            ));
+        }
+        if let Some(ms) = self.coalesce_inserts_ms {
+            if ms == 0 || ms > MAX_COALESCE_INSERTS_MS {
+                return Err(VibeError::InvalidPayload(format!(
+                    "coalesce_inserts_ms must be between 1 and {}",
+                    MAX_COALESCE_INSERTS_MS
+                )));
+            }
+        }
+        if let Some(inline_files) = &self.inline_files {
+            inline_files.validate()?;
+        }
+        crate::explode::validate_mapping(&self.explode_arrays)
+    }
+
+    /// Window insert events are summarized over, if they are
+    pub fn coalesce_inserts(&self) -> Option<Duration> {
+        self.coalesce_inserts_ms.map(Duration::from_millis)
+    }
+
+    /// Column mask for the given caller (anonymous callers see the least)
+    pub fn mask_for(&self, caller: Option<&AuthUser>) -> ColumnMask {
+        let privileged = caller
+            .map(|user| user.is_admin() || user.role == self.visible_to)
+            .unwrap_or(false);
+
+        if privileged {
+            ColumnMask::default()
+        } else {
+            ColumnMask {
+                hidden: self.hidden_columns.clone(),
+                placeholder: self.placeholder.clone(),
+            }
+        }
+    }
+}
+
+/// Columns a particular caller may not read or write
+#[derive(Debug, Clone, Default)]
+pub struct ColumnMask {
+    hidden: Vec<String>,
+    placeholder: Option<Value>,
+}
+
+impl ColumnMask {
+    pub fn is_empty(&self) -> bool {
+        self.hidden.is_empty()
+    }
+
+    pub fn is_hidden(&self, column: &str) -> bool {
+        self.hidden.iter().any(|h| h.eq_ignore_ascii_case(column))
+    }
+
+    /// Rejects any reference to a hidden column
+    ///
+    /// Filtering or sorting by a hidden column would leak its values through
+    /// the shape of the result, so it is forbidden rather than ignored.
+    pub fn check<'a>(&self, columns: impl IntoIterator<Item = &'a str>) -> VibeResult<()> {
+        for column in columns {
+            if self.is_hidden(column) {
+                return Err(VibeError::Forbidden(format!(
+                    "Column '{}' is not accessible",
+                    column
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// SELECT list for a table with the given columns
+    pub fn projection(&self, columns: &[String]) -> String {
+        if self.is_empty() {
+            return "*".to_string();
+        }
+        columns
+            .iter()
+            .filter(|c| !self.is_hidden(c))
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Removes hidden keys from an object, substituting the placeholder
+    pub fn apply(&self, object: &mut Map<String, Value>) {
+        object.retain(|key, _| !self.is_hidden(key));
+        // Projected-away columns get the placeholder too
+        if let Some(placeholder) = &self.placeholder {
+            for column in &self.hidden {
+                object.insert(column.clone(), placeholder.clone());
+            }
+        }
+    }
+
+    /// Applies the mask to a JSON value if it is an object
+    pub fn apply_value(&self, value: &mut Value) {
+        if let Value::Object(object) = value {
+            self.apply(object);
+        }
+    }
+
+    /// Masks a write event's `data` and drops hidden columns from its
+    /// `changed` list
+    pub fn apply_event(&self, event: &mut Value) {
+        if let Some(data) = event.get_mut("data") {
+            self.apply_value(data);
+        }
+        if let Some(Value::Array(changed)) = event.get_mut("changed") {
+            changed.retain(|column| !column.as_str().is_some_and(|c| self.is_hidden(c)));
+        }
+    }
+}
+
+/// Persistent, cached store of collection settings
+pub struct SettingsStore {
+    store: Arc<VibeStore>,
+    cache: DashMap<String, Arc<CollectionSettings>>,
+    initialized: OnceCell<()>,
+}
+
+impl SettingsStore {
+    pub fn new(store: Arc<VibeStore>) -> Self {
+        Self {
+            store,
+            cache: DashMap::new(),
+            initialized: OnceCell::new(),
+        }
+    }
+
+    async fn ensure_table(&self) -> VibeResult<()> {
+        self.initialized
+            .get_or_try_init(|| async {
+                self.store
+                    .execute_batch(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS vibe_collection_settings (
+                            collection TEXT PRIMARY KEY,
+                            settings TEXT NOT NULL,
+                            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                        );
+                        "#
+                        .to_string(),
+                    )
+                    .await
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Settings for a collection, defaulted when none are stored
+    pub async fn get(&self, collection: &str) -> VibeResult<Arc<CollectionSettings>> {
+        if let Some(settings) = self.cache.get(collection) {
+            return Ok(Arc::clone(&settings));
+        }
+
+        self.ensure_table().await?;
+        let rows = self
+            .store
+            .query(
+                "SELECT settings FROM vibe_collection_settings WHERE collection = ?".to_string(),
+                vec![SqlValue::Text(collection.to_string())],
+            )
+            .await?;
+
+        // JSON text comes back already decoded by the store
+        let settings = match rows.into_iter().next().and_then(|row| row.into_iter().next()) {
+            Some((_, value)) => serde_json::from_value(value)?,
+            None => CollectionSettings::default(),
+        };
+
+        let settings = Arc::new(settings);
+        self.cache
+            .insert(collection.to_string(), Arc::clone(&settings));
+        Ok(settings)
+    }
+
+    /// Collections whose hidden columns the caller can't see
+    pub async fn masked_collections(&self, caller: Option<&AuthUser>) -> VibeResult<Vec<String>> {
+        self.ensure_table().await?;
+        let rows = self
+            .store
+            .query(
+                "SELECT collection, settings FROM vibe_collection_settings ORDER BY collection"
+                    .to_string(),
+                vec![],
+            )
+            .await?;
+
+        let mut masked = Vec::new();
+        for row in rows {
+            let mut values = row.into_iter().map(|(_, value)| value);
+            let (Some(Value::String(collection)), Some(settings)) = (values.next(), values.next())
+            else {
+                continue;
+            };
+            let settings: CollectionSettings = serde_json::from_value(settings)?;
+            if !settings.mask_for(caller).is_empty() {
+                masked.push(collection);
+            }
+        }
+        Ok(masked)
+    }
+
+    /// Replaces the settings of a collection
+    ///
+    /// `audit_since` is carried over while auditing stays on and reset when
+    /// it is switched on; client-supplied values are ignored.
+    pub async fn put(
+        &self,
+        collection: &str,
+        mut settings: CollectionSettings,
+    ) -> VibeResult<Arc<CollectionSettings>> {
+        settings.validate()?;
+        let previous = self.get(collection).await?;
+        settings.audit_since = match (previous.audit, settings.audit) {
+            (true, true) => previous.audit_since.clone(),
+            (false, true) => Some(crate::audit::now_timestamp()),
+            (_, false) => None,
+        };
+
+        self.store
+            .execute(
+                "INSERT INTO vibe_collection_settings (collection, settings) VALUES (?, ?) \
+                 ON CONFLICT(collection) DO UPDATE SET settings = excluded.settings, \
+                 updated_at = CURRENT_TIMESTAMP"
+                    .to_string(),
+                vec![
+                    SqlValue::Text(collection.to_string()),
+                    SqlValue::Text(serde_json::to_string(&settings)?),
+                ],
+            )
+            .await?;
+
+        let settings = Arc::new(settings);
+        self.cache
+            .insert(collection.to_string(), Arc::clone(&settings));
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn user(role: &str) -> AuthUser {
+        AuthUser {
+            id: 1,
+            email: "u@vibedb.dev".to_string(),
+            role: role.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_mask_for_roles() {
+        let settings = CollectionSettings {
+            hidden_columns: vec!["cost_price".to_string()],
+            placeholder: Some(json!("***")),
+            ..Default::default()
+        };
+
+        assert!(settings.mask_for(Some(&user("admin"))).is_empty());
+        assert!(settings.mask_for(Some(&user("user"))).is_hidden("COST_PRICE"));
+        assert!(settings.mask_for(None).check(["name", "cost_price"]).is_err());
+
+        let mut row = json!({"name": "widget", "cost_price": 3});
+        settings.mask_for(None).apply_value(&mut row);
+        assert_eq!(row, json!({"name": "widget", "cost_price": "***"}));
+    }
+
+    #[tokio::test]
+    async fn test_settings_roundtrip() {
+        let store = Arc::new(VibeStore::in_memory().await.unwrap());
+        let settings = SettingsStore::new(Arc::clone(&store));
+
+        assert_eq!(*settings.get("products").await.unwrap(), CollectionSettings::default());
+
+        let updated = CollectionSettings {
+            hidden_columns: vec!["internal_notes".to_string()],
+            ..Default::default()
+        };
+        settings.put("products", updated.clone()).await.unwrap();
+
+        // A fresh store reads the persisted document
+        let reloaded = SettingsStore::new(store);
+        assert_eq!(*reloaded.get("products").await.unwrap(), updated);
+
+        let invalid = CollectionSettings {
+            hidden_columns: vec!["id".to_string()],
+            ..Default::default()
+        };
+        assert!(reloaded.put("products", invalid).await.is_err());
+    }
+}
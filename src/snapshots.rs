@@ -0,0 +1,213 @@
+//! # Snapshot Sessions
+//!
+//! Lets a client page through a collection, or export it in several
+//! requests, while writes continue, without rows shifting between pages.
+//! `POST /v1/snapshots` begins a read transaction on a dedicated read-only
+//! connection (see `VibeStore::begin_read_session`) and returns its id.
+//! Queries and exports sent with `X-Vibe-Snapshot: <id>` are then read
+//! through that transaction, so every page sees the same database state.
+//!
+//! A snapshot ends on `DELETE /v1/snapshots/:id` or when its TTL runs out;
+//! the sweeper drops expired ones. Writers are never blocked, but an open
+//! snapshot keeps the WAL from being checkpointed past its start, so only
+//! `snapshots.max_open` may be open at once. A snapshot is usable only by
+//! the caller that opened it (and admins).
+//!
+//! The snapshot fixes rows, not the schema cache: columns added after it
+//! began are not in it, and reading them through it fails.
+
+use crate::api::AppState;
+use crate::auth::AuthUser;
+use crate::clock::{SharedClock, SystemClock};
+use crate::config::SnapshotConfig;
+use crate::db::{ReadSession, VibeStore};
+use crate::error::{VibeError, VibeResult};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::info;
+
+/// Request header naming the snapshot to read from
+pub const SNAPSHOT_HEADER: &str = "x-vibe-snapshot";
+
+/// How often the sweeper looks for expired snapshots
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(10);
+
+/// An open snapshot, as returned to clients
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotInfo {
+    pub id: String,
+    pub ttl_secs: u64,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+struct OpenSnapshot {
+    session: Arc<ReadSession>,
+    info: SnapshotInfo,
+    /// Id of the user that opened it; `None` when auth is off
+    owner: Option<i64>,
+    expires: Instant,
+}
+
+impl OpenSnapshot {
+    fn usable_by(&self, caller: Option<&AuthUser>) -> bool {
+        match (self.owner, caller) {
+            (None, _) => true,
+            (Some(owner), Some(user)) => owner == user.id || user.is_admin(),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// Open snapshots by id
+pub struct SnapshotRegistry {
+    store: Arc<VibeStore>,
+    config: SnapshotConfig,
+    open: Mutex<HashMap<String, OpenSnapshot>>,
+    clock: SharedClock,
+}
+
+impl SnapshotRegistry {
+    pub fn new(store: Arc<VibeStore>, config: SnapshotConfig) -> Self {
+        Self {
+            store,
+            config,
+            open: Mutex::new(HashMap::new()),
+            clock: SystemClock::shared(),
+        }
+    }
+
+    /// Times TTLs by `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, OpenSnapshot>> {
+        self.open.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Error for a full registry, retryable once the next snapshot expires
+    fn full(&self, open: &HashMap<String, OpenSnapshot>) -> VibeError {
+        let now = self.clock.instant();
+        let retry_after = open
+            .values()
+            .map(|s| s.expires.saturating_duration_since(now))
+            .min()
+            .unwrap_or_default();
+        VibeError::RateLimited {
+            message: format!(
+                "{} snapshots are open; release one or wait for it to expire",
+                self.config.max_open
+            ),
+            retry_after,
+        }
+    }
+
+    /// Begins a snapshot that lives for `ttl` (the configured default when
+    /// `None`, at most `max_ttl`)
+    pub async fn open(&self, ttl: Option<Duration>, caller: Option<&AuthUser>) -> VibeResult<SnapshotInfo> {
+        let ttl = ttl.unwrap_or(self.config.default_ttl);
+        if ttl.is_zero() || ttl > self.config.max_ttl {
+            return Err(VibeError::InvalidPayload(format!(
+                "ttl_secs must be between 1 and {}",
+                self.config.max_ttl.as_secs()
+            )));
+        }
+        self.sweep();
+        {
+            let open = self.lock();
+            if open.len() >= self.config.max_open {
+                return Err(self.full(&open));
+            }
+        }
+
+        let session = self.store.begin_read_session().await?;
+        let created_at = self.clock.now();
+        let info = SnapshotInfo {
+            id: uuid::Uuid::new_v4().to_string(),
+            ttl_secs: ttl.as_secs(),
+            created_at,
+            expires_at: created_at + chrono::Duration::from_std(ttl).unwrap_or_default(),
+        };
+        let mut open = self.lock();
+        // Others may have taken the last slot while this one began
+        if open.len() >= self.config.max_open {
+            return Err(self.full(&open));
+        }
+        open.insert(
+            info.id.clone(),
+            OpenSnapshot {
+                session: Arc::new(session),
+                info: info.clone(),
+                owner: caller.map(|user| user.id),
+                expires: self.clock.instant() + ttl,
+            },
+        );
+        Ok(info)
+    }
+
+    /// The session of a live snapshot the caller may use
+    pub fn get(&self, id: &str, caller: Option<&AuthUser>) -> VibeResult<Arc<ReadSession>> {
+        let open = self.lock();
+        match open.get(id) {
+            Some(snapshot) if snapshot.expires > self.clock.instant() && snapshot.usable_by(caller) => {
+                Ok(Arc::clone(&snapshot.session))
+            }
+            _ => Err(not_found(id)),
+        }
+    }
+
+    /// Ends a snapshot; reads already running on it finish first
+    pub fn release(&self, id: &str, caller: Option<&AuthUser>) -> VibeResult<SnapshotInfo> {
+        let mut open = self.lock();
+        match open.get(id) {
+            Some(snapshot) if snapshot.usable_by(caller) => {}
+            _ => return Err(not_found(id)),
+        }
+        let snapshot = open.remove(id).ok_or_else(|| not_found(id))?;
+        Ok(snapshot.info)
+    }
+
+    /// Ends expired snapshots, returning how many
+    pub fn sweep(&self) -> usize {
+        let now = self.clock.instant();
+        let mut open = self.lock();
+        let before = open.len();
+        open.retain(|_, snapshot| snapshot.expires > now);
+        before - open.len()
+    }
+
+    /// Number of open snapshots
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn not_found(id: &str) -> VibeError {
+    VibeError::NotFound(format!("Snapshot '{}' not found or expired", id))
+}
+
+/// Ends expired snapshots every `tick`
+pub fn spawn_sweeper(state: AppState, tick: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tick);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let expired = state.snapshots.sweep();
+            if expired > 0 {
+                info!("📸 Ended {} expired snapshot(s)", expired);
+            }
+        }
+    })
+}
@@ -0,0 +1,767 @@
+//! # Pull Sources
+//!
+//! Polls remote HTTP JSON endpoints on a schedule and ingests the records
+//! they return, so a weather API or a SaaS export can feed a collection
+//! without an external cron.
+//!
+//! ```json
+//! {
+//!   "url": "https://api.example.com/readings",
+//!   "headers": {"x-api-key": "secret"},
+//!   "interval_secs": 300,
+//!   "collection": "readings",
+//!   "records_pointer": "/data/items",
+//!   "fields": {"station": "station", "/main/temp": "temperature"},
+//!   "dedup_keys": ["station", "observed_at"]
+//! }
+//! ```
+//!
+//! `records_pointer` is a JSON pointer to the array of records (empty for a
+//! top-level array). `fields` maps a record key, or a JSON pointer into the
+//! record, to a column name; without it records are stored as-is. Records
+//! whose `dedup_keys` values already exist in the collection are skipped;
+//! the check and the insert share a transaction.
+//!
+//! Every run is logged in `vibe_source_runs` with fetched, inserted, skipped
+//! and error counts. A run fails as a whole when the endpoint is unreachable,
+//! answers with a non-2xx status or has no array at the pointer. Failed
+//! sources back off exponentially and are disabled after
+//! `MAX_CONSECUTIVE_FAILURES` failures in a row. Header values are never
+//! returned by the API.
+//!
+//! ## System Tables
+//! - `vibe_sources` - One row per source, its definition as JSON
+//! - `vibe_source_runs` - One row per run
+
+use crate::api::AppState;
+use crate::audit::{self, format_timestamp, InsertAudit};
+use crate::clock::{SharedClock, SystemClock};
+use crate::db::{json_to_sql_value, SqlValue, VibeStore};
+use crate::error::{VibeError, VibeResult};
+use crate::guard::SchemaGuard;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Shown instead of header values
+pub const REDACTED: &str = "***";
+
+/// Failed runs in a row after which a source is disabled
+pub const MAX_CONSECUTIVE_FAILURES: u32 = 5;
+
+/// Longest wait between retries of a failing source
+const MAX_BACKOFF_SECS: u64 = 3600;
+
+/// How often the scheduler looks for due sources
+pub const SCHEDULER_TICK: Duration = Duration::from_secs(1);
+
+/// Time limit for one fetch
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Largest response body a run will parse
+const MAX_RESPONSE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Runs kept per source
+const MAX_RUNS_PER_SOURCE: i64 = 100;
+
+pub const RUN_OK: &str = "ok";
+pub const RUN_PARTIAL: &str = "partial";
+pub const RUN_FAILED: &str = "failed";
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// What to fetch and where to put it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceDefinition {
+    pub url: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    /// Request headers; values are redacted in responses
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub interval_secs: u64,
+    pub collection: String,
+    /// JSON pointer to the array of records
+    #[serde(default)]
+    pub records_pointer: String,
+    /// Record key or JSON pointer -> column; empty keeps records as-is
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+    /// Columns identifying a record already ingested
+    #[serde(default)]
+    pub dedup_keys: Vec<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+impl SourceDefinition {
+    /// Checks the definition before it is stored
+    pub fn validate(&self) -> VibeResult<()> {
+        if !(self.url.starts_with("http://") || self.url.starts_with("https://")) {
+            return Err(VibeError::InvalidPayload(
+                "url must be an http:// or https:// URL".to_string(),
+            ));
+        }
+        if !matches!(self.method.as_str(), "GET" | "POST") {
+            return Err(VibeError::InvalidPayload(
+                "method must be GET or POST".to_string(),
+            ));
+        }
+        if self.interval_secs == 0 {
+            return Err(VibeError::InvalidPayload(
+                "interval_secs must be at least 1".to_string(),
+            ));
+        }
+        SchemaGuard::validate_identifier(&self.collection)?;
+        if !self.records_pointer.is_empty() && !self.records_pointer.starts_with('/') {
+            return Err(VibeError::InvalidPayload(
+                "records_pointer must be empty or start with /".to_string(),
+            ));
+        }
+        for name in self.headers.keys() {
+            reqwest::header::HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+                VibeError::InvalidPayload(format!("Invalid header name {}", name))
+            })?;
+        }
+        for column in self.fields.values().chain(&self.dedup_keys) {
+            SchemaGuard::validate_identifier(column)?;
+        }
+        Ok(())
+    }
+
+    /// Shapes one fetched record into a row
+    fn map_record(&self, record: &Value) -> Result<Map<String, Value>, String> {
+        let object = record
+            .as_object()
+            .ok_or_else(|| "Record is not an object".to_string())?;
+        if self.fields.is_empty() {
+            return Ok(object.clone());
+        }
+
+        let mut row = Map::new();
+        for (from, to) in &self.fields {
+            let value = if from.starts_with('/') {
+                record.pointer(from)
+            } else {
+                object.get(from)
+            };
+            if let Some(value) = value {
+                row.insert(to.clone(), value.clone());
+            }
+        }
+        Ok(row)
+    }
+}
+
+/// A stored source
+#[derive(Debug, Clone, Serialize)]
+pub struct Source {
+    pub id: i64,
+    #[serde(flatten)]
+    pub definition: SourceDefinition,
+    pub consecutive_failures: u32,
+    /// Unix time of the next scheduled run
+    pub next_run_at: i64,
+    pub last_error: Option<String>,
+    pub created_at: String,
+}
+
+impl Source {
+    /// The source with header values hidden, for responses
+    pub fn redacted(mut self) -> Self {
+        for value in self.definition.headers.values_mut() {
+            *value = REDACTED.to_string();
+        }
+        self
+    }
+
+    fn from_row(row: Map<String, Value>) -> VibeResult<Self> {
+        let field = |name: &str| row.get(name).cloned().unwrap_or(Value::Null);
+        Ok(Self {
+            id: field("id").as_i64().unwrap_or_default(),
+            definition: serde_json::from_value(field("definition"))?,
+            consecutive_failures: field("consecutive_failures").as_u64().unwrap_or(0) as u32,
+            next_run_at: field("next_run_at").as_i64().unwrap_or(0),
+            last_error: field("last_error").as_str().map(str::to_string),
+            created_at: field("created_at").as_str().unwrap_or_default().to_string(),
+        })
+    }
+}
+
+/// Outcome of one run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceRun {
+    pub id: i64,
+    pub source_id: i64,
+    pub started_at: String,
+    pub finished_at: String,
+    /// `ok`, `partial` (some records failed) or `failed`
+    pub status: String,
+    pub fetched: u64,
+    pub inserted: u64,
+    pub skipped: u64,
+    pub errors: u64,
+    /// Why the run failed, or the first record error
+    pub error: Option<String>,
+}
+
+/// Counts gathered while a run ingests records
+#[derive(Debug, Default)]
+struct RunTally {
+    fetched: u64,
+    inserted: u64,
+    skipped: u64,
+    errors: u64,
+    first_error: Option<String>,
+}
+
+/// Persistent store of pull sources, and the runner for them
+pub struct SourceStore {
+    store: Arc<VibeStore>,
+    http: reqwest::Client,
+    initialized: OnceCell<()>,
+    clock: SharedClock,
+}
+
+impl SourceStore {
+    pub fn new(store: Arc<VibeStore>) -> Self {
+        Self {
+            store,
+            http: reqwest::Client::new(),
+            initialized: OnceCell::new(),
+            clock: SystemClock::shared(),
+        }
+    }
+
+    /// Schedules and stamps runs by `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn unix_now(&self) -> i64 {
+        self.clock.now().timestamp()
+    }
+
+    fn timestamp(&self) -> String {
+        format_timestamp(self.clock.now())
+    }
+
+    async fn ensure_table(&self) -> VibeResult<()> {
+        self.initialized
+            .get_or_try_init(|| async {
+                self.store
+                    .execute_batch(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS vibe_sources (
+                            id INTEGER PRIMARY KEY AUTOINCREMENT,
+                            definition TEXT NOT NULL,
+                            consecutive_failures INTEGER NOT NULL DEFAULT 0,
+                            next_run_at INTEGER NOT NULL,
+                            last_error TEXT,
+                            created_at TEXT NOT NULL
+                        );
+                        CREATE TABLE IF NOT EXISTS vibe_source_runs (
+                            id INTEGER PRIMARY KEY AUTOINCREMENT,
+                            source_id INTEGER NOT NULL
+                                REFERENCES vibe_sources(id) ON DELETE CASCADE,
+                            started_at TEXT NOT NULL,
+                            finished_at TEXT NOT NULL,
+                            status TEXT NOT NULL,
+                            fetched INTEGER NOT NULL,
+                            inserted INTEGER NOT NULL,
+                            skipped INTEGER NOT NULL,
+                            errors INTEGER NOT NULL,
+                            error TEXT
+                        );
+                        CREATE INDEX IF NOT EXISTS idx_vibe_source_runs_source
+                            ON vibe_source_runs(source_id, id);
+                        "#
+                        .to_string(),
+                    )
+                    .await
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Stores a new source, due immediately
+    pub async fn create(&self, definition: SourceDefinition) -> VibeResult<Source> {
+        definition.validate()?;
+        self.ensure_table().await?;
+        let id = self
+            .store
+            .insert_returning_id(
+                "INSERT INTO vibe_sources (definition, next_run_at, created_at) VALUES (?, ?, ?)"
+                    .to_string(),
+                vec![
+                    SqlValue::Text(serde_json::to_string(&definition)?),
+                    SqlValue::Integer(self.unix_now()),
+                    SqlValue::Text(self.timestamp()),
+                ],
+            )
+            .await?;
+        self.get(id).await
+    }
+
+    /// Loads a source, header values included
+    pub async fn get(&self, id: i64) -> VibeResult<Source> {
+        self.ensure_table().await?;
+        let rows = self
+            .store
+            .query(
+                "SELECT * FROM vibe_sources WHERE id = ?".to_string(),
+                vec![SqlValue::Integer(id)],
+            )
+            .await?;
+
+        match rows.into_iter().next() {
+            Some(row) => Source::from_row(row.into_iter().collect()),
+            None => Err(VibeError::NotFound(format!("Source {} not found", id))),
+        }
+    }
+
+    /// Every source, oldest first
+    pub async fn list(&self) -> VibeResult<Vec<Source>> {
+        self.ensure_table().await?;
+        self.store
+            .query_simple("SELECT * FROM vibe_sources ORDER BY id".to_string())
+            .await?
+            .into_iter()
+            .map(|row| Source::from_row(row.into_iter().collect()))
+            .collect()
+    }
+
+    /// Replaces a source's definition
+    ///
+    /// Headers sent back as `REDACTED` keep their stored value. Enabling a
+    /// source clears its failure count and makes it due immediately.
+    pub async fn update(&self, id: i64, mut definition: SourceDefinition) -> VibeResult<Source> {
+        definition.validate()?;
+        let current = self.get(id).await?;
+        for (name, value) in definition.headers.iter_mut() {
+            if value == REDACTED {
+                if let Some(stored) = current.definition.headers.get(name) {
+                    value.clone_from(stored);
+                }
+            }
+        }
+
+        let reenabled = definition.enabled && !current.definition.enabled;
+        let (failures, next_run_at) = if reenabled {
+            (0, self.unix_now())
+        } else {
+            (i64::from(current.consecutive_failures), current.next_run_at)
+        };
+        self.store
+            .execute(
+                "UPDATE vibe_sources SET definition = ?, consecutive_failures = ?, next_run_at = ? \
+                 WHERE id = ?"
+                    .to_string(),
+                vec![
+                    SqlValue::Text(serde_json::to_string(&definition)?),
+                    SqlValue::Integer(failures),
+                    SqlValue::Integer(next_run_at),
+                    SqlValue::Integer(id),
+                ],
+            )
+            .await?;
+        self.get(id).await
+    }
+
+    /// Deletes a source; its run log goes with it
+    pub async fn delete(&self, id: i64) -> VibeResult<()> {
+        self.get(id).await?;
+        self.store
+            .execute(
+                "DELETE FROM vibe_sources WHERE id = ?".to_string(),
+                vec![SqlValue::Integer(id)],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Most recent runs of a source, newest first
+    pub async fn runs(&self, id: i64, limit: u32) -> VibeResult<Vec<SourceRun>> {
+        self.get(id).await?;
+        self.store
+            .query(
+                "SELECT * FROM vibe_source_runs WHERE source_id = ? ORDER BY id DESC LIMIT ?"
+                    .to_string(),
+                vec![SqlValue::Integer(id), SqlValue::Integer(i64::from(limit))],
+            )
+            .await?
+            .into_iter()
+            .map(|row| Ok(serde_json::from_value(Value::Object(row.into_iter().collect()))?))
+            .collect()
+    }
+
+    /// Enabled sources whose next run is due
+    pub async fn due(&self) -> VibeResult<Vec<Source>> {
+        let now = self.unix_now();
+        Ok(self
+            .list()
+            .await?
+            .into_iter()
+            .filter(|source| source.definition.enabled && source.next_run_at <= now)
+            .collect())
+    }
+
+    /// Fetches the source's records and ingests them into its collection
+    ///
+    /// Runs regardless of the schedule; the outcome is logged and decides
+    /// when the source is due next.
+    pub async fn run(&self, state: &AppState, id: i64) -> VibeResult<SourceRun> {
+        let source = self.get(id).await?;
+        let definition = &source.definition;
+        let started_at = self.timestamp();
+
+        let outcome = match self.fetch(definition).await {
+            Ok(records) => Ok(self.ingest(state, definition, records).await),
+            Err(e) => Err(e),
+        };
+        let (status, tally, error) = match outcome {
+            Ok(tally) => {
+                let status = if tally.errors > 0 { RUN_PARTIAL } else { RUN_OK };
+                let error = tally.first_error.clone();
+                (status, tally, error)
+            }
+            Err(e) => (RUN_FAILED, RunTally::default(), Some(e)),
+        };
+
+        let run_id = self
+            .store
+            .insert_returning_id(
+                "INSERT INTO vibe_source_runs (source_id, started_at, finished_at, status, \
+                 fetched, inserted, skipped, errors, error) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+                    .to_string(),
+                vec![
+                    SqlValue::Integer(id),
+                    SqlValue::Text(started_at),
+                    SqlValue::Text(self.timestamp()),
+                    SqlValue::Text(status.to_string()),
+                    SqlValue::Integer(tally.fetched as i64),
+                    SqlValue::Integer(tally.inserted as i64),
+                    SqlValue::Integer(tally.skipped as i64),
+                    SqlValue::Integer(tally.errors as i64),
+                    error.clone().map(SqlValue::Text).unwrap_or(SqlValue::Null),
+                ],
+            )
+            .await?;
+        self.store
+            .execute(
+                "DELETE FROM vibe_source_runs WHERE source_id = ? AND id <= ?".to_string(),
+                vec![SqlValue::Integer(id), SqlValue::Integer(run_id - MAX_RUNS_PER_SOURCE)],
+            )
+            .await?;
+
+        if status == RUN_FAILED {
+            self.record_failure(&source, error.unwrap_or_default()).await?;
+        } else {
+            info!(
+                "🛰️ Source {} ingested {} of {} records into {}",
+                id, tally.inserted, tally.fetched, definition.collection
+            );
+            self.store
+                .execute(
+                    "UPDATE vibe_sources SET consecutive_failures = 0, last_error = NULL, \
+                     next_run_at = ? WHERE id = ?"
+                        .to_string(),
+                    vec![
+                        SqlValue::Integer(self.unix_now() + definition.interval_secs as i64),
+                        SqlValue::Integer(id),
+                    ],
+                )
+                .await?;
+        }
+
+        let runs = self.runs(id, 1).await?;
+        runs.into_iter()
+            .next()
+            .ok_or_else(|| VibeError::NotFound(format!("Run of source {} not found", id)))
+    }
+
+    /// Backs off, and disables the source after too many failures
+    async fn record_failure(&self, source: &Source, error: String) -> VibeResult<()> {
+        let failures = source.consecutive_failures + 1;
+        let interval = source.definition.interval_secs;
+        let backoff = interval
+            .saturating_mul(1 << failures.min(16))
+            .min(MAX_BACKOFF_SECS.max(interval));
+        warn!("⚠️ Source {} failed ({} in a row): {}", source.id, failures, error);
+
+        let mut definition = source.definition.clone();
+        if failures >= MAX_CONSECUTIVE_FAILURES {
+            warn!("⛔ Disabling source {} after {} failures", source.id, failures);
+            definition.enabled = false;
+        }
+        self.store
+            .execute(
+                "UPDATE vibe_sources SET definition = ?, consecutive_failures = ?, last_error = ?, \
+                 next_run_at = ? WHERE id = ?"
+                    .to_string(),
+                vec![
+                    SqlValue::Text(serde_json::to_string(&definition)?),
+                    SqlValue::Integer(i64::from(failures)),
+                    SqlValue::Text(error),
+                    SqlValue::Integer(self.unix_now() + backoff as i64),
+                    SqlValue::Integer(source.id),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Requests the URL and returns the records at the pointer
+    async fn fetch(&self, definition: &SourceDefinition) -> Result<Vec<Value>, String> {
+        let method = reqwest::Method::from_bytes(definition.method.as_bytes())
+            .map_err(|e| e.to_string())?;
+        let mut request = self
+            .http
+            .request(method, &definition.url)
+            .timeout(FETCH_TIMEOUT);
+        for (name, value) in &definition.headers {
+            request = request.header(name, value);
+        }
+
+        let mut response = request
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("Endpoint answered {}", status));
+        }
+        let too_large = || format!("Response is larger than {} bytes", MAX_RESPONSE_BYTES);
+        if response.content_length().is_some_and(|len| len > MAX_RESPONSE_BYTES as u64) {
+            return Err(too_large());
+        }
+        // The declared length may be missing or wrong; stop reading at the limit
+        let mut body = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| format!("Reading the response failed: {}", e))?
+        {
+            if body.len() + chunk.len() > MAX_RESPONSE_BYTES {
+                return Err(too_large());
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        let mut document: Value = serde_json::from_slice(&body)
+            .map_err(|e| format!("Response is not JSON: {}", e))?;
+        match document.pointer_mut(&definition.records_pointer).map(Value::take) {
+            Some(Value::Array(records)) => Ok(records),
+            _ => Err(format!(
+                "Expected an array at '{}'",
+                definition.records_pointer
+            )),
+        }
+    }
+
+    /// Inserts the records that pass mapping and dedup
+    async fn ingest(&self, state: &AppState, definition: &SourceDefinition, records: Vec<Value>) -> RunTally {
+        let mut tally = RunTally {
+            fetched: records.len() as u64,
+            ..Default::default()
+        };
+        let collection = &definition.collection;
+        if let Err(e) = state.guard.ensure_table(collection).await {
+            tally.errors = tally.fetched;
+            tally.first_error = Some(e.to_string());
+            return tally;
+        }
+
+        let audited = state
+            .settings
+            .get(collection)
+            .await
+            .map(|settings| settings.audit)
+            .unwrap_or(false);
+        // Audit entries commit with the rows they describe
+        if audited {
+            if let Err(e) = audit::ensure_table(&state.store).await {
+                tally.errors = tally.fetched;
+                tally.first_error = Some(e.to_string());
+                return tally;
+            }
+        }
+        let audit = audited.then(|| InsertAudit::new(collection, None));
+        let started = Instant::now();
+        for record in records {
+            let result = match definition.map_record(&record) {
+                Ok(row) => self.insert_record(state, definition, Value::Object(row), audit.clone()).await,
+                Err(e) => Err(VibeError::InvalidPayload(e)),
+            };
+            match result {
+                Ok(Some(_)) => tally.inserted += 1,
+                Ok(None) => tally.skipped += 1,
+                Err(e) => {
+                    tally.errors += 1;
+                    tally.first_error.get_or_insert_with(|| e.to_string());
+                }
+            }
+        }
+        state
+            .perf
+            .record_write(collection, started.elapsed(), tally.inserted, true);
+
+        state.row_counts.adjust(collection, tally.inserted as i64);
+        if tally.inserted > 0 {
+            state.broadcast(collection, json!({
+                "event": "batch_insert",
+                "count": tally.inserted
+            }));
+        }
+        tally
+    }
+
+    /// Inserts one row unless its dedup key values already exist
+    ///
+    /// Returns the new id, or `None` for a duplicate.
+    async fn insert_record(
+        &self,
+        state: &AppState,
+        definition: &SourceDefinition,
+        row: Value,
+        audit: Option<InsertAudit>,
+    ) -> VibeResult<Option<i64>> {
+        let collection = &definition.collection;
+        let columns = state.guard.ensure_columns(collection, &row).await?;
+        let object = row.as_object().cloned().unwrap_or_default();
+
+        let mut keys = Vec::with_capacity(definition.dedup_keys.len());
+        for key in &definition.dedup_keys {
+            match object.get(key) {
+                Some(value) if !value.is_null() => keys.push(json_to_sql_value(value)),
+                _ => {
+                    return Err(VibeError::InvalidPayload(format!(
+                        "Record has no value for dedup key {}",
+                        key
+                    )))
+                }
+            }
+        }
+        let exists_sql = format!(
+            "SELECT 1 FROM {} WHERE {} LIMIT 1",
+            collection,
+            definition
+                .dedup_keys
+                .iter()
+                .map(|key| format!("{} IS ?", key))
+                .collect::<Vec<_>>()
+                .join(" AND ")
+        );
+
+        let insert_sql = if columns.is_empty() {
+            format!("INSERT INTO {} DEFAULT VALUES", collection)
+        } else {
+            format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                collection,
+                columns.join(", "),
+                vec!["?"; columns.len()].join(", ")
+            )
+        };
+        let params: Vec<SqlValue> = columns
+            .iter()
+            .map(|col| object.get(col).map(json_to_sql_value).unwrap_or(SqlValue::Null))
+            .collect();
+
+        state
+            .store
+            .with_transaction_labeled(&state.perf.label(collection), move |conn| {
+                if !keys.is_empty() && conn.prepare(&exists_sql)?.exists(rusqlite::params_from_iter(keys.iter()))? {
+                    return Ok(None);
+                }
+                conn.execute(&insert_sql, rusqlite::params_from_iter(params.iter()))?;
+                let id = conn.last_insert_rowid();
+                if let Some(audit) = &audit {
+                    audit.record_in(conn, id)?;
+                }
+                Ok(Some(id))
+            })
+            .await
+    }
+}
+
+/// Runs due sources every `tick` until the task is aborted
+pub fn spawn_scheduler(state: AppState, tick: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tick);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let due = match state.sources.due().await {
+                Ok(due) => due,
+                Err(e) => {
+                    warn!("⚠️ Could not load pull sources: {}", e);
+                    continue;
+                }
+            };
+            for source in due {
+                if let Err(e) = state.sources.run(&state, source.id).await {
+                    warn!("⚠️ Run of source {} could not be recorded: {}", source.id, e);
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition() -> SourceDefinition {
+        serde_json::from_value(json!({
+            "url": "https://example.com/feed",
+            "interval_secs": 60,
+            "collection": "readings",
+            "fields": {"id": "station", "/main/temp": "temperature"}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_definition_validation_and_mapping() {
+        let definition = definition();
+        assert_eq!(definition.method, "GET");
+        assert!(definition.enabled);
+        assert!(definition.validate().is_ok());
+
+        let row = definition
+            .map_record(&json!({"id": "a", "main": {"temp": 3.5}, "noise": 1}))
+            .unwrap();
+        assert_eq!(Value::Object(row), json!({"station": "a", "temperature": 3.5}));
+        assert!(definition.map_record(&json!([1])).is_err());
+
+        for bad in [
+            json!({"url": "file:///etc/passwd"}),
+            json!({"method": "DELETE"}),
+            json!({"interval_secs": 0}),
+            json!({"records_pointer": "data"}),
+            json!({"dedup_keys": ["bad-key"]}),
+            json!({"headers": {"bad header": "x"}}),
+        ] {
+            let mut value = serde_json::to_value(definition.clone()).unwrap();
+            for (key, field) in bad.as_object().unwrap() {
+                value[key] = field.clone();
+            }
+            let bad: SourceDefinition = serde_json::from_value(value).unwrap();
+            assert!(bad.validate().is_err(), "{:?}", bad);
+        }
+    }
+}
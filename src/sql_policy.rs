@@ -0,0 +1,474 @@
+//! # SQL Policy
+//!
+//! Screens statements submitted to the raw SQL endpoints before they reach
+//! SQLite. Even a read-only query surface can open other database files
+//! (`ATTACH`), write the whole database somewhere else (`VACUUM INTO`) or touch
+//! the filesystem through extension functions, so those are rejected here.
+//!
+//! ## Blocklist Syntax
+//! - `ATTACH`, `VACUUM INTO`, `PRAGMA temp_store_directory` - statement rules.
+//!   The first word must start the statement; the remaining words must follow
+//!   it in order (so `VACUUM main INTO 'x'` matches `VACUUM INTO`).
+//! - `writefile(` - function rules, matched wherever the function is called.
+//!
+//! Matching is case-insensitive and ignores comments and string literals.
+//!
+//! ## PRAGMA Policy
+//! - Schema queries (`table_info`, `index_list`, `database_list`, ...) and
+//!   reads of settings (`PRAGMA user_version`) are open to every caller.
+//! - Any other pragma, including assigning a setting, needs an admin and
+//!   must be on the PRAGMA allow-list.
+//! - `journal_mode`, `synchronous` and `writable_schema` are set by the
+//!   server and can't be changed through SQL at all.
+
+use crate::error::{VibeError, VibeResult};
+
+/// Statements and functions blocked when no blocklist is configured
+pub const DEFAULT_BLOCKLIST: &[&str] = &[
+    "ATTACH",
+    "DETACH",
+    "VACUUM INTO",
+    "PRAGMA temp_store_directory",
+    "PRAGMA data_store_directory",
+    "load_extension(",
+    "readfile(",
+    "writefile(",
+    "edit(",
+];
+
+/// Pragmas admins may change when no allow-list is configured
+pub const DEFAULT_PRAGMA_ALLOWLIST: &[&str] = &["user_version", "application_id", "optimize"];
+
+/// Pragmas with no side effects, allowed with or without arguments
+const QUERY_PRAGMAS: &[&str] = &[
+    "TABLE_INFO",
+    "TABLE_XINFO",
+    "TABLE_LIST",
+    "INDEX_LIST",
+    "INDEX_INFO",
+    "INDEX_XINFO",
+    "FOREIGN_KEY_LIST",
+    "DATABASE_LIST",
+    "COLLATION_LIST",
+    "FUNCTION_LIST",
+    "PRAGMA_LIST",
+    "COMPILE_OPTIONS",
+];
+
+/// Settings anyone may read; assigning them is a change like any other
+const READABLE_SETTINGS: &[&str] = &[
+    "USER_VERSION",
+    "SCHEMA_VERSION",
+    "APPLICATION_ID",
+    "DATA_VERSION",
+    "PAGE_SIZE",
+    "PAGE_COUNT",
+    "FREELIST_COUNT",
+    "ENCODING",
+    "AUTO_VACUUM",
+    "FOREIGN_KEYS",
+    "JOURNAL_MODE",
+    "SYNCHRONOUS",
+];
+
+/// Pragmas the server configures at startup and nobody may change, since
+/// they affect durability or integrity for the whole instance
+const SERVER_PRAGMAS: &[&str] = &["JOURNAL_MODE", "SYNCHRONOUS", "WRITABLE_SCHEMA"];
+
+/// Who a statement is screened for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlAccess {
+    User,
+    Admin,
+}
+
+/// A lexical token of a SQL statement
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token {
+    /// Keyword or identifier (quoted identifiers are unquoted), uppercased
+    Word(String),
+    /// Any other single character outside literals and comments
+    Punct(char),
+}
+
+/// A compiled blocklist entry
+#[derive(Debug, Clone)]
+enum Rule {
+    Statement(Vec<String>),
+    Function(String),
+}
+
+impl Rule {
+    fn parse(entry: &str) -> Option<Self> {
+        let entry = entry.trim();
+        if let Some(name) = entry.strip_suffix('(') {
+            let name = name.trim();
+            return (!name.is_empty()).then(|| Rule::Function(name.to_uppercase()));
+        }
+
+        let words: Vec<String> = entry
+            .split(|c: char| c.is_whitespace() || c == '.')
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_uppercase())
+            .collect();
+        (!words.is_empty()).then_some(Rule::Statement(words))
+    }
+
+    fn matches(&self, statement: &[Token]) -> bool {
+        match self {
+            Rule::Statement(pattern) => {
+                let mut words = statement_words(statement);
+                if words.next() != Some(pattern[0].as_str()) {
+                    return false;
+                }
+                pattern[1..].iter().all(|p| words.any(|w| w == p))
+            }
+            Rule::Function(name) => statement.windows(2).any(|pair| {
+                matches!(&pair[0], Token::Word(w) if w == name) && pair[1] == Token::Punct('(')
+            }),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Rule::Statement(words) => words.join(" "),
+            Rule::Function(name) => format!("{}()", name),
+        }
+    }
+}
+
+/// Words of a statement with any leading `EXPLAIN [QUERY PLAN]` removed
+fn statement_words(statement: &[Token]) -> impl Iterator<Item = &str> {
+    let mut words = statement
+        .iter()
+        .filter_map(|t| match t {
+            Token::Word(w) => Some(w.as_str()),
+            Token::Punct(_) => None,
+        })
+        .peekable();
+
+    if words.peek() == Some(&"EXPLAIN") {
+        words.next();
+        if words.peek() == Some(&"QUERY") {
+            words.next();
+            if words.peek() == Some(&"PLAN") {
+                words.next();
+            }
+        }
+    }
+    words
+}
+
+/// A PRAGMA statement: its name (without schema) and whether it is given
+/// a value or argument
+struct Pragma<'a> {
+    name: &'a str,
+    has_argument: bool,
+}
+
+impl<'a> Pragma<'a> {
+    fn parse(statement: &'a [Token]) -> Option<Self> {
+        let mut rest = statement;
+        while let [Token::Word(w), tail @ ..] = rest {
+            if !matches!(w.as_str(), "EXPLAIN" | "QUERY" | "PLAN") {
+                break;
+            }
+            rest = tail;
+        }
+        let rest = match rest {
+            [Token::Word(w), tail @ ..] if w == "PRAGMA" => tail,
+            _ => return None,
+        };
+        let (name, tail) = match rest {
+            [Token::Word(_), Token::Punct('.'), Token::Word(name), tail @ ..] => (name, tail),
+            [Token::Word(name), tail @ ..] => (name, tail),
+            _ => return None,
+        };
+        Some(Self {
+            name,
+            has_argument: !tail.is_empty(),
+        })
+    }
+
+    fn is_read(&self) -> bool {
+        QUERY_PRAGMAS.contains(&self.name)
+            || (!self.has_argument && READABLE_SETTINGS.contains(&self.name))
+    }
+}
+
+/// Splits SQL into statements of tokens, dropping comments and literals
+pub(crate) fn tokenize(sql: &str) -> Vec<Vec<Token>> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut statements = Vec::new();
+    let mut current = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            // Line comment
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            // Block comment
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i += 2;
+            }
+            // String literal ('' escapes a quote)
+            '\'' => {
+                i += 1;
+                while i < chars.len() {
+                    if chars[i] == '\'' {
+                        if chars.get(i + 1) == Some(&'\'') {
+                            i += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            // Quoted identifiers
+            '"' | '`' | '[' => {
+                let close = if c == '[' { ']' } else { c };
+                let mut ident = String::new();
+                i += 1;
+                while i < chars.len() {
+                    if chars[i] == close {
+                        if close != ']' && chars.get(i + 1) == Some(&close) {
+                            i += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                    ident.push(chars[i]);
+                    i += 1;
+                }
+                i += 1;
+                current.push(Token::Word(ident.to_uppercase()));
+            }
+            ';' => {
+                if !current.is_empty() {
+                    statements.push(std::mem::take(&mut current));
+                }
+                i += 1;
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '$' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                current.push(Token::Word(word.to_uppercase()));
+            }
+            c if c.is_whitespace() => i += 1,
+            c => {
+                current.push(Token::Punct(c));
+                i += 1;
+            }
+        }
+    }
+
+    if !current.is_empty() {
+        statements.push(current);
+    }
+    statements
+}
+
+/// Compiled statement screening policy for the raw SQL endpoints
+#[derive(Debug, Clone)]
+pub struct SqlPolicy {
+    rules: Vec<Rule>,
+    /// Pragmas admins may change, uppercased
+    pragma_allowlist: Vec<String>,
+}
+
+impl Default for SqlPolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_BLOCKLIST)
+    }
+}
+
+impl SqlPolicy {
+    /// Compiles a blocklist; empty or malformed entries are ignored
+    ///
+    /// Admins may change the pragmas of `DEFAULT_PRAGMA_ALLOWLIST`.
+    pub fn new<S: AsRef<str>>(blocklist: &[S]) -> Self {
+        Self {
+            rules: blocklist
+                .iter()
+                .filter_map(|entry| Rule::parse(entry.as_ref()))
+                .collect(),
+            pragma_allowlist: Vec::new(),
+        }
+        .with_pragma_allowlist(DEFAULT_PRAGMA_ALLOWLIST)
+    }
+
+    /// Sets the pragmas admins may change
+    pub fn with_pragma_allowlist<S: AsRef<str>>(mut self, allowlist: &[S]) -> Self {
+        self.pragma_allowlist = allowlist
+            .iter()
+            .map(|name| name.as_ref().trim().to_uppercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+        self
+    }
+
+    /// Rejects the SQL if any of its statements matches the blocklist or
+    /// runs a pragma `access` doesn't allow
+    pub fn screen(&self, sql: &str, access: SqlAccess) -> VibeResult<()> {
+        for statement in tokenize(sql) {
+            if let Some(rule) = self.rules.iter().find(|r| r.matches(&statement)) {
+                return Err(VibeError::Forbidden(format!(
+                    "Statement blocked by SQL policy: {}",
+                    rule.describe()
+                )));
+            }
+            if let Some(pragma) = Pragma::parse(&statement) {
+                self.screen_pragma(&pragma, access)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Like `screen`, but also requires exactly one statement
+    pub fn screen_single(&self, sql: &str, access: SqlAccess) -> VibeResult<()> {
+        if tokenize(sql).len() != 1 {
+            return Err(VibeError::InvalidPayload(
+                "Exactly one SQL statement is required".to_string(),
+            ));
+        }
+        self.screen(sql, access)
+    }
+
+    fn screen_pragma(&self, pragma: &Pragma, access: SqlAccess) -> VibeResult<()> {
+        if pragma.is_read() {
+            return Ok(());
+        }
+        let name = pragma.name.to_lowercase();
+        if SERVER_PRAGMAS.contains(&pragma.name) {
+            return Err(VibeError::Forbidden(format!(
+                "PRAGMA {} is configured by the server at startup and can't be changed through SQL",
+                name
+            )));
+        }
+        if access != SqlAccess::Admin {
+            return Err(VibeError::Forbidden(format!(
+                "PRAGMA {} changes the database and requires an admin",
+                name
+            )));
+        }
+        if !self.pragma_allowlist.iter().any(|allowed| allowed == pragma.name) {
+            return Err(VibeError::Forbidden(format!(
+                "PRAGMA {} is not on the PRAGMA allow-list (VIBEDB_SQL_PRAGMA_ALLOWLIST)",
+                name
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks_attach_and_vacuum_into() {
+        let policy = SqlPolicy::default();
+
+        assert!(policy.screen("ATTACH DATABASE '/tmp/x.db' AS x", SqlAccess::User).is_err());
+        assert!(policy.screen("detach x", SqlAccess::User).is_err());
+        assert!(policy.screen("VACUUM INTO '/tmp/copy.db'", SqlAccess::User).is_err());
+        assert!(policy.screen("VACUUM main INTO '/tmp/copy.db'", SqlAccess::User).is_err());
+        assert!(policy.screen("PRAGMA main.temp_store_directory = '/tmp'", SqlAccess::User).is_err());
+        assert!(policy.screen("SELECT 1; /* hidden */ ATTACH 'x' AS y", SqlAccess::User).is_err());
+
+        assert!(policy.screen("VACUUM", SqlAccess::User).is_ok());
+        assert!(policy.screen("SELECT * FROM users", SqlAccess::User).is_ok());
+    }
+
+    #[test]
+    fn test_blocks_file_functions() {
+        let policy = SqlPolicy::default();
+
+        assert!(policy.screen("SELECT writefile('/tmp/x', 'data')", SqlAccess::User).is_err());
+        assert!(policy.screen("SELECT * FROM t WHERE x = (SELECT READFILE ('/etc/passwd'))", SqlAccess::User).is_err());
+        assert!(policy.screen("SELECT \"load_extension\"('evil')", SqlAccess::User).is_err());
+
+        // Mentions inside literals and comments are harmless
+        assert!(policy.screen("SELECT 'ATTACH writefile(' AS note", SqlAccess::User).is_ok());
+        assert!(policy.screen("-- ATTACH\nSELECT 1", SqlAccess::User).is_ok());
+    }
+
+    #[test]
+    fn test_custom_blocklist() {
+        let policy = SqlPolicy::new(&["DROP TABLE", "random("]);
+
+        assert!(policy.screen("DROP TABLE users", SqlAccess::User).is_err());
+        assert!(policy.screen("SELECT random()", SqlAccess::User).is_err());
+        assert!(policy.screen("ATTACH 'x.db' AS x", SqlAccess::User).is_ok());
+    }
+
+    #[test]
+    fn test_screen_single() {
+        let policy = SqlPolicy::default();
+
+        assert!(policy.screen_single("DELETE FROM t; -- trailing comment", SqlAccess::User).is_ok());
+        assert!(policy.screen_single("DELETE FROM t; DROP TABLE t", SqlAccess::User).is_err());
+        assert!(policy.screen_single("  ", SqlAccess::User).is_err());
+        assert!(policy.screen_single("ATTACH 'x.db' AS x", SqlAccess::User).is_err());
+    }
+
+    #[test]
+    fn test_schema_pragmas_are_open_to_everyone() {
+        let policy = SqlPolicy::default();
+
+        assert!(policy.screen("PRAGMA table_info(users)", SqlAccess::User).is_ok());
+        assert!(policy.screen("pragma main.index_list('users')", SqlAccess::User).is_ok());
+        assert!(policy.screen("PRAGMA database_list", SqlAccess::User).is_ok());
+        assert!(policy.screen("PRAGMA user_version", SqlAccess::User).is_ok());
+        assert!(policy.screen("PRAGMA journal_mode", SqlAccess::User).is_ok());
+    }
+
+    #[test]
+    fn test_changing_pragmas_needs_an_allowed_admin() {
+        let policy = SqlPolicy::default();
+
+        assert!(policy.screen("PRAGMA user_version = 3", SqlAccess::User).is_err());
+        assert!(policy.screen("PRAGMA user_version(3)", SqlAccess::User).is_err());
+        assert!(policy.screen("PRAGMA user_version = 3", SqlAccess::Admin).is_ok());
+
+        // Not on the default allow-list
+        assert!(policy.screen("PRAGMA wal_checkpoint(TRUNCATE)", SqlAccess::Admin).is_err());
+        assert!(policy.screen("PRAGMA cache_size = 10", SqlAccess::Admin).is_err());
+
+        let policy = SqlPolicy::default().with_pragma_allowlist(&["wal_checkpoint"]);
+        assert!(policy.screen("PRAGMA wal_checkpoint(TRUNCATE)", SqlAccess::Admin).is_ok());
+        assert!(policy.screen("PRAGMA wal_checkpoint", SqlAccess::User).is_err());
+        assert!(policy.screen("PRAGMA user_version = 3", SqlAccess::Admin).is_err());
+    }
+
+    #[test]
+    fn test_server_pragmas_are_always_blocked() {
+        let policy = SqlPolicy::default().with_pragma_allowlist(&["journal_mode", "synchronous"]);
+
+        for sql in [
+            "PRAGMA journal_mode=DELETE",
+            "PRAGMA main.synchronous = OFF",
+            "PRAGMA writable_schema = ON",
+            "SELECT 1; PRAGMA synchronous=0",
+        ] {
+            let err = policy.screen(sql, SqlAccess::Admin).unwrap_err();
+            assert!(err.to_string().contains("configured by the server"), "{}: {}", sql, err);
+        }
+    }
+}
@@ -6,39 +6,87 @@
 //! - Bucket-based organization (public/private)
 //! - File upload, download, delete, list operations
 //! - SQLite metadata tracking with filesystem storage
+//! - Optional per-bucket gzip/zstd compression of stored files
+//! - Bucket stats report the bytes taken by duplicate files; every object
+//!   still has its own file, as content isn't deduplicated on disk
+//! - Uploads streamed to disk with a SHA-256 checksum and moved into place
+//!   atomically once complete
+//! - Multi-file uploads to a path prefix, with optional per-file metadata
+//! - Buckets and objects created with a bearer token record the caller in
+//!   `owner_id`
+//! - Object metadata patched in place, without re-uploading the file
+//! - Upload and delete events on an SSE stream, optionally per bucket
+//! - An optional default bucket, created at startup, for uploads that name
+//!   no bucket
 //!
 //! ## System Tables
 //! - `vibe_buckets` - Stores bucket configuration
 //! - `vibe_objects` - Tracks file metadata
 
+use crate::api::AppState;
+use crate::auth::{AuthService, AuthUser};
+use crate::clock::{SharedClock, SystemClock};
+use crate::config::{BasePathConfig, DefaultBucketConfig, StorageQuotaConfig};
 use crate::db::{SqlValue, VibeStore};
 use crate::error::{VibeError, VibeResult};
+use crate::explorer::FORWARDED_PREFIX;
+use crate::guard::SQLITE_TIMESTAMP_FORMAT;
 
 use axum::{
-    extract::{Multipart, Path, Query, State},
-    http::{header, StatusCode},
-    response::IntoResponse,
-    routing::{delete, get, post},
+    body::Bytes,
+    extract::{FromRef, Multipart, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{sse::Event, IntoResponse, Sse},
+    routing::{delete, get, patch, post},
     Json, Router,
 };
+use dashmap::DashMap;
+use futures::{Stream, StreamExt};
+use ring::digest;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::io::Read;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
-use tracing::{debug, info};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
 
 // ============================================================================
 // Configuration
 // ============================================================================
 
 /// Default storage directory (relative to current working directory)
-const DEFAULT_STORAGE_PATH: &str = "./vibe_storage";
+pub const DEFAULT_STORAGE_PATH: &str = "./vibe_storage";
 
 /// Maximum file size (100 MB)
 const MAX_FILE_SIZE: usize = 100 * 1024 * 1024;
 
+/// Maximum combined size of the files of one upload request (500 MB)
+const MAX_UPLOAD_SIZE: usize = 500 * 1024 * 1024;
+
+/// Maximum number of files in one upload request
+const MAX_FILES_PER_UPLOAD: usize = 100;
+
+/// Maximum size of an upload's `metadata` part
+const MAX_METADATA_SIZE: usize = 16 * 1024;
+
+/// Objects listed per query while a bucket archive is built
+const ARCHIVE_PAGE_SIZE: i64 = 100;
+
+/// Size of the chunks object files are read in for archives
+const ARCHIVE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Lifetime of signed object URLs unless `expires_in` says otherwise
+pub const DEFAULT_SIGNED_URL_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Longest `expires_in` a signed object URL may be requested with
+pub const MAX_SIGNED_URL_TTL: Duration = Duration::from_secs(24 * 3600);
+
 // ============================================================================
 // Core Types
 // ============================================================================
@@ -48,6 +96,116 @@ const MAX_FILE_SIZE: usize = 100 * 1024 * 1024;
 pub struct StorageService {
     store: Arc<VibeStore>,
     storage_path: PathBuf,
+    quotas: StorageQuotaConfig,
+    /// Held from a quota check until its upload is recorded, so concurrent
+    /// uploads can't each fit a quota they exceed together
+    quota_lock: Arc<tokio::sync::Mutex<()>>,
+    /// Held while an object's file and row are replaced, by bucket and
+    /// path, so two uploads to one path can't pair one's file with the
+    /// other's row
+    object_locks: Arc<ObjectLocks>,
+    /// Object and bucket events (see `spawn_event_forwarder`)
+    events: broadcast::Sender<StorageEvent>,
+    /// Bucket of `POST /v1/storage/upload/*path`
+    default_bucket: Option<String>,
+    /// Time source of signed URL expiry
+    clock: SharedClock,
+}
+
+/// Codec applied to files before they are written to disk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    fn parse(value: &str) -> VibeResult<Self> {
+        match value {
+            "none" => Ok(Compression::None),
+            "gzip" => Ok(Compression::Gzip),
+            "zstd" => Ok(Compression::Zstd),
+            other => Err(VibeError::Internal(anyhow::anyhow!(
+                "Unknown compression: {}",
+                other
+            ))),
+        }
+    }
+
+    /// `Content-Encoding` token for data stored with this codec
+    pub fn content_encoding(&self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            codec => Some(codec.as_str()),
+        }
+    }
+
+    /// Compresses the file at `source` into `target` without loading it
+    /// whole, returning the compressed size
+    fn compress_file(&self, source: &std::path::Path, target: &std::path::Path) -> VibeResult<u64> {
+        let compress = || -> std::io::Result<u64> {
+            let mut input = std::fs::File::open(source)?;
+            let output = std::fs::File::create(target)?;
+            let output = match self {
+                Compression::None => {
+                    let mut output = output;
+                    std::io::copy(&mut input, &mut output)?;
+                    output
+                }
+                Compression::Gzip => {
+                    let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+                    std::io::copy(&mut input, &mut encoder)?;
+                    encoder.finish()?
+                }
+                Compression::Zstd => {
+                    let mut encoder = zstd::Encoder::new(output, 0)?;
+                    std::io::copy(&mut input, &mut encoder)?;
+                    encoder.finish()?
+                }
+            };
+            output.sync_all()?;
+            Ok(output.metadata()?.len())
+        };
+        compress().map_err(|e| VibeError::Storage(format!("Failed to compress file: {}", e)))
+    }
+
+    /// Compresses `data` in memory
+    pub(crate) fn compress(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                std::io::copy(&mut &data[..], &mut encoder)?;
+                encoder.finish()
+            }
+            Compression::Zstd => zstd::encode_all(data, 0),
+        }
+    }
+
+    pub(crate) fn decompress(&self, data: &[u8]) -> VibeResult<Vec<u8>> {
+        let decompressed = match self {
+            Compression::None => return Ok(data.to_vec()),
+            Compression::Gzip => {
+                let mut out = Vec::new();
+                flate2::read::GzDecoder::new(data)
+                    .read_to_end(&mut out)
+                    .map(|_| out)
+            }
+            Compression::Zstd => zstd::decode_all(data),
+        };
+        decompressed.map_err(|e| VibeError::Storage(format!("Failed to decompress file: {}", e)))
+    }
 }
 
 /// Bucket metadata
@@ -56,6 +214,8 @@ pub struct Bucket {
     pub id: i64,
     pub name: String,
     pub public: bool,
+    /// Codec applied to new uploads
+    pub compression: Compression,
     pub created_at: String,
     pub owner_id: Option<i64>,
 }
@@ -66,22 +226,147 @@ pub struct StorageObject {
     pub id: i64,
     pub bucket_name: String,
     pub path: String,
+    /// Logical (uncompressed) size in bytes
     pub size: i64,
+    /// Size of the file on disk
+    pub stored_size: i64,
+    /// Codec the file is stored with
+    pub compression: Compression,
+    /// Hex SHA-256 of the uncompressed content; `None` for objects uploaded
+    /// before checksums were recorded
+    pub checksum: Option<String>,
     pub mime_type: String,
+    /// JSON object sent with the upload in a `metadata` part
+    #[serde(default)]
+    pub metadata: Option<Value>,
     pub created_at: String,
     pub updated_at: String,
     pub owner_id: Option<i64>,
 }
 
+/// Pseudo-collection whose stream (`/v1/stream/_storage`) carries every
+/// storage event; `storage:<bucket>` carries one bucket's
+pub const STORAGE_STREAM: &str = "_storage";
+
+/// What a storage event reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageChange {
+    ObjectCreated,
+    /// An upload replaced an existing object
+    ObjectUpdated,
+    /// An object's metadata was patched; its file is unchanged
+    MetadataUpdated,
+    ObjectDeleted,
+    BucketCreated,
+    BucketDeleted,
+}
+
+/// An object or bucket that was written or removed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageEvent {
+    #[serde(rename = "event")]
+    pub change: StorageChange,
+    pub bucket: String,
+    /// Whether the bucket was public when the event happened
+    pub public: bool,
+    pub bucket_owner_id: Option<i64>,
+    /// Object path; `None` for bucket events
+    pub path: Option<String>,
+    /// Logical size of the object written or removed
+    pub size: Option<i64>,
+    pub mime_type: Option<String>,
+    /// Owner of the object, or of the bucket for bucket events
+    pub owner_id: Option<i64>,
+}
+
+impl StorageEvent {
+    fn for_object(change: StorageChange, bucket: &Bucket, object: &StorageObject) -> Self {
+        Self {
+            change,
+            bucket: bucket.name.clone(),
+            public: bucket.public,
+            bucket_owner_id: bucket.owner_id,
+            path: Some(object.path.clone()),
+            size: Some(object.size),
+            mime_type: Some(object.mime_type.clone()),
+            owner_id: object.owner_id,
+        }
+    }
+
+    fn for_bucket(change: StorageChange, bucket: &Bucket) -> Self {
+        Self {
+            change,
+            bucket: bucket.name.clone(),
+            public: bucket.public,
+            bucket_owner_id: bucket.owner_id,
+            path: None,
+            size: None,
+            mime_type: None,
+            owner_id: bucket.owner_id,
+        }
+    }
+
+    /// Whether `caller` may learn of the event when auth is on: everyone
+    /// for public buckets, otherwise admins, the bucket owner and the
+    /// object owner
+    pub fn visible_to(&self, caller: Option<&AuthUser>) -> bool {
+        self.public
+            || caller.is_some_and(|user| {
+                user.is_admin() || self.bucket_owner_id == Some(user.id) || self.owner_id == Some(user.id)
+            })
+    }
+
+    /// The `GET /v1/storage/events` message for object events
+    fn object_message(&self) -> Option<Value> {
+        let event = match self.change {
+            StorageChange::ObjectCreated | StorageChange::ObjectUpdated => "upload",
+            StorageChange::MetadataUpdated => "metadata",
+            StorageChange::ObjectDeleted => "delete",
+            StorageChange::BucketCreated | StorageChange::BucketDeleted => return None,
+        };
+        Some(json!({
+            "event": event,
+            "bucket": self.bucket,
+            "path": self.path,
+            "size": self.size
+        }))
+    }
+}
+
+/// Aggregate sizes of a bucket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BucketStats {
+    pub name: String,
+    pub object_count: i64,
+    /// Sum of uncompressed object sizes
+    pub logical_size: i64,
+    /// Sum of on-disk object sizes
+    pub stored_size: i64,
+    /// On-disk size of objects whose content (by checksum) an older object
+    /// of the bucket already has
+    pub duplicate_size: i64,
+}
+
 // ============================================================================
 // Request/Response DTOs
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CreateBucketRequest {
     pub name: String,
     #[serde(default)]
     pub public: bool,
+    #[serde(default)]
+    pub compression: Compression,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateBucketRequest {
+    #[serde(default)]
+    pub public: Option<bool>,
+    #[serde(default)]
+    pub compression: Option<Compression>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -92,12 +377,184 @@ pub struct ListObjectsQuery {
     pub limit: i64,
     #[serde(default)]
     pub offset: i64,
+    /// RFC 3339 bounds on `created_at` and `updated_at`: `_after` is
+    /// inclusive, `_before` exclusive
+    #[serde(default)]
+    pub created_after: Option<String>,
+    #[serde(default)]
+    pub created_before: Option<String>,
+    #[serde(default)]
+    pub updated_after: Option<String>,
+    #[serde(default)]
+    pub updated_before: Option<String>,
+}
+
+impl Default for ListObjectsQuery {
+    fn default() -> Self {
+        Self {
+            prefix: None,
+            limit: default_limit(),
+            offset: 0,
+            created_after: None,
+            created_before: None,
+            updated_after: None,
+            updated_before: None,
+        }
+    }
+}
+
+impl ListObjectsQuery {
+    /// Time-range conditions with their parameters, in the format the
+    /// timestamp columns are stored in
+    fn time_filters(&self) -> VibeResult<Vec<(&'static str, SqlValue)>> {
+        let bounds = [
+            ("created_after", "created_at >= ?", &self.created_after),
+            ("created_before", "created_at < ?", &self.created_before),
+            ("updated_after", "updated_at >= ?", &self.updated_after),
+            ("updated_before", "updated_at < ?", &self.updated_before),
+        ];
+        let mut filters = Vec::new();
+        for (param, condition, value) in bounds {
+            let Some(value) = value else { continue };
+            let parsed = chrono::DateTime::parse_from_rfc3339(value).map_err(|_| VibeError::InvalidFields {
+                message: format!("{} must be an RFC 3339 timestamp, got '{}'", param, value),
+                fields: vec![param.to_string()],
+            })?;
+            let utc = parsed.with_timezone(&chrono::Utc).format(SQLITE_TIMESTAMP_FORMAT);
+            filters.push((condition, SqlValue::Text(utc.to_string())));
+        }
+        Ok(filters)
+    }
 }
 
 fn default_limit() -> i64 {
     100
 }
 
+/// `url` options of object listings and uploads
+#[derive(Debug, Default, Deserialize)]
+pub struct ObjectUrlParams {
+    /// Sign the URLs of private buckets (bucket owner or admin only)
+    #[serde(default)]
+    pub sign: bool,
+    /// Signed URL lifetime in seconds, up to `MAX_SIGNED_URL_TTL`
+    #[serde(default)]
+    pub expires_in: Option<u64>,
+}
+
+/// Options of an upload, besides the `url` ones
+#[derive(Debug, Default, Deserialize)]
+pub struct UploadParams {
+    /// Store nothing unless every file of the request is accepted
+    #[serde(default)]
+    pub strict: bool,
+    /// `false` refuses to replace existing objects, like `If-None-Match: *`
+    #[serde(default)]
+    pub overwrite: Option<bool>,
+}
+
+impl UploadParams {
+    /// Whether existing objects may be replaced: yes unless `?overwrite=false`
+    /// or an `If-None-Match: *` header says otherwise
+    pub fn overwrite(&self, headers: &HeaderMap) -> bool {
+        let if_none_match = headers
+            .get(header::IF_NONE_MATCH)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.trim() == "*");
+        self.overwrite.unwrap_or(true) && !if_none_match
+    }
+}
+
+/// Query of a signed download URL
+#[derive(Debug, Default, Deserialize)]
+pub struct SignedUrlParams {
+    #[serde(default)]
+    pub expires: Option<u64>,
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+/// Object metadata with the URL it can be downloaded from
+#[derive(Debug, Serialize)]
+pub struct ObjectWithUrl {
+    #[serde(flatten)]
+    pub object: StorageObject,
+    /// `null` for private buckets unless signing was requested
+    pub url: Option<String>,
+}
+
+/// A file being written next to its destination, removed unless persisted
+///
+/// Dropping it (an error, or the upload request going away) deletes the
+/// partial file.
+struct TempFile {
+    path: PathBuf,
+    persisted: bool,
+}
+
+impl TempFile {
+    /// A unique hidden name in the directory of `destination`, so the final
+    /// rename stays on one filesystem
+    fn beside(destination: &std::path::Path) -> Self {
+        let name = destination.file_name().and_then(|n| n.to_str()).unwrap_or("object");
+        Self {
+            path: destination.with_file_name(format!(".{}.{}.upload", name, uuid::Uuid::new_v4())),
+            persisted: false,
+        }
+    }
+
+    fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// Atomically moves the file to `destination`, replacing what is there
+    async fn persist(mut self, destination: &std::path::Path) -> VibeResult<()> {
+        fs::rename(&self.path, destination)
+            .await
+            .map_err(|e| VibeError::Storage(format!("Failed to store file: {}", e)))?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+/// An upload written to disk but not yet moved into place
+struct StagedObject {
+    bucket: String,
+    path: String,
+    file_path: PathBuf,
+    file: TempFile,
+    size: usize,
+    stored_size: u64,
+    compression: Compression,
+    checksum: String,
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Per-object locks by bucket and path
+type ObjectLocks = DashMap<(String, String), Arc<tokio::sync::Mutex<()>>>;
+
+/// An object's entry in `StorageService::object_locks`, held; dropping it
+/// removes the entry once nobody waits on it
+struct ObjectLock {
+    locks: Arc<ObjectLocks>,
+    key: (String, String),
+    _held: tokio::sync::OwnedMutexGuard<()>,
+}
+
+impl Drop for ObjectLock {
+    fn drop(&mut self) {
+        // The map's reference and the one behind `_held`
+        self.locks.remove_if(&self.key, |_, lock| Arc::strong_count(lock) <= 2);
+    }
+}
+
 // ============================================================================
 // StorageService Implementation
 // ============================================================================
@@ -107,9 +564,16 @@ impl StorageService {
     pub async fn new(store: Arc<VibeStore>, storage_path: Option<PathBuf>) -> VibeResult<Self> {
         let path = storage_path.unwrap_or_else(|| PathBuf::from(DEFAULT_STORAGE_PATH));
         
+        let (events, _) = broadcast::channel(100);
         let service = Self {
             store,
             storage_path: path,
+            quotas: StorageQuotaConfig::default(),
+            quota_lock: Arc::new(tokio::sync::Mutex::new(())),
+            object_locks: Arc::new(DashMap::new()),
+            events,
+            default_bucket: None,
+            clock: SystemClock::shared(),
         };
 
         // Initialize tables
@@ -119,6 +583,56 @@ impl StorageService {
         Ok(service)
     }
 
+    /// Caps stored bytes per owner and overall
+    pub fn with_quotas(mut self, quotas: StorageQuotaConfig) -> Self {
+        if let Some(limit) = quotas.per_user {
+            info!("📏 Storage quota per user: {} bytes", limit);
+        }
+        if let Some(limit) = quotas.global {
+            info!("📏 Storage quota: {} bytes", limit);
+        }
+        self.quotas = quotas;
+        self
+    }
+
+    /// Times signed URLs by `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Sends uploads that name no bucket to `config.name`, creating the
+    /// bucket (without an owner) if it doesn't exist
+    pub async fn with_default_bucket(mut self, config: DefaultBucketConfig) -> VibeResult<Self> {
+        let request = CreateBucketRequest {
+            name: config.name.clone(),
+            public: config.public,
+            compression: Compression::None,
+        };
+        match self.create_bucket(request, None).await {
+            Ok(_) => info!(
+                "🪣 Created default bucket: {} ({})",
+                config.name,
+                if config.public { "public" } else { "private" }
+            ),
+            Err(VibeError::Conflict(_)) => info!("🪣 Default bucket: {}", config.name),
+            Err(e) => return Err(e),
+        }
+        self.default_bucket = Some(config.name);
+        Ok(self)
+    }
+
+    /// Bucket of uploads that name none, if one is configured
+    pub fn default_bucket(&self) -> Option<&str> {
+        self.default_bucket.as_deref()
+    }
+
+    /// Receives an event for every object and bucket written or removed
+    /// from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<StorageEvent> {
+        self.events.subscribe()
+    }
+
     /// Initialize storage tables
     async fn initialize_tables(&self) -> VibeResult<()> {
         // Create buckets table
@@ -159,10 +673,38 @@ impl StorageService {
             .to_string(),
         ).await?;
 
+        // Columns added after the initial schema
+        self.ensure_column("vibe_buckets", "compression", "TEXT NOT NULL DEFAULT 'none'").await?;
+        self.ensure_column("vibe_objects", "compression", "TEXT NOT NULL DEFAULT 'none'").await?;
+        self.ensure_column("vibe_objects", "stored_size", "INTEGER").await?;
+        self.ensure_column("vibe_objects", "checksum", "TEXT").await?;
+        self.ensure_column("vibe_objects", "metadata", "TEXT").await?;
+        // Finds duplicate content for bucket stats
+        self.store.execute_batch(
+            "CREATE INDEX IF NOT EXISTS idx_vibe_objects_checksum ON vibe_objects(bucket_name, checksum);"
+                .to_string(),
+        ).await?;
+
         debug!("Storage tables initialized");
         Ok(())
     }
 
+    /// Add a column to a system table if an older database lacks it
+    async fn ensure_column(&self, table: &str, column: &str, definition: &str) -> VibeResult<()> {
+        let existing = self.store.query(
+            format!("SELECT name FROM pragma_table_info('{}') WHERE name = ?", table),
+            vec![SqlValue::Text(column.to_string())],
+        ).await?;
+
+        if existing.is_empty() {
+            self.store.execute_batch(format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                table, column, definition
+            )).await?;
+        }
+        Ok(())
+    }
+
     /// Ensure storage directory exists
     async fn ensure_storage_dir(&self) -> VibeResult<()> {
         fs::create_dir_all(&self.storage_path)
@@ -206,15 +748,15 @@ impl StorageService {
     /// Validate object path
     fn validate_object_path(&self, path: &str) -> VibeResult<()> {
         if path.is_empty() || path.len() > 1024 {
-            return Err(VibeError::InvalidPayload(
-                "Object path must be 1-1024 characters".to_string(),
+            return Err(VibeError::InvalidObjectPath(
+                "must be 1-1024 characters".to_string(),
             ));
         }
 
         // Prevent path traversal
         if path.contains("..") || path.starts_with('/') {
-            return Err(VibeError::InvalidPayload(
-                "Invalid object path".to_string(),
+            return Err(VibeError::InvalidObjectPath(
+                "may not contain '..' or start with '/'".to_string(),
             ));
         }
 
@@ -229,34 +771,43 @@ impl StorageService {
     pub async fn create_bucket(&self, req: CreateBucketRequest, owner_id: Option<i64>) -> VibeResult<Bucket> {
         self.validate_bucket_name(&req.name)?;
 
-        // Check if bucket already exists
-        let existing = self.store.query(
-            "SELECT id FROM vibe_buckets WHERE name = ?".to_string(),
-            vec![SqlValue::Text(req.name.clone())],
-        ).await?;
-
-        if !existing.is_empty() {
+        // Existence check and insert in one transaction, so concurrent
+        // creates of the same bucket can't both pass the check
+        let params = [
+            SqlValue::Text(req.name.clone()),
+            SqlValue::Integer(if req.public { 1 } else { 0 }),
+            SqlValue::Text(req.compression.as_str().to_string()),
+            owner_id.map(SqlValue::Integer).unwrap_or(SqlValue::Null),
+        ];
+        let created = self
+            .store
+            .with_transaction(move |conn| {
+                let exists = conn
+                    .prepare("SELECT 1 FROM vibe_buckets WHERE name = ?")?
+                    .exists([&params[0]])?;
+                if !exists {
+                    conn.execute(
+                        "INSERT INTO vibe_buckets (name, public, compression, owner_id) VALUES (?, ?, ?, ?)",
+                        rusqlite::params_from_iter(params.iter()),
+                    )?;
+                }
+                Ok(!exists)
+            })
+            .await?;
+        if !created {
             return Err(VibeError::Conflict("Bucket already exists".to_string()));
         }
 
-        // Insert bucket
-        self.store.execute(
-            "INSERT INTO vibe_buckets (name, public, owner_id) VALUES (?, ?, ?)".to_string(),
-            vec![
-                SqlValue::Text(req.name.clone()),
-                SqlValue::Integer(if req.public { 1 } else { 0 }),
-                owner_id.map(SqlValue::Integer).unwrap_or(SqlValue::Null),
-            ],
-        ).await?;
-
-        info!("Created bucket: {}", req.name);
-        self.get_bucket(&req.name).await
+        info!("Created bucket: {} (owner {:?})", req.name, owner_id);
+        let bucket = self.get_bucket(&req.name).await?;
+        let _ = self.events.send(StorageEvent::for_bucket(StorageChange::BucketCreated, &bucket));
+        Ok(bucket)
     }
 
     /// Get bucket by name
     pub async fn get_bucket(&self, name: &str) -> VibeResult<Bucket> {
         let rows = self.store.query(
-            "SELECT id, name, public, owner_id, created_at FROM vibe_buckets WHERE name = ?"
+            "SELECT id, name, public, compression, owner_id, created_at FROM vibe_buckets WHERE name = ?"
                 .to_string(),
             vec![SqlValue::Text(name.to_string())],
         ).await?;
@@ -271,7 +822,7 @@ impl StorageService {
     /// List all buckets
     pub async fn list_buckets(&self) -> VibeResult<Vec<Bucket>> {
         let rows = self.store.query_simple(
-            "SELECT id, name, public, owner_id, created_at FROM vibe_buckets ORDER BY name"
+            "SELECT id, name, public, compression, owner_id, created_at FROM vibe_buckets ORDER BY name"
                 .to_string(),
         ).await?;
 
@@ -281,7 +832,7 @@ impl StorageService {
     /// Delete a bucket (must be empty)
     pub async fn delete_bucket(&self, name: &str) -> VibeResult<()> {
         // Check if bucket exists
-        let _ = self.get_bucket(name).await?;
+        let bucket = self.get_bucket(name).await?;
 
         // Check if bucket is empty
         let objects = self.store.query(
@@ -314,9 +865,76 @@ impl StorageService {
         ).await?;
 
         info!("Deleted bucket: {}", name);
+        let _ = self.events.send(StorageEvent::for_bucket(StorageChange::BucketDeleted, &bucket));
         Ok(())
     }
 
+    /// Update bucket visibility and/or compression
+    ///
+    /// A new codec only applies to later uploads; existing files keep theirs.
+    pub async fn update_bucket(&self, name: &str, req: UpdateBucketRequest) -> VibeResult<Bucket> {
+        let _ = self.get_bucket(name).await?;
+
+        if let Some(public) = req.public {
+            self.store.execute(
+                "UPDATE vibe_buckets SET public = ? WHERE name = ?".to_string(),
+                vec![
+                    SqlValue::Integer(if public { 1 } else { 0 }),
+                    SqlValue::Text(name.to_string()),
+                ],
+            ).await?;
+        }
+        if let Some(compression) = req.compression {
+            self.store.execute(
+                "UPDATE vibe_buckets SET compression = ? WHERE name = ?".to_string(),
+                vec![
+                    SqlValue::Text(compression.as_str().to_string()),
+                    SqlValue::Text(name.to_string()),
+                ],
+            ).await?;
+        }
+
+        info!("Updated bucket: {}", name);
+        self.get_bucket(name).await
+    }
+
+    /// Object count with logical and stored sizes
+    pub async fn bucket_stats(&self, name: &str) -> VibeResult<BucketStats> {
+        let _ = self.get_bucket(name).await?;
+
+        let rows = self.store.query(
+            r#"
+            SELECT COUNT(*) AS object_count,
+                   COALESCE(SUM(size), 0) AS logical_size,
+                   COALESCE(SUM(COALESCE(stored_size, size)), 0) AS stored_size,
+                   COALESCE(SUM(CASE WHEN EXISTS (
+                       SELECT 1 FROM vibe_objects older
+                       WHERE older.bucket_name = o.bucket_name
+                         AND older.checksum = o.checksum
+                         AND older.id < o.id
+                   ) THEN COALESCE(stored_size, size) END), 0) AS duplicate_size
+            FROM vibe_objects o WHERE bucket_name = ?
+            "#
+            .to_string(),
+            vec![SqlValue::Text(name.to_string())],
+        ).await?;
+
+        let get_i64 = |key: &str| {
+            rows.first()
+                .and_then(|row| row.iter().find(|(k, _)| k == key))
+                .and_then(|(_, v)| v.as_i64())
+                .unwrap_or(0)
+        };
+
+        Ok(BucketStats {
+            name: name.to_string(),
+            object_count: get_i64("object_count"),
+            logical_size: get_i64("logical_size"),
+            stored_size: get_i64("stored_size"),
+            duplicate_size: get_i64("duplicate_size"),
+        })
+    }
+
     /// Check if bucket is public
     pub async fn is_bucket_public(&self, name: &str) -> VibeResult<bool> {
         let bucket = self.get_bucket(name).await?;
@@ -336,18 +954,51 @@ impl StorageService {
         mime_type: &str,
         owner_id: Option<i64>,
     ) -> VibeResult<StorageObject> {
+        let chunks = futures::stream::once(async { Ok::<_, Infallible>(Bytes::from(data)) });
+        self.upload_object_stream(bucket, path, chunks, mime_type, owner_id).await
+    }
+
+    /// Upload a file to a bucket as its chunks arrive
+    ///
+    /// Chunks are written to a temporary file next to the destination while
+    /// the size and SHA-256 checksum are computed. The size limit applies to
+    /// the running total. The complete file is compressed (file to file) if
+    /// the bucket asks for it and then renamed into place, so readers never
+    /// see a partial file. A failed upload leaves any existing object as it
+    /// was.
+    pub async fn upload_object_stream<S, E>(
+        &self,
+        bucket: &str,
+        path: &str,
+        chunks: S,
+        mime_type: &str,
+        owner_id: Option<i64>,
+    ) -> VibeResult<StorageObject>
+    where
+        S: Stream<Item = Result<Bytes, E>>,
+        E: std::fmt::Display,
+    {
+        let staged = self.stage_object(bucket, path, chunks, MAX_FILE_SIZE).await?;
+        self.commit_object(staged, mime_type, owner_id, None, true).await
+    }
+
+    /// Writes an upload to a temporary file beside its destination, ready
+    /// to be committed; dropping the result discards it
+    async fn stage_object<S, E>(
+        &self,
+        bucket: &str,
+        path: &str,
+        chunks: S,
+        max_size: usize,
+    ) -> VibeResult<StagedObject>
+    where
+        S: Stream<Item = Result<Bytes, E>>,
+        E: std::fmt::Display,
+    {
         // Validate inputs
-        let _ = self.get_bucket(bucket).await?;
+        let bucket_info = self.get_bucket(bucket).await?;
         self.validate_object_path(path)?;
 
-        // Check file size
-        if data.len() > MAX_FILE_SIZE {
-            return Err(VibeError::InvalidPayload(format!(
-                "File too large. Maximum size is {} bytes",
-                MAX_FILE_SIZE
-            )));
-        }
-
         // Ensure storage directory exists
         self.ensure_storage_dir().await?;
 
@@ -361,63 +1012,315 @@ impl StorageService {
                 .map_err(|e| VibeError::Storage(format!("Failed to create directory: {}", e)))?;
         }
 
-        // Write file
-        let mut file = fs::File::create(&file_path)
+        // Stream into a temporary file, checking the size as it grows
+        let upload = TempFile::beside(&file_path);
+        let mut file = fs::File::create(upload.path())
             .await
             .map_err(|e| VibeError::Storage(format!("Failed to create file: {}", e)))?;
-        
-        file.write_all(&data)
+        let mut checksum = digest::Context::new(&digest::SHA256);
+        let mut size = 0usize;
+        let mut chunks = std::pin::pin!(chunks);
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk.map_err(|e| VibeError::InvalidPayload(format!("Failed to read file: {}", e)))?;
+            size += chunk.len();
+            if size > max_size {
+                // A smaller limit is what is left of a multi-file upload's budget
+                return Err(if max_size < MAX_FILE_SIZE {
+                    VibeError::FileTooLarge {
+                        message: format!("Files of one request may total at most {} bytes", MAX_UPLOAD_SIZE),
+                        limit: MAX_UPLOAD_SIZE as u64,
+                    }
+                } else {
+                    VibeError::FileTooLarge {
+                        message: format!("Files may be at most {} bytes", max_size),
+                        limit: max_size as u64,
+                    }
+                });
+            }
+            checksum.update(&chunk);
+            file.write_all(&chunk)
+                .await
+                .map_err(|e| VibeError::Storage(format!("Failed to write file: {}", e)))?;
+        }
+        file.sync_all()
             .await
             .map_err(|e| VibeError::Storage(format!("Failed to write file: {}", e)))?;
+        drop(file);
+        let checksum: String = checksum.finish().as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+
+        // Compress off the async runtime; keep the original if it doesn't shrink
+        let (compression, stored, stored_size) = match bucket_info.compression {
+            Compression::None => (Compression::None, upload, size as u64),
+            codec => {
+                let compressed = TempFile::beside(&file_path);
+                let (source, target) = (upload.path().to_path_buf(), compressed.path().to_path_buf());
+                let compressed_size = tokio::task::spawn_blocking(move || codec.compress_file(&source, &target))
+                    .await
+                    .map_err(|e| VibeError::Internal(anyhow::anyhow!("Compression task failed: {}", e)))??;
+
+                if compressed_size < size as u64 {
+                    (codec, compressed, compressed_size)
+                } else {
+                    (Compression::None, upload, size as u64)
+                }
+            }
+        };
 
-        // Upsert metadata
-        let size = data.len() as i64;
-        self.store.execute(
-            r#"
-            INSERT INTO vibe_objects (bucket_name, path, size, mime_type, owner_id)
-            VALUES (?, ?, ?, ?, ?)
-            ON CONFLICT(bucket_name, path) DO UPDATE SET
-                size = excluded.size,
-                mime_type = excluded.mime_type,
-                updated_at = CURRENT_TIMESTAMP
-            "#
-            .to_string(),
-            vec![
-                SqlValue::Text(bucket.to_string()),
-                SqlValue::Text(path.to_string()),
-                SqlValue::Integer(size),
-                SqlValue::Text(mime_type.to_string()),
-                owner_id.map(SqlValue::Integer).unwrap_or(SqlValue::Null),
-            ],
-        ).await?;
-
-        info!("Uploaded object: {}/{} ({} bytes)", bucket, path, size);
-        self.get_object(bucket, path).await
+        Ok(StagedObject {
+            bucket: bucket.to_string(),
+            path: path.to_string(),
+            file_path,
+            file: stored,
+            size,
+            stored_size,
+            compression,
+            checksum,
+        })
     }
 
-    /// Get object metadata
-    pub async fn get_object(&self, bucket: &str, path: &str) -> VibeResult<StorageObject> {
-        let rows = self.store.query(
-            r#"
-            SELECT id, bucket_name, path, size, mime_type, owner_id, created_at, updated_at
-            FROM vibe_objects WHERE bucket_name = ? AND path = ?
-            "#
-            .to_string(),
-            vec![
-                SqlValue::Text(bucket.to_string()),
-                SqlValue::Text(path.to_string()),
-            ],
-        ).await?;
+    /// Moves a staged upload into place and records its metadata
+    ///
+    /// Without `overwrite` the upload fails with `Conflict` if the object
+    /// exists. The row is then inserted before the file is moved into
+    /// place, so of two such uploads racing for a path only one stores.
+    /// With it, uploads to one path take turns, each writing its row before
+    /// moving its file in, so the stored file always matches the row.
+    async fn commit_object(
+        &self,
+        staged: StagedObject,
+        mime_type: &str,
+        owner_id: Option<i64>,
+        metadata: Option<&Value>,
+        overwrite: bool,
+    ) -> VibeResult<StorageObject> {
+        let StagedObject { bucket, path, file_path, file, size, stored_size, compression, checksum } = staged;
+        let _quota = if self.quotas.is_enabled() {
+            Some(self.quota_lock.lock().await)
+        } else {
+            None
+        };
+        self.check_quota(&bucket, owner_id, &[(&path, size as u64)]).await?;
+        // Size, checksum and codec are recorded together
+        let params = vec![
+            SqlValue::Text(bucket.clone()),
+            SqlValue::Text(path.clone()),
+            SqlValue::Integer(size as i64),
+            SqlValue::Integer(stored_size as i64),
+            SqlValue::Text(compression.as_str().to_string()),
+            SqlValue::Text(checksum),
+            SqlValue::Text(mime_type.to_string()),
+            owner_id.map(SqlValue::Integer).unwrap_or(SqlValue::Null),
+            metadata.map(|m| SqlValue::Text(m.to_string())).unwrap_or(SqlValue::Null),
+        ];
+        let replaced = if overwrite {
+            let _object = self.lock_object(&bucket, &path).await;
+            let previous = match self.get_object(&bucket, &path).await {
+                Ok(object) => Some(object),
+                Err(VibeError::NotFound(_)) => None,
+                Err(e) => return Err(e),
+            };
+            // The row is written first; a file that then fails to move in
+            // leaves the row as it was
+            self.store.execute(
+                r#"
+                INSERT INTO vibe_objects (bucket_name, path, size, stored_size, compression, checksum, mime_type, owner_id, metadata)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT(bucket_name, path) DO UPDATE SET
+                    size = excluded.size,
+                    stored_size = excluded.stored_size,
+                    compression = excluded.compression,
+                    checksum = excluded.checksum,
+                    mime_type = excluded.mime_type,
+                    metadata = excluded.metadata,
+                    updated_at = CURRENT_TIMESTAMP
+                "#
+                .to_string(),
+                params,
+            ).await?;
+            if let Err(e) = file.persist(&file_path).await {
+                self.restore_row(&bucket, &path, previous.as_ref()).await?;
+                return Err(e);
+            }
+            previous.is_some()
+        } else {
+            let inserted = self.store.execute(
+                r#"
+                INSERT INTO vibe_objects (bucket_name, path, size, stored_size, compression, checksum, mime_type, owner_id, metadata)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT(bucket_name, path) DO NOTHING
+                "#
+                .to_string(),
+                params,
+            ).await?;
+            if inserted == 0 {
+                return Err(already_exists(&bucket, &path));
+            }
+            if let Err(e) = file.persist(&file_path).await {
+                self.store.execute(
+                    "DELETE FROM vibe_objects WHERE bucket_name = ? AND path = ?".to_string(),
+                    vec![SqlValue::Text(bucket.clone()), SqlValue::Text(path.clone())],
+                ).await?;
+                return Err(e);
+            }
+            false
+        };
 
-        if rows.is_empty() {
-            return Err(VibeError::NotFound("Object not found".to_string()));
-        }
+        info!(
+            "Uploaded object: {}/{} ({} bytes, {} stored as {}, owner {:?})",
+            bucket, path, size, stored_size, compression.as_str(), owner_id
+        );
+        let object = self.get_object(&bucket, &path).await?;
+        let change = if replaced { StorageChange::ObjectUpdated } else { StorageChange::ObjectCreated };
+        let bucket = self.get_bucket(&bucket).await?;
+        let _ = self.events.send(StorageEvent::for_object(change, &bucket, &object));
+        Ok(object)
+    }
 
-        self.row_to_object(&rows[0])
+    /// Holds `path` of `bucket` against other uploads to it
+    async fn lock_object(&self, bucket: &str, path: &str) -> ObjectLock {
+        let key = (bucket.to_string(), path.to_string());
+        let lock = Arc::clone(self.object_locks.entry(key.clone()).or_default().value());
+        ObjectLock {
+            locks: Arc::clone(&self.object_locks),
+            key,
+            _held: lock.lock_owned().await,
+        }
     }
 
-    /// Download a file
+    /// Puts an object's row back as it was before a failed replacement, or
+    /// removes it if the object didn't exist
+    async fn restore_row(&self, bucket: &str, path: &str, previous: Option<&StorageObject>) -> VibeResult<()> {
+        let key = [SqlValue::Text(bucket.to_string()), SqlValue::Text(path.to_string())];
+        let Some(previous) = previous else {
+            self.store
+                .execute("DELETE FROM vibe_objects WHERE bucket_name = ? AND path = ?".to_string(), key.to_vec())
+                .await?;
+            return Ok(());
+        };
+        let mut params = vec![
+            SqlValue::Integer(previous.size),
+            SqlValue::Integer(previous.stored_size),
+            SqlValue::Text(previous.compression.as_str().to_string()),
+            previous.checksum.clone().map(SqlValue::Text).unwrap_or(SqlValue::Null),
+            SqlValue::Text(previous.mime_type.clone()),
+            previous.metadata.as_ref().map(|m| SqlValue::Text(m.to_string())).unwrap_or(SqlValue::Null),
+            SqlValue::Text(previous.updated_at.clone()),
+        ];
+        params.extend(key);
+        self.store
+            .execute(
+                "UPDATE vibe_objects SET size = ?, stored_size = ?, compression = ?, checksum = ?, \
+                 mime_type = ?, metadata = ?, updated_at = ? WHERE bucket_name = ? AND path = ?"
+                    .to_string(),
+                params,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Bytes stored by `owner_id`, or by everyone with `None`
+    pub async fn storage_usage(&self, owner_id: Option<i64>) -> VibeResult<u64> {
+        let (sql, params) = match owner_id {
+            Some(id) => (
+                "SELECT COALESCE(SUM(size), 0) AS used FROM vibe_objects WHERE owner_id = ?",
+                vec![SqlValue::Integer(id)],
+            ),
+            None => ("SELECT COALESCE(SUM(size), 0) AS used FROM vibe_objects", Vec::new()),
+        };
+        let rows = self.store.query(sql.to_string(), params).await?;
+        Ok(rows
+            .first()
+            .and_then(|row| row.first())
+            .and_then(|(_, v)| v.as_i64())
+            .unwrap_or(0) as u64)
+    }
+
+    /// Fails with `QuotaExceeded` if storing `files` (path and size) in
+    /// `bucket` for `owner_id` would go past a quota
+    ///
+    /// Objects the files replace free their size. The per-user quota
+    /// applies to uploads with an owner, the global one to all.
+    pub async fn check_quota(&self, bucket: &str, owner_id: Option<i64>, files: &[(&str, u64)]) -> VibeResult<()> {
+        if !self.quotas.is_enabled() {
+            return Ok(());
+        }
+
+        let (mut added, mut added_by_owner) = (0i64, 0i64);
+        for (path, size) in files {
+            let replaced = match self.get_object(bucket, path).await {
+                Ok(object) => Some(object),
+                Err(VibeError::NotFound(_)) => None,
+                Err(e) => return Err(e),
+            };
+            let freed = replaced.as_ref().map_or(0, |object| object.size);
+            added += *size as i64 - freed;
+            // A replaced object keeps its owner; only its owner gets the space back
+            let freed_by_owner = replaced
+                .filter(|object| owner_id.is_some() && object.owner_id == owner_id)
+                .map_or(0, |object| object.size);
+            added_by_owner += *size as i64 - freed_by_owner;
+        }
+
+        let checks = [
+            ("global", self.quotas.global, None, added),
+            ("user", self.quotas.per_user.filter(|_| owner_id.is_some()), owner_id, added_by_owner),
+        ];
+        for (scope, limit, owner, requested) in checks {
+            let Some(limit) = limit else {
+                continue;
+            };
+            if requested <= 0 {
+                continue;
+            }
+            let used = self.storage_usage(owner).await?;
+            if used + requested as u64 > limit {
+                warn!(
+                    target: "vibedb::security",
+                    "Upload to {} refused: {} quota ({} of {} bytes used, owner {:?})",
+                    bucket, scope, used, limit, owner_id
+                );
+                return Err(VibeError::QuotaExceeded {
+                    scope,
+                    used,
+                    requested: requested as u64,
+                    limit,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Get object metadata
+    pub async fn get_object(&self, bucket: &str, path: &str) -> VibeResult<StorageObject> {
+        let rows = self.store.query(
+            r#"
+            SELECT id, bucket_name, path, size, stored_size, compression, checksum, mime_type, metadata, owner_id, created_at, updated_at
+            FROM vibe_objects WHERE bucket_name = ? AND path = ?
+            "#
+            .to_string(),
+            vec![
+                SqlValue::Text(bucket.to_string()),
+                SqlValue::Text(path.to_string()),
+            ],
+        ).await?;
+
+        if rows.is_empty() {
+            return Err(VibeError::NotFound("Object not found".to_string()));
+        }
+
+        self.row_to_object(&rows[0])
+    }
+
+    /// Download a file, decompressed
     pub async fn download_object(&self, bucket: &str, path: &str) -> VibeResult<(Vec<u8>, String)> {
+        let (data, object) = self.read_stored_object(bucket, path).await?;
+        let data = decompress_blocking(object.compression, data).await?;
+
+        Ok((data, object.mime_type))
+    }
+
+    /// Read a file as stored on disk, along with its metadata
+    pub async fn read_stored_object(&self, bucket: &str, path: &str) -> VibeResult<(Vec<u8>, StorageObject)> {
         let object = self.get_object(bucket, path).await?;
         let file_path = self.get_file_path(bucket, path);
 
@@ -425,12 +1328,13 @@ impl StorageService {
             .await
             .map_err(|e| VibeError::Storage(format!("Failed to read file: {}", e)))?;
 
-        Ok((data, object.mime_type))
+        Ok((data, object))
     }
 
     /// Delete an object
     pub async fn delete_object(&self, bucket: &str, path: &str) -> VibeResult<()> {
-        let _ = self.get_object(bucket, path).await?;
+        let object = self.get_object(bucket, path).await?;
+        let bucket_info = self.get_bucket(bucket).await?;
         let file_path = self.get_file_path(bucket, path);
 
         // Delete file
@@ -449,53 +1353,213 @@ impl StorageService {
             ],
         ).await?;
 
-        info!("Deleted object: {}/{}", bucket, path);
+        debug!("Deleted object: {}/{}", bucket, path);
+        let _ = self.events.send(StorageEvent::for_object(StorageChange::ObjectDeleted, &bucket_info, &object));
         Ok(())
     }
 
+    /// Merges `patch` into an object's metadata and bumps its `updated_at`
+    ///
+    /// Keys set to `null` are removed and nested objects are merged, as in
+    /// a JSON merge patch (RFC 7396). The file itself is not touched.
+    pub async fn update_metadata(
+        &self,
+        bucket: &str,
+        path: &str,
+        patch: serde_json::Map<String, Value>,
+    ) -> VibeResult<StorageObject> {
+        let bucket_info = self.get_bucket(bucket).await?;
+        let key = [SqlValue::Text(bucket.to_string()), SqlValue::Text(path.to_string())];
+        // Read, merge and write in one transaction, so concurrent patches
+        // of the same object don't drop each other's keys
+        let merged_size = self
+            .store
+            .with_transaction(move |conn| {
+                let current: Option<Option<String>> = conn
+                    .query_row(
+                        "SELECT metadata FROM vibe_objects WHERE bucket_name = ? AND path = ?",
+                        rusqlite::params_from_iter(key.iter()),
+                        |row| row.get(0),
+                    )
+                    .map(Some)
+                    .or_else(|e| match e {
+                        rusqlite::Error::QueryReturnedNoRows => Ok(None),
+                        e => Err(e),
+                    })?;
+                let Some(current) = current else {
+                    return Ok(None);
+                };
+                let mut metadata = current
+                    .and_then(|text| serde_json::from_str(&text).ok())
+                    .filter(Value::is_object)
+                    .unwrap_or_else(|| json!({}));
+                merge_patch(&mut metadata, Value::Object(patch));
+                let merged = metadata.to_string();
+                if merged.len() <= MAX_METADATA_SIZE {
+                    conn.execute(
+                        "UPDATE vibe_objects SET metadata = ?, updated_at = CURRENT_TIMESTAMP WHERE bucket_name = ? AND path = ?",
+                        rusqlite::params![merged, &key[0], &key[1]],
+                    )?;
+                }
+                Ok(Some(merged.len()))
+            })
+            .await?;
+        match merged_size {
+            None => return Err(VibeError::NotFound("Object not found".to_string())),
+            Some(size) if size > MAX_METADATA_SIZE => {
+                return Err(VibeError::InvalidPayload(format!(
+                    "Object metadata exceeds {} bytes",
+                    MAX_METADATA_SIZE
+                )))
+            }
+            Some(_) => {}
+        }
+
+        debug!("Updated metadata of {}/{}", bucket, path);
+        let object = self.get_object(bucket, path).await?;
+        let _ = self.events.send(StorageEvent::for_object(StorageChange::MetadataUpdated, &bucket_info, &object));
+        Ok(object)
+    }
+
     /// List objects in a bucket
     pub async fn list_objects(&self, bucket: &str, query: ListObjectsQuery) -> VibeResult<Vec<StorageObject>> {
         let _ = self.get_bucket(bucket).await?;
 
-        let (sql, params) = if let Some(prefix) = query.prefix {
-            (
-                r#"
-                SELECT id, bucket_name, path, size, mime_type, owner_id, created_at, updated_at
-                FROM vibe_objects 
-                WHERE bucket_name = ? AND path LIKE ?
-                ORDER BY path
-                LIMIT ? OFFSET ?
-                "#
-                .to_string(),
-                vec![
-                    SqlValue::Text(bucket.to_string()),
-                    SqlValue::Text(format!("{}%", prefix)),
-                    SqlValue::Integer(query.limit),
-                    SqlValue::Integer(query.offset),
-                ],
-            )
-        } else {
-            (
-                r#"
-                SELECT id, bucket_name, path, size, mime_type, owner_id, created_at, updated_at
-                FROM vibe_objects 
-                WHERE bucket_name = ?
-                ORDER BY path
-                LIMIT ? OFFSET ?
-                "#
-                .to_string(),
-                vec![
-                    SqlValue::Text(bucket.to_string()),
-                    SqlValue::Integer(query.limit),
-                    SqlValue::Integer(query.offset),
-                ],
-            )
-        };
+        let mut conditions = vec!["bucket_name = ?"];
+        let mut params = vec![SqlValue::Text(bucket.to_string())];
+        if let Some(prefix) = &query.prefix {
+            conditions.push("path LIKE ?");
+            params.push(SqlValue::Text(format!("{}%", prefix)));
+        }
+        for (condition, value) in query.time_filters()? {
+            conditions.push(condition);
+            params.push(value);
+        }
+        params.push(SqlValue::Integer(query.limit));
+        params.push(SqlValue::Integer(query.offset));
+        let sql = format!(
+            r#"
+            SELECT id, bucket_name, path, size, stored_size, compression, checksum, mime_type, metadata, owner_id, created_at, updated_at
+            FROM vibe_objects
+            WHERE {}
+            ORDER BY path
+            LIMIT ? OFFSET ?
+            "#,
+            conditions.join(" AND ")
+        );
 
         let rows = self.store.query(sql, params).await?;
         rows.iter().map(|row| self.row_to_object(row)).collect()
     }
 
+    /// Tar archive of a bucket's objects under `prefix`, built as it is sent
+    ///
+    /// Objects are listed a page at a time and their files read in chunks,
+    /// decompressed on the blocking pool, so memory use stays flat however
+    /// large the bucket. With `owner` only that user's objects are
+    /// included. Objects whose file is missing are skipped; an object that
+    /// changes size while it is read aborts the archive.
+    pub fn archive_objects(
+        &self,
+        bucket: &str,
+        prefix: Option<String>,
+        owner: Option<i64>,
+    ) -> impl Stream<Item = std::io::Result<Bytes>> + Send + 'static {
+        let service = self.clone();
+        let bucket = bucket.to_string();
+        async_stream::stream! {
+            let mut offset = 0;
+            let mut archived = 0usize;
+            loop {
+                let query = ListObjectsQuery {
+                    prefix: prefix.clone(),
+                    limit: ARCHIVE_PAGE_SIZE,
+                    offset,
+                    ..Default::default()
+                };
+                let page = match service.list_objects(&bucket, query).await {
+                    Ok(page) => page,
+                    Err(e) => {
+                        warn!("Archive of {} aborted: {}", bucket, e);
+                        yield Err(std::io::Error::other(e.to_string()));
+                        return;
+                    }
+                };
+                offset += page.len() as i64;
+                let last_page = (page.len() as i64) < ARCHIVE_PAGE_SIZE;
+
+                for object in page {
+                    // LIKE treats `_` and `%` in the prefix as wildcards
+                    let outside = prefix.as_deref().is_some_and(|p| !object.path.starts_with(p));
+                    if outside || owner.is_some_and(|id| object.owner_id != Some(id)) {
+                        continue;
+                    }
+                    let file = match fs::File::open(service.get_file_path(&bucket, &object.path)).await {
+                        Ok(file) => file.into_std().await,
+                        Err(e) => {
+                            warn!("Archive of {} skips {}: {}", bucket, object.path, e);
+                            continue;
+                        }
+                    };
+                    // The open file is the version sent, even if it is replaced meanwhile
+                    let size = match object.compression {
+                        Compression::None => match file.metadata() {
+                            Ok(metadata) => metadata.len(),
+                            Err(e) => {
+                                yield Err(e);
+                                return;
+                            }
+                        },
+                        _ => object.size as u64,
+                    };
+
+                    match tar_header(&object.path, size, unix_timestamp(&object.updated_at)) {
+                        Ok(header) => yield Ok(header),
+                        Err(e) => {
+                            warn!("Archive of {} aborted: {}", bucket, e);
+                            yield Err(e);
+                            return;
+                        }
+                    }
+                    let mut chunks = read_chunks(file, object.compression);
+                    let mut written = 0u64;
+                    while let Some(chunk) = chunks.recv().await {
+                        let chunk = match chunk {
+                            Ok(chunk) if written + chunk.len() as u64 <= size => chunk,
+                            Ok(_) => {
+                                warn!("Archive of {} aborted: {} grew while read", bucket, object.path);
+                                yield Err(std::io::Error::other(format!("{} changed while archived", object.path)));
+                                return;
+                            }
+                            Err(e) => {
+                                warn!("Archive of {} aborted: {}", bucket, e);
+                                yield Err(e);
+                                return;
+                            }
+                        };
+                        written += chunk.len() as u64;
+                        yield Ok(chunk);
+                    }
+                    if written != size {
+                        warn!("Archive of {} aborted: {} shrank while read", bucket, object.path);
+                        yield Err(std::io::Error::other(format!("{} changed while archived", object.path)));
+                        return;
+                    }
+                    yield Ok(tar_padding(size));
+                    archived += 1;
+                }
+
+                if last_page {
+                    break;
+                }
+            }
+
+            // Two empty blocks end the archive
+            yield Ok(Bytes::from_static(&[0; 2 * TAR_BLOCK]));
+            info!("📦 Archived {} objects of bucket {}", archived, bucket);
+        }
+    }
+
     // ========================================================================
     // Helpers
     // ========================================================================
@@ -524,6 +1588,7 @@ impl StorageService {
             id: get_i64("id")?,
             name: get_str("name")?,
             public: get_i64("public")? == 1,
+            compression: Compression::parse(&get_str("compression")?)?,
             created_at: get_str("created_at")?,
             owner_id,
         })
@@ -549,12 +1614,24 @@ impl StorageService {
             .find(|(k, _)| k == "owner_id")
             .and_then(|(_, v)| v.as_i64());
 
+        let size = get_i64("size")?;
+
         Ok(StorageObject {
             id: get_i64("id")?,
             bucket_name: get_str("bucket_name")?,
             path: get_str("path")?,
-            size: get_i64("size")?,
+            size,
+            // Objects uploaded before compression support have no stored_size
+            stored_size: get_i64("stored_size").unwrap_or(size),
+            compression: Compression::parse(&get_str("compression")?)?,
+            checksum: get_str("checksum").ok(),
             mime_type: get_str("mime_type")?,
+            // The store hands JSON text back already parsed
+            metadata: row
+                .iter()
+                .find(|(k, _)| k == "metadata")
+                .map(|(_, v)| v.clone())
+                .filter(Value::is_object),
             created_at: get_str("created_at")?,
             updated_at: get_str("updated_at")?,
             owner_id,
@@ -562,6 +1639,132 @@ impl StorageService {
     }
 }
 
+/// Size of a tar block
+const TAR_BLOCK: usize = 512;
+
+/// ustar header block(s) of a regular file
+///
+/// Paths longer than the 100 bytes of the name field are preceded by a
+/// GNU `././@LongLink` entry, which GNU tar, bsdtar and 7-Zip all read.
+fn tar_header(path: &str, size: u64, mtime: i64) -> std::io::Result<Bytes> {
+    let mut out = Vec::with_capacity(3 * TAR_BLOCK);
+    if path.len() > 100 {
+        let name = [path.as_bytes(), &[0]].concat();
+        out.extend_from_slice(&tar_header_block("././@LongLink", name.len() as u64, 0, b'L')?);
+        out.extend_from_slice(&name);
+        out.extend_from_slice(&tar_padding(name.len() as u64));
+    }
+    out.extend_from_slice(&tar_header_block(path, size, mtime, b'0')?);
+    Ok(Bytes::from(out))
+}
+
+fn tar_header_block(path: &str, size: u64, mtime: i64, kind: u8) -> std::io::Result<[u8; TAR_BLOCK]> {
+    // Eleven octal digits hold sizes up to 8 GiB
+    if size >= 1 << 33 {
+        return Err(std::io::Error::other(format!("{} is too large for a tar entry", path)));
+    }
+    let mut block = [0u8; TAR_BLOCK];
+    let name = path.as_bytes();
+    let name = &name[..name.len().min(100)];
+    block[..name.len()].copy_from_slice(name);
+    let mut field = |offset: usize, value: &str| block[offset..offset + value.len()].copy_from_slice(value.as_bytes());
+    field(100, "0000644");
+    field(108, "0000000");
+    field(116, "0000000");
+    field(124, &format!("{:011o}", size));
+    field(136, &format!("{:011o}", mtime.clamp(0, 0o77777777777)));
+    field(257, "ustar\0");
+    field(263, "00");
+    block[156] = kind;
+
+    // The checksum is taken with its own field as spaces
+    block[148..156].fill(b' ');
+    let checksum: u32 = block.iter().map(|&b| b as u32).sum();
+    block[148..155].copy_from_slice(format!("{:06o}\0", checksum).as_bytes());
+    Ok(block)
+}
+
+/// Zeros completing the last block of `size` bytes of content
+fn tar_padding(size: u64) -> Bytes {
+    let remainder = (size % TAR_BLOCK as u64) as usize;
+    Bytes::from(vec![0; (TAR_BLOCK - remainder) % TAR_BLOCK])
+}
+
+/// Seconds since the epoch of a SQLite `CURRENT_TIMESTAMP`
+fn unix_timestamp(timestamp: &str) -> i64 {
+    chrono::NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S")
+        .map(|t| t.and_utc().timestamp())
+        .unwrap_or(0)
+}
+
+/// Reads a stored file on the blocking pool, decompressed, in chunks
+fn read_chunks(file: std::fs::File, compression: Compression) -> tokio::sync::mpsc::Receiver<std::io::Result<Bytes>> {
+    let (tx, rx) = tokio::sync::mpsc::channel(4);
+    tokio::task::spawn_blocking(move || {
+        let mut reader: Box<dyn Read> = match compression {
+            Compression::None => Box::new(file),
+            Compression::Gzip => Box::new(flate2::read::GzDecoder::new(file)),
+            Compression::Zstd => match zstd::Decoder::new(file) {
+                Ok(decoder) => Box::new(decoder),
+                Err(e) => {
+                    let _ = tx.blocking_send(Err(e));
+                    return;
+                }
+            },
+        };
+        loop {
+            let mut chunk = vec![0; ARCHIVE_CHUNK_SIZE];
+            let read = match reader.read(&mut chunk) {
+                Ok(0) => return,
+                Ok(read) => read,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    let _ = tx.blocking_send(Err(e));
+                    return;
+                }
+            };
+            chunk.truncate(read);
+            // A closed channel means the client went away
+            if tx.blocking_send(Ok(Bytes::from(chunk))).is_err() {
+                return;
+            }
+        }
+    });
+    rx
+}
+
+/// Decompress on the blocking pool
+async fn decompress_blocking(compression: Compression, data: Vec<u8>) -> VibeResult<Vec<u8>> {
+    if compression == Compression::None {
+        return Ok(data);
+    }
+    tokio::task::spawn_blocking(move || compression.decompress(&data))
+        .await
+        .map_err(|e| VibeError::Internal(anyhow::anyhow!("Decompression task failed: {}", e)))?
+}
+
+/// Whether an `Accept-Encoding` header allows the given coding
+fn accepts_encoding(headers: &HeaderMap, encoding: &str) -> bool {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|coding| {
+            let mut parts = coding.split(';');
+            let name = parts.next().unwrap_or("").trim();
+            // An explicit q=0 means "not acceptable"
+            let refused = parts.any(|p| {
+                p.trim()
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .map(|q| q == 0.0)
+                    .unwrap_or(false)
+            });
+            name.eq_ignore_ascii_case(encoding) && !refused
+        })
+}
+
 // ============================================================================
 // API Handlers
 // ============================================================================
@@ -570,14 +1773,219 @@ impl StorageService {
 #[derive(Clone)]
 pub struct StorageState {
     pub storage: StorageService,
+    /// Identifies callers for `owner_id` and guards private buckets; `None`
+    /// stores every write as anonymous and leaves every bucket readable
+    pub auth: Option<AuthService>,
+    /// Build URLs from `X-Forwarded-Proto`/`X-Forwarded-Host`
+    pub trust_proxy: bool,
+    /// Prefix of the routes, put between the host and `/v1/storage` in URLs
+    pub base_path: BasePathConfig,
+}
+
+impl StorageState {
+    /// How the objects of `bucket` are linked for this request
+    ///
+    /// Public buckets (and every bucket without auth) get plain paths.
+    /// Private buckets get `null`, or with `sign` URLs carrying `expires`
+    /// and `signature`, which only the bucket owner or an admin may mint.
+    fn object_urls(
+        &self,
+        headers: &HeaderMap,
+        bucket: &Bucket,
+        caller: Option<&AuthUser>,
+        params: &ObjectUrlParams,
+    ) -> VibeResult<ObjectUrls<'_>> {
+        let forwarded = headers.get(FORWARDED_PREFIX).and_then(|v| v.to_str().ok());
+        let base = base_url(headers, self.trust_proxy) + &self.base_path.public_prefix(forwarded);
+        let Some(auth) = self.auth.as_ref().filter(|_| !bucket.public) else {
+            return Ok(ObjectUrls::Plain { base });
+        };
+        if !params.sign {
+            return Ok(ObjectUrls::Private);
+        }
+
+        match caller {
+            None => {
+                return Err(VibeError::Unauthorized(
+                    "Signing URLs of a private bucket requires a token".to_string(),
+                ))
+            }
+            Some(user) if !user.is_admin() && bucket.owner_id != Some(user.id) => {
+                return Err(VibeError::Forbidden(format!(
+                    "Only the owner of bucket '{}' or an admin can sign its URLs",
+                    bucket.name
+                )))
+            }
+            Some(_) => {}
+        }
+        let ttl = params
+            .expires_in
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_SIGNED_URL_TTL);
+        if ttl > MAX_SIGNED_URL_TTL {
+            return Err(VibeError::InvalidPayload(format!(
+                "expires_in may be at most {} seconds",
+                MAX_SIGNED_URL_TTL.as_secs()
+            )));
+        }
+        Ok(ObjectUrls::Signed {
+            base,
+            auth,
+            expires: self.storage.clock.unix_secs() + ttl.as_secs(),
+        })
+    }
+
+    /// Lets only an admin or the bucket owner change `bucket` or see its
+    /// totals, when auth is on
+    fn authorize_manage(&self, bucket: &Bucket, caller: Option<&AuthUser>) -> VibeResult<()> {
+        if self.auth.is_none() {
+            return Ok(());
+        }
+        match caller {
+            None => Err(VibeError::Unauthorized("Missing or invalid token".to_string())),
+            Some(user) if user.is_admin() || bucket.owner_id == Some(user.id) => Ok(()),
+            Some(_) => Err(VibeError::Forbidden(format!(
+                "Only the owner of bucket '{}' or an admin can manage it",
+                bucket.name
+            ))),
+        }
+    }
+
+    /// Lets a private bucket's object be read with a valid signed URL, or
+    /// by an admin, the bucket owner or the object owner
+    async fn authorize_read(
+        &self,
+        bucket: &Bucket,
+        path: &str,
+        caller: Option<&AuthUser>,
+        signed: &SignedUrlParams,
+    ) -> VibeResult<()> {
+        let Some(auth) = self.auth.as_ref().filter(|_| !bucket.public) else {
+            return Ok(());
+        };
+
+        if let (Some(expires), Some(signature)) = (signed.expires, signed.signature.as_deref()) {
+            let message = signing_message(&bucket.name, path, expires);
+            if expires >= self.storage.clock.unix_secs() && auth.verify_url(&message, signature) {
+                return Ok(());
+            }
+            return Err(VibeError::Forbidden("Invalid or expired signed URL".to_string()));
+        }
+
+        let Some(user) = caller else {
+            return Err(VibeError::Unauthorized(format!(
+                "Bucket '{}' is private; send a token or use a signed URL",
+                bucket.name
+            )));
+        };
+        if user.is_admin() || bucket.owner_id == Some(user.id) {
+            return Ok(());
+        }
+        let object = self.storage.get_object(&bucket.name, path).await?;
+        if object.owner_id == Some(user.id) {
+            return Ok(());
+        }
+        Err(VibeError::Forbidden(format!(
+            "No read access to private bucket '{}'",
+            bucket.name
+        )))
+    }
+}
+
+/// Download URLs of one bucket's objects
+enum ObjectUrls<'a> {
+    Plain { base: String },
+    Signed { base: String, auth: &'a AuthService, expires: u64 },
+    Private,
+}
+
+impl ObjectUrls<'_> {
+    fn url(&self, object: &StorageObject) -> Option<String> {
+        let path = || {
+            format!(
+                "/v1/storage/object/{}/{}",
+                object.bucket_name,
+                encode_path(&object.path)
+            )
+        };
+        match self {
+            ObjectUrls::Plain { base } => Some(format!("{}{}", base, path())),
+            ObjectUrls::Signed { base, auth, expires } => {
+                let signature = auth.sign_url(&signing_message(&object.bucket_name, &object.path, *expires));
+                Some(format!(
+                    "{}{}?expires={}&signature={}",
+                    base,
+                    path(),
+                    expires,
+                    signature
+                ))
+            }
+            ObjectUrls::Private => None,
+        }
+    }
+
+    fn attach(&self, object: StorageObject) -> ObjectWithUrl {
+        ObjectWithUrl {
+            url: self.url(&object),
+            object,
+        }
+    }
+}
+
+/// What a signed URL's signature covers
+fn signing_message(bucket: &str, path: &str, expires: u64) -> String {
+    format!("{}/{}\n{}", bucket, path, expires)
+}
+
+/// Scheme and host clients reached the server by, e.g.
+/// `https://files.example.com`
+///
+/// The forwarded headers are only believed with `trust_proxy`; they are
+/// client-controlled otherwise. Without any host the URLs stay relative.
+fn base_url(headers: &HeaderMap, trust_proxy: bool) -> String {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(',').next())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    };
+    let forwarded = |name: &str| header(name).filter(|_| trust_proxy);
+
+    match forwarded("x-forwarded-host").or_else(|| header(header::HOST.as_str())) {
+        Some(host) => format!("{}://{}", forwarded("x-forwarded-proto").unwrap_or("http"), host),
+        None => String::new(),
+    }
+}
+
+/// Percent-encodes an object path for a URL, keeping its `/` separators
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+impl FromRef<StorageState> for Option<AuthService> {
+    fn from_ref(state: &StorageState) -> Self {
+        state.auth.clone()
+    }
 }
 
 /// POST /v1/storage/buckets - Create bucket
 async fn create_bucket_handler(
     State(state): State<StorageState>,
+    caller: Option<AuthUser>,
     Json(req): Json<CreateBucketRequest>,
 ) -> Result<impl IntoResponse, VibeError> {
-    let bucket = state.storage.create_bucket(req, None).await?;
+    let bucket = state.storage.create_bucket(req, caller.as_ref().map(|user| user.id)).await?;
     Ok((StatusCode::CREATED, Json(json!({
         "success": true,
         "data": bucket
@@ -607,12 +2015,102 @@ async fn get_bucket_handler(
     })))
 }
 
+/// PATCH /v1/storage/buckets/:name - Update bucket settings
+///
+/// Only admins and the bucket owner may, when auth is on.
+async fn update_bucket_handler(
+    State(state): State<StorageState>,
+    Path(name): Path<String>,
+    caller: Option<AuthUser>,
+    Json(req): Json<UpdateBucketRequest>,
+) -> Result<impl IntoResponse, VibeError> {
+    let bucket = state.storage.get_bucket(&name).await?;
+    state.authorize_manage(&bucket, caller.as_ref())?;
+    let bucket = state.storage.update_bucket(&name, req).await?;
+    Ok(Json(json!({
+        "success": true,
+        "data": bucket
+    })))
+}
+
+/// GET /v1/storage/buckets/:name/stats - Object count and sizes
+///
+/// Totals of private buckets are for admins and the bucket owner.
+async fn bucket_stats_handler(
+    State(state): State<StorageState>,
+    Path(name): Path<String>,
+    caller: Option<AuthUser>,
+) -> Result<impl IntoResponse, VibeError> {
+    let bucket = state.storage.get_bucket(&name).await?;
+    if !bucket.public {
+        state.authorize_manage(&bucket, caller.as_ref())?;
+    }
+    let stats = state.storage.bucket_stats(&name).await?;
+    Ok(Json(json!({
+        "success": true,
+        "data": stats
+    })))
+}
+
+/// Query of a bucket archive
+#[derive(Debug, Default, Deserialize)]
+pub struct ArchiveParams {
+    /// Only archive objects whose path starts with this
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+/// GET /v1/storage/buckets/:name/archive - Download objects as a tar archive
+///
+/// Readers of a private bucket other than admins and the bucket owner get
+/// only the objects they own. The archive is streamed as it is built, so
+/// a failure part way through ends the download early.
+async fn archive_handler(
+    State(state): State<StorageState>,
+    Path(name): Path<String>,
+    caller: Option<AuthUser>,
+    Query(params): Query<ArchiveParams>,
+) -> Result<impl IntoResponse, VibeError> {
+    let bucket = state.storage.get_bucket(&name).await?;
+    let owner = match caller.as_ref() {
+        _ if bucket.public || state.auth.is_none() => None,
+        None => {
+            return Err(VibeError::Unauthorized(format!(
+                "Bucket '{}' is private; send a token",
+                bucket.name
+            )))
+        }
+        Some(user) if user.is_admin() || bucket.owner_id == Some(user.id) => None,
+        Some(user) => Some(user.id),
+    };
+    info!(
+        "📦 Archiving bucket {}{} (by {})",
+        bucket.name,
+        params.prefix.as_deref().map(|p| format!(" under {}", p)).unwrap_or_default(),
+        AuthUser::label(caller.as_ref())
+    );
+
+    let body = state.storage.archive_objects(&bucket.name, params.prefix, owner);
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-tar".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}.tar\"", bucket.name),
+            ),
+        ],
+        axum::body::Body::from_stream(body),
+    ))
+}
+
 /// DELETE /v1/storage/buckets/:name - Delete bucket
 async fn delete_bucket_handler(
     State(state): State<StorageState>,
     Path(name): Path<String>,
+    caller: Option<AuthUser>,
 ) -> Result<impl IntoResponse, VibeError> {
     state.storage.delete_bucket(&name).await?;
+    info!("🗑️ Deleted bucket {} (by {})", name, AuthUser::label(caller.as_ref()));
     Ok(Json(json!({
         "success": true,
         "message": "Bucket deleted"
@@ -620,64 +2118,347 @@ async fn delete_bucket_handler(
 }
 
 /// POST /v1/storage/object/:bucket/*path - Upload file
+///
+/// A path ending in `/` is a prefix: every file part of the request is
+/// stored under it by its filename, and the response lists the outcome of
+/// each (201 when all were stored, 207 otherwise). With `?strict=true` the
+/// first rejected file fails the request and none are stored. Any other
+/// path stores the `file` part there. A `metadata` part holding a JSON
+/// object is recorded with the file that follows it. Existing objects are
+/// replaced unless the request has `If-None-Match: *` or `?overwrite=false`,
+/// which make it fail with 409 instead. A single file is kept even if its
+/// URL can't be signed; the `url` is then null and `warnings` says why.
 async fn upload_handler(
     State(state): State<StorageState>,
     Path((bucket, path)): Path<(String, String)>,
+    caller: Option<AuthUser>,
+    Query(url_params): Query<ObjectUrlParams>,
+    Query(upload_params): Query<UploadParams>,
+    headers: HeaderMap,
     mut multipart: Multipart,
-) -> Result<impl IntoResponse, VibeError> {
-    // Get the file from multipart
-    let mut file_data: Option<(Vec<u8>, String)> = None;
-    
-    while let Some(field) = multipart
-        .next_field()
-        .await
-        .map_err(|e| VibeError::InvalidPayload(format!("Multipart error: {}", e)))?
-    {
-        if field.name() == Some("file") {
-            let mime_type = field
-                .content_type()
-                .map(|s| s.to_string())
-                .unwrap_or_else(|| "application/octet-stream".to_string());
-            
-            let data = field
-                .bytes()
-                .await
-                .map_err(|e| VibeError::InvalidPayload(format!("Failed to read file: {}", e)))?;
-            
-            file_data = Some((data.to_vec(), mime_type));
-            break;
+) -> Result<axum::response::Response, VibeError> {
+    if path.ends_with('/') {
+        return upload_many(state, bucket, path, caller, url_params, upload_params, headers, multipart).await;
+    }
+    // Fail fast before reading the body; the commit checks again atomically
+    let overwrite = upload_params.overwrite(&headers);
+    if !overwrite && state.storage.get_object(&bucket, &path).await.is_ok() {
+        return Err(already_exists(&bucket, &path));
+    }
+
+    // Stream the "file" field to storage without buffering it
+    let mut metadata = None;
+    let mut object = None;
+    while let Some(field) = next_part(&mut multipart).await? {
+        match field.name() {
+            Some("metadata") => metadata = Some(read_metadata(field).await?),
+            Some("file") => {
+                let mime_type = part_mime_type(&field);
+                let staged = state.storage.stage_object(&bucket, &path, field, MAX_FILE_SIZE).await?;
+                object = Some(
+                    state
+                        .storage
+                        .commit_object(
+                            staged,
+                            &mime_type,
+                            caller.as_ref().map(|user| user.id),
+                            metadata.as_ref(),
+                            overwrite,
+                        )
+                        .await?,
+                );
+                break;
+            }
+            _ => {}
         }
     }
 
-    let (data, mime_type) = file_data.ok_or_else(|| {
+    let object = object.ok_or_else(|| {
         VibeError::InvalidPayload("No file provided".to_string())
     })?;
+    // The file is stored by now, so a refused signature only costs the URL
+    let bucket = state.storage.get_bucket(&bucket).await?;
+    let (urls, warning) = match state.object_urls(&headers, &bucket, caller.as_ref(), &url_params) {
+        Ok(urls) => (urls, None),
+        Err(e) => (ObjectUrls::Private, Some(format!("URL not signed: {}", e))),
+    };
+
+    let mut response = json!({
+        "success": true,
+        "data": urls.attach(object)
+    });
+    if let Some(warning) = warning {
+        response["warnings"] = json!([warning]);
+    }
+    Ok((StatusCode::CREATED, Json(response)).into_response())
+}
 
-    let object = state
+/// POST /v1/storage/upload/*path - Upload file to the default bucket
+///
+/// Same as `upload_handler` for the configured default bucket; 404 when
+/// there is none.
+async fn upload_default_handler(
+    State(state): State<StorageState>,
+    Path(path): Path<String>,
+    caller: Option<AuthUser>,
+    url_params: Query<ObjectUrlParams>,
+    upload_params: Query<UploadParams>,
+    headers: HeaderMap,
+    multipart: Multipart,
+) -> Result<axum::response::Response, VibeError> {
+    let bucket = state
         .storage
-        .upload_object(&bucket, &path, data, &mime_type, None)
-        .await?;
+        .default_bucket()
+        .ok_or_else(|| VibeError::NotFound("No default bucket is configured".to_string()))?
+        .to_string();
+    upload_handler(State(state), Path((bucket, path)), caller, url_params, upload_params, headers, multipart).await
+}
 
-    Ok((StatusCode::CREATED, Json(json!({
-        "success": true,
-        "data": object
-    }))))
+/// A file part of a prefix upload, staged or rejected
+struct UploadedPart {
+    filename: String,
+    path: Option<String>,
+    mime_type: String,
+    metadata: Option<Value>,
+    staged: VibeResult<StagedObject>,
+}
+
+/// Stores every file part under the `prefix` directory (see `upload_handler`)
+#[allow(clippy::too_many_arguments)]
+async fn upload_many(
+    state: StorageState,
+    bucket: String,
+    prefix: String,
+    caller: Option<AuthUser>,
+    url_params: ObjectUrlParams,
+    upload_params: UploadParams,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Result<axum::response::Response, VibeError> {
+    let bucket = state.storage.get_bucket(&bucket).await?;
+    let urls = state.object_urls(&headers, &bucket, caller.as_ref(), &url_params)?;
+
+    let mut parts: Vec<UploadedPart> = Vec::new();
+    let mut metadata: Option<VibeResult<Value>> = None;
+    let mut total = 0usize;
+    while let Some(field) = next_part(&mut multipart).await? {
+        let Some(filename) = field.file_name().map(str::to_string) else {
+            if field.name() == Some("metadata") {
+                metadata = Some(read_metadata(field).await);
+            }
+            continue;
+        };
+        if parts.len() == MAX_FILES_PER_UPLOAD {
+            return Err(VibeError::InvalidPayload(format!(
+                "Too many files. At most {} may be uploaded at once",
+                MAX_FILES_PER_UPLOAD
+            )));
+        }
+
+        // Browsers may send a client-side path; only its last component is used
+        let name = filename.rsplit(['/', '\\']).next().unwrap_or("").trim().to_string();
+        let path = format!("{}{}", prefix, name);
+        let mime_type = part_mime_type(&field);
+        let (part_metadata, staged) = match metadata.take().transpose() {
+            Err(e) => (None, Err(e)),
+            Ok(_) if name.is_empty() => (None, Err(VibeError::InvalidPayload("File part has no filename".to_string()))),
+            Ok(part_metadata) => {
+                let limit = MAX_FILE_SIZE.min(MAX_UPLOAD_SIZE - total);
+                (part_metadata, state.storage.stage_object(&bucket.name, &path, field, limit).await)
+            }
+        };
+        if let Ok(staged) = &staged {
+            total += staged.size;
+        }
+        let staged = match staged {
+            Err(VibeError::InvalidPayload(message)) if upload_params.strict => {
+                return Err(VibeError::InvalidPayload(format!("{}: {}", filename, message)));
+            }
+            Err(VibeError::InvalidObjectPath(message)) if upload_params.strict => {
+                return Err(VibeError::InvalidObjectPath(format!("{}: {}", filename, message)));
+            }
+            Err(VibeError::FileTooLarge { message, limit }) if upload_params.strict => {
+                return Err(VibeError::FileTooLarge { message: format!("{}: {}", filename, message), limit });
+            }
+            Err(e) if upload_params.strict => return Err(e),
+            staged => staged,
+        };
+        parts.push(UploadedPart {
+            filename,
+            path: (!name.is_empty()).then_some(path),
+            mime_type,
+            metadata: part_metadata,
+            staged,
+        });
+    }
+    if parts.is_empty() {
+        return Err(VibeError::InvalidPayload("No file provided".to_string()));
+    }
+
+    // Files are moved into place only once the whole request has been read
+    let owner_id = caller.as_ref().map(|user| user.id);
+    let overwrite = upload_params.overwrite(&headers);
+    if upload_params.strict {
+        let files: Vec<(&str, u64)> = parts
+            .iter()
+            .filter_map(|part| part.staged.as_ref().ok())
+            .map(|staged| (staged.path.as_str(), staged.size as u64))
+            .collect();
+        state.storage.check_quota(&bucket.name, owner_id, &files).await?;
+        if !overwrite {
+            for (path, _) in &files {
+                if state.storage.get_object(&bucket.name, path).await.is_ok() {
+                    return Err(already_exists(&bucket.name, path));
+                }
+            }
+        }
+    }
+    let mut results = Vec::with_capacity(parts.len());
+    let mut stored = 0;
+    for part in parts {
+        let outcome = match part.staged {
+            Ok(staged) => {
+                state
+                    .storage
+                    .commit_object(staged, &part.mime_type, owner_id, part.metadata.as_ref(), overwrite)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        results.push(match outcome {
+            Ok(object) => {
+                stored += 1;
+                json!({
+                    "filename": part.filename,
+                    "path": part.path,
+                    "success": true,
+                    "data": urls.attach(object)
+                })
+            }
+            Err(e) => json!({
+                "filename": part.filename,
+                "path": part.path,
+                "success": false,
+                "error": {"code": e.error_code(), "message": e.to_string()}
+            }),
+        });
+    }
+
+    let all_stored = stored == results.len();
+    info!(
+        "📦 Stored {}/{} uploaded files under {}/{} (by {})",
+        stored,
+        results.len(),
+        bucket.name,
+        prefix,
+        AuthUser::label(caller.as_ref())
+    );
+    let status = if all_stored { StatusCode::CREATED } else { StatusCode::MULTI_STATUS };
+    Ok((status, Json(json!({
+        "success": all_stored,
+        "data": results
+    }))).into_response())
+}
+
+async fn next_part(multipart: &mut Multipart) -> VibeResult<Option<axum::extract::multipart::Field<'_>>> {
+    multipart
+        .next_field()
+        .await
+        .map_err(|e| VibeError::InvalidPayload(format!("Multipart error: {}", e)))
+}
+
+fn part_mime_type(field: &axum::extract::multipart::Field<'_>) -> String {
+    field
+        .content_type()
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| "application/octet-stream".to_string())
+}
+
+/// Refusal of an upload that may not replace an existing object
+fn already_exists(bucket: &str, path: &str) -> VibeError {
+    VibeError::Conflict(format!("Object '{}/{}' already exists", bucket, path))
+}
+
+/// Reads a `metadata` part, which must be a JSON object
+async fn read_metadata(mut field: axum::extract::multipart::Field<'_>) -> VibeResult<Value> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|e| VibeError::InvalidPayload(format!("Multipart error: {}", e)))?
+    {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() > MAX_METADATA_SIZE {
+            return Err(VibeError::InvalidPayload(format!(
+                "Object metadata exceeds {} bytes",
+                MAX_METADATA_SIZE
+            )));
+        }
+    }
+    match serde_json::from_slice(&bytes) {
+        Ok(Value::Object(map)) => Ok(Value::Object(map)),
+        _ => Err(VibeError::InvalidPayload("Object metadata must be a JSON object".to_string())),
+    }
+}
+
+/// Applies a JSON merge patch (RFC 7396) to `target`
+fn merge_patch(target: &mut Value, patch: Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch;
+        return;
+    };
+    if !target.is_object() {
+        *target = json!({});
+    }
+    let Value::Object(target) = target else { unreachable!() };
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(&key);
+        } else {
+            merge_patch(target.entry(key).or_insert(Value::Null), value);
+        }
+    }
 }
 
 /// GET /v1/storage/object/:bucket/*path - Download file
+///
+/// Objects of private buckets need a token of an admin, the bucket owner
+/// or the object owner, or an unexpired signed URL (`expires` and
+/// `signature`, see `list_objects_handler`).
+///
+/// Compressed files are sent as stored with `Content-Encoding` when the
+/// client accepts that coding, and decompressed server-side otherwise.
 async fn download_handler(
     State(state): State<StorageState>,
     Path((bucket, path)): Path<(String, String)>,
+    caller: Option<AuthUser>,
+    Query(signed): Query<SignedUrlParams>,
+    headers: HeaderMap,
 ) -> Result<impl IntoResponse, VibeError> {
-    let (data, mime_type) = state.storage.download_object(&bucket, &path).await?;
+    let bucket = state.storage.get_bucket(&bucket).await?;
+    state.authorize_read(&bucket, &path, caller.as_ref(), &signed).await?;
+    let (data, object) = state.storage.read_stored_object(&bucket.name, &path).await?;
+
+    let mut encoding_headers = HeaderMap::new();
+    let data = match object.compression.content_encoding() {
+        Some(encoding) if accepts_encoding(&headers, encoding) => {
+            encoding_headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding));
+            data
+        }
+        _ => decompress_blocking(object.compression, data).await?,
+    };
+    if object.compression != Compression::None {
+        encoding_headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+    }
 
     Ok((
         StatusCode::OK,
+        encoding_headers,
         [
-            (header::CONTENT_TYPE, mime_type),
+            (header::CONTENT_TYPE, object.mime_type),
             (
                 header::CONTENT_DISPOSITION,
-                format!("inline; filename=\"{}\"", path.split('/').last().unwrap_or(&path)),
+                format!("inline; filename=\"{}\"", path.rsplit('/').next().unwrap_or(&path)),
             ),
         ],
         data,
@@ -688,27 +2469,165 @@ async fn download_handler(
 async fn delete_object_handler(
     State(state): State<StorageState>,
     Path((bucket, path)): Path<(String, String)>,
+    caller: Option<AuthUser>,
 ) -> Result<impl IntoResponse, VibeError> {
     state.storage.delete_object(&bucket, &path).await?;
+    info!("🗑️ Deleted {}/{} (by {})", bucket, path, AuthUser::label(caller.as_ref()));
     Ok(Json(json!({
         "success": true,
         "message": "Object deleted"
     })))
 }
 
+/// PATCH /v1/storage/object/:bucket/*path/metadata - Update object metadata
+///
+/// Merges a JSON object into the object's metadata without re-uploading
+/// the file: keys set to `null` are removed, nested objects are merged.
+async fn update_metadata_handler(
+    State(state): State<StorageState>,
+    Path((bucket, path)): Path<(String, String)>,
+    caller: Option<AuthUser>,
+    body: Bytes,
+) -> Result<impl IntoResponse, VibeError> {
+    // PATCH is only routed for the metadata of an object
+    let path = path
+        .strip_suffix("/metadata")
+        .filter(|path| !path.is_empty())
+        .ok_or_else(|| VibeError::NotFound("Object not found".to_string()))?;
+    if body.len() > MAX_METADATA_SIZE {
+        return Err(VibeError::InvalidPayload(format!(
+            "Object metadata exceeds {} bytes",
+            MAX_METADATA_SIZE
+        )));
+    }
+    let patch = match serde_json::from_slice(&body) {
+        Ok(Value::Object(map)) => map,
+        _ => return Err(VibeError::InvalidPayload("Object metadata must be a JSON object".to_string())),
+    };
+    let object = state.storage.update_metadata(&bucket, path, patch).await?;
+    info!("Updated metadata of {}/{} (by {})", bucket, path, AuthUser::label(caller.as_ref()));
+    Ok(Json(json!({
+        "success": true,
+        "data": object
+    })))
+}
+
 /// GET /v1/storage/list/:bucket - List objects
+///
+/// Every object carries a download `url`: a plain path for public buckets
+/// and `null` for private ones. With `?sign=true` the bucket owner or an
+/// admin gets signed URLs instead, valid for `expires_in` seconds (15
+/// minutes by default, at most a day). URLs use `X-Forwarded-Proto` and
+/// `X-Forwarded-Host` when the server trusts its proxy.
 async fn list_objects_handler(
     State(state): State<StorageState>,
     Path(bucket): Path<String>,
+    caller: Option<AuthUser>,
     Query(query): Query<ListObjectsQuery>,
+    Query(url_params): Query<ObjectUrlParams>,
+    headers: HeaderMap,
 ) -> Result<impl IntoResponse, VibeError> {
-    let objects = state.storage.list_objects(&bucket, query).await?;
+    let bucket = state.storage.get_bucket(&bucket).await?;
+    let urls = state.object_urls(&headers, &bucket, caller.as_ref(), &url_params)?;
+    let objects = state.storage.list_objects(&bucket.name, query).await?;
+    let objects: Vec<ObjectWithUrl> = objects.into_iter().map(|o| urls.attach(o)).collect();
     Ok(Json(json!({
         "success": true,
         "data": objects
     })))
 }
 
+/// Query parameters for storage event streams
+#[derive(Debug, Deserialize)]
+pub struct StorageEventParams {
+    /// Only send events of this bucket
+    pub bucket: Option<String>,
+}
+
+/// GET /v1/storage/events - Server-Sent Events stream of uploads and deletes
+///
+/// Sends `{"event": "upload" | "delete", "bucket", "path", "size"}` per
+/// object. Events of private buckets only reach callers who could read
+/// the object. Bucket events are only on `/v1/stream/_storage`.
+async fn storage_events_handler(
+    State(state): State<StorageState>,
+    Query(params): Query<StorageEventParams>,
+    caller: Option<AuthUser>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, VibeError> {
+    if let Some(bucket) = &params.bucket {
+        state.storage.get_bucket(bucket).await?;
+    }
+    info!(
+        "📡 New storage event subscriber for: {} (by {})",
+        params.bucket.as_deref().unwrap_or("all buckets"),
+        AuthUser::label(caller.as_ref())
+    );
+
+    let mut rx = state.storage.subscribe();
+    let stream = async_stream::stream! {
+        yield Ok(Event::default().data(json!({
+            "event": "connected",
+            "bucket": params.bucket
+        }).to_string()));
+
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    if params.bucket.as_ref().is_some_and(|bucket| *bucket != event.bucket) {
+                        continue;
+                    }
+                    if state.auth.is_some() && !event.visible_to(caller.as_ref()) {
+                        continue;
+                    }
+                    if let Some(message) = event.object_message() {
+                        yield Ok(Event::default().data(message.to_string()));
+                    }
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    yield Ok(Event::default().data(json!({
+                        "event": "warning",
+                        "message": format!("Missed {} messages", n)
+                    }).to_string()));
+                }
+            }
+        }
+    };
+
+    Ok(Sse::new(stream).keep_alive(
+        axum::response::sse::KeepAlive::new()
+            .interval(Duration::from_secs(30))
+            .text("ping"),
+    ))
+}
+
+/// Rebroadcasts storage events on the `_storage` and `storage:<bucket>`
+/// collection streams of `state`
+pub fn spawn_event_forwarder(state: AppState, storage: &StorageService) -> JoinHandle<()> {
+    // Subscribe before spawning so nothing written meanwhile is missed
+    let mut rx = storage.subscribe();
+    tokio::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    let value = json!(event);
+                    state.broadcast(&format!("storage:{}", event.bucket), value.clone());
+                    state.broadcast(STORAGE_STREAM, value);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Storage event forwarder missed {} events", n);
+                }
+            }
+        }
+    })
+}
+
+/// Whether `collection` names a stream fed by [`spawn_event_forwarder`]
+pub fn is_storage_stream(collection: &str) -> bool {
+    collection == STORAGE_STREAM || collection.starts_with("storage:")
+}
+
 // ============================================================================
 // Router
 // ============================================================================
@@ -721,11 +2640,17 @@ pub fn create_storage_router(storage_state: StorageState) -> Router {
         .route("/buckets", get(list_buckets_handler))
         .route("/buckets/:name", get(get_bucket_handler))
         .route("/buckets/:name", delete(delete_bucket_handler))
+        .route("/buckets/:name", patch(update_bucket_handler))
+        .route("/buckets/:name/stats", get(bucket_stats_handler))
+        .route("/buckets/:name/archive", get(archive_handler))
         // Object operations
         .route("/object/:bucket/*path", post(upload_handler))
+        .route("/upload/*path", post(upload_default_handler))
         .route("/object/:bucket/*path", get(download_handler))
         .route("/object/:bucket/*path", delete(delete_object_handler))
+        .route("/object/:bucket/*path", patch(update_metadata_handler))
         .route("/list/:bucket", get(list_objects_handler))
+        .route("/events", get(storage_events_handler))
         .with_state(storage_state)
 }
 
@@ -757,7 +2682,7 @@ mod tests {
         ).await.unwrap();
         
         let temp_dir = tempdir().unwrap();
-        StorageService::new(store, Some(temp_dir.into_path())).await.unwrap()
+        StorageService::new(store, Some(temp_dir.keep())).await.unwrap()
     }
 
     #[tokio::test]
@@ -769,6 +2694,7 @@ mod tests {
                 CreateBucketRequest {
                     name: "test-bucket".to_string(),
                     public: false,
+                    compression: Compression::None,
                 },
                 None,
             )
@@ -787,6 +2713,7 @@ mod tests {
             CreateBucketRequest {
                 name: "Invalid_Name".to_string(),
                 public: false,
+                compression: Compression::None,
             },
             None,
         ).await;
@@ -804,6 +2731,7 @@ mod tests {
                 CreateBucketRequest {
                     name: "files".to_string(),
                     public: true,
+                    compression: Compression::None,
                 },
                 None,
             )
@@ -827,6 +2755,118 @@ mod tests {
         assert_eq!(mime, "text/plain");
     }
 
+    #[tokio::test]
+    async fn test_upload_errors_are_typed() {
+        let service = create_test_service().await;
+        service
+            .create_bucket(
+                CreateBucketRequest {
+                    name: "files".to_string(),
+                    public: false,
+                    compression: Compression::None,
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        for path in ["", "../escape.txt", "/etc/passwd", &"x".repeat(1025)] {
+            let err = service.upload_object("files", path, b"x".to_vec(), "text/plain", None).await.unwrap_err();
+            assert_eq!((err.status_code(), err.error_code()), (StatusCode::BAD_REQUEST, "INVALID_PATH"), "{:?}", path);
+        }
+
+        let chunks = futures::stream::iter(vec![Ok::<_, String>(Bytes::from("12345")), Ok(Bytes::from("678"))]);
+        let err = service.stage_object("files", "big.bin", chunks, 6).await.err().unwrap();
+        assert_eq!((err.status_code(), err.error_code()), (StatusCode::PAYLOAD_TOO_LARGE, "FILE_TOO_LARGE"));
+        assert_eq!(err.details().unwrap()["limit"], MAX_UPLOAD_SIZE as u64);
+        // No partial upload is left behind
+        let leftovers = std::fs::read_dir(service.storage_path.join("files")).map_or(0, |dir| dir.count());
+        assert_eq!(leftovers, 0);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_upload_is_checksummed_and_atomic() {
+        let service = create_test_service().await;
+        service
+            .create_bucket(
+                CreateBucketRequest {
+                    name: "files".to_string(),
+                    public: false,
+                    compression: Compression::Gzip,
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let chunks = ["hello", " ", "world"].map(|c| Ok::<_, Infallible>(Bytes::from(c)));
+        let object = service
+            .upload_object_stream("files", "docs/greeting.txt", futures::stream::iter(chunks), "text/plain", None)
+            .await
+            .unwrap();
+        assert_eq!(object.size, 11);
+        assert_eq!(
+            object.checksum.as_deref(),
+            Some("b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9")
+        );
+
+        // A broken upload leaves the stored object and no partial files
+        let chunks = vec![Ok(Bytes::from("replacement")), Err("connection reset")];
+        let err = service
+            .upload_object_stream("files", "docs/greeting.txt", futures::stream::iter(chunks), "text/plain", None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.error_code(), "INVALID_PAYLOAD");
+        let (data, _) = service.download_object("files", "docs/greeting.txt").await.unwrap();
+        assert_eq!(data, b"hello world");
+        let entries: Vec<_> = std::fs::read_dir(service.get_file_path("files", "docs"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(entries, ["greeting.txt"]);
+        assert_eq!(service.get_object("files", "docs/greeting.txt").await.unwrap().checksum, object.checksum);
+    }
+
+    #[tokio::test]
+    async fn test_racing_overwrites_keep_file_and_row_paired() {
+        let service = create_test_service().await;
+        service
+            .create_bucket(
+                CreateBucketRequest {
+                    name: "files".to_string(),
+                    public: false,
+                    compression: Compression::None,
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let racers = (1..=8).map(|n| {
+            let content = "x".repeat(n * 100);
+            let service = service.clone();
+            async move {
+                service
+                    .upload_object("files", "race.txt", content.into_bytes(), "text/plain", None)
+                    .await
+            }
+        });
+        for result in futures::future::join_all(racers).await {
+            result.unwrap();
+        }
+
+        let object = service.get_object("files", "race.txt").await.unwrap();
+        let (data, _) = service.download_object("files", "race.txt").await.unwrap();
+        assert_eq!(data.len() as i64, object.size);
+        let checksum: String = digest::digest(&digest::SHA256, &data)
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        assert_eq!(object.checksum, Some(checksum));
+        assert!(service.object_locks.is_empty());
+    }
+
     #[tokio::test]
     async fn test_list_objects() {
         let service = create_test_service().await;
@@ -836,6 +2876,7 @@ mod tests {
                 CreateBucketRequest {
                     name: "test".to_string(),
                     public: false,
+                    compression: Compression::None,
                 },
                 None,
             )
@@ -857,17 +2898,81 @@ mod tests {
         }
 
         let objects = service
-            .list_objects("test", ListObjectsQuery {
-                prefix: None,
-                limit: 100,
-                offset: 0,
-            })
+            .list_objects("test", ListObjectsQuery::default())
             .await
             .unwrap();
 
         assert_eq!(objects.len(), 3);
     }
 
+    #[tokio::test]
+    async fn test_list_objects_by_time_range() {
+        let service = create_test_service().await;
+        service
+            .create_bucket(
+                CreateBucketRequest {
+                    name: "sync".to_string(),
+                    public: false,
+                    compression: Compression::None,
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        for (path, created, updated) in [
+            ("old.txt", "2024-01-01 08:00:00", "2024-01-01 08:00:00"),
+            ("edited.txt", "2024-01-01 09:00:00", "2024-03-01 12:00:00"),
+            ("new.txt", "2024-02-01 10:00:00", "2024-02-01 10:00:00"),
+        ] {
+            service.upload_object("sync", path, b"x".to_vec(), "text/plain", None).await.unwrap();
+            service
+                .store
+                .execute(
+                    "UPDATE vibe_objects SET created_at = ?, updated_at = ? WHERE path = ?".to_string(),
+                    vec![
+                        SqlValue::Text(created.to_string()),
+                        SqlValue::Text(updated.to_string()),
+                        SqlValue::Text(path.to_string()),
+                    ],
+                )
+                .await
+                .unwrap();
+        }
+
+        let list = |query: ListObjectsQuery| async {
+            let objects = service.list_objects("sync", query).await.unwrap();
+            objects.into_iter().map(|o| o.path).collect::<Vec<_>>()
+        };
+        // Offsets are converted to UTC; lower bounds are inclusive
+        let created_since = ListObjectsQuery {
+            created_after: Some("2024-01-01T10:00:00+01:00".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(list(created_since).await, ["edited.txt", "new.txt"]);
+        let january = ListObjectsQuery {
+            created_after: Some("2024-01-01T00:00:00Z".to_string()),
+            created_before: Some("2024-02-01T00:00:00Z".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(list(january).await, ["edited.txt", "old.txt"]);
+        let touched = ListObjectsQuery {
+            updated_after: Some("2024-01-15T00:00:00Z".to_string()),
+            updated_before: Some("2024-03-01T12:00:00Z".to_string()),
+            prefix: Some("n".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(list(touched).await, ["new.txt"]);
+
+        let invalid = ListObjectsQuery {
+            updated_before: Some("yesterday".to_string()),
+            ..Default::default()
+        };
+        match service.list_objects("sync", invalid).await {
+            Err(VibeError::InvalidFields { fields, .. }) => assert_eq!(fields, ["updated_before"]),
+            other => panic!("expected invalid fields, got {:?}", other),
+        }
+    }
+
     #[tokio::test]
     async fn test_delete_object() {
         let service = create_test_service().await;
@@ -877,6 +2982,7 @@ mod tests {
                 CreateBucketRequest {
                     name: "delete-test".to_string(),
                     public: false,
+                    compression: Compression::None,
                 },
                 None,
             )
@@ -893,4 +2999,758 @@ mod tests {
         let result = service.get_object("delete-test", "to-delete.txt").await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_compressed_bucket_roundtrip() {
+        let service = create_test_service().await;
+        let log = "2024-05-01 INFO request handled in 3ms\n".repeat(500).into_bytes();
+
+        for codec in [Compression::Gzip, Compression::Zstd] {
+            let bucket = format!("logs-{}", codec.as_str());
+            service
+                .create_bucket(
+                    CreateBucketRequest {
+                        name: bucket.clone(),
+                        public: false,
+                        compression: codec,
+                    },
+                    None,
+                )
+                .await
+                .unwrap();
+
+            let object = service
+                .upload_object(&bucket, "app.log", log.clone(), "text/plain", None)
+                .await
+                .unwrap();
+            assert_eq!(object.compression, codec);
+            assert_eq!(object.size, log.len() as i64);
+            assert!(object.stored_size < object.size / 10);
+
+            let (downloaded, _) = service.download_object(&bucket, "app.log").await.unwrap();
+            assert_eq!(downloaded, log);
+
+            let stats = service.bucket_stats(&bucket).await.unwrap();
+            assert_eq!(stats.object_count, 1);
+            assert_eq!(stats.logical_size, object.size);
+            assert_eq!(stats.stored_size, object.stored_size);
+            assert_eq!(stats.duplicate_size, 0);
+
+            // A copy is stored again and reported as duplicate bytes
+            let copy = service
+                .upload_object(&bucket, "copy.log", log.clone(), "text/plain", None)
+                .await
+                .unwrap();
+            let stats = service.bucket_stats(&bucket).await.unwrap();
+            assert_eq!(stats.stored_size, object.stored_size + copy.stored_size);
+            assert_eq!(stats.duplicate_size, copy.stored_size);
+        }
+
+        // Data that does not shrink is stored as-is
+        let noise: Vec<u8> = (0..4096).map(|_| rand::random::<u8>()).collect();
+        let object = service
+            .upload_object("logs-gzip", "noise.bin", noise, "application/octet-stream", None)
+            .await
+            .unwrap();
+        assert_eq!(object.compression, Compression::None);
+        assert_eq!(object.stored_size, object.size);
+    }
+
+    #[tokio::test]
+    async fn test_download_content_encoding() {
+        use crate::testing::TestServer;
+
+        let server = TestServer::spawn().await;
+        server
+            .storage
+            .create_bucket(
+                CreateBucketRequest {
+                    name: "csv".to_string(),
+                    public: false,
+                    compression: Compression::Gzip,
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        let data = "id,name\n1,widget\n".repeat(200).into_bytes();
+        server.upload_fixture_file("csv", "export.csv", data.clone()).await;
+        let admin = server.create_user("ops@example.com", crate::auth::ADMIN_ROLE).await;
+
+        let download = |accept: Option<&str>| {
+            let mut request = server.get("/v1/storage/object/csv/export.csv").bearer(&admin.token);
+            if let Some(accept) = accept {
+                request = request.header(header::ACCEPT_ENCODING.as_str(), accept);
+            }
+            request.send()
+        };
+
+        // Clients accepting gzip get the stored bytes
+        let response = download(Some("br, gzip")).await;
+        assert_eq!(response.headers[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(Compression::Gzip.decompress(&response.body).unwrap(), data);
+
+        // Everyone else gets plain content
+        for accept in [None, Some("gzip;q=0")] {
+            let response = download(accept).await;
+            assert!(response.headers.get(header::CONTENT_ENCODING).is_none());
+            assert_eq!(response.body.to_vec(), data);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_listed_urls_follow_bucket_visibility() {
+        use crate::auth::USER_ROLE;
+        use crate::testing::TestServer;
+
+        let server = TestServer::spawn().await;
+        let owner = server.create_user("owner@example.com", USER_ROLE).await;
+        let other = server.create_user("other@example.com", USER_ROLE).await;
+        for (name, public) in [("assets", true), ("vault", false)] {
+            server
+                .post("/v1/storage/buckets")
+                .bearer(&owner.token)
+                .json(json!({"name": name, "public": public}))
+                .send()
+                .await
+                .assert_success();
+            server
+                .storage
+                .upload_object(name, "logo v2.png", b"png".to_vec(), "image/png", Some(owner.id))
+                .await
+                .unwrap();
+        }
+
+        // Public buckets list plain, working paths
+        let listed = server.get("/v1/storage/list/assets").send().await.assert_success();
+        let url = listed[0]["url"].as_str().unwrap();
+        assert_eq!(url, "/v1/storage/object/assets/logo%20v2.png");
+        assert_eq!(server.get(url).send().await.assert_status(StatusCode::OK).body.to_vec(), b"png");
+
+        // Private buckets list nulls and refuse anonymous reads
+        let listed = server.get("/v1/storage/list/vault").send().await.assert_success();
+        assert_eq!(listed[0]["url"], Value::Null);
+        let plain = "/v1/storage/object/vault/logo%20v2.png";
+        server.get(plain).send().await.assert_error(StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+        server.get(plain).bearer(&other.token).send().await.assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+
+        // The owner can sign them; the signed URL works without a token
+        let listed = server
+            .get("/v1/storage/list/vault?sign=true&expires_in=60")
+            .bearer(&owner.token)
+            .send()
+            .await
+            .assert_success();
+        let signed = listed[0]["url"].as_str().unwrap().to_string();
+        assert!(signed.starts_with(&format!("{}?expires=", plain)));
+        assert_eq!(server.get(&signed).send().await.assert_status(StatusCode::OK).body.to_vec(), b"png");
+        let tampered = signed.replace("expires=", "expires=1");
+        server.get(&tampered).send().await.assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+
+        // Everyone else is refused, as are lifetimes past the cap
+        server
+            .get("/v1/storage/list/vault?sign=true")
+            .bearer(&other.token)
+            .send()
+            .await
+            .assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+        server
+            .get(&format!("/v1/storage/list/vault?sign=true&expires_in={}", MAX_SIGNED_URL_TTL.as_secs() + 1))
+            .bearer(&owner.token)
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+
+        // Uploads keep the file when signing is refused
+        let uploaded = server
+            .post("/v1/storage/object/vault/notes.txt?sign=true")
+            .bearer(&other.token)
+            .body(multipart_body(&[("file", Some("notes.txt"), "hi")]), "multipart/form-data; boundary=X")
+            .send()
+            .await
+            .assert_status(StatusCode::CREATED)
+            .json();
+        assert_eq!(uploaded["data"]["path"], "notes.txt");
+        assert_eq!(uploaded["data"]["url"], Value::Null);
+        assert!(uploaded["warnings"][0].as_str().unwrap().starts_with("URL not signed: "));
+        assert!(server.storage.get_object("vault", "notes.txt").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_signed_urls_expire_by_the_clock() {
+        use crate::testing::{ManualClock, TestServer};
+
+        let clock = ManualClock::new();
+        let server = TestServer::builder().clock(clock.clone()).spawn().await;
+        let admin = server.create_user("admin@example.com", "admin").await;
+        server.upload_fixture_file("vault", "a.txt", "secret").await;
+
+        let listed = server
+            .get("/v1/storage/list/vault?sign=true&expires_in=60")
+            .bearer(&admin.token)
+            .send()
+            .await
+            .assert_success();
+        let signed = listed[0]["url"].as_str().unwrap().to_string();
+
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(server.get(&signed).send().await.assert_status(StatusCode::OK).body.to_vec(), b"secret");
+        clock.advance(Duration::from_secs(1));
+        server.get(&signed).send().await.assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+    }
+
+    #[tokio::test]
+    async fn test_only_owners_manage_buckets() {
+        use crate::auth::{ADMIN_ROLE, USER_ROLE};
+        use crate::testing::TestServer;
+
+        let server = TestServer::spawn().await;
+        let owner = server.create_user("owner@example.com", USER_ROLE).await;
+        let other = server.create_user("other@example.com", USER_ROLE).await;
+        let admin = server.create_user("admin@example.com", ADMIN_ROLE).await;
+        server
+            .post("/v1/storage/buckets")
+            .bearer(&owner.token)
+            .json(json!({"name": "vault", "public": false}))
+            .send()
+            .await
+            .assert_success();
+
+        let publish = json!({"public": true});
+        server
+            .patch("/v1/storage/buckets/vault")
+            .json(publish.clone())
+            .send()
+            .await
+            .assert_error(StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+        server
+            .patch("/v1/storage/buckets/vault")
+            .bearer(&other.token)
+            .json(publish.clone())
+            .send()
+            .await
+            .assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+        server.get("/v1/storage/buckets/vault/stats").send().await.assert_error(StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+        server
+            .get("/v1/storage/buckets/vault/stats")
+            .bearer(&other.token)
+            .send()
+            .await
+            .assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+        assert!(!server.storage.get_bucket("vault").await.unwrap().public);
+
+        server.get("/v1/storage/buckets/vault/stats").bearer(&owner.token).send().await.assert_success();
+        server
+            .patch("/v1/storage/buckets/vault")
+            .bearer(&admin.token)
+            .json(json!({"compression": "gzip"}))
+            .send()
+            .await
+            .assert_success();
+        let patched = server
+            .patch("/v1/storage/buckets/vault")
+            .bearer(&owner.token)
+            .json(publish)
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(patched["public"], true);
+        // Public totals are for everyone
+        server.get("/v1/storage/buckets/vault/stats").send().await.assert_success();
+    }
+
+    /// A multipart body of `(field name, filename, content)` parts
+    fn multipart_body(parts: &[(&str, Option<&str>, &str)]) -> String {
+        let mut body = String::new();
+        for (name, filename, content) in parts {
+            let filename = filename.map(|f| format!("; filename=\"{}\"", f)).unwrap_or_default();
+            body.push_str(&format!(
+                "--X\r\nContent-Disposition: form-data; name=\"{}\"{}\r\nContent-Type: text/plain\r\n\r\n{}\r\n",
+                name, filename, content
+            ));
+        }
+        body + "--X--\r\n"
+    }
+
+    #[tokio::test]
+    async fn test_upload_many_files_to_a_prefix() {
+        use crate::testing::TestServer;
+
+        let server = TestServer::builder().anonymous().spawn().await;
+        server.post("/v1/storage/buckets").json(json!({"name": "docs", "public": true})).send().await.assert_success();
+
+        let body = multipart_body(&[
+            ("files", Some("a.txt"), "alpha"),
+            ("metadata", None, r#"{"lang": "en"}"#),
+            ("files", Some("C:\\Users\\me\\b.txt"), "beta"),
+            ("files", Some("c.txt"), "gamma"),
+        ]);
+        let uploaded = server
+            .post("/v1/storage/object/docs/reports/")
+            .body(body, "multipart/form-data; boundary=X")
+            .send()
+            .await
+            .assert_success();
+        let paths: Vec<_> = uploaded.as_array().unwrap().iter().map(|r| r["data"]["path"].clone()).collect();
+        assert_eq!(paths, vec![json!("reports/a.txt"), json!("reports/b.txt"), json!("reports/c.txt")]);
+        assert_eq!(uploaded[1]["filename"], "C:\\Users\\me\\b.txt");
+        assert_eq!(uploaded[1]["data"]["metadata"], json!({"lang": "en"}));
+        assert_eq!(uploaded[0]["data"]["metadata"], Value::Null);
+        let (data, _) = server.storage.download_object("docs", "reports/c.txt").await.unwrap();
+        assert_eq!(data, b"gamma");
+
+        // Rejected files are reported beside the stored ones
+        let body = multipart_body(&[
+            ("files", Some("d.txt"), "delta"),
+            ("metadata", None, "[1, 2]"),
+            ("files", Some("e.txt"), "epsilon"),
+            ("files", Some(".."), "up"),
+        ]);
+        let response = server
+            .post("/v1/storage/object/docs/reports/")
+            .body(body.clone(), "multipart/form-data; boundary=X")
+            .send()
+            .await
+            .assert_status(StatusCode::MULTI_STATUS)
+            .json();
+        assert_eq!(response["success"], false);
+        let outcomes: Vec<_> = response["data"].as_array().unwrap().iter().map(|r| r["success"].clone()).collect();
+        assert_eq!(outcomes, vec![json!(true), json!(false), json!(false)]);
+        assert_eq!(response["data"][1]["error"]["code"], "INVALID_PAYLOAD");
+        assert_eq!(response["data"][2]["path"], "reports/..");
+        assert_eq!(response["data"][2]["error"]["code"], "INVALID_PATH");
+        assert!(server.storage.get_object("docs", "reports/d.txt").await.is_ok());
+        assert!(server.storage.get_object("docs", "reports/e.txt").await.is_err());
+
+        // Strict uploads store nothing unless every file is accepted
+        server.storage.delete_object("docs", "reports/d.txt").await.unwrap();
+        server
+            .post("/v1/storage/object/docs/reports/?strict=true")
+            .body(body, "multipart/form-data; boundary=X")
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        assert!(server.storage.get_object("docs", "reports/d.txt").await.is_err());
+
+        // Paths without a trailing slash still take a single `file` part
+        let single = server
+            .post("/v1/storage/object/docs/notes.txt")
+            .body(multipart_body(&[("file", Some("ignored.txt"), "note")]), "multipart/form-data; boundary=X")
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(single["path"], "notes.txt");
+    }
+
+    #[tokio::test]
+    async fn test_storage_events_stream() {
+        use crate::auth::USER_ROLE;
+        use crate::testing::TestServer;
+
+        let server = TestServer::spawn().await;
+        let alice = server.create_user("alice@example.com", USER_ROLE).await;
+        let bob = server.create_user("bob@example.com", USER_ROLE).await;
+        for (name, public) in [("assets", true), ("vault", false)] {
+            server
+                .post("/v1/storage/buckets")
+                .bearer(&alice.token)
+                .json(json!({"name": name, "public": public}))
+                .send()
+                .await
+                .assert_success();
+        }
+        server
+            .get("/v1/storage/events?bucket=ghost")
+            .send()
+            .await
+            .assert_error(StatusCode::NOT_FOUND, "NOT_FOUND");
+
+        let mut everything = server.subscribe_uri("/v1/storage/events", Some(&alice.token)).await;
+        let mut outsider = server.subscribe_uri("/v1/storage/events", Some(&bob.token)).await;
+        let mut vault = server.subscribe_uri("/v1/storage/events?bucket=vault", Some(&alice.token)).await;
+
+        server.upload_fixture_file("vault", "secret.txt", "hush").await;
+        server.upload_fixture_file("assets", "logo.png", "png!").await;
+        server
+            .delete("/v1/storage/object/vault/secret.txt")
+            .bearer(&alice.token)
+            .send()
+            .await
+            .assert_success();
+
+        let upload = everything.next().await;
+        assert_eq!(upload, json!({"event": "upload", "bucket": "vault", "path": "secret.txt", "size": 4}));
+        assert_eq!(everything.next().await["path"], "logo.png");
+        assert_eq!(everything.next().await["event"], "delete");
+
+        // Private buckets are hidden from callers who can't read them
+        assert_eq!(outsider.next().await["path"], "logo.png");
+
+        assert_eq!(vault.next().await["event"], "upload");
+        let delete = vault.next().await;
+        assert_eq!(delete, json!({"event": "delete", "bucket": "vault", "path": "secret.txt", "size": 4}));
+    }
+
+    #[tokio::test]
+    async fn test_conditional_uploads_never_overwrite() {
+        use crate::testing::TestServer;
+
+        let server = TestServer::builder().anonymous().spawn().await;
+        server.upload_fixture_file("docs", "kept.txt", "first").await;
+        let upload = |uri: &str, content: &str| {
+            server
+                .post(uri)
+                .body(multipart_body(&[("file", Some("f.txt"), content)]), "multipart/form-data; boundary=X")
+        };
+
+        upload("/v1/storage/object/docs/kept.txt", "second")
+            .header("If-None-Match", "*")
+            .send()
+            .await
+            .assert_error(StatusCode::CONFLICT, "CONFLICT");
+        upload("/v1/storage/object/docs/kept.txt?overwrite=false", "second")
+            .send()
+            .await
+            .assert_error(StatusCode::CONFLICT, "CONFLICT");
+        let (data, _) = server.storage.download_object("docs", "kept.txt").await.unwrap();
+        assert_eq!(data, b"first");
+
+        // Of racing create-only uploads exactly one is stored
+        let racers = ["a", "b", "c"].map(|content| {
+            upload("/v1/storage/object/docs/once.txt", content).header("If-None-Match", "*").send()
+        });
+        let statuses: Vec<StatusCode> = futures::future::join_all(racers).await.iter().map(|r| r.status).collect();
+        assert_eq!(statuses.iter().filter(|s| **s == StatusCode::CREATED).count(), 1, "{:?}", statuses);
+        assert_eq!(statuses.iter().filter(|s| **s == StatusCode::CONFLICT).count(), 2, "{:?}", statuses);
+
+        // Prefix uploads report the conflicting file; upserting stays the default
+        server.upload_fixture_file("docs", "notes/kept.txt", "first").await;
+        let body = multipart_body(&[("file", Some("kept.txt"), "x"), ("file", Some("new.txt"), "y")]);
+        let results = server
+            .post("/v1/storage/object/docs/notes/?overwrite=false")
+            .body(body, "multipart/form-data; boundary=X")
+            .send()
+            .await
+            .assert_status(StatusCode::MULTI_STATUS)
+            .json();
+        assert_eq!(results["data"][0]["error"]["code"], "CONFLICT");
+        assert_eq!(results["data"][1]["success"], true);
+        upload("/v1/storage/object/docs/kept.txt", "second").send().await.assert_status(StatusCode::CREATED);
+        let (data, _) = server.storage.download_object("docs", "kept.txt").await.unwrap();
+        assert_eq!(data, b"second");
+    }
+
+    #[tokio::test]
+    async fn test_metadata_patch_keeps_the_file() {
+        use crate::testing::TestServer;
+
+        let server = TestServer::builder().anonymous().spawn().await;
+        server.upload_fixture_file("docs", "notes/a.txt", "content").await;
+        server
+            .storage
+            .update_metadata("docs", "notes/a.txt", json!({"tags": {"x": 1}, "stale": true}).as_object().unwrap().clone())
+            .await
+            .unwrap();
+        server
+            .store
+            .execute("UPDATE vibe_objects SET updated_at = '2000-01-01 00:00:00'".to_string(), vec![])
+            .await
+            .unwrap();
+        let before = server.storage.get_object("docs", "notes/a.txt").await.unwrap();
+
+        let data = server
+            .patch("/v1/storage/object/docs/notes/a.txt/metadata")
+            .json(json!({"title": "A", "stale": null, "tags": {"y": 2}}))
+            .send()
+            .await
+            .assert_success();
+        assert_eq!(data["metadata"], json!({"title": "A", "tags": {"x": 1, "y": 2}}));
+        assert_ne!(data["updated_at"], "2000-01-01 00:00:00");
+        assert_eq!(data["created_at"], before.created_at.as_str());
+        let (content, object) = server.storage.read_stored_object("docs", "notes/a.txt").await.unwrap();
+        assert_eq!(content, b"content");
+        assert_eq!(object.checksum, before.checksum);
+
+        for body in ["[1]", "\"text\"", "{not json"] {
+            server
+                .patch("/v1/storage/object/docs/notes/a.txt/metadata")
+                .body(body.to_string(), "application/json")
+                .send()
+                .await
+                .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        }
+        let huge = json!({"blob": "x".repeat(MAX_METADATA_SIZE)});
+        server
+            .patch("/v1/storage/object/docs/notes/a.txt/metadata")
+            .json(huge)
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_PAYLOAD");
+        for uri in ["/v1/storage/object/docs/notes/b.txt/metadata", "/v1/storage/object/docs/notes/a.txt"] {
+            server.patch(uri).json(json!({"a": 1})).send().await.assert_error(StatusCode::NOT_FOUND, "NOT_FOUND");
+        }
+        let object = server.storage.get_object("docs", "notes/a.txt").await.unwrap();
+        assert_eq!(object.metadata, Some(json!({"title": "A", "tags": {"x": 1, "y": 2}})));
+    }
+
+    #[tokio::test]
+    async fn test_default_bucket_uploads() {
+        use crate::testing::TestServer;
+
+        let server = TestServer::builder()
+            .default_bucket(DefaultBucketConfig {
+                name: "uploads".to_string(),
+                public: true,
+            })
+            .spawn()
+            .await;
+        assert!(server.storage.get_bucket("uploads").await.unwrap().public);
+
+        let uploaded = server
+            .post("/v1/storage/upload/notes/a.txt")
+            .body(multipart_body(&[("file", Some("a.txt"), "hello")]), "multipart/form-data; boundary=X")
+            .send()
+            .await
+            .assert_status(StatusCode::CREATED)
+            .json();
+        assert_eq!(uploaded["data"]["bucket_name"], "uploads");
+        let (data, _) = server.storage.download_object("uploads", "notes/a.txt").await.unwrap();
+        assert_eq!(data, b"hello");
+
+        // An existing bucket is reused as it is
+        let storage = server
+            .storage
+            .clone()
+            .with_default_bucket(DefaultBucketConfig {
+                name: "uploads".to_string(),
+                public: false,
+            })
+            .await
+            .unwrap();
+        assert_eq!(storage.default_bucket(), Some("uploads"));
+        assert!(storage.get_bucket("uploads").await.unwrap().public);
+
+        let plain = TestServer::spawn().await;
+        plain
+            .post("/v1/storage/upload/a.txt")
+            .body(multipart_body(&[("file", Some("a.txt"), "hello")]), "multipart/form-data; boundary=X")
+            .send()
+            .await
+            .assert_error(StatusCode::NOT_FOUND, "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_storage_events_on_collection_streams() {
+        use crate::auth::USER_ROLE;
+        use crate::testing::TestServer;
+
+        let server = TestServer::spawn().await;
+        let alice = server.create_user("alice@example.com", USER_ROLE).await;
+        let bob = server.create_user("bob@example.com", USER_ROLE).await;
+        let mut everything = server.subscribe("_storage", Some(&alice.token)).await;
+        let mut outsider = server.subscribe("_storage", Some(&bob.token)).await;
+        let mut vault = server.subscribe("storage:vault", Some(&alice.token)).await;
+
+        for (name, public) in [("vault", false), ("assets", true)] {
+            server
+                .post("/v1/storage/buckets")
+                .bearer(&alice.token)
+                .json(json!({"name": name, "public": public}))
+                .send()
+                .await
+                .assert_success();
+        }
+        for data in ["hush", "hushed"] {
+            server
+                .post("/v1/storage/object/vault/secret.txt")
+                .bearer(&alice.token)
+                .body(multipart_body(&[("file", Some("secret.txt"), data)]), "multipart/form-data; boundary=X")
+                .send()
+                .await
+                .assert_success();
+        }
+        server.upload_fixture_file("assets", "logo.png", "png!").await;
+        server
+            .delete("/v1/storage/object/vault/secret.txt")
+            .bearer(&alice.token)
+            .send()
+            .await
+            .assert_success();
+
+        let created = everything.next().await;
+        assert_eq!(created["event"], "bucket_created");
+        assert_eq!(created["collection"], "_storage");
+        assert_eq!(everything.next().await["bucket"], "assets");
+        let uploaded = everything.next().await;
+        assert_eq!(uploaded["event"], "object_created");
+        assert_eq!(uploaded["bucket"], "vault");
+        assert_eq!(uploaded["path"], "secret.txt");
+        assert_eq!(uploaded["size"], 4);
+        assert_eq!(uploaded["mime_type"], "text/plain");
+        assert_eq!(uploaded["owner_id"], alice.id);
+        let replaced = everything.next().await;
+        assert_eq!(replaced["event"], "object_updated");
+        assert_eq!(replaced["size"], 6);
+        assert_eq!(everything.next().await["path"], "logo.png");
+        assert_eq!(everything.next().await["event"], "object_deleted");
+
+        // Private buckets are hidden from callers who can't read them
+        assert_eq!(outsider.next().await["bucket"], "assets");
+        assert_eq!(outsider.next().await["path"], "logo.png");
+
+        let created = vault.next().await;
+        assert_eq!(created["event"], "bucket_created");
+        assert_eq!(created["collection"], "storage:vault");
+        assert_eq!(vault.next().await["event"], "object_created");
+        assert_eq!(vault.next().await["event"], "object_updated");
+        assert_eq!(vault.next().await["event"], "object_deleted");
+
+        // Pushes can't spoof storage events
+        server
+            .post("/v1/push/_storage")
+            .json(json!({"event": "object_deleted"}))
+            .send()
+            .await
+            .assert_error(StatusCode::BAD_REQUEST, "INVALID_IDENTIFIER");
+    }
+
+    #[tokio::test]
+    async fn test_storage_quotas() {
+        use crate::auth::USER_ROLE;
+        use crate::testing::TestServer;
+
+        let server = TestServer::builder()
+            .storage_quotas(StorageQuotaConfig {
+                per_user: Some(10),
+                global: Some(25),
+            })
+            .spawn()
+            .await;
+        let alice = server.create_user("alice@example.com", USER_ROLE).await;
+        let bob = server.create_user("bob@example.com", USER_ROLE).await;
+        let carol = server.create_user("carol@example.com", USER_ROLE).await;
+        server
+            .post("/v1/storage/buckets")
+            .bearer(&alice.token)
+            .json(json!({"name": "shared", "public": true}))
+            .send()
+            .await
+            .assert_success();
+        let upload = |path: &str, content: &str, token: &str| {
+            server
+                .post(&format!("/v1/storage/object/shared/{}", path))
+                .body(multipart_body(&[("file", Some("f.txt"), content)]), "multipart/form-data; boundary=X")
+                .bearer(token)
+        };
+
+        upload("a.txt", "123456", &alice.token).send().await.assert_success();
+        let error = upload("b.txt", "123456", &alice.token)
+            .send()
+            .await
+            .assert_error(StatusCode::PAYLOAD_TOO_LARGE, "QUOTA_EXCEEDED");
+        assert_eq!(error["details"], json!({"scope": "user", "used": 6, "requested": 6, "limit": 10}));
+        assert!(server.storage.get_object("shared", "b.txt").await.is_err());
+
+        // Replacing an object only counts the difference
+        upload("a.txt", "12345678", &alice.token).send().await.assert_success();
+        assert_eq!(server.storage.storage_usage(Some(alice.id)).await.unwrap(), 8);
+
+        upload("c.txt", "1234567890", &bob.token).send().await.assert_success();
+        let error = upload("d.txt", "12345678", &carol.token)
+            .send()
+            .await
+            .assert_error(StatusCode::PAYLOAD_TOO_LARGE, "QUOTA_EXCEEDED");
+        assert_eq!(error["details"]["scope"], "global");
+        assert_eq!(error["details"]["used"], 18);
+        upload("d.txt", "1234567", &carol.token).send().await.assert_success();
+        assert_eq!(server.storage.storage_usage(None).await.unwrap(), 25);
+    }
+
+    /// `(path, content)` of the files in a tar archive
+    fn untar(mut archive: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let mut files = Vec::new();
+        let mut long_name = None;
+        while archive.len() >= 2 * TAR_BLOCK && archive[..TAR_BLOCK].iter().any(|&b| b != 0) {
+            let header = &archive[..TAR_BLOCK];
+            let checksum: u32 = header[..148].iter().chain([b' '; 8].iter()).chain(&header[156..]).map(|&b| b as u32).sum();
+            let field = |range: std::ops::Range<usize>| {
+                String::from_utf8(header[range].iter().copied().take_while(|&b| b != 0).collect()).unwrap()
+            };
+            assert_eq!(u32::from_str_radix(field(148..154).as_str(), 8).unwrap(), checksum);
+            let size = usize::from_str_radix(&field(124..135), 8).unwrap();
+            let content = archive[TAR_BLOCK..TAR_BLOCK + size].to_vec();
+            if header[156] == b'L' {
+                long_name = Some(String::from_utf8(content[..size - 1].to_vec()).unwrap());
+            } else {
+                files.push((long_name.take().unwrap_or_else(|| field(0..100)), content));
+            }
+            archive = &archive[TAR_BLOCK + size.div_ceil(TAR_BLOCK) * TAR_BLOCK..];
+        }
+        assert_eq!(archive, [0; 2 * TAR_BLOCK]);
+        files
+    }
+
+    #[tokio::test]
+    async fn test_bucket_archive() {
+        use crate::auth::USER_ROLE;
+        use crate::testing::TestServer;
+
+        let server = TestServer::spawn().await;
+        let owner = server.create_user("owner@example.com", USER_ROLE).await;
+        let other = server.create_user("other@example.com", USER_ROLE).await;
+        server
+            .post("/v1/storage/buckets")
+            .bearer(&owner.token)
+            .json(json!({"name": "photos", "compression": "zstd"}))
+            .send()
+            .await
+            .assert_success();
+        let long_path = format!("2024/{}.txt", "x".repeat(120));
+        let big = "abc".repeat(50_000);
+        for (path, content, user) in [
+            ("2024/a.txt", "alpha", &owner),
+            (long_path.as_str(), big.as_str(), &owner),
+            ("2025/b.txt", "beta", &other),
+        ] {
+            server
+                .storage
+                .upload_object("photos", path, content.as_bytes().to_vec(), "text/plain", Some(user.id))
+                .await
+                .unwrap();
+        }
+
+        let response = server.get("/v1/storage/buckets/photos/archive").bearer(&owner.token).send().await;
+        let response = response.assert_status(StatusCode::OK);
+        assert_eq!(response.headers[header::CONTENT_TYPE], "application/x-tar");
+        let files = untar(&response.body);
+        let paths: Vec<_> = files.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, vec!["2024/a.txt", long_path.as_str(), "2025/b.txt"]);
+        assert_eq!(files[0].1, b"alpha");
+        assert_eq!(files[1].1, big.as_bytes());
+
+        let response = server.get("/v1/storage/buckets/photos/archive?prefix=2025/").bearer(&owner.token).send().await;
+        assert_eq!(untar(&response.assert_status(StatusCode::OK).body), vec![("2025/b.txt".to_string(), b"beta".to_vec())]);
+
+        // Other readers of the private bucket get only their own objects
+        let response = server.get("/v1/storage/buckets/photos/archive").bearer(&other.token).send().await;
+        assert_eq!(untar(&response.assert_status(StatusCode::OK).body).len(), 1);
+        server
+            .get("/v1/storage/buckets/photos/archive")
+            .send()
+            .await
+            .assert_error(StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+    }
+
+    #[test]
+    fn test_base_url_trusts_forwarded_headers_only_when_told() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(base_url(&headers, true), "");
+
+        headers.insert(header::HOST, HeaderValue::from_static("10.0.0.5:3000"));
+        headers.insert("x-forwarded-host", HeaderValue::from_static("files.example.com, proxy.internal"));
+        headers.insert("x-forwarded-proto", HeaderValue::from_static("https"));
+        assert_eq!(base_url(&headers, false), "http://10.0.0.5:3000");
+        assert_eq!(base_url(&headers, true), "https://files.example.com");
+        assert_eq!(encode_path("a b/ü.txt"), "a%20b/%C3%BC.txt");
+    }
 }
@@ -0,0 +1,475 @@
+//! # HTML Templates
+//!
+//! Server-side rendering of query results into HTML snippets through a
+//! small, logic-less subset of Mustache:
+//!
+//! - `{{name}}` - a value, HTML-escaped; dotted names walk into objects and
+//!   `{{.}}` is the current value
+//! - `{{{name}}}` or `{{& name}}` - a value as is, for trusted HTML
+//! - `{{#name}}...{{/name}}` - repeated for each item of a list, rendered
+//!   once with an object or any other truthy value in scope, skipped for
+//!   `null`, `false`, `""` and `[]`
+//! - `{{^name}}...{{/name}}` - rendered only when `name` is falsy
+//! - `{{! comment}}` - dropped
+//!
+//! There are no partials, lambdas or delimiter changes, so a template can't
+//! run code or reach anything beyond the values it is given. Templates,
+//! their output and the time spent rendering them are capped.
+//!
+//! Named templates live in `vibe_templates_html` and are parsed before they
+//! are stored.
+
+use crate::db::{SqlValue, VibeStore};
+use crate::error::{VibeError, VibeResult};
+
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+/// Largest template accepted, in bytes
+pub const MAX_TEMPLATE_BYTES: usize = 64 * 1024;
+
+/// Most rows a single render reads
+pub const MAX_RENDER_ROWS: usize = 1000;
+
+/// Deepest nesting of sections accepted
+pub const MAX_SECTION_DEPTH: usize = 16;
+
+/// Longest template name accepted, in characters
+pub const MAX_NAME_LENGTH: usize = 64;
+
+/// Caps on a single render
+#[derive(Debug, Clone)]
+pub struct RenderLimits {
+    /// Largest output produced, in bytes
+    pub max_output_bytes: usize,
+    /// Time a render may take
+    pub timeout: Duration,
+}
+
+impl Default for RenderLimits {
+    fn default() -> Self {
+        Self {
+            max_output_bytes: 4 * 1024 * 1024,
+            timeout: Duration::from_millis(500),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Value { name: String, raw: bool },
+    Section { name: String, inverted: bool, children: Vec<Node> },
+}
+
+/// A parsed template
+#[derive(Debug, Clone)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    /// Parses a template, rejecting oversized or malformed ones
+    pub fn parse(source: &str) -> VibeResult<Self> {
+        if source.len() > MAX_TEMPLATE_BYTES {
+            return Err(VibeError::InvalidPayload(format!(
+                "Template is {} bytes; the limit is {}",
+                source.len(),
+                MAX_TEMPLATE_BYTES
+            )));
+        }
+
+        // Open sections, innermost last, with the nodes collected so far
+        let mut open: Vec<(String, bool, Vec<Node>)> = Vec::new();
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut rest = source;
+
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                nodes.push(Node::Text(rest[..start].to_string()));
+            }
+            let after = &rest[start + 2..];
+            let (tag, raw_braces, consumed) = if let Some(inner) = after.strip_prefix('{') {
+                let end = inner
+                    .find("}}}")
+                    .ok_or_else(|| malformed("unclosed {{{ tag"))?;
+                (&inner[..end], true, start + 3 + end + 3)
+            } else {
+                let end = after.find("}}").ok_or_else(|| malformed("unclosed {{ tag"))?;
+                (&after[..end], false, start + 2 + end + 2)
+            };
+            rest = &rest[consumed..];
+
+            let tag = tag.trim();
+            if raw_braces {
+                nodes.push(Node::Value { name: tag_name(tag)?, raw: true });
+                continue;
+            }
+            match tag.chars().next() {
+                Some('!') => {}
+                Some('&') => nodes.push(Node::Value { name: tag_name(&tag[1..])?, raw: true }),
+                Some(c @ ('#' | '^')) => {
+                    if open.len() >= MAX_SECTION_DEPTH {
+                        return Err(malformed(&format!(
+                            "sections are nested deeper than {}",
+                            MAX_SECTION_DEPTH
+                        )));
+                    }
+                    let name = tag_name(&tag[1..])?;
+                    open.push((name, c == '^', std::mem::take(&mut nodes)));
+                }
+                Some('/') => {
+                    let name = tag_name(&tag[1..])?;
+                    let Some((opened, inverted, outer)) = open.pop() else {
+                        return Err(malformed(&format!("{{{{/{}}}}} closes nothing", name)));
+                    };
+                    if opened != name {
+                        return Err(malformed(&format!(
+                            "{{{{/{}}}}} closes {{{{#{}}}}}",
+                            name, opened
+                        )));
+                    }
+                    let children = std::mem::replace(&mut nodes, outer);
+                    nodes.push(Node::Section { name, inverted, children });
+                }
+                _ => nodes.push(Node::Value { name: tag_name(tag)?, raw: false }),
+            }
+        }
+        if !rest.is_empty() {
+            nodes.push(Node::Text(rest.to_string()));
+        }
+        if let Some((name, _, _)) = open.pop() {
+            return Err(malformed(&format!("{{{{#{}}}}} is never closed", name)));
+        }
+
+        Ok(Self { nodes })
+    }
+
+    /// Renders the template against `context`
+    pub fn render(&self, context: &Value, limits: &RenderLimits) -> VibeResult<String> {
+        let mut renderer = Renderer {
+            out: String::new(),
+            limits,
+            deadline: Instant::now() + limits.timeout,
+        };
+        let mut scopes = vec![context];
+        renderer.nodes(&self.nodes, &mut scopes)?;
+        Ok(renderer.out)
+    }
+}
+
+fn malformed(message: &str) -> VibeError {
+    VibeError::InvalidPayload(format!("Malformed template: {}", message))
+}
+
+fn tag_name(tag: &str) -> VibeResult<String> {
+    let name = tag.trim();
+    let valid = name == "."
+        || (!name.is_empty()
+            && name.split('.').all(|part| {
+                !part.is_empty()
+                    && part.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+            }));
+    if !valid {
+        return Err(malformed(&format!("invalid tag name '{}'", name)));
+    }
+    Ok(name.to_string())
+}
+
+struct Renderer<'a> {
+    out: String,
+    limits: &'a RenderLimits,
+    deadline: Instant,
+}
+
+impl Renderer<'_> {
+    fn nodes(&mut self, nodes: &[Node], scopes: &mut Vec<&Value>) -> VibeResult<()> {
+        for node in nodes {
+            if Instant::now() > self.deadline {
+                return Err(VibeError::Timeout {
+                    operation: "template render".to_string(),
+                    timeout_ms: self.limits.timeout.as_millis() as u64,
+                });
+            }
+            match node {
+                Node::Text(text) => self.write(text)?,
+                Node::Value { name, raw } => {
+                    let Some(value) = lookup(scopes, name) else {
+                        continue;
+                    };
+                    let text = match value {
+                        Value::Null => continue,
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    if *raw {
+                        self.write(&text)?;
+                    } else {
+                        self.write(&escape_html(&text))?;
+                    }
+                }
+                Node::Section { name, inverted, children } => {
+                    let value = lookup(scopes, name);
+                    let truthy = value.is_some_and(is_truthy);
+                    if *inverted {
+                        if !truthy {
+                            self.nodes(children, scopes)?;
+                        }
+                        continue;
+                    }
+                    let Some(value) = value.filter(|v| is_truthy(v)) else {
+                        continue;
+                    };
+                    let items: &[Value] = match value {
+                        Value::Array(items) => items,
+                        other => std::slice::from_ref(other),
+                    };
+                    for item in items {
+                        scopes.push(item);
+                        let result = self.nodes(children, scopes);
+                        scopes.pop();
+                        result?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, text: &str) -> VibeResult<()> {
+        if self.out.len() + text.len() > self.limits.max_output_bytes {
+            return Err(VibeError::InvalidPayload(format!(
+                "Rendered output exceeds {} bytes",
+                self.limits.max_output_bytes
+            )));
+        }
+        self.out.push_str(text);
+        Ok(())
+    }
+}
+
+/// Resolves a (dotted) name against the innermost scope that has its first
+/// part
+fn lookup<'v>(scopes: &[&'v Value], name: &str) -> Option<&'v Value> {
+    if name == "." {
+        return scopes.last().copied();
+    }
+    let mut parts = name.split('.');
+    let first = parts.next()?;
+    let mut value = scopes.iter().rev().find_map(|scope| scope.get(first))?;
+    for part in parts {
+        value = value.get(part)?;
+    }
+    Some(value)
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Bool(false) => false,
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        _ => true,
+    }
+}
+
+/// Escapes text for HTML element content and quoted attribute values
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A named template as stored
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredTemplate {
+    pub name: String,
+    pub template: String,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// Persistent store of named templates
+pub struct TemplateStore {
+    store: Arc<VibeStore>,
+    initialized: OnceCell<()>,
+}
+
+impl TemplateStore {
+    pub fn new(store: Arc<VibeStore>) -> Self {
+        Self {
+            store,
+            initialized: OnceCell::new(),
+        }
+    }
+
+    async fn ensure_table(&self) -> VibeResult<()> {
+        self.initialized
+            .get_or_try_init(|| async {
+                self.store
+                    .execute_batch(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS vibe_templates_html (
+                            name TEXT PRIMARY KEY,
+                            template TEXT NOT NULL,
+                            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                        );
+                        "#
+                        .to_string(),
+                    )
+                    .await
+            })
+            .await?;
+        Ok(())
+    }
+
+    fn validate_name(name: &str) -> VibeResult<()> {
+        if name.is_empty()
+            || name.chars().count() > MAX_NAME_LENGTH
+            || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(VibeError::InvalidPayload(format!(
+                "Template names are 1-{} letters, digits, '-' or '_'",
+                MAX_NAME_LENGTH
+            )));
+        }
+        Ok(())
+    }
+
+    /// All templates, by name
+    pub async fn list(&self) -> VibeResult<Vec<StoredTemplate>> {
+        self.ensure_table().await?;
+        let rows = self
+            .store
+            .query(
+                "SELECT name, template, created_at, updated_at FROM vibe_templates_html ORDER BY name"
+                    .to_string(),
+                Vec::new(),
+            )
+            .await?;
+        Ok(rows.into_iter().filter_map(stored_template).collect())
+    }
+
+    pub async fn get(&self, name: &str) -> VibeResult<Option<StoredTemplate>> {
+        self.ensure_table().await?;
+        let rows = self
+            .store
+            .query(
+                "SELECT name, template, created_at, updated_at FROM vibe_templates_html WHERE name = ?"
+                    .to_string(),
+                vec![SqlValue::Text(name.to_string())],
+            )
+            .await?;
+        Ok(rows.into_iter().find_map(stored_template))
+    }
+
+    /// Creates or replaces a template once it parses
+    pub async fn put(&self, name: &str, template: &str) -> VibeResult<StoredTemplate> {
+        Self::validate_name(name)?;
+        Template::parse(template)?;
+        self.ensure_table().await?;
+        self.store
+            .execute(
+                "INSERT INTO vibe_templates_html (name, template) VALUES (?1, ?2) \
+                 ON CONFLICT(name) DO UPDATE SET template = excluded.template, \
+                 updated_at = CURRENT_TIMESTAMP"
+                    .to_string(),
+                vec![SqlValue::Text(name.to_string()), SqlValue::Text(template.to_string())],
+            )
+            .await?;
+        self.get(name)
+            .await?
+            .ok_or_else(|| VibeError::NotFound(format!("Template '{}' not found", name)))
+    }
+
+    /// Deletes a template, returning whether it existed
+    pub async fn delete(&self, name: &str) -> VibeResult<bool> {
+        self.ensure_table().await?;
+        let removed = self
+            .store
+            .execute(
+                "DELETE FROM vibe_templates_html WHERE name = ?".to_string(),
+                vec![SqlValue::Text(name.to_string())],
+            )
+            .await?;
+        Ok(removed > 0)
+    }
+}
+
+fn stored_template(row: Vec<(String, Value)>) -> Option<StoredTemplate> {
+    let mut values = row.into_iter().map(|(_, v)| v);
+    let name = values.next()?.as_str()?.to_string();
+    let template = values.next()?.as_str()?.to_string();
+    let created_at = values.next().and_then(|v| v.as_str().map(str::to_string));
+    let updated_at = values.next().and_then(|v| v.as_str().map(str::to_string));
+    Some(StoredTemplate { name, template, created_at, updated_at })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(template: &str, context: Value) -> String {
+        Template::parse(template)
+            .unwrap()
+            .render(&context, &RenderLimits::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_values_are_escaped_unless_raw() {
+        let context = json!({"name": "<b>\"Tom\" & 'Jerry'</b>"});
+        assert_eq!(
+            render("{{name}}", context.clone()),
+            "&lt;b&gt;&quot;Tom&quot; &amp; &#39;Jerry&#39;&lt;/b&gt;"
+        );
+        assert_eq!(render("{{{name}}}|{{& name}}", context.clone()), "<b>\"Tom\" & 'Jerry'</b>|<b>\"Tom\" & 'Jerry'</b>");
+        assert_eq!(render("[{{missing}}{{! note }}]", context), "[]");
+    }
+
+    #[test]
+    fn test_sections() {
+        let context = json!({
+            "rows": [{"name": "a", "tags": ["x", "y"]}, {"name": "b", "tags": []}],
+            "owner": {"name": "root"},
+            "empty": []
+        });
+        assert_eq!(
+            render(
+                "{{#rows}}{{name}}:{{#tags}}{{.}}{{/tags}}{{^tags}}-{{/tags}}@{{owner.name}};{{/rows}}",
+                context.clone()
+            ),
+            "a:xy@root;b:-@root;"
+        );
+        assert_eq!(render("{{#empty}}x{{/empty}}{{^empty}}none{{/empty}}", context), "none");
+    }
+
+    #[test]
+    fn test_malformed_and_capped_templates_are_rejected() {
+        for template in ["{{#a}}", "{{/a}}", "{{#a}}{{/b}}", "{{name", "{{{name}}", "{{a b}}"] {
+            assert!(Template::parse(template).is_err(), "{}", template);
+        }
+        assert!(Template::parse(&"x".repeat(MAX_TEMPLATE_BYTES + 1)).is_err());
+        assert!(Template::parse(&"{{#a}}".repeat(MAX_SECTION_DEPTH + 1)).is_err());
+
+        let template = Template::parse("{{#rows}}{{.}}{{/rows}}").unwrap();
+        let context = json!({"rows": vec!["abcdef"; 10]});
+        let tight = RenderLimits { max_output_bytes: 20, ..RenderLimits::default() };
+        assert!(matches!(template.render(&context, &tight), Err(VibeError::InvalidPayload(_))));
+        let instant = RenderLimits { timeout: Duration::ZERO, ..RenderLimits::default() };
+        assert!(matches!(template.render(&context, &instant), Err(VibeError::Timeout { .. })));
+    }
+}
@@ -0,0 +1,693 @@
+//! # Test Support
+//!
+//! An in-process VibeDB for tests, behind the `test-support` feature:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! vibedb = { version = "1", features = ["test-support"] }
+//! ```
+//!
+//! `TestServer::spawn()` wires the API, auth, storage and explorer routers
+//! exactly like the binary, over an in-memory store and a throwaway storage
+//! directory. Auth uses [`TEST_JWT_SECRET`], so tokens can be minted without
+//! a login round trip. Requests go straight through the router (no socket);
+//! call [`TestServer::listen`] when a client needs a real port.
+//!
+//! ```no_run
+//! # async fn example() {
+//! use vibedb::testing::{json, TestServer};
+//!
+//! let server = TestServer::spawn().await;
+//! let admin = server.create_user("admin@vibedb.dev", "admin").await;
+//! server
+//!     .post("/v1/push/users")
+//!     .bearer(&admin.token)
+//!     .json(json!({"name": "Alice"}))
+//!     .send()
+//!     .await
+//!     .assert_success();
+//! # }
+//! ```
+//!
+//! Fixtures and assertions panic on failure, naming the request and the
+//! response body.
+//!
+//! Expiry is tested without sleeping by giving the server a
+//! [`ManualClock`] and advancing it:
+//!
+//! ```no_run
+//! # async fn example() {
+//! use vibedb::testing::{ManualClock, TestServer};
+//! use std::time::Duration;
+//!
+//! let clock = ManualClock::new();
+//! let server = TestServer::builder().clock(clock.clone()).spawn().await;
+//! clock.advance(Duration::from_secs(3600));
+//! # }
+//! ```
+
+use crate::api::{cors_layer, create_router, nest_under_base_path, AppState};
+use crate::auth::{create_auth_router, AuthService, AuthState, Claims};
+use crate::bootstrap::{create_setup_router, SetupState};
+use crate::clock::{Clock, SharedClock, SystemClock};
+use crate::config::{DefaultBucketConfig, StorageQuotaConfig, VibeConfig};
+use crate::db::VibeStore;
+use crate::explorer::{create_explorer_router_for, ExplorerMode};
+use crate::limits::limit_concurrency;
+use crate::storage::{
+    CreateBucketRequest, StorageObject, StorageService, StorageState, create_storage_router,
+    spawn_event_forwarder,
+};
+
+use axum::body::{Body, BodyDataStream, Bytes};
+use axum::http::{header, HeaderMap, Method, Request};
+use axum::Router;
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use jsonwebtoken::{encode, EncodingKey, Header};
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tower::util::ServiceExt;
+
+// What tests need besides the harness itself
+pub use axum::http::StatusCode;
+pub use serde_json::json;
+
+/// JWT secret of every test server
+pub const TEST_JWT_SECRET: &[u8] = b"vibedb-test-secret";
+
+/// Password of users created with [`TestServer::create_user`]
+pub const TEST_PASSWORD: &str = "password123";
+
+/// Lifetime of minted tokens
+const TOKEN_TTL: Duration = Duration::from_secs(3600);
+
+/// How long a subscription waits for the next event
+const EVENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Mints an access token accepted by any test server
+pub fn mint_token(user_id: i64, email: &str, role: &str) -> String {
+    mint_token_at(&SystemClock, user_id, email, role)
+}
+
+/// Mints an access token valid for [`TOKEN_TTL`] from the clock's time
+fn mint_token_at(clock: &dyn Clock, user_id: i64, email: &str, role: &str) -> String {
+    let now = clock.unix_secs();
+    let claims = Claims {
+        sub: user_id,
+        email: email.to_string(),
+        role: role.to_string(),
+        iat: now,
+        exp: now + TOKEN_TTL.as_secs(),
+    };
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(TEST_JWT_SECRET))
+        .expect("failed to mint test token")
+}
+
+/// A clock that only moves when told to
+///
+/// Starts at the current time. Clones share the same time, so a test can
+/// keep one and hand another to [`TestServerBuilder::clock`]. Deadlines
+/// waited on with `sleep_until` pass as soon as `advance` reaches them.
+#[derive(Clone)]
+pub struct ManualClock {
+    wall: DateTime<Utc>,
+    start: Instant,
+    /// Time advanced so far
+    offset: Arc<watch::Sender<Duration>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::at(Utc::now())
+    }
+
+    /// A clock whose wall-clock time starts at `wall`
+    pub fn at(wall: DateTime<Utc>) -> Self {
+        Self {
+            wall,
+            start: Instant::now(),
+            offset: Arc::new(watch::Sender::new(Duration::ZERO)),
+        }
+    }
+
+    /// Moves the clock forward, waking timers whose deadline has come
+    pub fn advance(&self, by: Duration) {
+        self.offset.send_modify(|offset| *offset += by);
+    }
+
+    /// Time advanced since the clock was created
+    pub fn elapsed(&self) -> Duration {
+        *self.offset.borrow()
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.wall + chrono::Duration::from_std(self.elapsed()).expect("clock advanced too far")
+    }
+
+    fn instant(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<'static, ()> {
+        let start = self.start;
+        // Holding the sender keeps the wait pending until the deadline
+        let offset = Arc::clone(&self.offset);
+        Box::pin(async move {
+            let mut elapsed = offset.subscribe();
+            let _ = elapsed.wait_for(|elapsed| start + *elapsed >= deadline).await;
+        })
+    }
+}
+
+/// A user created through [`TestServer::create_user`]
+#[derive(Debug, Clone)]
+pub struct TestUser {
+    pub id: i64,
+    pub email: String,
+    pub role: String,
+    /// Bearer token for the user
+    pub token: String,
+}
+
+/// Options for [`TestServer::builder`]
+#[derive(Default)]
+pub struct TestServerBuilder {
+    store: Option<Arc<VibeStore>>,
+    config: VibeConfig,
+    anonymous: bool,
+    storage_quotas: StorageQuotaConfig,
+    default_bucket: Option<DefaultBucketConfig>,
+    clock: Option<SharedClock>,
+}
+
+impl TestServerBuilder {
+    /// Serves an existing store instead of a fresh in-memory one
+    pub fn store(mut self, store: Arc<VibeStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub fn config(mut self, config: VibeConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn storage_quotas(mut self, quotas: StorageQuotaConfig) -> Self {
+        self.storage_quotas = quotas;
+        self
+    }
+
+    pub fn default_bucket(mut self, bucket: DefaultBucketConfig) -> Self {
+        self.default_bucket = Some(bucket);
+        self
+    }
+
+    /// Reads time from `clock` instead of the system clock, e.g. a
+    /// [`ManualClock`]
+    pub fn clock(mut self, clock: impl Clock) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Leaves the data API without auth: callers are never identified and
+    /// admin-only endpoints are open, as when embedding without `with_auth`
+    pub fn anonymous(mut self) -> Self {
+        self.anonymous = true;
+        self
+    }
+
+    pub async fn spawn(self) -> TestServer {
+        let store = match self.store {
+            Some(store) => store,
+            None => Arc::new(VibeStore::in_memory().await.expect("failed to open test store")),
+        };
+        let storage_dir = std::env::temp_dir().join(format!("vibedb-test-{}", uuid::Uuid::new_v4()));
+        let clock = self.clock.unwrap_or_else(SystemClock::shared);
+
+        let auth = AuthService::new(Arc::clone(&store), TEST_JWT_SECRET.to_vec())
+            .await
+            .expect("failed to start auth")
+            .with_clock(Arc::clone(&clock));
+        let mut storage = StorageService::new(Arc::clone(&store), Some(storage_dir.clone()))
+            .await
+            .expect("failed to start storage")
+            .with_quotas(self.storage_quotas)
+            .with_clock(Arc::clone(&clock));
+        if let Some(bucket) = self.default_bucket {
+            storage = storage
+                .with_default_bucket(bucket)
+                .await
+                .expect("failed to create default bucket");
+        }
+
+        self.config.cors.validate().expect("invalid CORS configuration");
+        let mut state = AppState::with_clock(Arc::clone(&store), self.config, Arc::clone(&clock))
+            .with_storage(storage.clone());
+        if !self.anonymous {
+            state = state.with_auth(auth.clone());
+        }
+        spawn_event_forwarder(state.clone(), &storage);
+        let router = create_router(state.clone())
+            .nest(
+                "/v1/auth",
+                create_auth_router(AuthState { auth: auth.clone() }).merge(create_setup_router(SetupState {
+                    auth: auth.clone(),
+                    storage: storage.clone(),
+                })),
+            )
+            .nest(
+                "/v1/storage",
+                create_storage_router(StorageState {
+                    storage: storage.clone(),
+                    auth: (!self.anonymous).then(|| auth.clone()),
+                    trust_proxy: false,
+                    base_path: state.config.base_path.clone(),
+                }),
+            )
+            .merge(create_explorer_router_for(&ExplorerMode::Embedded, &state.config.base_path))
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::clone(&state.limits),
+                limit_concurrency,
+            ));
+        let router = nest_under_base_path(router, &state.config.base_path).layer(cors_layer(&state.config.cors));
+
+        TestServer {
+            store,
+            state,
+            auth,
+            storage,
+            clock,
+            router,
+            storage_dir,
+        }
+    }
+}
+
+/// An in-process VibeDB with every router wired
+pub struct TestServer {
+    pub store: Arc<VibeStore>,
+    pub state: AppState,
+    pub auth: AuthService,
+    pub storage: StorageService,
+    /// Time source of every service
+    pub clock: SharedClock,
+    router: Router,
+    storage_dir: PathBuf,
+}
+
+impl TestServer {
+    /// Spawns a server over a fresh in-memory store with auth enabled
+    pub async fn spawn() -> Self {
+        Self::builder().spawn().await
+    }
+
+    pub fn builder() -> TestServerBuilder {
+        TestServerBuilder::default()
+    }
+
+    /// The router as a `tower::Service`
+    pub fn router(&self) -> Router {
+        self.router.clone()
+    }
+
+    /// Serves the router on a random local port for the rest of the runtime
+    pub async fn listen(&self) -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind test listener");
+        let addr = listener.local_addr().expect("test listener has no address");
+        let router = self.router.clone();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        addr
+    }
+
+    pub fn request(&self, method: Method, uri: &str) -> TestRequest<'_> {
+        TestRequest {
+            server: self,
+            method,
+            uri: uri.to_string(),
+            headers: HeaderMap::new(),
+            body: Body::empty(),
+        }
+    }
+
+    pub fn get(&self, uri: &str) -> TestRequest<'_> {
+        self.request(Method::GET, uri)
+    }
+
+    pub fn post(&self, uri: &str) -> TestRequest<'_> {
+        self.request(Method::POST, uri)
+    }
+
+    pub fn put(&self, uri: &str) -> TestRequest<'_> {
+        self.request(Method::PUT, uri)
+    }
+
+    pub fn delete(&self, uri: &str) -> TestRequest<'_> {
+        self.request(Method::DELETE, uri)
+    }
+
+    pub fn patch(&self, uri: &str) -> TestRequest<'_> {
+        self.request(Method::PATCH, uri)
+    }
+
+    /// Opens the SSE stream of a collection
+    ///
+    /// Returns once the subscription is live, so events for writes made
+    /// afterwards are never missed.
+    pub async fn subscribe(&self, collection: &str, token: Option<&str>) -> Subscription {
+        self.subscribe_uri(&format!("/v1/stream/{}", collection), token).await
+    }
+
+    /// Opens any SSE endpoint whose first message is `connected`, such as
+    /// `/v1/storage/events`
+    pub async fn subscribe_uri(&self, uri: &str, token: Option<&str>) -> Subscription {
+        let mut request = self.get(uri);
+        if let Some(token) = token {
+            request = request.bearer(token);
+        }
+        let response = request.dispatch().await;
+        assert_eq!(response.status(), StatusCode::OK, "failed to subscribe to {}", uri);
+
+        let mut subscription = Subscription {
+            body: response.into_body().into_data_stream(),
+            buffer: String::new(),
+            connected: Value::Null,
+        };
+        subscription.connected = subscription.next().await;
+        assert_eq!(subscription.connected["event"], "connected");
+        subscription
+    }
+
+    // ========================================================================
+    // Fixtures
+    // ========================================================================
+
+    /// Creates a user with [`TEST_PASSWORD`] and a bearer token, valid for
+    /// an hour of the server's clock
+    pub async fn create_user(&self, email: &str, role: &str) -> TestUser {
+        let user = self
+            .auth
+            .create_user(email, TEST_PASSWORD, role, json!({}))
+            .await
+            .unwrap_or_else(|e| panic!("failed to create user {}: {}", email, e));
+        TestUser {
+            token: mint_token_at(self.clock.as_ref(), user.id, &user.email, &user.role),
+            id: user.id,
+            email: user.email,
+            role: user.role,
+        }
+    }
+
+    /// Creates a collection with the columns a push of `schema` would create,
+    /// without inserting a row
+    ///
+    /// `schema` is a prototype document: `{"title": "", "price": 0.0}`.
+    pub async fn create_collection_with(&self, collection: &str, schema: Value) {
+        let result = async {
+            self.state.guard.ensure_table(collection).await?;
+            self.state.guard.ensure_columns(collection, &schema).await
+        };
+        if let Err(e) = result.await {
+            panic!("failed to create collection {}: {}", collection, e);
+        }
+    }
+
+    /// Uploads a file, creating the bucket as private when it is missing
+    ///
+    /// The content type is guessed from the path.
+    pub async fn upload_fixture_file(
+        &self,
+        bucket: &str,
+        path: &str,
+        data: impl Into<Vec<u8>>,
+    ) -> StorageObject {
+        if self.storage.get_bucket(bucket).await.is_err() {
+            let request = CreateBucketRequest {
+                name: bucket.to_string(),
+                public: false,
+                compression: Default::default(),
+            };
+            self.storage
+                .create_bucket(request, None)
+                .await
+                .unwrap_or_else(|e| panic!("failed to create bucket {}: {}", bucket, e));
+        }
+
+        let mime_type = mime_guess::from_path(path).first_or_octet_stream();
+        self.storage
+            .upload_object(bucket, path, data.into(), mime_type.essence_str(), None)
+            .await
+            .unwrap_or_else(|e| panic!("failed to upload {}/{}: {}", bucket, path, e))
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.storage_dir);
+    }
+}
+
+/// A request being built against a [`TestServer`]
+pub struct TestRequest<'a> {
+    server: &'a TestServer,
+    method: Method,
+    uri: String,
+    headers: HeaderMap,
+    body: Body,
+}
+
+impl TestRequest<'_> {
+    pub fn bearer(self, token: &str) -> Self {
+        self.header(header::AUTHORIZATION.as_str(), &format!("Bearer {}", token))
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        let name = header::HeaderName::try_from(name).expect("invalid header name");
+        self.headers.insert(name, value.parse().expect("invalid header value"));
+        self
+    }
+
+    pub fn json(self, body: Value) -> Self {
+        self.body(body.to_string(), "application/json")
+    }
+
+    pub fn body(mut self, body: impl Into<Bytes>, content_type: &str) -> Self {
+        self.body = Body::from(body.into());
+        self.header(header::CONTENT_TYPE.as_str(), content_type)
+    }
+
+    /// Sends the request and buffers the response
+    pub async fn send(self) -> TestResponse {
+        let request = format!("{} {}", self.method, self.uri);
+        let response = self.dispatch().await;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap_or_else(|e| panic!("{}: failed to read body: {}", request, e));
+
+        TestResponse {
+            request,
+            status,
+            headers,
+            body,
+        }
+    }
+
+    async fn dispatch(self) -> axum::response::Response {
+        let mut request = Request::builder()
+            .method(self.method)
+            .uri(&self.uri)
+            .body(self.body)
+            .expect("invalid test request");
+        *request.headers_mut() = self.headers;
+
+        match self.server.router.clone().oneshot(request).await {
+            Ok(response) => response,
+            Err(infallible) => match infallible {},
+        }
+    }
+}
+
+/// A buffered response
+#[derive(Debug)]
+pub struct TestResponse {
+    /// `METHOD uri`, for assertion messages
+    pub request: String,
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl TestResponse {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// The body as JSON, or `Value::Null` when it isn't JSON
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or(Value::Null)
+    }
+
+    /// One value per line of an NDJSON body
+    #[track_caller]
+    pub fn ndjson(&self) -> Vec<Value> {
+        self.text()
+            .lines()
+            .map(|line| {
+                serde_json::from_str(line)
+                    .unwrap_or_else(|e| panic!("{}: invalid NDJSON line {:?}: {}", self.request, line, e))
+            })
+            .collect()
+    }
+
+    #[track_caller]
+    pub fn assert_status(self, status: StatusCode) -> Self {
+        assert_eq!(self.status, status, "{}: {}", self.request, self.text());
+        self
+    }
+
+    /// Asserts a 2xx `{"success": true, ...}` envelope and returns its `data`
+    #[track_caller]
+    pub fn assert_success(&self) -> Value {
+        let body = self.json();
+        assert!(
+            self.status.is_success() && body["success"] == true,
+            "{}: expected success, got {} {}",
+            self.request,
+            self.status,
+            self.text()
+        );
+        body["data"].clone()
+    }
+
+    /// Asserts a `{"success": false, "error": {"code": ...}}` envelope with
+    /// the given status and code, and returns the error object
+    #[track_caller]
+    pub fn assert_error(&self, status: StatusCode, code: &str) -> Value {
+        let body = self.json();
+        assert!(
+            self.status == status && body["success"] == false && body["error"]["code"] == code,
+            "{}: expected {} {}, got {} {}",
+            self.request,
+            status,
+            code,
+            self.status,
+            self.text()
+        );
+        body["error"].clone()
+    }
+}
+
+/// Events of a collection's SSE stream
+pub struct Subscription {
+    body: BodyDataStream,
+    buffer: String,
+    connected: Value,
+}
+
+impl Subscription {
+    /// The stream's `connected` message
+    pub fn connected(&self) -> &Value {
+        &self.connected
+    }
+
+    /// The next event, skipping keep-alives
+    pub async fn next(&mut self) -> Value {
+        tokio::time::timeout(EVENT_TIMEOUT, self.next_event())
+            .await
+            .expect("no stream event within 5 seconds")
+    }
+
+    async fn next_event(&mut self) -> Value {
+        loop {
+            if let Some(end) = self.buffer.find("\n\n") {
+                let frame: String = self.buffer.drain(..end + 2).collect();
+                let data: Vec<&str> = frame
+                    .lines()
+                    .filter_map(|line| line.strip_prefix("data:"))
+                    .map(str::trim_start)
+                    .collect();
+                if data.is_empty() {
+                    continue;
+                }
+                return serde_json::from_str(&data.join("\n")).expect("stream event is not JSON");
+            }
+
+            let chunk = self
+                .body
+                .next()
+                .await
+                .expect("stream ended")
+                .expect("stream failed");
+            self.buffer.push_str(std::str::from_utf8(&chunk).expect("stream is not UTF-8"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_listen_serves_the_router() {
+        let server = TestServer::spawn().await;
+        let addr = server.listen().await;
+
+        let mut socket = tokio::net::TcpStream::connect(addr).await.unwrap();
+        socket
+            .write_all(b"GET /health/live HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        socket.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains(r#""status":"alive""#));
+    }
+
+    #[tokio::test]
+    async fn test_fixtures() {
+        let server = TestServer::spawn().await;
+
+        server.create_collection_with("products", json!({"title": "", "price": 0.0})).await;
+        let data = server.get("/v1/tables/products").send().await.assert_success();
+        let columns: Vec<&str> = data["columns"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["name"].as_str().unwrap())
+            .collect();
+        assert!(columns.contains(&"title") && columns.contains(&"price"));
+
+        let object = server.upload_fixture_file("docs", "a/readme.txt", "hello").await;
+        assert_eq!(object.mime_type, "text/plain");
+        let (data, _) = server.storage.download_object("docs", "a/readme.txt").await.unwrap();
+        assert_eq!(data, b"hello");
+
+        let alice = server.create_user("alice@vibedb.dev", "user").await;
+        let me = server.get("/v1/auth/me").bearer(&alice.token).send().await.assert_success();
+        assert_eq!(me["id"], alice.id);
+        server
+            .get("/v1/auth/me")
+            .bearer("not-a-token")
+            .send()
+            .await
+            .assert_error(StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+    }
+}
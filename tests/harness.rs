@@ -0,0 +1,30 @@
+//! Exercises VibeDB the way a downstream crate would, through `vibedb::testing` only.
+
+use vibedb::testing::{json, StatusCode, TestServer};
+
+#[tokio::test]
+async fn push_query_stream_and_auth() {
+    let server = TestServer::spawn().await;
+    let admin = server.create_user("admin@vibedb.dev", "admin").await;
+    let alice = server.create_user("alice@vibedb.dev", "user").await;
+
+    let mut events = server.subscribe("notes", Some(&alice.token)).await;
+    let pushed = server.post("/v1/push/notes").bearer(&alice.token).json(json!({"text": "hi"})).send().await;
+    assert_eq!(pushed.assert_status(StatusCode::CREATED).assert_success()["id"], 1);
+    assert_eq!(events.next().await["data"]["text"], "hi");
+
+    let rows = server.get("/v1/query/notes").send().await.assert_success();
+    assert_eq!(rows[0]["text"], "hi");
+    let lines = server.get("/v1/query/notes?stream=true").send().await.ndjson();
+    assert_eq!(lines, vec![rows[0].clone()]);
+
+    // Settings are admin-only; tokens are checked
+    let settings = json!({"hidden_columns": ["text"]});
+    let put = |token: &str| server.put("/v1/tables/notes/settings").bearer(token).json(settings.clone()).send();
+    put(&alice.token).await.assert_error(StatusCode::FORBIDDEN, "FORBIDDEN");
+    put(&admin.token).await.assert_success();
+    let me = server.get("/v1/auth/me").bearer(&alice.token).send().await.assert_success();
+    assert_eq!(me["email"], alice.email);
+    let forged = server.get("/v1/auth/me").bearer("forged").send().await;
+    forged.assert_error(StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+}
@@ -8,6 +8,7 @@ import DashboardView from './components/DashboardView';
 import AnalysisView from './components/AnalysisView';
 import PulsePanel from './components/PulsePanel';
 import FileMenu from './components/FileMenu';
+import SetupScreen from './components/SetupScreen';
 import { AcceleratorGallery } from './components/MoreAdvancedFeatures';
 import {
   LayoutGrid, Save, Undo, Redo, Share2, HelpCircle, Database, Terminal, Table as TableIcon,
@@ -17,7 +18,7 @@ import {
   Waves, Thermometer, GitCommit, GitMerge, Map as MapIcon
 } from 'lucide-react';
 
-const API_Base = import.meta.env.DEV ? 'http://localhost:3000' : window.location.origin;
+const API_Base = import.meta.env.DEV ? 'http://localhost:3000' : window.location.origin + (window.VIBEDB_BASE_PATH || '');
 
 const DEFAULT_CONFIG = {
   columns: [],
@@ -34,6 +35,7 @@ function App() {
   const [tables, setTables] = useState([]);
   const [activeTable, setActiveTable] = useState('');
   const [loading, setLoading] = useState(true);
+  const [needsSetup, setNeedsSetup] = useState(false);
 
   // Navigation
   const [activeTab, setActiveTab] = useState('worksheet'); // 'worksheet' | 'dashboard' | 'analysis' | 'database'
@@ -212,9 +214,21 @@ function App() {
     }
   };
 
+  // First run: no user exists yet, so offer to create the admin
+  const fetchAuthConfig = async () => {
+    try {
+      const res = await fetch(`${API_Base}/v1/auth/config`);
+      const json = await res.json();
+      if (json.success) setNeedsSetup(json.data.needs_setup);
+    } catch (err) {
+      console.error("Failed to load auth config", err);
+    }
+  };
+
   useEffect(() => {
     // Initial history push
     pushHistory(sheets);
+    fetchAuthConfig();
     fetchMetadata();
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, []); // Run once
@@ -466,6 +480,10 @@ function App() {
     e.dataTransfer.setData('field', JSON.stringify(field));
   };
 
+  if (needsSetup) {
+    return <SetupScreen apiBase={API_Base} onComplete={() => { setNeedsSetup(false); fetchMetadata(); }} />;
+  }
+
   return (
     <div className="flex flex-col h-screen text-gray-100 overflow-hidden font-sans bg-[var(--bg-app)]">
 
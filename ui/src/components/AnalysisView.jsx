@@ -3,7 +3,7 @@ import React, { useState, useEffect } from 'react';
 import { BarChart, Bar, CartesianGrid, XAxis, YAxis, Tooltip, ResponsiveContainer, PieChart, Pie, Cell } from 'recharts';
 import { Activity, Database, Table, AlertTriangle } from 'lucide-react';
 
-const API_Base = import.meta.env.DEV ? 'http://localhost:3000' : window.location.origin;
+const API_Base = import.meta.env.DEV ? 'http://localhost:3000' : window.location.origin + (window.VIBEDB_BASE_PATH || '');
 
 const COLORS = ['#6366f1', '#10b981', '#f59e0b', '#ec4899', '#3b82f6'];
 export default function AnalysisView({ tables }) {
@@ -1,7 +1,7 @@
 import React, { useState, useEffect } from 'react';
 import { Layout, Plus, FileText, Trash2 } from 'lucide-react';
 
-const API_Base = import.meta.env.DEV ? 'http://localhost:3000' : window.location.origin;
+const API_Base = import.meta.env.DEV ? 'http://localhost:3000' : window.location.origin + (window.VIBEDB_BASE_PATH || '');
 
 export default function DashboardView({ onLoadConfig }) {
     const [dashboards, setDashboards] = useState([]);
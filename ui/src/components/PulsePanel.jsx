@@ -6,7 +6,7 @@ import {
 import _ from 'lodash';
 import { generateInsights } from '../services/aiService';
 
-const API_Base = import.meta.env.DEV ? 'http://localhost:3000' : window.location.origin;
+const API_Base = import.meta.env.DEV ? 'http://localhost:3000' : window.location.origin + (window.VIBEDB_BASE_PATH || '');
 
 export default function PulsePanel({ tableName }) {
     const [insights, setInsights] = useState([]);
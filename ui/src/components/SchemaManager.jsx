@@ -5,7 +5,7 @@ import { Database, Plus, Trash2, Edit2, Save, X, Table, Key, Type, AlertCircle,
 import Papa from 'papaparse';
 import * as XLSX from 'xlsx';
 
-const API_Base = import.meta.env.DEV ? 'http://localhost:3000' : window.location.origin;
+const API_Base = import.meta.env.DEV ? 'http://localhost:3000' : window.location.origin + (window.VIBEDB_BASE_PATH || '');
 
 const COLUMN_TYPES = ['TEXT', 'INTEGER', 'REAL', 'BLOB', 'NUMERIC'];
 
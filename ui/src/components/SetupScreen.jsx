@@ -0,0 +1,106 @@
+import React, { useState } from 'react';
+import { LayoutGrid, Plus, X } from 'lucide-react';
+
+// First-run setup: shown while GET /v1/auth/config reports needs_setup.
+// Creates the first admin (and optional buckets) with POST /v1/auth/setup.
+export default function SetupScreen({ apiBase, onComplete }) {
+    const [email, setEmail] = useState('');
+    const [password, setPassword] = useState('');
+    const [confirm, setConfirm] = useState('');
+    const [buckets, setBuckets] = useState([]);
+    const [error, setError] = useState('');
+    const [submitting, setSubmitting] = useState(false);
+
+    const updateBucket = (index, changes) => {
+        setBuckets(buckets.map((bucket, i) => (i === index ? { ...bucket, ...changes } : bucket)));
+    };
+
+    const handleSubmit = async (e) => {
+        e.preventDefault();
+        if (password !== confirm) {
+            setError('Passwords do not match');
+            return;
+        }
+        setSubmitting(true);
+        setError('');
+        try {
+            const res = await fetch(`${apiBase}/v1/auth/setup`, {
+                method: 'POST',
+                headers: { 'Content-Type': 'application/json' },
+                body: JSON.stringify({
+                    email,
+                    password,
+                    buckets: buckets.filter(b => b.name.trim()).map(b => ({ name: b.name.trim(), public: b.public })),
+                }),
+            });
+            const json = await res.json();
+            if (!json.success) {
+                setError(json.error?.message || 'Setup failed');
+                return;
+            }
+            localStorage.setItem('vibedb_access_token', json.data.access_token);
+            localStorage.setItem('vibedb_refresh_token', json.data.refresh_token);
+            onComplete(json.data.user);
+        } catch (err) {
+            console.error("Setup failed", err);
+            setError('Could not reach the server');
+        } finally {
+            setSubmitting(false);
+        }
+    };
+
+    const inputClass = "w-full px-3 py-2 rounded bg-[var(--bg-app)] border border-[var(--border-subtle)] text-sm text-gray-100 focus:outline-none focus:border-violet-500";
+
+    return (
+        <div className="flex items-center justify-center h-screen bg-[var(--bg-app)] text-gray-100 font-sans">
+            <form onSubmit={handleSubmit} className="w-full max-w-sm p-6 rounded-lg bg-[var(--bg-header)] border border-[var(--border-subtle)] space-y-4">
+                <div className="flex items-center gap-2 text-violet-400 font-bold tracking-tight">
+                    <LayoutGrid className="w-5 h-5" />
+                    <span>Welcome to VibeDB</span>
+                </div>
+                <p className="text-sm text-gray-400">
+                    No users exist yet. Create the first admin account to claim this instance.
+                </p>
+
+                <input type="email" required placeholder="Admin email" value={email}
+                    onChange={e => setEmail(e.target.value)} className={inputClass} />
+                <input type="password" required placeholder="Password" value={password}
+                    onChange={e => setPassword(e.target.value)} className={inputClass} />
+                <input type="password" required placeholder="Confirm password" value={confirm}
+                    onChange={e => setConfirm(e.target.value)} className={inputClass} />
+
+                <div className="space-y-2">
+                    <div className="flex items-center justify-between text-xs text-gray-400">
+                        <span>Buckets (optional)</span>
+                        <button type="button" onClick={() => setBuckets([...buckets, { name: '', public: false }])}
+                            className="flex items-center gap-1 hover:text-violet-400">
+                            <Plus className="w-3 h-3" /> Add
+                        </button>
+                    </div>
+                    {buckets.map((bucket, i) => (
+                        <div key={i} className="flex items-center gap-2">
+                            <input placeholder="Bucket name" value={bucket.name}
+                                onChange={e => updateBucket(i, { name: e.target.value })} className={inputClass} />
+                            <label className="flex items-center gap-1 text-xs text-gray-400">
+                                <input type="checkbox" checked={bucket.public}
+                                    onChange={e => updateBucket(i, { public: e.target.checked })} />
+                                Public
+                            </label>
+                            <button type="button" onClick={() => setBuckets(buckets.filter((_, j) => j !== i))}
+                                className="text-gray-500 hover:text-red-400">
+                                <X className="w-4 h-4" />
+                            </button>
+                        </div>
+                    ))}
+                </div>
+
+                {error && <div className="text-sm text-red-400">{error}</div>}
+
+                <button type="submit" disabled={submitting}
+                    className="w-full py-2 rounded bg-violet-600 hover:bg-violet-500 disabled:opacity-50 text-sm font-medium">
+                    {submitting ? 'Creating admin...' : 'Create admin'}
+                </button>
+            </form>
+        </div>
+    );
+}
@@ -4,7 +4,7 @@ import { Play, Database, History, Code, Terminal, ChevronDown, Table, FileText,
 import { SqlToNosqlTranspiler, QueryManager, EmbeddedShell, AskData } from './MoreAdvancedFeatures';
 import { Sparkles } from 'lucide-react';
 
-const API_Base = import.meta.env.DEV ? 'http://localhost:3000' : window.location.origin;
+const API_Base = import.meta.env.DEV ? 'http://localhost:3000' : window.location.origin + (window.VIBEDB_BASE_PATH || '');
 
 export default function SqlConsole({ tables }) {
     const [query, setQuery] = useState('SELECT * FROM users LIMIT 10;');